@@ -0,0 +1,686 @@
+//! Auto-claim scheduler
+//!
+//! Checks pending staking rewards every `auto_claim_interval_hours` and claims
+//! them once they reach `auto_claim_threshold_wei`, so gas isn't wasted on dust.
+//! A failed check is retried sooner, backing off up to the regular interval.
+//! With auto-compound on, each service's rewards are claimed separately and
+//! restaked into that service straight away, unless the claim is below
+//! `auto_compound_min_wei` or wouldn't cover the stake transaction's gas.
+//! The settings are mirrored into the app store so they survive restarts.
+//!
+//! The claim and stake transactions aren't wired to the staking contracts yet.
+//! Builds without the `staking-claims` feature save the settings but the
+//! scheduler stays idle, which `get_staking_info` reports to the UI.
+
+use crate::commands::staking::{
+    self, ClaimResult, ServiceStakeInfo, StakeRequest, StakeResult, StakingContext,
+};
+use crate::config::EarningsConfig;
+use crate::earnings::{EarningsEntry, EarningsEventType};
+use crate::state::AppStateInner;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+use tokio::sync::RwLock;
+
+const STORE_PATH: &str = "settings.json";
+const STORE_KEY: &str = "auto_claim";
+
+/// How often the scheduler wakes up to see whether a check is due
+const TICK: Duration = Duration::from_secs(60);
+
+/// Wait before retrying a failed check, doubled for each further failure
+const RETRY_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Auto-claim settings as persisted in the store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AutoClaimSettings {
+    enabled: bool,
+    threshold_wei: String,
+    interval_hours: u32,
+    #[serde(default)]
+    compound: bool,
+    #[serde(default)]
+    compound_min_wei: Option<String>,
+}
+
+/// Emitted after every successful auto-claim
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoClaimEvent {
+    pub amount_claimed_wei: String,
+    pub gas_spent_wei: String,
+    pub tx_hash: Option<String>,
+}
+
+/// Emitted after claimed rewards are restaked
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoCompoundEvent {
+    pub service_id: String,
+    pub amount_restaked_wei: String,
+    pub principal_before_wei: String,
+    pub principal_after_wei: String,
+    pub tx_hash: Option<String>,
+}
+
+/// What a scheduler run did
+#[derive(Debug, Clone)]
+pub enum SchedulerEvent {
+    Claimed(AutoClaimEvent),
+    Compounded(AutoCompoundEvent),
+}
+
+/// Save the auto-claim settings to the store
+pub fn persist(app: &AppHandle, earnings: &EarningsConfig) -> Result<(), String> {
+    let settings = AutoClaimSettings {
+        enabled: earnings.auto_claim,
+        threshold_wei: earnings.auto_claim_threshold_wei.clone(),
+        interval_hours: earnings.auto_claim_interval_hours,
+        compound: earnings.auto_compound,
+        compound_min_wei: Some(earnings.auto_compound_min_wei.clone()),
+    };
+
+    let store = app.store(STORE_PATH).map_err(|e| e.to_string())?;
+    store.set(
+        STORE_KEY,
+        serde_json::to_value(&settings).map_err(|e| e.to_string())?,
+    );
+    store.save().map_err(|e| e.to_string())
+}
+
+/// Apply previously persisted auto-claim settings, if any
+pub fn restore(app: &AppHandle, earnings: &mut EarningsConfig) {
+    let settings = match app.store(STORE_PATH) {
+        Ok(store) => store.get(STORE_KEY),
+        Err(e) => {
+            tracing::warn!("Failed to open settings store: {}", e);
+            return;
+        }
+    };
+
+    let Some(value) = settings else {
+        return;
+    };
+
+    match serde_json::from_value::<AutoClaimSettings>(value) {
+        Ok(settings) => {
+            earnings.auto_claim = settings.enabled;
+            earnings.auto_claim_threshold_wei = settings.threshold_wei;
+            earnings.auto_claim_interval_hours = settings.interval_hours;
+            earnings.auto_compound = settings.compound;
+            if let Some(min) = settings.compound_min_wei {
+                earnings.auto_compound_min_wei = min;
+            }
+        }
+        Err(e) => tracing::warn!("Ignoring invalid auto-claim settings: {}", e),
+    }
+}
+
+/// Staking operations a scheduler run needs, so runs can be driven by a
+/// stand-in rewards source
+#[async_trait::async_trait]
+trait StakingBackend: Send + Sync {
+    /// Whether claims can be sent right now
+    fn available(&self) -> bool;
+    async fn pending_rewards(&self) -> Result<Vec<ServiceStakeInfo>, String>;
+    async fn claim(&self, service_id: Option<&str>) -> Result<ClaimResult, String>;
+    async fn stake(&self, request: &StakeRequest) -> Result<StakeResult, String>;
+}
+
+/// The staking contracts, through the connected wallet
+struct ContractStaking(StakingContext);
+
+#[async_trait::async_trait]
+impl StakingBackend for ContractStaking {
+    fn available(&self) -> bool {
+        staking::CLAIMS_SUPPORTED && self.0.wallet_connected()
+    }
+
+    async fn pending_rewards(&self) -> Result<Vec<ServiceStakeInfo>, String> {
+        staking::pending_rewards(&self.0).await
+    }
+
+    async fn claim(&self, service_id: Option<&str>) -> Result<ClaimResult, String> {
+        staking::claim(&self.0, service_id).await
+    }
+
+    async fn stake(&self, request: &StakeRequest) -> Result<StakeResult, String> {
+        staking::stake_to(&self.0, request).await
+    }
+}
+
+/// What a run did: events for the UI and entries for the earnings history
+#[derive(Default)]
+struct RunOutcome {
+    events: Vec<SchedulerEvent>,
+    history: Vec<EarningsEntry>,
+}
+
+/// Run the scheduler forever, reporting claims and compounds through `on_event`
+pub async fn run<F>(state: Arc<RwLock<AppStateInner>>, on_event: F)
+where
+    F: Fn(SchedulerEvent) + Send + 'static,
+{
+    let mut next_check: Option<Instant> = None;
+    let mut failures: u32 = 0;
+
+    loop {
+        tokio::time::sleep(TICK).await;
+
+        let earnings = state.read().await.config.earnings.clone();
+        if !earnings.claims_automatically() {
+            failures = 0;
+            if next_check.take().is_some() {
+                state.write().await.next_auto_claim_timestamp = None;
+            }
+            continue;
+        }
+
+        let now = Instant::now();
+        if next_check.is_some_and(|at| now < at) {
+            continue;
+        }
+
+        // The guard is released before any RPC call goes out
+        let backend = ContractStaking(StakingContext::new(&*state.read().await));
+        let result = run_once(&backend, &earnings).await;
+
+        match result {
+            Ok(outcome) => {
+                failures = 0;
+                let mut inner = state.write().await;
+                for entry in outcome.history {
+                    inner.earnings_tracker.add_entry(entry);
+                }
+                drop(inner);
+                outcome.events.into_iter().for_each(&on_event);
+            }
+            Err(e) => {
+                failures = failures.saturating_add(1);
+                tracing::warn!("Auto-claim failed ({} in a row): {}", failures, e);
+            }
+        }
+
+        let interval =
+            Duration::from_secs(u64::from(earnings.auto_claim_interval_hours.max(1)) * 3600);
+        let delay = next_check_delay(interval, failures);
+        next_check = Some(now + delay);
+        state.write().await.next_auto_claim_timestamp =
+            Some(chrono::Utc::now().timestamp() as u64 + delay.as_secs());
+    }
+}
+
+/// Time until the next check: the full interval after a good run, otherwise
+/// `RETRY_BACKOFF` doubled per consecutive failure, never beyond the interval
+fn next_check_delay(interval: Duration, failures: u32) -> Duration {
+    if failures == 0 {
+        return interval;
+    }
+    RETRY_BACKOFF
+        .saturating_mul(1 << (failures - 1).min(16))
+        .min(interval)
+}
+
+/// One check: claim, or claim and restake, once pending rewards reach the threshold
+async fn run_once(
+    backend: &impl StakingBackend,
+    earnings: &EarningsConfig,
+) -> Result<RunOutcome, String> {
+    if !backend.available() {
+        tracing::debug!("Skipping auto-claim, claims unavailable or no wallet connected");
+        return Ok(RunOutcome::default());
+    }
+
+    if earnings.auto_compound {
+        check_and_compound(backend, earnings).await
+    } else {
+        check_and_claim(backend, earnings).await
+    }
+}
+
+fn parse_threshold(earnings: &EarningsConfig) -> Result<u128, String> {
+    earnings.auto_claim_threshold_wei.parse().map_err(|_| {
+        format!(
+            "Invalid claim threshold: {}",
+            earnings.auto_claim_threshold_wei
+        )
+    })
+}
+
+fn total_pending(stakes: &[ServiceStakeInfo]) -> u128 {
+    stakes
+        .iter()
+        .filter_map(|s| s.pending_rewards_wei.parse::<u128>().ok())
+        .sum()
+}
+
+/// Claim all pending rewards if they have reached the threshold
+async fn check_and_claim(
+    backend: &impl StakingBackend,
+    earnings: &EarningsConfig,
+) -> Result<RunOutcome, String> {
+    let threshold = parse_threshold(earnings)?;
+    let pending = total_pending(&backend.pending_rewards().await?);
+
+    if pending == 0 || pending < threshold {
+        tracing::debug!(
+            "Pending rewards {} wei below auto-claim threshold {} wei",
+            pending,
+            threshold
+        );
+        return Ok(RunOutcome::default());
+    }
+
+    let result = backend.claim(None).await?;
+    if !result.success {
+        return Err(result.error.unwrap_or_else(|| "Claim failed".to_string()));
+    }
+
+    tracing::info!(
+        "Auto-claimed {} wei (gas {} wei)",
+        result.amount_claimed_wei,
+        result.gas_spent_wei
+    );
+
+    Ok(RunOutcome {
+        history: vec![history_entry(
+            "staking",
+            &result.amount_claimed_wei,
+            result.tx_hash.clone(),
+            EarningsEventType::Claim,
+        )],
+        events: vec![SchedulerEvent::Claimed(AutoClaimEvent {
+            amount_claimed_wei: result.amount_claimed_wei,
+            gas_spent_wei: result.gas_spent_wei,
+            tx_hash: result.tx_hash,
+        })],
+    })
+}
+
+/// Claim each service's rewards and restake them into the same service, once
+/// the total pending has reached the threshold
+async fn check_and_compound(
+    backend: &impl StakingBackend,
+    earnings: &EarningsConfig,
+) -> Result<RunOutcome, String> {
+    let threshold = parse_threshold(earnings)?;
+    let min_compound: u128 = earnings.auto_compound_min_wei.parse().map_err(|_| {
+        format!(
+            "Invalid minimum compound amount: {}",
+            earnings.auto_compound_min_wei
+        )
+    })?;
+
+    let stakes = backend.pending_rewards().await?;
+    let pending = total_pending(&stakes);
+    if pending == 0 || pending < threshold {
+        tracing::debug!(
+            "Pending rewards {} wei below auto-claim threshold {} wei",
+            pending,
+            threshold
+        );
+        return Ok(RunOutcome::default());
+    }
+
+    let mut events = Vec::new();
+    let mut history = Vec::new();
+
+    for stake in &stakes {
+        if stake.pending_rewards_wei.parse::<u128>().unwrap_or(0) == 0 {
+            continue;
+        }
+
+        let claim = match backend.claim(Some(&stake.service_id)).await {
+            Ok(claim) if claim.success => claim,
+            Ok(claim) => {
+                tracing::warn!(
+                    "Auto-compound claim for {} failed: {}",
+                    stake.service_id,
+                    claim.error.unwrap_or_default()
+                );
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Auto-compound claim for {} failed: {}", stake.service_id, e);
+                continue;
+            }
+        };
+
+        history.push(history_entry(
+            &stake.service_id,
+            &claim.amount_claimed_wei,
+            claim.tx_hash.clone(),
+            EarningsEventType::Claim,
+        ));
+        events.push(SchedulerEvent::Claimed(AutoClaimEvent {
+            amount_claimed_wei: claim.amount_claimed_wei.clone(),
+            gas_spent_wei: claim.gas_spent_wei.clone(),
+            tx_hash: claim.tx_hash.clone(),
+        }));
+
+        let claimed: u128 = claim.amount_claimed_wei.parse().unwrap_or(0);
+        // A stake transaction costs at least what the claim did
+        let stake_gas: u128 = claim.gas_spent_wei.parse().unwrap_or(0);
+        if claimed < min_compound || claimed <= stake_gas {
+            tracing::info!(
+                "Not compounding {} wei for {}: below minimum {} wei or gas {} wei",
+                claimed,
+                stake.service_id,
+                min_compound,
+                stake_gas
+            );
+            continue;
+        }
+
+        let request = StakeRequest {
+            service_id: stake.service_id.clone(),
+            amount_wei: claimed.to_string(),
+            token_address: stake_token_address(&stake.stake_token),
+        };
+        let result = match backend.stake(&request).await {
+            Ok(result) if result.success => result,
+            Ok(result) => {
+                tracing::warn!(
+                    "Auto-compound stake for {} failed: {}",
+                    stake.service_id,
+                    result.error.unwrap_or_default()
+                );
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Auto-compound stake for {} failed: {}", stake.service_id, e);
+                continue;
+            }
+        };
+
+        tracing::info!(
+            "Compounded {} wei into {} ({} -> {} wei staked)",
+            claimed,
+            stake.service_id,
+            stake.staked_wei,
+            result.new_stake_wei
+        );
+        history.push(history_entry(
+            &stake.service_id,
+            &request.amount_wei,
+            result.tx_hash.clone(),
+            EarningsEventType::Stake,
+        ));
+        events.push(SchedulerEvent::Compounded(AutoCompoundEvent {
+            service_id: stake.service_id.clone(),
+            amount_restaked_wei: request.amount_wei,
+            principal_before_wei: stake.staked_wei.clone(),
+            principal_after_wei: result.new_stake_wei,
+            tx_hash: result.tx_hash,
+        }));
+    }
+
+    Ok(RunOutcome { events, history })
+}
+
+/// Stake token for `StakeRequest`, where `None` means ETH
+fn stake_token_address(stake_token: &str) -> Option<String> {
+    (!stake_token.eq_ignore_ascii_case("ETH")).then(|| stake_token.to_string())
+}
+
+fn history_entry(
+    service_id: &str,
+    amount_wei: &str,
+    tx_hash: Option<String>,
+    event_type: EarningsEventType,
+) -> EarningsEntry {
+    EarningsEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+        service_id: service_id.to_string(),
+        amount_wei: amount_wei.to_string(),
+        tx_hash,
+        event_type,
+        usd_at_time: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const ETH: u128 = 1_000_000_000_000_000_000;
+
+    /// Rewards source that pays out whatever is pending and records every call
+    struct FakeStaking {
+        available: bool,
+        stakes: Vec<ServiceStakeInfo>,
+        gas_wei: u128,
+        claim_error: Option<String>,
+        claims: Mutex<Vec<Option<String>>>,
+        restakes: Mutex<Vec<(String, String)>>,
+    }
+
+    impl FakeStaking {
+        fn new(pending: &[(&str, u128)]) -> Self {
+            Self {
+                available: true,
+                stakes: pending
+                    .iter()
+                    .map(|(service_id, pending)| ServiceStakeInfo {
+                        service_id: service_id.to_string(),
+                        service_name: service_id.to_string(),
+                        staked_wei: (10 * ETH).to_string(),
+                        staked_usd: 0.0,
+                        pending_rewards_wei: pending.to_string(),
+                        stake_token: "ETH".to_string(),
+                        min_stake_wei: "0".to_string(),
+                    })
+                    .collect(),
+                gas_wei: 1_000_000_000_000_000,
+                claim_error: None,
+                claims: Mutex::new(Vec::new()),
+                restakes: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl StakingBackend for FakeStaking {
+        fn available(&self) -> bool {
+            self.available
+        }
+
+        async fn pending_rewards(&self) -> Result<Vec<ServiceStakeInfo>, String> {
+            Ok(self.stakes.clone())
+        }
+
+        async fn claim(&self, service_id: Option<&str>) -> Result<ClaimResult, String> {
+            self.claims
+                .lock()
+                .unwrap()
+                .push(service_id.map(String::from));
+            if let Some(error) = &self.claim_error {
+                return Ok(ClaimResult {
+                    success: false,
+                    tx_hash: None,
+                    amount_claimed_wei: "0".to_string(),
+                    gas_spent_wei: "0".to_string(),
+                    error: Some(error.clone()),
+                });
+            }
+            let claimed: u128 = self
+                .stakes
+                .iter()
+                .filter(|s| service_id.is_none() || service_id == Some(s.service_id.as_str()))
+                .map(|s| s.pending_rewards_wei.parse::<u128>().unwrap())
+                .sum();
+            Ok(ClaimResult {
+                success: true,
+                tx_hash: Some("0xc1a1".to_string()),
+                amount_claimed_wei: claimed.to_string(),
+                gas_spent_wei: self.gas_wei.to_string(),
+                error: None,
+            })
+        }
+
+        async fn stake(&self, request: &StakeRequest) -> Result<StakeResult, String> {
+            self.restakes
+                .lock()
+                .unwrap()
+                .push((request.service_id.clone(), request.amount_wei.clone()));
+            let amount: u128 = request.amount_wei.parse().unwrap();
+            Ok(StakeResult {
+                success: true,
+                tx_hash: Some("0x57a4e".to_string()),
+                new_stake_wei: (10 * ETH + amount).to_string(),
+                error: None,
+            })
+        }
+    }
+
+    fn earnings(threshold_wei: u128) -> EarningsConfig {
+        EarningsConfig {
+            auto_claim: true,
+            auto_claim_threshold_wei: threshold_wei.to_string(),
+            auto_compound: false,
+            ..EarningsConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claims_once_pending_reaches_threshold() {
+        let backend = FakeStaking::new(&[("compute", 6 * ETH / 10), ("storage", 5 * ETH / 10)]);
+
+        let outcome = run_once(&backend, &earnings(ETH)).await.unwrap();
+        assert_eq!(*backend.claims.lock().unwrap(), vec![None]);
+        assert_eq!(outcome.history.len(), 1);
+        assert!(matches!(
+            outcome.history[0].event_type,
+            EarningsEventType::Claim
+        ));
+        match &outcome.events[..] {
+            [SchedulerEvent::Claimed(event)] => {
+                assert_eq!(event.amount_claimed_wei, (11 * ETH / 10).to_string());
+                assert_eq!(event.gas_spent_wei, backend.gas_wei.to_string());
+            }
+            _ => panic!("expected a single claim"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nothing_claimed_without_claim_support() {
+        let mut backend = FakeStaking::new(&[("compute", 5 * ETH)]);
+        backend.available = false;
+
+        let outcome = run_once(&backend, &earnings(ETH)).await.unwrap();
+        assert!(outcome.events.is_empty());
+        assert!(backend.claims.lock().unwrap().is_empty());
+
+        // Nor does the real backend without a wallet
+        let state = crate::state::AppState::new();
+        let backend = ContractStaking(StakingContext::new(&*state.inner.read().await));
+        assert!(!backend.available());
+    }
+
+    #[tokio::test]
+    async fn test_nothing_claimed_below_threshold() {
+        let backend = FakeStaking::new(&[("compute", 6 * ETH / 10), ("storage", 3 * ETH / 10)]);
+        let outcome = run_once(&backend, &earnings(ETH)).await.unwrap();
+        assert!(outcome.events.is_empty());
+        assert!(outcome.history.is_empty());
+
+        // A zero threshold still doesn't claim nothing
+        let backend = FakeStaking::new(&[("compute", 0)]);
+        let outcome = run_once(&backend, &earnings(0)).await.unwrap();
+        assert!(outcome.events.is_empty());
+        assert!(backend.claims.lock().unwrap().is_empty());
+
+        let mut config = earnings(ETH);
+        config.auto_claim_threshold_wei = "one ether".to_string();
+        assert!(run_once(&backend, &config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_claim_fails_the_run() {
+        let mut backend = FakeStaking::new(&[("compute", 2 * ETH)]);
+        backend.claim_error = Some("reverted".to_string());
+
+        let result = run_once(&backend, &earnings(ETH)).await;
+        assert_eq!(result.err().as_deref(), Some("reverted"));
+        assert_eq!(backend.claims.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_compound_restakes_each_service_above_minimum_and_gas() {
+        // Default minimum is 0.01 ETH
+        let mut backend = FakeStaking::new(&[
+            ("compute", 2 * ETH),
+            // Below the compound minimum
+            ("storage", 5 * ETH / 1000),
+            // Above the minimum, but the stake gas would eat it
+            ("proxy", 15 * ETH / 1000),
+            ("idle", 0),
+        ]);
+        backend.gas_wei = 2 * ETH / 100;
+        let mut config = earnings(ETH);
+        config.auto_claim = false;
+        config.auto_compound = true;
+
+        let outcome = run_once(&backend, &config).await.unwrap();
+
+        assert_eq!(
+            *backend.claims.lock().unwrap(),
+            vec![
+                Some("compute".to_string()),
+                Some("storage".to_string()),
+                Some("proxy".to_string()),
+            ]
+        );
+        assert_eq!(
+            *backend.restakes.lock().unwrap(),
+            vec![("compute".to_string(), (2 * ETH).to_string())]
+        );
+        // Three claims and one stake
+        assert_eq!(outcome.history.len(), 4);
+        let compounded: Vec<_> = outcome
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                SchedulerEvent::Compounded(event) => Some(event),
+                SchedulerEvent::Claimed(_) => None,
+            })
+            .collect();
+        match &compounded[..] {
+            [event] => {
+                assert_eq!(event.service_id, "compute");
+                assert_eq!(event.principal_before_wei, (10 * ETH).to_string());
+                assert_eq!(event.principal_after_wei, (12 * ETH).to_string());
+            }
+            _ => panic!("expected a single compound"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compound_waits_for_total_threshold() {
+        let backend = FakeStaking::new(&[("compute", ETH / 2)]);
+        let mut config = earnings(ETH);
+        config.auto_compound = true;
+
+        let outcome = run_once(&backend, &config).await.unwrap();
+        assert!(outcome.events.is_empty());
+        assert!(backend.claims.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_runs_back_off_up_to_the_interval() {
+        let interval = Duration::from_secs(24 * 3600);
+        assert_eq!(next_check_delay(interval, 0), interval);
+        assert_eq!(next_check_delay(interval, 1), RETRY_BACKOFF);
+        assert_eq!(next_check_delay(interval, 2), RETRY_BACKOFF * 2);
+        assert_eq!(next_check_delay(interval, 4), RETRY_BACKOFF * 8);
+        assert_eq!(next_check_delay(interval, 10), interval);
+        assert_eq!(next_check_delay(interval, u32::MAX), interval);
+
+        // Retries never wait longer than a short interval would
+        let hourly = Duration::from_secs(3600);
+        assert_eq!(next_check_delay(hourly, 5), hourly);
+    }
+}
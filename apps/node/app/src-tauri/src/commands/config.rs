@@ -1,10 +1,11 @@
 //! Configuration management commands
 
-use crate::config::{BotConfig, EarningsConfig, NetworkConfig, ServiceConfig};
+use crate::config::{BotConfig, EarningsConfig, HealthCheckConfig, NetworkConfig, ServiceConfig};
+use crate::rpc_client::EndpointStatus;
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::State;
+use tauri::{AppHandle, State};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -12,6 +13,7 @@ pub struct AppConfig {
     pub network: NetworkConfig,
     pub wallet: WalletConfigPublic,
     pub earnings: EarningsConfig,
+    pub health_check: HealthCheckConfig,
     pub services: HashMap<String, ServiceConfig>,
     pub bots: HashMap<String, BotConfig>,
     pub start_minimized: bool,
@@ -29,6 +31,7 @@ pub struct WalletConfigPublic {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateConfigRequest {
     pub earnings: Option<EarningsConfig>,
+    pub health_check: Option<HealthCheckConfig>,
     pub services: Option<HashMap<String, ServiceConfig>>,
     pub bots: Option<HashMap<String, BotConfig>>,
     pub start_minimized: Option<bool>,
@@ -66,6 +69,7 @@ pub async fn get_config(state: State<'_, AppState>) -> Result<AppConfig, String>
             agent_id: inner.config.wallet.agent_id,
         },
         earnings: inner.config.earnings.clone(),
+        health_check: inner.config.health_check.clone(),
         services: inner.config.services.clone(),
         bots: inner.config.bots.clone(),
         start_minimized: inner.config.start_minimized,
@@ -76,15 +80,21 @@ pub async fn get_config(state: State<'_, AppState>) -> Result<AppConfig, String>
 
 #[tauri::command]
 pub async fn update_config(
+    app: AppHandle,
     state: State<'_, AppState>,
     request: UpdateConfigRequest,
 ) -> Result<AppConfig, String> {
     let mut inner = state.inner.write().await;
 
+    let earnings_changed = request.earnings.is_some();
     if let Some(earnings) = request.earnings {
         inner.config.earnings = earnings;
     }
 
+    if let Some(health_check) = request.health_check {
+        inner.config.health_check = health_check;
+    }
+
     if let Some(services) = request.services {
         for (id, config) in services {
             inner.config.services.insert(id, config);
@@ -110,6 +120,9 @@ pub async fn update_config(
     }
 
     inner.config.save().map_err(|e| e.to_string())?;
+    if earnings_changed {
+        crate::auto_claim::persist(&app, &inner.config.earnings)?;
+    }
 
     // Return updated config
     let wallet_type = match inner.config.wallet.wallet_type {
@@ -128,6 +141,7 @@ pub async fn update_config(
             agent_id: inner.config.wallet.agent_id,
         },
         earnings: inner.config.earnings.clone(),
+        health_check: inner.config.health_check.clone(),
         services: inner.config.services.clone(),
         bots: inner.config.bots.clone(),
         start_minimized: inner.config.start_minimized,
@@ -154,6 +168,7 @@ pub async fn set_network(
             network: "mainnet".to_string(),
             chain_id: 420690,
             rpc_url: "https://rpc.jejunetwork.org".to_string(),
+            fallback_rpc_urls: Vec::new(),
             ws_url: Some("wss://ws.jejunetwork.org".to_string()),
             explorer_url: "https://explorer.jejunetwork.org".to_string(),
         },
@@ -161,6 +176,7 @@ pub async fn set_network(
             network: "testnet".to_string(),
             chain_id: 420691,
             rpc_url: "https://testnet-rpc.jejunetwork.org".to_string(),
+            fallback_rpc_urls: Vec::new(),
             ws_url: Some("wss://testnet-ws.jejunetwork.org".to_string()),
             explorer_url: "https://testnet-explorer.jejunetwork.org".to_string(),
         },
@@ -168,6 +184,7 @@ pub async fn set_network(
             network: "localnet".to_string(),
             chain_id: 31337,
             rpc_url: "http://localhost:6546".to_string(),
+            fallback_rpc_urls: Vec::new(),
             ws_url: Some("ws://localhost:6547".to_string()),
             explorer_url: "http://localhost:4000".to_string(),
         },
@@ -186,3 +203,42 @@ pub async fn set_network(
 
     Ok(network_config)
 }
+
+#[tauri::command]
+pub async fn set_rpc_endpoints(
+    state: State<'_, AppState>,
+    urls: Vec<String>,
+) -> Result<NetworkConfig, String> {
+    let urls: Vec<String> = urls.into_iter().map(|u| u.trim().to_string()).collect();
+    if urls.is_empty() {
+        return Err("At least one RPC endpoint is required".to_string());
+    }
+    if let Some(url) = urls
+        .iter()
+        .find(|u| !u.starts_with("http://") && !u.starts_with("https://"))
+    {
+        return Err(format!("Invalid RPC endpoint: {}", url));
+    }
+
+    let mut inner = state.inner.write().await;
+
+    inner.config.network.rpc_url = urls[0].clone();
+    inner.config.network.fallback_rpc_urls = urls[1..].to_vec();
+    inner.config.save().map_err(|e| e.to_string())?;
+
+    let config_clone = inner.config.clone();
+    inner
+        .service_manager
+        .initialize(&config_clone)
+        .map_err(|e| e.to_string())?;
+
+    Ok(inner.config.network.clone())
+}
+
+#[tauri::command]
+pub async fn get_rpc_endpoint_status(
+    state: State<'_, AppState>,
+) -> Result<Vec<EndpointStatus>, String> {
+    let inner = state.inner.read().await;
+    Ok(inner.service_manager.rpc_endpoint_status())
+}
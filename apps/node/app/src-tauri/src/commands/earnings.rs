@@ -1,8 +1,10 @@
 //! Earnings tracking commands
 
+use crate::earnings::ExportFormat;
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EarningsSummary {
@@ -212,16 +214,40 @@ pub async fn get_projected_earnings(
 
 #[tauri::command]
 pub async fn export_earnings(
-    _state: State<'_, AppState>,
-    _format: String, // "csv" or "json"
-    _start_timestamp: Option<i64>,
-    _end_timestamp: Option<i64>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    format: ExportFormat,
+    start_timestamp: Option<i64>,
+    end_timestamp: Option<i64>,
 ) -> Result<String, String> {
-    // TODO: Export earnings data to file
-    // 1. Query all earnings history
-    // 2. Format as CSV or JSON
-    // 3. Write to file in data directory
-    // 4. Return file path
-
-    Err("Export not yet implemented".to_string())
+    // Serialize first so a failure doesn't leave the user with an empty file
+    let contents = state
+        .inner
+        .read()
+        .await
+        .earnings_tracker
+        .export(format, start_timestamp, end_timestamp)
+        .map_err(|e| e.to_string())?;
+
+    let extension = format.extension();
+    let file_name = format!(
+        "jeju-earnings-{}.{}",
+        chrono::Utc::now().format("%Y-%m-%d"),
+        extension
+    );
+
+    let path = app
+        .dialog()
+        .file()
+        .set_title("Export Earnings")
+        .set_file_name(&file_name)
+        .add_filter(extension.to_uppercase(), &[extension])
+        .blocking_save_file()
+        .ok_or("Export cancelled")?
+        .into_path()
+        .map_err(|e| e.to_string())?;
+
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+
+    Ok(path.to_string_lossy().into_owned())
 }
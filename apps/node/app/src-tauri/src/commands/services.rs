@@ -13,6 +13,9 @@ pub struct StartServiceRequest {
     pub auto_stake: bool,
     pub stake_amount: Option<String>,
     pub custom_settings: Option<HashMap<String, serde_json::Value>>,
+    /// Start even if the hardware doesn't meet the service's requirements
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -83,6 +86,8 @@ pub async fn get_available_services(
                     earnings_wei: "0".to_string(),
                     last_error: None,
                     health: "stopped".to_string(),
+                    rpc_endpoint: None,
+                    usage: None,
                 },
                 meets_requirements: meets,
                 requirement_issues: issues,
@@ -98,11 +103,33 @@ pub async fn start_service(
     state: State<'_, AppState>,
     request: StartServiceRequest,
 ) -> Result<ServiceState, String> {
-    let mut inner = state.inner.write().await;
-
     // Parse service ID
     let service_id: ServiceId = request.service_id.parse()?;
 
+    // Detect hardware before taking the state lock; it can take a while
+    let mut detector = HardwareDetector::new();
+    let hardware = detector.detect();
+
+    let mut inner = state.inner.write().await;
+
+    // Pre-flight: refuse to launch a service this machine can't sustain
+    let reqs = inner.service_manager.get_requirements(service_id)?;
+    let (meets, issues) = detector.meets_requirements(&hardware, &reqs);
+    if !meets {
+        if !request.force {
+            return Err(format!(
+                "Hardware does not meet the requirements for {}: {}",
+                service_id.as_str(),
+                issues.join("; ")
+            ));
+        }
+        tracing::warn!(
+            "Force-starting {} on unsupported hardware: {}",
+            service_id.as_str(),
+            issues.join("; ")
+        );
+    }
+
     // Get or create service config
     let config = inner
         .config
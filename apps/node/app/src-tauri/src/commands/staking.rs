@@ -1,8 +1,32 @@
 //! Staking management commands
 
-use crate::state::AppState;
+use crate::state::{AppState, AppStateInner};
+use crate::wallet::WalletManager;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
+
+/// Whether the auto-claim scheduler sends claim and stake transactions. Off
+/// until the staking contracts are wired up; the settings are still saved.
+pub(crate) const CLAIMS_SUPPORTED: bool = cfg!(feature = "staking-claims");
+
+/// What the staking calls need from app state, copied out so no lock is held
+/// across RPC round-trips
+#[derive(Clone)]
+pub(crate) struct StakingContext {
+    wallet: Option<WalletManager>,
+}
+
+impl StakingContext {
+    pub fn new(inner: &AppStateInner) -> Self {
+        Self {
+            wallet: inner.wallet_manager.clone(),
+        }
+    }
+
+    pub fn wallet_connected(&self) -> bool {
+        self.wallet.is_some()
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StakingInfo {
@@ -14,6 +38,9 @@ pub struct StakingInfo {
     pub can_unstake: bool,
     pub unstake_cooldown_seconds: u64,
     pub auto_claim_enabled: bool,
+    pub auto_compound_enabled: bool,
+    /// Whether this build acts on the auto-claim settings
+    pub auto_claim_available: bool,
     pub next_auto_claim_timestamp: Option<u64>,
 }
 
@@ -54,6 +81,7 @@ pub struct ClaimResult {
     pub success: bool,
     pub tx_hash: Option<String>,
     pub amount_claimed_wei: String,
+    pub gas_spent_wei: String,
     pub error: Option<String>,
 }
 
@@ -76,20 +104,29 @@ pub async fn get_staking_info(state: State<'_, AppState>) -> Result<StakingInfo,
         pending_rewards_usd: 0.0,
         can_unstake: false,
         unstake_cooldown_seconds: 0,
-        auto_claim_enabled: inner.config.earnings.auto_claim,
-        next_auto_claim_timestamp: None,
+        auto_claim_enabled: inner.config.earnings.claims_automatically(),
+        auto_compound_enabled: inner.config.earnings.auto_compound,
+        auto_claim_available: CLAIMS_SUPPORTED,
+        next_auto_claim_timestamp: inner.next_auto_claim_timestamp,
     })
 }
 
 #[tauri::command]
 pub async fn stake(
     state: State<'_, AppState>,
-    _request: StakeRequest,
+    request: StakeRequest,
 ) -> Result<StakeResult, String> {
-    let inner = state.inner.read().await;
+    let context = StakingContext::new(&*state.inner.read().await);
+    stake_to(&context, &request).await
+}
 
+/// Stake path shared by the command and auto-compounding
+pub(crate) async fn stake_to(
+    context: &StakingContext,
+    _request: &StakeRequest,
+) -> Result<StakeResult, String> {
     // Verify wallet
-    if inner.wallet_manager.is_none() {
+    if !context.wallet_connected() {
         return Err("Wallet not connected".to_string());
     }
 
@@ -125,12 +162,19 @@ pub async fn unstake(
 #[tauri::command]
 pub async fn claim_rewards(
     state: State<'_, AppState>,
-    _service_id: Option<String>,
+    service_id: Option<String>,
 ) -> Result<ClaimResult, String> {
-    let inner = state.inner.read().await;
+    let context = StakingContext::new(&*state.inner.read().await);
+    claim(&context, service_id.as_deref()).await
+}
 
+/// Claim path shared by the command and the auto-claim scheduler
+pub(crate) async fn claim(
+    context: &StakingContext,
+    _service_id: Option<&str>,
+) -> Result<ClaimResult, String> {
     // Verify wallet
-    if inner.wallet_manager.is_none() {
+    if !context.wallet_connected() {
         return Err("Wallet not connected".to_string());
     }
 
@@ -144,6 +188,7 @@ pub async fn claim_rewards(
 
 #[tauri::command]
 pub async fn enable_auto_claim(
+    app: AppHandle,
     state: State<'_, AppState>,
     enabled: bool,
     threshold_wei: Option<String>,
@@ -162,13 +207,47 @@ pub async fn enable_auto_claim(
     }
 
     inner.config.save().map_err(|e| e.to_string())?;
+    crate::auto_claim::persist(&app, &inner.config.earnings)?;
+
+    Ok(())
+}
+
+/// Restake claimed rewards automatically. Compounding claims on the auto-claim
+/// schedule even when plain auto-claim is off.
+#[tauri::command]
+pub async fn enable_auto_compound(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    enabled: bool,
+    min_compound_wei: Option<String>,
+) -> Result<(), String> {
+    let mut inner = state.inner.write().await;
+
+    if let Some(min) = min_compound_wei {
+        min.parse::<u128>()
+            .map_err(|_| format!("Invalid minimum compound amount: {}", min))?;
+        inner.config.earnings.auto_compound_min_wei = min;
+    }
+
+    inner.config.earnings.auto_compound = enabled;
+
+    inner.config.save().map_err(|e| e.to_string())?;
+    crate::auto_claim::persist(&app, &inner.config.earnings)?;
 
     Ok(())
 }
 
 #[tauri::command]
 pub async fn get_pending_rewards(
-    _state: State<'_, AppState>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ServiceStakeInfo>, String> {
+    let context = StakingContext::new(&*state.inner.read().await);
+    pending_rewards(&context).await
+}
+
+/// Pending rewards per service, shared with the auto-claim scheduler
+pub(crate) async fn pending_rewards(
+    _context: &StakingContext,
 ) -> Result<Vec<ServiceStakeInfo>, String> {
     // TODO: Query all staking contracts for pending rewards
 
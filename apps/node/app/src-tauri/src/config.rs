@@ -11,6 +11,22 @@ pub struct ServiceConfig {
     pub auto_start: bool,
     pub stake_amount: Option<String>,
     pub custom_settings: HashMap<String, serde_json::Value>,
+    /// Share of one core the service's task may use, 1-100. Services run
+    /// in-process, so there is no memory limit.
+    #[serde(default)]
+    pub max_cpu_percent: Option<u8>,
+}
+
+impl ServiceConfig {
+    pub fn validate_limits(&self) -> Result<(), String> {
+        if self
+            .max_cpu_percent
+            .is_some_and(|pct| !(1..=100).contains(&pct))
+        {
+            return Err("max_cpu_percent must be between 1 and 100".to_string());
+        }
+        Ok(())
+    }
 }
 
 impl Default for ServiceConfig {
@@ -20,6 +36,7 @@ impl Default for ServiceConfig {
             auto_start: false,
             stake_amount: None,
             custom_settings: HashMap::new(),
+            max_cpu_percent: None,
         }
     }
 }
@@ -53,17 +70,32 @@ impl Default for BotConfig {
 pub struct NetworkConfig {
     pub network: String,
     pub chain_id: u64,
+    /// Primary RPC endpoint
     pub rpc_url: String,
+    /// Endpoints tried in order when the primary is down or rate-limited
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
     pub ws_url: Option<String>,
     pub explorer_url: String,
 }
 
+impl NetworkConfig {
+    /// All RPC endpoints in priority order, primary first
+    pub fn rpc_endpoints(&self) -> Vec<String> {
+        std::iter::once(&self.rpc_url)
+            .chain(&self.fallback_rpc_urls)
+            .cloned()
+            .collect()
+    }
+}
+
 impl Default for NetworkConfig {
     fn default() -> Self {
         Self {
             network: "mainnet".to_string(),
             chain_id: 420690,
             rpc_url: "https://rpc.jejunetwork.org".to_string(),
+            fallback_rpc_urls: Vec::new(),
             ws_url: Some("wss://ws.jejunetwork.org".to_string()),
             explorer_url: "https://explorer.jejunetwork.org".to_string(),
         }
@@ -105,10 +137,25 @@ pub struct EarningsConfig {
     pub auto_claim: bool,
     pub auto_claim_threshold_wei: String,
     pub auto_claim_interval_hours: u32,
+    /// Restake claimed rewards; implies auto-claim
     pub auto_compound: bool,
+    /// Smallest claim worth restaking, so the stake transaction's gas doesn't eat it
+    #[serde(default = "default_auto_compound_min_wei")]
+    pub auto_compound_min_wei: String,
     pub auto_stake_earnings: bool,
 }
 
+fn default_auto_compound_min_wei() -> String {
+    "10000000000000000".to_string() // 0.01 ETH
+}
+
+impl EarningsConfig {
+    /// Whether the scheduler claims rewards, either plainly or to compound them
+    pub fn claims_automatically(&self) -> bool {
+        self.auto_claim || self.auto_compound
+    }
+}
+
 impl Default for EarningsConfig {
     fn default() -> Self {
         Self {
@@ -116,11 +163,33 @@ impl Default for EarningsConfig {
             auto_claim_threshold_wei: "1000000000000000000".to_string(), // 1 ETH
             auto_claim_interval_hours: 24,
             auto_compound: false,
+            auto_compound_min_wei: default_auto_compound_min_wei(),
             auto_stake_earnings: false,
         }
     }
 }
 
+/// Service health supervision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckConfig {
+    pub interval_secs: u64,
+    pub auto_restart: bool,
+    /// Consecutive failed checks before a service is restarted
+    pub max_failures: u32,
+    pub max_restart_backoff_secs: u64,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            auto_restart: true,
+            max_failures: 3,
+            max_restart_backoff_secs: 600,
+        }
+    }
+}
+
 /// Main node configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeConfig {
@@ -128,6 +197,8 @@ pub struct NodeConfig {
     pub network: NetworkConfig,
     pub wallet: WalletConfig,
     pub earnings: EarningsConfig,
+    #[serde(default)]
+    pub health_check: HealthCheckConfig,
     pub services: HashMap<String, ServiceConfig>,
     pub bots: HashMap<String, BotConfig>,
     pub start_minimized: bool,
@@ -171,6 +242,7 @@ impl Default for NodeConfig {
             network: NetworkConfig::default(),
             wallet: WalletConfig::default(),
             earnings: EarningsConfig::default(),
+            health_check: HealthCheckConfig::default(),
             services,
             bots,
             start_minimized: false,
@@ -11,6 +11,9 @@ pub struct EarningsEntry {
     pub amount_wei: String,
     pub tx_hash: Option<String>,
     pub event_type: EarningsEventType,
+    /// USD value of the amount when it was earned, if a price was available
+    #[serde(default)]
+    pub usd_at_time: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +26,46 @@ pub enum EarningsEventType {
     Unstake,
 }
 
+/// File format for exported earnings history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// One exported earnings row
+#[derive(Debug, Clone, Serialize)]
+struct ExportRecord<'a> {
+    /// ISO-8601, UTC
+    timestamp: String,
+    service: &'a str,
+    amount_wei: &'a str,
+    usd_at_time: Option<f64>,
+}
+
+impl<'a> From<&'a EarningsEntry> for ExportRecord<'a> {
+    fn from(entry: &'a EarningsEntry) -> Self {
+        Self {
+            timestamp: chrono::DateTime::from_timestamp(entry.timestamp, 0)
+                .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                .unwrap_or_default(),
+            service: &entry.service_id,
+            amount_wei: &entry.amount_wei,
+            usd_at_time: entry.usd_at_time,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EarningsStats {
     pub total_wei: String,
@@ -159,6 +202,39 @@ impl EarningsTracker {
         filtered
     }
 
+    /// Serialize the history in `[start_time, end_time]`, oldest first
+    pub fn export(
+        &self,
+        format: ExportFormat,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<String, serde_json::Error> {
+        let mut entries = self.get_entries(None, start_time, end_time, None);
+        entries.reverse();
+
+        let records: Vec<ExportRecord> = entries.into_iter().map(ExportRecord::from).collect();
+
+        match format {
+            ExportFormat::Json => serde_json::to_string_pretty(&records),
+            ExportFormat::Csv => {
+                let mut csv = String::from("timestamp,service,amount_wei,usd_at_time\n");
+                for record in &records {
+                    csv.push_str(&format!(
+                        "{},{},{},{}\n",
+                        record.timestamp,
+                        csv_field(record.service),
+                        csv_field(record.amount_wei),
+                        record
+                            .usd_at_time
+                            .map(|usd| usd.to_string())
+                            .unwrap_or_default()
+                    ));
+                }
+                Ok(csv)
+            }
+        }
+    }
+
     pub fn get_stats(&self) -> &EarningsStats {
         &self.stats
     }
@@ -173,6 +249,15 @@ impl EarningsTracker {
     }
 }
 
+/// Quote a CSV field if it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EarningsData {
     entries: Vec<EarningsEntry>,
@@ -2,10 +2,12 @@
 //!
 //! Shared library components for the Jeju Node Tauri application.
 
+pub mod auto_claim;
 pub mod commands;
 pub mod config;
 pub mod earnings;
 pub mod hardware;
+pub mod rpc_client;
 pub mod services;
 pub mod state;
 pub mod tee;
@@ -8,16 +8,20 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod auto_claim;
 mod commands;
 mod config;
 mod earnings;
 mod hardware;
+mod rpc_client;
 mod services;
 mod state;
 mod tee;
 mod wallet;
 
-use tauri::Manager;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn main() {
@@ -37,6 +41,7 @@ fn main() {
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_dialog::init())
         .manage(state::AppState::new())
         .invoke_handler(tauri::generate_handler![
             // Hardware detection
@@ -67,6 +72,7 @@ fn main() {
             commands::staking::unstake,
             commands::staking::claim_rewards,
             commands::staking::enable_auto_claim,
+            commands::staking::enable_auto_compound,
             commands::staking::get_pending_rewards,
             // Earnings
             commands::earnings::get_earnings_summary,
@@ -78,6 +84,8 @@ fn main() {
             commands::config::update_config,
             commands::config::get_network_config,
             commands::config::set_network,
+            commands::config::set_rpc_endpoints,
+            commands::config::get_rpc_endpoint_status,
             // Trading bots
             commands::bots::get_available_bots,
             commands::bots::start_bot,
@@ -91,6 +99,47 @@ fn main() {
             // Initialize state
             let state = app.state::<state::AppState>();
             state.initialize(&handle)?;
+            auto_claim::restore(&handle, &mut state.inner.blocking_write().config.earnings);
+
+            // Claim (and optionally restake) staking rewards once they cross the threshold
+            let claim_handle = handle.clone();
+            tauri::async_runtime::spawn(auto_claim::run(state.inner.clone(), move |event| {
+                let _ = match event {
+                    auto_claim::SchedulerEvent::Claimed(event) => {
+                        claim_handle.emit("auto_claim", &event)
+                    }
+                    auto_claim::SchedulerEvent::Compounded(event) => {
+                        claim_handle.emit("auto_compound", &event)
+                    }
+                };
+            }));
+
+            // Watch service health and surface transitions to the UI and tray
+            let health_handle = handle.clone();
+            let unhealthy = Mutex::new(HashSet::new());
+            tauri::async_runtime::spawn(services::supervise_health(
+                state.inner.clone(),
+                move |event| {
+                    let _ = health_handle.emit("service_health", &event);
+
+                    let mut unhealthy = unhealthy.lock().unwrap();
+                    if event.health == "unhealthy" {
+                        unhealthy.insert(event.service_id.clone());
+                    } else {
+                        unhealthy.remove(&event.service_id);
+                    }
+
+                    #[cfg(desktop)]
+                    if let Some(tray) = health_handle.tray_by_id("main") {
+                        let tooltip = if unhealthy.is_empty() {
+                            "Jeju Node".to_string()
+                        } else {
+                            format!("Jeju Node - {} service(s) unhealthy", unhealthy.len())
+                        };
+                        let _ = tray.set_tooltip(Some(tooltip));
+                    }
+                },
+            ));
 
             // Set up system tray
             #[cfg(desktop)]
@@ -121,6 +170,16 @@ fn main() {
                 api.prevent_close();
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Give services a bounded chance to wind down before the process exits
+                let state = app.state::<state::AppState>();
+                tauri::async_runtime::block_on(async {
+                    let mut inner = state.inner.write().await;
+                    inner.service_manager.shutdown_all().await;
+                });
+            }
+        });
 }
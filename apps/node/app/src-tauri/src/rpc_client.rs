@@ -0,0 +1,238 @@
+//! JSON-RPC client with endpoint failover
+//!
+//! Requests go to the first healthy endpoint in the configured order. Connection
+//! errors, timeouts, HTTP 429 and 5xx responses mark an endpoint unhealthy and
+//! the request moves on to the next one. Unhealthy endpoints are skipped for
+//! `COOLDOWN`, and a background task probes them so traffic returns to the
+//! primary once it recovers. Errors returned by the node itself (JSON-RPC
+//! `error` objects) are passed through without failing over.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a failed endpoint is skipped before requests try it again
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How often unhealthy endpoints are probed in the background
+const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Health of one configured endpoint, for service status and debugging
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+}
+
+/// Why a request to one endpoint failed
+enum Failure {
+    /// The endpoint is unreachable or refusing us; try the next one
+    Endpoint(String),
+    /// The node answered with an error; another endpoint would say the same
+    Rpc(String),
+}
+
+#[derive(Default)]
+struct Health {
+    consecutive_failures: u32,
+    retry_at: Option<Instant>,
+}
+
+struct Endpoint {
+    url: String,
+    health: Mutex<Health>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    endpoints: Vec<Endpoint>,
+}
+
+/// Shared JSON-RPC client. Clones share endpoint health.
+#[derive(Clone)]
+pub struct RpcClient {
+    inner: Arc<Inner>,
+}
+
+impl RpcClient {
+    /// Client over `urls`, in priority order. Duplicates and blanks are dropped.
+    pub fn new(urls: Vec<String>) -> Self {
+        let mut endpoints: Vec<Endpoint> = Vec::new();
+        for url in urls {
+            let url = url.trim().to_string();
+            if url.is_empty() || endpoints.iter().any(|e| e.url == url) {
+                continue;
+            }
+            endpoints.push(Endpoint {
+                url,
+                health: Mutex::new(Health::default()),
+            });
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        let inner = Arc::new(Inner { client, endpoints });
+
+        // Nothing to fail back to with a single endpoint
+        if inner.endpoints.len() > 1 {
+            tauri::async_runtime::spawn(probe_unhealthy(Arc::downgrade(&inner)));
+        }
+
+        Self { inner }
+    }
+
+    /// Endpoint the next request will go to
+    pub fn active_endpoint(&self) -> Option<String> {
+        let index = *self.inner.candidates().first()?;
+        Some(self.inner.endpoints[index].url.clone())
+    }
+
+    /// Health of every configured endpoint, in priority order
+    pub fn endpoint_status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.inner
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock();
+                EndpointStatus {
+                    url: endpoint.url.clone(),
+                    healthy: health.retry_at.map_or(true, |at| now >= at),
+                    consecutive_failures: health.consecutive_failures,
+                }
+            })
+            .collect()
+    }
+
+    /// Send a JSON-RPC request, failing over between endpoints
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let mut last_error = "No RPC endpoints configured".to_string();
+
+        for index in self.inner.candidates() {
+            match self.inner.send(index, method, &params).await {
+                Ok(result) => {
+                    self.inner.mark_healthy(index);
+                    return Ok(result);
+                }
+                Err(Failure::Rpc(e)) => {
+                    self.inner.mark_healthy(index);
+                    return Err(e);
+                }
+                Err(Failure::Endpoint(e)) => {
+                    let url = &self.inner.endpoints[index].url;
+                    tracing::warn!("RPC endpoint {} failed, trying next: {}", url, e);
+                    self.inner.mark_failed(index);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(format!("All RPC endpoints failed: {}", last_error))
+    }
+}
+
+impl Inner {
+    /// Endpoint indices to try: healthy ones in priority order, then those
+    /// cooling down as a last resort
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let (mut ready, cooling): (Vec<usize>, Vec<usize>) =
+            (0..self.endpoints.len()).partition(|&i| {
+                self.endpoints[i]
+                    .health
+                    .lock()
+                    .retry_at
+                    .map_or(true, |at| now >= at)
+            });
+        ready.extend(cooling);
+        ready
+    }
+
+    async fn send(&self, index: usize, method: &str, params: &Value) -> Result<Value, Failure> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .client
+            .post(&self.endpoints[index].url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| Failure::Endpoint(e.to_string()))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return Err(Failure::Endpoint(format!("HTTP {}", status)));
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| Failure::Endpoint(format!("Invalid response: {}", e)))?;
+
+        if let Some(error) = body.get("error") {
+            return Err(Failure::Rpc(format!("RPC error: {}", error)));
+        }
+        body.get("result")
+            .cloned()
+            .ok_or_else(|| Failure::Endpoint("Response has no result".to_string()))
+    }
+
+    fn mark_healthy(&self, index: usize) {
+        let endpoint = &self.endpoints[index];
+        let mut health = endpoint.health.lock();
+        if health.consecutive_failures > 0 {
+            tracing::info!("RPC endpoint {} is healthy again", endpoint.url);
+        }
+        *health = Health::default();
+    }
+
+    fn mark_failed(&self, index: usize) {
+        let mut health = self.endpoints[index].health.lock();
+        health.consecutive_failures += 1;
+        health.retry_at = Some(Instant::now() + COOLDOWN);
+    }
+}
+
+/// Periodically probe unhealthy endpoints until the client is dropped
+async fn probe_unhealthy(inner: Weak<Inner>) {
+    loop {
+        tokio::time::sleep(PROBE_INTERVAL).await;
+
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        for index in 0..inner.endpoints.len() {
+            if inner.endpoints[index].health.lock().consecutive_failures == 0 {
+                continue;
+            }
+            match inner
+                .send(index, "eth_chainId", &Value::Array(vec![]))
+                .await
+            {
+                Ok(_) | Err(Failure::Rpc(_)) => inner.mark_healthy(index),
+                Err(Failure::Endpoint(e)) => {
+                    tracing::debug!(
+                        "RPC endpoint {} still failing: {}",
+                        inner.endpoints[index].url,
+                        e
+                    );
+                    inner.mark_failed(index);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Per-service CPU budgets
+//!
+//! Services run as tasks inside the app process, not as child processes, so
+//! there is no per-service process for cgroups, job objects or `setrlimit` to
+//! cap. `max_cpu_percent` is enforced on the task instead: CPU is the time spent
+//! polling the service's task, and once that passes the cap within a one-second
+//! window the task isn't polled again until its share is back under the cap.
+//! Work handed to `spawn_blocking` or to tasks the service spawns itself isn't
+//! counted.
+//!
+//! Memory can't be attributed to a task, so there is no memory cap. OS-level
+//! CPU and memory limits become possible once a service runs its workload as a
+//! child process (the execution client for the sequencer, Ollama for compute).
+
+use super::ServiceId;
+use crate::config::ServiceConfig;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Window CPU usage is measured and capped over
+const CPU_WINDOW: Duration = Duration::from_secs(1);
+
+/// Usage of each service's task over its last full window, in tenths of a
+/// percent, indexed by `ServiceId`
+static CPU_PERMILLE: [AtomicU32; SERVICES] = [const { AtomicU32::new(0) }; SERVICES];
+
+const SERVICES: usize = ServiceId::Sequencer as usize + 1;
+
+/// Resources a running service's task is using
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Share of one core over the last measured window
+    pub cpu_percent: f32,
+}
+
+/// Current usage of `id`'s task
+pub fn usage(id: ServiceId) -> ResourceUsage {
+    ResourceUsage {
+        cpu_percent: CPU_PERMILLE[id as usize].load(Ordering::Relaxed) as f32 / 10.0,
+    }
+}
+
+/// Wrap a service's loop so its polling time is charged to `id`
+pub(super) fn budgeted<F>(id: ServiceId, config: &ServiceConfig, future: F) -> Budgeted<F>
+where
+    F: Future<Output = ()>,
+{
+    Budgeted {
+        future: Box::pin(future),
+        id,
+        meter: CpuMeter::new(config.max_cpu_percent, Instant::now()),
+        pause: None,
+    }
+}
+
+pub(super) struct Budgeted<F> {
+    future: Pin<Box<F>>,
+    id: ServiceId,
+    meter: CpuMeter,
+    /// Set while the task is held back for going over its CPU cap
+    pause: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<F: Future<Output = ()>> Future for Budgeted<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        if let Some(pause) = &mut this.pause {
+            if pause.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.pause = None;
+        }
+
+        let started = Instant::now();
+        let poll = this.future.as_mut().poll(cx);
+        let now = Instant::now();
+
+        if let Some(delay) = this.meter.charge(now, now - started) {
+            tracing::debug!(
+                "Service {} is over its CPU limit, pausing it for {:?}",
+                this.id.as_str(),
+                delay
+            );
+            this.pause = Some(Box::pin(tokio::time::sleep(delay)));
+        }
+        CPU_PERMILLE[this.id as usize].store(this.meter.permille, Ordering::Relaxed);
+        poll
+    }
+}
+
+impl<F> Drop for Budgeted<F> {
+    fn drop(&mut self) {
+        CPU_PERMILLE[self.id as usize].store(0, Ordering::Relaxed);
+    }
+}
+
+/// Polling time a task has used in the current window
+struct CpuMeter {
+    max_percent: Option<u8>,
+    window_start: Instant,
+    busy: Duration,
+    /// Usage over the last full window, in tenths of a percent
+    permille: u32,
+}
+
+impl CpuMeter {
+    fn new(max_percent: Option<u8>, now: Instant) -> Self {
+        Self {
+            max_percent,
+            window_start: now,
+            busy: Duration::ZERO,
+            permille: 0,
+        }
+    }
+
+    /// Charge `busy` polling time ending at `now`. Returns how long to hold off
+    /// the next poll to get back under the cap.
+    fn charge(&mut self, now: Instant, busy: Duration) -> Option<Duration> {
+        let elapsed = now - self.window_start;
+        if elapsed >= CPU_WINDOW {
+            let permille = self.busy.as_micros() * 1000 / elapsed.as_micros().max(1);
+            self.permille = permille.min(1000) as u32;
+            self.window_start = now;
+            self.busy = Duration::ZERO;
+        }
+        self.busy += busy;
+
+        let max = u32::from(self.max_percent?.max(1));
+        if self.busy * 100 <= CPU_WINDOW * max {
+            return None;
+        }
+        // Until the busy time is `max` percent of the time since the window began
+        (self.busy * 100 / max).checked_sub(now - self.window_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_meter_holds_back_a_task_over_its_cap() {
+        let start = Instant::now();
+        let mut meter = CpuMeter::new(Some(25), start);
+        let ms = Duration::from_millis;
+
+        // 200ms of a 250ms allowance
+        assert_eq!(meter.charge(start + ms(200), ms(200)), None);
+        // 300ms busy needs 1.2s of wall time at 25%
+        assert_eq!(meter.charge(start + ms(300), ms(100)), Some(ms(900)));
+
+        // The next window reports the last one's usage and starts from zero
+        assert_eq!(meter.charge(start + ms(1200), ms(10)), None);
+        assert_eq!(meter.permille, 250);
+    }
+
+    #[test]
+    fn test_cpu_meter_without_a_cap_only_measures() {
+        let start = Instant::now();
+        let mut meter = CpuMeter::new(None, start);
+
+        assert_eq!(meter.charge(start + CPU_WINDOW / 2, CPU_WINDOW / 2), None);
+        assert_eq!(meter.charge(start + CPU_WINDOW, Duration::ZERO), None);
+        assert_eq!(meter.permille, 500);
+    }
+}
@@ -1,33 +1,36 @@
 //! Compute service - AI inference provider
 
-use super::{Service, ServiceId, ServiceMetadata, ServiceState};
+use super::{Service, ServiceId, ServiceMetadata, ServiceState, ServiceTask, ShutdownReceiver};
 use crate::config::ServiceConfig;
 use crate::hardware::ServiceRequirements;
+use crate::rpc_client::RpcClient;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
 pub struct ComputeService {
-    rpc_url: String,
+    rpc: RpcClient,
     running: Arc<AtomicBool>,
     start_time: Arc<RwLock<Option<Instant>>>,
     requests_served: Arc<AtomicU64>,
     earnings_wei: Arc<RwLock<String>>,
     last_error: Arc<RwLock<Option<String>>>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<ServiceTask>,
 }
 
 impl ComputeService {
-    pub fn new(rpc_url: &str) -> Self {
+    pub fn new(rpc: RpcClient) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
+            rpc,
             running: Arc::new(AtomicBool::new(false)),
             start_time: Arc::new(RwLock::new(None)),
             requests_served: Arc::new(AtomicU64::new(0)),
             earnings_wei: Arc::new(RwLock::new("0".to_string())),
             last_error: Arc::new(RwLock::new(None)),
             shutdown_tx: None,
+            task: None,
         }
     }
 }
@@ -63,7 +66,11 @@ impl Service for ComputeService {
         }
     }
 
-    async fn start(&mut self, config: &ServiceConfig) -> Result<(), String> {
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        mut shutdown: ShutdownReceiver,
+    ) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
             return Err("Service already running".to_string());
         }
@@ -83,16 +90,20 @@ impl Service for ComputeService {
         let _requests_served = self.requests_served.clone();
         let _earnings_wei = self.earnings_wei.clone();
         let _last_error = self.last_error.clone();
-        let _rpc_url = self.rpc_url.clone();
+        let _rpc = self.rpc.clone();
         let stake_amount = config.stake_amount.clone();
 
         // Spawn service task
-        tokio::spawn(async move {
+        self.task = Some(ServiceTask::spawn(self.id(), config, async move {
             tracing::info!("Compute service started with stake: {:?}", stake_amount);
 
             // Main service loop
             loop {
                 tokio::select! {
+                    _ = shutdown.recv() => {
+                        tracing::info!("Compute service received app shutdown");
+                        break;
+                    }
                     _ = &mut rx => {
                         tracing::info!("Compute service received shutdown signal");
                         break;
@@ -113,7 +124,7 @@ impl Service for ComputeService {
 
             running.store(false, Ordering::SeqCst);
             tracing::info!("Compute service stopped");
-        });
+        }));
 
         Ok(())
     }
@@ -130,8 +141,10 @@ impl Service for ComputeService {
             let _ = tx.send(());
         }
 
-        // Wait for shutdown
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the service loop to exit
+        if let Some(mut task) = self.task.take() {
+            task.join().await;
+        }
 
         self.running.store(false, Ordering::SeqCst);
         *self.start_time.write().await = None;
@@ -158,6 +171,8 @@ impl Service for ComputeService {
             } else {
                 "stopped".to_string()
             },
+            rpc_endpoint: self.rpc.active_endpoint(),
+            usage: None,
         }
     }
 
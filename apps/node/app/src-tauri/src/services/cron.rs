@@ -1,15 +1,16 @@
 //! Cron service - trigger executor
 
-use super::{Service, ServiceId, ServiceMetadata, ServiceState};
+use super::{Service, ServiceId, ServiceMetadata, ServiceState, ServiceTask, ShutdownReceiver};
 use crate::config::ServiceConfig;
 use crate::hardware::ServiceRequirements;
+use crate::rpc_client::RpcClient;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
 pub struct CronService {
-    rpc_url: String,
+    rpc: RpcClient,
     running: Arc<AtomicBool>,
     start_time: Arc<RwLock<Option<Instant>>>,
     triggers_executed: Arc<AtomicU64>,
@@ -17,12 +18,13 @@ pub struct CronService {
     earnings_wei: Arc<RwLock<String>>,
     last_error: Arc<RwLock<Option<String>>>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<ServiceTask>,
 }
 
 impl CronService {
-    pub fn new(rpc_url: &str) -> Self {
+    pub fn new(rpc: RpcClient) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
+            rpc,
             running: Arc::new(AtomicBool::new(false)),
             start_time: Arc::new(RwLock::new(None)),
             triggers_executed: Arc::new(AtomicU64::new(0)),
@@ -30,6 +32,7 @@ impl CronService {
             earnings_wei: Arc::new(RwLock::new("0".to_string())),
             last_error: Arc::new(RwLock::new(None)),
             shutdown_tx: None,
+            task: None,
         }
     }
 }
@@ -65,7 +68,11 @@ impl Service for CronService {
         }
     }
 
-    async fn start(&mut self, _config: &ServiceConfig) -> Result<(), String> {
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        mut shutdown: ShutdownReceiver,
+    ) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
             return Err("Service already running".to_string());
         }
@@ -82,9 +89,9 @@ impl Service for CronService {
         let _triggers_executed = self.triggers_executed.clone();
         let _successful_executions = self.successful_executions.clone();
         let _earnings_wei = self.earnings_wei.clone();
-        let _rpc_url = self.rpc_url.clone();
+        let _rpc = self.rpc.clone();
 
-        tokio::spawn(async move {
+        self.task = Some(ServiceTask::spawn(self.id(), config, async move {
             tracing::info!("Cron service started");
 
             // Check interval for pending triggers
@@ -92,6 +99,10 @@ impl Service for CronService {
 
             loop {
                 tokio::select! {
+                    _ = shutdown.recv() => {
+                        tracing::info!("Cron service received app shutdown");
+                        break;
+                    }
                     _ = &mut rx => {
                         tracing::info!("Cron service received shutdown signal");
                         break;
@@ -112,7 +123,7 @@ impl Service for CronService {
 
             running.store(false, Ordering::SeqCst);
             tracing::info!("Cron service stopped");
-        });
+        }));
 
         Ok(())
     }
@@ -128,7 +139,10 @@ impl Service for CronService {
             let _ = tx.send(());
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the service loop to exit
+        if let Some(mut task) = self.task.take() {
+            task.join().await;
+        }
 
         self.running.store(false, Ordering::SeqCst);
         *self.start_time.write().await = None;
@@ -155,6 +169,8 @@ impl Service for CronService {
             } else {
                 "stopped".to_string()
             },
+            rpc_endpoint: self.rpc.active_endpoint(),
+            usage: None,
         }
     }
 
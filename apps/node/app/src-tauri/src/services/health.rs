@@ -0,0 +1,103 @@
+//! Service health supervision
+//!
+//! Polls `health_check` on every service the manager has started. A failing
+//! service is reported as unhealthy and, once it has failed enough checks in a
+//! row, restarted with exponential backoff between restarts.
+
+use super::ServiceId;
+use crate::config::{HealthCheckConfig, ServiceConfig};
+use crate::state::AppStateInner;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Health bookkeeping for a service the manager is expected to keep running
+pub(super) struct HealthRecord {
+    /// Config the service was started with, reused on restart
+    pub config: ServiceConfig,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub restarts: u32,
+    pub next_restart: Option<Instant>,
+}
+
+impl HealthRecord {
+    pub fn new(config: ServiceConfig) -> Self {
+        Self {
+            config,
+            healthy: true,
+            consecutive_failures: 0,
+            restarts: 0,
+            next_restart: None,
+        }
+    }
+
+    /// Whether the restart policy allows restarting now
+    pub fn should_restart(&self, policy: &HealthCheckConfig, now: Instant) -> bool {
+        policy.auto_restart
+            && self.consecutive_failures >= policy.max_failures.max(1)
+            && self.next_restart.map_or(true, |at| now >= at)
+    }
+
+    /// Record a restart and schedule the earliest time the next one may happen
+    pub fn restarted(&mut self, policy: &HealthCheckConfig, now: Instant) {
+        let base = Duration::from_secs(policy.interval_secs.max(1));
+        let max = Duration::from_secs(policy.max_restart_backoff_secs).max(base);
+        let backoff = base
+            .checked_mul(1 << self.restarts.min(16))
+            .unwrap_or(max)
+            .min(max);
+
+        self.restarts += 1;
+        self.consecutive_failures = 0;
+        self.next_restart = Some(now + backoff);
+    }
+}
+
+/// Health transition, forwarded to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthEvent {
+    pub service_id: String,
+    pub health: String,
+    pub consecutive_failures: u32,
+    pub restarted: bool,
+}
+
+impl HealthEvent {
+    pub(super) fn new(id: ServiceId, record: &HealthRecord, restarted: bool) -> Self {
+        Self {
+            service_id: id.as_str().to_string(),
+            health: if record.healthy {
+                "healthy".to_string()
+            } else {
+                "unhealthy".to_string()
+            },
+            consecutive_failures: record.consecutive_failures,
+            restarted,
+        }
+    }
+}
+
+/// Run health checks forever, reporting transitions through `on_event`
+pub async fn supervise<F>(state: Arc<RwLock<AppStateInner>>, on_event: F)
+where
+    F: Fn(HealthEvent) + Send + 'static,
+{
+    loop {
+        // Re-read every round so interval changes take effect without a restart
+        let policy = state.read().await.config.health_check.clone();
+        tokio::time::sleep(Duration::from_secs(policy.interval_secs.max(1))).await;
+
+        let events = state
+            .write()
+            .await
+            .service_manager
+            .check_health(&policy)
+            .await;
+
+        for event in events {
+            on_event(event);
+        }
+    }
+}
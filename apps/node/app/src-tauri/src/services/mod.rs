@@ -1,7 +1,9 @@
 //! Service management - all node services
 
+mod budget;
 mod compute;
 mod cron;
+mod health;
 mod oracle;
 mod proxy;
 mod rpc;
@@ -10,8 +12,10 @@ mod solver;
 mod storage;
 mod xlp;
 
+pub use budget::ResourceUsage;
 pub use compute::ComputeService;
 pub use cron::CronService;
+pub use health::{supervise as supervise_health, HealthEvent};
 pub use oracle::OracleService;
 pub use proxy::ProxyService;
 pub use rpc::RpcService;
@@ -22,10 +26,46 @@ pub use xlp::XlpService;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tokio::sync::mpsc;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 
-use crate::config::{NodeConfig, ServiceConfig};
+use crate::config::{HealthCheckConfig, NodeConfig, ServiceConfig};
 use crate::hardware::{HardwareInfo, ServiceRequirements};
+use crate::rpc_client::{EndpointStatus, RpcClient};
+use health::HealthRecord;
+
+/// How long `shutdown_all` waits for services to stop before aborting them
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Receives the app-wide shutdown signal
+pub type ShutdownReceiver = broadcast::Receiver<()>;
+
+/// Handle to a service's background loop. Dropping it aborts the loop, so a
+/// service that ignores its shutdown signal can still be torn down.
+pub struct ServiceTask(JoinHandle<()>);
+
+impl ServiceTask {
+    /// Spawn `id`'s loop, held to the CPU limit in `config`
+    pub fn spawn<F>(id: ServiceId, config: &ServiceConfig, future: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        Self(tokio::spawn(budget::budgeted(id, config, future)))
+    }
+
+    /// Wait for the loop to exit
+    pub async fn join(&mut self) {
+        let _ = (&mut self.0).await;
+    }
+}
+
+impl Drop for ServiceTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
 
 /// Service identifier
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -113,6 +153,11 @@ pub struct ServiceState {
     pub earnings_wei: String,
     pub last_error: Option<String>,
     pub health: String,
+    /// RPC endpoint the service is currently using
+    pub rpc_endpoint: Option<String>,
+    /// CPU used by the service's task while it runs
+    #[serde(default)]
+    pub usage: Option<ResourceUsage>,
 }
 
 /// Service trait
@@ -122,7 +167,12 @@ pub trait Service: Send + Sync {
     fn metadata(&self) -> ServiceMetadata;
     fn requirements(&self) -> ServiceRequirements;
 
-    async fn start(&mut self, config: &ServiceConfig) -> Result<(), String>;
+    /// Start the service. Its loop must exit once `shutdown` fires.
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        shutdown: ShutdownReceiver,
+    ) -> Result<(), String>;
     async fn stop(&mut self) -> Result<(), String>;
     async fn status(&self) -> ServiceState;
     async fn health_check(&self) -> bool;
@@ -131,7 +181,11 @@ pub trait Service: Send + Sync {
 /// Service manager coordinates all services
 pub struct ServiceManager {
     services: HashMap<ServiceId, Box<dyn Service>>,
-    shutdown_tx: Option<mpsc::Sender<()>>,
+    shutdown_tx: Option<broadcast::Sender<()>>,
+    /// Services started through the manager, watched by the health supervisor
+    health: HashMap<ServiceId, HealthRecord>,
+    /// Shared by every service so endpoint health is tracked once
+    rpc: Option<RpcClient>,
 }
 
 impl ServiceManager {
@@ -139,46 +193,42 @@ impl ServiceManager {
         Self {
             services: HashMap::new(),
             shutdown_tx: None,
+            health: HashMap::new(),
+            rpc: None,
         }
     }
 
     pub fn initialize(&mut self, config: &NodeConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let (shutdown_tx, _) = broadcast::channel(1);
+        self.shutdown_tx = Some(shutdown_tx);
+
+        let rpc = RpcClient::new(config.network.rpc_endpoints());
+        self.rpc = Some(rpc.clone());
+
         // Initialize all services
         self.services.insert(
             ServiceId::Compute,
-            Box::new(ComputeService::new(&config.network.rpc_url)),
+            Box::new(ComputeService::new(rpc.clone())),
         );
         self.services.insert(
             ServiceId::Storage,
-            Box::new(StorageService::new(&config.network.rpc_url)),
-        );
-        self.services.insert(
-            ServiceId::Oracle,
-            Box::new(OracleService::new(&config.network.rpc_url)),
-        );
-        self.services.insert(
-            ServiceId::Proxy,
-            Box::new(ProxyService::new(&config.network.rpc_url)),
-        );
-        self.services.insert(
-            ServiceId::Cron,
-            Box::new(CronService::new(&config.network.rpc_url)),
-        );
-        self.services.insert(
-            ServiceId::Rpc,
-            Box::new(RpcService::new(&config.network.rpc_url)),
-        );
-        self.services.insert(
-            ServiceId::Xlp,
-            Box::new(XlpService::new(&config.network.rpc_url)),
-        );
-        self.services.insert(
-            ServiceId::Solver,
-            Box::new(SolverService::new(&config.network.rpc_url)),
+            Box::new(StorageService::new(rpc.clone())),
         );
+        self.services
+            .insert(ServiceId::Oracle, Box::new(OracleService::new(rpc.clone())));
+        self.services
+            .insert(ServiceId::Proxy, Box::new(ProxyService::new(rpc.clone())));
+        self.services
+            .insert(ServiceId::Cron, Box::new(CronService::new(rpc.clone())));
+        self.services
+            .insert(ServiceId::Rpc, Box::new(RpcService::new(rpc.clone())));
+        self.services
+            .insert(ServiceId::Xlp, Box::new(XlpService::new(rpc.clone())));
+        self.services
+            .insert(ServiceId::Solver, Box::new(SolverService::new(rpc.clone())));
         self.services.insert(
             ServiceId::Sequencer,
-            Box::new(SequencerService::new(&config.network.rpc_url)),
+            Box::new(SequencerService::new(rpc.clone())),
         );
 
         Ok(())
@@ -204,37 +254,145 @@ impl ServiceManager {
             .collect()
     }
 
+    /// Health of the configured RPC endpoints, empty before `initialize`
+    pub fn rpc_endpoint_status(&self) -> Vec<EndpointStatus> {
+        self.rpc
+            .as_ref()
+            .map(RpcClient::endpoint_status)
+            .unwrap_or_default()
+    }
+
+    pub fn get_requirements(&self, id: ServiceId) -> Result<ServiceRequirements, String> {
+        let service = self.services.get(&id).ok_or("Service not found")?;
+        Ok(service.requirements())
+    }
+
     pub async fn start_service(
         &mut self,
         id: ServiceId,
         config: &ServiceConfig,
     ) -> Result<(), String> {
+        config.validate_limits()?;
+        let shutdown = self
+            .shutdown_tx
+            .as_ref()
+            .ok_or("Services not initialized")?
+            .subscribe();
         let service = self.services.get_mut(&id).ok_or("Service not found")?;
-        service.start(config).await
+        service.start(config, shutdown).await?;
+
+        self.health.insert(id, HealthRecord::new(config.clone()));
+        Ok(())
     }
 
     pub async fn stop_service(&mut self, id: ServiceId) -> Result<(), String> {
         let service = self.services.get_mut(&id).ok_or("Service not found")?;
+        self.health.remove(&id);
         service.stop().await
     }
 
     pub async fn get_service_status(&self, id: ServiceId) -> Result<ServiceState, String> {
         let service = self.services.get(&id).ok_or("Service not found")?;
-        Ok(service.status().await)
+        Ok(self.with_supervision(id, service.status().await))
     }
 
     pub async fn get_all_status(&self) -> HashMap<String, ServiceState> {
         let mut statuses = HashMap::new();
         for (id, service) in &self.services {
-            statuses.insert(id.as_str().to_string(), service.status().await);
+            let status = self.with_supervision(*id, service.status().await);
+            statuses.insert(id.as_str().to_string(), status);
         }
         statuses
     }
 
+    /// Overlay what the manager knows: the supervisor's verdict, which outlives
+    /// a wedged service's own report, and the task's resource usage
+    fn with_supervision(&self, id: ServiceId, mut status: ServiceState) -> ServiceState {
+        if self.health.get(&id).is_some_and(|record| !record.healthy) {
+            status.health = "unhealthy".to_string();
+        }
+        status.usage = status.running.then(|| budget::usage(id));
+        status
+    }
+
+    /// Run one round of health checks over the supervised services, restarting
+    /// those that have failed too often. Returns the health transitions.
+    pub async fn check_health(&mut self, policy: &HealthCheckConfig) -> Vec<HealthEvent> {
+        let mut events = Vec::new();
+
+        for (id, record) in &mut self.health {
+            let Some(service) = self.services.get_mut(id) else {
+                continue;
+            };
+
+            let healthy = service.health_check().await;
+            let was_healthy = record.healthy;
+            record.healthy = healthy;
+
+            if healthy {
+                record.consecutive_failures = 0;
+                record.restarts = 0;
+                record.next_restart = None;
+                if !was_healthy {
+                    tracing::info!("Service {} is healthy again", id.as_str());
+                    events.push(HealthEvent::new(*id, record, false));
+                }
+                continue;
+            }
+
+            record.consecutive_failures += 1;
+            if was_healthy {
+                tracing::warn!("Service {} failed its health check", id.as_str());
+                events.push(HealthEvent::new(*id, record, false));
+            }
+
+            let now = std::time::Instant::now();
+            if !record.should_restart(policy, now) {
+                continue;
+            }
+
+            tracing::warn!(
+                "Restarting service {} after {} failed health checks",
+                id.as_str(),
+                record.consecutive_failures
+            );
+            record.restarted(policy, now);
+
+            if let Err(e) = service.stop().await {
+                tracing::error!("Failed to stop service {}: {}", id.as_str(), e);
+            }
+            let Some(tx) = &self.shutdown_tx else {
+                continue;
+            };
+            match service.start(&record.config, tx.subscribe()).await {
+                Ok(()) => events.push(HealthEvent::new(*id, record, true)),
+                Err(e) => tracing::error!("Failed to restart service {}: {}", id.as_str(), e),
+            }
+        }
+
+        events
+    }
+
+    /// Signal every service to stop, then wait up to `SHUTDOWN_TIMEOUT` for all
+    /// of them. Services still running at the deadline have their loops aborted.
     pub async fn shutdown_all(&mut self) {
+        // All loops start winding down at once, not one after another
+        if let Some(tx) = &self.shutdown_tx {
+            let _ = tx.send(());
+        }
+        self.health.clear();
+
+        let deadline = tokio::time::Instant::now() + SHUTDOWN_TIMEOUT;
         for (id, service) in &mut self.services {
-            if let Err(e) = service.stop().await {
-                tracing::error!("Failed to stop service {:?}: {}", id, e);
+            // Timing out drops `stop`, and with it the service's task handle
+            match tokio::time::timeout_at(deadline, service.stop()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::error!("Failed to stop service {:?}: {}", id, e),
+                Err(_) => tracing::warn!(
+                    "Service {:?} did not stop within {:?}, aborting it",
+                    id,
+                    SHUTDOWN_TIMEOUT
+                ),
             }
         }
     }
@@ -245,3 +403,41 @@ impl Default for ServiceManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cpu_limit_is_validated_and_usage_reported() {
+        let mut manager = ServiceManager::new();
+        manager.initialize(&NodeConfig::default()).unwrap();
+
+        let mut config = ServiceConfig {
+            max_cpu_percent: Some(0),
+            ..ServiceConfig::default()
+        };
+        assert!(manager
+            .start_service(ServiceId::Compute, &config)
+            .await
+            .is_err());
+
+        config.max_cpu_percent = Some(50);
+        manager
+            .start_service(ServiceId::Compute, &config)
+            .await
+            .unwrap();
+        let status = manager
+            .get_service_status(ServiceId::Compute)
+            .await
+            .unwrap();
+        assert!(status.usage.is_some());
+
+        manager.shutdown_all().await;
+        let status = manager
+            .get_service_status(ServiceId::Compute)
+            .await
+            .unwrap();
+        assert_eq!(status.usage, None);
+    }
+}
@@ -1,15 +1,16 @@
 //! Oracle service - price feed provider
 
-use super::{Service, ServiceId, ServiceMetadata, ServiceState};
+use super::{Service, ServiceId, ServiceMetadata, ServiceState, ServiceTask, ShutdownReceiver};
 use crate::config::ServiceConfig;
 use crate::hardware::ServiceRequirements;
+use crate::rpc_client::RpcClient;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
 pub struct OracleService {
-    rpc_url: String,
+    rpc: RpcClient,
     running: Arc<AtomicBool>,
     start_time: Arc<RwLock<Option<Instant>>>,
     reports_submitted: Arc<AtomicU64>,
@@ -17,12 +18,13 @@ pub struct OracleService {
     earnings_wei: Arc<RwLock<String>>,
     last_error: Arc<RwLock<Option<String>>>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<ServiceTask>,
 }
 
 impl OracleService {
-    pub fn new(rpc_url: &str) -> Self {
+    pub fn new(rpc: RpcClient) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
+            rpc,
             running: Arc::new(AtomicBool::new(false)),
             start_time: Arc::new(RwLock::new(None)),
             reports_submitted: Arc::new(AtomicU64::new(0)),
@@ -30,6 +32,7 @@ impl OracleService {
             earnings_wei: Arc::new(RwLock::new("0".to_string())),
             last_error: Arc::new(RwLock::new(None)),
             shutdown_tx: None,
+            task: None,
         }
     }
 }
@@ -68,7 +71,11 @@ impl Service for OracleService {
         }
     }
 
-    async fn start(&mut self, _config: &ServiceConfig) -> Result<(), String> {
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        mut shutdown: ShutdownReceiver,
+    ) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
             return Err("Service already running".to_string());
         }
@@ -85,9 +92,9 @@ impl Service for OracleService {
         let reports_submitted = self.reports_submitted.clone();
         let _reports_accepted = self.reports_accepted.clone();
         let _earnings_wei = self.earnings_wei.clone();
-        let _rpc_url = self.rpc_url.clone();
+        let _rpc = self.rpc.clone();
 
-        tokio::spawn(async move {
+        self.task = Some(ServiceTask::spawn(self.id(), config, async move {
             tracing::info!("Oracle service started");
 
             // Poll interval (typically 60 seconds for price feeds)
@@ -95,6 +102,10 @@ impl Service for OracleService {
 
             loop {
                 tokio::select! {
+                    _ = shutdown.recv() => {
+                        tracing::info!("Oracle service received app shutdown");
+                        break;
+                    }
                     _ = &mut rx => {
                         tracing::info!("Oracle service received shutdown signal");
                         break;
@@ -118,7 +129,7 @@ impl Service for OracleService {
 
             running.store(false, Ordering::SeqCst);
             tracing::info!("Oracle service stopped");
-        });
+        }));
 
         Ok(())
     }
@@ -134,7 +145,10 @@ impl Service for OracleService {
             let _ = tx.send(());
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the service loop to exit
+        if let Some(mut task) = self.task.take() {
+            task.join().await;
+        }
 
         self.running.store(false, Ordering::SeqCst);
         *self.start_time.write().await = None;
@@ -161,6 +175,8 @@ impl Service for OracleService {
             } else {
                 "stopped".to_string()
             },
+            rpc_endpoint: self.rpc.active_endpoint(),
+            usage: None,
         }
     }
 
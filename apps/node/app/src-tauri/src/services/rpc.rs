@@ -1,15 +1,16 @@
 //! RPC service - decentralized RPC provider
 
-use super::{Service, ServiceId, ServiceMetadata, ServiceState};
+use super::{Service, ServiceId, ServiceMetadata, ServiceState, ServiceTask, ShutdownReceiver};
 use crate::config::ServiceConfig;
 use crate::hardware::ServiceRequirements;
+use crate::rpc_client::RpcClient;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
 pub struct RpcService {
-    rpc_url: String,
+    rpc: RpcClient,
     running: Arc<AtomicBool>,
     start_time: Arc<RwLock<Option<Instant>>>,
     requests_served: Arc<AtomicU64>,
@@ -17,12 +18,13 @@ pub struct RpcService {
     earnings_wei: Arc<RwLock<String>>,
     last_error: Arc<RwLock<Option<String>>>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<ServiceTask>,
 }
 
 impl RpcService {
-    pub fn new(rpc_url: &str) -> Self {
+    pub fn new(rpc: RpcClient) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
+            rpc,
             running: Arc::new(AtomicBool::new(false)),
             start_time: Arc::new(RwLock::new(None)),
             requests_served: Arc::new(AtomicU64::new(0)),
@@ -30,6 +32,7 @@ impl RpcService {
             earnings_wei: Arc::new(RwLock::new("0".to_string())),
             last_error: Arc::new(RwLock::new(None)),
             shutdown_tx: None,
+            task: None,
         }
     }
 }
@@ -68,7 +71,11 @@ impl Service for RpcService {
         }
     }
 
-    async fn start(&mut self, _config: &ServiceConfig) -> Result<(), String> {
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        mut shutdown: ShutdownReceiver,
+    ) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
             return Err("Service already running".to_string());
         }
@@ -86,11 +93,15 @@ impl Service for RpcService {
         let _blocks_synced = self.blocks_synced.clone();
         let _earnings_wei = self.earnings_wei.clone();
 
-        tokio::spawn(async move {
+        self.task = Some(ServiceTask::spawn(self.id(), config, async move {
             tracing::info!("RPC service started");
 
             loop {
                 tokio::select! {
+                    _ = shutdown.recv() => {
+                        tracing::info!("RPC service received app shutdown");
+                        break;
+                    }
                     _ = &mut rx => {
                         tracing::info!("RPC service received shutdown signal");
                         break;
@@ -111,7 +122,7 @@ impl Service for RpcService {
 
             running.store(false, Ordering::SeqCst);
             tracing::info!("RPC service stopped");
-        });
+        }));
 
         Ok(())
     }
@@ -127,7 +138,10 @@ impl Service for RpcService {
             let _ = tx.send(());
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the service loop to exit
+        if let Some(mut task) = self.task.take() {
+            task.join().await;
+        }
 
         self.running.store(false, Ordering::SeqCst);
         *self.start_time.write().await = None;
@@ -154,6 +168,8 @@ impl Service for RpcService {
             } else {
                 "stopped".to_string()
             },
+            rpc_endpoint: self.rpc.active_endpoint(),
+            usage: None,
         }
     }
 
@@ -1,15 +1,16 @@
 //! Sequencer service - block producer
 
-use super::{Service, ServiceId, ServiceMetadata, ServiceState};
+use super::{Service, ServiceId, ServiceMetadata, ServiceState, ServiceTask, ShutdownReceiver};
 use crate::config::ServiceConfig;
 use crate::hardware::ServiceRequirements;
+use crate::rpc_client::RpcClient;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
 pub struct SequencerService {
-    rpc_url: String,
+    rpc: RpcClient,
     running: Arc<AtomicBool>,
     start_time: Arc<RwLock<Option<Instant>>>,
     blocks_proposed: Arc<AtomicU64>,
@@ -17,12 +18,13 @@ pub struct SequencerService {
     earnings_wei: Arc<RwLock<String>>,
     last_error: Arc<RwLock<Option<String>>>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<ServiceTask>,
 }
 
 impl SequencerService {
-    pub fn new(rpc_url: &str) -> Self {
+    pub fn new(rpc: RpcClient) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
+            rpc,
             running: Arc::new(AtomicBool::new(false)),
             start_time: Arc::new(RwLock::new(None)),
             blocks_proposed: Arc::new(AtomicU64::new(0)),
@@ -30,6 +32,7 @@ impl SequencerService {
             earnings_wei: Arc::new(RwLock::new("0".to_string())),
             last_error: Arc::new(RwLock::new(None)),
             shutdown_tx: None,
+            task: None,
         }
     }
 }
@@ -73,7 +76,11 @@ impl Service for SequencerService {
         }
     }
 
-    async fn start(&mut self, config: &ServiceConfig) -> Result<(), String> {
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        mut shutdown: ShutdownReceiver,
+    ) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
             return Err("Service already running".to_string());
         }
@@ -108,9 +115,9 @@ impl Service for SequencerService {
         let _blocks_missed = self.blocks_missed.clone();
         let _earnings = self.earnings_wei.clone();
         let _last_error = self.last_error.clone();
-        let _rpc_url = self.rpc_url.clone();
+        let _rpc = self.rpc.clone();
 
-        tokio::spawn(async move {
+        self.task = Some(ServiceTask::spawn(self.id(), config, async move {
             tracing::info!("Sequencer service started");
 
             // Heartbeat interval
@@ -118,6 +125,10 @@ impl Service for SequencerService {
 
             loop {
                 tokio::select! {
+                    _ = shutdown.recv() => {
+                        tracing::info!("Sequencer service received app shutdown");
+                        break;
+                    }
                     _ = &mut rx => {
                         tracing::info!("Sequencer service received shutdown signal");
                         break;
@@ -141,7 +152,7 @@ impl Service for SequencerService {
 
             running.store(false, Ordering::SeqCst);
             tracing::info!("Sequencer service stopped");
-        });
+        }));
 
         Ok(())
     }
@@ -157,7 +168,10 @@ impl Service for SequencerService {
             let _ = tx.send(());
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the service loop to exit
+        if let Some(mut task) = self.task.take() {
+            task.join().await;
+        }
 
         self.running.store(false, Ordering::SeqCst);
         *self.start_time.write().await = None;
@@ -184,6 +198,8 @@ impl Service for SequencerService {
             } else {
                 "stopped".to_string()
             },
+            rpc_endpoint: self.rpc.active_endpoint(),
+            usage: None,
         }
     }
 
@@ -1,15 +1,16 @@
 //! Storage service - decentralized storage provider
 
-use super::{Service, ServiceId, ServiceMetadata, ServiceState};
+use super::{Service, ServiceId, ServiceMetadata, ServiceState, ServiceTask, ShutdownReceiver};
 use crate::config::ServiceConfig;
 use crate::hardware::ServiceRequirements;
+use crate::rpc_client::RpcClient;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
 
 pub struct StorageService {
-    rpc_url: String,
+    rpc: RpcClient,
     running: Arc<AtomicBool>,
     start_time: Arc<RwLock<Option<Instant>>>,
     bytes_stored: Arc<AtomicU64>,
@@ -17,12 +18,13 @@ pub struct StorageService {
     earnings_wei: Arc<RwLock<String>>,
     last_error: Arc<RwLock<Option<String>>>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    task: Option<ServiceTask>,
 }
 
 impl StorageService {
-    pub fn new(rpc_url: &str) -> Self {
+    pub fn new(rpc: RpcClient) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
+            rpc,
             running: Arc::new(AtomicBool::new(false)),
             start_time: Arc::new(RwLock::new(None)),
             bytes_stored: Arc::new(AtomicU64::new(0)),
@@ -30,6 +32,7 @@ impl StorageService {
             earnings_wei: Arc::new(RwLock::new("0".to_string())),
             last_error: Arc::new(RwLock::new(None)),
             shutdown_tx: None,
+            task: None,
         }
     }
 }
@@ -65,7 +68,11 @@ impl Service for StorageService {
         }
     }
 
-    async fn start(&mut self, _config: &ServiceConfig) -> Result<(), String> {
+    async fn start(
+        &mut self,
+        config: &ServiceConfig,
+        mut shutdown: ShutdownReceiver,
+    ) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
             return Err("Service already running".to_string());
         }
@@ -82,13 +89,17 @@ impl Service for StorageService {
         let _bytes_stored = self.bytes_stored.clone();
         let _files_pinned = self.files_pinned.clone();
         let _earnings_wei = self.earnings_wei.clone();
-        let _rpc_url = self.rpc_url.clone();
+        let _rpc = self.rpc.clone();
 
-        tokio::spawn(async move {
+        self.task = Some(ServiceTask::spawn(self.id(), config, async move {
             tracing::info!("Storage service started");
 
             loop {
                 tokio::select! {
+                    _ = shutdown.recv() => {
+                        tracing::info!("Storage service received app shutdown");
+                        break;
+                    }
                     _ = &mut rx => {
                         tracing::info!("Storage service received shutdown signal");
                         break;
@@ -109,7 +120,7 @@ impl Service for StorageService {
 
             running.store(false, Ordering::SeqCst);
             tracing::info!("Storage service stopped");
-        });
+        }));
 
         Ok(())
     }
@@ -125,7 +136,10 @@ impl Service for StorageService {
             let _ = tx.send(());
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Wait for the service loop to exit
+        if let Some(mut task) = self.task.take() {
+            task.join().await;
+        }
 
         self.running.store(false, Ordering::SeqCst);
         *self.start_time.write().await = None;
@@ -152,6 +166,8 @@ impl Service for StorageService {
             } else {
                 "stopped".to_string()
             },
+            rpc_endpoint: self.rpc.active_endpoint(),
+            usage: None,
         }
     }
 
@@ -66,6 +66,8 @@ pub struct AppStateInner {
     pub service_status: HashMap<String, ServiceStatus>,
     pub bot_status: HashMap<String, BotStatus>,
     pub network_info: NetworkInfo,
+    /// When the auto-claim scheduler will next check pending rewards
+    pub next_auto_claim_timestamp: Option<u64>,
     pub initialized: bool,
 }
 
@@ -92,6 +94,7 @@ impl AppState {
                     block_number: 0,
                     gas_price_gwei: 0.0,
                 },
+                next_auto_claim_timestamp: None,
                 initialized: false,
             })),
         }
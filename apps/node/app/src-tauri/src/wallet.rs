@@ -36,6 +36,7 @@ pub struct TransactionResult {
 }
 
 /// Wallet manager handles both embedded and external wallets
+#[derive(Clone)]
 pub struct WalletManager {
     signer: Option<PrivateKeySigner>,
     chain_id: u64,
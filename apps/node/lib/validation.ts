@@ -377,6 +377,12 @@ export const AppConfigSchema = z.object({
     auto_compound: z.boolean(),
     auto_stake_earnings: z.boolean(),
   }),
+  health_check: z.object({
+    interval_secs: z.number().int().positive(),
+    auto_restart: z.boolean(),
+    max_failures: z.number().int().positive(),
+    max_restart_backoff_secs: NonNegativeIntSchema,
+  }),
   services: z.record(z.string(), ServiceConfigSchema),
   bots: z.record(z.string(), BotConfigSchema),
   start_minimized: z.boolean(),
@@ -465,6 +471,7 @@ export const StartServiceRequestSchema = z.object({
   auto_stake: z.boolean(),
   stake_amount: WeiStringSchema.nullable(),
   custom_settings: z.record(z.string(), CustomSettingValueSchema).nullable(),
+  force: z.boolean().optional(),
 })
 
 export const StartBotRequestSchema = z.object({
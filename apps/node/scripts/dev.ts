@@ -479,6 +479,12 @@ function getMockConfig() {
       auto_compound: false,
       auto_stake_earnings: false,
     },
+    health_check: {
+      interval_secs: 30,
+      auto_restart: true,
+      max_failures: 3,
+      max_restart_backoff_secs: 600,
+    },
     services: {},
     bots: {},
     start_minimized: false,
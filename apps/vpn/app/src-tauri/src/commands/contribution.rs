@@ -41,3 +41,10 @@ pub async fn get_contribution_stats(
     let contribution = state.contribution.read().await;
     Ok(contribution.get_stats())
 }
+
+/// Settle pending contribution tokens, returning the amount to claim on-chain
+#[tauri::command]
+pub async fn claim_contribution_tokens(state: State<'_, AppState>) -> Result<f64, String> {
+    let mut contribution = state.contribution.write().await;
+    Ok(contribution.claim_tokens())
+}
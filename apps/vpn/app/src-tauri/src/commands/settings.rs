@@ -18,6 +18,54 @@ pub async fn update_settings(
     settings: VPNConfig,
 ) -> Result<(), String> {
     let mut config = state.config.write().await;
+    if settings.kill_switch != config.kill_switch {
+        state
+            .vpn
+            .write()
+            .await
+            .set_kill_switch_enabled(settings.kill_switch)
+            .map_err(|e| e.to_string())?;
+    }
+    if settings.dns_leak_protection != config.dns_leak_protection {
+        state
+            .vpn
+            .write()
+            .await
+            .set_dns_leak_protection(settings.dns_leak_protection)
+            .map_err(|e| e.to_string())?;
+    }
+    if settings.split_tunnel != config.split_tunnel {
+        state
+            .vpn
+            .write()
+            .await
+            .set_split_tunnel(settings.split_tunnel.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    if settings.mtu != config.mtu {
+        state
+            .vpn
+            .write()
+            .await
+            .set_mtu(settings.mtu)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    if settings.ipv6 != config.ipv6 {
+        state
+            .vpn
+            .write()
+            .await
+            .set_ipv6_enabled(settings.ipv6)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    state
+        .vpn
+        .write()
+        .await
+        .set_reconnect_policy(settings.reconnect_policy());
     *config = settings;
     Ok(())
 }
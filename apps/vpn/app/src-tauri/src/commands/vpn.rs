@@ -3,13 +3,20 @@
 //! These commands expose VPN functionality to the frontend via Tauri IPC.
 //! The underlying implementation uses Cloudflare's boringtun for WireGuard.
 
+use crate::node_preference::{self, SelectedNodeUnavailableEvent};
 use crate::state::AppState;
-use crate::vpn::{ConnectionStats, ConnectionStatus, VPNConnection, VPNNode};
-use tauri::State;
+use crate::vpn::{
+    dns_leak_test, refresh_public_ip, ConnectionStats, ConnectionStatus, DnsLeakReport,
+    SplitTunnelConfig, VPNConnection, VPNManager, VPNNode,
+};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::RwLock;
 
 /// Connect to VPN using boringtun WireGuard tunnel
 #[tauri::command]
 pub async fn connect(
+    app: AppHandle,
     state: State<'_, AppState>,
     node_id: Option<String>,
 ) -> Result<VPNConnection, String> {
@@ -22,8 +29,77 @@ pub async fn connect(
     } else {
         None
     };
+    let fallback_from = match node {
+        Some(_) => None,
+        None => vpn.selected_node_id().map(String::from),
+    };
+
+    let connection = vpn.connect(node).await.map_err(|e| e.to_string())?;
+    drop(vpn);
+
+    if let Some(selected_node_id) = fallback_from {
+        if selected_node_id != connection.node.node_id {
+            let _ = app.emit_all(
+                "vpn_selected_node_unavailable",
+                SelectedNodeUnavailableEvent {
+                    selected_node_id,
+                    connected_node_id: connection.node.node_id.clone(),
+                },
+            );
+        }
+    }
+
+    let last_node_id = connection.node.node_id.clone();
+    if let Err(e) = node_preference::update(&app, |p| p.last_node_id = Some(last_node_id)) {
+        tracing::warn!("Failed to save last VPN node: {}", e);
+    }
+
+    spawn_public_ip_check(app, state.vpn.clone());
+    Ok(connection)
+}
+
+/// Connect through two nodes: `entry_node_id` sees our IP, `exit_node_id` our destinations
+#[tauri::command]
+pub async fn connect_multihop(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    entry_node_id: String,
+    exit_node_id: String,
+) -> Result<VPNConnection, String> {
+    let mut vpn = state.vpn.write().await;
+
+    let nodes = vpn.get_nodes(None).await.map_err(|e| e.to_string())?;
+    let find = |id: &str| {
+        nodes
+            .iter()
+            .find(|n| n.node_id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown node: {}", id))
+    };
+    let entry = find(&entry_node_id)?;
+    let exit = find(&exit_node_id)?;
+
+    let connection = vpn
+        .connect_multihop(entry, exit)
+        .await
+        .map_err(|e| e.to_string())?;
+    drop(vpn);
+
+    spawn_public_ip_check(app, state.vpn.clone());
+    Ok(connection)
+}
 
-    vpn.connect(node).await.map_err(|e| e.to_string())
+/// Detect the exit IP in the background and emit `vpn_public_ip` once known
+pub fn spawn_public_ip_check(app: AppHandle, vpn: Arc<RwLock<VPNManager>>) {
+    tauri::async_runtime::spawn(async move {
+        match refresh_public_ip(&vpn).await {
+            Ok(Some(event)) => {
+                let _ = app.emit_all("vpn_public_ip", event);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to detect VPN exit IP: {}", e),
+        }
+    });
 }
 
 /// Get our WireGuard public key (for peer configuration)
@@ -61,11 +137,80 @@ pub async fn get_nodes(
     vpn.get_nodes(country_code).await.map_err(|e| e.to_string())
 }
 
-/// Select a specific node
+/// Measure every known node's latency again and return the nodes
+#[tauri::command]
+pub async fn refresh_latencies(state: State<'_, AppState>) -> Result<Vec<VPNNode>, String> {
+    let mut vpn = state.vpn.write().await;
+    vpn.refresh_latencies().await.map_err(|e| e.to_string())
+}
+
+/// Select a specific node, remembered across restarts
+#[tauri::command]
+pub async fn select_node(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    node_id: String,
+) -> Result<(), String> {
+    let mut vpn = state.vpn.write().await;
+    vpn.select_node(node_id.clone());
+    node_preference::update(&app, |p| p.selected_node_id = Some(node_id))
+}
+
+/// Forget the selected node so `connect` picks the best one again
+#[tauri::command]
+pub async fn clear_selected_node(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let mut vpn = state.vpn.write().await;
+    vpn.clear_selected_node();
+    node_preference::update(&app, |p| p.selected_node_id = None)
+}
+
+/// Enable or disable the kill switch
+#[tauri::command]
+pub async fn set_kill_switch_enabled(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut vpn = state.vpn.write().await;
+    vpn.set_kill_switch_enabled(enabled)
+        .map_err(|e| e.to_string())?;
+
+    state.config.write().await.kill_switch = enabled;
+    Ok(())
+}
+
+/// Enable or disable forcing system DNS through the tunnel
 #[tauri::command]
-pub async fn select_node(state: State<'_, AppState>, node_id: String) -> Result<(), String> {
+pub async fn set_dns_leak_protection(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut vpn = state.vpn.write().await;
+    vpn.set_dns_leak_protection(enabled)
+        .map_err(|e| e.to_string())?;
+
+    state.config.write().await.dns_leak_protection = enabled;
+    Ok(())
+}
+
+/// Resolve a known domain and check the system used the tunnel resolvers
+#[tauri::command]
+pub async fn run_dns_leak_test(state: State<'_, AppState>) -> Result<DnsLeakReport, String> {
+    let dns_servers = state.vpn.read().await.dns_servers().to_vec();
+    dns_leak_test(&dns_servers).await.map_err(|e| e.to_string())
+}
+
+/// Replace the split tunneling rules, reconnecting if a tunnel is up
+#[tauri::command]
+pub async fn set_split_tunnel(
+    state: State<'_, AppState>,
+    split_tunnel: SplitTunnelConfig,
+) -> Result<(), String> {
     let mut vpn = state.vpn.write().await;
-    vpn.select_node(node_id);
+    vpn.set_split_tunnel(split_tunnel.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    state.config.write().await.split_tunnel = split_tunnel;
     Ok(())
 }
 
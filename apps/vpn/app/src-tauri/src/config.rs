@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::vpn::{ReconnectPolicy, SplitTunnelConfig};
+
 /// VPN application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VPNConfig {
@@ -23,6 +25,34 @@ pub struct VPNConfig {
     /// Kill switch enabled
     pub kill_switch: bool,
 
+    /// Force system DNS through the tunnel while connected
+    #[serde(default = "default_true")]
+    pub dns_leak_protection: bool,
+
+    /// Reconnect automatically when the tunnel fails
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+
+    /// Reconnect attempts before giving up
+    #[serde(default = "default_max_reconnect_attempts")]
+    pub max_reconnect_attempts: u32,
+
+    /// Fail over to the next-best node when reconnecting
+    #[serde(default = "default_true")]
+    pub reconnect_failover: bool,
+
+    /// Split tunneling rules
+    #[serde(default)]
+    pub split_tunnel: SplitTunnelConfig,
+
+    /// Tunnel MTU override, for networks like PPPoE that need a smaller one
+    #[serde(default)]
+    pub mtu: Option<u16>,
+
+    /// Tunnel IPv6 alongside IPv4 when the node supports it; off forces IPv4-only
+    #[serde(default = "default_true")]
+    pub ipv6: bool,
+
     /// Auto-connect on startup
     pub auto_connect: bool,
 
@@ -82,6 +112,13 @@ impl Default for VPNConfig {
             coordinator_url: "wss://vpn-coordinator.jejunetwork.org".to_string(),
             dns_servers: vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()],
             kill_switch: true,
+            dns_leak_protection: true,
+            auto_reconnect: true,
+            max_reconnect_attempts: default_max_reconnect_attempts(),
+            reconnect_failover: true,
+            split_tunnel: SplitTunnelConfig::default(),
+            mtu: None,
+            ipv6: true,
             auto_connect: false,
             auto_start: false,
             minimize_to_tray: true,
@@ -92,6 +129,25 @@ impl Default for VPNConfig {
     }
 }
 
+impl VPNConfig {
+    /// Auto-reconnect settings for the connection supervisor
+    pub fn reconnect_policy(&self) -> ReconnectPolicy {
+        ReconnectPolicy {
+            enabled: self.auto_reconnect,
+            max_attempts: self.max_reconnect_attempts,
+            failover: self.reconnect_failover,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_reconnect_attempts() -> u32 {
+    5
+}
+
 impl Default for ContributionConfig {
     fn default() -> Self {
         Self {
@@ -6,6 +6,7 @@
 //! - Contribution capped at 3x their VPN usage
 //! - Contribution includes: CDN serving + VPN relay (where legal)
 
+use chrono::Timelike;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -16,6 +17,11 @@ pub const CONTRIBUTION_MULTIPLIER: u64 = 3;
 /// Default bandwidth percent to share when idle
 pub const DEFAULT_BANDWIDTH_PERCENT: u8 = 10;
 
+/// Default tokens credited per GB served in earning mode
+pub const DEFAULT_TOKENS_PER_GB: f64 = 1.0;
+
+const BYTES_PER_GB: f64 = 1_000_000_000.0;
+
 /// Contribution status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContributionStatus {
@@ -71,6 +77,10 @@ pub struct ContributionSettings {
     /// Earning mode bandwidth percent (default 50%)
     pub earning_bandwidth_percent: u8,
 
+    /// Tokens credited per GB of CDN or relay traffic served in earning mode
+    #[serde(default = "default_tokens_per_gb")]
+    pub tokens_per_gb: f64,
+
     /// Schedule enabled
     pub schedule_enabled: bool,
 
@@ -79,6 +89,18 @@ pub struct ContributionSettings {
 
     /// Schedule end time (e.g., "06:00")
     pub schedule_end: String,
+
+    /// Pause contribution while on a metered connection (default true)
+    #[serde(default = "default_true")]
+    pub auto_pause_on_metered: bool,
+}
+
+fn default_tokens_per_gb() -> f64 {
+    DEFAULT_TOKENS_PER_GB
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for ContributionSettings {
@@ -90,9 +112,11 @@ impl Default for ContributionSettings {
             share_vpn_relay: true,
             earning_mode: false,
             earning_bandwidth_percent: 50,
+            tokens_per_gb: DEFAULT_TOKENS_PER_GB,
             schedule_enabled: false,
             schedule_start: "22:00".to_string(),
             schedule_end: "06:00".to_string(),
+            auto_pause_on_metered: true,
         }
     }
 }
@@ -112,7 +136,7 @@ pub struct ContributionStats {
     /// Tokens earned (if earning mode)
     pub tokens_earned: f64,
 
-    /// Pending tokens
+    /// Tokens credited for contribution but not yet settled on-chain
     pub tokens_pending: f64,
 
     /// Unique users helped (sessions served)
@@ -133,6 +157,9 @@ pub struct ContributionManager {
 
     /// Country code of this node (for legal compliance)
     country_code: String,
+
+    /// The current pause was made by the metered-network check, not the user
+    paused_for_metered: bool,
 }
 
 impl ContributionManager {
@@ -170,6 +197,7 @@ impl ContributionManager {
                 uptime_seconds: 0,
             },
             country_code: "US".to_string(),
+            paused_for_metered: false,
         }
     }
 
@@ -250,6 +278,19 @@ impl ContributionManager {
         self.stats.total_bytes_contributed += actual;
         self.stats.users_helped += 1;
         self.update_ratio();
+
+        // Only traffic under the cap earns, since `actual` is already capped
+        if self.settings.earning_mode {
+            self.stats.tokens_pending += tokens_for_bytes(actual, self.settings.tokens_per_gb);
+        }
+    }
+
+    /// Settle pending tokens: moves them to earned and returns the amount to claim on-chain
+    pub fn claim_tokens(&mut self) -> f64 {
+        let amount = self.stats.tokens_pending;
+        self.stats.tokens_pending = 0.0;
+        self.stats.tokens_earned += amount;
+        amount
     }
 
     /// Check if contribution is allowed (under cap, not paused, etc.)
@@ -307,6 +348,32 @@ impl ContributionManager {
         self.status.is_paused = false;
     }
 
+    /// Follow the network's metered status: pause contribution while metered (if
+    /// `auto_pause_on_metered` is set) and resume once it isn't. A pause the user
+    /// made is left alone. Returns true if contribution was paused or resumed.
+    pub fn set_metered(&mut self, metered: bool) -> bool {
+        let pause = metered && self.settings.auto_pause_on_metered;
+        if pause == self.paused_for_metered {
+            return false;
+        }
+
+        if pause {
+            if self.status.is_paused {
+                return false;
+            }
+            self.pause();
+        } else {
+            self.resume();
+        }
+        self.paused_for_metered = pause;
+        true
+    }
+
+    /// Whether contribution is paused because the network is metered
+    pub fn is_paused_for_metered(&self) -> bool {
+        self.paused_for_metered
+    }
+
     /// Start contributing
     pub fn start_contributing(&mut self) {
         if self.can_contribute() {
@@ -338,81 +405,41 @@ impl ContributionManager {
 
     /// Check if current time is within scheduled contribution window
     ///
-    /// Parses schedule_start and schedule_end times (HH:MM format)
-    /// and checks if current local time falls within the window.
-    /// Handles overnight windows (e.g., 22:00 - 06:00).
+    /// Uses the node's local time. An unparseable schedule allows contribution
+    /// rather than silently disabling it.
     fn is_within_schedule(&self) -> bool {
         if !self.settings.schedule_enabled {
             return true;
         }
 
-        // Parse start time
-        let start_parts: Vec<&str> = self.settings.schedule_start.split(':').collect();
-        let end_parts: Vec<&str> = self.settings.schedule_end.split(':').collect();
+        let now = chrono::Local::now().time();
+        let current_mins = now.hour() * 60 + now.minute();
 
-        if start_parts.len() != 2 || end_parts.len() != 2 {
-            tracing::warn!(
-                "Invalid schedule format: {} - {}, allowing contribution",
-                self.settings.schedule_start,
-                self.settings.schedule_end
-            );
-            return true;
-        }
-
-        let start_hour: u32 = match start_parts[0].parse() {
-            Ok(h) => h,
-            Err(_) => return true,
-        };
-        let start_min: u32 = match start_parts[1].parse() {
-            Ok(m) => m,
-            Err(_) => return true,
-        };
-        let end_hour: u32 = match end_parts[0].parse() {
-            Ok(h) => h,
-            Err(_) => return true,
-        };
-        let end_min: u32 = match end_parts[1].parse() {
-            Ok(m) => m,
-            Err(_) => return true,
-        };
-
-        // Get current local time
-        // Using system time and calculating hours/minutes from midnight
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        // Get seconds since midnight in local time (approximation using UTC)
-        // For proper timezone support, would need chrono crate
-        let seconds_today = now % 86400;
-        let current_hour = (seconds_today / 3600) as u32;
-        let current_min = ((seconds_today % 3600) / 60) as u32;
-
-        let current_mins = current_hour * 60 + current_min;
-        let start_mins = start_hour * 60 + start_min;
-        let end_mins = end_hour * 60 + end_min;
-
-        // Handle overnight windows (e.g., 22:00 - 06:00)
-        let within = if start_mins <= end_mins {
-            // Same-day window (e.g., 09:00 - 17:00)
-            current_mins >= start_mins && current_mins < end_mins
-        } else {
-            // Overnight window (e.g., 22:00 - 06:00)
-            current_mins >= start_mins || current_mins < end_mins
-        };
-
-        tracing::debug!(
-            "Schedule check: {} ({:02}:{:02}) within {} - {} = {}",
+        match schedule_window_contains(
+            &self.settings.schedule_start,
+            &self.settings.schedule_end,
             current_mins,
-            current_hour,
-            current_min,
-            self.settings.schedule_start,
-            self.settings.schedule_end,
-            within
-        );
-
-        within
+        ) {
+            Some(within) => {
+                tracing::debug!(
+                    "Schedule check: {:02}:{:02} within {} - {} = {}",
+                    now.hour(),
+                    now.minute(),
+                    self.settings.schedule_start,
+                    self.settings.schedule_end,
+                    within
+                );
+                within
+            }
+            None => {
+                tracing::warn!(
+                    "Invalid schedule format: {} - {}, allowing contribution",
+                    self.settings.schedule_start,
+                    self.settings.schedule_end
+                );
+                true
+            }
+        }
     }
 
     /// Update contribution ratio stat
@@ -429,3 +456,181 @@ impl Default for ContributionManager {
         Self::new()
     }
 }
+
+/// Tokens earned for serving `bytes` at `tokens_per_gb`
+fn tokens_for_bytes(bytes: u64, tokens_per_gb: f64) -> f64 {
+    bytes as f64 / BYTES_PER_GB * tokens_per_gb.max(0.0)
+}
+
+/// Parse an "HH:MM" time into minutes since midnight
+fn parse_schedule_time(time: &str) -> Option<u32> {
+    let (hour, minute) = time.trim().split_once(':')?;
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+/// Whether `current_mins` (minutes since midnight) falls in the `start`-`end` window.
+///
+/// The start minute is inside the window and the end minute is not. A start
+/// after the end wraps past midnight (e.g. 22:00 - 06:00); equal times mean all
+/// day. Returns `None` if either time is not valid "HH:MM".
+fn schedule_window_contains(start: &str, end: &str, current_mins: u32) -> Option<bool> {
+    let start_mins = parse_schedule_time(start)?;
+    let end_mins = parse_schedule_time(end)?;
+
+    Some(if start_mins < end_mins {
+        // Same-day window (e.g., 09:00 - 17:00)
+        current_mins >= start_mins && current_mins < end_mins
+    } else if start_mins > end_mins {
+        // Overnight window (e.g., 22:00 - 06:00)
+        current_mins >= start_mins || current_mins < end_mins
+    } else {
+        true
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn within(start: &str, end: &str, hour: u32, minute: u32) -> bool {
+        schedule_window_contains(start, end, hour * 60 + minute).expect("valid schedule")
+    }
+
+    #[test]
+    fn test_same_day_window() {
+        assert!(within("09:00", "17:00", 12, 30));
+        assert!(!within("09:00", "17:00", 8, 59));
+        assert!(!within("09:00", "17:00", 17, 30));
+        assert!(!within("09:00", "17:00", 23, 0));
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        assert!(within("22:00", "06:00", 23, 15));
+        assert!(within("22:00", "06:00", 0, 0));
+        assert!(within("22:00", "06:00", 3, 45));
+        assert!(!within("22:00", "06:00", 12, 0));
+        assert!(!within("22:00", "06:00", 21, 59));
+    }
+
+    #[test]
+    fn test_boundary_minutes() {
+        // Start is inclusive, end is exclusive
+        assert!(within("09:00", "17:00", 9, 0));
+        assert!(within("09:00", "17:00", 16, 59));
+        assert!(!within("09:00", "17:00", 17, 0));
+
+        assert!(within("22:00", "06:00", 22, 0));
+        assert!(within("22:00", "06:00", 5, 59));
+        assert!(!within("22:00", "06:00", 6, 0));
+
+        assert!(within("08:00", "08:00", 2, 0));
+    }
+
+    fn earning_manager(tokens_per_gb: f64) -> ContributionManager {
+        let mut manager = ContributionManager::new();
+        manager.update_settings(ContributionSettings {
+            earning_mode: true,
+            tokens_per_gb,
+            ..ContributionSettings::default()
+        });
+        manager
+    }
+
+    #[test]
+    fn test_contribution_credits_pending_tokens() {
+        let mut manager = earning_manager(2.0);
+        manager.record_vpn_usage(1_000_000_000);
+
+        manager.record_contribution(500_000_000, true);
+        manager.record_contribution(250_000_000, false);
+
+        let stats = manager.get_stats();
+        assert!((stats.tokens_pending - 1.5).abs() < 1e-9);
+        assert_eq!(stats.tokens_earned, 0.0);
+    }
+
+    #[test]
+    fn test_tokens_stop_at_contribution_cap() {
+        let mut manager = earning_manager(1.0);
+        manager.record_vpn_usage(1_000_000_000);
+
+        // Cap is 3 GB; the excess earns nothing
+        manager.record_contribution(5_000_000_000, true);
+        manager.record_contribution(1_000_000_000, false);
+
+        assert!((manager.get_stats().tokens_pending - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_no_tokens_outside_earning_mode() {
+        let mut manager = ContributionManager::new();
+        manager.record_vpn_usage(1_000_000_000);
+        manager.record_contribution(1_000_000_000, true);
+
+        assert_eq!(manager.get_stats().tokens_pending, 0.0);
+    }
+
+    #[test]
+    fn test_claim_tokens_moves_pending_to_earned() {
+        let mut manager = earning_manager(1.0);
+        manager.record_vpn_usage(1_000_000_000);
+        manager.record_contribution(1_000_000_000, true);
+
+        assert!((manager.claim_tokens() - 1.0).abs() < 1e-9);
+        let stats = manager.get_stats();
+        assert_eq!(stats.tokens_pending, 0.0);
+        assert!((stats.tokens_earned - 1.0).abs() < 1e-9);
+
+        assert_eq!(manager.claim_tokens(), 0.0);
+    }
+
+    #[test]
+    fn test_metered_network_pauses_and_resumes() {
+        let mut manager = ContributionManager::new();
+
+        assert!(manager.set_metered(true));
+        assert!(manager.get_status().is_paused);
+        assert!(manager.is_paused_for_metered());
+
+        // Still metered: nothing changes
+        assert!(!manager.set_metered(true));
+
+        assert!(manager.set_metered(false));
+        assert!(!manager.get_status().is_paused);
+        assert!(!manager.is_paused_for_metered());
+    }
+
+    #[test]
+    fn test_metered_network_leaves_user_pause() {
+        let mut manager = ContributionManager::new();
+        manager.pause();
+
+        assert!(!manager.set_metered(true));
+        assert!(!manager.set_metered(false));
+        assert!(manager.get_status().is_paused);
+    }
+
+    #[test]
+    fn test_metered_auto_pause_disabled() {
+        let mut manager = ContributionManager::new();
+        assert!(manager.set_metered(true));
+
+        manager.update_settings(ContributionSettings {
+            auto_pause_on_metered: false,
+            ..ContributionSettings::default()
+        });
+        assert!(manager.set_metered(true));
+        assert!(!manager.get_status().is_paused);
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        assert_eq!(schedule_window_contains("24:00", "06:00", 60), None);
+        assert_eq!(schedule_window_contains("22:60", "06:00", 60), None);
+        assert_eq!(schedule_window_contains("22", "06:00", 60), None);
+        assert_eq!(schedule_window_contains("22:00", "six", 60), None);
+    }
+}
@@ -8,6 +8,8 @@ pub mod commands;
 pub mod config;
 pub mod contribution;
 pub mod dws;
+pub mod metered;
+pub mod node_preference;
 pub mod notifications;
 pub mod state;
 pub mod vpn;
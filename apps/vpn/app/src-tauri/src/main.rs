@@ -14,6 +14,8 @@ mod commands;
 mod config;
 mod contribution;
 mod dws;
+mod metered;
+mod node_preference;
 mod notifications;
 mod state;
 mod vpn;
@@ -114,6 +116,7 @@ fn main() {
     let tray = SystemTray::new().with_menu(build_tray_menu(false, None, 10));
 
     let app = tauri::Builder::default()
+        .plugin(tauri_plugin_store::Builder::default().build())
         .system_tray(tray)
         .on_system_tray_event(|app, event| match event {
             SystemTrayEvent::LeftClick { .. } => {
@@ -201,6 +204,44 @@ fn main() {
         })
         .setup(|app| {
             let state = state::AppState::new();
+
+            // Keep connecting to the user's chosen server across restarts
+            if let Some(node_id) = node_preference::load(&app.handle()).selected_node_id {
+                tracing::info!("Restored selected VPN node {}", node_id);
+                state.vpn.blocking_write().select_node(node_id);
+            }
+
+            // Reconnect failed tunnels and keep the UI and tray in sync
+            let supervisor_handle = app.handle();
+            let supervisor_vpn = state.vpn.clone();
+            tauri::async_runtime::spawn(vpn::supervise(state.vpn.clone(), move |event| {
+                let name = match &event {
+                    vpn::SupervisorEvent::Reconnecting { .. } => {
+                        update_tray_icon(&supervisor_handle, false);
+                        "vpn_reconnecting"
+                    }
+                    vpn::SupervisorEvent::Reconnected { .. } => {
+                        update_tray_icon(&supervisor_handle, true);
+                        commands::vpn::spawn_public_ip_check(
+                            supervisor_handle.clone(),
+                            supervisor_vpn.clone(),
+                        );
+                        "vpn_reconnected"
+                    }
+                    vpn::SupervisorEvent::ReconnectFailed { .. } => {
+                        update_tray_icon(&supervisor_handle, false);
+                        "vpn_reconnect_failed"
+                    }
+                };
+                let _ = supervisor_handle.emit_all(name, event);
+            }));
+
+            // Keep contribution off metered connections
+            let metered_handle = app.handle();
+            tauri::async_runtime::spawn(metered::watch(state.contribution.clone(), move |event| {
+                let _ = metered_handle.emit_all("contribution_metered_changed", event);
+            }));
+
             app.manage(state);
 
             // Initialize auto-start manager
@@ -255,16 +296,24 @@ fn main() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::vpn::connect,
+            commands::vpn::connect_multihop,
             commands::vpn::disconnect,
             commands::vpn::get_status,
             commands::vpn::get_nodes,
+            commands::vpn::refresh_latencies,
             commands::vpn::select_node,
+            commands::vpn::clear_selected_node,
             commands::vpn::get_connection_stats,
             commands::vpn::get_public_key,
+            commands::vpn::set_kill_switch_enabled,
+            commands::vpn::set_dns_leak_protection,
+            commands::vpn::run_dns_leak_test,
+            commands::vpn::set_split_tunnel,
             commands::contribution::get_contribution_status,
             commands::contribution::get_contribution_settings,
             commands::contribution::set_contribution_settings,
             commands::contribution::get_contribution_stats,
+            commands::contribution::claim_contribution_tokens,
             commands::auth::login_with_wallet,
             commands::auth::logout,
             commands::auth::get_session,
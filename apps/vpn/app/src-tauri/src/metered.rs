@@ -0,0 +1,257 @@
+//! Metered network detection
+//!
+//! Contribution is paused while the machine is on a metered connection (phone
+//! hotspot, cellular modem) so CDN and relay traffic doesn't eat the user's data
+//! cap. Only contribution is affected; the user's own VPN traffic never is.
+//!
+//! - Linux: NetworkManager's `Metered` property, read with `busctl`
+//! - macOS: `NWPathMonitor` reports expensive (cellular, hotspot) and
+//!   constrained (Low Data Mode) paths
+//! - Windows: the connection cost of the internet profile, read with PowerShell
+
+use crate::contribution::ContributionManager;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// How often the network is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Metered state changes, forwarded to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct MeteredEvent {
+    pub metered: bool,
+    /// Contribution is paused because of the metered connection
+    pub contribution_paused: bool,
+}
+
+/// Watch the network forever, pausing contribution while it's metered and
+/// reporting each change through `on_change`
+pub async fn watch<F>(contribution: Arc<RwLock<ContributionManager>>, on_change: F)
+where
+    F: Fn(MeteredEvent) + Send + 'static,
+{
+    let mut last = None;
+    loop {
+        let metered = tokio::task::spawn_blocking(is_metered).await.ok().flatten();
+
+        // Unknown network status leaves contribution as it is
+        if let Some(metered) = metered {
+            let mut contribution = contribution.write().await;
+            let flipped = contribution.set_metered(metered);
+            if flipped || last != Some(metered) {
+                tracing::info!(
+                    "Network is {}, contribution {}",
+                    if metered { "metered" } else { "unmetered" },
+                    if contribution.is_paused_for_metered() {
+                        "paused"
+                    } else {
+                        "allowed"
+                    }
+                );
+                on_change(MeteredEvent {
+                    metered,
+                    contribution_paused: contribution.is_paused_for_metered(),
+                });
+            }
+            last = Some(metered);
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Whether the default network is metered, or `None` if it can't be told
+#[cfg(target_os = "linux")]
+pub fn is_metered() -> Option<bool> {
+    let output = command_output(
+        "busctl",
+        &[
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ],
+    )?;
+    parse_nm_metered(&output)
+}
+
+/// Whether the default network is metered, or `None` if it can't be told
+#[cfg(target_os = "macos")]
+pub fn is_metered() -> Option<bool> {
+    path_monitor::is_metered()
+}
+
+/// Whether the default network is metered, or `None` if it can't be told
+#[cfg(target_os = "windows")]
+pub fn is_metered() -> Option<bool> {
+    let output = command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "$p = [Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile(); \
+             if ($p) { $c = $p.GetConnectionCost(); \"$($c.NetworkCostType) $($c.Roaming) $($c.OverDataLimit)\" }",
+        ],
+    )?;
+    parse_connection_cost(&output)
+}
+
+/// Whether the default network is metered, or `None` if it can't be told
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn is_metered() -> Option<bool> {
+    None
+}
+
+/// Stdout of a command that succeeded
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        tracing::debug!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// NetworkManager's `NMMetered` from `busctl get-property`, printed as `u <n>`.
+/// The guessed values come from the device type (e.g. a phone's hotspot).
+#[cfg(any(target_os = "linux", test))]
+fn parse_nm_metered(output: &str) -> Option<bool> {
+    match output.trim().strip_prefix("u ")?.trim() {
+        // NM_METERED_YES, NM_METERED_GUESS_YES
+        "1" | "3" => Some(true),
+        // NM_METERED_NO, NM_METERED_GUESS_NO
+        "2" | "4" => Some(false),
+        // NM_METERED_UNKNOWN
+        _ => None,
+    }
+}
+
+/// `<NetworkCostType> <Roaming> <OverDataLimit>` from the PowerShell query.
+/// Anything but an unrestricted plan counts, as do roaming and an exceeded limit.
+#[cfg(any(target_os = "windows", test))]
+fn parse_connection_cost(output: &str) -> Option<bool> {
+    let mut fields = output.split_whitespace();
+    let cost_type = fields.next()?;
+    let flagged = fields.any(|flag| flag.eq_ignore_ascii_case("true"));
+
+    match cost_type {
+        "Unrestricted" => Some(flagged),
+        "Fixed" | "Variable" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod path_monitor {
+    //! Network.framework path monitor, started once and left running
+
+    use block2::{Block, RcBlock};
+    use std::ffi::{c_char, c_void};
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::Once;
+
+    const UNKNOWN: u8 = 0;
+    const METERED: u8 = 1;
+    const UNMETERED: u8 = 2;
+
+    /// Latest path status from the update handler
+    static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+    static START: Once = Once::new();
+
+    type NwObject = *mut c_void;
+
+    #[link(name = "Network", kind = "framework")]
+    extern "C" {
+        fn nw_path_monitor_create() -> NwObject;
+        fn nw_path_monitor_set_queue(monitor: NwObject, queue: NwObject);
+        fn nw_path_monitor_set_update_handler(monitor: NwObject, handler: &Block<dyn Fn(NwObject)>);
+        fn nw_path_monitor_start(monitor: NwObject);
+        fn nw_path_is_expensive(path: NwObject) -> bool;
+        fn nw_path_is_constrained(path: NwObject) -> bool;
+    }
+
+    extern "C" {
+        fn dispatch_queue_create(label: *const c_char, attr: NwObject) -> NwObject;
+    }
+
+    pub fn is_metered() -> Option<bool> {
+        START.call_once(start);
+        match STATE.load(Ordering::Relaxed) {
+            METERED => Some(true),
+            UNMETERED => Some(false),
+            _ => None,
+        }
+    }
+
+    fn start() {
+        let handler = RcBlock::new(|path: NwObject| {
+            // SAFETY: `path` is valid for the duration of the handler
+            let metered = unsafe { nw_path_is_expensive(path) || nw_path_is_constrained(path) };
+            STATE.store(if metered { METERED } else { UNMETERED }, Ordering::Relaxed);
+        });
+
+        // SAFETY: the monitor and queue are never released, so the monitor keeps
+        // running for the life of the process; the handler block is copied by
+        // Network.framework when it's set
+        unsafe {
+            let monitor = nw_path_monitor_create();
+            let queue = dispatch_queue_create(
+                b"org.jejunetwork.vpn.path-monitor\0".as_ptr() as *const c_char,
+                std::ptr::null_mut(),
+            );
+            nw_path_monitor_set_queue(monitor, queue);
+            nw_path_monitor_set_update_handler(monitor, &handler);
+            nw_path_monitor_start(monitor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nm_metered() {
+        assert_eq!(parse_nm_metered("u 1\n"), Some(true));
+        assert_eq!(parse_nm_metered("u 3\n"), Some(true));
+        assert_eq!(parse_nm_metered("u 2\n"), Some(false));
+        assert_eq!(parse_nm_metered("u 4\n"), Some(false));
+        assert_eq!(parse_nm_metered("u 0\n"), None);
+        assert_eq!(parse_nm_metered("Failed to get property"), None);
+    }
+
+    #[test]
+    fn test_parse_connection_cost() {
+        assert_eq!(
+            parse_connection_cost("Unrestricted False False\r\n"),
+            Some(false)
+        );
+        assert_eq!(
+            parse_connection_cost("Unrestricted True False\r\n"),
+            Some(true)
+        );
+        assert_eq!(
+            parse_connection_cost("Unrestricted False True\r\n"),
+            Some(true)
+        );
+        assert_eq!(parse_connection_cost("Fixed False False\r\n"), Some(true));
+        assert_eq!(
+            parse_connection_cost("Variable False False\r\n"),
+            Some(true)
+        );
+        assert_eq!(parse_connection_cost("Unknown False False\r\n"), None);
+        assert_eq!(parse_connection_cost(""), None);
+    }
+}
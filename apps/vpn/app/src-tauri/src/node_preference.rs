@@ -0,0 +1,70 @@
+//! Preferred VPN node persistence
+//!
+//! The node the user selected, and the last one connected to, are saved in the
+//! app store so `connect(None)` keeps using the user's server across restarts
+//! instead of silently switching to the best-scoring node.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreBuilder;
+
+const STORE_PATH: &str = "settings.json";
+const STORE_KEY: &str = "node_preference";
+
+/// Node choices as persisted in the store
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodePreference {
+    pub selected_node_id: Option<String>,
+    pub last_node_id: Option<String>,
+}
+
+/// Emitted when the selected node is gone and `connect` fell back to another
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectedNodeUnavailableEvent {
+    pub selected_node_id: String,
+    pub connected_node_id: String,
+}
+
+/// Load the persisted preference, or the default if there is none
+pub fn load(app: &AppHandle) -> NodePreference {
+    let mut store = StoreBuilder::new(app.clone(), PathBuf::from(STORE_PATH)).build();
+    if let Err(e) = store.load() {
+        tracing::debug!("No settings store loaded: {}", e);
+        return NodePreference::default();
+    }
+
+    let Some(value) = store.get(STORE_KEY).cloned() else {
+        return NodePreference::default();
+    };
+
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid node preference: {}", e);
+        NodePreference::default()
+    })
+}
+
+/// Apply `change` to the persisted preference and save it
+pub fn update<F>(app: &AppHandle, change: F) -> Result<(), String>
+where
+    F: FnOnce(&mut NodePreference),
+{
+    let mut store = StoreBuilder::new(app.clone(), PathBuf::from(STORE_PATH)).build();
+    // A missing store is created on save
+    let _ = store.load();
+
+    let mut preference: NodePreference = store
+        .get(STORE_KEY)
+        .cloned()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    change(&mut preference);
+
+    store
+        .insert(
+            STORE_KEY.to_string(),
+            serde_json::to_value(&preference).map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string())?;
+    store.save().map_err(|e| e.to_string())
+}
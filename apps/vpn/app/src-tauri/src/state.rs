@@ -7,7 +7,7 @@ use crate::bandwidth::AdaptiveBandwidthManager;
 use crate::config::VPNConfig;
 use crate::contribution::ContributionManager;
 use crate::dws::{DWSConfig, DWSManager};
-use crate::vpn::VPNManager;
+use crate::vpn::{NodeDiscovery, VPNManager};
 
 /// Main application state
 pub struct AppState {
@@ -40,12 +40,30 @@ pub struct UserSession {
 
 impl AppState {
     pub fn new() -> Self {
+        let config = VPNConfig::default();
+
         Self {
-            vpn: Arc::new(RwLock::new(VPNManager::new())),
+            vpn: Arc::new(RwLock::new(
+                VPNManager::new()
+                    .with_discovery(
+                        NodeDiscovery::with_config(
+                            config.coordinator_url.clone(),
+                            config.rpc_url.clone(),
+                        )
+                        .with_registry(&config.vpn_registry),
+                    )
+                    .with_kill_switch(config.kill_switch)
+                    .with_dns_servers(&config.dns_servers)
+                    .with_mtu(config.mtu)
+                    .with_ipv6(config.ipv6)
+                    .with_dns_leak_protection(config.dns_leak_protection)
+                    .with_reconnect_policy(config.reconnect_policy())
+                    .with_split_tunnel(config.split_tunnel.clone()),
+            )),
             contribution: Arc::new(RwLock::new(ContributionManager::new())),
             bandwidth: Arc::new(RwLock::new(AdaptiveBandwidthManager::new())),
             dws: Arc::new(RwLock::new(DWSManager::new(DWSConfig::default()))),
-            config: Arc::new(RwLock::new(VPNConfig::default())),
+            config: Arc::new(RwLock::new(config)),
             session: Arc::new(RwLock::new(None)),
         }
     }
@@ -0,0 +1,653 @@
+//! DNS leak protection: point the system resolvers at the tunnel's DNS servers
+//!
+//! Routing every packet through the tunnel doesn't help if the OS keeps sending
+//! lookups to the ISP resolver it learned over DHCP. While connected, the
+//! system DNS is replaced with the tunnel resolvers and the previous setup is
+//! put back on disconnect.
+//!
+//! - Linux: systemd-resolved (`resolvectl`) when it's running, otherwise
+//!   openresolv's `resolvconf`, otherwise `/etc/resolv.conf` is rewritten
+//! - macOS: `networksetup -setdnsservers` on every enabled network service
+//! - Windows: static `netsh` servers on the tunnel interface, which also gets the
+//!   lowest metric so Windows prefers it over other interfaces' resolvers
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use super::kill_switch::run_with_stdin;
+use super::VPNError;
+use hickory_resolver::TokioAsyncResolver;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Resolved by the leak self-test
+const TEST_DOMAIN: &str = "example.com.";
+
+/// systemd-resolved's stub listeners, which forward to the upstreams it reports
+const RESOLVED_STUBS: [IpAddr; 2] = [
+    IpAddr::V4(Ipv4Addr::new(127, 0, 0, 53)),
+    IpAddr::V4(Ipv4Addr::new(127, 0, 0, 54)),
+];
+
+/// How the system DNS was overridden, with whatever is needed to undo it
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Applied {
+    #[cfg(target_os = "linux")]
+    Resolved { interface: String },
+    #[cfg(target_os = "linux")]
+    Resolvconf { interface: String },
+    #[cfg(target_os = "linux")]
+    ResolvConf { previous: String },
+    #[cfg(target_os = "macos")]
+    NetworkSetup {
+        /// Each service and the servers it had; empty means DHCP-provided
+        previous: Vec<(String, Vec<String>)>,
+    },
+    #[cfg(target_os = "windows")]
+    Netsh { interface: String },
+}
+
+/// Result of the DNS leak self-test
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsLeakReport {
+    /// Resolvers that answer the system's lookups: the configured ones, with
+    /// the systemd-resolved stub replaced by the upstreams it forwards to
+    pub resolvers: Vec<String>,
+    /// Whether the test domain resolved through them
+    pub resolved: bool,
+    /// True if any system resolver is outside the tunnel's set
+    pub leaking: bool,
+}
+
+/// Overrides and restores the system DNS configuration
+pub struct DnsGuard {
+    applied: Mutex<Option<Applied>>,
+}
+
+impl DnsGuard {
+    pub fn new() -> Self {
+        Self {
+            applied: Mutex::new(None),
+        }
+    }
+
+    /// Whether the system DNS is currently overridden
+    pub fn is_active(&self) -> bool {
+        self.applied.lock().is_some()
+    }
+
+    /// Point the system resolvers at `servers`, reached through `interface`.
+    ///
+    /// Re-applying (e.g. for a new tunnel after a reconnect) keeps the original
+    /// configuration for `restore`.
+    pub fn apply(&self, interface: &str, servers: &[IpAddr]) -> Result<(), VPNError> {
+        if servers.is_empty() {
+            return Err(VPNError::TunnelError(
+                "No tunnel DNS servers configured".to_string(),
+            ));
+        }
+
+        let mut applied = self.applied.lock();
+        let state = install(interface, servers, applied.take())?;
+        tracing::info!("DNS leak protection on: using {:?}", servers);
+        *applied = Some(state);
+        Ok(())
+    }
+
+    /// Put back the DNS configuration from before `apply`
+    pub fn restore(&self) -> Result<(), VPNError> {
+        let Some(state) = self.applied.lock().take() else {
+            return Ok(());
+        };
+        tracing::info!("DNS leak protection off: restoring system DNS");
+        uninstall(state)
+    }
+}
+
+impl Default for DnsGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DnsGuard {
+    fn drop(&mut self) {
+        // Best-effort cleanup so exiting the app never leaves DNS pointing at a dead tunnel
+        let _ = self.restore();
+    }
+}
+
+/// Resolve a known domain through the system resolvers and check they're the tunnel's.
+///
+/// A loopback stub answers from whatever upstreams it forwards to, so the
+/// systemd-resolved stub is looked through to the servers it routes ordinary
+/// lookups to. Other loopback resolvers can't be seen through and count as a leak.
+pub async fn dns_leak_test(tunnel_dns: &[IpAddr]) -> Result<DnsLeakReport, VPNError> {
+    let (config, options) = hickory_resolver::system_conf::read_system_conf()
+        .map_err(|e| VPNError::ConnectionFailed(format!("Failed to read system DNS: {}", e)))?;
+
+    let configured: Vec<IpAddr> = config
+        .name_servers()
+        .iter()
+        .map(|server| server.socket_addr.ip())
+        .collect();
+    let resolvers = if configured.iter().any(|ip| RESOLVED_STUBS.contains(ip)) {
+        expand_stubs(&configured, resolved_upstreams().as_deref())
+    } else {
+        expand_stubs(&configured, None)
+    };
+
+    let resolver = TokioAsyncResolver::tokio(config, options);
+    let resolved = match resolver.lookup_ip(TEST_DOMAIN).await {
+        Ok(_) => true,
+        Err(e) => {
+            tracing::warn!("DNS leak test lookup failed: {}", e);
+            false
+        }
+    };
+
+    let leaking = resolvers_leak(&resolvers, tunnel_dns);
+    if leaking {
+        tracing::warn!(
+            "DNS leak: system resolvers {:?} are outside the tunnel",
+            resolvers
+        );
+    }
+
+    Ok(DnsLeakReport {
+        resolvers: resolvers.iter().map(|ip| ip.to_string()).collect(),
+        resolved,
+        leaking,
+    })
+}
+
+fn resolvers_leak(resolvers: &[IpAddr], tunnel_dns: &[IpAddr]) -> bool {
+    resolvers.iter().any(|ip| !tunnel_dns.contains(ip))
+}
+
+/// Configured resolvers with the systemd-resolved stub replaced by `upstreams`,
+/// deduplicated. Without upstreams the stub stays in, and so counts as a leak.
+fn expand_stubs(configured: &[IpAddr], upstreams: Option<&[IpAddr]>) -> Vec<IpAddr> {
+    let mut resolvers: Vec<IpAddr> = Vec::new();
+    for ip in configured {
+        let answering = match upstreams {
+            Some(upstreams) if RESOLVED_STUBS.contains(ip) => upstreams,
+            _ => std::slice::from_ref(ip),
+        };
+        for ip in answering {
+            if !resolvers.contains(ip) {
+                resolvers.push(*ip);
+            }
+        }
+    }
+    resolvers
+}
+
+/// Upstreams systemd-resolved sends ordinary lookups to, or `None` if it can't be asked
+#[cfg(target_os = "linux")]
+fn resolved_upstreams() -> Option<Vec<IpAddr>> {
+    match run_with_stdin("resolvectl", &["status"], "") {
+        Ok(output) => Some(routed_servers(&parse_resolvectl_status(&output))),
+        Err(e) => {
+            tracing::warn!("Can't see past the DNS stub: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resolved_upstreams() -> Option<Vec<IpAddr>> {
+    None
+}
+
+/// The global section or one link of `resolvectl status`
+#[cfg(any(target_os = "linux", test))]
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ResolvedScope {
+    servers: Vec<IpAddr>,
+    domains: Vec<String>,
+    default_route: bool,
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn parse_resolvectl_status(output: &str) -> Vec<ResolvedScope> {
+    let mut scopes: Vec<ResolvedScope> = Vec::new();
+    let mut key = String::new();
+
+    let lines = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    for line in lines {
+        if line == "Global" || (line.starts_with("Link ") && line.ends_with(')')) {
+            scopes.push(ResolvedScope {
+                // Global servers take any lookup no link's domains claim
+                default_route: line == "Global",
+                ..Default::default()
+            });
+            key.clear();
+            continue;
+        }
+        let Some(scope) = scopes.last_mut() else {
+            continue;
+        };
+
+        // IPv6 servers contain colons but never ": ", and values can wrap onto
+        // lines of their own
+        let value = match line.split_once(": ") {
+            Some((k, v)) => {
+                key = k.to_string();
+                v
+            }
+            None if line.ends_with(':') && line.parse::<IpAddr>().is_err() => {
+                key = line.trim_end_matches(':').to_string();
+                ""
+            }
+            None => line,
+        };
+
+        for token in value.split_whitespace() {
+            match key.as_str() {
+                "DNS Servers" => {
+                    // Drop DNS-over-TLS names and scope IDs: 1.1.1.1#one.one.one.one, fe80::1%2
+                    let address = token.split(['#', '%']).next().unwrap_or(token);
+                    if let Ok(ip) = address.parse() {
+                        scope.servers.push(ip);
+                    }
+                }
+                "DNS Domain" => scope.domains.push(token.to_string()),
+                "Protocols" if token == "+DefaultRoute" => scope.default_route = true,
+                "Protocols" if token == "-DefaultRoute" => scope.default_route = false,
+                "DefaultRoute setting" => scope.default_route = token == "yes",
+                _ => {}
+            }
+        }
+    }
+    scopes
+}
+
+/// Servers an ordinary lookup goes to: those of scopes routing every domain
+/// (`~.`), or if none does, those of every default-route scope
+#[cfg(any(target_os = "linux", test))]
+fn routed_servers(scopes: &[ResolvedScope]) -> Vec<IpAddr> {
+    let catch_all = scopes
+        .iter()
+        .any(|scope| scope.domains.iter().any(|domain| domain == "~."));
+    let mut servers: Vec<IpAddr> = Vec::new();
+    for scope in scopes.iter().filter(|scope| {
+        if catch_all {
+            scope.domains.iter().any(|domain| domain == "~.")
+        } else {
+            scope.default_route
+        }
+    }) {
+        for ip in &scope.servers {
+            if !servers.contains(ip) {
+                servers.push(*ip);
+            }
+        }
+    }
+    servers
+}
+
+#[cfg(target_os = "linux")]
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+#[cfg(target_os = "linux")]
+fn install(
+    interface: &str,
+    servers: &[IpAddr],
+    current: Option<Applied>,
+) -> Result<Applied, VPNError> {
+    let addresses: Vec<String> = servers.iter().map(|ip| ip.to_string()).collect();
+
+    if run_with_stdin("resolvectl", &["status"], "").is_ok() {
+        let mut args = vec!["dns", interface];
+        args.extend(addresses.iter().map(String::as_str));
+        run_with_stdin("resolvectl", &args, "")?;
+        // Send every domain to the tunnel link, not just its own search domains
+        run_with_stdin("resolvectl", &["domain", interface, "~."], "")?;
+        run_with_stdin("resolvectl", &["default-route", interface, "true"], "")?;
+        return Ok(Applied::Resolved {
+            interface: interface.to_string(),
+        });
+    }
+
+    let contents = resolv_conf(servers);
+
+    // -x makes our entry exclusive, so other interfaces' resolvers are dropped
+    match run_with_stdin("resolvconf", &["-a", interface, "-x"], &contents) {
+        Ok(_) => {
+            return Ok(Applied::Resolvconf {
+                interface: interface.to_string(),
+            })
+        }
+        Err(e) => tracing::debug!("resolvconf unavailable, writing {}: {}", RESOLV_CONF, e),
+    }
+
+    let previous = match current {
+        Some(Applied::ResolvConf { previous }) => previous,
+        _ => std::fs::read_to_string(RESOLV_CONF)?,
+    };
+    std::fs::write(RESOLV_CONF, contents)?;
+    Ok(Applied::ResolvConf { previous })
+}
+
+#[cfg(target_os = "linux")]
+fn uninstall(state: Applied) -> Result<(), VPNError> {
+    match state {
+        // The tunnel link may already be gone, taking its settings with it
+        Applied::Resolved { interface } => {
+            let _ = run_with_stdin("resolvectl", &["revert", &interface], "");
+            Ok(())
+        }
+        Applied::Resolvconf { interface } => {
+            run_with_stdin("resolvconf", &["-d", &interface], "").map(|_| ())
+        }
+        Applied::ResolvConf { previous } => Ok(std::fs::write(RESOLV_CONF, previous)?),
+    }
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn resolv_conf(servers: &[IpAddr]) -> String {
+    let mut contents = String::from("# Generated by Jeju VPN\n");
+    for server in servers {
+        contents.push_str(&format!("nameserver {}\n", server));
+    }
+    contents
+}
+
+#[cfg(target_os = "macos")]
+fn install(
+    _interface: &str,
+    servers: &[IpAddr],
+    current: Option<Applied>,
+) -> Result<Applied, VPNError> {
+    let previous = match current {
+        Some(Applied::NetworkSetup { previous }) => previous,
+        _ => {
+            let services = run_with_stdin("networksetup", &["-listallnetworkservices"], "")?;
+            parse_network_services(&services)
+                .into_iter()
+                .map(|service| {
+                    let output = run_with_stdin("networksetup", &["-getdnsservers", &service], "")?;
+                    Ok((service, parse_dns_servers(&output)))
+                })
+                .collect::<Result<Vec<_>, VPNError>>()?
+        }
+    };
+
+    let addresses: Vec<String> = servers.iter().map(|ip| ip.to_string()).collect();
+    for (service, _) in &previous {
+        let mut args = vec!["-setdnsservers", service.as_str()];
+        args.extend(addresses.iter().map(String::as_str));
+        run_with_stdin("networksetup", &args, "")?;
+    }
+
+    Ok(Applied::NetworkSetup { previous })
+}
+
+#[cfg(target_os = "macos")]
+fn uninstall(state: Applied) -> Result<(), VPNError> {
+    let Applied::NetworkSetup { previous } = state;
+    for (service, servers) in previous {
+        let mut args = vec!["-setdnsservers", service.as_str()];
+        if servers.is_empty() {
+            // "Empty" hands DNS back to DHCP
+            args.push("Empty");
+        } else {
+            args.extend(servers.iter().map(String::as_str));
+        }
+        run_with_stdin("networksetup", &args, "")?;
+    }
+    Ok(())
+}
+
+/// Services from `networksetup -listallnetworkservices`. The first line is a
+/// legend and disabled services are prefixed with `*`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_network_services(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('*'))
+        .map(String::from)
+        .collect()
+}
+
+/// Servers from `networksetup -getdnsservers`, which prints a sentence instead
+/// when the service uses DHCP-provided DNS
+#[cfg(any(target_os = "macos", test))]
+fn parse_dns_servers(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.parse::<IpAddr>().is_ok())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn install(
+    interface: &str,
+    servers: &[IpAddr],
+    _current: Option<Applied>,
+) -> Result<Applied, VPNError> {
+    let name = format!("name={}", interface);
+
+    for (family, v4) in [("ipv4", true), ("ipv6", false)] {
+        let family_servers: Vec<String> = servers
+            .iter()
+            .filter(|ip| ip.is_ipv4() == v4)
+            .map(|ip| ip.to_string())
+            .collect();
+
+        for (i, server) in family_servers.iter().enumerate() {
+            if i == 0 {
+                run_with_stdin(
+                    "netsh",
+                    &[
+                        "interface",
+                        family,
+                        "set",
+                        "dnsservers",
+                        &name,
+                        "static",
+                        server,
+                        "primary",
+                        "validate=no",
+                    ],
+                    "",
+                )?;
+            } else {
+                run_with_stdin(
+                    "netsh",
+                    &[
+                        "interface",
+                        family,
+                        "add",
+                        "dnsservers",
+                        &name,
+                        server,
+                        &format!("index={}", i + 1),
+                        "validate=no",
+                    ],
+                    "",
+                )?;
+            }
+        }
+    }
+
+    // Windows asks every interface's resolvers, preferring the lowest metric
+    run_with_stdin(
+        "netsh",
+        &[
+            "interface",
+            "ipv4",
+            "set",
+            "interface",
+            interface,
+            "metric=1",
+        ],
+        "",
+    )?;
+
+    Ok(Applied::Netsh {
+        interface: interface.to_string(),
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn uninstall(state: Applied) -> Result<(), VPNError> {
+    let Applied::Netsh { interface } = state;
+    // The adapter usually disappears with the tunnel; this covers the case it doesn't
+    let _ = run_with_stdin(
+        "netsh",
+        &[
+            "interface",
+            "ipv4",
+            "set",
+            "dnsservers",
+            &format!("name={}", interface),
+            "source=dhcp",
+        ],
+        "",
+    );
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn install(
+    _interface: &str,
+    _servers: &[IpAddr],
+    _current: Option<Applied>,
+) -> Result<Applied, VPNError> {
+    Err(VPNError::TunnelError(
+        "DNS leak protection unsupported on this platform".to_string(),
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn uninstall(_state: Applied) -> Result<(), VPNError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolv_conf_lists_tunnel_servers() {
+        let servers: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap(), "8.8.8.8".parse().unwrap()];
+        assert_eq!(
+            resolv_conf(&servers),
+            "# Generated by Jeju VPN\nnameserver 1.1.1.1\nnameserver 8.8.8.8\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_networksetup_output() {
+        let services = "An asterisk (*) denotes that a network service is disabled.\n\
+                        Wi-Fi\n\
+                        *Bluetooth PAN\n\
+                        USB 10/100/1000 LAN\n";
+        assert_eq!(
+            parse_network_services(services),
+            vec!["Wi-Fi".to_string(), "USB 10/100/1000 LAN".to_string()]
+        );
+
+        assert!(parse_dns_servers("There aren't any DNS Servers set on Wi-Fi.\n").is_empty());
+        assert_eq!(
+            parse_dns_servers("192.168.1.1\nfe80::1\n"),
+            vec!["192.168.1.1".to_string(), "fe80::1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_resolvers_leak() {
+        let tunnel: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap()];
+
+        assert!(!resolvers_leak(&["1.1.1.1".parse().unwrap()], &tunnel));
+        assert!(resolvers_leak(
+            &["1.1.1.1".parse().unwrap(), "192.168.1.1".parse().unwrap()],
+            &tunnel
+        ));
+        // A stub we can't see past isn't vouched for
+        assert!(resolvers_leak(&["127.0.0.1".parse().unwrap()], &tunnel));
+    }
+
+    const RESOLVED_CONNECTED: &str = "Global
+       Protocols: +LLMNR +mDNS -DNSOverTLS DNSSEC=no/unsupported
+resolv.conf mode: stub
+
+Link 2 (enp3s0)
+    Current Scopes: DNS LLMNR/IPv4
+         Protocols: +DefaultRoute +LLMNR -mDNS -DNSOverTLS DNSSEC=no/unsupported
+Current DNS Server: 192.168.1.1
+       DNS Servers: 192.168.1.1 fe80::1%2
+        DNS Domain: lan
+
+Link 7 (jeju0)
+    Current Scopes: DNS
+         Protocols: +DefaultRoute -LLMNR -mDNS -DNSOverTLS DNSSEC=no/unsupported
+Current DNS Server: 1.1.1.1
+       DNS Servers: 1.1.1.1#one.one.one.one
+                    2606:4700:4700::1111
+        DNS Domain: ~.
+";
+
+    #[test]
+    fn test_parse_resolvectl_status() {
+        let scopes = parse_resolvectl_status(RESOLVED_CONNECTED);
+        assert_eq!(scopes.len(), 3);
+        assert!(scopes[0].default_route && scopes[0].servers.is_empty());
+        assert_eq!(
+            scopes[1].servers,
+            vec![
+                "192.168.1.1".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            scopes[2].servers,
+            vec![
+                "1.1.1.1".parse::<IpAddr>().unwrap(),
+                "2606:4700:4700::1111".parse().unwrap()
+            ]
+        );
+        assert_eq!(scopes[2].domains, vec!["~.".to_string()]);
+    }
+
+    #[test]
+    fn test_stub_judged_by_the_upstream_that_answers() {
+        let stub: Vec<IpAddr> = vec!["127.0.0.53".parse().unwrap()];
+        let tunnel: Vec<IpAddr> = vec![
+            "1.1.1.1".parse().unwrap(),
+            "2606:4700:4700::1111".parse().unwrap(),
+        ];
+
+        // The tunnel link claims every domain, so the LAN resolver is never asked
+        let upstreams = routed_servers(&parse_resolvectl_status(RESOLVED_CONNECTED));
+        let resolvers = expand_stubs(&stub, Some(&upstreams));
+        assert_eq!(resolvers, tunnel);
+        assert!(!resolvers_leak(&resolvers, &tunnel));
+
+        // Without the catch-all, resolved still asks the LAN resolver
+        let leaky = RESOLVED_CONNECTED.replace("DNS Domain: ~.", "DNS Domain: vpn");
+        let upstreams = routed_servers(&parse_resolvectl_status(&leaky));
+        let resolvers = expand_stubs(&stub, Some(&upstreams));
+        assert!(resolvers.contains(&"192.168.1.1".parse().unwrap()));
+        assert!(resolvers_leak(&resolvers, &tunnel));
+
+        // Older resolved reports the default route on its own line
+        let old = "Link 3 (eth0)\n      Current Scopes: DNS\nDefaultRoute setting: yes\n         DNS Servers: 192.168.1.1\n";
+        assert_eq!(
+            routed_servers(&parse_resolvectl_status(old)),
+            vec!["192.168.1.1".parse::<IpAddr>().unwrap()]
+        );
+
+        // A stub resolved won't describe stays in, and counts as a leak
+        assert_eq!(expand_stubs(&stub, None), stub);
+        assert!(resolvers_leak(&expand_stubs(&stub, None), &tunnel));
+    }
+}
@@ -0,0 +1,589 @@
+//! Kill switch: firewall rules that stop traffic from leaving outside the tunnel
+//!
+//! While connected, outbound traffic is only allowed over loopback, through the
+//! tunnel interface, to the WireGuard endpoint itself, and to any destinations
+//! split tunneling deliberately routes outside the tunnel. If the tunnel drops
+//! unexpectedly the rules are narrowed to loopback only until the user reconnects
+//! or disconnects.
+//!
+//! - Linux: an nftables table (`inet jeju_killswitch`) with a drop-policy output chain
+//! - macOS: a pf anchor under `com.apple/`, which the stock pf.conf already evaluates
+//! - Windows: WFP filters in a dynamic session, removed when the engine handle closes
+
+use super::{Cidr, VPNError};
+use parking_lot::Mutex;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// What the kill switch allows while the tunnel is up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KillSwitchRules {
+    /// WireGuard peer endpoint
+    pub endpoint: SocketAddr,
+    /// Tunnel interface name
+    pub tunnel_interface: String,
+    /// Address assigned to the tunnel interface
+    pub tunnel_address: Ipv4Addr,
+    /// IPv6 address assigned to the tunnel interface, for dual-stack tunnels
+    pub tunnel_address_v6: Option<Ipv6Addr>,
+    /// Destinations split tunneling sends outside the tunnel
+    pub bypass: Vec<Cidr>,
+}
+
+/// Current kill switch enforcement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KillSwitchMode {
+    /// No rules installed
+    Off,
+    /// Only tunnel traffic allowed
+    Tunnel(KillSwitchRules),
+    /// Tunnel dropped; everything but loopback blocked
+    Blocking,
+}
+
+/// Installs and removes kill switch firewall rules
+pub struct KillSwitch {
+    mode: Mutex<KillSwitchMode>,
+    #[cfg(target_os = "macos")]
+    pf_token: Mutex<Option<String>>,
+    #[cfg(target_os = "windows")]
+    engine: Mutex<Option<wfp::Engine>>,
+}
+
+impl KillSwitch {
+    pub fn new() -> Self {
+        Self {
+            mode: Mutex::new(KillSwitchMode::Off),
+            #[cfg(target_os = "macos")]
+            pf_token: Mutex::new(None),
+            #[cfg(target_os = "windows")]
+            engine: Mutex::new(None),
+        }
+    }
+
+    /// Current enforcement mode
+    pub fn mode(&self) -> KillSwitchMode {
+        self.mode.lock().clone()
+    }
+
+    /// Allow only traffic through the tunnel and to its endpoint
+    pub fn engage(&self, rules: &KillSwitchRules) -> Result<(), VPNError> {
+        tracing::info!(
+            "Kill switch engaged: allowing {} and {} only",
+            rules.endpoint,
+            rules.tunnel_interface
+        );
+        self.install(Some(rules))?;
+        *self.mode.lock() = KillSwitchMode::Tunnel(rules.clone());
+        Ok(())
+    }
+
+    /// Block all non-loopback traffic (tunnel is down)
+    pub fn block_all(&self) -> Result<(), VPNError> {
+        tracing::warn!("Kill switch blocking all traffic: tunnel is down");
+        self.install(None)?;
+        *self.mode.lock() = KillSwitchMode::Blocking;
+        Ok(())
+    }
+
+    /// Remove all kill switch rules
+    pub fn disengage(&self) -> Result<(), VPNError> {
+        if *self.mode.lock() == KillSwitchMode::Off {
+            return Ok(());
+        }
+        tracing::info!("Kill switch disengaged");
+        self.uninstall()?;
+        *self.mode.lock() = KillSwitchMode::Off;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn install(&self, rules: Option<&KillSwitchRules>) -> Result<(), VPNError> {
+        run_with_stdin("nft", &["-f", "-"], &nft_ruleset(rules)).map(|_| ())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn uninstall(&self) -> Result<(), VPNError> {
+        run_with_stdin("nft", &["delete", "table", "inet", NFT_TABLE], "").map(|_| ())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn install(&self, rules: Option<&KillSwitchRules>) -> Result<(), VPNError> {
+        run_with_stdin("pfctl", &["-a", PF_ANCHOR, "-f", "-"], &pf_ruleset(rules))?;
+
+        // Take a reference on pf so it stays enabled while we need it
+        let mut token = self.pf_token.lock();
+        if token.is_none() {
+            let output = run_with_stdin("pfctl", &["-E"], "")?;
+            *token = output
+                .lines()
+                .find_map(|line| line.strip_prefix("Token : "))
+                .map(|t| t.trim().to_string());
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn uninstall(&self) -> Result<(), VPNError> {
+        run_with_stdin("pfctl", &["-a", PF_ANCHOR, "-F", "all"], "")?;
+        if let Some(token) = self.pf_token.lock().take() {
+            run_with_stdin("pfctl", &["-X", &token], "")?;
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn install(&self, rules: Option<&KillSwitchRules>) -> Result<(), VPNError> {
+        let mut engine = self.engine.lock();
+        // Closing the previous dynamic session removes its filters
+        *engine = None;
+        *engine = Some(wfp::install(rules)?);
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn uninstall(&self) -> Result<(), VPNError> {
+        *self.engine.lock() = None;
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    fn install(&self, _rules: Option<&KillSwitchRules>) -> Result<(), VPNError> {
+        Err(VPNError::TunnelError(
+            "Kill switch unsupported on this platform".to_string(),
+        ))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    fn uninstall(&self) -> Result<(), VPNError> {
+        Ok(())
+    }
+}
+
+impl Default for KillSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for KillSwitch {
+    fn drop(&mut self) {
+        // Best-effort cleanup so exiting the app never leaves the host offline
+        let _ = self.disengage();
+    }
+}
+
+#[cfg(any(target_os = "linux", test))]
+const NFT_TABLE: &str = "jeju_killswitch";
+
+/// nftables script that atomically replaces the kill switch table
+#[cfg(any(target_os = "linux", test))]
+fn nft_ruleset(rules: Option<&KillSwitchRules>) -> String {
+    let mut accept = vec!["oifname \"lo\" accept".to_string()];
+    if let Some(rules) = rules {
+        let family = if rules.endpoint.is_ipv4() {
+            "ip"
+        } else {
+            "ip6"
+        };
+        accept.push(format!("oifname \"{}\" accept", rules.tunnel_interface));
+        accept.push(format!(
+            "{} daddr {} udp dport {} accept",
+            family,
+            rules.endpoint.ip(),
+            rules.endpoint.port()
+        ));
+        for cidr in &rules.bypass {
+            let family = if cidr.is_ipv4() { "ip" } else { "ip6" };
+            accept.push(format!("{} daddr {} accept", family, cidr));
+        }
+    }
+
+    format!(
+        "add table inet {table}\n\
+         delete table inet {table}\n\
+         table inet {table} {{\n\
+         \tchain output {{\n\
+         \t\ttype filter hook output priority 0; policy drop;\n\
+         {rules}\
+         \t}}\n\
+         }}\n",
+        table = NFT_TABLE,
+        rules = accept
+            .iter()
+            .map(|rule| format!("\t\t{}\n", rule))
+            .collect::<String>()
+    )
+}
+
+#[cfg(target_os = "macos")]
+const PF_ANCHOR: &str = "com.apple/250.JejuKillSwitch";
+
+/// pf rules for the kill switch anchor. utun names are kernel-assigned, so
+/// tunnel traffic is matched by its source address.
+#[cfg(any(target_os = "macos", test))]
+fn pf_ruleset(rules: Option<&KillSwitchRules>) -> String {
+    let mut ruleset = String::from("pass out quick on lo0 all\n");
+    if let Some(rules) = rules {
+        let family = if rules.endpoint.is_ipv4() {
+            "inet"
+        } else {
+            "inet6"
+        };
+        ruleset.push_str(&format!(
+            "pass out quick inet from {} to any\n",
+            rules.tunnel_address
+        ));
+        if let Some(address) = rules.tunnel_address_v6 {
+            ruleset.push_str(&format!("pass out quick inet6 from {} to any\n", address));
+        }
+        ruleset.push_str(&format!(
+            "pass out quick {} proto udp to {} port {}\n",
+            family,
+            rules.endpoint.ip(),
+            rules.endpoint.port()
+        ));
+        for cidr in &rules.bypass {
+            let family = if cidr.is_ipv4() { "inet" } else { "inet6" };
+            ruleset.push_str(&format!("pass out quick {} to {}\n", family, cidr));
+        }
+    }
+    ruleset.push_str("block drop out quick all\n");
+    ruleset
+}
+
+/// Run a system command, feeding `input` on stdin. Returns combined output.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+pub(super) fn run_with_stdin(
+    program: &str,
+    args: &[&str],
+    input: &str,
+) -> Result<String, VPNError> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| VPNError::TunnelError(format!("Failed to run {}: {}", program, e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| VPNError::TunnelError(format!("Failed to write to {}: {}", program, e)))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| VPNError::TunnelError(format!("Failed to run {}: {}", program, e)))?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    if !output.status.success() {
+        return Err(VPNError::TunnelError(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            text.trim()
+        )));
+    }
+    Ok(text)
+}
+
+#[cfg(target_os = "windows")]
+mod wfp {
+    use super::{KillSwitchRules, VPNError};
+    use std::net::IpAddr;
+    use windows::core::{GUID, PCWSTR};
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::NetworkManagement::IpHelper::ConvertInterfaceAliasToLuid;
+    use windows::Win32::NetworkManagement::Ndis::NET_LUID_LH;
+    use windows::Win32::NetworkManagement::WindowsFilteringPlatform::*;
+    use windows::Win32::System::Rpc::RPC_C_AUTHN_WINNT;
+
+    const IPPROTO_UDP: u8 = 17;
+
+    /// Open WFP engine handle; its dynamic session owns every filter we add
+    pub struct Engine(HANDLE);
+
+    // The handle is only used behind the kill switch mutex
+    unsafe impl Send for Engine {}
+
+    impl Drop for Engine {
+        fn drop(&mut self) {
+            unsafe {
+                FwpmEngineClose0(self.0);
+            }
+        }
+    }
+
+    fn check(status: u32, action: &str) -> Result<(), VPNError> {
+        if status != 0 {
+            return Err(VPNError::TunnelError(format!(
+                "WFP {} failed: 0x{:08x}",
+                action, status
+            )));
+        }
+        Ok(())
+    }
+
+    fn condition(
+        field: GUID,
+        match_type: FWP_MATCH_TYPE,
+        value: FWP_CONDITION_VALUE0,
+    ) -> FWPM_FILTER_CONDITION0 {
+        FWPM_FILTER_CONDITION0 {
+            fieldKey: field,
+            matchType: match_type,
+            conditionValue: value,
+        }
+    }
+
+    fn add_filter(
+        engine: HANDLE,
+        layer: GUID,
+        action: FWP_ACTION_TYPE,
+        weight: u8,
+        conditions: &mut [FWPM_FILTER_CONDITION0],
+    ) -> Result<(), VPNError> {
+        let mut name: Vec<u16> = "Jeju VPN kill switch\0".encode_utf16().collect();
+        let mut filter = FWPM_FILTER0::default();
+        filter.displayData.name = windows::core::PWSTR(name.as_mut_ptr());
+        filter.layerKey = layer;
+        filter.action.r#type = action;
+        filter.weight.r#type = FWP_UINT8;
+        filter.weight.Anonymous.uint8 = weight;
+        filter.numFilterConditions = conditions.len() as u32;
+        if !conditions.is_empty() {
+            filter.filterCondition = conditions.as_mut_ptr();
+        }
+
+        check(
+            unsafe { FwpmFilterAdd0(engine, &filter, None, None) },
+            "filter add",
+        )
+    }
+
+    /// Install block-by-default filters, permitting loopback and, when given, the tunnel
+    pub fn install(rules: Option<&KillSwitchRules>) -> Result<Engine, VPNError> {
+        let session = FWPM_SESSION0 {
+            flags: FWPM_SESSION_FLAG_DYNAMIC,
+            ..Default::default()
+        };
+
+        let mut handle = HANDLE::default();
+        check(
+            unsafe {
+                FwpmEngineOpen0(
+                    PCWSTR::null(),
+                    RPC_C_AUTHN_WINNT,
+                    None,
+                    Some(&session),
+                    &mut handle,
+                )
+            },
+            "engine open",
+        )?;
+        let engine = Engine(handle);
+
+        let mut luid = NET_LUID_LH::default();
+        if let Some(rules) = rules {
+            let alias: Vec<u16> = rules
+                .tunnel_interface
+                .encode_utf16()
+                .chain(Some(0))
+                .collect();
+            let status = unsafe { ConvertInterfaceAliasToLuid(PCWSTR(alias.as_ptr()), &mut luid) };
+            if status.0 != 0 {
+                return Err(VPNError::TunnelError(format!(
+                    "Tunnel interface {} not found",
+                    rules.tunnel_interface
+                )));
+            }
+        }
+
+        check(
+            unsafe { FwpmTransactionBegin0(handle, 0) },
+            "transaction begin",
+        )?;
+
+        for layer in [
+            FWPM_LAYER_ALE_AUTH_CONNECT_V4,
+            FWPM_LAYER_ALE_AUTH_CONNECT_V6,
+        ] {
+            add_filter(handle, layer, FWP_ACTION_BLOCK, 0, &mut [])?;
+
+            let mut loopback = [condition(
+                FWPM_CONDITION_FLAGS,
+                FWP_MATCH_FLAGS_ALL_SET,
+                FWP_CONDITION_VALUE0 {
+                    r#type: FWP_UINT32,
+                    Anonymous: FWP_CONDITION_VALUE0_0 {
+                        uint32: FWP_CONDITION_FLAG_IS_LOOPBACK,
+                    },
+                },
+            )];
+            add_filter(handle, layer, FWP_ACTION_PERMIT, 15, &mut loopback)?;
+
+            let Some(rules) = rules else {
+                continue;
+            };
+
+            let mut luid_value = unsafe { luid.Value };
+            let mut tunnel = [condition(
+                FWPM_CONDITION_IP_LOCAL_INTERFACE,
+                FWP_MATCH_EQUAL,
+                FWP_CONDITION_VALUE0 {
+                    r#type: FWP_UINT64,
+                    Anonymous: FWP_CONDITION_VALUE0_0 {
+                        uint64: &mut luid_value,
+                    },
+                },
+            )];
+            add_filter(handle, layer, FWP_ACTION_PERMIT, 15, &mut tunnel)?;
+
+            for cidr in &rules.bypass {
+                let mut mask_v4 = FWP_V4_ADDR_AND_MASK::default();
+                let mut mask_v6 = FWP_V6_ADDR_AND_MASK::default();
+                let value = match cidr.addr() {
+                    IpAddr::V4(ip) if layer == FWPM_LAYER_ALE_AUTH_CONNECT_V4 => {
+                        mask_v4.addr = u32::from(ip);
+                        mask_v4.mask = u32::MAX.checked_shl(32 - cidr.prefix() as u32).unwrap_or(0);
+                        FWP_CONDITION_VALUE0 {
+                            r#type: FWP_V4_ADDR_MASK,
+                            Anonymous: FWP_CONDITION_VALUE0_0 {
+                                v4AddrMask: &mut mask_v4,
+                            },
+                        }
+                    }
+                    IpAddr::V6(ip) if layer == FWPM_LAYER_ALE_AUTH_CONNECT_V6 => {
+                        mask_v6.addr = ip.octets();
+                        mask_v6.prefixLength = cidr.prefix();
+                        FWP_CONDITION_VALUE0 {
+                            r#type: FWP_V6_ADDR_MASK,
+                            Anonymous: FWP_CONDITION_VALUE0_0 {
+                                v6AddrMask: &mut mask_v6,
+                            },
+                        }
+                    }
+                    _ => continue,
+                };
+
+                let mut bypass = [condition(
+                    FWPM_CONDITION_IP_REMOTE_ADDRESS,
+                    FWP_MATCH_EQUAL,
+                    value,
+                )];
+                add_filter(handle, layer, FWP_ACTION_PERMIT, 15, &mut bypass)?;
+            }
+
+            let mut address_v6 = FWP_BYTE_ARRAY16::default();
+            let remote_address = match rules.endpoint.ip() {
+                IpAddr::V4(ip) if layer == FWPM_LAYER_ALE_AUTH_CONNECT_V4 => FWP_CONDITION_VALUE0 {
+                    r#type: FWP_UINT32,
+                    Anonymous: FWP_CONDITION_VALUE0_0 {
+                        uint32: u32::from(ip),
+                    },
+                },
+                IpAddr::V6(ip) if layer == FWPM_LAYER_ALE_AUTH_CONNECT_V6 => {
+                    address_v6.byteArray16 = ip.octets();
+                    FWP_CONDITION_VALUE0 {
+                        r#type: FWP_BYTE_ARRAY16_TYPE,
+                        Anonymous: FWP_CONDITION_VALUE0_0 {
+                            byteArray16: &mut address_v6,
+                        },
+                    }
+                }
+                _ => continue,
+            };
+
+            let mut endpoint = [
+                condition(
+                    FWPM_CONDITION_IP_REMOTE_ADDRESS,
+                    FWP_MATCH_EQUAL,
+                    remote_address,
+                ),
+                condition(
+                    FWPM_CONDITION_IP_REMOTE_PORT,
+                    FWP_MATCH_EQUAL,
+                    FWP_CONDITION_VALUE0 {
+                        r#type: FWP_UINT16,
+                        Anonymous: FWP_CONDITION_VALUE0_0 {
+                            uint16: rules.endpoint.port(),
+                        },
+                    },
+                ),
+                condition(
+                    FWPM_CONDITION_IP_PROTOCOL,
+                    FWP_MATCH_EQUAL,
+                    FWP_CONDITION_VALUE0 {
+                        r#type: FWP_UINT8,
+                        Anonymous: FWP_CONDITION_VALUE0_0 { uint8: IPPROTO_UDP },
+                    },
+                ),
+            ];
+            add_filter(handle, layer, FWP_ACTION_PERMIT, 15, &mut endpoint)?;
+        }
+
+        if let Err(e) = check(
+            unsafe { FwpmTransactionCommit0(handle) },
+            "transaction commit",
+        ) {
+            unsafe {
+                FwpmTransactionAbort0(handle);
+            }
+            return Err(e);
+        }
+
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> KillSwitchRules {
+        KillSwitchRules {
+            endpoint: "203.0.113.7:51820".parse().unwrap(),
+            tunnel_interface: "jeju0".to_string(),
+            tunnel_address: Ipv4Addr::new(10, 8, 0, 3),
+            tunnel_address_v6: Some("fd00:8::3".parse().unwrap()),
+            bypass: vec!["192.168.0.0/16".parse().unwrap()],
+        }
+    }
+
+    #[test]
+    fn test_nft_ruleset_allows_only_tunnel_and_endpoint() {
+        let ruleset = nft_ruleset(Some(&rules()));
+        assert!(ruleset.contains("policy drop;"));
+        assert!(ruleset.contains("oifname \"jeju0\" accept"));
+        assert!(ruleset.contains("ip daddr 203.0.113.7 udp dport 51820 accept"));
+        assert!(ruleset.contains("ip daddr 192.168.0.0/16 accept"));
+
+        let blocking = nft_ruleset(None);
+        assert!(blocking.contains("oifname \"lo\" accept"));
+        assert!(!blocking.contains("jeju0"));
+        assert!(!blocking.contains("dport"));
+    }
+
+    #[test]
+    fn test_pf_ruleset_blocks_last() {
+        let ruleset = pf_ruleset(Some(&rules()));
+        assert!(ruleset.contains("pass out quick inet from 10.8.0.3 to any"));
+        assert!(ruleset.contains("pass out quick inet6 from fd00:8::3 to any"));
+        assert!(ruleset.contains("proto udp to 203.0.113.7 port 51820"));
+        assert!(ruleset.contains("pass out quick inet to 192.168.0.0/16"));
+        assert!(ruleset.trim_end().ends_with("block drop out quick all"));
+
+        assert_eq!(
+            pf_ruleset(None),
+            "pass out quick on lo0 all\nblock drop out quick all\n"
+        );
+    }
+}
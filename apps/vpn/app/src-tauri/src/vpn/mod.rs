@@ -5,15 +5,31 @@
 //! - Cross-platform TUN interface management
 //! - Node discovery via WebSocket coordinator
 
+mod dns;
+mod kill_switch;
+mod multihop;
 mod node_discovery;
+mod public_ip;
+mod registry;
+mod split_tunnel;
+mod supervisor;
 mod tunnel;
 mod wireguard;
 
+pub use dns::*;
+pub use kill_switch::*;
+pub use multihop::*;
 pub use node_discovery::*;
+pub use public_ip::*;
+pub use registry::*;
+pub use split_tunnel::*;
+pub use supervisor::*;
 pub use tunnel::*;
 pub use wireguard::*;
 
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
 
 /// VPN connection status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -36,8 +52,32 @@ pub struct VPNNode {
     pub wireguard_pubkey: String,
     pub latency_ms: u32,
     pub load: u8,
+    /// Reputation used for selection: the on-chain value when known,
+    /// otherwise what the coordinator reported
     pub reputation: u8,
+    /// Reputation the coordinator reported, kept once the on-chain value replaces it
+    #[serde(default)]
+    pub reported_reputation: Option<u8>,
+    /// Reputation from the on-chain registry, `None` if the node isn't registered
+    #[serde(default)]
+    pub onchain_reputation: Option<u8>,
+    /// The coordinator's figure is far from the on-chain one; never selected
+    #[serde(default)]
+    pub reputation_mismatch: bool,
     pub capabilities: NodeCapabilities,
+    /// Tunnel address allocated to this client by the node's coordinator
+    #[serde(default)]
+    pub assigned_ip: Option<String>,
+    /// IPv6 tunnel address, if the node runs a dual-stack tunnel
+    #[serde(default)]
+    pub assigned_ipv6: Option<String>,
+    /// Base64 WireGuard pre-shared key the node issued for this client
+    #[serde(default)]
+    pub preshared_key: Option<String>,
+    /// Known only from the on-chain registry. Tunnel addresses come from the
+    /// coordinator, so the node can't be connected to while it is down.
+    #[serde(default)]
+    pub registry_only: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,9 +94,16 @@ pub struct NodeCapabilities {
 pub struct VPNConnection {
     pub connection_id: String,
     pub status: ConnectionStatus,
+    /// Node we connect to; the entry node for multi-hop connections
     pub node: VPNNode,
+    /// Second hop, where traffic leaves for the internet
+    #[serde(default)]
+    pub exit_node: Option<VPNNode>,
     pub connected_at: Option<u64>,
     pub local_ip: Option<String>,
+    /// Tunnel IPv6 address; `None` when the connection is IPv4-only
+    #[serde(default)]
+    pub local_ipv6: Option<String>,
     pub public_ip: Option<String>,
     pub bytes_up: u64,
     pub bytes_down: u64,
@@ -66,12 +113,29 @@ pub struct VPNConnection {
 /// Connection statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionStats {
+    /// User data through the tunnel interface
     pub bytes_up: u64,
     pub bytes_down: u64,
     pub packets_up: u64,
     pub packets_down: u64,
+    /// WireGuard framing, handshakes and keepalives on top of the user data
+    pub overhead_up: u64,
+    pub overhead_down: u64,
     pub connected_seconds: u64,
     pub latency_ms: u32,
+    /// MTU of the tunnel interface, after clamping and multi-hop overhead
+    pub mtu: u16,
+    /// One entry per node the traffic crosses, entry first
+    pub hops: Vec<HopStats>,
+}
+
+/// Traffic through one node of a connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HopStats {
+    pub node_id: String,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    pub latency_ms: u32,
 }
 
 /// VPN Manager - handles all VPN operations
@@ -94,6 +158,33 @@ pub struct VPNManager {
     /// Our keypair (persisted across connections)
     private_key: String,
     public_key: String,
+
+    /// Firewall rules that block traffic outside the tunnel
+    kill_switch: Arc<KillSwitch>,
+    kill_switch_enabled: bool,
+    kill_switch_rules: Option<KillSwitchRules>,
+
+    /// Auto-reconnect behaviour used by the supervisor
+    reconnect_policy: ReconnectPolicy,
+
+    /// Which destinations are routed through the tunnel
+    split_tunnel: SplitTunnelConfig,
+
+    /// Public IP seen before the tunnel came up, to confirm egress changed
+    direct_ip: Option<IpAddr>,
+
+    /// Resolvers used inside the tunnel
+    dns_servers: Vec<IpAddr>,
+
+    /// Tunnel MTU override, for networks that fragment at the default
+    mtu: Option<u16>,
+
+    /// Carry IPv6 as well as IPv4 when the node offers it
+    ipv6_enabled: bool,
+
+    /// Points the system resolvers at `dns_servers` while connected
+    dns_guard: DnsGuard,
+    dns_leak_protection: bool,
 }
 
 impl VPNManager {
@@ -109,7 +200,83 @@ impl VPNManager {
             selected_node_id: None,
             private_key,
             public_key,
+            kill_switch: Arc::new(KillSwitch::new()),
+            kill_switch_enabled: false,
+            kill_switch_rules: None,
+            reconnect_policy: ReconnectPolicy::default(),
+            split_tunnel: SplitTunnelConfig::default(),
+            direct_ip: None,
+            dns_servers: vec![
+                IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+            ],
+            mtu: None,
+            ipv6_enabled: true,
+            dns_guard: DnsGuard::new(),
+            dns_leak_protection: true,
+        }
+    }
+
+    /// Use `discovery` to find nodes
+    pub fn with_discovery(mut self, discovery: NodeDiscovery) -> Self {
+        self.discovery = discovery;
+        self
+    }
+
+    /// Set whether the kill switch is used for new connections
+    pub fn with_kill_switch(mut self, enabled: bool) -> Self {
+        self.kill_switch_enabled = enabled;
+        self
+    }
+
+    /// Set the resolvers used inside the tunnel. Invalid addresses are skipped.
+    pub fn with_dns_servers(mut self, servers: &[String]) -> Self {
+        let servers: Vec<IpAddr> = servers
+            .iter()
+            .filter_map(|s| match s.parse() {
+                Ok(ip) => Some(ip),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid DNS server: {}", s);
+                    None
+                }
+            })
+            .collect();
+        if !servers.is_empty() {
+            self.dns_servers = servers;
         }
+        self
+    }
+
+    /// Set the tunnel MTU for new connections; `None` uses `TUNNEL_MTU`
+    pub fn with_mtu(mut self, mtu: Option<u16>) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Set whether new connections use IPv6 when the node offers it. Off forces
+    /// IPv4-only tunnels; IPv6 destinations stay routed into the tunnel, so they
+    /// are dropped there instead of leaking around it.
+    pub fn with_ipv6(mut self, enabled: bool) -> Self {
+        self.ipv6_enabled = enabled;
+        self
+    }
+
+    /// Set whether system DNS is forced through the tunnel for new connections
+    pub fn with_dns_leak_protection(mut self, enabled: bool) -> Self {
+        self.dns_leak_protection = enabled;
+        self
+    }
+
+    /// Set the auto-reconnect behaviour
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// Set the split tunnel rules used for new connections
+    pub fn with_split_tunnel(mut self, split_tunnel: SplitTunnelConfig) -> Self {
+        self.split_tunnel = split_tunnel;
+        self
     }
 
     /// Get our public key (for sharing with peers)
@@ -123,47 +290,172 @@ impl VPNManager {
         let target_node = match node {
             Some(n) => n,
             None => {
-                // Use selected node or find best one
-                if let Some(ref id) = self.selected_node_id {
-                    self.nodes
-                        .iter()
-                        .find(|n| n.node_id == *id)
-                        .cloned()
-                        .ok_or(VPNError::NoNodeSelected)?
-                } else {
-                    self.find_best_node().await?
+                // Use selected node while it's still around, else find the best one
+                match self.selected_node().await? {
+                    Some(n) => n,
+                    None => self.find_best_node().await?,
                 }
             }
         };
 
-        tracing::info!(
-            "Connecting to VPN node: {} ({})",
-            target_node.node_id,
-            target_node.country_code
-        );
+        self.establish(target_node, None).await
+    }
+
+    /// Connect through `entry` to `exit`, so neither node sees both our IP and
+    /// our destinations. See the `multihop` module for the throughput cost.
+    pub async fn connect_multihop(
+        &mut self,
+        entry: VPNNode,
+        exit: VPNNode,
+    ) -> Result<VPNConnection, VPNError> {
+        if entry.node_id == exit.node_id {
+            return Err(VPNError::ConnectionFailed(
+                "Entry and exit must be different nodes".to_string(),
+            ));
+        }
+        if !exit.capabilities.is_vpn_exit {
+            return Err(VPNError::ConnectionFailed(format!(
+                "Node {} is not a VPN exit",
+                exit.node_id
+            )));
+        }
+
+        self.establish(entry, Some(exit)).await
+    }
+
+    /// Bring up a tunnel to `target_node`, nesting a second one to `exit_node` if given
+    async fn establish(
+        &mut self,
+        mut target_node: VPNNode,
+        mut exit_node: Option<VPNNode>,
+    ) -> Result<VPNConnection, VPNError> {
+        match &exit_node {
+            Some(exit) => tracing::info!(
+                "Connecting to VPN exit {} ({}) via {} ({})",
+                exit.node_id,
+                exit.country_code,
+                target_node.node_id,
+                target_node.country_code
+            ),
+            None => tracing::info!(
+                "Connecting to VPN node: {} ({})",
+                target_node.node_id,
+                target_node.country_code
+            ),
+        }
+
+        let assignment = self.assignment(&mut target_node).await?;
+        let exit_hop = match &mut exit_node {
+            Some(exit) => {
+                let exit_assignment = self.assignment(exit).await?;
+                Some(ExitHop {
+                    peer_pubkey: exit.wireguard_pubkey.clone(),
+                    endpoint: exit.endpoint.clone(),
+                    assigned_ip: exit_assignment.assigned_ip,
+                    assigned_ipv6: exit_assignment.assigned_ipv6,
+                    preshared_key: exit_assignment.preshared_key,
+                })
+            }
+            None => None,
+        };
+        // The interface carries the innermost tunnel's address
+        let (tunnel_address, tunnel_address_v6) = match &exit_hop {
+            Some(hop) => (&hop.assigned_ip, &hop.assigned_ipv6),
+            None => (&assignment.assigned_ip, &assignment.assigned_ipv6),
+        };
+
+        let endpoint = target_node
+            .endpoint
+            .parse::<SocketAddr>()
+            .map_err(|e| VPNError::TunnelError(format!("Invalid endpoint: {}", e)))?;
+        let allowed_ips = self
+            .split_tunnel
+            .tunneled(endpoint.ip())?
+            .iter()
+            .map(|cidr| cidr.to_string())
+            .collect();
+
+        let kill_switch_rules = KillSwitchRules {
+            endpoint,
+            tunnel_interface: TunConfig::default().name,
+            tunnel_address: tunnel_address
+                .parse::<Ipv4Addr>()
+                .map_err(|e| VPNError::TunnelError(format!("Invalid assigned IP: {}", e)))?,
+            tunnel_address_v6: tunnel_address_v6
+                .as_deref()
+                .map(str::parse::<Ipv6Addr>)
+                .transpose()
+                .map_err(|e| VPNError::TunnelError(format!("Invalid assigned IPv6: {}", e)))?,
+            bypass: self.split_tunnel.bypassed()?,
+        };
 
         // Create WireGuard config using our persistent private key
         let wg_config = WireGuardConfig {
             private_key: self.private_key.clone(),
             peer_pubkey: target_node.wireguard_pubkey.clone(),
             endpoint: target_node.endpoint.clone(),
-            allowed_ips: vec!["0.0.0.0/0".to_string(), "::/0".to_string()],
-            dns: vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()],
+            allowed_ips,
+            dns: self.dns_servers.iter().map(|ip| ip.to_string()).collect(),
             keepalive: 25,
+            mtu: self.mtu,
+            assigned_ip: Some(assignment.assigned_ip),
+            assigned_ipv6: assignment.assigned_ipv6,
+            preshared_key: assignment.preshared_key,
+            exit_hop,
         };
 
+        // Remember our direct IP while traffic can still leave outside the tunnel.
+        // Skipped on reconnect, where the kill switch may already be blocking it.
+        if self.connection.is_none() {
+            self.direct_ip = match fetch_public_ip(None).await {
+                Ok(ip) => Some(ip),
+                Err(e) => {
+                    tracing::debug!("Could not determine pre-connect IP: {}", e);
+                    None
+                }
+            };
+        }
+
+        // Lock traffic to the endpoint before any packet leaves
+        if self.kill_switch_enabled {
+            self.kill_switch.engage(&kill_switch_rules)?;
+        }
+
         // Create and start tunnel using boringtun
         let mut tunnel = WireGuardTunnel::new(wg_config).await?;
-        tunnel.start().await?;
+        if self.kill_switch_enabled {
+            tunnel.set_kill_switch(self.kill_switch.clone());
+        }
+        if let Err(e) = tunnel.start().await {
+            self.abort_connect();
+            return Err(e);
+        }
+
+        if self.dns_leak_protection {
+            if let Err(e) = self
+                .dns_guard
+                .apply(&kill_switch_rules.tunnel_interface, &self.dns_servers)
+            {
+                let _ = tunnel.stop().await;
+                self.abort_connect();
+                return Err(e);
+            }
+        }
 
-        // Get assigned IP
+        // Get assigned IPs
         let local_ip = tunnel.get_local_ip().await?;
+        let local_ipv6 = tunnel.get_local_ipv6().await;
+
+        // Measured per node, so a chain's latency is the sum of its hops
+        let latency_ms =
+            target_node.latency_ms + exit_node.as_ref().map_or(0, |exit| exit.latency_ms);
 
         // Create connection
         let connection = VPNConnection {
             connection_id: uuid::Uuid::new_v4().to_string(),
             status: ConnectionStatus::Connected,
             node: target_node,
+            exit_node,
             connected_at: Some(
                 std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
@@ -171,30 +463,255 @@ impl VPNManager {
                     .as_secs(),
             ),
             local_ip: Some(local_ip),
-            public_ip: None, // Will be fetched async
+            local_ipv6,
+            public_ip: None, // Filled in by refresh_public_ip
             bytes_up: 0,
             bytes_down: 0,
-            latency_ms: 0,
+            latency_ms,
         };
 
         self.tunnel = Some(tunnel);
         self.connection = Some(connection.clone());
+        self.kill_switch_rules = Some(kill_switch_rules);
 
         tracing::info!("VPN connected successfully via boringtun");
         Ok(connection)
     }
 
+    /// Tunnel addresses and pre-shared key for `node`: the ones it carries, else
+    /// a fresh allocation from the coordinator, which is kept on `node` so the
+    /// connection can be re-established without asking again. Never guess an address.
+    async fn assignment(&self, node: &mut VPNNode) -> Result<AddressAssignment, VPNError> {
+        let mut assignment = match node.assigned_ip.clone() {
+            Some(assigned_ip) => AddressAssignment {
+                assigned_ip,
+                assigned_ipv6: node.assigned_ipv6.clone(),
+                preshared_key: node.preshared_key.clone(),
+            },
+            None => {
+                let assignment = self
+                    .discovery
+                    .request_address(&node.node_id, &self.public_key)
+                    .await?;
+                node.assigned_ip = Some(assignment.assigned_ip.clone());
+                node.assigned_ipv6 = assignment.assigned_ipv6.clone();
+                node.preshared_key = assignment.preshared_key.clone();
+                assignment
+            }
+        };
+        if !self.ipv6_enabled {
+            assignment.assigned_ipv6 = None;
+        }
+        Ok(assignment)
+    }
+
+    /// Undo protections set up by a connect attempt that failed
+    fn abort_connect(&self) {
+        if self.connection.is_some() {
+            // Reconnecting: stay locked down until a tunnel is back up
+            if self.kill_switch_enabled {
+                let _ = self.kill_switch.block_all();
+            }
+        } else {
+            // Never connected, so there's nothing to protect
+            let _ = self.kill_switch.disengage();
+            let _ = self.dns_guard.restore();
+        }
+    }
+
     /// Disconnect from VPN
     pub async fn disconnect(&mut self) -> Result<(), VPNError> {
         if let Some(mut tunnel) = self.tunnel.take() {
             tunnel.stop().await?;
         }
 
+        self.dns_guard.restore()?;
+        self.kill_switch.disengage()?;
+        self.kill_switch_rules = None;
         self.connection = None;
         tracing::info!("VPN disconnected");
         Ok(())
     }
 
+    /// Replace the tunnel for the current connection, keeping the kill switch engaged.
+    ///
+    /// With `failover`, the next-best node other than the current one is tried.
+    /// Multi-hop connections keep their exit node and only fail over the entry.
+    ///
+    /// Once the old tunnel is gone the kill switch leaves no route to the
+    /// coordinator, so the new tunnel must not need it: the current nodes keep
+    /// the addresses and pre-shared keys they were assigned, and a failover
+    /// node is only used if its allocation succeeds before the teardown.
+    pub async fn reconnect(&mut self, failover: bool) -> Result<VPNConnection, VPNError> {
+        let previous = self.connection.clone().ok_or(VPNError::NotConnected)?;
+
+        let entry = if failover {
+            self.failover_node(&previous).await
+        } else {
+            previous.node.clone()
+        };
+
+        if let Some(mut tunnel) = self.tunnel.take() {
+            let _ = tunnel.stop().await;
+        }
+
+        self.establish(entry, previous.exit_node).await
+    }
+
+    /// Next-best entry node for `previous`, with its address already allocated.
+    /// Falls back to the current entry if there is none or allocation fails.
+    async fn failover_node(&mut self, previous: &VPNConnection) -> VPNNode {
+        let exit_id = previous
+            .exit_node
+            .as_ref()
+            .map(|exit| exit.node_id.as_str());
+        match self.next_best_node(&previous.node.node_id).await {
+            Ok(mut node) if Some(node.node_id.as_str()) != exit_id => {
+                match self.assignment(&mut node).await {
+                    Ok(_) => return node,
+                    Err(e) => tracing::warn!("Not failing over to {}: {}", node.node_id, e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("No failover node: {}", e),
+        }
+        previous.node.clone()
+    }
+
+    /// Whether the current connection's tunnel has failed underneath it
+    pub async fn tunnel_failed(&self) -> bool {
+        if self.get_status() != ConnectionStatus::Connected {
+            return false;
+        }
+        match &self.tunnel {
+            Some(tunnel) => matches!(
+                tunnel.get_state().await,
+                TunnelState::Error | TunnelState::Stopped
+            ),
+            None => true,
+        }
+    }
+
+    /// Mark the connection as reconnecting. Returns its node, or `None` if disconnected.
+    pub fn begin_reconnect(&mut self) -> Option<String> {
+        let connection = self.connection.as_mut()?;
+        connection.status = ConnectionStatus::Reconnecting;
+        Some(connection.node.node_id.clone())
+    }
+
+    /// Mark the connection as failed. Returns false if there is no connection.
+    pub fn mark_failed(&mut self) -> bool {
+        match self.connection.as_mut() {
+            Some(connection) => {
+                connection.status = ConnectionStatus::Error;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the split tunnel rules, re-establishing the tunnel if connected
+    pub async fn set_split_tunnel(
+        &mut self,
+        split_tunnel: SplitTunnelConfig,
+    ) -> Result<(), VPNError> {
+        split_tunnel.validate()?;
+        self.split_tunnel = split_tunnel;
+
+        if self.get_status() == ConnectionStatus::Connected {
+            self.reconnect(false).await?;
+        }
+        Ok(())
+    }
+
+    /// Change the tunnel MTU, reconnecting so the interface picks it up
+    pub async fn set_mtu(&mut self, mtu: Option<u16>) -> Result<(), VPNError> {
+        self.mtu = mtu;
+
+        if self.get_status() == ConnectionStatus::Connected {
+            self.reconnect(false).await?;
+        }
+        Ok(())
+    }
+
+    /// Switch between dual-stack and IPv4-only, reconnecting so the interface
+    /// picks it up
+    pub async fn set_ipv6_enabled(&mut self, enabled: bool) -> Result<(), VPNError> {
+        self.ipv6_enabled = enabled;
+
+        if self.get_status() == ConnectionStatus::Connected {
+            self.reconnect(false).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether new connections use IPv6 when the node offers it
+    pub fn ipv6_enabled(&self) -> bool {
+        self.ipv6_enabled
+    }
+
+    /// Current split tunnel rules
+    pub fn split_tunnel(&self) -> &SplitTunnelConfig {
+        &self.split_tunnel
+    }
+
+    /// Auto-reconnect behaviour
+    pub fn reconnect_policy(&self) -> &ReconnectPolicy {
+        &self.reconnect_policy
+    }
+
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy;
+    }
+
+    /// Enable or disable the kill switch, applying it to the current connection
+    pub fn set_kill_switch_enabled(&mut self, enabled: bool) -> Result<(), VPNError> {
+        self.kill_switch_enabled = enabled;
+
+        if !enabled {
+            return self.kill_switch.disengage();
+        }
+
+        match (&self.kill_switch_rules, self.tunnel.as_mut()) {
+            (Some(rules), Some(tunnel)) => {
+                tunnel.set_kill_switch(self.kill_switch.clone());
+                self.kill_switch.engage(rules)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Enable or disable DNS leak protection, applying it to the current connection
+    pub fn set_dns_leak_protection(&mut self, enabled: bool) -> Result<(), VPNError> {
+        self.dns_leak_protection = enabled;
+
+        if !enabled {
+            return self.dns_guard.restore();
+        }
+
+        match &self.kill_switch_rules {
+            Some(rules) if self.tunnel.is_some() => self
+                .dns_guard
+                .apply(&rules.tunnel_interface, &self.dns_servers),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolvers used inside the tunnel
+    pub fn dns_servers(&self) -> &[IpAddr] {
+        &self.dns_servers
+    }
+
+    /// Whether the system DNS is currently forced through the tunnel
+    pub fn dns_leak_protection_active(&self) -> bool {
+        self.dns_guard.is_active()
+    }
+
+    /// Current kill switch enforcement
+    pub fn kill_switch_mode(&self) -> KillSwitchMode {
+        self.kill_switch.mode()
+    }
+
     /// Get current connection status
     pub fn get_status(&self) -> ConnectionStatus {
         self.connection
@@ -208,6 +725,22 @@ impl VPNManager {
         self.connection.as_ref()
     }
 
+    /// Public IP seen before the current connection was established
+    pub fn direct_ip(&self) -> Option<IpAddr> {
+        self.direct_ip
+    }
+
+    /// Record the exit IP for `connection_id`. Returns false if that connection is gone.
+    pub fn set_public_ip(&mut self, connection_id: &str, public_ip: String) -> bool {
+        match self.connection.as_mut() {
+            Some(connection) if connection.connection_id == connection_id => {
+                connection.public_ip = Some(public_ip);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Get connection statistics
     pub async fn get_stats(&self) -> Option<ConnectionStats> {
         let conn = self.connection.as_ref()?;
@@ -215,6 +748,7 @@ impl VPNManager {
 
         let (bytes_up, bytes_down) = tunnel.get_transfer_stats().await.ok()?;
         let (packets_up, packets_down) = tunnel.get_packet_stats().await.ok()?;
+        let (overhead_up, overhead_down) = tunnel.get_overhead_stats().await.ok()?;
 
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -223,13 +757,33 @@ impl VPNManager {
 
         let connected_seconds = conn.connected_at.map(|t| now - t).unwrap_or(0);
 
+        let mut hops = vec![HopStats {
+            node_id: conn.node.node_id.clone(),
+            bytes_up,
+            bytes_down,
+            latency_ms: conn.node.latency_ms,
+        }];
+        if let (Some(exit), Some((up, down))) = (&conn.exit_node, tunnel.get_exit_hop_stats().await)
+        {
+            hops.push(HopStats {
+                node_id: exit.node_id.clone(),
+                bytes_up: up,
+                bytes_down: down,
+                latency_ms: exit.latency_ms,
+            });
+        }
+
         Some(ConnectionStats {
             bytes_up,
             bytes_down,
             packets_up,
             packets_down,
+            overhead_up,
+            overhead_down,
             connected_seconds,
             latency_ms: conn.latency_ms,
+            mtu: tunnel.mtu(),
+            hops,
         })
     }
 
@@ -238,34 +792,114 @@ impl VPNManager {
         self.selected_node_id = Some(node_id);
     }
 
-    /// Get available nodes
+    /// Forget the selected node
+    pub fn clear_selected_node(&mut self) {
+        self.selected_node_id = None;
+    }
+
+    /// Node the user selected, if any
+    pub fn selected_node_id(&self) -> Option<&str> {
+        self.selected_node_id.as_deref()
+    }
+
+    /// The selected node, or `None` if none is selected or discovery no longer lists it
+    async fn selected_node(&mut self) -> Result<Option<VPNNode>, VPNError> {
+        let Some(id) = self.selected_node_id.clone() else {
+            return Ok(None);
+        };
+
+        self.ensure_nodes().await?;
+        let node = self.nodes.iter().find(|n| n.node_id == id).cloned();
+        if node.is_none() {
+            tracing::warn!("Selected node {} is no longer available", id);
+        }
+        Ok(node)
+    }
+
+    /// Get available nodes. Only nodes not measured before are pinged; use
+    /// `refresh_latencies` to measure them all again.
     pub async fn get_nodes(
         &mut self,
         country_code: Option<String>,
     ) -> Result<Vec<VPNNode>, VPNError> {
-        // Refresh nodes from discovery
-        self.nodes = self
+        let mut nodes = self
             .discovery
             .discover_nodes(country_code.as_deref())
             .await?;
+        self.discovery
+            .measure_new_latencies(&mut nodes, &self.private_key)
+            .await;
+
+        let cached = self.discovery.cached_nodes().await;
+        self.nodes = if cached.is_empty() {
+            nodes.clone()
+        } else {
+            cached
+        };
+        Ok(nodes)
+    }
+
+    /// Measure every known node's latency again
+    pub async fn refresh_latencies(&mut self) -> Result<Vec<VPNNode>, VPNError> {
+        self.ensure_nodes().await?;
+        self.discovery
+            .measure_latencies(&mut self.nodes, &self.private_key)
+            .await;
         Ok(self.nodes.clone())
     }
 
-    /// Find best node based on latency and load
-    async fn find_best_node(&mut self) -> Result<VPNNode, VPNError> {
-        if self.nodes.is_empty() {
+    /// Bring the node list up to date with discovery's cache, which follows
+    /// coordinator updates, discovering nodes if nothing is known yet
+    async fn ensure_nodes(&mut self) -> Result<(), VPNError> {
+        let cached = self.discovery.cached_nodes().await;
+        if !cached.is_empty() {
+            self.nodes = cached;
+        } else if self.nodes.is_empty() {
             self.nodes = self.discovery.discover_nodes(None).await?;
         }
+        self.discovery
+            .measure_new_latencies(&mut self.nodes, &self.private_key)
+            .await;
+        Ok(())
+    }
+
+    /// Find best node based on latency, load and reputation
+    async fn find_best_node(&mut self) -> Result<VPNNode, VPNError> {
+        self.ensure_nodes().await?;
+
+        self.nodes
+            .iter()
+            .filter(|n| is_selectable(n))
+            .min_by_key(|n| selection_cost(n))
+            .cloned()
+            .ok_or(VPNError::NoNodesAvailable)
+    }
+
+    /// Best exit node other than `exclude_id`, for failover
+    async fn next_best_node(&mut self, exclude_id: &str) -> Result<VPNNode, VPNError> {
+        self.ensure_nodes().await?;
 
         self.nodes
             .iter()
-            .filter(|n| n.capabilities.is_vpn_exit)
-            .min_by_key(|n| n.latency_ms as u32 + n.load as u32 * 10)
+            .filter(|n| is_selectable(n) && n.node_id != exclude_id)
+            .min_by_key(|n| selection_cost(n))
             .cloned()
             .ok_or(VPNError::NoNodesAvailable)
     }
 }
 
+/// Exit nodes whose reputation the coordinator hasn't misreported, and that
+/// it can allocate an address on
+fn is_selectable(node: &VPNNode) -> bool {
+    node.capabilities.is_vpn_exit && !node.reputation_mismatch && !node.registry_only
+}
+
+/// Lower is better: latency, plus 10ms per load point and 5ms per point of
+/// reputation below 100
+fn selection_cost(node: &VPNNode) -> u32 {
+    node.latency_ms + node.load as u32 * 10 + (100 - node.reputation.min(100)) as u32 * 5
+}
+
 impl Default for VPNManager {
     fn default() -> Self {
         Self::new()
@@ -293,6 +927,97 @@ pub enum VPNError {
     #[error("Not connected")]
     NotConnected,
 
+    #[error("No tunnel IP assigned by node {0}")]
+    NoAssignedIp(String),
+
+    #[error("Node {0} needs the coordinator to assign a tunnel IP, and it is unreachable")]
+    CoordinatorUnavailable(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    fn assigned_node() -> VPNNode {
+        VPNNode {
+            node_id: "0x5555555555555555555555555555555555555555".to_string(),
+            operator: "0x6666666666666666666666666666666666666666".to_string(),
+            country_code: "DE".to_string(),
+            region: "eu-central-1".to_string(),
+            endpoint: "203.0.113.7:51820".to_string(),
+            wireguard_pubkey: "Z2VybWFueSB0ZXN0IGtleSBmb3IgdGVzdGluZw==".to_string(),
+            latency_ms: 35,
+            load: 55,
+            reputation: 92,
+            reported_reputation: None,
+            onchain_reputation: None,
+            reputation_mismatch: false,
+            capabilities: NodeCapabilities {
+                supports_wireguard: true,
+                supports_socks5: true,
+                supports_http: true,
+                serves_cdn: true,
+                is_vpn_exit: true,
+            },
+            assigned_ip: Some("10.8.0.2".to_string()),
+            assigned_ipv6: Some("fd00:8::2".to_string()),
+            preshared_key: Some("cHNrIGZvciB0aGlzIGNsaWVudCBvbmx5IDAwMDAwMDA=".to_string()),
+            registry_only: false,
+        }
+    }
+
+    #[test]
+    fn test_kept_assignment_needs_no_coordinator() {
+        let manager = VPNManager::new().with_ipv6(false);
+        let mut node = assigned_node();
+
+        let assignment = manager
+            .assignment(&mut node)
+            .now_or_never()
+            .expect("no coordinator round trip")
+            .unwrap();
+        assert_eq!(assignment.assigned_ip, "10.8.0.2");
+        assert_eq!(assignment.preshared_key, node.preshared_key);
+        // IPv4-only tunnels leave the IPv6 address unused but keep it on the node
+        assert_eq!(assignment.assigned_ipv6, None);
+        assert_eq!(node.assigned_ipv6.as_deref(), Some("fd00:8::2"));
+
+        // Without a kept assignment there's nothing to connect with
+        node.assigned_ip = None;
+        assert!(matches!(
+            manager.assignment(&mut node).now_or_never().unwrap(),
+            Err(VPNError::CoordinatorUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_failover_stays_put_without_an_allocation() {
+        let mut manager = VPNManager::new();
+        let previous = VPNConnection {
+            connection_id: "c1".to_string(),
+            status: ConnectionStatus::Reconnecting,
+            node: assigned_node(),
+            exit_node: None,
+            connected_at: Some(1),
+            local_ip: Some("10.8.0.2".to_string()),
+            local_ipv6: None,
+            public_ip: None,
+            bytes_up: 0,
+            bytes_down: 0,
+            latency_ms: 35,
+        };
+
+        // Other nodes are known, but the coordinator can't allocate on them
+        let node = manager
+            .failover_node(&previous)
+            .now_or_never()
+            .expect("no coordinator round trip");
+        assert!(!manager.nodes.is_empty());
+        assert_eq!(node.node_id, previous.node.node_id);
+        assert_eq!(node.preshared_key, previous.node.preshared_key);
+    }
+}
@@ -0,0 +1,315 @@
+//! Multi-hop (double VPN): a WireGuard tunnel nested inside another
+//!
+//! Packets from the TUN device are encrypted for the exit node, wrapped in an
+//! IPv4/UDP datagram addressed to the exit node's endpoint, then encrypted again
+//! for the entry node. The entry node only sees our real IP and an encrypted
+//! stream to the exit node; the exit node only sees the entry node and the
+//! destinations. Both layers run in the same userspace loop, so the OS still
+//! sees one interface and one endpoint, and the kill switch and DNS protection
+//! work unchanged.
+//!
+//! Throughput tradeoff: every packet is encrypted twice and crosses two nodes,
+//! so latency is roughly the sum of both hops and bandwidth is capped by the
+//! slower one. The inner layer adds 60 bytes per packet (IPv4 + UDP + WireGuard
+//! headers), so the interface MTU drops to `MULTIHOP_MTU` to avoid fragmenting
+//! on the outer link. Chains are limited to two hops for now.
+
+use super::{VPNError, TUNNEL_MTU};
+use boringtun::noise::errors::WireGuardError;
+use boringtun::noise::{Tunn, TunnResult};
+use std::net::SocketAddrV4;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// IPv4 header (20) + UDP header (8) + WireGuard data overhead (32)
+const INNER_OVERHEAD: u16 = 60;
+
+/// Interface MTU when the exit hop is nested inside the entry tunnel
+pub const MULTIHOP_MTU: u16 = multihop_mtu(TUNNEL_MTU);
+
+/// Interface MTU for an exit hop nested in an entry tunnel with `outer_mtu`
+pub const fn multihop_mtu(outer_mtu: u16) -> u16 {
+    outer_mtu - INNER_OVERHEAD
+}
+
+/// Source port for datagrams to the exit node; the entry node NATs it
+pub(super) const HOP_SOURCE_PORT: u16 = 51821;
+
+const IPV4_HEADER_LEN: usize = 20;
+const UDP_HEADER_LEN: usize = 8;
+const IPPROTO_UDP: u8 = 17;
+const HOP_TTL: u8 = 64;
+
+/// Room for the largest wrapped datagram
+const HOP_BUFFER_SIZE: usize = 2048;
+
+/// Second WireGuard peer reached through the first
+#[derive(Debug, Clone)]
+pub struct ExitHop {
+    pub peer_pubkey: String,
+    pub endpoint: String,
+    /// Tunnel address assigned by the exit node, used for the TUN interface
+    pub assigned_ip: String,
+    /// IPv6 tunnel address assigned by the exit node, if it is dual-stack
+    pub assigned_ipv6: Option<String>,
+    /// Base64 pre-shared key for the exit tunnel, if the exit node issued one
+    pub preshared_key: Option<String>,
+}
+
+/// What the exit hop produced from a datagram delivered by the entry tunnel
+pub(super) enum HopOutput<'a> {
+    /// Wrapped datagram to send back through the entry tunnel
+    Network(&'a [u8]),
+    /// Decrypted packet for the TUN device
+    Tunnel(&'a [u8]),
+    Done,
+}
+
+/// Exit-hop WireGuard state running inside the entry tunnel
+pub(super) struct InnerHop {
+    tunn: Box<Tunn>,
+    /// Our address inside the entry tunnel
+    source: SocketAddrV4,
+    /// Exit node endpoint
+    endpoint: SocketAddrV4,
+    bytes_up: Arc<AtomicU64>,
+    bytes_down: Arc<AtomicU64>,
+    crypt_buf: Box<[u8; HOP_BUFFER_SIZE]>,
+    frame_buf: Box<[u8; HOP_BUFFER_SIZE]>,
+}
+
+impl InnerHop {
+    pub fn new(
+        tunn: Tunn,
+        source: SocketAddrV4,
+        endpoint: SocketAddrV4,
+        bytes_up: Arc<AtomicU64>,
+        bytes_down: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            tunn: Box::new(tunn),
+            source,
+            endpoint,
+            bytes_up,
+            bytes_down,
+            crypt_buf: Box::new([0u8; HOP_BUFFER_SIZE]),
+            frame_buf: Box::new([0u8; HOP_BUFFER_SIZE]),
+        }
+    }
+
+    /// Wrapped handshake initiation for the exit node
+    pub fn handshake(&mut self) -> Option<&[u8]> {
+        let result = self
+            .tunn
+            .format_handshake_initiation(&mut self.crypt_buf[..], false);
+        frame(
+            result,
+            self.source,
+            self.endpoint,
+            &self.bytes_up,
+            &mut self.frame_buf[..],
+        )
+    }
+
+    /// Encrypt a TUN packet for the exit node. `None` if boringtun queued it.
+    pub fn encapsulate(&mut self, packet: &[u8]) -> Option<&[u8]> {
+        let result = self.tunn.encapsulate(packet, &mut self.crypt_buf[..]);
+        frame(
+            result,
+            self.source,
+            self.endpoint,
+            &self.bytes_up,
+            &mut self.frame_buf[..],
+        )
+    }
+
+    /// Run the exit hop's timers, returning any keepalive or handshake to send
+    pub fn update_timers(&mut self) -> Result<Option<&[u8]>, VPNError> {
+        let result = self.tunn.update_timers(&mut self.crypt_buf[..]);
+        if let TunnResult::Err(WireGuardError::ConnectionExpired) = result {
+            return Err(VPNError::TunnelError(
+                "Exit hop handshake expired".to_string(),
+            ));
+        }
+        Ok(frame(
+            result,
+            self.source,
+            self.endpoint,
+            &self.bytes_up,
+            &mut self.frame_buf[..],
+        ))
+    }
+
+    /// Handle an IP packet the entry tunnel decrypted
+    pub fn decapsulate(&mut self, packet: &[u8]) -> HopOutput<'_> {
+        let Some(payload) = unwrap_udp(packet, self.endpoint) else {
+            tracing::debug!("Dropping non exit-hop packet from entry tunnel");
+            return HopOutput::Done;
+        };
+        self.bytes_down
+            .fetch_add(payload.len() as u64, Ordering::Relaxed);
+
+        match self
+            .tunn
+            .decapsulate(None, payload, &mut self.crypt_buf[..])
+        {
+            TunnResult::WriteToNetwork(data) => {
+                let n = wrap_udp(self.source, self.endpoint, data, &mut self.frame_buf[..]);
+                self.bytes_up
+                    .fetch_add(data.len() as u64, Ordering::Relaxed);
+                HopOutput::Network(&self.frame_buf[..n])
+            }
+            TunnResult::WriteToTunnelV4(packet, _) | TunnResult::WriteToTunnelV6(packet, _) => {
+                HopOutput::Tunnel(packet)
+            }
+            TunnResult::Err(e) => {
+                tracing::warn!("Exit hop decapsulation error: {:?}", e);
+                HopOutput::Done
+            }
+            TunnResult::Done => HopOutput::Done,
+        }
+    }
+
+    /// Packets released by a completed exit-hop handshake
+    pub fn drain(&mut self) -> Option<&[u8]> {
+        let result = self.tunn.decapsulate(None, &[], &mut self.crypt_buf[..]);
+        frame(
+            result,
+            self.source,
+            self.endpoint,
+            &self.bytes_up,
+            &mut self.frame_buf[..],
+        )
+    }
+}
+
+/// Wrap boringtun's output for the exit node into `out`, counting it
+fn frame<'a>(
+    result: TunnResult<'_>,
+    source: SocketAddrV4,
+    endpoint: SocketAddrV4,
+    bytes_up: &AtomicU64,
+    out: &'a mut [u8],
+) -> Option<&'a [u8]> {
+    match result {
+        TunnResult::WriteToNetwork(data) => {
+            let n = wrap_udp(source, endpoint, data, out);
+            bytes_up.fetch_add(data.len() as u64, Ordering::Relaxed);
+            Some(&out[..n])
+        }
+        TunnResult::Err(e) => {
+            tracing::warn!("Exit hop encapsulation error: {:?}", e);
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Build an IPv4/UDP datagram around `payload` in `out`, returning its length.
+///
+/// The UDP checksum is left as zero, which IPv4 permits; WireGuard
+/// authenticates the payload anyway.
+fn wrap_udp(source: SocketAddrV4, dest: SocketAddrV4, payload: &[u8], out: &mut [u8]) -> usize {
+    let udp_len = UDP_HEADER_LEN + payload.len();
+    let total_len = IPV4_HEADER_LEN + udp_len;
+
+    let header = &mut out[..IPV4_HEADER_LEN];
+    header.fill(0);
+    header[0] = 0x45; // IPv4, 5-word header
+    header[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+    header[6] = 0x40; // Don't fragment
+    header[8] = HOP_TTL;
+    header[9] = IPPROTO_UDP;
+    header[12..16].copy_from_slice(&source.ip().octets());
+    header[16..20].copy_from_slice(&dest.ip().octets());
+    let checksum = ipv4_checksum(header);
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    let udp = &mut out[IPV4_HEADER_LEN..total_len];
+    udp[0..2].copy_from_slice(&source.port().to_be_bytes());
+    udp[2..4].copy_from_slice(&dest.port().to_be_bytes());
+    udp[4..6].copy_from_slice(&(udp_len as u16).to_be_bytes());
+    udp[6..8].fill(0);
+    udp[UDP_HEADER_LEN..].copy_from_slice(payload);
+
+    total_len
+}
+
+/// UDP payload of an IPv4 datagram, if it came from `source`
+fn unwrap_udp(packet: &[u8], source: SocketAddrV4) -> Option<&[u8]> {
+    if packet.first()? >> 4 != 4 || *packet.get(9)? != IPPROTO_UDP {
+        return None;
+    }
+    let ihl = ((packet[0] & 0x0f) as usize) * 4;
+    let total_len = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]) as usize;
+    let packet = packet.get(..total_len)?;
+
+    if packet.get(12..16)? != source.ip().octets() {
+        return None;
+    }
+
+    let udp = packet.get(ihl..)?;
+    if u16::from_be_bytes([*udp.first()?, *udp.get(1)?]) != source.port() {
+        return None;
+    }
+    let udp_len = u16::from_be_bytes([*udp.get(4)?, *udp.get(5)?]) as usize;
+    udp.get(UDP_HEADER_LEN..udp_len)
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddrV4 {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_wrap_udp_builds_valid_header() {
+        let mut out = [0u8; HOP_BUFFER_SIZE];
+        let n = wrap_udp(
+            addr("10.0.0.2:51821"),
+            addr("203.0.113.9:51820"),
+            b"hello",
+            &mut out,
+        );
+
+        assert_eq!(n, IPV4_HEADER_LEN + UDP_HEADER_LEN + 5);
+        assert_eq!(u16::from_be_bytes([out[2], out[3]]) as usize, n);
+        assert_eq!(out[9], IPPROTO_UDP);
+        // A header including its own checksum sums to zero
+        assert_eq!(ipv4_checksum(&out[..IPV4_HEADER_LEN]), 0);
+        assert_eq!(&out[28..n], b"hello");
+    }
+
+    #[test]
+    fn test_unwrap_udp_checks_source() {
+        let exit = addr("203.0.113.9:51820");
+        let mut out = [0u8; HOP_BUFFER_SIZE];
+
+        // Reply from the exit node back to us
+        let n = wrap_udp(exit, addr("10.0.0.2:51821"), b"reply", &mut out);
+        assert_eq!(unwrap_udp(&out[..n], exit), Some(&b"reply"[..]));
+
+        assert_eq!(unwrap_udp(&out[..n], addr("203.0.113.10:51820")), None);
+        assert_eq!(unwrap_udp(&out[..n], addr("203.0.113.9:51821")), None);
+        assert_eq!(unwrap_udp(&out[..4], exit), None);
+    }
+
+    #[test]
+    fn test_multihop_mtu_fits_outer_tunnel() {
+        assert_eq!(MULTIHOP_MTU + INNER_OVERHEAD, TUNNEL_MTU);
+        assert_eq!(multihop_mtu(1380) + INNER_OVERHEAD, 1380);
+    }
+}
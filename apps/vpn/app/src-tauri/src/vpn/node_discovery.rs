@@ -1,19 +1,57 @@
 //! VPN node discovery via WebSocket coordinator and on-chain registry
 
-use super::{NodeCapabilities, VPNError, VPNNode};
+use super::wireguard::{parse_base64_key, parse_preshared_key};
+use super::{NodeCapabilities, RegistryClient, VPNError, VPNNode};
+use boringtun::noise::{Tunn, TunnResult};
+use boringtun::x25519::{PublicKey, StaticSecret};
+use futures_util::future::join_all;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
+/// Default time to wait for a ping answer
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default time to wait for the coordinator to allocate a tunnel address
+pub const DEFAULT_ADDRESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Latency recorded for nodes that could not be reached
+pub const UNREACHABLE_LATENCY_MS: u32 = 10_000;
+
+/// How long an on-chain reputation lookup is reused
+const REPUTATION_TTL: Duration = Duration::from_secs(600);
+
+/// Nodes neither the coordinator nor the registry has mentioned for this long
+/// are dropped. Node lists can be filtered by country, so a node missing from
+/// one isn't gone.
+pub const NODE_TTL: Duration = Duration::from_secs(900);
+
+/// Largest gap between coordinator-reported and on-chain reputation that is
+/// put down to the registry lagging rather than a coordinator lying
+pub const MAX_REPUTATION_DIVERGENCE: u8 = 25;
+
 /// Messages sent to coordinator
 #[derive(Debug, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum CoordinatorRequest {
-    Subscribe { country_codes: Option<Vec<String>> },
-    GetNodes { country_code: Option<String> },
-    Ping { node_id: String },
+    Subscribe {
+        country_codes: Option<Vec<String>>,
+    },
+    GetNodes {
+        country_code: Option<String>,
+    },
+    Ping {
+        node_id: String,
+    },
+    AllocateAddress {
+        node_id: String,
+        client_pubkey: String,
+    },
     Unsubscribe,
 }
 
@@ -21,13 +59,45 @@ enum CoordinatorRequest {
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum CoordinatorResponse {
-    Nodes { nodes: Vec<NodeInfo> },
-    NodeUpdate { node: NodeInfo },
-    NodeOffline { node_id: String },
-    PingResult { node_id: String, latency_ms: u32 },
-    Error { message: String },
+    Nodes {
+        nodes: Vec<NodeInfo>,
+    },
+    NodeUpdate {
+        node: NodeInfo,
+    },
+    NodeOffline {
+        node_id: String,
+    },
+    PingResult {
+        node_id: String,
+        latency_ms: u32,
+    },
+    AddressAssigned {
+        node_id: String,
+        /// Client the allocation is for; the coordinator may be serving several
+        client_pubkey: String,
+        assigned_ip: String,
+        #[serde(default)]
+        assigned_ipv6: Option<String>,
+        #[serde(default)]
+        preshared_key: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Tunnel addresses and pre-shared key a node allocated to one client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressAssignment {
+    pub assigned_ip: String,
+    pub assigned_ipv6: Option<String>,
+    pub preshared_key: Option<String>,
 }
 
+/// Address requests waiting for the coordinator, by node ID and client public key
+type PendingAddresses = HashMap<(String, String), oneshot::Sender<AddressAssignment>>;
+
 #[derive(Debug, Clone, Deserialize)]
 struct NodeInfo {
     node_id: String,
@@ -39,6 +109,12 @@ struct NodeInfo {
     load: u8,
     reputation: u8,
     capabilities: NodeCapabilities,
+    #[serde(default)]
+    assigned_ip: Option<String>,
+    #[serde(default)]
+    assigned_ipv6: Option<String>,
+    #[serde(default)]
+    preshared_key: Option<String>,
 }
 
 impl From<NodeInfo> for VPNNode {
@@ -53,18 +129,142 @@ impl From<NodeInfo> for VPNNode {
             latency_ms: 0, // Will be measured
             load: info.load,
             reputation: info.reputation,
+            reported_reputation: None,
+            onchain_reputation: None,
+            reputation_mismatch: false,
             capabilities: info.capabilities,
+            assigned_ip: info.assigned_ip,
+            assigned_ipv6: info.assigned_ipv6,
+            preshared_key: info.preshared_key,
+            registry_only: false,
+        }
+    }
+}
+
+/// Known nodes, kept up to date from coordinator messages instead of being
+/// replaced wholesale, so measured latencies survive refreshes
+#[derive(Default)]
+struct NodeCache {
+    nodes: Vec<VPNNode>,
+    /// When each node was last reported, by node ID
+    seen: HashMap<String, Instant>,
+}
+
+impl NodeCache {
+    /// Add or update a reported node. Measurements and assignments the report
+    /// doesn't carry are kept from the cached copy.
+    fn upsert(&mut self, mut node: VPNNode, now: Instant) {
+        self.seen.insert(node.node_id.clone(), now);
+        match self.nodes.iter_mut().find(|n| n.node_id == node.node_id) {
+            Some(existing) => {
+                if node.latency_ms == 0 {
+                    node.latency_ms = existing.latency_ms;
+                }
+                if node.assigned_ip.is_none() {
+                    node.assigned_ip = existing.assigned_ip.take();
+                }
+                if node.assigned_ipv6.is_none() {
+                    node.assigned_ipv6 = existing.assigned_ipv6.take();
+                }
+                if node.preshared_key.is_none() {
+                    node.preshared_key = existing.preshared_key.take();
+                }
+                // Don't let a fresh coordinator figure undo the on-chain check
+                if let Some(onchain) = existing.onchain_reputation {
+                    apply_onchain_reputation(&mut node, onchain);
+                }
+                *existing = node;
+            }
+            None => self.nodes.push(node),
+        }
+    }
+
+    /// Add registry nodes the coordinator hasn't reported, keyed by node ID
+    /// (the operator address, compared case-insensitively). Coordinator data
+    /// wins since it carries live load and region details the registry lacks.
+    fn merge_registry(&mut self, registry: Vec<VPNNode>, now: Instant) {
+        for node in registry {
+            match self
+                .nodes
+                .iter()
+                .find(|n| n.node_id.eq_ignore_ascii_case(&node.node_id))
+            {
+                Some(existing) => {
+                    self.seen.insert(existing.node_id.clone(), now);
+                }
+                None => self.upsert(node, now),
+            }
+        }
+    }
+
+    fn remove(&mut self, node_id: &str) {
+        self.nodes.retain(|n| n.node_id != node_id);
+        self.seen.remove(node_id);
+    }
+
+    /// Drop nodes last reported more than `ttl` before `now`
+    fn evict_stale(&mut self, ttl: Duration, now: Instant) {
+        let seen = &mut self.seen;
+        self.nodes.retain(|n| {
+            let fresh = seen
+                .get(&n.node_id)
+                .is_some_and(|at| now.saturating_duration_since(*at) <= ttl);
+            if !fresh {
+                tracing::debug!("Evicting stale node {}", n.node_id);
+                seen.remove(&n.node_id);
+            }
+            fresh
+        });
+    }
+
+    fn get_mut(&mut self, node_id: &str) -> Option<&mut VPNNode> {
+        self.nodes.iter_mut().find(|n| n.node_id == node_id)
+    }
+
+    /// Record this client's allocation on a node
+    fn assign(&mut self, node_id: &str, assignment: &AddressAssignment) {
+        if let Some(node) = self.get_mut(node_id) {
+            node.assigned_ip = Some(assignment.assigned_ip.clone());
+            node.assigned_ipv6 = assignment.assigned_ipv6.clone();
+            if assignment.preshared_key.is_some() {
+                node.preshared_key = assignment.preshared_key.clone();
+            }
         }
     }
 }
 
+/// Hand an allocation to the request waiting for it and cache it. Allocations
+/// nobody here asked for, such as another client's, are dropped.
+fn deliver_assignment(
+    pending: &mut PendingAddresses,
+    cache: &mut NodeCache,
+    node_id: &str,
+    client_pubkey: &str,
+    assignment: AddressAssignment,
+) -> bool {
+    let Some(waiter) = pending.remove(&(node_id.to_string(), client_pubkey.to_string())) else {
+        tracing::debug!("Ignoring unrequested address assignment on {}", node_id);
+        return false;
+    };
+    cache.assign(node_id, &assignment);
+    let _ = waiter.send(assignment);
+    true
+}
+
 /// Node discovery service with WebSocket connection to coordinator
 pub struct NodeDiscovery {
     coordinator_url: String,
     rpc_url: String,
-    nodes: Arc<RwLock<Vec<VPNNode>>>,
+    nodes: Arc<RwLock<NodeCache>>,
     ws_tx: Option<mpsc::Sender<CoordinatorRequest>>,
     connected: Arc<RwLock<bool>>,
+    pending_pings: Arc<Mutex<HashMap<String, oneshot::Sender<u32>>>>,
+    ping_timeout: Duration,
+    pending_addresses: Arc<Mutex<PendingAddresses>>,
+    address_timeout: Duration,
+    registry: Option<RegistryClient>,
+    /// On-chain reputation by lowercased operator address, and when it was read
+    onchain_reputations: Arc<Mutex<HashMap<String, (Option<u8>, Instant)>>>,
 }
 
 impl NodeDiscovery {
@@ -72,9 +272,15 @@ impl NodeDiscovery {
         Self {
             coordinator_url: "wss://vpn-coordinator.jejunetwork.org".to_string(),
             rpc_url: "https://rpc.jejunetwork.org".to_string(),
-            nodes: Arc::new(RwLock::new(Vec::new())),
+            nodes: Arc::new(RwLock::new(NodeCache::default())),
             ws_tx: None,
             connected: Arc::new(RwLock::new(false)),
+            pending_pings: Arc::new(Mutex::new(HashMap::new())),
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            pending_addresses: Arc::new(Mutex::new(HashMap::new())),
+            address_timeout: DEFAULT_ADDRESS_TIMEOUT,
+            registry: None,
+            onchain_reputations: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -82,12 +288,37 @@ impl NodeDiscovery {
         Self {
             coordinator_url,
             rpc_url,
-            nodes: Arc::new(RwLock::new(Vec::new())),
+            nodes: Arc::new(RwLock::new(NodeCache::default())),
             ws_tx: None,
             connected: Arc::new(RwLock::new(false)),
+            pending_pings: Arc::new(Mutex::new(HashMap::new())),
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            pending_addresses: Arc::new(Mutex::new(HashMap::new())),
+            address_timeout: DEFAULT_ADDRESS_TIMEOUT,
+            registry: None,
+            onchain_reputations: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Query the on-chain registry at `address` when the coordinator is down.
+    /// A zero address leaves the fallback disabled.
+    pub fn with_registry(mut self, address: &str) -> Self {
+        self.registry = RegistryClient::new(&self.rpc_url, address);
+        self
+    }
+
+    /// Set how long to wait for a ping answer
+    pub fn with_ping_timeout(mut self, ping_timeout: Duration) -> Self {
+        self.ping_timeout = ping_timeout;
+        self
+    }
+
+    /// Set how long to wait for the coordinator to allocate a tunnel address
+    pub fn with_address_timeout(mut self, address_timeout: Duration) -> Self {
+        self.address_timeout = address_timeout;
+        self
+    }
+
     /// Connect to the coordinator WebSocket
     pub async fn connect_coordinator(&mut self) -> Result<(), VPNError> {
         let url = &self.coordinator_url;
@@ -106,6 +337,8 @@ impl NodeDiscovery {
 
                 let nodes = self.nodes.clone();
                 let connected = self.connected.clone();
+                let pending_pings = self.pending_pings.clone();
+                let pending_addresses = self.pending_addresses.clone();
 
                 // Spawn reader task
                 tokio::spawn(async move {
@@ -117,40 +350,56 @@ impl NodeDiscovery {
                                 {
                                     match response {
                                         CoordinatorResponse::Nodes { nodes: node_list } => {
-                                            let vpn_nodes: Vec<VPNNode> =
-                                                node_list.into_iter().map(Into::into).collect();
-                                            *nodes.write().await = vpn_nodes;
                                             tracing::debug!(
                                                 "Received {} nodes from coordinator",
-                                                nodes.read().await.len()
+                                                node_list.len()
                                             );
+                                            let now = Instant::now();
+                                            let mut cache = nodes.write().await;
+                                            for node in node_list {
+                                                cache.upsert(node.into(), now);
+                                            }
                                         }
                                         CoordinatorResponse::NodeUpdate { node } => {
-                                            let mut nodes_lock = nodes.write().await;
-                                            let vpn_node: VPNNode = node.into();
-                                            if let Some(existing) = nodes_lock
-                                                .iter_mut()
-                                                .find(|n| n.node_id == vpn_node.node_id)
-                                            {
-                                                *existing = vpn_node;
-                                            } else {
-                                                nodes_lock.push(vpn_node);
-                                            }
+                                            nodes.write().await.upsert(node.into(), Instant::now());
                                         }
                                         CoordinatorResponse::NodeOffline { node_id } => {
-                                            let mut nodes_lock = nodes.write().await;
-                                            nodes_lock.retain(|n| n.node_id != node_id);
+                                            tracing::debug!("Node {} went offline", node_id);
+                                            nodes.write().await.remove(&node_id);
                                         }
                                         CoordinatorResponse::PingResult {
                                             node_id,
                                             latency_ms,
                                         } => {
-                                            let mut nodes_lock = nodes.write().await;
                                             if let Some(node) =
-                                                nodes_lock.iter_mut().find(|n| n.node_id == node_id)
+                                                nodes.write().await.get_mut(&node_id)
                                             {
                                                 node.latency_ms = latency_ms;
                                             }
+                                            if let Some(waiter) =
+                                                pending_pings.lock().await.remove(&node_id)
+                                            {
+                                                let _ = waiter.send(latency_ms);
+                                            }
+                                        }
+                                        CoordinatorResponse::AddressAssigned {
+                                            node_id,
+                                            client_pubkey,
+                                            assigned_ip,
+                                            assigned_ipv6,
+                                            preshared_key,
+                                        } => {
+                                            deliver_assignment(
+                                                &mut *pending_addresses.lock().await,
+                                                &mut *nodes.write().await,
+                                                &node_id,
+                                                &client_pubkey,
+                                                AddressAssignment {
+                                                    assigned_ip,
+                                                    assigned_ipv6,
+                                                    preshared_key,
+                                                },
+                                            );
                                         }
                                         CoordinatorResponse::Error { message } => {
                                             tracing::error!("Coordinator error: {}", message);
@@ -197,9 +446,8 @@ impl NodeDiscovery {
                 Ok(())
             }
             Err(e) => {
-                tracing::warn!("Failed to connect to coordinator: {}, using fallback", e);
-                // Fallback to mock nodes for development
-                *self.nodes.write().await = self.get_fallback_nodes();
+                // Discovery falls back to the on-chain registry
+                tracing::warn!("Failed to connect to coordinator: {}, using registry", e);
                 Ok(())
             }
         }
@@ -223,72 +471,208 @@ impl NodeDiscovery {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
 
-        let nodes = self.nodes.read().await;
+        // Without the coordinator, fill in from the on-chain registry
+        if !*self.connected.read().await {
+            if let Some(ref registry) = self.registry {
+                match registry.active_exit_nodes().await {
+                    Ok(registry_nodes) => {
+                        tracing::info!("Loaded {} nodes from registry", registry_nodes.len());
+                        self.nodes
+                            .write()
+                            .await
+                            .merge_registry(registry_nodes, Instant::now());
+                    }
+                    Err(e) => tracing::warn!("Registry discovery failed: {}", e),
+                }
+            }
+        }
+
+        let mut nodes = self.cached_nodes().await;
 
         // If no nodes cached, use fallback
         if nodes.is_empty() {
-            drop(nodes);
             return Ok(self.get_fallback_nodes());
         }
 
+        // Keep the checked reputations in the cache so rankings built from it agree
+        self.check_reputations(&mut nodes).await;
+        {
+            let mut cache = self.nodes.write().await;
+            for node in &nodes {
+                if let Some(entry) = cache.get_mut(&node.node_id) {
+                    entry.reputation = node.reputation;
+                    entry.reported_reputation = node.reported_reputation;
+                    entry.onchain_reputation = node.onchain_reputation;
+                    entry.reputation_mismatch = node.reputation_mismatch;
+                }
+            }
+        }
+
         // Filter by country if specified
-        let filtered = if let Some(code) = country_code {
-            nodes
-                .iter()
-                .filter(|n| n.country_code == code)
-                .cloned()
-                .collect()
-        } else {
-            nodes.clone()
+        if let Some(code) = country_code {
+            nodes.retain(|n| n.country_code == code);
+        }
+        Ok(nodes)
+    }
+
+    /// Every node currently known, after evicting those unseen for `NODE_TTL`
+    pub async fn cached_nodes(&self) -> Vec<VPNNode> {
+        let mut cache = self.nodes.write().await;
+        cache.evict_stale(NODE_TTL, Instant::now());
+        cache.nodes.clone()
+    }
+
+    /// Replace coordinator-reported reputations with the registry's where the
+    /// operator is registered, flagging nodes whose figures diverge
+    async fn check_reputations(&self, nodes: &mut [VPNNode]) {
+        let Some(ref registry) = self.registry else {
+            return;
         };
 
-        Ok(filtered)
+        let mut cache = self.onchain_reputations.lock().await;
+        let stale: Vec<String> = nodes
+            .iter()
+            .filter(|n| n.onchain_reputation.is_none())
+            .map(|n| n.operator.to_lowercase())
+            .filter(|operator| {
+                !cache
+                    .get(operator)
+                    .is_some_and(|(_, read_at)| read_at.elapsed() < REPUTATION_TTL)
+            })
+            .collect();
+
+        let results = join_all(stale.iter().map(|operator| registry.reputation(operator))).await;
+        for (operator, result) in stale.into_iter().zip(results) {
+            match result {
+                Ok(reputation) => {
+                    cache.insert(operator, (reputation, Instant::now()));
+                }
+                Err(e) => tracing::debug!("Reputation lookup for {} failed: {}", operator, e),
+            }
+        }
+
+        for node in nodes.iter_mut() {
+            if let Some((Some(onchain), _)) = cache.get(&node.operator.to_lowercase()) {
+                apply_onchain_reputation(node, *onchain);
+            }
+        }
     }
 
-    /// Ping a node to measure latency
-    pub async fn ping_node(&self, node_id: &str) -> Result<u32, VPNError> {
+    /// Measure round-trip latency to a node
+    ///
+    /// Asks the coordinator first; if it doesn't answer within the ping timeout,
+    /// falls back to timing a WireGuard handshake with the node's endpoint.
+    pub async fn ping_node(&self, node: &VPNNode, private_key: &str) -> Result<u32, VPNError> {
         if let Some(ref tx) = self.ws_tx {
+            let (result_tx, result_rx) = oneshot::channel();
+            self.pending_pings
+                .lock()
+                .await
+                .insert(node.node_id.clone(), result_tx);
+
             let _ = tx
                 .send(CoordinatorRequest::Ping {
-                    node_id: node_id.to_string(),
+                    node_id: node.node_id.clone(),
                 })
                 .await;
 
-            // Wait for ping result
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            if let Ok(Ok(latency_ms)) = tokio::time::timeout(self.ping_timeout, result_rx).await {
+                return Ok(latency_ms);
+            }
+
+            self.pending_pings.lock().await.remove(&node.node_id);
+            tracing::debug!("No ping result for {}, probing endpoint", node.node_id);
+        }
+
+        probe_endpoint_rtt(
+            &node.endpoint,
+            &node.wireguard_pubkey,
+            node.preshared_key.as_deref(),
+            private_key,
+            self.ping_timeout,
+        )
+        .await
+    }
 
-            let nodes = self.nodes.read().await;
-            if let Some(node) = nodes.iter().find(|n| n.node_id == node_id) {
-                return Ok(node.latency_ms);
+    /// Measure latency to every node concurrently, updating them and the cache.
+    /// Unreachable nodes get `UNREACHABLE_LATENCY_MS` so they sort last.
+    pub async fn measure_latencies(&self, nodes: &mut [VPNNode], private_key: &str) {
+        let results = join_all(nodes.iter().map(|node| self.ping_node(node, private_key))).await;
+
+        let mut cached = self.nodes.write().await;
+        for (node, result) in nodes.iter_mut().zip(results) {
+            node.latency_ms = result.unwrap_or_else(|e| {
+                tracing::debug!("Node {} unreachable: {}", node.node_id, e);
+                UNREACHABLE_LATENCY_MS
+            });
+
+            if let Some(entry) = cached.get_mut(&node.node_id) {
+                entry.latency_ms = node.latency_ms;
             }
         }
+    }
 
-        // Fallback: do a simple HTTP ping
-        let start = std::time::Instant::now();
-        let nodes = self.nodes.read().await;
-        if let Some(node) = nodes.iter().find(|n| n.node_id == node_id) {
-            // Try to connect to the endpoint
-            let endpoint = &node.endpoint;
-            if let Ok(addr) = endpoint.parse::<std::net::SocketAddr>() {
-                match tokio::time::timeout(
-                    tokio::time::Duration::from_secs(5),
-                    tokio::net::TcpStream::connect(addr),
-                )
-                .await
-                {
-                    Ok(Ok(_)) => return Ok(start.elapsed().as_millis() as u32),
-                    _ => {}
-                }
+    /// Measure only the nodes without a latency yet, leaving earlier
+    /// measurements alone so rankings don't shift with every refresh
+    pub async fn measure_new_latencies(&self, nodes: &mut [VPNNode], private_key: &str) {
+        let mut unmeasured: Vec<VPNNode> = nodes
+            .iter()
+            .filter(|n| n.latency_ms == 0)
+            .cloned()
+            .collect();
+        if unmeasured.is_empty() {
+            return;
+        }
+
+        self.measure_latencies(&mut unmeasured, private_key).await;
+        for measured in unmeasured {
+            if let Some(node) = nodes.iter_mut().find(|n| n.node_id == measured.node_id) {
+                node.latency_ms = measured.latency_ms;
+            }
+        }
+    }
+
+    /// Ask the coordinator to allocate this client's tunnel address on a node,
+    /// waiting up to the address timeout for the answer addressed to `client_pubkey`.
+    /// Nothing else can allocate one, so this fails at once while it is down.
+    pub async fn request_address(
+        &self,
+        node_id: &str,
+        client_pubkey: &str,
+    ) -> Result<AddressAssignment, VPNError> {
+        let tx = match self.ws_tx {
+            Some(ref tx) if *self.connected.read().await => tx,
+            _ => return Err(VPNError::CoordinatorUnavailable(node_id.to_string())),
+        };
+
+        let key = (node_id.to_string(), client_pubkey.to_string());
+        let (result_tx, result_rx) = oneshot::channel();
+        self.pending_addresses
+            .lock()
+            .await
+            .insert(key.clone(), result_tx);
+
+        let request = CoordinatorRequest::AllocateAddress {
+            node_id: node_id.to_string(),
+            client_pubkey: client_pubkey.to_string(),
+        };
+        if tx.send(request).await.is_ok() {
+            if let Ok(Ok(assignment)) = tokio::time::timeout(self.address_timeout, result_rx).await
+            {
+                return Ok(assignment);
             }
         }
 
-        Ok(100) // Default fallback latency
+        self.pending_addresses.lock().await.remove(&key);
+        tracing::warn!("No address assigned on {} in time", node_id);
+        Err(VPNError::NoAssignedIp(node_id.to_string()))
     }
 
     /// Get node details from contract
     pub async fn get_node_details(&self, node_id: &str) -> Result<VPNNode, VPNError> {
         let nodes = self.nodes.read().await;
         nodes
+            .nodes
             .iter()
             .find(|n| n.node_id == node_id)
             .cloned()
@@ -308,6 +692,9 @@ impl NodeDiscovery {
                 latency_ms: 25,
                 load: 30,
                 reputation: 95,
+                reported_reputation: None,
+                onchain_reputation: None,
+                reputation_mismatch: false,
                 capabilities: NodeCapabilities {
                     supports_wireguard: true,
                     supports_socks5: true,
@@ -315,6 +702,10 @@ impl NodeDiscovery {
                     serves_cdn: true,
                     is_vpn_exit: true,
                 },
+                assigned_ip: None,
+                assigned_ipv6: None,
+                preshared_key: None,
+                registry_only: false,
             },
             VPNNode {
                 node_id: "0xabcdef1234567890abcdef1234567890abcdef12".to_string(),
@@ -326,6 +717,9 @@ impl NodeDiscovery {
                 latency_ms: 80,
                 load: 45,
                 reputation: 90,
+                reported_reputation: None,
+                onchain_reputation: None,
+                reputation_mismatch: false,
                 capabilities: NodeCapabilities {
                     supports_wireguard: true,
                     supports_socks5: true,
@@ -333,6 +727,10 @@ impl NodeDiscovery {
                     serves_cdn: true,
                     is_vpn_exit: true,
                 },
+                assigned_ip: None,
+                assigned_ipv6: None,
+                preshared_key: None,
+                registry_only: false,
             },
             VPNNode {
                 node_id: "0x9876543210fedcba9876543210fedcba98765432".to_string(),
@@ -344,6 +742,9 @@ impl NodeDiscovery {
                 latency_ms: 150,
                 load: 20,
                 reputation: 98,
+                reported_reputation: None,
+                onchain_reputation: None,
+                reputation_mismatch: false,
                 capabilities: NodeCapabilities {
                     supports_wireguard: true,
                     supports_socks5: true,
@@ -351,6 +752,10 @@ impl NodeDiscovery {
                     serves_cdn: true,
                     is_vpn_exit: true,
                 },
+                assigned_ip: None,
+                assigned_ipv6: None,
+                preshared_key: None,
+                registry_only: false,
             },
             VPNNode {
                 node_id: "0x5555555555555555555555555555555555555555".to_string(),
@@ -362,6 +767,9 @@ impl NodeDiscovery {
                 latency_ms: 35,
                 load: 55,
                 reputation: 92,
+                reported_reputation: None,
+                onchain_reputation: None,
+                reputation_mismatch: false,
                 capabilities: NodeCapabilities {
                     supports_wireguard: true,
                     supports_socks5: true,
@@ -369,13 +777,258 @@ impl NodeDiscovery {
                     serves_cdn: true,
                     is_vpn_exit: true,
                 },
+                assigned_ip: None,
+                assigned_ipv6: None,
+                preshared_key: None,
+                registry_only: false,
             },
         ]
     }
 }
 
+/// Prefer the registry's reputation over the coordinator's, keeping both
+fn apply_onchain_reputation(node: &mut VPNNode, onchain: u8) {
+    let reported = *node.reported_reputation.get_or_insert(node.reputation);
+    node.onchain_reputation = Some(onchain);
+    node.reputation = onchain;
+    node.reputation_mismatch = reported.abs_diff(onchain) > MAX_REPUTATION_DIVERGENCE;
+    if node.reputation_mismatch {
+        tracing::warn!(
+            "Node {} reported reputation {} but has {} on-chain",
+            node.node_id,
+            reported,
+            onchain
+        );
+    }
+}
+
+/// Time a WireGuard handshake with a node's endpoint.
+///
+/// WireGuard never answers unauthenticated datagrams, so the probe sends a real
+/// handshake initiation and measures until the peer's handshake response arrives.
+async fn probe_endpoint_rtt(
+    endpoint: &str,
+    node_pubkey: &str,
+    preshared_key: Option<&str>,
+    private_key: &str,
+    timeout: Duration,
+) -> Result<u32, VPNError> {
+    let addr = tokio::net::lookup_host(endpoint)
+        .await?
+        .next()
+        .ok_or_else(|| VPNError::DiscoveryError(format!("Cannot resolve {}", endpoint)))?;
+
+    let mut tunn = Tunn::new(
+        StaticSecret::from(parse_base64_key(private_key)?),
+        PublicKey::from(parse_base64_key(node_pubkey)?),
+        parse_preshared_key(preshared_key)?,
+        None,
+        0,
+        None,
+    )
+    .map_err(|e| VPNError::DiscoveryError(format!("Failed to create probe: {:?}", e)))?;
+
+    let bind_addr = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(addr).await?;
+
+    let mut send_buf = [0u8; 256];
+    let mut recv_buf = [0u8; 256];
+
+    let TunnResult::WriteToNetwork(initiation) =
+        tunn.format_handshake_initiation(&mut send_buf, false)
+    else {
+        return Err(VPNError::DiscoveryError(
+            "Failed to build handshake".to_string(),
+        ));
+    };
+
+    let start = Instant::now();
+    socket.send(initiation).await?;
+
+    let n = tokio::time::timeout(timeout, socket.recv(&mut recv_buf))
+        .await
+        .map_err(|_| VPNError::DiscoveryError(format!("Probe to {} timed out", endpoint)))??;
+    let rtt = start.elapsed();
+
+    // Only a valid handshake response counts as an answer from the node
+    match tunn.decapsulate(None, &recv_buf[..n], &mut send_buf) {
+        TunnResult::Err(e) => Err(VPNError::DiscoveryError(format!(
+            "Invalid probe response from {}: {:?}",
+            endpoint, e
+        ))),
+        _ => Ok(rtt.as_millis() as u32),
+    }
+}
+
 impl Default for NodeDiscovery {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_nodes_prefers_coordinator_and_dedupes() {
+        let fallback = NodeDiscovery::new().get_fallback_nodes();
+        let now = Instant::now();
+        let mut cache = NodeCache::default();
+        cache.upsert(fallback[0].clone(), now);
+
+        let mut duplicate = fallback[0].clone();
+        duplicate.node_id = duplicate.node_id.to_uppercase().replacen("0X", "0x", 1);
+        duplicate.load = 99;
+        let registry = vec![duplicate, fallback[1].clone()];
+
+        cache.merge_registry(registry, now);
+        let merged = &cache.nodes;
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].load, fallback[0].load);
+        assert_eq!(merged[1].node_id, fallback[1].node_id);
+    }
+
+    #[test]
+    fn test_cache_keeps_latency_across_updates() {
+        let mut node = NodeDiscovery::new().get_fallback_nodes().remove(0);
+        let now = Instant::now();
+        let mut cache = NodeCache::default();
+        node.latency_ms = 0;
+        cache.upsert(node.clone(), now);
+        cache.get_mut(&node.node_id).unwrap().latency_ms = 42;
+        cache.get_mut(&node.node_id).unwrap().assigned_ip = Some("10.8.0.2".to_string());
+        cache.get_mut(&node.node_id).unwrap().assigned_ipv6 = Some("fd00:8::2".to_string());
+
+        // A fresh report carries no measurement or assignment
+        node.load = 70;
+        cache.upsert(node.clone(), now);
+        assert_eq!(cache.nodes.len(), 1);
+        assert_eq!(cache.nodes[0].load, 70);
+        assert_eq!(cache.nodes[0].latency_ms, 42);
+        assert_eq!(cache.nodes[0].assigned_ip.as_deref(), Some("10.8.0.2"));
+        assert_eq!(cache.nodes[0].assigned_ipv6.as_deref(), Some("fd00:8::2"));
+
+        cache.remove(&node.node_id);
+        assert!(cache.nodes.is_empty());
+        assert!(cache.seen.is_empty());
+    }
+
+    #[test]
+    fn test_cache_evicts_unseen_nodes() {
+        let fallback = NodeDiscovery::new().get_fallback_nodes();
+        let start = Instant::now();
+        let mut cache = NodeCache::default();
+        cache.upsert(fallback[0].clone(), start);
+        cache.upsert(fallback[1].clone(), start);
+
+        // Only the second node is reported again
+        let later = start + NODE_TTL;
+        cache.upsert(fallback[1].clone(), later);
+        cache.evict_stale(NODE_TTL, later);
+        assert_eq!(cache.nodes.len(), 2);
+
+        cache.evict_stale(NODE_TTL, later + Duration::from_secs(1));
+        assert_eq!(cache.nodes.len(), 1);
+        assert_eq!(cache.nodes[0].node_id, fallback[1].node_id);
+        assert!(!cache.seen.contains_key(&fallback[0].node_id));
+    }
+
+    fn assignment(ip: &str) -> AddressAssignment {
+        AddressAssignment {
+            assigned_ip: ip.to_string(),
+            assigned_ipv6: Some("fd00:8::2".to_string()),
+            preshared_key: Some("cHNrIGZvciB0aGlzIGNsaWVudCBvbmx5IDAwMDAwMDA=".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_assignment_delivered_to_requesting_client_only() {
+        let node = NodeDiscovery::new().get_fallback_nodes().remove(0);
+        let mut cache = NodeCache::default();
+        cache.upsert(node.clone(), Instant::now());
+
+        let mut pending = PendingAddresses::new();
+        let (tx, mut rx) = oneshot::channel();
+        pending.insert((node.node_id.clone(), "our-pubkey".to_string()), tx);
+
+        // Another client's allocation on the same node is neither taken nor cached
+        assert!(!deliver_assignment(
+            &mut pending,
+            &mut cache,
+            &node.node_id,
+            "their-pubkey",
+            assignment("10.8.0.3"),
+        ));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(cache.nodes[0].assigned_ip, None);
+        assert_eq!(cache.nodes[0].preshared_key, None);
+
+        assert!(deliver_assignment(
+            &mut pending,
+            &mut cache,
+            &node.node_id,
+            "our-pubkey",
+            assignment("10.8.0.2"),
+        ));
+        assert_eq!(rx.try_recv().unwrap(), assignment("10.8.0.2"));
+        assert_eq!(cache.nodes[0].assigned_ip.as_deref(), Some("10.8.0.2"));
+        assert!(pending.is_empty());
+
+        // A repeat answer has no one waiting for it
+        assert!(!deliver_assignment(
+            &mut pending,
+            &mut cache,
+            &node.node_id,
+            "our-pubkey",
+            assignment("10.8.0.9"),
+        ));
+        assert_eq!(cache.nodes[0].assigned_ip.as_deref(), Some("10.8.0.2"));
+    }
+
+    #[test]
+    fn test_address_assignment_names_client() {
+        let json = r#"{"type":"address_assigned","node_id":"n1","assigned_ip":"10.8.0.2"}"#;
+        assert!(serde_json::from_str::<CoordinatorResponse>(json).is_err());
+
+        let json = r#"{"type":"address_assigned","node_id":"n1","client_pubkey":"k","assigned_ip":"10.8.0.2"}"#;
+        assert!(matches!(
+            serde_json::from_str::<CoordinatorResponse>(json).unwrap(),
+            CoordinatorResponse::AddressAssigned { client_pubkey, .. } if client_pubkey == "k"
+        ));
+    }
+
+    #[test]
+    fn test_onchain_reputation_preferred() {
+        let mut node = NodeDiscovery::new().get_fallback_nodes().remove(0);
+        node.reputation = 95;
+
+        apply_onchain_reputation(&mut node, 80);
+        assert_eq!(node.reputation, 80);
+        assert_eq!(node.reported_reputation, Some(95));
+        assert_eq!(node.onchain_reputation, Some(80));
+        assert!(!node.reputation_mismatch);
+
+        // Reapplying keeps the coordinator's original figure
+        apply_onchain_reputation(&mut node, 85);
+        assert_eq!(node.reported_reputation, Some(95));
+        assert_eq!(node.reputation, 85);
+    }
+
+    #[test]
+    fn test_inflated_reputation_flagged() {
+        let mut node = NodeDiscovery::new().get_fallback_nodes().remove(0);
+        node.reputation = 100;
+
+        apply_onchain_reputation(&mut node, 100 - MAX_REPUTATION_DIVERGENCE - 1);
+        assert!(node.reputation_mismatch);
+
+        apply_onchain_reputation(&mut node, 100 - MAX_REPUTATION_DIVERGENCE);
+        assert!(!node.reputation_mismatch);
+    }
+}
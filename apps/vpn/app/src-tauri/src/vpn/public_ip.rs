@@ -0,0 +1,112 @@
+//! Public (egress) IP detection
+//!
+//! After a tunnel comes up, an IP echo service is queried from the tunnel
+//! address to learn the exit IP the rest of the internet sees. The result is
+//! compared against the IP seen before connecting, so a tunnel that isn't
+//! actually carrying traffic can be flagged.
+
+use super::{VPNError, VPNManager};
+use serde::Serialize;
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Returns the caller's IP as plain text
+const IP_ECHO_URL: &str = "https://api.ipify.org";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The tunnel may need a moment after the handshake before it carries traffic
+const ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Exit IP detected for a connection, forwarded to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicIpEvent {
+    pub connection_id: String,
+    pub public_ip: String,
+    /// IP seen before connecting, if it could be determined
+    pub direct_ip: Option<String>,
+    /// False when traffic still appears to leave from `direct_ip`
+    pub changed: bool,
+}
+
+/// Query the echo service once, optionally from a specific local address
+pub async fn fetch_public_ip(local_address: Option<IpAddr>) -> Result<IpAddr, VPNError> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .local_address(local_address)
+        .build()
+        .map_err(|e| VPNError::ConnectionFailed(format!("HTTP client error: {}", e)))?;
+
+    let body = client
+        .get(IP_ECHO_URL)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| VPNError::ConnectionFailed(format!("IP echo request failed: {}", e)))?
+        .text()
+        .await
+        .map_err(|e| VPNError::ConnectionFailed(format!("Invalid IP echo response: {}", e)))?;
+
+    body.trim()
+        .parse()
+        .map_err(|e| VPNError::ConnectionFailed(format!("Invalid IP echo response: {}", e)))
+}
+
+/// Detect the current connection's exit IP and record it on the connection.
+///
+/// Returns `Ok(None)` if the connection went away or was replaced meanwhile.
+pub async fn refresh_public_ip(
+    vpn: &RwLock<VPNManager>,
+) -> Result<Option<PublicIpEvent>, VPNError> {
+    let (connection_id, local_ip, direct_ip) = {
+        let vpn = vpn.read().await;
+        let connection = vpn.get_connection().ok_or(VPNError::NotConnected)?;
+        (
+            connection.connection_id.clone(),
+            connection
+                .local_ip
+                .as_deref()
+                .and_then(|ip| ip.parse::<IpAddr>().ok()),
+            vpn.direct_ip(),
+        )
+    };
+
+    let mut attempt = 0;
+    let public_ip = loop {
+        attempt += 1;
+        match fetch_public_ip(local_ip).await {
+            Ok(ip) => break ip,
+            Err(e) if attempt < ATTEMPTS => {
+                tracing::debug!("Public IP check attempt {} failed: {}", attempt, e);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    let changed = direct_ip != Some(public_ip);
+    if !changed {
+        tracing::warn!(
+            "Exit IP {} matches the pre-connect IP; traffic may not be using the tunnel",
+            public_ip
+        );
+    }
+
+    if !vpn
+        .write()
+        .await
+        .set_public_ip(&connection_id, public_ip.to_string())
+    {
+        return Ok(None);
+    }
+
+    tracing::info!("VPN exit IP: {}", public_ip);
+    Ok(Some(PublicIpEvent {
+        connection_id,
+        public_ip: public_ip.to_string(),
+        direct_ip: direct_ip.map(|ip| ip.to_string()),
+        changed,
+    }))
+}
@@ -0,0 +1,257 @@
+//! On-chain VPN node registry
+//!
+//! Read-only client for the `VPNRegistry` contract, used to discover exit nodes
+//! when the coordinator is unreachable. Calls go straight to `eth_call` on the
+//! configured RPC endpoint; nothing is signed or sent.
+
+use super::{NodeCapabilities, VPNError, VPNNode};
+use alloy::primitives::{Address, Bytes};
+use alloy::sol_types::SolCall;
+use futures_util::future::join_all;
+use serde::Deserialize;
+
+alloy::sol! {
+    interface IVPNRegistry {
+        struct NodeCapabilities {
+            bool supportsWireGuard;
+            bool supportsSOCKS5;
+            bool supportsHTTPConnect;
+            bool servesCDN;
+            bool isVPNExit;
+        }
+
+        struct VPNNode {
+            address operator;
+            bytes2 countryCode;
+            bytes32 regionHash;
+            string endpoint;
+            string wireguardPubKey;
+            uint256 stake;
+            uint256 registeredAt;
+            uint256 lastSeen;
+            NodeCapabilities capabilities;
+            bool active;
+            uint256 totalBytesServed;
+            uint256 totalSessions;
+            uint256 successfulSessions;
+        }
+
+        function getNode(address operator) external view returns (VPNNode memory);
+        function getActiveExitNodes() external view returns (address[] memory);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<String>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    message: String,
+}
+
+/// Client for the VPN node registry contract
+#[derive(Clone)]
+pub struct RegistryClient {
+    client: reqwest::Client,
+    rpc_url: String,
+    address: Address,
+}
+
+impl RegistryClient {
+    /// Returns `None` for an unparseable or zero address (registry not deployed)
+    pub fn new(rpc_url: &str, address: &str) -> Option<Self> {
+        let address: Address = address.parse().ok()?;
+        if address.is_zero() {
+            return None;
+        }
+
+        Some(Self {
+            client: reqwest::Client::new(),
+            rpc_url: rpc_url.to_string(),
+            address,
+        })
+    }
+
+    /// Fetch every active exit node. Nodes that fail to load are skipped.
+    pub async fn active_exit_nodes(&self) -> Result<Vec<VPNNode>, VPNError> {
+        let operators = self.call(IVPNRegistry::getActiveExitNodesCall {}).await?._0;
+
+        let nodes = join_all(operators.iter().map(|operator| {
+            self.call(IVPNRegistry::getNodeCall {
+                operator: *operator,
+            })
+        }))
+        .await;
+
+        Ok(nodes
+            .into_iter()
+            .zip(&operators)
+            .filter_map(|(result, operator)| match result {
+                Ok(node) => Some(node._0),
+                Err(e) => {
+                    tracing::debug!("Failed to load registry node {}: {}", operator, e);
+                    None
+                }
+            })
+            .filter(|node| node.active && node.capabilities.isVPNExit)
+            .map(registry_node_to_vpn_node)
+            .collect())
+    }
+
+    /// Session reputation of the node `operator` registered, or `None` if it
+    /// isn't registered
+    pub async fn reputation(&self, operator: &str) -> Result<Option<u8>, VPNError> {
+        let Ok(operator) = operator.parse::<Address>() else {
+            return Ok(None);
+        };
+
+        let node = self.call(IVPNRegistry::getNodeCall { operator }).await?._0;
+        if node.operator.is_zero() {
+            return Ok(None);
+        }
+        Ok(Some(session_reputation(&node)))
+    }
+
+    async fn call<C: SolCall>(&self, call: C) -> Result<C::Return, VPNError> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_call",
+            "params": [
+                { "to": self.address, "data": Bytes::from(call.abi_encode()) },
+                "latest"
+            ],
+        });
+
+        let response: RpcResponse = self
+            .client
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| VPNError::DiscoveryError(format!("Registry RPC failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| {
+                VPNError::DiscoveryError(format!("Invalid registry RPC response: {}", e))
+            })?;
+
+        if let Some(error) = response.error {
+            return Err(VPNError::DiscoveryError(format!(
+                "Registry call failed: {}",
+                error.message
+            )));
+        }
+
+        let data: Bytes = response
+            .result
+            .ok_or_else(|| VPNError::DiscoveryError("Empty registry RPC response".to_string()))?
+            .parse()
+            .map_err(|e| {
+                VPNError::DiscoveryError(format!("Invalid registry call result: {}", e))
+            })?;
+
+        C::abi_decode_returns(&data, true)
+            .map_err(|e| VPNError::DiscoveryError(format!("Failed to decode registry call: {}", e)))
+    }
+}
+
+/// Sessions are the only quality signal on-chain; mirror `getSuccessRate`
+fn session_reputation(node: &IVPNRegistry::VPNNode) -> u8 {
+    let total: u128 = node.totalSessions.try_into().unwrap_or(u128::MAX);
+    let successful: u128 = node.successfulSessions.try_into().unwrap_or(u128::MAX);
+    if total == 0 {
+        100
+    } else {
+        (successful.min(total) * 100 / total) as u8
+    }
+}
+
+fn registry_node_to_vpn_node(node: IVPNRegistry::VPNNode) -> VPNNode {
+    let operator = node.operator.to_checksum(None);
+    let reputation = session_reputation(&node);
+
+    VPNNode {
+        node_id: operator.clone(),
+        operator,
+        country_code: String::from_utf8_lossy(node.countryCode.as_slice()).into_owned(),
+        // Only the region hash is stored on-chain
+        region: String::new(),
+        endpoint: node.endpoint,
+        wireguard_pubkey: node.wireguardPubKey,
+        latency_ms: 0, // Will be measured
+        load: 0,       // Not tracked on-chain
+        reputation,
+        reported_reputation: None,
+        onchain_reputation: Some(reputation),
+        reputation_mismatch: false,
+        capabilities: NodeCapabilities {
+            supports_wireguard: node.capabilities.supportsWireGuard,
+            supports_socks5: node.capabilities.supportsSOCKS5,
+            supports_http: node.capabilities.supportsHTTPConnect,
+            serves_cdn: node.capabilities.servesCDN,
+            is_vpn_exit: node.capabilities.isVPNExit,
+        },
+        assigned_ip: None,
+        assigned_ipv6: None,
+        preshared_key: None,
+        registry_only: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vpn::{is_selectable, KillSwitchMode, VPNManager};
+    use alloy::primitives::{FixedBytes, B256, U256};
+    use futures_util::FutureExt;
+
+    fn registry_node() -> IVPNRegistry::VPNNode {
+        IVPNRegistry::VPNNode {
+            operator: Address::repeat_byte(0x11),
+            countryCode: FixedBytes(*b"NL"),
+            regionHash: B256::ZERO,
+            endpoint: "203.0.113.7:51820".to_string(),
+            wireguardPubKey: "aGVsbG8gd29ybGQgdGhpcyBpcyBhIHRlc3Qga2V5".to_string(),
+            stake: U256::from(1_000u64),
+            registeredAt: U256::ZERO,
+            lastSeen: U256::ZERO,
+            capabilities: IVPNRegistry::NodeCapabilities {
+                supportsWireGuard: true,
+                supportsSOCKS5: false,
+                supportsHTTPConnect: false,
+                servesCDN: false,
+                isVPNExit: true,
+            },
+            active: true,
+            totalBytesServed: U256::ZERO,
+            totalSessions: U256::from(10u64),
+            successfulSessions: U256::from(9u64),
+        }
+    }
+
+    #[test]
+    fn test_registry_node_without_coordinator_is_not_connectable() {
+        let node = registry_node_to_vpn_node(registry_node());
+        assert!(node.registry_only);
+        assert_eq!(node.reputation, 90);
+        assert_eq!(node.assigned_ip, None);
+        assert!(!is_selectable(&node));
+
+        // Picking it explicitly fails before any tunnel or firewall work
+        let mut manager = VPNManager::new();
+        let result = manager
+            .connect(Some(node.clone()))
+            .now_or_never()
+            .expect("no coordinator to wait for");
+        assert!(matches!(
+            result,
+            Err(VPNError::CoordinatorUnavailable(id)) if id == node.node_id
+        ));
+        assert!(manager.get_connection().is_none());
+        assert_eq!(manager.kill_switch_mode(), KillSwitchMode::Off);
+    }
+}
@@ -0,0 +1,492 @@
+//! Split tunneling
+//!
+//! Chooses which destinations go through the tunnel:
+//! - `Off`: everything
+//! - `Include`: only the listed CIDRs
+//! - `Exclude`: everything except the listed CIDRs
+//!
+//! Every mode is reduced to the set of prefixes routed via the tunnel interface,
+//! which doubles as the peer's allowed IPs. The WireGuard endpoint is always
+//! carved out so encrypted traffic keeps using the physical route, and `/0`
+//! prefixes are split into two `/1`s so they outrank the host's default route
+//! instead of replacing it.
+//!
+//! App-based rules are stored but not enforced yet. Per-app splitting on Windows
+//! (WFP filters matching `FWPM_CONDITION_ALE_APP_ID`) is a follow-up.
+
+use super::VPNError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// Which traffic goes through the tunnel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitTunnelMode {
+    /// Route everything through the tunnel
+    #[default]
+    Off,
+    /// Route only the listed CIDRs through the tunnel
+    Include,
+    /// Route everything except the listed CIDRs through the tunnel
+    Exclude,
+}
+
+/// Split tunnel settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitTunnelConfig {
+    pub mode: SplitTunnelMode,
+    /// Destinations included or excluded, e.g. "10.0.0.0/8" or a bare address
+    pub cidrs: Vec<String>,
+    /// Application paths included or excluded (not enforced yet)
+    pub apps: Vec<String>,
+}
+
+impl SplitTunnelConfig {
+    /// Check the rules without applying them
+    pub fn validate(&self) -> Result<(), VPNError> {
+        let cidrs = self.parse_cidrs()?;
+        if self.mode == SplitTunnelMode::Include && cidrs.is_empty() {
+            return Err(VPNError::TunnelError(
+                "Split tunnel include mode needs at least one CIDR".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Prefixes routed through the tunnel (and allowed from the peer)
+    pub fn tunneled(&self, endpoint: IpAddr) -> Result<Vec<Cidr>, VPNError> {
+        self.validate()?;
+        if !self.apps.is_empty() && self.mode != SplitTunnelMode::Off {
+            tracing::warn!("App-based split tunneling is not supported yet; ignoring app rules");
+        }
+
+        let endpoint = Cidr::host(endpoint);
+        let cidrs = self.parse_cidrs()?;
+
+        let tunneled = match self.mode {
+            SplitTunnelMode::Off => subtract_all(everything(), &[endpoint]),
+            SplitTunnelMode::Exclude => {
+                let mut excluded = cidrs;
+                excluded.push(endpoint);
+                subtract_all(everything(), &excluded)
+            }
+            SplitTunnelMode::Include => subtract_all(cidrs, &[endpoint]),
+        };
+
+        Ok(tunneled
+            .into_iter()
+            .flat_map(|cidr| {
+                if cidr.prefix == 0 {
+                    let (lower, upper) = cidr.halves();
+                    vec![lower, upper]
+                } else {
+                    vec![cidr]
+                }
+            })
+            .collect())
+    }
+
+    /// Destinations that deliberately bypass the tunnel, for the kill switch
+    pub fn bypassed(&self) -> Result<Vec<Cidr>, VPNError> {
+        let cidrs = self.parse_cidrs()?;
+        Ok(match self.mode {
+            SplitTunnelMode::Off => vec![],
+            SplitTunnelMode::Exclude => cidrs,
+            SplitTunnelMode::Include => subtract_all(everything(), &cidrs),
+        })
+    }
+
+    fn parse_cidrs(&self) -> Result<Vec<Cidr>, VPNError> {
+        self.cidrs.iter().map(|cidr| cidr.parse()).collect()
+    }
+}
+
+/// An IPv4 or IPv6 prefix with its host bits cleared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, VPNError> {
+        let width = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > width {
+            return Err(VPNError::TunnelError(format!(
+                "Invalid prefix length /{} for {}",
+                prefix, addr
+            )));
+        }
+
+        let cidr = Self { addr, prefix };
+        Ok(cidr.with_bits(cidr.bits() & cidr.mask(), prefix))
+    }
+
+    /// Single-address prefix
+    pub fn host(addr: IpAddr) -> Self {
+        let prefix = if addr.is_ipv4() { 32 } else { 128 };
+        Self { addr, prefix }
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        self.addr.is_ipv4()
+    }
+
+    /// Whether every address in `other` is also in `self`
+    pub fn contains(&self, other: &Cidr) -> bool {
+        self.is_ipv4() == other.is_ipv4()
+            && self.prefix <= other.prefix
+            && other.bits() & self.mask() == self.bits()
+    }
+
+    /// Everything in `self` that is not in `excluded`
+    pub fn subtract(self, excluded: &Cidr) -> Vec<Cidr> {
+        if excluded.contains(&self) {
+            return vec![];
+        }
+        if !self.contains(excluded) {
+            return vec![self];
+        }
+
+        let (lower, upper) = self.halves();
+        let mut remaining = lower.subtract(excluded);
+        remaining.extend(upper.subtract(excluded));
+        remaining
+    }
+
+    fn width(&self) -> u8 {
+        if self.is_ipv4() {
+            32
+        } else {
+            128
+        }
+    }
+
+    fn bits(&self) -> u128 {
+        match self.addr {
+            IpAddr::V4(addr) => u32::from(addr) as u128,
+            IpAddr::V6(addr) => u128::from(addr),
+        }
+    }
+
+    fn mask(&self) -> u128 {
+        let full = u128::MAX >> (128 - self.width() as u32);
+        full & !full.checked_shr(self.prefix as u32).unwrap_or(0)
+    }
+
+    fn with_bits(&self, bits: u128, prefix: u8) -> Self {
+        let addr = if self.is_ipv4() {
+            IpAddr::V4(Ipv4Addr::from(bits as u32))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(bits))
+        };
+        Self { addr, prefix }
+    }
+
+    /// The two prefixes one bit longer that make up `self`
+    fn halves(&self) -> (Cidr, Cidr) {
+        let prefix = self.prefix + 1;
+        let upper_bit = 1u128 << (self.width() - prefix);
+        (
+            self.with_bits(self.bits(), prefix),
+            self.with_bits(self.bits() | upper_bit, prefix),
+        )
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = VPNError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VPNError::TunnelError(format!("Invalid CIDR: {}", s));
+
+        match s.trim().split_once('/') {
+            Some((addr, prefix)) => Cidr::new(
+                addr.parse().map_err(|_| invalid())?,
+                prefix.parse().map_err(|_| invalid())?,
+            ),
+            None => Ok(Cidr::host(s.trim().parse().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// All IPv4 and IPv6 addresses
+fn everything() -> Vec<Cidr> {
+    vec![
+        Cidr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0).expect("valid prefix"),
+        Cidr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0).expect("valid prefix"),
+    ]
+}
+
+fn subtract_all(ranges: Vec<Cidr>, excluded: &[Cidr]) -> Vec<Cidr> {
+    excluded.iter().fold(ranges, |ranges, excluded| {
+        ranges
+            .into_iter()
+            .flat_map(|range| range.subtract(excluded))
+            .collect()
+    })
+}
+
+/// Routes installed through the tunnel interface, removed again on drop
+pub struct TunnelRoutes {
+    interface: String,
+    routes: Vec<Cidr>,
+}
+
+impl TunnelRoutes {
+    /// Route `routes` via `interface`. IPv6 failures are only logged, since
+    /// IPv6 may be disabled on the host.
+    pub fn install(interface: &str, routes: &[Cidr]) -> Result<Self, VPNError> {
+        let mut installed = Self {
+            interface: interface.to_string(),
+            routes: Vec::with_capacity(routes.len()),
+        };
+
+        for route in routes {
+            match add_route(interface, route) {
+                Ok(()) => installed.routes.push(*route),
+                Err(e) if !route.is_ipv4() => {
+                    tracing::warn!("Skipping IPv6 route {}: {}", route, e);
+                }
+                // Dropping `installed` removes the routes added so far
+                Err(e) => return Err(e),
+            }
+        }
+
+        tracing::info!(
+            "Routed {} prefixes through {}",
+            installed.routes.len(),
+            interface
+        );
+        Ok(installed)
+    }
+}
+
+impl Drop for TunnelRoutes {
+    fn drop(&mut self) {
+        for route in &self.routes {
+            if let Err(e) = delete_route(&self.interface, route) {
+                tracing::debug!("Failed to remove route {}: {}", route, e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn add_route(interface: &str, route: &Cidr) -> Result<(), VPNError> {
+    run_route_command(
+        "ip",
+        &["route", "replace", &route.to_string(), "dev", interface],
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn delete_route(interface: &str, route: &Cidr) -> Result<(), VPNError> {
+    run_route_command(
+        "ip",
+        &["route", "del", &route.to_string(), "dev", interface],
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn add_route(interface: &str, route: &Cidr) -> Result<(), VPNError> {
+    let family = if route.is_ipv4() { "-inet" } else { "-inet6" };
+    run_route_command(
+        "route",
+        &[
+            "-n",
+            "add",
+            family,
+            "-net",
+            &route.to_string(),
+            "-interface",
+            interface,
+        ],
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn delete_route(interface: &str, route: &Cidr) -> Result<(), VPNError> {
+    let family = if route.is_ipv4() { "-inet" } else { "-inet6" };
+    run_route_command(
+        "route",
+        &[
+            "-n",
+            "delete",
+            family,
+            "-net",
+            &route.to_string(),
+            "-interface",
+            interface,
+        ],
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn add_route(interface: &str, route: &Cidr) -> Result<(), VPNError> {
+    let family = if route.is_ipv4() { "ipv4" } else { "ipv6" };
+    run_route_command(
+        "netsh",
+        &[
+            "interface",
+            family,
+            "add",
+            "route",
+            &route.to_string(),
+            interface,
+            "store=active",
+        ],
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn delete_route(interface: &str, route: &Cidr) -> Result<(), VPNError> {
+    let family = if route.is_ipv4() { "ipv4" } else { "ipv6" };
+    run_route_command(
+        "netsh",
+        &[
+            "interface",
+            family,
+            "delete",
+            "route",
+            &route.to_string(),
+            interface,
+        ],
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn add_route(_interface: &str, _route: &Cidr) -> Result<(), VPNError> {
+    Err(VPNError::TunnelError(
+        "Routing unsupported on this platform".to_string(),
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn delete_route(_interface: &str, _route: &Cidr) -> Result<(), VPNError> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run_route_command(program: &str, args: &[&str]) -> Result<(), VPNError> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| VPNError::TunnelError(format!("Failed to run {}: {}", program, e)))?;
+
+    if !output.status.success() {
+        return Err(VPNError::TunnelError(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    fn endpoint() -> IpAddr {
+        "203.0.113.7".parse().unwrap()
+    }
+
+    fn config(mode: SplitTunnelMode, cidrs: &[&str]) -> SplitTunnelConfig {
+        SplitTunnelConfig {
+            mode,
+            cidrs: cidrs.iter().map(|c| c.to_string()).collect(),
+            apps: vec![],
+        }
+    }
+
+    /// Whether `addr` falls in any of `cidrs`
+    fn covered(cidrs: &[Cidr], addr: &str) -> bool {
+        let host = Cidr::host(addr.parse().unwrap());
+        cidrs.iter().any(|c| c.contains(&host))
+    }
+
+    #[test]
+    fn test_parse_cidr_normalizes_host_bits() {
+        assert_eq!(cidr("192.168.1.77/24").to_string(), "192.168.1.0/24");
+        assert_eq!(cidr("10.1.2.3").to_string(), "10.1.2.3/32");
+        assert_eq!(cidr("2001:db8::1/32").to_string(), "2001:db8::/32");
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("not-a-cidr".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_subtract_leaves_exact_complement() {
+        let remaining = cidr("10.0.0.0/8").subtract(&cidr("10.1.0.0/16"));
+        assert_eq!(remaining.len(), 8);
+        assert!(!covered(&remaining, "10.1.2.3"));
+        assert!(covered(&remaining, "10.0.0.1"));
+        assert!(covered(&remaining, "10.255.0.1"));
+
+        // Different families never overlap
+        assert_eq!(
+            cidr("::/0").subtract(&cidr("10.0.0.0/8")),
+            vec![cidr("::/0")]
+        );
+    }
+
+    #[test]
+    fn test_full_tunnel_excludes_endpoint_and_avoids_default_routes() {
+        let routes = config(SplitTunnelMode::Off, &[])
+            .tunneled(endpoint())
+            .unwrap();
+
+        assert!(!covered(&routes, "203.0.113.7"));
+        assert!(covered(&routes, "203.0.113.8"));
+        assert!(covered(&routes, "2001:db8::1"));
+        assert!(routes.iter().all(|r| r.prefix() > 0));
+        assert!(routes.contains(&cidr("::/1")));
+        assert!(routes.contains(&cidr("8000::/1")));
+    }
+
+    #[test]
+    fn test_include_mode_routes_only_listed_cidrs() {
+        let split = config(SplitTunnelMode::Include, &["10.0.0.0/8", "192.168.50.0/24"]);
+        let routes = split.tunneled(endpoint()).unwrap();
+
+        assert_eq!(routes, vec![cidr("10.0.0.0/8"), cidr("192.168.50.0/24")]);
+        assert!(covered(&split.bypassed().unwrap(), "8.8.8.8"));
+        assert!(!covered(&split.bypassed().unwrap(), "10.2.3.4"));
+
+        assert!(config(SplitTunnelMode::Include, &[]).validate().is_err());
+    }
+
+    #[test]
+    fn test_exclude_mode_routes_everything_else() {
+        let split = config(SplitTunnelMode::Exclude, &["192.168.0.0/16"]);
+        let routes = split.tunneled(endpoint()).unwrap();
+
+        assert!(!covered(&routes, "192.168.1.1"));
+        assert!(!covered(&routes, "203.0.113.7"));
+        assert!(covered(&routes, "8.8.8.8"));
+        assert_eq!(split.bypassed().unwrap(), vec![cidr("192.168.0.0/16")]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Connection supervisor
+//!
+//! Watches the active tunnel and, when it fails (tunnel task error or expired
+//! handshake), moves the connection to `Reconnecting` and retries with
+//! exponential backoff, optionally failing over to the next-best node.
+
+use super::{VPNConnection, VPNManager};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// How often the tunnel state is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Reconnect behaviour, taken from settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Reconnect automatically when the tunnel fails
+    pub enabled: bool,
+    /// Give up after this many attempts
+    pub max_attempts: u32,
+    /// Try the next-best node after the first failed attempt
+    pub failover: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_attempts: 5,
+            failover: true,
+        }
+    }
+}
+
+/// Supervisor progress, forwarded to the UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SupervisorEvent {
+    Reconnecting {
+        attempt: u32,
+        max_attempts: u32,
+        node_id: String,
+    },
+    Reconnected {
+        connection: VPNConnection,
+    },
+    ReconnectFailed {
+        attempts: u32,
+    },
+}
+
+/// Run the supervisor forever, reporting progress through `on_event`
+pub async fn supervise<F>(vpn: Arc<RwLock<VPNManager>>, on_event: F)
+where
+    F: Fn(SupervisorEvent) + Send + 'static,
+{
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        if !vpn.read().await.tunnel_failed().await {
+            continue;
+        }
+
+        let policy = vpn.read().await.reconnect_policy().clone();
+        if !policy.enabled {
+            tracing::warn!("Tunnel failed and auto-reconnect is disabled");
+            vpn.write().await.mark_failed();
+            continue;
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut reconnected = false;
+        let mut attempts = 0;
+
+        while attempts < policy.max_attempts {
+            attempts += 1;
+
+            // The user may have disconnected while we were backing off
+            let Some(node_id) = vpn.write().await.begin_reconnect() else {
+                break;
+            };
+
+            tracing::info!(
+                "Reconnecting to {} (attempt {}/{})",
+                node_id,
+                attempts,
+                policy.max_attempts
+            );
+            on_event(SupervisorEvent::Reconnecting {
+                attempt: attempts,
+                max_attempts: policy.max_attempts,
+                node_id,
+            });
+
+            tokio::time::sleep(backoff).await;
+
+            let failover = policy.failover && attempts > 1;
+            match vpn.write().await.reconnect(failover).await {
+                Ok(connection) => {
+                    tracing::info!("Reconnected to {}", connection.node.node_id);
+                    on_event(SupervisorEvent::Reconnected { connection });
+                    reconnected = true;
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!("Reconnect attempt {} failed: {}", attempts, e);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+
+        if !reconnected && vpn.write().await.mark_failed() {
+            tracing::error!("Giving up reconnecting after {} attempts", attempts);
+            on_event(SupervisorEvent::ReconnectFailed { attempts });
+        }
+    }
+}
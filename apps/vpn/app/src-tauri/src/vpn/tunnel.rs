@@ -23,12 +23,24 @@
 //! let dev = tun::create(&config)?;
 //! ```
 
-use super::VPNError;
-use std::net::Ipv4Addr;
+use super::{Cidr, VPNError};
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 /// Maximum transmission unit for tunnel interface
 pub const TUNNEL_MTU: u16 = 1420;
 
+/// Prefix length of the IPv6 tunnel subnet
+pub const TUNNEL_IPV6_PREFIX: u8 = 64;
+
+/// Smallest configurable MTU; below this IPv6 breaks and overhead dominates
+pub const MIN_TUNNEL_MTU: u16 = 1280;
+
+/// Keep a configured MTU within what the tunnel can carry.
+/// `TUNNEL_MTU` is already the most a 1500-byte link fits after WireGuard overhead.
+pub fn clamp_mtu(mtu: u16) -> u16 {
+    mtu.clamp(MIN_TUNNEL_MTU, TUNNEL_MTU)
+}
+
 /// Validate interface name to prevent command injection
 /// Only allows alphanumeric characters and underscores, max 15 chars
 fn validate_interface_name(name: &str) -> Result<(), VPNError> {
@@ -73,8 +85,12 @@ pub struct TunConfig {
     pub name: String,
     pub address: Ipv4Addr,
     pub netmask: Ipv4Addr,
+    /// IPv6 address on the tunnel subnet; `None` for an IPv4-only interface
+    pub address_v6: Option<Ipv6Addr>,
     pub mtu: u16,
     pub dns: Vec<Ipv4Addr>,
+    /// Destinations routed through the interface
+    pub routes: Vec<Cidr>,
 }
 
 impl Default for TunConfig {
@@ -83,8 +99,10 @@ impl Default for TunConfig {
             name: "jeju0".to_string(),
             address: Ipv4Addr::new(10, 0, 0, 2),
             netmask: Ipv4Addr::new(255, 255, 255, 0),
+            address_v6: None,
             mtu: TUNNEL_MTU,
             dns: vec![Ipv4Addr::new(1, 1, 1, 1), Ipv4Addr::new(8, 8, 8, 8)],
+            routes: vec![],
         }
     }
 }
@@ -599,5 +617,14 @@ mod tests {
         assert_eq!(config.name, "jeju0");
         assert_eq!(config.mtu, TUNNEL_MTU);
         assert_eq!(config.address, Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(config.address_v6, None);
+        assert!(config.routes.is_empty());
+    }
+
+    #[test]
+    fn test_clamp_mtu() {
+        assert_eq!(clamp_mtu(1380), 1380);
+        assert_eq!(clamp_mtu(576), MIN_TUNNEL_MTU);
+        assert_eq!(clamp_mtu(9000), TUNNEL_MTU);
     }
 }
@@ -6,14 +6,22 @@
 //! - Handles encryption/decryption of packets
 //! - Manages handshakes and timers
 
-use super::VPNError;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use super::kill_switch::run_with_stdin;
+use super::multihop::{HopOutput, InnerHop, HOP_SOURCE_PORT};
+use super::{
+    clamp_mtu, multihop_mtu, ExitHop, KillSwitch, TunConfig, TunnelRoutes, VPNError,
+    TUNNEL_IPV6_PREFIX, TUNNEL_MTU,
+};
+use boringtun::noise::errors::WireGuardError;
 use boringtun::noise::{Tunn, TunnResult};
 use boringtun::x25519::{PublicKey, StaticSecret};
 use parking_lot::Mutex;
-use std::net::{SocketAddr, UdpSocket};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::net::UdpSocket;
 use tokio::sync::mpsc;
 
 /// WireGuard configuration
@@ -22,9 +30,20 @@ pub struct WireGuardConfig {
     pub private_key: String,
     pub peer_pubkey: String,
     pub endpoint: String,
+    /// CIDRs routed through the tunnel
     pub allowed_ips: Vec<String>,
     pub dns: Vec<String>,
     pub keepalive: u16,
+    /// Entry tunnel MTU, for links that fragment at the default `TUNNEL_MTU`
+    pub mtu: Option<u16>,
+    /// Tunnel address assigned to this client by the node
+    pub assigned_ip: Option<String>,
+    /// IPv6 tunnel address assigned by the node; `None` for an IPv4-only tunnel
+    pub assigned_ipv6: Option<String>,
+    /// Base64 pre-shared key mixed into the handshake, if the node issued one
+    pub preshared_key: Option<String>,
+    /// Exit node to nest inside this tunnel, for multi-hop
+    pub exit_hop: Option<ExitHop>,
 }
 
 /// WireGuard tunnel state
@@ -37,13 +56,80 @@ pub enum TunnelState {
     Error,
 }
 
-/// Maximum transmission unit for WireGuard packets
-#[allow(dead_code)]
-const MTU: usize = 1420;
-
 /// Buffer size for packet handling (must be > MTU + overhead)
 const BUFFER_SIZE: usize = 2048;
 
+/// Traffic counters for one tunnel
+///
+/// Data counters are taken at the TUN device, so they measure the IP packets the
+/// user actually sent and received. Wire counters see every datagram exchanged
+/// with the peer, handshakes and keepalives included; the difference is the
+/// WireGuard (and, for multi-hop, exit hop) overhead.
+#[derive(Debug, Default)]
+pub struct TunnelStats {
+    data_bytes_up: AtomicU64,
+    data_bytes_down: AtomicU64,
+    packets_up: AtomicU64,
+    packets_down: AtomicU64,
+    wire_bytes_up: AtomicU64,
+    wire_bytes_down: AtomicU64,
+}
+
+impl TunnelStats {
+    /// A packet read from the TUN device, headed into the tunnel
+    pub fn record_tun_read(&self, len: usize) {
+        self.data_bytes_up.fetch_add(len as u64, Ordering::Relaxed);
+        self.packets_up.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A packet from the tunnel written to the TUN device
+    pub fn record_tun_write(&self, len: usize) {
+        self.data_bytes_down
+            .fetch_add(len as u64, Ordering::Relaxed);
+        self.packets_down.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A datagram sent to the peer
+    pub fn record_sent(&self, len: usize) {
+        self.wire_bytes_up.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// A datagram received from the peer
+    pub fn record_received(&self, len: usize) {
+        self.wire_bytes_down
+            .fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// User data (bytes up, bytes down)
+    pub fn transfer(&self) -> (u64, u64) {
+        (
+            self.data_bytes_up.load(Ordering::Relaxed),
+            self.data_bytes_down.load(Ordering::Relaxed),
+        )
+    }
+
+    /// User packets (packets up, packets down)
+    pub fn packets(&self) -> (u64, u64) {
+        (
+            self.packets_up.load(Ordering::Relaxed),
+            self.packets_down.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Bytes on the wire beyond the user data (bytes up, bytes down)
+    pub fn overhead(&self) -> (u64, u64) {
+        let (data_up, data_down) = self.transfer();
+        (
+            self.wire_bytes_up
+                .load(Ordering::Relaxed)
+                .saturating_sub(data_up),
+            self.wire_bytes_down
+                .load(Ordering::Relaxed)
+                .saturating_sub(data_down),
+        )
+    }
+}
+
 /// WireGuard tunnel manager using boringtun
 pub struct WireGuardTunnel {
     config: WireGuardConfig,
@@ -51,16 +137,19 @@ pub struct WireGuardTunnel {
     running: Arc<AtomicBool>,
 
     // Statistics
-    bytes_up: Arc<AtomicU64>,
-    bytes_down: Arc<AtomicU64>,
-    packets_up: Arc<AtomicU64>,
-    packets_down: Arc<AtomicU64>,
+    stats: Arc<TunnelStats>,
+    hop_bytes_up: Arc<AtomicU64>,
+    hop_bytes_down: Arc<AtomicU64>,
 
-    // Assigned IP
+    // Assigned IPs
     local_ip: Arc<Mutex<Option<String>>>,
+    local_ipv6: Arc<Mutex<Option<String>>>,
 
     // Shutdown signal
     shutdown_tx: Option<mpsc::Sender<()>>,
+
+    // Locks the host down if the tunnel fails
+    kill_switch: Option<Arc<KillSwitch>>,
 }
 
 impl WireGuardTunnel {
@@ -70,15 +159,21 @@ impl WireGuardTunnel {
             config,
             state: Arc::new(Mutex::new(TunnelState::Stopped)),
             running: Arc::new(AtomicBool::new(false)),
-            bytes_up: Arc::new(AtomicU64::new(0)),
-            bytes_down: Arc::new(AtomicU64::new(0)),
-            packets_up: Arc::new(AtomicU64::new(0)),
-            packets_down: Arc::new(AtomicU64::new(0)),
+            stats: Arc::new(TunnelStats::default()),
+            hop_bytes_up: Arc::new(AtomicU64::new(0)),
+            hop_bytes_down: Arc::new(AtomicU64::new(0)),
             local_ip: Arc::new(Mutex::new(None)),
+            local_ipv6: Arc::new(Mutex::new(None)),
             shutdown_tx: None,
+            kill_switch: None,
         })
     }
 
+    /// Block all traffic through this kill switch if the tunnel fails
+    pub fn set_kill_switch(&mut self, kill_switch: Arc<KillSwitch>) {
+        self.kill_switch = Some(kill_switch);
+    }
+
     /// Start the WireGuard tunnel
     pub async fn start(&mut self) -> Result<(), VPNError> {
         *self.state.lock() = TunnelState::Starting;
@@ -93,12 +188,13 @@ impl WireGuardTunnel {
             .map_err(|_| VPNError::TunnelError("Invalid private key".to_string()))?;
 
         let peer_public = PublicKey::from(peer_pubkey);
+        let preshared_key = parse_preshared_key(self.config.preshared_key.as_deref())?;
 
         // Create boringtun tunnel
         let tunn = Tunn::new(
-            static_secret,
+            static_secret.clone(),
             peer_public,
-            None, // Pre-shared key (optional)
+            preshared_key,
             Some(self.config.keepalive),
             0,    // Tunnel index
             None, // Rate limiter (optional)
@@ -114,16 +210,52 @@ impl WireGuardTunnel {
 
         // Create UDP socket for WireGuard traffic
         let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
             .map_err(|e| VPNError::TunnelError(format!("Failed to bind UDP socket: {}", e)))?;
 
-        socket
-            .set_nonblocking(true)
-            .map_err(|e| VPNError::TunnelError(format!("Failed to set non-blocking: {}", e)))?;
-
         socket
             .connect(endpoint)
+            .await
             .map_err(|e| VPNError::TunnelError(format!("Failed to connect to endpoint: {}", e)))?;
 
+        let address: Ipv4Addr = self
+            .config
+            .assigned_ip
+            .as_deref()
+            .ok_or_else(|| VPNError::NoAssignedIp(self.config.endpoint.clone()))?
+            .parse()
+            .map_err(|e| VPNError::TunnelError(format!("Invalid assigned IP: {}", e)))?;
+
+        // Multi-hop: the exit node's tunnel runs inside this one and owns the TUN address
+        let (hop, address) = match &self.config.exit_hop {
+            Some(exit) => {
+                tracing::info!("Nesting exit hop to {}", exit.endpoint);
+                let hop = self.exit_hop(exit, static_secret, address)?;
+                let exit_address = exit
+                    .assigned_ip
+                    .parse()
+                    .map_err(|e| VPNError::TunnelError(format!("Invalid exit hop IP: {}", e)))?;
+                (Some(hop), exit_address)
+            }
+            None => (None, address),
+        };
+
+        let routes = self
+            .config
+            .allowed_ips
+            .iter()
+            .map(|cidr| cidr.parse())
+            .collect::<Result<_, _>>()?;
+        let tun_config = TunConfig {
+            address,
+            address_v6: self.tunnel_ipv6()?,
+            mtu: self.mtu(),
+            routes,
+            ..TunConfig::default()
+        };
+        *self.local_ip.lock() = Some(tun_config.address.to_string());
+        *self.local_ipv6.lock() = tun_config.address_v6.map(|ip| ip.to_string());
+
         // Create shutdown channel
         let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
         self.shutdown_tx = Some(shutdown_tx);
@@ -131,30 +263,35 @@ impl WireGuardTunnel {
         // Start the tunnel processing task
         let running = self.running.clone();
         let state = self.state.clone();
-        let bytes_up = self.bytes_up.clone();
-        let bytes_down = self.bytes_down.clone();
-        let packets_up = self.packets_up.clone();
-        let packets_down = self.packets_down.clone();
-        let local_ip = self.local_ip.clone();
+        let stats = self.stats.clone();
+        let kill_switch = self.kill_switch.clone();
+        let local_ipv6 = self.local_ipv6.clone();
 
         running.store(true, Ordering::SeqCst);
 
         tokio::spawn(async move {
             if let Err(e) = run_tunnel_loop(
                 Box::new(tunn),
+                hop,
                 socket,
+                tun_config,
                 running.clone(),
-                bytes_up,
-                bytes_down,
-                packets_up,
-                packets_down,
-                local_ip,
+                stats,
+                local_ipv6,
                 shutdown_rx,
             )
             .await
             {
                 tracing::error!("Tunnel loop error: {}", e);
                 *state.lock() = TunnelState::Error;
+                running.store(false, Ordering::SeqCst);
+
+                if let Some(kill_switch) = kill_switch {
+                    if let Err(e) = kill_switch.block_all() {
+                        tracing::error!("Kill switch failed to block traffic: {}", e);
+                    }
+                }
+                return;
             }
 
             running.store(false, Ordering::SeqCst);
@@ -167,6 +304,57 @@ impl WireGuardTunnel {
         Ok(())
     }
 
+    /// IPv6 address for the TUN interface. Like the IPv4 one, it belongs to the
+    /// innermost tunnel.
+    fn tunnel_ipv6(&self) -> Result<Option<Ipv6Addr>, VPNError> {
+        let assigned = match &self.config.exit_hop {
+            Some(exit) => exit.assigned_ipv6.as_deref(),
+            None => self.config.assigned_ipv6.as_deref(),
+        };
+        assigned
+            .map(|ip| {
+                ip.parse()
+                    .map_err(|e| VPNError::TunnelError(format!("Invalid assigned IPv6: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// Exit-hop peer, reached from `source` inside this tunnel
+    fn exit_hop(
+        &self,
+        exit: &ExitHop,
+        static_secret: StaticSecret,
+        source: Ipv4Addr,
+    ) -> Result<InnerHop, VPNError> {
+        let endpoint = match exit.endpoint.parse::<SocketAddr>() {
+            Ok(SocketAddr::V4(endpoint)) => endpoint,
+            _ => {
+                return Err(VPNError::TunnelError(format!(
+                    "Exit hop endpoint must be IPv4: {}",
+                    exit.endpoint
+                )))
+            }
+        };
+
+        let tunn = Tunn::new(
+            static_secret,
+            PublicKey::from(parse_base64_key(&exit.peer_pubkey)?),
+            parse_preshared_key(exit.preshared_key.as_deref())?,
+            Some(self.config.keepalive),
+            1, // Tunnel index, distinct from the entry tunnel
+            None,
+        )
+        .map_err(|e| VPNError::TunnelError(format!("Failed to create exit hop: {:?}", e)))?;
+
+        Ok(InnerHop::new(
+            tunn,
+            SocketAddrV4::new(source, HOP_SOURCE_PORT),
+            endpoint,
+            self.hop_bytes_up.clone(),
+            self.hop_bytes_down.clone(),
+        ))
+    }
+
     /// Stop the tunnel
     pub async fn stop(&mut self) -> Result<(), VPNError> {
         *self.state.lock() = TunnelState::Stopping;
@@ -184,6 +372,7 @@ impl WireGuardTunnel {
 
         *self.state.lock() = TunnelState::Stopped;
         *self.local_ip.lock() = None;
+        *self.local_ipv6.lock() = None;
 
         tracing::info!("WireGuard tunnel stopped");
         Ok(())
@@ -199,76 +388,110 @@ impl WireGuardTunnel {
         self.local_ip.lock().clone().ok_or(VPNError::NotConnected)
     }
 
-    /// Get transfer statistics (bytes up, bytes down)
+    /// Assigned local IPv6 address, or `None` for an IPv4-only tunnel
+    pub async fn get_local_ipv6(&self) -> Option<String> {
+        self.local_ipv6.lock().clone()
+    }
+
+    /// MTU of the TUN interface: the configured MTU, clamped, less the exit hop's overhead
+    pub fn mtu(&self) -> u16 {
+        let mtu = clamp_mtu(self.config.mtu.unwrap_or(TUNNEL_MTU));
+        match self.config.exit_hop {
+            Some(_) => multihop_mtu(mtu),
+            None => mtu,
+        }
+    }
+
+    /// Get user data transferred through the TUN device (bytes up, bytes down)
     pub async fn get_transfer_stats(&self) -> Result<(u64, u64), VPNError> {
-        let up = self.bytes_up.load(Ordering::Relaxed);
-        let down = self.bytes_down.load(Ordering::Relaxed);
-        Ok((up, down))
+        Ok(self.stats.transfer())
     }
 
-    /// Get packet statistics (packets up, packets down)
+    /// Get packets read from and written to the TUN device (packets up, packets down)
     pub async fn get_packet_stats(&self) -> Result<(u64, u64), VPNError> {
-        let up = self.packets_up.load(Ordering::Relaxed);
-        let down = self.packets_down.load(Ordering::Relaxed);
-        Ok((up, down))
+        Ok(self.stats.packets())
     }
 
-    /// Record bytes transferred (for external tracking)
+    /// Get WireGuard overhead on the wire (bytes up, bytes down)
+    pub async fn get_overhead_stats(&self) -> Result<(u64, u64), VPNError> {
+        Ok(self.stats.overhead())
+    }
+
+    /// Exit hop traffic (bytes up, bytes down), or `None` for a single-hop tunnel
+    pub async fn get_exit_hop_stats(&self) -> Option<(u64, u64)> {
+        self.config.exit_hop.as_ref()?;
+        let up = self.hop_bytes_up.load(Ordering::Relaxed);
+        let down = self.hop_bytes_down.load(Ordering::Relaxed);
+        Some((up, down))
+    }
+
+    /// Record user data transferred outside the tunnel loop (for external tracking).
+    /// Packet counts are left alone; they only come from the TUN device.
     pub async fn record_transfer(&self, bytes_up: u64, bytes_down: u64) {
-        self.bytes_up.fetch_add(bytes_up, Ordering::Relaxed);
-        self.bytes_down.fetch_add(bytes_down, Ordering::Relaxed);
-        self.packets_up.fetch_add(1, Ordering::Relaxed);
-        self.packets_down.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .data_bytes_up
+            .fetch_add(bytes_up, Ordering::Relaxed);
+        self.stats
+            .data_bytes_down
+            .fetch_add(bytes_down, Ordering::Relaxed);
     }
 }
 
 /// Main tunnel processing loop
+///
+/// Plaintext IP packets read from the TUN device are encapsulated and sent to the
+/// peer; datagrams from the peer are decapsulated and written back to the TUN device.
+/// With an exit `hop`, packets are encapsulated for it first and everything the
+/// peer delivers is handed to it before reaching the TUN device.
+///
+/// If the interface can't take its IPv6 address, `local_ipv6` is cleared and
+/// the tunnel carries IPv4 only.
+#[allow(clippy::too_many_arguments)]
 async fn run_tunnel_loop(
-    tunn: Box<Tunn>,
+    mut tunn: Box<Tunn>,
+    mut hop: Option<InnerHop>,
     socket: UdpSocket,
+    tun_config: TunConfig,
     running: Arc<AtomicBool>,
-    bytes_up: Arc<AtomicU64>,
-    bytes_down: Arc<AtomicU64>,
-    packets_up: Arc<AtomicU64>,
-    packets_down: Arc<AtomicU64>,
-    local_ip: Arc<Mutex<Option<String>>>,
+    stats: Arc<TunnelStats>,
+    local_ipv6: Arc<Mutex<Option<String>>>,
     mut shutdown_rx: mpsc::Receiver<()>,
 ) -> Result<(), VPNError> {
-    let tunn = Arc::new(Mutex::new(tunn));
-    let socket = Arc::new(socket);
-
-    // Create TUN interface
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    let tun_device = create_tun_interface().await?;
+    // Create TUN interface with the tunnel address
+    let mut tun_device = create_tun_interface(&tun_config).await?;
+
+    // The TUN crates only configure IPv4; the IPv6 address is added separately.
+    // Hosts with IPv6 disabled refuse it, which leaves an IPv4-only tunnel.
+    if let Some(address) = tun_config.address_v6 {
+        if let Err(e) = assign_ipv6(&tun_device, address) {
+            tracing::warn!("Tunnel is IPv4-only, IPv6 address not assigned: {}", e);
+            *local_ipv6.lock() = None;
+        }
+    }
 
-    // Set a placeholder IP (will be assigned by server in real implementation)
-    *local_ip.lock() = Some("10.0.0.2".to_string());
+    // Route the allowed IPs through it; removed when the loop exits
+    let _routes = TunnelRoutes::install(&tun_device.name, &tun_config.routes)?;
 
-    // Buffer for receiving data
+    // Buffers for network datagrams, TUN packets and boringtun output
     let mut recv_buf = [0u8; BUFFER_SIZE];
+    let mut tun_buf = [0u8; BUFFER_SIZE];
     let mut send_buf = [0u8; BUFFER_SIZE];
 
     // Initiate handshake
+    if let TunnResult::WriteToNetwork(data) = tunn.format_handshake_initiation(&mut send_buf, false)
     {
-        let mut tunn_guard = tunn.lock();
-        match tunn_guard.format_handshake_initiation(&mut send_buf, false) {
-            TunnResult::WriteToNetwork(data) => {
-                if let Err(e) = socket.send(data) {
-                    tracing::warn!("Failed to send handshake initiation: {}", e);
-                }
-            }
-            _ => {}
-        }
+        send_to_peer(&socket, data, &stats).await;
+    }
+
+    // Queued by the entry tunnel until its own handshake completes
+    if let Some(frame) = hop.as_mut().and_then(|hop| hop.handshake()) {
+        seal_and_send(&mut tunn, &socket, frame, &mut send_buf, &stats).await;
     }
 
     // Timer tick interval for keepalive and handshake management
     let mut timer_interval = tokio::time::interval(Duration::from_millis(250));
 
-    loop {
-        if !running.load(Ordering::SeqCst) {
-            break;
-        }
-
+    while running.load(Ordering::SeqCst) {
         tokio::select! {
             // Check for shutdown signal
             _ = shutdown_rx.recv() => {
@@ -278,103 +501,126 @@ async fn run_tunnel_loop(
 
             // Timer tick for boringtun
             _ = timer_interval.tick() => {
-                let mut tunn_guard = tunn.lock();
-                match tunn_guard.update_timers(&mut send_buf) {
+                match tunn.update_timers(&mut send_buf) {
                     TunnResult::WriteToNetwork(data) => {
-                        if let Err(e) = socket.send(data) {
-                            tracing::warn!("Failed to send timer packet: {}", e);
-                        }
+                        send_to_peer(&socket, data, &stats).await;
+                    }
+                    TunnResult::Err(WireGuardError::ConnectionExpired) => {
+                        return Err(VPNError::TunnelError("Handshake expired".to_string()));
                     }
                     TunnResult::Err(e) => {
                         tracing::warn!("Timer update error: {:?}", e);
                     }
                     _ => {}
                 }
+
+                if let Some(hop) = hop.as_mut() {
+                    if let Some(frame) = hop.update_timers()? {
+                        seal_and_send(
+                            &mut tunn,
+                            &socket,
+                            frame,
+                            &mut send_buf,
+                            &stats,
+                        )
+                        .await;
+                    }
+                }
             }
 
             // Process incoming UDP packets from WireGuard peer
-            _ = tokio::task::yield_now() => {
-                match socket.recv(&mut recv_buf) {
-                    Ok(n) if n > 0 => {
-                        bytes_down.fetch_add(n as u64, Ordering::Relaxed);
-                        packets_down.fetch_add(1, Ordering::Relaxed);
-
-                        // Collect data to write while holding lock, then release before async writes
-                        let mut tun_writes: Vec<Vec<u8>> = Vec::new();
+            received = socket.recv(&mut recv_buf) => {
+                let n = match received {
+                    Ok(n) => n,
+                    Err(e) => {
+                        tracing::warn!("Socket receive error: {}", e);
+                        continue;
+                    }
+                };
+
+                stats.record_received(n);
+
+                match tunn.decapsulate(None, &recv_buf[..n], &mut send_buf) {
+                    TunnResult::WriteToNetwork(data) => {
+                        send_to_peer(&socket, data, &stats).await;
+
+                        // Handshake completion may release queued packets; drain them
+                        while let TunnResult::WriteToNetwork(queued) =
+                            tunn.decapsulate(None, &[], &mut send_buf)
                         {
-                            let mut tunn_guard = tunn.lock();
-                            let mut result = tunn_guard.decapsulate(None, &recv_buf[..n], &mut send_buf);
-
-                            loop {
-                                match result {
-                                    TunnResult::WriteToNetwork(data) => {
-                                        if let Err(e) = socket.send(data) {
-                                            tracing::warn!("Failed to send response: {}", e);
-                                        }
-                                        bytes_up.fetch_add(data.len() as u64, Ordering::Relaxed);
-                                        packets_up.fetch_add(1, Ordering::Relaxed);
-                                    }
-                                    TunnResult::WriteToTunnelV4(data, _src) => {
-                                        tun_writes.push(data.to_vec());
-                                    }
-                                    TunnResult::WriteToTunnelV6(data, _src) => {
-                                        tun_writes.push(data.to_vec());
-                                    }
-                                    TunnResult::Done => break,
-                                    TunnResult::Err(e) => {
-                                        tracing::warn!("Decapsulation error: {:?}", e);
-                                        break;
+                            send_to_peer(&socket, queued, &stats).await;
+                        }
+                    }
+                    TunnResult::WriteToTunnelV4(packet, _) | TunnResult::WriteToTunnelV6(packet, _) => {
+                        let packet = match hop.as_mut() {
+                            Some(hop) => match hop.decapsulate(packet) {
+                                HopOutput::Network(frame) => {
+                                    seal_and_send(
+                                        &mut tunn,
+                                        &socket,
+                                        frame,
+                                        &mut send_buf,
+                                        &stats,
+                                    )
+                                    .await;
+                                    while let Some(queued) = hop.drain() {
+                                        seal_and_send(
+                                            &mut tunn,
+                                            &socket,
+                                            queued,
+                                            &mut send_buf,
+                                            &stats,
+                                        )
+                                        .await;
                                     }
+                                    continue;
                                 }
-
-                                // Check if there's more data to process
-                                result = tunn_guard.decapsulate(None, &[], &mut send_buf);
-                            }
-                        } // tunn_guard dropped here
-
-                        // Now perform async TUN writes without holding the lock
-                        #[cfg(any(target_os = "linux", target_os = "macos"))]
-                        for data in tun_writes {
-                            if let Err(e) = write_to_tun(&tun_device, &data).await {
-                                tracing::warn!("Failed to write to TUN: {}", e);
-                            }
-                        }
-                        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
-                        for data in tun_writes {
-                            tracing::debug!("Received {} bytes from tunnel", data.len());
+                                HopOutput::Tunnel(inner) => inner,
+                                HopOutput::Done => continue,
+                            },
+                            None => packet,
+                        };
+                        match write_to_tun(&mut tun_device, packet).await {
+                            Ok(()) => stats.record_tun_write(packet.len()),
+                            Err(e) => tracing::warn!("Failed to write to TUN: {}", e),
                         }
                     }
-                    Ok(_) => {}
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        // No data available, yield
-                        tokio::time::sleep(Duration::from_millis(1)).await;
-                    }
-                    Err(e) => {
-                        tracing::warn!("Socket receive error: {}", e);
+                    TunnResult::Err(e) => {
+                        tracing::warn!("Decapsulation error: {:?}", e);
                     }
+                    TunnResult::Done => {}
                 }
             }
-        }
 
-        // Read from TUN and encapsulate for sending
-        #[cfg(any(target_os = "linux", target_os = "macos"))]
-        {
-            if let Ok(data) = read_from_tun(&tun_device, &mut recv_buf).await {
-                if !data.is_empty() {
-                    let mut tunn_guard = tunn.lock();
-                    match tunn_guard.encapsulate(data, &mut send_buf) {
-                        TunnResult::WriteToNetwork(encrypted) => {
-                            if let Err(e) = socket.send(encrypted) {
-                                tracing::warn!("Failed to send encapsulated packet: {}", e);
-                            }
-                            bytes_up.fetch_add(encrypted.len() as u64, Ordering::Relaxed);
-                            packets_up.fetch_add(1, Ordering::Relaxed);
-                        }
-                        TunnResult::Err(e) => {
-                            tracing::warn!("Encapsulation error: {:?}", e);
-                        }
-                        _ => {}
+            // Read from TUN and encapsulate for sending
+            read = read_from_tun(&mut tun_device, &mut tun_buf) => {
+                let packet = match read {
+                    Ok(packet) if !packet.is_empty() => packet,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::error!("TUN read error: {}", e);
+                        return Err(e);
+                    }
+                };
+                stats.record_tun_read(packet.len());
+                let packet = match hop.as_mut() {
+                    Some(hop) => match hop.encapsulate(packet) {
+                        Some(frame) => frame,
+                        // Queued by boringtun until the exit hop handshake completes
+                        None => continue,
+                    },
+                    None => packet,
+                };
+
+                match tunn.encapsulate(packet, &mut send_buf) {
+                    TunnResult::WriteToNetwork(encrypted) => {
+                        send_to_peer(&socket, encrypted, &stats).await;
+                    }
+                    TunnResult::Err(e) => {
+                        tracing::warn!("Encapsulation error: {:?}", e);
                     }
+                    // Queued by boringtun until the handshake completes
+                    _ => {}
                 }
             }
         }
@@ -384,8 +630,33 @@ async fn run_tunnel_loop(
     Ok(())
 }
 
+/// Send an encrypted datagram to the peer and record it
+async fn send_to_peer(socket: &UdpSocket, data: &[u8], stats: &TunnelStats) {
+    match socket.send(data).await {
+        Ok(n) => stats.record_sent(n),
+        Err(e) => tracing::warn!("Failed to send to peer: {}", e),
+    }
+}
+
+/// Encrypt an exit-hop datagram for the entry peer and send it
+async fn seal_and_send(
+    tunn: &mut Tunn,
+    socket: &UdpSocket,
+    frame: &[u8],
+    send_buf: &mut [u8],
+    stats: &TunnelStats,
+) {
+    match tunn.encapsulate(frame, send_buf) {
+        TunnResult::WriteToNetwork(encrypted) => {
+            send_to_peer(socket, encrypted, stats).await;
+        }
+        TunnResult::Err(e) => tracing::warn!("Encapsulation error: {:?}", e),
+        _ => {}
+    }
+}
+
 /// Parse a base64-encoded 32-byte key
-fn parse_base64_key(key: &str) -> Result<[u8; 32], VPNError> {
+pub(super) fn parse_base64_key(key: &str) -> Result<[u8; 32], VPNError> {
     use base64::Engine;
 
     let decoded = base64::engine::general_purpose::STANDARD
@@ -404,6 +675,11 @@ fn parse_base64_key(key: &str) -> Result<[u8; 32], VPNError> {
     Ok(key_array)
 }
 
+/// Parse an optional base64 pre-shared key; no key means no PSK
+pub(super) fn parse_preshared_key(key: Option<&str>) -> Result<Option<[u8; 32]>, VPNError> {
+    key.map(parse_base64_key).transpose()
+}
+
 /// Generate a new WireGuard keypair using boringtun's x25519
 pub fn generate_keypair() -> (String, String) {
     use base64::Engine;
@@ -445,106 +721,122 @@ pub fn derive_public_key(private_key: &str) -> Result<String, VPNError> {
 #[cfg(target_os = "linux")]
 mod platform {
     use super::*;
-    use std::io::{Read, Write};
-    use std::sync::Arc;
-    use tokio::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tun::Device as _;
 
     pub struct TunDevice {
         pub name: String,
-        device: Arc<Mutex<tun::platform::Device>>,
+        device: tun::AsyncDevice,
     }
 
     /// Create a TUN interface on Linux using the tun crate
-    pub async fn create_tun_interface() -> Result<TunDevice, VPNError> {
+    pub async fn create_tun_interface(config: &TunConfig) -> Result<TunDevice, VPNError> {
         tracing::info!("Creating TUN interface on Linux");
 
-        let mut config = tun::Configuration::default();
-        config
-            .name("jeju0")
-            .mtu(MTU as i32)
-            .address((10, 0, 0, 2))
-            .netmask((255, 255, 255, 0))
+        let mut tun_config = tun::Configuration::default();
+        tun_config
+            .name(&config.name)
+            .mtu(config.mtu as i32)
+            .address(config.address)
+            .netmask(config.netmask)
             .up();
 
-        #[cfg(target_os = "linux")]
-        config.platform(|config| {
-            config.packet_information(true);
+        // Raw IP packets, as boringtun expects; no 4-byte packet information header
+        tun_config.platform(|config| {
+            config.packet_information(false);
         });
 
-        let device = tun::create(&config)
+        let device = tun::create_as_async(&tun_config)
             .map_err(|e| VPNError::TunnelError(format!("Failed to create TUN device: {}", e)))?;
 
         let name = device
+            .get_ref()
             .name()
             .map_err(|e| VPNError::TunnelError(format!("Failed to get TUN device name: {}", e)))?;
 
         tracing::info!("Created TUN interface: {}", name);
 
-        Ok(TunDevice {
-            name,
-            device: Arc::new(Mutex::new(device)),
-        })
+        Ok(TunDevice { name, device })
     }
 
-    /// Write data to the TUN device
-    pub async fn write_to_tun(device: &TunDevice, data: &[u8]) -> Result<(), VPNError> {
-        let mut dev = device.device.lock().await;
-        dev.write_all(data)
+    /// Add an IPv6 address to the TUN device. A TUN link has no neighbours, so
+    /// duplicate address detection is skipped.
+    pub fn assign_ipv6(device: &TunDevice, address: Ipv6Addr) -> Result<(), VPNError> {
+        let cidr = format!("{}/{}", address, TUNNEL_IPV6_PREFIX);
+        let args = [
+            "-6",
+            "addr",
+            "add",
+            &cidr,
+            "dev",
+            device.name.as_str(),
+            "nodad",
+        ];
+        run_with_stdin("ip", &args, "")?;
+        Ok(())
+    }
+
+    /// Write an IP packet to the TUN device
+    pub async fn write_to_tun(device: &mut TunDevice, data: &[u8]) -> Result<(), VPNError> {
+        device
+            .device
+            .write_all(data)
+            .await
             .map_err(|e| VPNError::TunnelError(format!("Failed to write to TUN: {}", e)))?;
         tracing::trace!("Wrote {} bytes to TUN {}", data.len(), device.name);
         Ok(())
     }
 
-    /// Read data from the TUN device
+    /// Read the next IP packet from the TUN device
     pub async fn read_from_tun<'a>(
-        device: &TunDevice,
+        device: &mut TunDevice,
         buf: &'a mut [u8],
     ) -> Result<&'a [u8], VPNError> {
-        let mut dev = device.device.lock().await;
-
-        // Use non-blocking read
-        match dev.read(buf) {
-            Ok(n) if n > 0 => {
-                tracing::trace!("Read {} bytes from TUN {}", n, device.name);
-                Ok(&buf[..n])
-            }
-            Ok(_) => Ok(&[]),
-            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(&[]),
-            Err(e) => Err(VPNError::TunnelError(format!(
-                "Failed to read from TUN: {}",
-                e
-            ))),
-        }
+        let n = device
+            .device
+            .read(buf)
+            .await
+            .map_err(|e| VPNError::TunnelError(format!("Failed to read from TUN: {}", e)))?;
+        tracing::trace!("Read {} bytes from TUN {}", n, device.name);
+        Ok(&buf[..n])
     }
 }
 
 #[cfg(target_os = "macos")]
 mod platform {
     use super::*;
-    use std::io::{Read, Write};
-    use std::sync::Arc;
-    use tokio::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tun::Device as _;
+
+    /// utun frames every packet with a 4-byte address family header
+    const AF_HEADER_LEN: usize = 4;
+    const AF_INET: u8 = 2;
+    const AF_INET6: u8 = 30;
 
     pub struct TunDevice {
         pub name: String,
-        device: Arc<Mutex<tun::platform::Device>>,
+        device: tun::AsyncDevice,
+        frame: Vec<u8>,
     }
 
     /// Create a TUN interface on macOS using the tun crate (utun)
-    pub async fn create_tun_interface() -> Result<TunDevice, VPNError> {
+    pub async fn create_tun_interface(config: &TunConfig) -> Result<TunDevice, VPNError> {
         tracing::info!("Creating TUN interface on macOS (utun)");
 
-        let mut config = tun::Configuration::default();
-        config
-            .mtu(MTU as i32)
-            .address((10, 0, 0, 2))
-            .netmask((255, 255, 255, 0))
+        // utun names are assigned by the kernel, so `config.name` is not applied
+        let mut tun_config = tun::Configuration::default();
+        tun_config
+            .mtu(config.mtu as i32)
+            .address(config.address)
+            .destination(config.address)
+            .netmask(config.netmask)
             .up();
 
-        let device = tun::create(&config)
+        let device = tun::create_as_async(&tun_config)
             .map_err(|e| VPNError::TunnelError(format!("Failed to create utun device: {}", e)))?;
 
         let name = device
+            .get_ref()
             .name()
             .map_err(|e| VPNError::TunnelError(format!("Failed to get utun device name: {}", e)))?;
 
@@ -552,53 +844,88 @@ mod platform {
 
         Ok(TunDevice {
             name,
-            device: Arc::new(Mutex::new(device)),
+            device,
+            frame: vec![0u8; BUFFER_SIZE + AF_HEADER_LEN],
         })
     }
 
-    /// Write data to the utun device
-    pub async fn write_to_tun(device: &TunDevice, data: &[u8]) -> Result<(), VPNError> {
-        let mut dev = device.device.lock().await;
-        dev.write_all(data)
+    /// Add an IPv6 address to the utun device
+    pub fn assign_ipv6(device: &TunDevice, address: Ipv6Addr) -> Result<(), VPNError> {
+        let address = address.to_string();
+        let prefix = TUNNEL_IPV6_PREFIX.to_string();
+        run_with_stdin(
+            "ifconfig",
+            &[
+                &device.name,
+                "inet6",
+                &address,
+                "prefixlen",
+                &prefix,
+                "alias",
+            ],
+            "",
+        )?;
+        Ok(())
+    }
+
+    /// Write an IP packet to the utun device
+    pub async fn write_to_tun(device: &mut TunDevice, data: &[u8]) -> Result<(), VPNError> {
+        let family = match data.first().map(|b| b >> 4) {
+            Some(4) => AF_INET,
+            Some(6) => AF_INET6,
+            _ => return Err(VPNError::TunnelError("Not an IP packet".to_string())),
+        };
+
+        let len = AF_HEADER_LEN + data.len();
+        if len > device.frame.len() {
+            return Err(VPNError::TunnelError(format!(
+                "Packet too large: {}",
+                data.len()
+            )));
+        }
+        device.frame[..AF_HEADER_LEN].copy_from_slice(&[0, 0, 0, family]);
+        device.frame[AF_HEADER_LEN..len].copy_from_slice(data);
+
+        device
+            .device
+            .write_all(&device.frame[..len])
+            .await
             .map_err(|e| VPNError::TunnelError(format!("Failed to write to utun: {}", e)))?;
         tracing::trace!("Wrote {} bytes to utun {}", data.len(), device.name);
         Ok(())
     }
 
-    /// Read data from the utun device
+    /// Read the next IP packet from the utun device
     pub async fn read_from_tun<'a>(
-        device: &TunDevice,
+        device: &mut TunDevice,
         buf: &'a mut [u8],
     ) -> Result<&'a [u8], VPNError> {
-        let mut dev = device.device.lock().await;
+        let n = device
+            .device
+            .read(&mut device.frame)
+            .await
+            .map_err(|e| VPNError::TunnelError(format!("Failed to read from utun: {}", e)))?;
 
-        match dev.read(buf) {
-            Ok(n) if n > 0 => {
-                tracing::trace!("Read {} bytes from utun {}", n, device.name);
-                Ok(&buf[..n])
-            }
-            Ok(_) => Ok(&[]),
-            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(&[]),
-            Err(e) => Err(VPNError::TunnelError(format!(
-                "Failed to read from utun: {}",
-                e
-            ))),
-        }
+        let packet = device.frame.get(AF_HEADER_LEN..n).unwrap_or_default();
+        let len = packet.len().min(buf.len());
+        buf[..len].copy_from_slice(&packet[..len]);
+        tracing::trace!("Read {} bytes from utun {}", len, device.name);
+        Ok(&buf[..len])
     }
 }
 
 #[cfg(target_os = "windows")]
 mod platform {
     use super::*;
-    use std::sync::Arc;
 
     pub struct TunDevice {
         pub name: String,
         session: Arc<wintun::Session>,
+        packets: mpsc::Receiver<Vec<u8>>,
     }
 
     /// Create a TUN interface on Windows using the wintun crate
-    pub async fn create_tun_interface() -> Result<TunDevice, VPNError> {
+    pub async fn create_tun_interface(config: &TunConfig) -> Result<TunDevice, VPNError> {
         tracing::info!("Creating TUN interface on Windows (WinTun)");
 
         // Load the WinTun DLL
@@ -607,43 +934,100 @@ mod platform {
 
         // Create adapter
         let adapter =
-            wintun::Adapter::create(&wintun, "JejuVPN", "JejuVPN", None).map_err(|e| {
+            wintun::Adapter::create(&wintun, &config.name, "JejuVPN", None).map_err(|e| {
                 VPNError::TunnelError(format!("Failed to create WinTun adapter: {}", e))
             })?;
 
-        // Set IP address using netsh (WinTun doesn't do this automatically)
-        let output = std::process::Command::new("netsh")
-            .args([
+        // Set IP address and MTU using netsh (WinTun doesn't do this automatically)
+        let interface_name = format!("name={}", config.name);
+        let address = config.address.to_string();
+        let netmask = config.netmask.to_string();
+        let mtu = format!("mtu={}", config.mtu);
+        let commands: [&[&str]; 2] = [
+            &[
                 "interface",
                 "ip",
                 "set",
                 "address",
-                "name=JejuVPN",
+                &interface_name,
                 "static",
-                "10.0.0.2",
-                "255.255.255.0",
-            ])
-            .output();
-
-        if let Err(e) = output {
-            tracing::warn!("Failed to set IP address: {}", e);
+                &address,
+                &netmask,
+            ],
+            &[
+                "interface",
+                "ipv4",
+                "set",
+                "subinterface",
+                &config.name,
+                &mtu,
+                "store=active",
+            ],
+        ];
+        for args in commands {
+            if let Err(e) = std::process::Command::new("netsh").args(args).output() {
+                tracing::warn!("Failed to configure WinTun adapter: {}", e);
+            }
         }
 
         // Start session
-        let session = adapter
-            .start_session(wintun::MAX_RING_CAPACITY)
-            .map_err(|e| VPNError::TunnelError(format!("Failed to start WinTun session: {}", e)))?;
+        let session = Arc::new(
+            adapter
+                .start_session(wintun::MAX_RING_CAPACITY)
+                .map_err(|e| {
+                    VPNError::TunnelError(format!("Failed to start WinTun session: {}", e))
+                })?,
+        );
+
+        // WinTun reads block, so forward packets from a dedicated thread
+        let (tx, packets) = mpsc::channel(256);
+        let reader = session.clone();
+        std::thread::spawn(move || {
+            while let Ok(packet) = reader.receive_blocking() {
+                if tx.blocking_send(packet.bytes().to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
 
-        tracing::info!("Created WinTun interface: JejuVPN");
+        tracing::info!("Created WinTun interface: {}", config.name);
 
         Ok(TunDevice {
-            name: "JejuVPN".to_string(),
-            session: Arc::new(session),
+            name: config.name.clone(),
+            session,
+            packets,
         })
     }
 
-    /// Write data to the WinTun device
-    pub async fn write_to_tun(device: &TunDevice, data: &[u8]) -> Result<(), VPNError> {
+    impl Drop for TunDevice {
+        fn drop(&mut self) {
+            // Unblocks the reader thread
+            let _ = self.session.shutdown();
+        }
+    }
+
+    /// Add an IPv6 address to the WinTun adapter
+    pub fn assign_ipv6(device: &TunDevice, address: Ipv6Addr) -> Result<(), VPNError> {
+        let interface = format!("interface={}", device.name);
+        let address = format!("address={}/{}", address, TUNNEL_IPV6_PREFIX);
+        run_with_stdin(
+            "netsh",
+            &[
+                "interface",
+                "ipv6",
+                "add",
+                "address",
+                &interface,
+                &address,
+                "store=active",
+            ],
+            "",
+        )?;
+        Ok(())
+    }
+
+    /// Write an IP packet to the WinTun device
+    pub async fn write_to_tun(device: &mut TunDevice, data: &[u8]) -> Result<(), VPNError> {
         let mut packet = device
             .session
             .allocate_send_packet(data.len() as u16)
@@ -658,24 +1042,21 @@ mod platform {
         Ok(())
     }
 
-    /// Read data from the WinTun device
+    /// Read the next IP packet from the WinTun device
     pub async fn read_from_tun<'a>(
-        device: &TunDevice,
+        device: &mut TunDevice,
         buf: &'a mut [u8],
     ) -> Result<&'a [u8], VPNError> {
-        match device.session.try_receive() {
-            Ok(Some(packet)) => {
-                let len = packet.bytes().len().min(buf.len());
-                buf[..len].copy_from_slice(&packet.bytes()[..len]);
-                tracing::trace!("Read {} bytes from WinTun {}", len, device.name);
-                Ok(&buf[..len])
-            }
-            Ok(None) => Ok(&[]),
-            Err(e) => Err(VPNError::TunnelError(format!(
-                "Failed to read from WinTun: {}",
-                e
-            ))),
-        }
+        let packet = device
+            .packets
+            .recv()
+            .await
+            .ok_or_else(|| VPNError::TunnelError("WinTun session closed".to_string()))?;
+
+        let len = packet.len().min(buf.len());
+        buf[..len].copy_from_slice(&packet[..len]);
+        tracing::trace!("Read {} bytes from WinTun {}", len, device.name);
+        Ok(&buf[..len])
     }
 }
 
@@ -685,27 +1066,27 @@ mod platform {
 
     pub struct TunDevice;
 
-    pub async fn create_tun_interface() -> Result<TunDevice, VPNError> {
+    pub async fn create_tun_interface(_config: &TunConfig) -> Result<TunDevice, VPNError> {
+        Err(VPNError::TunnelError("Unsupported platform".to_string()))
+    }
+
+    pub fn assign_ipv6(_device: &TunDevice, _address: Ipv6Addr) -> Result<(), VPNError> {
         Err(VPNError::TunnelError("Unsupported platform".to_string()))
     }
 
-    pub async fn write_to_tun(_device: &TunDevice, _data: &[u8]) -> Result<(), VPNError> {
+    pub async fn write_to_tun(_device: &mut TunDevice, _data: &[u8]) -> Result<(), VPNError> {
         Err(VPNError::TunnelError("Unsupported platform".to_string()))
     }
 
     pub async fn read_from_tun<'a>(
-        _device: &TunDevice,
+        _device: &mut TunDevice,
         _buf: &'a mut [u8],
     ) -> Result<&'a [u8], VPNError> {
         Err(VPNError::TunnelError("Unsupported platform".to_string()))
     }
 }
 
-#[cfg(any(target_os = "linux", target_os = "macos"))]
-use platform::{create_tun_interface, read_from_tun, write_to_tun, TunDevice};
-
-#[cfg(target_os = "windows")]
-use platform::TunDevice;
+use platform::{assign_ipv6, create_tun_interface, read_from_tun, write_to_tun};
 
 #[cfg(test)]
 mod tests {
@@ -736,6 +1117,196 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_preshared_key() {
+        use base64::Engine;
+
+        let psk = [7u8; 32];
+        let encoded = base64::engine::general_purpose::STANDARD.encode(psk);
+        assert_eq!(parse_preshared_key(Some(&encoded)).unwrap(), Some(psk));
+
+        assert_eq!(parse_preshared_key(None).unwrap(), None);
+
+        let short = base64::engine::general_purpose::STANDARD.encode([7u8; 16]);
+        assert!(parse_preshared_key(Some(&short)).is_err());
+    }
+
+    #[test]
+    fn test_tunnel_stats_separate_data_from_overhead() {
+        let stats = TunnelStats::default();
+
+        // Handshake: one initiation out, one response back, no user data
+        stats.record_sent(148);
+        stats.record_received(92);
+
+        // Three 100-byte packets up, two 60-byte packets down, each padded and
+        // framed by WireGuard on the wire
+        for _ in 0..3 {
+            stats.record_tun_read(100);
+            stats.record_sent(144);
+        }
+        for _ in 0..2 {
+            stats.record_received(96);
+            stats.record_tun_write(60);
+        }
+
+        // A keepalive carries no user data
+        stats.record_sent(32);
+
+        assert_eq!(stats.transfer(), (300, 120));
+        assert_eq!(stats.packets(), (3, 2));
+        assert_eq!(stats.overhead(), (148 + 3 * 44 + 32, 92 + 2 * 36));
+    }
+
+    #[tokio::test]
+    async fn test_record_transfer_leaves_packet_counts() {
+        let (private_key, peer_pubkey) = generate_keypair();
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            private_key,
+            peer_pubkey,
+            endpoint: "203.0.113.1:51820".to_string(),
+            allowed_ips: vec![],
+            dns: vec![],
+            keepalive: 25,
+            mtu: None,
+            assigned_ip: None,
+            assigned_ipv6: None,
+            preshared_key: None,
+            exit_hop: None,
+        })
+        .await
+        .unwrap();
+
+        tunnel.record_transfer(1000, 2000).await;
+        tunnel.record_transfer(500, 0).await;
+
+        assert_eq!(tunnel.get_transfer_stats().await.unwrap(), (1500, 2000));
+        assert_eq!(tunnel.get_packet_stats().await.unwrap(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_mtu() {
+        let (private_key, peer_pubkey) = generate_keypair();
+        let config = WireGuardConfig {
+            private_key,
+            peer_pubkey: peer_pubkey.clone(),
+            endpoint: "203.0.113.1:51820".to_string(),
+            allowed_ips: vec!["0.0.0.0/0".to_string()],
+            dns: vec![],
+            keepalive: 25,
+            mtu: None,
+            assigned_ip: Some("10.0.0.2".to_string()),
+            assigned_ipv6: None,
+            preshared_key: None,
+            exit_hop: None,
+        };
+
+        let tunnel = WireGuardTunnel::new(config.clone()).await.unwrap();
+        assert_eq!(tunnel.mtu(), TUNNEL_MTU);
+
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            mtu: Some(1380),
+            ..config.clone()
+        })
+        .await
+        .unwrap();
+        assert_eq!(tunnel.mtu(), 1380);
+
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            mtu: Some(9000),
+            ..config.clone()
+        })
+        .await
+        .unwrap();
+        assert_eq!(tunnel.mtu(), TUNNEL_MTU);
+
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            mtu: Some(1380),
+            exit_hop: Some(ExitHop {
+                peer_pubkey,
+                endpoint: "203.0.113.2:51820".to_string(),
+                assigned_ip: "10.1.0.2".to_string(),
+                assigned_ipv6: None,
+                preshared_key: None,
+            }),
+            ..config
+        })
+        .await
+        .unwrap();
+        assert_eq!(tunnel.mtu(), multihop_mtu(1380));
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_ipv6_belongs_to_innermost_tunnel() {
+        let (private_key, peer_pubkey) = generate_keypair();
+        let config = WireGuardConfig {
+            private_key,
+            peer_pubkey: peer_pubkey.clone(),
+            endpoint: "203.0.113.1:51820".to_string(),
+            allowed_ips: vec!["0.0.0.0/1".to_string(), "::/1".to_string()],
+            dns: vec![],
+            keepalive: 25,
+            mtu: None,
+            assigned_ip: Some("10.0.0.2".to_string()),
+            assigned_ipv6: None,
+            preshared_key: None,
+            exit_hop: None,
+        };
+        let exit = ExitHop {
+            peer_pubkey,
+            endpoint: "203.0.113.2:51820".to_string(),
+            assigned_ip: "10.1.0.2".to_string(),
+            assigned_ipv6: None,
+            preshared_key: None,
+        };
+
+        // IPv4-only
+        let tunnel = WireGuardTunnel::new(config.clone()).await.unwrap();
+        assert_eq!(tunnel.tunnel_ipv6().unwrap(), None);
+
+        // Dual-stack
+        let dual_stack = WireGuardConfig {
+            assigned_ipv6: Some("fd00::2".to_string()),
+            ..config.clone()
+        };
+        let tunnel = WireGuardTunnel::new(dual_stack.clone()).await.unwrap();
+        assert_eq!(
+            tunnel.tunnel_ipv6().unwrap(),
+            Some("fd00::2".parse().unwrap())
+        );
+
+        // An IPv4-only exit hop makes the whole chain IPv4-only
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            exit_hop: Some(exit.clone()),
+            ..dual_stack.clone()
+        })
+        .await
+        .unwrap();
+        assert_eq!(tunnel.tunnel_ipv6().unwrap(), None);
+
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            exit_hop: Some(ExitHop {
+                assigned_ipv6: Some("fd01::2".to_string()),
+                ..exit
+            }),
+            ..dual_stack
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            tunnel.tunnel_ipv6().unwrap(),
+            Some("fd01::2".parse().unwrap())
+        );
+
+        let tunnel = WireGuardTunnel::new(WireGuardConfig {
+            assigned_ipv6: Some("10.0.0.2".to_string()),
+            ..config
+        })
+        .await
+        .unwrap();
+        assert!(tunnel.tunnel_ipv6().is_err());
+    }
+
     #[test]
     fn test_keypair_derivation_consistency() {
         for _ in 0..10 {
@@ -747,4 +1318,184 @@ mod tests {
             );
         }
     }
+
+    #[cfg(all(feature = "tun-integration", target_os = "linux"))]
+    mod loopback {
+        use super::*;
+
+        /// Turn an IPv4 ICMP echo request into its reply
+        fn echo_reply(request: &[u8]) -> Option<Vec<u8>> {
+            let ihl = ((*request.first()? & 0x0f) as usize) * 4;
+            if request[0] >> 4 != 4 || request.get(9) != Some(&1) || request.get(ihl) != Some(&8) {
+                return None;
+            }
+
+            let mut reply = request.to_vec();
+            reply[12..16].copy_from_slice(&request[16..20]);
+            reply[16..20].copy_from_slice(&request[12..16]);
+            reply[ihl] = 0;
+            reply[ihl + 2..ihl + 4].fill(0);
+            let checksum = internet_checksum(&reply[ihl..]);
+            reply[ihl + 2..ihl + 4].copy_from_slice(&checksum.to_be_bytes());
+            Some(reply)
+        }
+
+        /// Turn an ICMPv6 echo request into its reply
+        fn echo_reply_v6(request: &[u8]) -> Option<Vec<u8>> {
+            const HEADER_LEN: usize = 40;
+            if *request.first()? >> 4 != 6
+                || request.get(6) != Some(&58)
+                || request.get(HEADER_LEN) != Some(&128)
+            {
+                return None;
+            }
+
+            let mut reply = request.to_vec();
+            reply[8..24].copy_from_slice(&request[24..40]);
+            reply[24..40].copy_from_slice(&request[8..24]);
+            reply[HEADER_LEN] = 129;
+            reply[HEADER_LEN + 2..HEADER_LEN + 4].fill(0);
+
+            // The checksum covers a pseudo-header of both addresses, the
+            // payload length and the next header
+            let body = &reply[HEADER_LEN..];
+            let mut pseudo = reply[8..40].to_vec();
+            pseudo.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, 58]);
+            pseudo.extend_from_slice(body);
+            let checksum = internet_checksum(&pseudo);
+            reply[HEADER_LEN + 2..HEADER_LEN + 4].copy_from_slice(&checksum.to_be_bytes());
+            Some(reply)
+        }
+
+        fn internet_checksum(data: &[u8]) -> u16 {
+            let mut sum: u32 = data
+                .chunks(2)
+                .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+                .sum();
+            while sum >> 16 != 0 {
+                sum = (sum & 0xffff) + (sum >> 16);
+            }
+            !(sum as u16)
+        }
+
+        /// WireGuard peer on localhost that answers pings sent through the tunnel
+        async fn run_peer(socket: UdpSocket, mut tunn: Tunn) {
+            let mut recv_buf = [0u8; BUFFER_SIZE];
+            let mut send_buf = [0u8; BUFFER_SIZE];
+            let mut reply_buf = [0u8; BUFFER_SIZE];
+
+            while let Ok((n, from)) = socket.recv_from(&mut recv_buf).await {
+                match tunn.decapsulate(Some(from.ip()), &recv_buf[..n], &mut send_buf) {
+                    TunnResult::WriteToNetwork(data) => {
+                        let _ = socket.send_to(data, from).await;
+                        while let TunnResult::WriteToNetwork(queued) =
+                            tunn.decapsulate(None, &[], &mut send_buf)
+                        {
+                            let _ = socket.send_to(queued, from).await;
+                        }
+                    }
+                    TunnResult::WriteToTunnelV4(packet, _)
+                    | TunnResult::WriteToTunnelV6(packet, _) => {
+                        let Some(reply) = echo_reply(packet).or_else(|| echo_reply_v6(packet))
+                        else {
+                            continue;
+                        };
+                        if let TunnResult::WriteToNetwork(data) =
+                            tunn.encapsulate(&reply, &mut reply_buf)
+                        {
+                            let _ = socket.send_to(data, from).await;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        /// Bring up a tunnel to a loopback peer and ping `target` through it.
+        /// Returns whether the ping succeeded and the packets written to the TUN device.
+        async fn ping_through_tunnel(
+            assigned_ipv6: Option<&str>,
+            allowed_ips: &[&str],
+            target: &str,
+        ) -> (bool, u64) {
+            let (client_private, client_public) = generate_keypair();
+            let (peer_private, peer_public) = generate_keypair();
+
+            let peer = Tunn::new(
+                StaticSecret::from(parse_base64_key(&peer_private).unwrap()),
+                PublicKey::from(parse_base64_key(&client_public).unwrap()),
+                None,
+                None,
+                1,
+                None,
+            )
+            .expect("Should create peer tunnel");
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = socket.local_addr().unwrap();
+            tokio::spawn(run_peer(socket, peer));
+
+            let mut tunnel = WireGuardTunnel::new(WireGuardConfig {
+                private_key: client_private,
+                peer_pubkey: peer_public,
+                endpoint: endpoint.to_string(),
+                allowed_ips: allowed_ips.iter().map(|cidr| cidr.to_string()).collect(),
+                dns: vec![],
+                keepalive: 25,
+                mtu: None,
+                assigned_ip: Some("10.0.0.2".to_string()),
+                assigned_ipv6: assigned_ipv6.map(str::to_string),
+                preshared_key: None,
+                exit_hop: None,
+            })
+            .await
+            .unwrap();
+            tunnel.start().await.expect("Should start tunnel");
+
+            // The TUN interface is created by the tunnel task
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            assert_eq!(
+                tunnel.get_local_ipv6().await.as_deref(),
+                assigned_ipv6,
+                "IPv6 address not assigned to the interface"
+            );
+
+            let status = tokio::process::Command::new("ping")
+                .args(["-c", "1", "-W", "3", target])
+                .status()
+                .await
+                .expect("Should run ping");
+
+            let (_, packets_down) = tunnel.get_packet_stats().await.unwrap();
+            tunnel.stop().await.unwrap();
+            (status.success(), packets_down)
+        }
+
+        /// Requires root (CAP_NET_ADMIN) and a `ping` binary:
+        /// `sudo -E cargo test --features tun-integration ping_through_loopback_peer`
+        #[tokio::test(flavor = "multi_thread")]
+        async fn ping_through_loopback_peer() {
+            // Other addresses on the tunnel subnet route through the interface to the peer
+            let (replied, packets_down) =
+                ping_through_tunnel(None, &["10.0.0.0/24"], "10.0.0.1").await;
+
+            assert!(replied, "Ping through tunnel failed");
+            assert!(packets_down > 0, "No packets received from peer");
+        }
+
+        /// IPv6 replies come out of boringtun as `WriteToTunnelV6` and must reach
+        /// the kernel through the same TUN write path as IPv4
+        #[tokio::test(flavor = "multi_thread")]
+        async fn ping_through_loopback_peer_ipv6() {
+            let (replied, packets_down) = ping_through_tunnel(
+                Some("fd00:6a::2"),
+                &["10.0.0.0/24", "fd00:6a::/64"],
+                "fd00:6a::1",
+            )
+            .await;
+
+            assert!(replied, "IPv6 ping through tunnel failed");
+            assert!(packets_down > 0, "No IPv6 packets received from peer");
+        }
+    }
 }
@@ -295,6 +295,8 @@ const handlers: Record<string, MockHandler> = {
     return mockNodes
   },
 
+  refresh_latencies: async (): Promise<VPNNode[]> => mockNodes,
+
   select_node: async (args: Record<string, unknown>): Promise<null> => {
     const validated = SelectNodeInputSchema.parse(args)
     mockState.selectedNodeId = validated.nodeId
@@ -24,6 +24,7 @@ export const VPNNodeSchema = z
     load: z.number().int().min(0).max(100),
     reputation: z.number().int().min(0).max(100),
     capabilities: VPNNodeCapabilitiesSchema,
+    assigned_ip: z.string().nullable().optional(),
   })
   .strict()
 
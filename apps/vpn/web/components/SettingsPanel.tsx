@@ -22,6 +22,14 @@ const VPNConfigSchema = z.object({
   coordinator_url: z.string(),
   dns_servers: z.array(z.string()),
   kill_switch: z.boolean(),
+  auto_reconnect: z.boolean(),
+  max_reconnect_attempts: z.number(),
+  reconnect_failover: z.boolean(),
+  split_tunnel: z.object({
+    mode: z.enum(['off', 'include', 'exclude']),
+    cidrs: z.array(z.string()),
+    apps: z.array(z.string()),
+  }),
   auto_connect: z.boolean(),
   auto_start: z.boolean(),
   minimize_to_tray: z.boolean(),
@@ -2,22 +2,33 @@
 //!
 //! This SP1 program proves that:
 //! 1. A set of Ed25519 signatures are valid
-//! 2. The signatures represent ≥2/3 of total stake
+//! 2. The signatures represent >2/3 of total stake
 //! 3. All signatures attest to the same bank hash
 //!
 //! The proof enables trustless verification of Solana state on EVM chains.
 
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
 use sha2::{Digest, Sha256};
 use ed25519_dalek::{Signature, VerifyingKey, Verifier};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use public_inputs::{Encoder, PublicInputs};
+use supermajority::CIRCUIT_THRESHOLD;
 
 /// Maximum number of validators in a proof batch
 const MAX_VALIDATORS: usize = 100;
 
+/// Solana mainnet slots per epoch
+const SLOTS_PER_EPOCH: u64 = 432_000;
+
+/// Domain-separation prefixes for the stake tree, so a leaf can never be
+/// mistaken for an internal node or the other way round
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+
 /// Validator stake and vote
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone)]
@@ -35,6 +46,8 @@ pub struct ValidatorVote {
     /// Ed25519 signature (64 bytes)
     #[serde_as(as = "[_; 64]")]
     pub signature: [u8; 64],
+    /// Merkle proof of (pubkey, stake) in `EpochStakes::stakes_root`
+    pub stake_proof: Vec<[u8; 32]>,
 }
 
 /// Epoch stake information
@@ -59,7 +72,7 @@ pub struct ConsensusProofInputs {
     pub new_bank_hash: [u8; 32],
     /// Epoch stakes
     pub epoch_stakes: EpochStakes,
-    /// Validator votes (must sum to ≥2/3 stake)
+    /// Validator votes (must sum to >2/3 stake)
     pub votes: Vec<ValidatorVote>,
 }
 
@@ -71,10 +84,27 @@ pub struct ConsensusProofOutputs {
     pub new_slot: u64,
     pub new_bank_hash: [u8; 32],
     pub epoch_stakes_root: [u8; 32],
+    pub epoch: u64,
     pub total_stake: u64,
     pub voting_stake: u64,
 }
 
+impl PublicInputs for ConsensusProofOutputs {
+    /// 8 prev_slot | 32 prev_bank_hash | 8 new_slot | 32 new_bank_hash |
+    /// 32 epoch_stakes_root | 8 epoch | 8 total_stake | 8 voting_stake (136 bytes)
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder
+            .u64(self.prev_slot)
+            .bytes(&self.prev_bank_hash)
+            .u64(self.new_slot)
+            .bytes(&self.new_bank_hash)
+            .bytes(&self.epoch_stakes_root)
+            .u64(self.epoch)
+            .u64(self.total_stake)
+            .u64(self.voting_stake);
+    }
+}
+
 fn main() {
     // Read inputs from SP1 host
     let inputs: ConsensusProofInputs = sp1_zkvm::io::read();
@@ -83,14 +113,34 @@ fn main() {
     assert!(inputs.new_slot > inputs.prev_slot, "New slot must be greater");
     assert!(inputs.votes.len() <= MAX_VALIDATORS, "Too many validators");
     assert!(inputs.votes.len() > 0, "No votes provided");
-
-    // Calculate required stake (2/3 of total)
-    let required_stake = (inputs.epoch_stakes.total_stake * 2) / 3;
+    // Stakes only mean anything for the epoch they were snapshotted in
+    assert_eq!(
+        inputs.new_slot / SLOTS_PER_EPOCH,
+        inputs.epoch_stakes.epoch,
+        "Slot not in stake epoch"
+    );
 
     // Verify each signature and accumulate stake
     let mut voting_stake: u64 = 0;
+    let mut counted_voters: Vec<[u8; 32]> = Vec::with_capacity(inputs.votes.len());
 
     for vote in &inputs.votes {
+        // Skip if already counted (no double voting)
+        if counted_voters.contains(&vote.pubkey) {
+            continue;
+        }
+
+        // The claimed stake must be the validator's stake in this epoch's snapshot
+        assert!(
+            verify_stake_merkle_proof(
+                &vote.pubkey,
+                vote.stake,
+                &vote.stake_proof,
+                &inputs.epoch_stakes.stakes_root,
+            ),
+            "Invalid stake proof"
+        );
+
         // Verify the vote is for the correct slot and bank hash
         assert_eq!(vote.slot, inputs.new_slot, "Vote slot mismatch");
         assert_eq!(vote.bank_hash, inputs.new_bank_hash, "Vote bank hash mismatch");
@@ -110,11 +160,15 @@ fn main() {
             .expect("Signature verification failed");
 
         // Accumulate stake
-        voting_stake += vote.stake;
+        voting_stake = voting_stake.checked_add(vote.stake).expect("Stake overflow");
+        counted_voters.push(vote.pubkey);
     }
 
     // Verify supermajority
-    assert!(voting_stake >= required_stake, "Insufficient voting stake");
+    assert!(
+        has_supermajority(voting_stake, inputs.epoch_stakes.total_stake),
+        "Insufficient voting stake"
+    );
 
     // Construct outputs
     let outputs = ConsensusProofOutputs {
@@ -123,12 +177,18 @@ fn main() {
         new_slot: inputs.new_slot,
         new_bank_hash: inputs.new_bank_hash,
         epoch_stakes_root: inputs.epoch_stakes.stakes_root,
+        epoch: inputs.epoch_stakes.epoch,
         total_stake: inputs.epoch_stakes.total_stake,
         voting_stake,
     };
 
-    // Commit outputs as public inputs
-    sp1_zkvm::io::commit(&outputs);
+    // Commit the outputs digest as the single public input
+    public_inputs::commit(&outputs);
+}
+
+/// Whether `voting_stake` clears the threshold this circuit is built with
+fn has_supermajority(voting_stake: u64, total_stake: u64) -> bool {
+    CIRCUIT_THRESHOLD.is_met(voting_stake, total_stake)
 }
 
 /// Helper to compute message hash for signature verification
@@ -141,7 +201,6 @@ fn compute_vote_message_hash(slot: u64, bank_hash: &[u8; 32]) -> [u8; 32] {
 }
 
 /// Verify a merkle proof for validator stake inclusion
-#[allow(dead_code)]
 fn verify_stake_merkle_proof(
     pubkey: &[u8; 32],
     stake: u64,
@@ -150,6 +209,7 @@ fn verify_stake_merkle_proof(
 ) -> bool {
     // Compute leaf hash
     let mut hasher = Sha256::new();
+    hasher.update(&[LEAF_TAG]);
     hasher.update(pubkey);
     hasher.update(&stake.to_le_bytes());
     let mut current_hash: [u8; 32] = hasher.finalize().into();
@@ -157,6 +217,7 @@ fn verify_stake_merkle_proof(
     // Traverse proof
     for sibling in proof {
         let mut hasher = Sha256::new();
+        hasher.update(&[NODE_TAG]);
         if current_hash <= *sibling {
             hasher.update(&current_hash);
             hasher.update(sibling);
@@ -169,3 +230,79 @@ fn verify_stake_merkle_proof(
 
     current_hash == *root
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stake_leaf(pubkey: &[u8; 32], stake: u64) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(&[LEAF_TAG]);
+        hasher.update(pubkey);
+        hasher.update(&stake.to_le_bytes());
+        hasher.finalize().into()
+    }
+
+    fn sorted_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        let mut hasher = Sha256::new();
+        hasher.update(&[NODE_TAG]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    #[test]
+    #[cfg(not(feature = "devnet"))]
+    fn supermajority_boundary() {
+        // Exactly 2/3 fails, one lamport more passes
+        assert!(!has_supermajority(200, 300));
+        assert!(has_supermajority(201, 300));
+        assert!(!has_supermajority(6, 10));
+        assert!(has_supermajority(7, 10));
+        assert!(!has_supermajority(0, 0));
+    }
+
+    #[test]
+    fn stake_proof_binds_pubkey_and_stake() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let alice_leaf = stake_leaf(&alice, 700);
+        let bob_leaf = stake_leaf(&bob, 300);
+        let root = sorted_pair(&alice_leaf, &bob_leaf);
+
+        assert!(verify_stake_merkle_proof(&alice, 700, &[bob_leaf], &root));
+        assert!(verify_stake_merkle_proof(&bob, 300, &[alice_leaf], &root));
+
+        // Inflating the stake or swapping the validator breaks the proof
+        assert!(!verify_stake_merkle_proof(&alice, 701, &[bob_leaf], &root));
+        assert!(!verify_stake_merkle_proof(&bob, 700, &[bob_leaf], &root));
+    }
+
+    #[test]
+    fn untagged_stake_tree_is_rejected() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let untagged = |parts: &[&[u8]]| -> [u8; 32] {
+            let mut hasher = Sha256::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().into()
+        };
+
+        // A tree built without domain separation doesn't verify
+        let alice_leaf = untagged(&[&alice, &700u64.to_le_bytes()]);
+        let bob_leaf = untagged(&[&bob, &300u64.to_le_bytes()]);
+        let root = if alice_leaf <= bob_leaf {
+            untagged(&[&alice_leaf, &bob_leaf])
+        } else {
+            untagged(&[&bob_leaf, &alice_leaf])
+        };
+        assert!(!verify_stake_merkle_proof(&alice, 700, &[bob_leaf], &root));
+
+        // Nor does presenting the tagged root's internal node as a proof-less leaf
+        let tagged_root = sorted_pair(&stake_leaf(&alice, 700), &stake_leaf(&bob, 300));
+        assert!(!verify_stake_merkle_proof(&alice, 700, &[], &tagged_root));
+    }
+}
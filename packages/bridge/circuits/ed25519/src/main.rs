@@ -8,11 +8,18 @@
 //! - Hash of all verified messages
 //! - Count of valid signatures
 //! - Aggregated public key commitment
+//!
+//! Batches are capped at `MAX_SIGNATURES`, so larger sets are proven in two levels:
+//! `Leaf` proofs each verify one batch, and an `Aggregate` proof recursively verifies
+//! N leaf (or aggregate) proofs of this same program and folds their outputs into one
+//! commitment with a summed count.
 
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use public_inputs::{Encoder, PublicInputs};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, Bytes};
 use sha2::{Digest, Sha256};
@@ -20,6 +27,9 @@ use sha2::{Digest, Sha256};
 /// Maximum signatures per batch
 const MAX_SIGNATURES: usize = 64;
 
+/// Maximum sub-proofs folded by one aggregate proof
+const MAX_SUB_PROOFS: usize = 64;
+
 /// Single signature entry
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone)]
@@ -41,9 +51,24 @@ pub struct BatchInputs {
     pub entries: Vec<SignatureEntry>,
 }
 
+/// Aggregation inputs: the public outputs of sub-proofs to verify and fold
+#[derive(Serialize, Deserialize)]
+pub struct AggregateInputs {
+    /// Verification key of this program, which produced every sub-proof
+    pub vkey: [u32; 8],
+    pub batches: Vec<BatchOutputs>,
+}
+
+/// Proving mode
+#[derive(Serialize, Deserialize)]
+pub enum CircuitInput {
+    Leaf(BatchInputs),
+    Aggregate(AggregateInputs),
+}
+
 /// Batch outputs (public inputs)
 #[serde_as]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct BatchOutputs {
     /// Hash of all verified messages
     #[serde_as(as = "Bytes")]
@@ -53,12 +78,33 @@ pub struct BatchOutputs {
     pub pubkeys_hash: [u8; 32],
     /// Number of valid signatures
     pub count: u32,
+    /// Verification key the folded sub-proofs were checked against (zero for leaves).
+    /// The verifier must check this equals the program's own key.
+    pub sub_proof_vkey: [u32; 8],
+}
+
+impl PublicInputs for BatchOutputs {
+    /// 32 messages_hash | 32 pubkeys_hash | 4 count | 32 sub_proof_vkey (100 bytes)
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder
+            .bytes(&self.messages_hash)
+            .bytes(&self.pubkeys_hash)
+            .u32(self.count)
+            .words(&self.sub_proof_vkey);
+    }
 }
 
 fn main() {
-    // Read inputs
-    let inputs: BatchInputs = sp1_zkvm::io::read();
+    let outputs = match sp1_zkvm::io::read::<CircuitInput>() {
+        CircuitInput::Leaf(inputs) => prove_batch(&inputs),
+        CircuitInput::Aggregate(inputs) => prove_aggregate(&inputs),
+    };
 
+    public_inputs::commit(&outputs);
+}
+
+/// Verify one batch of signatures
+fn prove_batch(inputs: &BatchInputs) -> BatchOutputs {
     assert!(inputs.entries.len() <= MAX_SIGNATURES, "Too many signatures");
     assert!(!inputs.entries.is_empty(), "No signatures");
 
@@ -82,12 +128,82 @@ fn main() {
         count += 1;
     }
 
-    // Finalize outputs
-    let outputs = BatchOutputs {
+    BatchOutputs {
         messages_hash: messages_hasher.finalize().into(),
         pubkeys_hash: pubkeys_hasher.finalize().into(),
         count,
-    };
+        sub_proof_vkey: [0u32; 8],
+    }
+}
+
+/// Recursively verify sub-proofs of this program and fold their outputs
+fn prove_aggregate(inputs: &AggregateInputs) -> BatchOutputs {
+    assert!(!inputs.batches.is_empty(), "No sub-proofs");
+    assert!(inputs.batches.len() <= MAX_SUB_PROOFS, "Too many sub-proofs");
 
-    sp1_zkvm::io::commit(&outputs);
+    for batch in &inputs.batches {
+        // Nested aggregates must have been built against the same program key
+        assert!(
+            batch.sub_proof_vkey == [0u32; 8] || batch.sub_proof_vkey == inputs.vkey,
+            "Sub-proof verification key mismatch"
+        );
+
+        // A sub-proof's public values are exactly the outputs digest it committed
+        let public_values = public_inputs::digest(batch);
+        let public_values_digest: [u8; 32] = Sha256::digest(&public_values).into();
+        sp1_zkvm::lib::verify::verify_sp1_proof(&inputs.vkey, &public_values_digest);
+    }
+
+    fold_batches(&inputs.batches, inputs.vkey)
+}
+
+/// Fold sub-proof outputs in order: hashes chain the sub-hashes, counts are summed
+fn fold_batches(batches: &[BatchOutputs], vkey: [u32; 8]) -> BatchOutputs {
+    let mut messages_hasher = Sha256::new();
+    let mut pubkeys_hasher = Sha256::new();
+    let mut count: u32 = 0;
+
+    for batch in batches {
+        messages_hasher.update(&batch.messages_hash);
+        pubkeys_hasher.update(&batch.pubkeys_hash);
+        count = count.checked_add(batch.count).expect("Signature count overflow");
+    }
+
+    BatchOutputs {
+        messages_hash: messages_hasher.finalize().into(),
+        pubkeys_hash: pubkeys_hasher.finalize().into(),
+        count,
+        sub_proof_vkey: vkey,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signed_entry(seed: u8, message: &[u8]) -> SignatureEntry {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        SignatureEntry {
+            pubkey: key.verifying_key().to_bytes(),
+            message: message.to_vec(),
+            signature: key.sign(message).to_bytes(),
+        }
+    }
+
+    #[test]
+    fn fold_sums_counts_and_is_order_sensitive() {
+        let a = prove_batch(&BatchInputs {
+            entries: vec![signed_entry(1, b"slot 1"), signed_entry(2, b"slot 1")],
+        });
+        let b = prove_batch(&BatchInputs {
+            entries: vec![signed_entry(3, b"slot 1")],
+        });
+        let vkey = [7u32; 8];
+
+        let folded = fold_batches(&[a.clone(), b.clone()], vkey);
+        assert_eq!(folded.count, 3);
+        assert_eq!(folded.sub_proof_vkey, vkey);
+        assert_ne!(folded.messages_hash, fold_batches(&[b, a], vkey).messages_hash);
+    }
 }
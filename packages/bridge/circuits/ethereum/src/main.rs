@@ -10,15 +10,77 @@
 //! BLS12-381 verification is handled via SP1 precompiles for efficiency,
 //! following Succinct's eth-proof-of-consensus architecture.
 
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
+use bls12_381::{G1Affine, G1Projective};
+use public_inputs::{Encoder, PublicInputs};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, Bytes};
 use sha2::{Digest, Sha256};
 
-/// Sync committee size (512 validators)
-const SYNC_COMMITTEE_SIZE: usize = 512;
+/// Layout version of `EthConsensusInputs`. SP1 reads inputs with bincode, which
+/// has no field names or defaults, so a prover built against another layout
+/// must be refused up front rather than misread. Version 2 added
+/// `sync_committee_size` and the variable-length bitfield.
+const INPUT_VERSION: u32 = 2;
+
+/// Mainnet sync committee size (512 validators)
+const MAINNET_SYNC_COMMITTEE_SIZE: usize = 512;
+
+/// Committee sizes an update may claim: mainnet and the consensus spec's minimal
+/// preset used by testnets. None may exceed `SYNC_COMMITTEE_SIZE` in the
+/// evm-light-client program.
+const ALLOWED_SYNC_COMMITTEE_SIZES: [usize; 2] = [32, 512];
+
+/// Beacon chain fork, selecting the `BeaconState` layout the finality branch is proven against.
+/// Only forks listed here are accepted, so a prover cannot choose arbitrary generalized indices.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fork {
+    Altair,
+    Bellatrix,
+    Capella,
+    Deneb,
+    Electra,
+}
+
+impl Fork {
+    /// Generalized index of `finalized_checkpoint.root` in `BeaconState`
+    /// (FINALIZED_ROOT_GINDEX in the light client spec)
+    pub fn finalized_root_gindex(self) -> usize {
+        match self {
+            // BeaconState has <= 32 fields: depth 5 + 1 for the checkpoint container
+            Fork::Altair | Fork::Bellatrix | Fork::Capella | Fork::Deneb => 105,
+            // Electra grows BeaconState past 32 fields, adding a level to the tree
+            Fork::Electra => 169,
+        }
+    }
+
+    /// Generalized index of `next_sync_committee` in `BeaconState`
+    /// (NEXT_SYNC_COMMITTEE_GINDEX in the light client spec)
+    pub fn next_sync_committee_gindex(self) -> usize {
+        match self {
+            Fork::Altair | Fork::Bellatrix | Fork::Capella | Fork::Deneb => 55,
+            Fork::Electra => 87,
+        }
+    }
+
+    /// (depth, index within that depth) of the finality branch
+    pub fn finality_position(self) -> (usize, usize) {
+        gindex_position(self.finalized_root_gindex())
+    }
+
+    /// (depth, index within that depth) of the next sync committee branch
+    pub fn next_sync_committee_position(self) -> (usize, usize) {
+        gindex_position(self.next_sync_committee_gindex())
+    }
+}
+
+fn gindex_position(gindex: usize) -> (usize, usize) {
+    let depth = gindex.ilog2() as usize;
+    (depth, gindex - (1 << depth))
+}
 
 /// Beacon block header
 #[serde_as]
@@ -38,9 +100,10 @@ pub struct BeaconBlockHeader {
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SyncAggregate {
-    /// Bitfield of participating validators (512 bits = 64 bytes)
+    /// Bitfield of participating validators, one bit per committee member
+    /// (64 bytes on mainnet)
     #[serde_as(as = "Bytes")]
-    pub sync_committee_bits: [u8; 64],
+    pub sync_committee_bits: Vec<u8>,
     /// Aggregated BLS signature (96 bytes compressed)
     #[serde_as(as = "Bytes")]
     pub sync_committee_signature: [u8; 96],
@@ -53,17 +116,35 @@ pub struct LightClientUpdate {
     pub attested_header: BeaconBlockHeader,
     pub finalized_header: BeaconBlockHeader,
     pub sync_aggregate: SyncAggregate,
-    /// Current sync committee aggregate public key
+    /// Current sync committee aggregate public key (`SyncCommittee.aggregate_pubkey`)
     #[serde_as(as = "Bytes")]
     pub sync_committee_pubkey: [u8; 48],
+    /// Individual committee member pubkeys, in committee order (private input)
+    #[serde_as(as = "Vec<Bytes>")]
+    pub sync_committee_pubkeys: Vec<[u8; 48]>,
+    /// Claimed aggregate of the pubkeys selected by `sync_committee_bits`
+    #[serde_as(as = "Bytes")]
+    pub participant_pubkey: [u8; 48],
     /// Merkle branch for finalized header
     pub finality_branch: Vec<[u8; 32]>,
+    /// hash_tree_root of the attested state's `next_sync_committee`
+    #[serde_as(as = "Bytes")]
+    pub next_sync_committee_root: [u8; 32],
+    /// Merkle branch for `next_sync_committee_root` in the attested state
+    pub next_sync_committee_branch: Vec<[u8; 32]>,
+    /// Fork of the attested state, fixing the finality branch depth/index
+    pub fork: Fork,
+    /// Number of validators in the sync committee, one of `ALLOWED_SYNC_COMMITTEE_SIZES`
+    /// (512 on mainnet)
+    pub sync_committee_size: usize,
 }
 
 /// Proof inputs
 #[serde_as]
 #[derive(Serialize, Deserialize)]
 pub struct EthConsensusInputs {
+    /// Must be `INPUT_VERSION`; read first so any other layout fails here
+    pub version: u32,
     pub prev_slot: u64,
     #[serde_as(as = "Bytes")]
     pub prev_block_root: [u8; 32],
@@ -85,11 +166,32 @@ pub struct EthConsensusOutputs {
     #[serde_as(as = "Bytes")]
     pub sync_committee_root: [u8; 32],
     pub participation_count: u32,
+    #[serde_as(as = "Bytes")]
+    pub next_sync_committee_root: [u8; 32],
+}
+
+impl PublicInputs for EthConsensusOutputs {
+    /// 8 prev_slot | 32 prev_block_root | 8 new_slot | 32 new_block_root |
+    /// 32 new_state_root | 32 sync_committee_root | 4 participation_count |
+    /// 32 next_sync_committee_root (180 bytes).
+    /// Must match `validate_public_inputs` in the evm-light-client program.
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder
+            .u64(self.prev_slot)
+            .bytes(&self.prev_block_root)
+            .u64(self.new_slot)
+            .bytes(&self.new_block_root)
+            .bytes(&self.new_state_root)
+            .bytes(&self.sync_committee_root)
+            .u32(self.participation_count)
+            .bytes(&self.next_sync_committee_root);
+    }
 }
 
 fn main() {
     // Read inputs
     let inputs: EthConsensusInputs = sp1_zkvm::io::read();
+    check_input_version(inputs.version);
 
     let update = &inputs.update;
 
@@ -99,40 +201,72 @@ fn main() {
         "Slot not advanced"
     );
 
+    check_committee_shape(
+        update.sync_committee_size,
+        update.sync_committee_pubkeys.len(),
+        update.sync_aggregate.sync_committee_bits.len(),
+    );
+
     // Count participating validators
     let participation = count_sync_committee_bits(&update.sync_aggregate.sync_committee_bits);
-    let required_participation = (SYNC_COMMITTEE_SIZE * 2) / 3;
     assert!(
-        participation >= required_participation,
+        participation >= required_participation(update.sync_committee_size),
         "Insufficient sync committee participation"
     );
 
     // Verify finality branch (proves finalized header is included in attested state)
     let finalized_root = hash_beacon_header(&update.finalized_header);
+    let (finality_depth, finality_index) = update.fork.finality_position();
     assert!(
         verify_merkle_branch(
             &finalized_root,
             &update.finality_branch,
-            6, // Finality depth in beacon state
-            41, // Finality index
+            finality_depth,
+            finality_index,
             &update.attested_header.state_root
         ),
         "Invalid finality branch"
     );
 
+    // Prove the committee that takes over at the next period, so the verifier
+    // can rotate to it without trusting the relayer
+    let (next_committee_depth, next_committee_index) = update.fork.next_sync_committee_position();
+    assert!(
+        verify_merkle_branch(
+            &update.next_sync_committee_root,
+            &update.next_sync_committee_branch,
+            next_committee_depth,
+            next_committee_index,
+            &update.attested_header.state_root
+        ),
+        "Invalid next sync committee branch"
+    );
+
     // Compute attested block root
     let attested_root = hash_beacon_header(&update.attested_header);
 
+    // The participants' aggregate must be derived from the committed committee,
+    // otherwise any pubkey could be paired with an all-ones bitfield
+    let participant_pubkey = aggregate_participant_pubkeys(
+        &update.sync_committee_pubkeys,
+        &update.sync_aggregate.sync_committee_bits,
+    );
+    assert_eq!(
+        participant_pubkey, update.participant_pubkey,
+        "Participant aggregate pubkey mismatch"
+    );
+
     // Verify BLS signature via SP1 precompile
     verify_bls_signature(
-        &update.sync_committee_pubkey,
+        &participant_pubkey,
         &attested_root,
         &update.sync_aggregate.sync_committee_signature,
         &update.sync_aggregate.sync_committee_bits,
     );
 
-    // Compute sync committee root
-    let sync_committee_root = compute_sync_committee_root(&update.sync_committee_pubkey);
+    // Commit the committee itself so the verifier can match it against the stored committee
+    let sync_committee_root =
+        compute_sync_committee_root(&update.sync_committee_pubkeys, &update.sync_committee_pubkey);
 
     // Output public inputs
     let outputs = EthConsensusOutputs {
@@ -143,24 +277,103 @@ fn main() {
         new_state_root: update.finalized_header.state_root,
         sync_committee_root,
         participation_count: participation as u32,
+        next_sync_committee_root: update.next_sync_committee_root,
     };
 
-    sp1_zkvm::io::commit(&outputs);
+    public_inputs::commit(&outputs);
+}
+
+fn check_input_version(version: u32) {
+    assert_eq!(version, INPUT_VERSION, "Unsupported input version");
+}
+
+/// The claimed committee size must be allowed, and the pubkeys and bitfield
+/// must both be sized for it
+fn check_committee_shape(size: usize, pubkeys_len: usize, bits_len: usize) {
+    assert!(
+        ALLOWED_SYNC_COMMITTEE_SIZES.contains(&size),
+        "Unsupported sync committee size"
+    );
+    assert_eq!(pubkeys_len, size, "Sync committee size mismatch");
+    assert_eq!(
+        bits_len,
+        size / 8,
+        "Sync committee bitfield length mismatch"
+    );
+}
+
+/// Signers needed for a committee of `size`: the spec's supermajority
+/// `participants * 3 >= size * 2`, i.e. 2/3 of it rounded up
+fn required_participation(size: usize) -> usize {
+    (size * 2).div_ceil(3)
 }
 
 /// Count set bits in sync committee bitfield
-fn count_sync_committee_bits(bits: &[u8; 64]) -> usize {
+fn count_sync_committee_bits(bits: &[u8]) -> usize {
     bits.iter().map(|b| b.count_ones() as usize).sum()
 }
 
-/// Hash a beacon block header (SSZ)
+/// SSZ Bitvector bit order: bit `i` is bit `i % 8` of byte `i / 8`
+fn is_participant(bits: &[u8], index: usize) -> bool {
+    (bits[index / 8] >> (index % 8)) & 1 == 1
+}
+
+/// Sum the G1 pubkeys of every committee member whose participation bit is set
+fn aggregate_participant_pubkeys(pubkeys: &[[u8; 48]], bits: &[u8]) -> [u8; 48] {
+    let mut aggregate = G1Projective::identity();
+
+    for (index, pubkey) in pubkeys.iter().enumerate() {
+        if !is_participant(bits, index) {
+            continue;
+        }
+        let point: Option<G1Affine> = G1Affine::from_compressed(pubkey).into();
+        aggregate += point.expect("Invalid sync committee pubkey");
+    }
+
+    G1Affine::from(aggregate).to_compressed()
+}
+
+/// SSZ hash_tree_root of a beacon block header.
+///
+/// The five fields become 32-byte chunks (uint64s little-endian, zero-padded),
+/// padded with zero chunks to 8 leaves and merkleized with SHA-256.
 fn hash_beacon_header(header: &BeaconBlockHeader) -> [u8; 32] {
+    merkleize(&[
+        u64_chunk(header.slot),
+        u64_chunk(header.proposer_index),
+        header.parent_root,
+        header.state_root,
+        header.body_root,
+    ])
+}
+
+/// SSZ basic-type packing of a uint64 into a single chunk
+fn u64_chunk(value: u64) -> [u8; 32] {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+/// SSZ merkleization: pad to the next power of two with zero chunks and hash pairwise
+fn merkleize(chunks: &[[u8; 32]]) -> [u8; 32] {
+    let width = chunks.len().max(1).next_power_of_two();
+    let mut layer = chunks.to_vec();
+    layer.resize(width, [0u8; 32]);
+
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| sha256_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    layer[0]
+}
+
+fn sha256_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
     let mut hasher = Sha256::new();
-    hasher.update(&header.slot.to_le_bytes());
-    hasher.update(&header.proposer_index.to_le_bytes());
-    hasher.update(&header.parent_root);
-    hasher.update(&header.state_root);
-    hasher.update(&header.body_root);
+    hasher.update(left);
+    hasher.update(right);
     hasher.finalize().into()
 }
 
@@ -209,7 +422,7 @@ fn verify_bls_signature(
     pubkey: &[u8; 48],
     message: &[u8; 32],
     signature: &[u8; 96],
-    bits: &[u8; 64],
+    bits: &[u8],
 ) {
     // Wrap in Vec for serde serialization (arrays > 32 bytes need wrapper)
     let pubkey_vec = pubkey.to_vec();
@@ -230,9 +443,182 @@ fn verify_bls_signature(
     assert!(is_valid, "BLS signature verification failed");
 }
 
-/// Compute sync committee root from aggregate pubkey
-fn compute_sync_committee_root(pubkey: &[u8; 48]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(pubkey);
-    hasher.finalize().into()
+/// SSZ hash_tree_root of `SyncCommittee { pubkeys: Vector[BLSPubkey, N], aggregate_pubkey }`,
+/// the same root the beacon state commits to for the current sync committee
+fn compute_sync_committee_root(pubkeys: &[[u8; 48]], aggregate_pubkey: &[u8; 48]) -> [u8; 32] {
+    let pubkey_leaves: Vec<[u8; 32]> = pubkeys.iter().map(hash_pubkey).collect();
+    let pubkeys_root = merkleize(&pubkey_leaves);
+    sha256_pair(&pubkeys_root, &hash_pubkey(aggregate_pubkey))
+}
+
+/// A 48-byte BLSPubkey spans two chunks: bytes 0..32 and 32..48 zero-padded
+fn hash_pubkey(pubkey: &[u8; 48]) -> [u8; 32] {
+    let mut first = [0u8; 32];
+    let mut second = [0u8; 32];
+    first.copy_from_slice(&pubkey[..32]);
+    second[..16].copy_from_slice(&pubkey[32..]);
+    sha256_pair(&first, &second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_hex32(hex: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn empty_header_root_is_depth_three_zero_hash() {
+        let header = BeaconBlockHeader {
+            slot: 0,
+            proposer_index: 0,
+            parent_root: [0u8; 32],
+            state_root: [0u8; 32],
+            body_root: [0u8; 32],
+        };
+        // zero_hashes[3] from the consensus spec
+        assert_eq!(
+            hash_beacon_header(&header),
+            decode_hex32("c78009fdf07fc56a11f122370658a353aaa542ed63e44c4bc15ff4cd105ab33c")
+        );
+    }
+
+    #[test]
+    fn finality_positions_per_fork() {
+        for fork in [Fork::Altair, Fork::Bellatrix, Fork::Capella, Fork::Deneb] {
+            assert_eq!(fork.finalized_root_gindex(), 105);
+            assert_eq!(fork.finality_position(), (6, 41));
+        }
+        assert_eq!(Fork::Electra.finalized_root_gindex(), 169);
+        assert_eq!(Fork::Electra.finality_position(), (7, 41));
+    }
+
+    #[test]
+    fn next_sync_committee_positions_per_fork() {
+        for fork in [Fork::Altair, Fork::Bellatrix, Fork::Capella, Fork::Deneb] {
+            assert_eq!(fork.next_sync_committee_position(), (5, 23));
+        }
+        assert_eq!(Fork::Electra.next_sync_committee_position(), (6, 23));
+    }
+
+    #[test]
+    fn finality_branch_verifies_only_at_fork_depth() {
+        let leaf = [7u8; 32];
+        let branch: Vec<[u8; 32]> = (0..7u8).map(|i| [i; 32]).collect();
+
+        // Build the Electra root by hand: index 41 = 0b0101001 read LSB first
+        let mut root = leaf;
+        let mut idx = 41;
+        for sibling in &branch {
+            root = if idx % 2 == 0 {
+                sha256_pair(&root, sibling)
+            } else {
+                sha256_pair(sibling, &root)
+            };
+            idx /= 2;
+        }
+
+        let (depth, index) = Fork::Electra.finality_position();
+        assert!(verify_merkle_branch(&leaf, &branch, depth, index, &root));
+
+        let (depth, index) = Fork::Deneb.finality_position();
+        assert!(!verify_merkle_branch(&leaf, &branch, depth, index, &root));
+    }
+
+    #[test]
+    fn participant_aggregate_only_includes_set_bits() {
+        let g = G1Affine::generator();
+        let two_g = G1Affine::from(G1Projective::from(g) + g);
+        let pubkeys = [g.to_compressed(), two_g.to_compressed(), g.to_compressed()];
+
+        let mut bits = vec![0u8; 64];
+        bits[0] = 0b0000_0011;
+        let expected = G1Affine::from(G1Projective::from(g) + two_g).to_compressed();
+        assert_eq!(aggregate_participant_pubkeys(&pubkeys, &bits), expected);
+
+        // Flipping a bit changes the derived aggregate
+        bits[0] = 0b0000_0101;
+        assert_ne!(aggregate_participant_pubkeys(&pubkeys, &bits), expected);
+    }
+
+    #[test]
+    fn mainnet_committee_shape_and_threshold() {
+        check_committee_shape(MAINNET_SYNC_COMMITTEE_SIZE, 512, 64);
+        // 341 * 3 = 1023 < 1024 is one short of the supermajority
+        assert_eq!(required_participation(MAINNET_SYNC_COMMITTEE_SIZE), 342);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported input version")]
+    fn inputs_from_another_layout_are_rejected() {
+        check_input_version(INPUT_VERSION - 1);
+    }
+
+    #[test]
+    fn testnet_committee_shape_and_threshold() {
+        check_committee_shape(32, 32, 4);
+        assert_eq!(required_participation(32), 22);
+
+        // 22 of 32 signers is enough, 21 is not
+        let bits = vec![0xff, 0xff, 0x3f, 0x00];
+        assert_eq!(count_sync_committee_bits(&bits), 22);
+        assert!(count_sync_committee_bits(&bits) >= required_participation(32));
+        let bits = vec![0xff, 0xff, 0x1f, 0x00];
+        assert!(count_sync_committee_bits(&bits) < required_participation(32));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported sync committee size")]
+    fn committee_size_outside_allow_list_is_rejected() {
+        check_committee_shape(64, 64, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sync committee bitfield length mismatch")]
+    fn mainnet_bitfield_with_testnet_committee_is_rejected() {
+        check_committee_shape(32, 32, 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sync committee size mismatch")]
+    fn pubkey_count_must_match_committee_size() {
+        check_committee_shape(32, 512, 4);
+    }
+
+    #[test]
+    fn sync_committee_root_binds_every_pubkey() {
+        let g = G1Affine::generator().to_compressed();
+        let pubkeys = vec![g; 4];
+        let root = compute_sync_committee_root(&pubkeys, &g);
+
+        let mut swapped = pubkeys.clone();
+        swapped[3] = G1Affine::identity().to_compressed();
+        assert_ne!(compute_sync_committee_root(&swapped, &g), root);
+    }
+
+    #[test]
+    fn mainnet_genesis_header_root() {
+        // Ethereum mainnet beacon chain genesis block (slot 0). Its block root
+        // 0x4d611d5b93fdab69013a7f0a2f961caca0c853f87cfe9595fe50038163079360
+        // and state root 0x7e76880eb67bbdc86250aa578958e9d0675e64e714337855204fb5abaaf82c2b
+        // are the published genesis values (`/eth/v1/beacon/headers/0`,
+        // `/eth/v1/beacon/genesis`). The body is the spec's empty
+        // `BeaconBlockBody()`, whose root is the body_root below.
+        let header = BeaconBlockHeader {
+            slot: 0,
+            proposer_index: 0,
+            parent_root: [0u8; 32],
+            state_root: decode_hex32("7e76880eb67bbdc86250aa578958e9d0675e64e714337855204fb5abaaf82c2b"),
+            body_root: decode_hex32("ccb62460692be0ec813b56be97f68a82cf57abc102e27bf49ebf4190ff22eedd"),
+        };
+        assert_eq!(
+            hash_beacon_header(&header),
+            decode_hex32("4d611d5b93fdab69013a7f0a2f961caca0c853f87cfe9595fe50038163079360")
+        );
+    }
 }
@@ -0,0 +1,127 @@
+//! Canonical Public Inputs
+//!
+//! Every circuit commits a single 32-byte digest of its outputs instead of the
+//! serialized struct, so the on-chain Groth16 verifier binds all outputs with
+//! one field-element public input.
+//!
+//! Canonical encoding (fields concatenated in struct declaration order, no padding):
+//! - `bool`        1 byte, 0x00 or 0x01
+//! - `u32`         4 bytes little-endian
+//! - `u64`         8 bytes little-endian
+//! - `[u8; N]`     N raw bytes
+//! - `[u32; N]`    N × 4 bytes little-endian
+//!
+//! Digest: `keccak256(encoding)` with the top 3 bits of byte 0 cleared, so that
+//! read as a big-endian integer it is below the BN254 scalar field modulus.
+//! The on-chain verifier rebuilds the same encoding and must match these rules.
+
+use sha3::{Digest, Keccak256};
+
+/// Mask applied to the first digest byte to keep it inside the BN254 scalar field
+pub const FIELD_MASK: u8 = 0x1f;
+
+/// Builds the canonical byte encoding of a circuit's outputs
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.buf.push(value as u8);
+        self
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn words(&mut self, value: &[u32]) -> &mut Self {
+        for word in value {
+            self.u32(*word);
+        }
+        self
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Circuit outputs with a canonical encoding
+pub trait PublicInputs {
+    /// Append every field, in declaration order
+    fn encode(&self, encoder: &mut Encoder);
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        self.encode(&mut encoder);
+        encoder.into_bytes()
+    }
+}
+
+/// Field-safe keccak256 digest of a canonical encoding
+pub fn digest_bytes(encoded: &[u8]) -> [u8; 32] {
+    let mut digest: [u8; 32] = Keccak256::digest(encoded).into();
+    digest[0] &= FIELD_MASK;
+    digest
+}
+
+/// Field-safe keccak256 digest of the outputs
+pub fn digest<T: PublicInputs>(outputs: &T) -> [u8; 32] {
+    digest_bytes(&outputs.to_canonical_bytes())
+}
+
+/// Commit the outputs' digest as the proof's only public value
+pub fn commit<T: PublicInputs>(outputs: &T) {
+    sp1_zkvm::io::commit_slice(&digest(outputs));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sample {
+        slot: u64,
+        root: [u8; 32],
+        count: u32,
+        ok: bool,
+    }
+
+    impl PublicInputs for Sample {
+        fn encode(&self, encoder: &mut Encoder) {
+            encoder.u64(self.slot).bytes(&self.root).u32(self.count).bool(self.ok);
+        }
+    }
+
+    #[test]
+    fn encoding_is_packed_little_endian() {
+        let sample = Sample { slot: 0x0102, root: [0xaa; 32], count: 3, ok: true };
+        let encoded = sample.to_canonical_bytes();
+
+        assert_eq!(encoded.len(), 8 + 32 + 4 + 1);
+        assert_eq!(&encoded[..8], &[0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&encoded[8..40], &[0xaa; 32]);
+        assert_eq!(&encoded[40..44], &[3, 0, 0, 0]);
+        assert_eq!(encoded[44], 1);
+    }
+
+    #[test]
+    fn digest_fits_bn254_scalar_field() {
+        // keccak256("") starts with 0xc5, which has the top bits set
+        let digest = digest_bytes(&[]);
+        assert_eq!(digest[0], 0xc5 & FIELD_MASK);
+        assert_eq!(digest[1], 0xd2);
+    }
+}
@@ -5,12 +5,15 @@
 //! 2. The votes are from valid validators (Ed25519 signatures)
 //! 3. The bank hash is correctly derived
 
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use public_inputs::{Encoder, PublicInputs};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, Bytes};
+use supermajority::CIRCUIT_THRESHOLD;
 
 /// Validator vote to be verified
 #[serde_as]
@@ -77,6 +80,18 @@ pub struct ConsensusOutput {
     pub supermajority: bool,
 }
 
+impl PublicInputs for ConsensusOutput {
+    /// 8 slot | 32 bank_hash | 8 voted_stake | 8 total_stake | 1 supermajority (57 bytes)
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder
+            .u64(self.slot)
+            .bytes(&self.bank_hash)
+            .u64(self.voted_stake)
+            .u64(self.total_stake)
+            .bool(self.supermajority);
+    }
+}
+
 fn main() {
     // Read input from the prover
     let input: ConsensusInput = sp1_zkvm::io::read();
@@ -117,9 +132,7 @@ fn main() {
         }
     }
 
-    // Check supermajority (2/3 + 1)
-    let required_stake = (input.total_stake * 2 / 3) + 1;
-    let supermajority = voted_stake >= required_stake;
+    let supermajority = has_supermajority(voted_stake, input.total_stake);
 
     assert!(supermajority, "Supermajority not achieved");
 
@@ -132,8 +145,13 @@ fn main() {
         supermajority,
     };
 
-    // Commit the output
-    sp1_zkvm::io::commit(&output);
+    // Commit the output digest
+    public_inputs::commit(&output);
+}
+
+/// Whether `voted_stake` clears the threshold this circuit is built with
+fn has_supermajority(voted_stake: u64, total_stake: u64) -> bool {
+    CIRCUIT_THRESHOLD.is_met(voted_stake, total_stake)
 }
 
 fn create_vote_message(slot: u64, bank_hash: &[u8; 32]) -> Vec<u8> {
@@ -152,3 +170,19 @@ fn verify_ed25519_signature(pubkey: &[u8; 32], signature: &[u8; 64], message: &[
     };
     verifying_key.verify(message, &sig).is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(feature = "devnet"))]
+    fn supermajority_boundary() {
+        // Same cases as the consensus circuit: exactly 2/3 fails, one lamport more passes
+        assert!(!has_supermajority(200, 300));
+        assert!(has_supermajority(201, 300));
+        assert!(!has_supermajority(6, 10));
+        assert!(has_supermajority(7, 10));
+        assert!(!has_supermajority(0, 0));
+    }
+}
@@ -6,14 +6,28 @@
 //! For Solana: Proves transaction inclusion in a bank hash
 //! For EVM: Proves log/receipt inclusion in a state root via Merkle-Patricia Trie
 
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
+use public_inputs::{Encoder, PublicInputs};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, Bytes};
 use sha2::{Digest, Sha256};
 use sha3::Keccak256;
 
+/// keccak256("TransferInitiated(bytes32,address,address,bytes32,uint256,uint256)"),
+/// emitted by the EVM bridge with topics [sig, transferId, token, sender] and
+/// data `recipient (32) || amount (32) || destChainId (32)`
+const TRANSFER_INITIATED_SIG: &[u8] =
+    b"TransferInitiated(bytes32,address,address,bytes32,uint256,uint256)";
+
+/// Domain-separation prefixes for the Solana inclusion tree. Without them a
+/// 64-byte "signature" made of two sibling hashes would hash to their parent,
+/// passing an internal node off as a leaf.
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+
 /// Transfer details
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone)]
@@ -51,6 +65,8 @@ pub struct SolanaMerkleProof {
 pub struct EVMMerkleProof {
     #[serde_as(as = "Bytes")]
     pub receipt_root: [u8; 32],
+    /// Receipt exactly as stored in the receipt trie: plain RLP for legacy
+    /// transactions, `type || rlp` for EIP-2718 typed transactions
     #[serde_as(as = "Bytes")]
     pub receipt: Vec<u8>,
     pub proof_nodes: Vec<Vec<u8>>,
@@ -58,6 +74,10 @@ pub struct EVMMerkleProof {
     #[serde_as(as = "Bytes")]
     pub state_root: [u8; 32],
     pub receipt_index: u64,
+    /// EVM bridge contract that must have emitted the transfer log. Chosen by
+    /// the prover, so it is committed in the outputs for the verifier to check
+    #[serde_as(as = "Bytes")]
+    pub bridge_address: [u8; 20],
 }
 
 /// Proof type enum
@@ -88,27 +108,55 @@ pub struct TransferProofOutputs {
     #[serde_as(as = "Bytes")]
     pub verified_root: [u8; 32],
     pub block_slot: u64,
+    /// keccak256 of the RLP-encoded bridge log matched in the receipt (zero for Solana)
+    #[serde_as(as = "Bytes")]
+    pub log_digest: [u8; 32],
+    /// Emitter of the matched log (zero for Solana). The verifier must rebuild
+    /// the encoding with the bridge it has registered for `source_chain`, so a
+    /// log from any other contract yields a digest that doesn't match
+    #[serde_as(as = "Bytes")]
+    pub bridge_address: [u8; 20],
+}
+
+impl PublicInputs for TransferProofOutputs {
+    /// 32 transfer_id | 32 transfer_hash | 8 source_chain | 8 dest_chain | 8 amount |
+    /// 32 verified_root | 8 block_slot | 32 log_digest | 20 bridge_address (180 bytes)
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder
+            .bytes(&self.transfer_id)
+            .bytes(&self.transfer_hash)
+            .u64(self.source_chain)
+            .u64(self.dest_chain)
+            .u64(self.amount)
+            .bytes(&self.verified_root)
+            .u64(self.block_slot)
+            .bytes(&self.log_digest)
+            .bytes(&self.bridge_address);
+    }
 }
 
 fn main() {
     let inputs: TransferProofInputs = sp1_zkvm::io::read();
+    public_inputs::commit(&prove_transfer(&inputs));
+}
 
+fn prove_transfer(inputs: &TransferProofInputs) -> TransferProofOutputs {
     // Compute transfer hash for commitment
     let transfer_hash = compute_transfer_hash(&inputs.transfer);
 
     // Verify proof based on chain type
-    let (verified_root, block_slot) = match &inputs.proof {
+    let (verified_root, block_slot, log_digest, bridge_address) = match &inputs.proof {
         ChainProof::Solana(proof) => {
             verify_solana_proof(&inputs.transfer, proof);
-            (proof.bank_hash, proof.slot)
+            (proof.bank_hash, proof.slot, [0u8; 32], [0u8; 20])
         }
         ChainProof::EVM(proof) => {
-            verify_evm_proof(&inputs.transfer, proof);
-            (proof.state_root, proof.block_number)
+            let log_digest = verify_evm_proof(&inputs.transfer, proof);
+            (proof.state_root, proof.block_number, log_digest, proof.bridge_address)
         }
     };
 
-    let outputs = TransferProofOutputs {
+    TransferProofOutputs {
         transfer_id: inputs.transfer.transfer_id,
         transfer_hash,
         source_chain: inputs.transfer.source_chain,
@@ -116,9 +164,9 @@ fn main() {
         amount: inputs.transfer.amount,
         verified_root,
         block_slot,
-    };
-
-    sp1_zkvm::io::commit(&outputs);
+        log_digest,
+        bridge_address,
+    }
 }
 
 fn compute_transfer_hash(transfer: &TokenTransfer) -> [u8; 32] {
@@ -136,34 +184,43 @@ fn compute_transfer_hash(transfer: &TokenTransfer) -> [u8; 32] {
 }
 
 fn verify_solana_proof(transfer: &TokenTransfer, proof: &SolanaMerkleProof) {
-    // Compute transaction hash from signature
+    let root = solana_proof_root(&proof.signature, &proof.path);
+    assert_eq!(root, proof.bank_hash, "Bank hash mismatch");
+    assert_eq!(transfer.source_chain, 101, "Source must be Solana");
+}
+
+/// Root reached from a transaction signature and its sibling path. Leaves are
+/// sha256(LEAF_TAG || signature); parents hash their children in sorted order
+/// after NODE_TAG.
+fn solana_proof_root(signature: &[u8; 64], path: &[[u8; 32]]) -> [u8; 32] {
     let mut hasher = Sha256::new();
-    hasher.update(&proof.signature);
-    let tx_hash: [u8; 32] = hasher.finalize().into();
+    hasher.update(&[LEAF_TAG]);
+    hasher.update(signature);
+    let mut current: [u8; 32] = hasher.finalize().into();
 
-    // Verify merkle path to bank hash
-    let mut current = tx_hash;
-    for sibling in &proof.path {
-        let mut hasher = Sha256::new();
-        if current <= *sibling {
-            hasher.update(&current);
-            hasher.update(sibling);
+    for sibling in path {
+        let (left, right) = if current <= *sibling {
+            (current, *sibling)
         } else {
-            hasher.update(sibling);
-            hasher.update(&current);
-        }
+            (*sibling, current)
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(&[NODE_TAG]);
+        hasher.update(&left);
+        hasher.update(&right);
         current = hasher.finalize().into();
     }
 
-    assert_eq!(current, proof.bank_hash, "Bank hash mismatch");
-    assert_eq!(transfer.source_chain, 101, "Source must be Solana");
+    current
 }
 
-fn verify_evm_proof(transfer: &TokenTransfer, proof: &EVMMerkleProof) {
+/// Returns the digest of the bridge log in the receipt that matches `transfer`
+fn verify_evm_proof(transfer: &TokenTransfer, proof: &EVMMerkleProof) -> [u8; 32] {
     assert!(!proof.receipt.is_empty(), "Receipt required");
     assert!(!proof.proof_nodes.is_empty(), "Proof nodes required");
 
-    // Compute receipt hash using keccak256
+    // Compute receipt hash using keccak256. The trie stores typed receipts with their
+    // type prefix, so the hash covers `type || rlp` as-is
     let receipt_hash = keccak256(&proof.receipt);
 
     // Compute the key for the receipt in the trie (RLP-encoded index)
@@ -188,69 +245,187 @@ fn verify_evm_proof(transfer: &TokenTransfer, proof: &EVMMerkleProof) {
             || transfer.source_chain == 31337,
         "Invalid EVM source chain"
     );
+
+    // The receipt being included is not enough: it must carry the bridge's log for this transfer
+    find_transfer_log(&proof.receipt, &proof.bridge_address, transfer)
+        .expect("Receipt does not contain a matching bridge transfer log")
 }
 
-/// Verify a Merkle-Patricia Trie proof
-fn verify_mpt_proof(key_nibbles: &[u8], value_hash: &[u8; 32], proof_nodes: &[Vec<u8>]) -> [u8; 32] {
-    assert!(!proof_nodes.is_empty(), "Empty proof");
+/// Split an EIP-2718 receipt envelope into its transaction type (0 for legacy)
+/// and the decoded `[status, cumulativeGasUsed, logsBloom, logs]` fields
+fn decode_receipt(receipt: &[u8]) -> (u8, Vec<Vec<u8>>) {
+    assert!(!receipt.is_empty(), "Receipt required");
+
+    // Legacy receipts are a bare RLP list (first byte >= 0xc0); typed receipts
+    // start with a transaction type byte in 0x00..=0x7f
+    let (tx_type, payload) = match receipt[0] {
+        tx_type @ 0x00..=0x7f => (tx_type, &receipt[1..]),
+        0xc0..=0xff => (0, receipt),
+        _ => panic!("Invalid receipt encoding"),
+    };
 
-    let mut key_idx = 0;
-    let mut expected_hash = keccak256(&proof_nodes[0]);
+    let fields = decode_rlp_node(payload);
+    assert_eq!(fields.len(), 4, "Invalid receipt encoding");
 
-    for (i, node) in proof_nodes.iter().enumerate() {
-        // Verify node hash matches expected
-        if i > 0 {
-            let node_hash = keccak256(node);
-            assert_eq!(node_hash, expected_hash, "Node hash mismatch at index {}", i);
+    (tx_type, fields)
+}
+
+/// Search a receipt `[status, cumulativeGasUsed, logsBloom, logs]` for the bridge's
+/// `TransferInitiated` log matching every committed transfer field.
+fn find_transfer_log(
+    receipt: &[u8],
+    bridge_address: &[u8; 20],
+    transfer: &TokenTransfer,
+) -> Option<[u8; 32]> {
+    let (_, fields) = decode_receipt(receipt);
+
+    let event_sig = keccak256(TRANSFER_INITIATED_SIG);
+    let expected_data = [
+        transfer.recipient,
+        u64_to_word(transfer.amount),
+        u64_to_word(transfer.dest_chain),
+    ]
+    .concat();
+
+    decode_rlp_node(&fields[3]).into_iter().find_map(|log| {
+        // log = [address, [topics...], data]
+        let log_fields = decode_rlp_node(&log);
+        if log_fields.len() != 3 || log_fields[0].as_slice() != bridge_address.as_slice() {
+            return None;
         }
 
+        let topics = decode_rlp_node(&log_fields[1]);
+        let matches = topics.len() == 4
+            && topics[0] == event_sig
+            && topics[1] == transfer.transfer_id
+            && topics[2] == transfer.token
+            && topics[3] == transfer.sender
+            && log_fields[2] == expected_data;
+
+        matches.then(|| keccak256(&log))
+    })
+}
+
+/// Encode a u64 as a 32-byte big-endian EVM word
+fn u64_to_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Where a key's path through the trie ended
+#[derive(Debug, PartialEq)]
+enum MptLookup {
+    /// The key is present with this value
+    Found(Vec<u8>),
+    /// The path ends in an empty branch child or diverges from the key
+    Absent,
+}
+
+/// Reference from a branch or extension node to a child node
+enum NodeRef {
+    /// keccak256 of the child, which must be the next proof node
+    Hash([u8; 32]),
+    /// Child shorter than 32 bytes, stored inline as an RLP list
+    Embedded(Vec<u8>),
+}
+
+impl NodeRef {
+    /// Decode a child reference as returned by `decode_rlp_item`: hashes are
+    /// unwrapped strings, embedded nodes keep their list header
+    fn decode(item: &[u8]) -> Self {
+        match item.len() {
+            32 => {
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(item);
+                Self::Hash(hash)
+            }
+            1..=31 if item[0] >= 0xc0 => Self::Embedded(item.to_vec()),
+            len => panic!("Invalid child reference: len={}", len),
+        }
+    }
+}
+
+/// Walk `proof_nodes` from the root along `key_nibbles`, checking every node hash
+///
+/// Like go-ethereum's `resolveNode`, a hashed child must be the next proof node,
+/// while a child shorter than 32 bytes is embedded in its parent and decoded in
+/// place; it is already authenticated by the parent's hash.
+fn walk_mpt(key_nibbles: &[u8], proof_nodes: &[Vec<u8>]) -> MptLookup {
+    assert!(!proof_nodes.is_empty(), "Empty proof");
+
+    let mut key_idx = 0;
+    let mut proof_idx = 0;
+    let mut next = NodeRef::Hash(keccak256(&proof_nodes[0]));
+
+    loop {
+        let node = match next {
+            NodeRef::Hash(expected_hash) => {
+                let node = proof_nodes
+                    .get(proof_idx)
+                    .expect("Proof ended before the key was resolved");
+                let node_hash = keccak256(node);
+                assert_eq!(node_hash, expected_hash, "Node hash mismatch at index {}", proof_idx);
+                proof_idx += 1;
+                node.clone()
+            }
+            NodeRef::Embedded(node) => node,
+        };
+
         // Decode RLP node
-        let decoded = decode_rlp_node(node);
+        let decoded = decode_rlp_node(&node);
 
         match decoded.len() {
             2 => {
                 // Leaf or extension node
                 let (path, is_leaf) = decode_compact_path(&decoded[0]);
+                let remaining = &key_nibbles[key_idx..];
 
                 if is_leaf {
-                    // Leaf node - verify key and value match
-                    assert_eq!(
-                        &key_nibbles[key_idx..],
-                        path.as_slice(),
-                        "Leaf key mismatch"
-                    );
-                    // Value should be the RLP-encoded receipt, hash it
-                    let leaf_value_hash = keccak256(&decoded[1]);
-                    assert_eq!(leaf_value_hash, *value_hash, "Leaf value mismatch");
-                    // Return the root hash (first node's hash)
-                    return keccak256(&proof_nodes[0]);
-                } else {
-                    // Extension node - follow the path
-                    assert_eq!(
-                        &key_nibbles[key_idx..key_idx + path.len()],
-                        path.as_slice(),
-                        "Extension key mismatch"
-                    );
-                    key_idx += path.len();
-                    expected_hash = decode_hash(&decoded[1]);
+                    // A leaf for a different key proves ours is absent
+                    if remaining != path.as_slice() {
+                        return MptLookup::Absent;
+                    }
+                    return MptLookup::Found(decoded[1].clone());
+                }
+
+                // An extension that diverges from our key proves it is absent
+                if !remaining.starts_with(&path) {
+                    return MptLookup::Absent;
                 }
+                key_idx += path.len();
+                next = NodeRef::decode(&decoded[1]);
             }
             17 => {
                 // Branch node
                 if key_idx < key_nibbles.len() {
-                    let nibble = key_nibbles[key_idx] as usize;
+                    let child = &decoded[key_nibbles[key_idx] as usize];
+                    if child.is_empty() {
+                        return MptLookup::Absent;
+                    }
                     key_idx += 1;
-                    expected_hash = decode_hash(&decoded[nibble]);
+                    next = NodeRef::decode(child);
+                } else if decoded[16].is_empty() {
+                    return MptLookup::Absent;
                 } else {
                     // Value is in the 17th element
-                    let branch_value_hash = keccak256(&decoded[16]);
-                    assert_eq!(branch_value_hash, *value_hash, "Branch value mismatch");
-                    return keccak256(&proof_nodes[0]);
+                    return MptLookup::Found(decoded[16].clone());
                 }
             }
             _ => panic!("Invalid RLP node length: {}", decoded.len()),
         }
     }
+}
+
+/// Verify a Merkle-Patricia Trie inclusion proof and return the root it was proven against
+fn verify_mpt_proof(key_nibbles: &[u8], value_hash: &[u8; 32], proof_nodes: &[Vec<u8>]) -> [u8; 32] {
+    match walk_mpt(key_nibbles, proof_nodes) {
+        MptLookup::Found(value) => {
+            // Value should be the RLP-encoded receipt, hash it
+            assert_eq!(keccak256(&value), *value_hash, "Leaf value mismatch");
+        }
+        MptLookup::Absent => panic!("Key not present in trie"),
+    }
 
     keccak256(&proof_nodes[0])
 }
@@ -353,25 +528,6 @@ fn decode_rlp_item(data: &[u8]) -> (Vec<u8>, usize) {
     }
 }
 
-/// Decode a 32-byte hash from RLP
-fn decode_hash(data: &[u8]) -> [u8; 32] {
-    if data.len() == 32 {
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(data);
-        hash
-    } else if data.len() == 33 && data[0] == 0xa0 {
-        // RLP-encoded 32-byte string
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&data[1..]);
-        hash
-    } else if data.len() < 32 {
-        // Short hash (inline value)
-        keccak256(data)
-    } else {
-        panic!("Invalid hash encoding: len={}", data.len());
-    }
-}
-
 /// Convert bytes to nibbles (4-bit values)
 fn bytes_to_nibbles(bytes: &[u8]) -> Vec<u8> {
     let mut nibbles = Vec::with_capacity(bytes.len() * 2);
@@ -409,3 +565,321 @@ fn keccak256(data: &[u8]) -> [u8; 32] {
     hasher.update(data);
     hasher.finalize().into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solana_leaf(signature: &[u8; 64]) -> [u8; 32] {
+        solana_proof_root(signature, &[])
+    }
+
+    fn solana_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        let mut hasher = Sha256::new();
+        hasher.update(&[NODE_TAG]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    #[test]
+    fn solana_signature_path_reproduces_root() {
+        let sigs = [[1u8; 64], [2u8; 64], [3u8; 64], [4u8; 64]];
+        let leaves: Vec<[u8; 32]> = sigs.iter().map(solana_leaf).collect();
+        let left = solana_node(&leaves[0], &leaves[1]);
+        let right = solana_node(&leaves[2], &leaves[3]);
+        let root = solana_node(&left, &right);
+
+        assert_eq!(solana_proof_root(&sigs[0], &[leaves[1], right]), root);
+        assert_eq!(solana_proof_root(&sigs[3], &[leaves[2], left]), root);
+        assert_ne!(solana_proof_root(&[9u8; 64], &[leaves[1], right]), root);
+    }
+
+    #[test]
+    fn internal_node_posing_as_signature_is_rejected() {
+        let sigs = [[1u8; 64], [2u8; 64], [3u8; 64], [4u8; 64]];
+        let leaves: Vec<[u8; 32]> = sigs.iter().map(solana_leaf).collect();
+        let left = solana_node(&leaves[0], &leaves[1]);
+        let right = solana_node(&leaves[2], &leaves[3]);
+        let root = solana_node(&left, &right);
+
+        // Without tags, hashing the two sorted children of `left` as a 64-byte
+        // signature gives `left` itself, and `[right]` is then a valid path
+        let (a, b) = if leaves[0] <= leaves[1] {
+            (leaves[0], leaves[1])
+        } else {
+            (leaves[1], leaves[0])
+        };
+        let mut forged = [0u8; 64];
+        forged[..32].copy_from_slice(&a);
+        forged[32..].copy_from_slice(&b);
+
+        let untagged = |data: &[&[u8]]| -> [u8; 32] {
+            let mut hasher = Sha256::new();
+            for part in data {
+                hasher.update(part);
+            }
+            hasher.finalize().into()
+        };
+        assert_eq!(untagged(&[&forged]), untagged(&[&a, &b]));
+
+        assert_ne!(solana_proof_root(&forged, &[right]), root);
+    }
+
+    fn rlp_bytes(data: &[u8]) -> Vec<u8> {
+        if data.len() == 1 && data[0] < 0x80 {
+            return data.to_vec();
+        }
+        rlp_with_header(0x80, data)
+    }
+
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        rlp_with_header(0xc0, &items.concat())
+    }
+
+    fn rlp_with_header(base: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = if payload.len() <= 55 {
+            vec![base + payload.len() as u8]
+        } else {
+            let len_bytes: Vec<u8> = (payload.len() as u64)
+                .to_be_bytes()
+                .into_iter()
+                .skip_while(|b| *b == 0)
+                .collect();
+            let mut header = vec![base + 55 + len_bytes.len() as u8];
+            header.extend(len_bytes);
+            header
+        };
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn padded_address(byte: u8) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(&[byte; 20]);
+        word
+    }
+
+    fn sample_transfer() -> TokenTransfer {
+        TokenTransfer {
+            transfer_id: [9u8; 32],
+            source_chain: 1,
+            dest_chain: 101,
+            token: padded_address(0x11),
+            sender: padded_address(0x22),
+            recipient: [0x33; 32],
+            amount: 5_000,
+            nonce: 1,
+            timestamp: 0,
+        }
+    }
+
+    fn transfer_log(address: &[u8; 20], transfer: &TokenTransfer, amount: u64) -> Vec<u8> {
+        let topics = rlp_list(&[
+            rlp_bytes(&keccak256(TRANSFER_INITIATED_SIG)),
+            rlp_bytes(&transfer.transfer_id),
+            rlp_bytes(&transfer.token),
+            rlp_bytes(&transfer.sender),
+        ]);
+        let data = [transfer.recipient, u64_to_word(amount), u64_to_word(transfer.dest_chain)].concat();
+        rlp_list(&[rlp_bytes(address), topics, rlp_bytes(&data)])
+    }
+
+    fn receipt_with_logs(logs: &[Vec<u8>]) -> Vec<u8> {
+        rlp_list(&[
+            rlp_bytes(&[1]),
+            rlp_bytes(&[0x52, 0x08]),
+            rlp_bytes(&[0u8; 256]),
+            rlp_list(logs),
+        ])
+    }
+
+    /// Receipt trie with a branch root and one leaf at `index`
+    fn receipt_trie(index: u64, receipt: &[u8]) -> Vec<Vec<u8>> {
+        let key = bytes_to_nibbles(&rlp_encode_index(index));
+        // Leaf path is the single nibble left after the branch (odd length, leaf flag)
+        assert_eq!(key.len(), 2);
+        let leaf = rlp_list(&[rlp_bytes(&[0x30 | key[1]]), rlp_bytes(receipt)]);
+
+        let mut children = vec![rlp_bytes(&[]); 17];
+        children[key[0] as usize] = rlp_bytes(&keccak256(&leaf));
+        vec![rlp_list(&children), leaf]
+    }
+
+    #[test]
+    fn embedded_branch_child_is_resolved() {
+        // Receipts are far too large for their own leaves to be inlined, but the
+        // trie format allows any node under 32 bytes to be, so the walk must too
+        let value = b"tiny".to_vec();
+        let key = bytes_to_nibbles(&rlp_encode_index(0));
+        let inline_leaf = rlp_list(&[rlp_bytes(&[0x30 | key[1]]), rlp_bytes(&value)]);
+        assert!(inline_leaf.len() < 32);
+
+        // A sibling under a hashed child, proven through the same root
+        let sibling_receipt = receipt_with_logs(&[]);
+        let sibling_key = bytes_to_nibbles(&rlp_encode_index(1));
+        let sibling_leaf =
+            rlp_list(&[rlp_bytes(&[0x30 | sibling_key[1]]), rlp_bytes(&sibling_receipt)]);
+
+        let mut children = vec![rlp_bytes(&[]); 17];
+        children[key[0] as usize] = inline_leaf;
+        children[sibling_key[0] as usize] = rlp_bytes(&keccak256(&sibling_leaf));
+        let branch = rlp_list(&children);
+        let root = keccak256(&branch);
+
+        // The inline leaf needs no proof node of its own
+        assert_eq!(verify_mpt_proof(&key, &keccak256(&value), &[branch.clone()]), root);
+        assert_eq!(
+            verify_mpt_proof(&sibling_key, &keccak256(&sibling_receipt), &[branch, sibling_leaf]),
+            root
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Leaf value mismatch")]
+    fn embedded_branch_child_value_is_checked() {
+        let key = bytes_to_nibbles(&rlp_encode_index(0));
+        let mut children = vec![rlp_bytes(&[]); 17];
+        children[key[0] as usize] = rlp_list(&[rlp_bytes(&[0x30 | key[1]]), rlp_bytes(b"tiny")]);
+
+        verify_mpt_proof(&key, &keccak256(b"other"), &[rlp_list(&children)]);
+    }
+
+    #[test]
+    fn included_receipt_is_verified() {
+        let receipt = receipt_with_logs(&[]);
+        let proof = receipt_trie(0, &receipt);
+        let key = bytes_to_nibbles(&rlp_encode_index(0));
+
+        assert_eq!(verify_mpt_proof(&key, &keccak256(&receipt), &proof), keccak256(&proof[0]));
+    }
+
+    #[test]
+    fn missing_key_walks_to_absent() {
+        let proof = receipt_trie(0, &receipt_with_logs(&[]));
+
+        // Index 1 (key 0x01) hits an empty branch child
+        let empty_child = bytes_to_nibbles(&rlp_encode_index(1));
+        assert_eq!(walk_mpt(&empty_child, &proof), MptLookup::Absent);
+
+        // Key 0x81 shares the branch nibble but diverges at the leaf
+        let divergent = bytes_to_nibbles(&[0x81]);
+        assert_eq!(walk_mpt(&divergent, &proof), MptLookup::Absent);
+    }
+
+    #[test]
+    #[should_panic(expected = "Key not present in trie")]
+    fn inclusion_of_missing_key_fails() {
+        let receipt = receipt_with_logs(&[]);
+        let proof = receipt_trie(0, &receipt);
+        verify_mpt_proof(&bytes_to_nibbles(&rlp_encode_index(1)), &keccak256(&receipt), &proof);
+    }
+
+    fn typed_receipt(tx_type: u8, logs: &[Vec<u8>]) -> Vec<u8> {
+        let mut receipt = vec![tx_type];
+        receipt.extend(receipt_with_logs(logs));
+        receipt
+    }
+
+    #[test]
+    fn legacy_and_typed_receipts_are_decoded() {
+        let bridge = [0xbb; 20];
+        let transfer = sample_transfer();
+        let log = transfer_log(&bridge, &transfer, transfer.amount);
+
+        // Legacy, EIP-2930 and EIP-1559
+        let receipts = [
+            (0u8, receipt_with_logs(&[log.clone()])),
+            (1u8, typed_receipt(0x01, &[log.clone()])),
+            (2u8, typed_receipt(0x02, &[log.clone()])),
+        ];
+
+        for (tx_type, receipt) in receipts {
+            let (decoded_type, fields) = decode_receipt(&receipt);
+            assert_eq!(decoded_type, tx_type);
+            assert_eq!(fields[0], vec![1]);
+
+            // The trie commits to the typed envelope, not the inner RLP
+            let proof = receipt_trie(0, &receipt);
+            let key = bytes_to_nibbles(&rlp_encode_index(0));
+            assert_eq!(verify_mpt_proof(&key, &keccak256(&receipt), &proof), keccak256(&proof[0]));
+
+            assert_eq!(find_transfer_log(&receipt, &bridge, &transfer), Some(keccak256(&log)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Leaf value mismatch")]
+    fn typed_receipt_hash_must_include_type_byte() {
+        let receipt = typed_receipt(0x02, &[]);
+        let proof = receipt_trie(0, &receipt);
+        let inner_hash = keccak256(&receipt[1..]);
+        verify_mpt_proof(&bytes_to_nibbles(&rlp_encode_index(0)), &inner_hash, &proof);
+    }
+
+    #[test]
+    fn matching_log_is_found_and_digested() {
+        let bridge = [0xbb; 20];
+        let transfer = sample_transfer();
+        let other_log = transfer_log(&[0xcc; 20], &transfer, transfer.amount);
+        let log = transfer_log(&bridge, &transfer, transfer.amount);
+        let receipt = receipt_with_logs(&[other_log, log.clone()]);
+
+        assert_eq!(find_transfer_log(&receipt, &bridge, &transfer), Some(keccak256(&log)));
+    }
+
+    fn evm_inputs(bridge: [u8; 20], emitter: [u8; 20]) -> TransferProofInputs {
+        let transfer = sample_transfer();
+        let receipt = receipt_with_logs(&[transfer_log(&emitter, &transfer, transfer.amount)]);
+        let proof_nodes = receipt_trie(0, &receipt);
+        TransferProofInputs {
+            transfer,
+            proof: ChainProof::EVM(EVMMerkleProof {
+                receipt_root: keccak256(&proof_nodes[0]),
+                receipt,
+                proof_nodes,
+                block_number: 100,
+                state_root: [0x44; 32],
+                receipt_index: 0,
+                bridge_address: bridge,
+            }),
+        }
+    }
+
+    #[test]
+    fn bridge_address_is_committed() {
+        let bridge = [0xbb; 20];
+        let outputs = prove_transfer(&evm_inputs(bridge, bridge));
+        assert_eq!(outputs.bridge_address, bridge);
+
+        let encoded = outputs.to_canonical_bytes();
+        assert_eq!(encoded.len(), 180);
+        assert_eq!(encoded[160..], bridge);
+
+        // A prover pointing the circuit at its own contract gets a proof, but
+        // not one that matches the encoding a verifier builds for the real bridge
+        let forged = prove_transfer(&evm_inputs([0xcc; 20], [0xcc; 20]));
+        let forged_digest = public_inputs::digest(&forged);
+        let expected = TransferProofOutputs { bridge_address: bridge, ..forged };
+        assert_ne!(forged_digest, public_inputs::digest(&expected));
+    }
+
+    #[test]
+    #[should_panic(expected = "Receipt does not contain a matching bridge transfer log")]
+    fn log_from_another_emitter_is_rejected() {
+        prove_transfer(&evm_inputs([0xbb; 20], [0xcc; 20]));
+    }
+
+    #[test]
+    fn fabricated_transfer_fields_are_rejected() {
+        let bridge = [0xbb; 20];
+        let transfer = sample_transfer();
+        let receipt = receipt_with_logs(&[transfer_log(&bridge, &transfer, 1)]);
+        assert_eq!(find_transfer_log(&receipt, &bridge, &transfer), None);
+
+        // Right fields, wrong emitter
+        let receipt = receipt_with_logs(&[transfer_log(&[0xcc; 20], &transfer, transfer.amount)]);
+        assert_eq!(find_transfer_log(&receipt, &bridge, &transfer), None);
+    }
+}
@@ -0,0 +1,98 @@
+//! Supermajority Threshold
+//!
+//! The one stake threshold both Solana consensus circuits enforce, so they
+//! cannot drift apart on the boundary.
+//!
+//! Solana's supermajority is strictly more than 2/3 of the epoch's total
+//! stake (`VOTE_THRESHOLD_SIZE`, compared with `>`). With integers that is
+//! `voted * 3 > total * 2`, which is also `voted >= total * 2 / 3 + 1`.
+//!
+//! The threshold is fixed when a circuit is built, not read from its inputs,
+//! so a prover can't pick a lower one. Building with the `devnet` feature
+//! swaps in a simple majority; that builds a different program with its own
+//! verifying key, so a devnet proof never passes a mainnet verifier.
+
+/// Stake must be strictly more than `numerator / denominator` of the total
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Threshold {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl Threshold {
+    /// Solana's supermajority, more than 2/3 of stake
+    pub const SOLANA_SUPERMAJORITY: Threshold = Threshold::new(2, 3);
+
+    /// Simple majority, more than 1/2 of stake
+    pub const SIMPLE_MAJORITY: Threshold = Threshold::new(1, 2);
+
+    pub const fn new(numerator: u64, denominator: u64) -> Self {
+        assert!(
+            denominator > 0 && numerator < denominator,
+            "Invalid threshold"
+        );
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Whether `voted` stake is strictly more than the threshold share of
+    /// `total`. Nothing meets it when there is no stake at all.
+    pub fn is_met(&self, voted: u64, total: u64) -> bool {
+        total > 0
+            && voted as u128 * self.denominator as u128 > total as u128 * self.numerator as u128
+    }
+
+    /// Least stake that meets the threshold
+    pub fn required_stake(&self, total: u64) -> u64 {
+        (total as u128 * self.numerator as u128 / self.denominator as u128) as u64 + 1
+    }
+}
+
+/// Threshold the consensus circuits are built with
+pub const CIRCUIT_THRESHOLD: Threshold = if cfg!(feature = "devnet") {
+    Threshold::SIMPLE_MAJORITY
+} else {
+    Threshold::SOLANA_SUPERMAJORITY
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supermajority_is_strictly_more_than_two_thirds() {
+        let threshold = Threshold::SOLANA_SUPERMAJORITY;
+
+        // Exactly 2/3 is not enough
+        assert!(!threshold.is_met(6, 9));
+        assert!(threshold.is_met(7, 9));
+        assert!(!threshold.is_met(200, 300));
+        assert!(threshold.is_met(201, 300));
+
+        // Not divisible by 3: the first stake above 2/3 is enough
+        assert!(!threshold.is_met(6, 10));
+        assert!(threshold.is_met(7, 10));
+
+        assert!(!threshold.is_met(0, 0));
+        assert!(threshold.is_met(u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn required_stake_is_the_boundary() {
+        for threshold in [Threshold::SOLANA_SUPERMAJORITY, Threshold::SIMPLE_MAJORITY] {
+            for total in [1, 2, 3, 9, 10, 300, 1_000_000_007, u64::MAX] {
+                let required = threshold.required_stake(total);
+                assert!(threshold.is_met(required, total));
+                assert!(!threshold.is_met(required - 1, total));
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "devnet"))]
+    fn default_build_uses_solana_supermajority() {
+        assert_eq!(CIRCUIT_THRESHOLD, Threshold::SOLANA_SUPERMAJORITY);
+    }
+}
@@ -2,18 +2,27 @@
 //!
 //! This SP1 program verifies cross-chain token transfers by proving:
 //! 1. The transfer was included in a valid source chain state
-//! 2. The sender had sufficient balance
+//! 2. The sender had sufficient balance, by proving their pre-transfer balance
+//!    against the same state root
 //! 3. The transfer data is correctly formatted
 //!
 //! The program outputs a commitment that can be verified on-chain.
 
-#![no_main]
+#![cfg_attr(not(test), no_main)]
+#[cfg(not(test))]
 sp1_zkvm::entrypoint!(main);
 
+use public_inputs::{Encoder, PublicInputs};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, Bytes};
 use sha3::{Digest, Keccak256};
 
+/// Domain-separation prefixes for the state tree. Leaves are hashed once more
+/// under LEAF_TAG, so an internal node can't be presented as a leaf with a
+/// shorter proof.
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+
 /// Transfer data to be proven
 #[serde_as]
 #[derive(Serialize, Deserialize)]
@@ -45,6 +54,12 @@ pub struct TransferInput {
     pub merkle_proof: Vec<[u8; 32]>,
     /// Merkle proof indices (left/right)
     pub proof_indices: Vec<bool>,
+    /// Sender's balance of `token` before the transfer
+    pub sender_balance: u64,
+    /// Merkle proof of the sender's balance leaf against `state_root`
+    pub balance_proof: Vec<[u8; 32]>,
+    /// Balance proof indices (left/right)
+    pub balance_proof_indices: Vec<bool>,
 }
 
 /// Output commitment proven by this program
@@ -61,6 +76,21 @@ pub struct TransferOutput {
     pub source_chain: u32,
     /// Destination chain ID
     pub dest_chain: u32,
+    /// Sender balance proven against `state_root`
+    pub sender_balance: u64,
+}
+
+impl PublicInputs for TransferOutput {
+    /// 32 transfer_hash | 32 state_root | 4 source_chain | 4 dest_chain |
+    /// 8 sender_balance (80 bytes)
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder
+            .bytes(&self.transfer_hash)
+            .bytes(&self.state_root)
+            .u32(self.source_chain)
+            .u32(self.dest_chain)
+            .u64(self.sender_balance);
+    }
 }
 
 fn main() {
@@ -83,6 +113,18 @@ fn main() {
         "Merkle proof verification failed"
     );
 
+    // Verify the sender's balance against the same state root
+    let balance_leaf = compute_balance_leaf(&input.sender, &input.token, input.sender_balance);
+    let balance_root = verify_merkle_proof(
+        &balance_leaf,
+        &input.balance_proof,
+        &input.balance_proof_indices,
+    );
+    assert_eq!(
+        balance_root, input.state_root,
+        "Balance proof verification failed"
+    );
+
     // Validate chain IDs
     assert!(input.source_chain > 0, "Invalid source chain");
     assert!(input.dest_chain > 0, "Invalid dest chain");
@@ -90,6 +132,7 @@ fn main() {
 
     // Validate amount
     assert!(input.amount > 0, "Zero amount transfer");
+    assert!(input.sender_balance >= input.amount, "Insufficient sender balance");
 
     // Create output commitment
     let output = TransferOutput {
@@ -97,10 +140,11 @@ fn main() {
         state_root: input.state_root,
         source_chain: input.source_chain,
         dest_chain: input.dest_chain,
+        sender_balance: input.sender_balance,
     };
 
-    // Commit the output (this becomes the public inputs to the ZK proof)
-    sp1_zkvm::io::commit(&output);
+    // Commit the output digest (this becomes the public input to the ZK proof)
+    public_inputs::commit(&output);
 }
 
 /// Compute keccak256 hash of transfer data
@@ -117,14 +161,28 @@ fn compute_transfer_hash(input: &TransferInput) -> [u8; 32] {
     hasher.finalize().into()
 }
 
+/// Compute the state leaf recording `owner`'s balance of `token`
+fn compute_balance_leaf(owner: &[u8; 32], token: &[u8; 32], balance: u64) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"balance");
+    hasher.update(owner);
+    hasher.update(token);
+    hasher.update(&balance.to_be_bytes());
+    hasher.finalize().into()
+}
+
 /// Verify a merkle proof
 fn verify_merkle_proof(leaf: &[u8; 32], proof: &[[u8; 32]], indices: &[bool]) -> [u8; 32] {
     assert_eq!(proof.len(), indices.len(), "Proof/indices length mismatch");
 
-    let mut current = *leaf;
+    let mut hasher = Keccak256::new();
+    hasher.update(&[LEAF_TAG]);
+    hasher.update(leaf);
+    let mut current: [u8; 32] = hasher.finalize().into();
 
     for (i, sibling) in proof.iter().enumerate() {
         let mut hasher = Keccak256::new();
+        hasher.update(&[NODE_TAG]);
         if indices[i] {
             // Current is on the right
             hasher.update(sibling);
@@ -139,3 +197,112 @@ fn verify_merkle_proof(leaf: &[u8; 32], proof: &[[u8; 32]], indices: &[bool]) ->
 
     current
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keccak_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(&[LEAF_TAG]);
+        hasher.update(leaf);
+        hasher.finalize().into()
+    }
+
+    fn keccak_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(&[NODE_TAG]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    fn sample_input() -> TransferInput {
+        TransferInput {
+            transfer_id: [1u8; 32],
+            source_chain: 1,
+            dest_chain: 420690,
+            sender: [2u8; 32],
+            recipient: [3u8; 32],
+            token: [4u8; 32],
+            amount: 1_000,
+            nonce: 7,
+            state_root: [0u8; 32],
+            merkle_proof: vec![[5u8; 32], [6u8; 32]],
+            proof_indices: vec![false, true],
+            sender_balance: 5_000,
+            balance_proof: vec![[8u8; 32]],
+            balance_proof_indices: vec![false],
+        }
+    }
+
+    #[test]
+    fn valid_proof_reproduces_root() {
+        let input = sample_input();
+        let leaf = compute_transfer_hash(&input);
+        let expected = keccak_pair(&[6u8; 32], &keccak_pair(&keccak_leaf(&leaf), &[5u8; 32]));
+
+        let root = verify_merkle_proof(&leaf, &input.merkle_proof, &input.proof_indices);
+        assert_eq!(root, expected);
+    }
+
+    #[test]
+    fn tampered_proof_fails_to_reproduce_root() {
+        let input = sample_input();
+        let leaf = compute_transfer_hash(&input);
+        let root = verify_merkle_proof(&leaf, &input.merkle_proof, &input.proof_indices);
+
+        let mut tampered_sibling = input.merkle_proof.clone();
+        tampered_sibling[0][0] ^= 1;
+        assert_ne!(verify_merkle_proof(&leaf, &tampered_sibling, &input.proof_indices), root);
+
+        let flipped_indices = vec![true, true];
+        assert_ne!(verify_merkle_proof(&leaf, &input.merkle_proof, &flipped_indices), root);
+
+        let mut tampered = sample_input();
+        tampered.amount += 1;
+        let tampered_leaf = compute_transfer_hash(&tampered);
+        assert_ne!(
+            verify_merkle_proof(&tampered_leaf, &input.merkle_proof, &input.proof_indices),
+            root
+        );
+    }
+
+    #[test]
+    fn internal_node_posing_as_leaf_is_rejected() {
+        let input = sample_input();
+        let leaf = compute_transfer_hash(&input);
+        let root = verify_merkle_proof(&leaf, &input.merkle_proof, &input.proof_indices);
+
+        // The node one level up, with the rest of the path, would reproduce
+        // the root if leaves and internal nodes hashed the same way
+        let internal = keccak_pair(&keccak_leaf(&leaf), &[5u8; 32]);
+        assert_eq!(keccak_pair(&[6u8; 32], &internal), root);
+        assert_ne!(verify_merkle_proof(&internal, &[[6u8; 32]], &[true]), root);
+    }
+
+    #[test]
+    fn balance_leaf_binds_owner_token_and_amount() {
+        let input = sample_input();
+        let leaf = compute_balance_leaf(&input.sender, &input.token, input.sender_balance);
+
+        assert_ne!(leaf, compute_balance_leaf(&input.sender, &input.token, input.sender_balance + 1));
+        assert_ne!(leaf, compute_balance_leaf(&input.recipient, &input.token, input.sender_balance));
+        assert_ne!(leaf, compute_balance_leaf(&input.sender, &input.recipient, input.sender_balance));
+        // Domain-separated from transfer leaves
+        assert_ne!(leaf, compute_transfer_hash(&input));
+    }
+
+    #[test]
+    fn balance_proof_reproduces_root() {
+        let input = sample_input();
+        let leaf = compute_balance_leaf(&input.sender, &input.token, input.sender_balance);
+
+        let root = verify_merkle_proof(&leaf, &input.balance_proof, &input.balance_proof_indices);
+        assert_eq!(root, keccak_pair(&keccak_leaf(&leaf), &[8u8; 32]));
+
+        // A forged balance doesn't reproduce the root
+        let forged = compute_balance_leaf(&input.sender, &input.token, u64::MAX);
+        assert_ne!(verify_merkle_proof(&forged, &input.balance_proof, &input.balance_proof_indices), root);
+    }
+}
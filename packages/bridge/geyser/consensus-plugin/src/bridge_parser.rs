@@ -0,0 +1,118 @@
+//! Decoding of token-bridge `initiate_transfer` instructions.
+//!
+//! The bridge is an Anchor program, so instruction data is laid out as
+//! `[8-byte discriminator][borsh args]` and events are logged as
+//! `Program data: <base64([8-byte discriminator][borsh fields])>`.
+
+use base64::Engine;
+use solana_sdk::{
+    clock::Slot, hash::hash, instruction::CompiledInstruction, pubkey::Pubkey,
+    signature::Signature,
+};
+
+use crate::BridgeTransferEvent;
+
+/// Position of the `sender` signer in the bridge's `InitiateTransfer` accounts struct:
+/// state, token_config, transfer_record, mint, bridge_vault, sender_token_account, sender, ...
+const SENDER_ACCOUNT_INDEX: usize = 6;
+
+const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Decoded arguments of `initiate_transfer(evm_recipient, amount, payload)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitiateTransferArgs {
+    pub evm_recipient: [u8; 20],
+    pub amount: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Anchor discriminator: first 8 bytes of sha256("<namespace>:<name>")
+fn discriminator(preimage: &str) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&hash(preimage.as_bytes()).to_bytes()[..8]);
+    out
+}
+
+pub fn initiate_transfer_discriminator() -> [u8; 8] {
+    discriminator("global:initiate_transfer")
+}
+
+pub fn transfer_initiated_event_discriminator() -> [u8; 8] {
+    discriminator("event:TransferInitiated")
+}
+
+/// Parse `initiate_transfer` instruction data. Returns `None` for any other instruction.
+pub fn parse_initiate_transfer(data: &[u8]) -> Option<InitiateTransferArgs> {
+    let (disc, args) = data.split_at_checked(8)?;
+    if disc != initiate_transfer_discriminator() {
+        return None;
+    }
+
+    let (recipient, rest) = args.split_at_checked(20)?;
+    let (amount, rest) = rest.split_at_checked(8)?;
+    let (payload_len, rest) = rest.split_at_checked(4)?;
+    let payload_len = u32::from_le_bytes(payload_len.try_into().ok()?) as usize;
+    let payload = rest.get(..payload_len)?;
+
+    Some(InitiateTransferArgs {
+        evm_recipient: recipient.try_into().ok()?,
+        amount: u64::from_le_bytes(amount.try_into().ok()?),
+        payload: payload.to_vec(),
+    })
+}
+
+/// Extract transfer IDs from `TransferInitiated` events in the transaction logs, in emit order.
+pub fn transfer_ids_from_logs(logs: &[String]) -> Vec<[u8; 32]> {
+    let event_disc = transfer_initiated_event_discriminator();
+
+    logs.iter()
+        .filter_map(|line| line.strip_prefix(PROGRAM_DATA_LOG_PREFIX))
+        .filter_map(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded).ok())
+        .filter(|data| data.len() >= 40 && data[..8] == event_disc)
+        .filter_map(|data| data[8..40].try_into().ok())
+        .collect()
+}
+
+/// Decode every top-level bridge `initiate_transfer` in a transaction.
+///
+/// Each decoded instruction is paired with the transfer ID from the matching
+/// `TransferInitiated` event; instructions without one (e.g. the transaction failed)
+/// are dropped, since the transfer ID is assigned by the program at execution time.
+pub fn parse_bridge_transfers(
+    bridge_program_id: &Pubkey,
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    logs: &[String],
+    signature: &Signature,
+    slot: Slot,
+    dest_chain: u64,
+) -> Vec<BridgeTransferEvent> {
+    let mut transfer_ids = transfer_ids_from_logs(logs).into_iter();
+    let mut signature_bytes = [0u8; 64];
+    signature_bytes.copy_from_slice(signature.as_ref());
+
+    instructions
+        .iter()
+        .filter(|ix| account_keys.get(ix.program_id_index as usize) == Some(bridge_program_id))
+        .filter_map(|ix| {
+            let args = parse_initiate_transfer(&ix.data)?;
+            let sender_index = *ix.accounts.get(SENDER_ACCOUNT_INDEX)? as usize;
+            let sender = account_keys.get(sender_index)?;
+            let transfer_id = transfer_ids.next()?;
+
+            // EVM addresses are left-padded to 32 bytes, matching the bridge's ABI encoding
+            let mut recipient = [0u8; 32];
+            recipient[12..].copy_from_slice(&args.evm_recipient);
+
+            Some(BridgeTransferEvent {
+                transfer_id,
+                slot,
+                signature: signature_bytes,
+                sender: sender.to_bytes(),
+                recipient,
+                amount: args.amount,
+                dest_chain,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+//! Per-slot consensus data accumulation.
+//!
+//! Votes for slot N land in later slots, so a snapshot is held as pending when its
+//! block metadata arrives and only released once the slot is confirmed, by which
+//! point a supermajority of votes for it has been observed.
+
+use solana_sdk::{
+    clock::Slot,
+    hash::hashv,
+    instruction::CompiledInstruction,
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    signature::Signature,
+    vote::instruction::VoteInstruction,
+};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::{ConsensusSnapshot, ValidatorVote};
+
+/// Slots of votes/signatures kept behind the newest pending snapshot before pruning
+const MAX_BUFFERED_SLOTS: u64 = 512;
+
+/// Domain-separation prefixes for the transactions tree, matching the state
+/// circuit's Solana inclusion proof. Without them a 64-byte signature made of
+/// two sibling hashes would hash to their parent.
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+
+/// Decode the votes cast by a vote transaction.
+///
+/// The validator identity is the fee payer (first account key), which signs vote
+/// transactions; the vote account is the first account of each vote instruction.
+pub fn parse_votes(
+    vote_program_id: &Pubkey,
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    signature: &Signature,
+) -> Vec<ValidatorVote> {
+    let Some(validator) = account_keys.first() else {
+        return vec![];
+    };
+    let mut signature_bytes = [0u8; 64];
+    signature_bytes.copy_from_slice(signature.as_ref());
+
+    instructions
+        .iter()
+        .filter(|ix| account_keys.get(ix.program_id_index as usize) == Some(vote_program_id))
+        .filter_map(|ix| {
+            let vote_ix: VoteInstruction = limited_deserialize(&ix.data).ok()?;
+            let slot = vote_ix.last_voted_slot()?;
+            let hash = vote_ix.hash()?;
+            let vote_account = account_keys.get(*ix.accounts.first()? as usize)?;
+
+            Some(ValidatorVote {
+                validator: validator.to_bytes(),
+                vote_account: vote_account.to_bytes(),
+                slot,
+                hash: hash.to_bytes(),
+                signature: signature_bytes,
+                timestamp: vote_ix.timestamp().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Transaction leaf as hashed by the state circuit
+pub fn transaction_leaf(signature: &[u8; 64]) -> [u8; 32] {
+    hashv(&[&[LEAF_TAG], signature.as_ref()]).to_bytes()
+}
+
+fn hash_sorted(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[NODE_TAG], left.as_ref(), right.as_ref()]).to_bytes()
+}
+
+/// Binary SHA-256 Merkle root over transaction signatures, in notification order.
+/// Parents hash their children in sorted order and an odd node is carried up
+/// unchanged, so the state circuit can check inclusion from a sibling path
+/// alone; an empty slot has a zero root.
+pub fn merkle_root(signatures: &[[u8; 64]]) -> [u8; 32] {
+    if signatures.is_empty() {
+        return [0u8; 32];
+    }
+
+    let mut level: Vec<[u8; 32]> = signatures.iter().map(transaction_leaf).collect();
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_sorted(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
+/// Buffers votes, transaction signatures and pending snapshots keyed by slot
+#[derive(Default)]
+pub struct ConsensusBuffer {
+    votes: Mutex<BTreeMap<Slot, Vec<ValidatorVote>>>,
+    signatures: Mutex<BTreeMap<Slot, Vec<[u8; 64]>>>,
+    pending: Mutex<BTreeMap<Slot, ConsensusSnapshot>>,
+}
+
+impl ConsensusBuffer {
+    pub fn record_votes(&self, votes: Vec<ValidatorVote>) {
+        let mut buffered = self.votes.lock().unwrap();
+        for vote in votes {
+            let slot_votes = buffered.entry(vote.slot).or_default();
+            // A validator may re-vote (e.g. after a fork switch); keep one vote per validator
+            if !slot_votes.iter().any(|v| v.validator == vote.validator) {
+                slot_votes.push(vote);
+            }
+        }
+    }
+
+    pub fn record_signature(&self, slot: Slot, signature: &Signature) {
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(signature.as_ref());
+        self.signatures.lock().unwrap().entry(slot).or_default().push(bytes);
+    }
+
+    /// Remove the slot's recorded signatures and return their Merkle root
+    pub fn take_transactions_root(&self, slot: Slot) -> [u8; 32] {
+        let signatures = self.signatures.lock().unwrap().remove(&slot).unwrap_or_default();
+        merkle_root(&signatures)
+    }
+
+    pub fn add_pending(&self, snapshot: ConsensusSnapshot) {
+        let slot = snapshot.slot;
+        self.pending.lock().unwrap().insert(slot, snapshot);
+        self.prune(slot.saturating_sub(MAX_BUFFERED_SLOTS));
+    }
+
+    /// Release the pending snapshot for a confirmed slot with its votes attached
+    pub fn take_confirmed(&self, slot: Slot) -> Option<ConsensusSnapshot> {
+        let mut snapshot = self.pending.lock().unwrap().remove(&slot)?;
+        snapshot.votes = self.votes.lock().unwrap().remove(&slot).unwrap_or_default();
+        Some(snapshot)
+    }
+
+    /// Drop everything buffered for slots older than `min_slot`
+    fn prune(&self, min_slot: Slot) {
+        self.votes.lock().unwrap().retain(|slot, _| *slot >= min_slot);
+        self.signatures.lock().unwrap().retain(|slot, _| *slot >= min_slot);
+        self.pending.lock().unwrap().retain(|slot, _| *slot >= min_slot);
+    }
+}
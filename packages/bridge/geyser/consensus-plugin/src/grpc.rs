@@ -0,0 +1,174 @@
+//! gRPC streaming transport to the relayer.
+//!
+//! One bidirectional `Publish` stream (see `proto/relayer.proto`) stays open
+//! while the relayer is reachable. Each message is sent as an `Envelope` and is
+//! not considered delivered until the relayer acks its sequence number; the
+//! next message waits for that ack, which is what pushes back on the plugin
+//! when the relayer falls behind. A broken stream is dropped and reopened on
+//! the next message.
+
+use std::fs;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint};
+
+pub mod proto {
+    tonic::include_proto!("evmsol.relayer.v1");
+}
+
+use proto::relayer_client::RelayerClient;
+use proto::{Ack, Envelope};
+
+use crate::poster::PayloadSignature;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the relayer has to ack a message before the stream is abandoned
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a message was not delivered over the stream
+#[derive(Debug)]
+pub enum GrpcError {
+    /// The relayer received the message and nacked it
+    Rejected(String),
+    /// No usable stream; the message may be sent another way
+    Unavailable(String),
+}
+
+struct Session {
+    requests: mpsc::Sender<Envelope>,
+    acks: tonic::Streaming<Ack>,
+    next_sequence: u64,
+}
+
+/// Client for the relayer's `Publish` stream
+pub struct GrpcTransport {
+    endpoint: Endpoint,
+    bearer_token: Option<MetadataValue<Ascii>>,
+    session: Mutex<Option<Session>>,
+}
+
+impl GrpcTransport {
+    /// Transport for `url`. TLS is used for `https://` URLs, trusting
+    /// `ca_cert_path` in addition to the system roots.
+    pub fn new(url: &str, ca_cert_path: Option<&str>, bearer_token: Option<&str>) -> Result<Self, String> {
+        let mut endpoint = Endpoint::from_shared(url.to_string())
+            .map_err(|e| format!("Invalid gRPC endpoint {}: {}", url, e))?
+            .connect_timeout(CONNECT_TIMEOUT);
+
+        if url.starts_with("https://") {
+            let mut tls = ClientTlsConfig::new();
+            if let Some(path) = ca_cert_path {
+                let pem = fs::read(path).map_err(|e| format!("Failed to read CA cert {}: {}", path, e))?;
+                tls = tls.ca_certificate(Certificate::from_pem(pem));
+            }
+            endpoint = endpoint.tls_config(tls).map_err(|e| e.to_string())?;
+        }
+
+        let bearer_token = bearer_token
+            .map(|token| {
+                format!("Bearer {}", token)
+                    .parse()
+                    .map_err(|_| "Auth token is not valid gRPC metadata".to_string())
+            })
+            .transpose()?;
+
+        Ok(Self {
+            endpoint,
+            bearer_token,
+            session: Mutex::new(None),
+        })
+    }
+
+    async fn connect(&self) -> Result<Session, String> {
+        let channel = self.endpoint.connect().await.map_err(|e| e.to_string())?;
+        let token = self.bearer_token.clone();
+        let mut client = RelayerClient::with_interceptor(channel, move |mut request: tonic::Request<()>| {
+            if let Some(token) = &token {
+                request.metadata_mut().insert("authorization", token.clone());
+            }
+            Ok(request)
+        });
+
+        // Capacity 1: at most one envelope is ever awaiting its ack
+        let (requests, outgoing) = mpsc::channel(1);
+        let acks = client
+            .publish(ReceiverStream::new(outgoing))
+            .await
+            .map_err(|e| e.to_string())?
+            .into_inner();
+
+        log::info!("Opened relayer gRPC stream to {}", self.endpoint.uri());
+        Ok(Session {
+            requests,
+            acks,
+            next_sequence: 0,
+        })
+    }
+
+    /// Send one message and wait for the relayer to ack it
+    pub async fn publish(
+        &self,
+        kind: &str,
+        payload: &[u8],
+        signature: Option<&PayloadSignature>,
+    ) -> Result<(), GrpcError> {
+        let mut session = self.session.lock().await;
+        let active = match session.take() {
+            Some(active) => active,
+            None => self.connect().await.map_err(GrpcError::Unavailable)?,
+        };
+        let active = session.insert(active);
+
+        let signed = signature.cloned().unwrap_or_default();
+        let envelope = Envelope {
+            sequence: active.next_sequence,
+            kind: kind.to_string(),
+            payload: payload.to_vec(),
+            signature: signed.signature,
+            pubkey: signed.pubkey,
+            timestamp: signed.timestamp,
+        };
+        active.next_sequence += 1;
+
+        let result = exchange(active, envelope).await;
+        if matches!(result, Err(GrpcError::Unavailable(_))) {
+            *session = None;
+        }
+        result
+    }
+}
+
+async fn exchange(session: &mut Session, envelope: Envelope) -> Result<(), GrpcError> {
+    let sequence = envelope.sequence;
+    session
+        .requests
+        .send(envelope)
+        .await
+        .map_err(|_| GrpcError::Unavailable("stream closed".to_string()))?;
+
+    let wait_for_ack = async {
+        loop {
+            match session.acks.message().await {
+                Ok(Some(ack)) if ack.sequence == sequence => return Ok(ack),
+                // Late ack for a message that already timed out
+                Ok(Some(_)) => continue,
+                Ok(None) => return Err("stream ended".to_string()),
+                Err(status) => return Err(status.to_string()),
+            }
+        }
+    };
+
+    let ack = tokio::time::timeout(ACK_TIMEOUT, wait_for_ack)
+        .await
+        .map_err(|_| GrpcError::Unavailable(format!("no ack for message {}", sequence)))?
+        .map_err(GrpcError::Unavailable)?;
+
+    if ack.ok {
+        Ok(())
+    } else {
+        Err(GrpcError::Rejected(ack.error))
+    }
+}
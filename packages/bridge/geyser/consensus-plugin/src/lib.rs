@@ -4,12 +4,21 @@
 //!
 //! Data captured:
 //! - Bank hashes at each slot
-//! - Validator votes
-//! - Epoch stake snapshots
+//! - Validator votes (attached to snapshots once the slot is confirmed)
+//! - Epoch stake snapshots (vote-account stakes, posted at each epoch boundary)
 //! - Transaction confirmations for bridge transfers
 //!
 //! The plugin posts captured data to the relayer service which generates
-//! ZK proofs and submits them to EVM chains.
+//! ZK proofs and submits them to EVM chains. Edits to the config file are
+//! picked up without restarting the validator (see `reload`).
+
+pub mod bridge_parser;
+pub mod consensus;
+pub mod grpc;
+pub mod metrics;
+pub mod poster;
+pub mod reload;
+pub mod stakes;
 
 use solana_geyser_plugin_interface::geyser_plugin_interface::{
     GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions,
@@ -17,14 +26,22 @@ use solana_geyser_plugin_interface::geyser_plugin_interface::{
     SlotStatus,
 };
 use solana_sdk::{
-    clock::Slot,
+    clock::{Epoch, Slot},
     pubkey::Pubkey,
-    signature::Signature,
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde_with::serde_as;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use crate::consensus::ConsensusBuffer;
+use crate::metrics::Metrics;
+use crate::poster::{PosterStats, RelayerMessage, RelayerPoster};
+use crate::reload::{ActiveConfig, SharedConfig};
+
 /// Plugin configuration
 #[derive(Debug, Clone, Deserialize)]
 pub struct PluginConfig {
@@ -38,6 +55,110 @@ pub struct PluginConfig {
     pub snapshot_interval: u64,
     /// Whether to capture all transactions or only bridge-related
     pub capture_all_transactions: bool,
+    /// EVM chain the bridge sends to, reported as `dest_chain` on transfer events
+    #[serde(default = "default_evm_chain_id")]
+    pub evm_chain_id: u64,
+    /// File where undeliverable messages are spooled for replay; no spooling if unset
+    #[serde(default)]
+    pub spool_path: Option<String>,
+    /// Maximum number of spooled messages before new failures are dropped
+    #[serde(default = "default_max_spool_entries")]
+    pub max_spool_entries: usize,
+    /// File where messages the relayer refused (4xx other than 408 and 429)
+    /// are appended; they are only logged if unset
+    #[serde(default)]
+    pub dead_letter_path: Option<String>,
+    /// Retries per message (with exponential backoff) before spooling it
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Sent as `Authorization: Bearer <token>` on every relayer request
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// Permit a plain `http://` relayer endpoint (local development only)
+    #[serde(default)]
+    pub allow_insecure: bool,
+    /// Extra PEM CA certificate to trust for the relayer's TLS certificate
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// Solana keypair file used to sign every payload posted to the relayer
+    #[serde(default)]
+    pub signing_keypair_path: Option<String>,
+    /// Validator RPC queried for vote-account stakes at each epoch boundary
+    #[serde(default = "default_rpc_url")]
+    pub rpc_url: String,
+    /// Slots per epoch, used to detect epoch boundaries
+    #[serde(default = "default_slots_per_epoch")]
+    pub slots_per_epoch: u64,
+    /// How messages reach the relayer
+    #[serde(default)]
+    pub transport: Transport,
+    /// Relayer gRPC endpoint, required by the `grpc` transport
+    #[serde(default)]
+    pub grpc_endpoint: Option<String>,
+    /// Messages buffered for the background worker; when it is full new
+    /// messages are dropped (see `queue_full_timeout_ms`). A larger queue rides out longer
+    /// relayer stalls at the cost of memory (each message holds a snapshot's
+    /// votes or a transfer); a smaller one starts dropping messages sooner.
+    /// Read once at load.
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+    /// How long a callback waits for room in the full queue before the message
+    /// is dropped and counted. Longer waits lose fewer messages but hold up
+    /// the validator's notification thread; zero, the default, drops
+    /// immediately.
+    #[serde(default = "default_queue_full_timeout_ms")]
+    pub queue_full_timeout_ms: u64,
+    /// Threads in the background runtime that posts to the relayer. Read once
+    /// at load.
+    #[serde(default = "default_worker_threads")]
+    pub worker_threads: usize,
+    /// Port serving Prometheus metrics on `/metrics`; disabled if unset. Read
+    /// once at load, so a change needs a plugin reload.
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+}
+
+fn default_evm_chain_id() -> u64 {
+    420690
+}
+
+fn default_max_spool_entries() -> usize {
+    10_000
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_rpc_url() -> String {
+    "http://127.0.0.1:8899".to_string()
+}
+
+fn default_slots_per_epoch() -> u64 {
+    432_000
+}
+
+fn default_channel_capacity() -> usize {
+    10_000
+}
+
+fn default_queue_full_timeout_ms() -> u64 {
+    0
+}
+
+fn default_worker_threads() -> usize {
+    2
+}
+
+/// Transport used to deliver messages to the relayer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// One HTTP POST per message
+    #[default]
+    Http,
+    /// The relayer's ack'd gRPC stream, falling back to HTTP while it is down
+    Grpc,
 }
 
 impl Default for PluginConfig {
@@ -48,12 +169,29 @@ impl Default for PluginConfig {
             vote_program_id: "Vote111111111111111111111111111111111111111".to_string(),
             snapshot_interval: 32,
             capture_all_transactions: false,
+            evm_chain_id: default_evm_chain_id(),
+            spool_path: None,
+            max_spool_entries: default_max_spool_entries(),
+            dead_letter_path: None,
+            max_retries: default_max_retries(),
+            auth_token: None,
+            allow_insecure: false,
+            ca_cert_path: None,
+            signing_keypair_path: None,
+            rpc_url: default_rpc_url(),
+            slots_per_epoch: default_slots_per_epoch(),
+            transport: Transport::Http,
+            grpc_endpoint: None,
+            channel_capacity: default_channel_capacity(),
+            queue_full_timeout_ms: default_queue_full_timeout_ms(),
+            worker_threads: default_worker_threads(),
+            metrics_port: None,
         }
     }
 }
 
 /// Consensus data snapshot
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsensusSnapshot {
     pub slot: Slot,
     pub bank_hash: [u8; 32],
@@ -64,21 +202,42 @@ pub struct ConsensusSnapshot {
 }
 
 /// Validator vote
-#[derive(Debug, Clone, Serialize)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidatorVote {
     pub validator: [u8; 32],
     pub vote_account: [u8; 32],
     pub slot: Slot,
     pub hash: [u8; 32],
+    #[serde_as(as = "[_; 64]")]
     pub signature: [u8; 64],
     pub timestamp: i64,
 }
 
+/// Stake of one validator identity in an epoch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorStake {
+    pub pubkey: [u8; 32],
+    pub stake: u64,
+}
+
+/// Vote-account stakes for an epoch, committed to by `stakes_root`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochStakeSnapshot {
+    pub epoch: Epoch,
+    pub total_stake: u64,
+    pub stakes_root: [u8; 32],
+    /// Leaves of `stakes_root`, sorted by pubkey, for building stake proofs
+    pub stakes: Vec<ValidatorStake>,
+}
+
 /// Bridge transfer event
-#[derive(Debug, Clone, Serialize)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BridgeTransferEvent {
     pub transfer_id: [u8; 32],
     pub slot: Slot,
+    #[serde_as(as = "[_; 64]")]
     pub signature: [u8; 64],
     pub sender: [u8; 32],
     pub recipient: [u8; 32],
@@ -88,84 +247,215 @@ pub struct BridgeTransferEvent {
 
 /// Main plugin struct
 pub struct EVMSolGeyserPlugin {
-    config: PluginConfig,
+    config: SharedConfig,
     runtime: Option<tokio::runtime::Runtime>,
-    tx: Option<mpsc::UnboundedSender<PluginMessage>>,
-    last_snapshot_slot: Slot,
-    bridge_program_id: Pubkey,
-    vote_program_id: Pubkey,
+    tx: Option<mpsc::Sender<PluginMessage>>,
+    last_snapshot_slot: Arc<AtomicU64>,
+    /// Last epoch whose stakes were requested, `u64::MAX` before the first
+    last_stake_epoch: Arc<AtomicU64>,
+    consensus: Arc<ConsensusBuffer>,
+    metrics: Arc<Metrics>,
 }
 
-enum PluginMessage {
+pub(crate) enum PluginMessage {
     ConsensusSnapshot(ConsensusSnapshot),
     BridgeTransfer(BridgeTransferEvent),
-    SlotUpdate { slot: Slot, status: String },
+    EpochBoundary { epoch: Epoch },
+    /// The config file changed; deliver through `poster` from now on
+    Reconfigure { poster: Box<RelayerPoster>, rpc_url: String },
 }
 
 impl EVMSolGeyserPlugin {
     fn new() -> Self {
         Self {
-            config: PluginConfig::default(),
+            config: Arc::new(RwLock::new(Arc::new(ActiveConfig {
+                config: PluginConfig::default(),
+                bridge_program_id: Pubkey::default(),
+                vote_program_id: Pubkey::default(),
+            }))),
             runtime: None,
             tx: None,
-            last_snapshot_slot: 0,
-            bridge_program_id: Pubkey::default(),
-            vote_program_id: Pubkey::default(),
+            last_snapshot_slot: Arc::new(AtomicU64::new(0)),
+            last_stake_epoch: Arc::new(AtomicU64::new(u64::MAX)),
+            consensus: Arc::new(ConsensusBuffer::default()),
+            metrics: Arc::new(Metrics::new(Arc::new(PosterStats::default()))),
         }
     }
 
-    fn start_background_worker(&mut self) {
-        let (tx, mut rx) = mpsc::unbounded_channel::<PluginMessage>();
+    /// The active config; callers hold one snapshot for a whole callback
+    fn config(&self) -> Arc<ActiveConfig> {
+        self.config.read().unwrap().clone()
+    }
+
+    fn start_background_worker(&mut self, poster: RelayerPoster, config_path: PathBuf) {
+        let config = self.config();
+        let (tx, mut rx) = mpsc::channel::<PluginMessage>(config.config.channel_capacity);
+        let watcher_tx = tx.downgrade();
         self.tx = Some(tx);
 
-        let relayer_endpoint = self.config.relayer_endpoint.clone();
+        let mut poster = poster;
+        let mut rpc_url = config.config.rpc_url.clone();
+        let metrics = self.metrics.clone();
 
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
-            .worker_threads(2)
+            .worker_threads(config.config.worker_threads)
             .build()
             .expect("Failed to create tokio runtime");
 
         runtime.spawn(async move {
-            let client = reqwest::Client::new();
-
-            while let Some(msg) = rx.recv().await {
-                match msg {
-                    PluginMessage::ConsensusSnapshot(snapshot) => {
-                        let url = format!("{}/consensus", relayer_endpoint);
-                        if let Err(e) = client.post(&url).json(&snapshot).send().await {
-                            log::error!("Failed to post consensus snapshot: {}", e);
-                        } else {
-                            log::info!("Posted consensus snapshot for slot {}", snapshot.slot);
+            let mut replay_delay = poster::REPLAY_BACKOFF;
+            let replay = tokio::time::sleep(replay_delay);
+            tokio::pin!(replay);
+
+            loop {
+                tokio::select! {
+                    msg = rx.recv() => {
+                        let Some(msg) = msg else { break };
+                        // Reconfigure comes from the watcher, not `send_message`
+                        if !matches!(msg, PluginMessage::Reconfigure { .. }) {
+                            metrics.queue_depth.fetch_sub(1, Ordering::Relaxed);
                         }
-                    }
-                    PluginMessage::BridgeTransfer(transfer) => {
-                        let url = format!("{}/transfer", relayer_endpoint);
-                        if let Err(e) = client.post(&url).json(&transfer).send().await {
-                            log::error!("Failed to post bridge transfer: {}", e);
-                        } else {
-                            log::info!(
-                                "Posted bridge transfer {} for slot {}",
-                                bs58::encode(&transfer.transfer_id).into_string(),
-                                transfer.slot
-                            );
+                        match msg {
+                            PluginMessage::ConsensusSnapshot(snapshot) => {
+                                poster.deliver(RelayerMessage::Consensus(snapshot)).await;
+                            }
+                            PluginMessage::BridgeTransfer(transfer) => {
+                                poster.deliver(RelayerMessage::Transfer(transfer)).await;
+                            }
+                            PluginMessage::EpochBoundary { epoch } => {
+                                match stakes::fetch_epoch_stakes(&rpc_url, epoch).await {
+                                    Ok(snapshot) => {
+                                        log::info!(
+                                            "Epoch {} stakes: {} validators, {} total",
+                                            epoch,
+                                            snapshot.stakes.len(),
+                                            snapshot.total_stake
+                                        );
+                                        poster.deliver(RelayerMessage::EpochStakes(snapshot)).await;
+                                    }
+                                    Err(e) => {
+                                        log::error!("Failed to collect epoch {} stakes: {}", epoch, e);
+                                    }
+                                }
+                            }
+                            PluginMessage::Reconfigure { poster: new_poster, rpc_url: new_rpc_url } => {
+                                poster = *new_poster;
+                                rpc_url = new_rpc_url;
+                            }
                         }
                     }
-                    PluginMessage::SlotUpdate { slot, status } => {
-                        log::debug!("Slot {} status: {}", slot, status);
+                    _ = &mut replay => {
+                        let flushed = poster.replay_spool().await;
+                        replay_delay = poster::next_replay_delay(replay_delay, flushed);
+                        replay.as_mut().reset(tokio::time::Instant::now() + replay_delay);
                     }
                 }
             }
         });
 
+        runtime.spawn(reload::watch(
+            config_path,
+            self.config.clone(),
+            watcher_tx,
+            self.metrics.poster.clone(),
+        ));
+
+        if let Some(port) = self.config().config.metrics_port {
+            runtime.spawn(metrics::serve(port, self.metrics.clone()));
+        }
+
         self.runtime = Some(runtime);
     }
 
+    /// Claim the right to emit a snapshot for `slot`.
+    ///
+    /// Returns true at most once per `snapshot_interval` slots. The CAS makes this safe
+    /// when validator threads deliver block metadata concurrently.
+    fn try_claim_snapshot(&self, slot: Slot) -> bool {
+        let mut last = self.last_snapshot_slot.load(Ordering::Acquire);
+        loop {
+            if slot.saturating_sub(last) < self.config().config.snapshot_interval {
+                return false;
+            }
+            match self.last_snapshot_slot.compare_exchange_weak(
+                last,
+                slot,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(current) => last = current,
+            }
+        }
+    }
+
+    /// Claim the right to snapshot stakes for `epoch`; true once per new epoch,
+    /// including the first epoch seen after loading.
+    fn try_claim_epoch(&self, epoch: Epoch) -> bool {
+        let mut last = self.last_stake_epoch.load(Ordering::Acquire);
+        loop {
+            if last != u64::MAX && epoch <= last {
+                return false;
+            }
+            match self.last_stake_epoch.compare_exchange_weak(
+                last,
+                epoch,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(current) => last = current,
+            }
+        }
+    }
+
+    /// Number of undelivered messages waiting in the spool
+    pub fn pending_spooled(&self) -> u64 {
+        self.metrics.poster.pending.load(Ordering::Relaxed)
+    }
+
+    /// Queue `msg` for the worker. A full queue means the relayer is behind; the
+    /// message is dropped and counted once `queue_full_timeout_ms` passes, which
+    /// by default is straight away so the validator thread is never held up.
+    /// Callbacks may run inside the validator's own runtime, so this polls
+    /// rather than using `blocking_send`.
     fn send_message(&self, msg: PluginMessage) {
-        if let Some(tx) = &self.tx {
-            if let Err(e) = tx.send(msg) {
-                log::error!("Failed to send plugin message: {}", e);
+        let Some(tx) = &self.tx else {
+            return;
+        };
+
+        let mut msg = msg;
+        let mut deadline = None;
+        loop {
+            // Counted before sending so the worker never decrements first
+            self.metrics.queue_depth.fetch_add(1, Ordering::Relaxed);
+            let returned = match tx.try_send(msg) {
+                Ok(()) => return,
+                Err(e) => {
+                    self.metrics.queue_depth.fetch_sub(1, Ordering::Relaxed);
+                    match e {
+                        mpsc::error::TrySendError::Full(returned) => returned,
+                        mpsc::error::TrySendError::Closed(_) => {
+                            log::error!("Failed to send plugin message: worker stopped");
+                            return;
+                        }
+                    }
+                }
+            };
+
+            let give_up_at = *deadline.get_or_insert_with(|| {
+                self.metrics.queue_full_waits.fetch_add(1, Ordering::Relaxed);
+                let timeout = self.config().config.queue_full_timeout_ms;
+                Instant::now() + Duration::from_millis(timeout)
+            });
+            if Instant::now() >= give_up_at {
+                self.metrics.queue_dropped.fetch_add(1, Ordering::Relaxed);
+                log::error!("Worker queue full, dropping plugin message");
+                return;
             }
+            msg = returned;
+            std::thread::sleep(Duration::from_millis(1));
         }
     }
 }
@@ -173,7 +463,7 @@ impl EVMSolGeyserPlugin {
 impl std::fmt::Debug for EVMSolGeyserPlugin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("EVMSolGeyserPlugin")
-            .field("config", &self.config)
+            .field("config", &self.config().config)
             .finish()
     }
 }
@@ -183,34 +473,24 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
         "evmsol-geyser-plugin"
     }
 
-    fn on_load(&mut self, config_file: &str, _is_reload: bool) -> PluginResult<()> {
-        env_logger::init();
-        log::info!("Loading EVMSol Geyser plugin from config: {}", config_file);
-
-        // Load configuration
-        let config_content = std::fs::read_to_string(config_file)
-            .map_err(|e| GeyserPluginError::ConfigFileReadError {
-                msg: e.to_string(),
-            })?;
+    /// Load and validate the whole config before starting. A reloaded plugin
+    /// (`is_reload`) runs in the same process as its predecessor, which already
+    /// installed the logger.
+    fn on_load(&mut self, config_file: &str, is_reload: bool) -> PluginResult<()> {
+        let _ = env_logger::try_init();
+        log::info!(
+            "{} EVMSol Geyser plugin from config: {}",
+            if is_reload { "Reloading" } else { "Loading" },
+            config_file
+        );
 
-        self.config = serde_json::from_str(&config_content)
-            .map_err(|e| GeyserPluginError::ConfigFileReadError {
-                msg: e.to_string(),
-            })?;
-
-        // Parse program IDs
-        self.bridge_program_id = self.config.bridge_program_id.parse()
-            .map_err(|e| GeyserPluginError::ConfigFileReadError {
-                msg: format!("Invalid bridge program ID: {}", e),
-            })?;
-
-        self.vote_program_id = self.config.vote_program_id.parse()
-            .map_err(|e| GeyserPluginError::ConfigFileReadError {
-                msg: format!("Invalid vote program ID: {}", e),
-            })?;
+        let config_path = PathBuf::from(config_file);
+        let (active, poster) = reload::load(&config_path, self.metrics.poster.clone())
+            .map_err(|msg| GeyserPluginError::ConfigFileReadError { msg })?;
+        *self.config.write().unwrap() = Arc::new(active);
 
         // Start background worker
-        self.start_background_worker();
+        self.start_background_worker(poster, config_path);
 
         log::info!("EVMSol Geyser plugin loaded successfully");
         Ok(())
@@ -238,7 +518,7 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
             _ => return Ok(()),
         };
 
-        if account_info.owner == self.vote_program_id.as_ref() {
+        if account_info.owner == self.config().vote_program_id.as_ref() {
             // This is a vote account - extract vote data
             log::debug!("Vote account update at slot {}", slot);
         }
@@ -263,10 +543,19 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
             SlotStatus::Confirmed => "confirmed",
         };
 
-        self.send_message(PluginMessage::SlotUpdate {
-            slot,
-            status: status_str.to_string(),
-        });
+        // Logged here rather than queued so it never competes with relayer messages
+        log::debug!("Slot {} status: {}", slot, status_str);
+
+        // Votes for a slot arrive after its block; once it is confirmed a supermajority
+        // has voted, so the pending snapshot can go out with those votes attached.
+        if matches!(status, SlotStatus::Confirmed | SlotStatus::Rooted) {
+            if let Some(snapshot) = self.consensus.take_confirmed(slot) {
+                log::debug!("Snapshot for slot {} has {} votes", slot, snapshot.votes.len());
+                self.metrics.snapshots_emitted.fetch_add(1, Ordering::Relaxed);
+                self.metrics.last_snapshot_slot.store(snapshot.slot, Ordering::Relaxed);
+                self.send_message(PluginMessage::ConsensusSnapshot(snapshot));
+            }
+        }
 
         Ok(())
     }
@@ -280,6 +569,22 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
             ReplicaTransactionInfoVersions::V0_0_2(info) => info,
             _ => return Ok(()),
         };
+        let config = self.config();
+
+        self.consensus.record_signature(slot, tx_info.signature);
+
+        if tx_info.is_vote {
+            let message = tx_info.transaction.message();
+            let account_keys: Vec<Pubkey> = message.account_keys().iter().copied().collect();
+            let votes = consensus::parse_votes(
+                &config.vote_program_id,
+                &account_keys,
+                message.instructions(),
+                tx_info.signature,
+            );
+            self.consensus.record_votes(votes);
+            return Ok(());
+        }
 
         // Check if transaction involves the bridge program
         let involves_bridge = tx_info
@@ -287,24 +592,33 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
             .message()
             .account_keys()
             .iter()
-            .any(|key| key == &self.bridge_program_id);
+            .any(|key| key == &config.bridge_program_id);
 
         if involves_bridge {
             log::info!("Bridge transaction detected at slot {}", slot);
 
-            // Parse transfer event from transaction
-            // In production, decode the instruction data properly
-            let transfer = BridgeTransferEvent {
-                transfer_id: [0u8; 32], // Would parse from instruction
+            let message = tx_info.transaction.message();
+            let account_keys: Vec<Pubkey> = message.account_keys().iter().copied().collect();
+            let logs = tx_info
+                .transaction_status_meta
+                .log_messages
+                .as_deref()
+                .unwrap_or_default();
+
+            let transfers = bridge_parser::parse_bridge_transfers(
+                &config.bridge_program_id,
+                &account_keys,
+                message.instructions(),
+                logs,
+                tx_info.signature,
                 slot,
-                signature: [0u8; 64],
-                sender: [0u8; 32],
-                recipient: [0u8; 32],
-                amount: 0,
-                dest_chain: 0,
-            };
+                config.config.evm_chain_id,
+            );
 
-            self.send_message(PluginMessage::BridgeTransfer(transfer));
+            for transfer in transfers {
+                self.metrics.transfers_detected.fetch_add(1, Ordering::Relaxed);
+                self.send_message(PluginMessage::BridgeTransfer(transfer));
+            }
         }
 
         Ok(())
@@ -319,18 +633,26 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
             _ => return Ok(()),
         };
 
+        // All of the slot's transactions have been notified by now
+        let transactions_root = self.consensus.take_transactions_root(block_info.slot);
+
         // Check if we should create a consensus snapshot
-        if block_info.slot - self.last_snapshot_slot >= self.config.snapshot_interval {
+        if self.try_claim_snapshot(block_info.slot) {
             let snapshot = ConsensusSnapshot {
                 slot: block_info.slot,
-                bank_hash: *block_info.blockhash.as_ref(),
-                parent_hash: *block_info.parent_blockhash.as_ref(),
+                bank_hash: decode_hash(block_info.blockhash),
+                parent_hash: decode_hash(block_info.parent_blockhash),
                 block_time: block_info.block_time,
-                votes: vec![], // Would collect from vote accounts
-                transactions_root: [0u8; 32], // Would compute from transactions
+                votes: vec![], // Attached once the slot is confirmed
+                transactions_root,
             };
 
-            self.send_message(PluginMessage::ConsensusSnapshot(snapshot));
+            self.consensus.add_pending(snapshot);
+        }
+
+        let epoch = block_info.slot / self.config().config.slots_per_epoch;
+        if self.try_claim_epoch(epoch) {
+            self.send_message(PluginMessage::EpochBoundary { epoch });
         }
 
         Ok(())
@@ -345,6 +667,16 @@ impl GeyserPlugin for EVMSolGeyserPlugin {
     }
 }
 
+/// Decode a base58 block hash as reported by the validator
+fn decode_hash(encoded: &str) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    match bs58::decode(encoded).into_vec() {
+        Ok(bytes) if bytes.len() == 32 => out.copy_from_slice(&bytes),
+        _ => log::warn!("Invalid block hash: {}", encoded),
+    }
+    out
+}
+
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
 pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
@@ -356,6 +688,7 @@ pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::poster::Spool;
 
     #[test]
     fn test_plugin_creation() {
@@ -375,5 +708,717 @@ mod tests {
 
         let config: PluginConfig = serde_json::from_str(config_json).unwrap();
         assert_eq!(config.snapshot_interval, 64);
+        assert_eq!(config.evm_chain_id, 420690);
+    }
+
+    #[test]
+    fn test_reload_validates_whole_config() {
+        let valid = r#"{
+            "relayer_endpoint": "https://relayer.jejunetwork.org",
+            "bridge_program_id": "TokenBridge11111111111111111111111111111111",
+            "vote_program_id": "Vote111111111111111111111111111111111111111",
+            "snapshot_interval": 16,
+            "capture_all_transactions": false
+        }"#;
+
+        let active = reload::ActiveConfig::parse(valid).unwrap();
+        assert_eq!(active.config.snapshot_interval, 16);
+        assert_eq!(active.vote_program_id, solana_sdk::vote::program::id());
+
+        // Any invalid field rejects the whole file
+        let broken = [
+            valid.replace("Vote111111111111111111111111111111111111111", "not-a-pubkey"),
+            valid.replace("https://", "http://"),
+            valid.replace("\"snapshot_interval\": 16", "\"snapshot_interval\": -1"),
+            valid.replace("false\n", "false, \"slots_per_epoch\": 0\n"),
+            valid.replace("false\n", "false, \"transport\": \"grpc\"\n"),
+            valid.replace("false\n", "false, \"transport\": \"quic\"\n"),
+            valid.replace("false\n", "false, \"worker_threads\": 0\n"),
+        ];
+        for content in &broken {
+            assert!(reload::ActiveConfig::parse(content).is_err(), "accepted {}", content);
+        }
+
+        let grpc = valid.replace(
+            "false\n",
+            "false, \"transport\": \"grpc\", \"grpc_endpoint\": \"https://relayer.jejunetwork.org:50051\"\n",
+        );
+        let active_grpc = reload::ActiveConfig::parse(&grpc).unwrap();
+        assert_eq!(active_grpc.config.transport, Transport::Grpc);
+        assert_eq!(active.config.transport, Transport::Http);
+
+        // So does relayer material that cannot be loaded
+        let mut config = active.config.clone();
+        config.signing_keypair_path = Some("/nonexistent/keypair.json".to_string());
+        assert!(reload::build_poster(&config, Arc::new(PosterStats::default())).is_err());
+    }
+
+    #[test]
+    fn test_reload_swaps_active_config() {
+        let plugin = EVMSolGeyserPlugin::new();
+        let before = plugin.config();
+
+        let mut config = PluginConfig::default();
+        config.snapshot_interval = 8;
+        config.allow_insecure = true;
+        *plugin.config.write().unwrap() = Arc::new(reload::ActiveConfig::validate(config).unwrap());
+
+        // A callback that took a snapshot keeps a consistent view
+        assert_eq!(before.config.snapshot_interval, 32);
+        assert_eq!(plugin.config().config.snapshot_interval, 8);
+        assert!(plugin.try_claim_snapshot(8));
+        assert!(plugin.try_claim_snapshot(16));
+    }
+
+    #[test]
+    fn test_metrics_render() {
+        let metrics = Metrics::new(Arc::new(PosterStats::default()));
+        metrics.snapshots_emitted.fetch_add(3, Ordering::Relaxed);
+        metrics.last_snapshot_slot.store(1_234, Ordering::Relaxed);
+        metrics.poster.post_retries.fetch_add(2, Ordering::Relaxed);
+
+        let body = metrics.render();
+        assert!(body.contains("# TYPE evmsol_geyser_snapshots_emitted_total counter\n"));
+        assert!(body.contains("\nevmsol_geyser_snapshots_emitted_total 3\n"));
+        assert!(body.contains("\nevmsol_geyser_post_retries_total 2\n"));
+        assert!(body.contains("\nevmsol_geyser_last_snapshot_slot 1234\n"));
+        assert!(body.contains("\nevmsol_geyser_queue_depth 0\n"));
+
+        assert_eq!(metrics::request_path("GET /metrics HTTP/1.1\r\nHost: x\r\n"), Some("/metrics"));
+        assert_eq!(metrics::request_path("GET /metrics?x=1 HTTP/1.1\r\n"), Some("/metrics"));
+        assert_eq!(metrics::request_path("POST /metrics HTTP/1.1\r\n"), None);
+        assert_eq!(metrics::request_path(""), None);
+    }
+
+    #[test]
+    fn test_flooded_queue_stays_bounded() {
+        let mut plugin = EVMSolGeyserPlugin::new();
+        let mut config = PluginConfig::default();
+        config.channel_capacity = 64;
+        config.allow_insecure = true;
+        *plugin.config.write().unwrap() = Arc::new(reload::ActiveConfig::validate(config).unwrap());
+
+        // A worker that never drains the queue
+        let (tx, _rx) = mpsc::channel::<PluginMessage>(64);
+        plugin.tx = Some(tx.clone());
+
+        // Dropped straight away by default instead of holding up the caller
+        for epoch in 0..10_000 {
+            plugin.send_message(PluginMessage::EpochBoundary { epoch });
+        }
+
+        assert_eq!(tx.capacity(), 0);
+        assert_eq!(plugin.metrics.queue_depth.load(Ordering::Relaxed), 64);
+        assert_eq!(plugin.metrics.queue_dropped.load(Ordering::Relaxed), 10_000 - 64);
+        assert_eq!(plugin.metrics.queue_full_waits.load(Ordering::Relaxed), 10_000 - 64);
+    }
+
+    #[test]
+    fn test_snapshot_emitted_once_per_interval() {
+        let plugin = EVMSolGeyserPlugin::new();
+        assert_eq!(plugin.config().config.snapshot_interval, 32);
+
+        let emitted = [100, 110].iter().filter(|slot| plugin.try_claim_snapshot(**slot)).count();
+        assert_eq!(emitted, 1);
+        assert_eq!(plugin.last_snapshot_slot.load(Ordering::Acquire), 100);
+
+        assert!(!plugin.try_claim_snapshot(131));
+        assert!(plugin.try_claim_snapshot(132));
+    }
+
+    #[test]
+    fn test_votes_attached_to_confirmed_snapshot() {
+        use solana_sdk::{
+            hash::Hash,
+            instruction::CompiledInstruction,
+            signature::Signature,
+            vote::{instruction::vote, state::Vote},
+        };
+
+        let vote_program_id = solana_sdk::vote::program::id();
+        let validator = Pubkey::new_unique();
+        let vote_account = Pubkey::new_unique();
+        let bank_hash = Hash::new_unique();
+
+        let ix = vote(&vote_account, &validator, Vote::new(vec![100], bank_hash));
+        let account_keys = vec![validator, vote_account, vote_program_id];
+        let compiled = CompiledInstruction {
+            program_id_index: 2,
+            accounts: vec![1],
+            data: ix.data,
+        };
+
+        let votes = consensus::parse_votes(
+            &vote_program_id,
+            &account_keys,
+            &[compiled],
+            &Signature::from([3u8; 64]),
+        );
+        assert_eq!(votes.len(), 1);
+        assert_eq!(votes[0].slot, 100);
+        assert_eq!(votes[0].hash, bank_hash.to_bytes());
+        assert_eq!(votes[0].validator, validator.to_bytes());
+        assert_eq!(votes[0].vote_account, vote_account.to_bytes());
+
+        let buffer = ConsensusBuffer::default();
+        // Duplicate votes from the same validator are only counted once
+        buffer.record_votes(votes.clone());
+        buffer.record_votes(votes);
+        buffer.add_pending(ConsensusSnapshot {
+            slot: 100,
+            bank_hash: bank_hash.to_bytes(),
+            parent_hash: [0u8; 32],
+            block_time: None,
+            votes: vec![],
+            transactions_root: [0u8; 32],
+        });
+
+        assert!(buffer.take_confirmed(99).is_none());
+        let snapshot = buffer.take_confirmed(100).unwrap();
+        assert_eq!(snapshot.votes.len(), 1);
+        assert!(buffer.take_confirmed(100).is_none());
+    }
+
+    #[test]
+    fn test_stake_snapshot_claimed_once_per_epoch() {
+        let plugin = EVMSolGeyserPlugin::new();
+
+        // The first epoch seen after loading is snapshotted too
+        assert!(plugin.try_claim_epoch(0));
+        assert!(!plugin.try_claim_epoch(0));
+        assert!(plugin.try_claim_epoch(1));
+        assert!(!plugin.try_claim_epoch(0));
+        assert!(plugin.try_claim_epoch(3));
+    }
+
+    #[test]
+    fn test_stakes_root_matches_circuit_proof_format() {
+        use solana_sdk::hash::hashv;
+
+        // Mirrors `verify_stake_merkle_proof` in the consensus circuit
+        fn verify(pubkey: &[u8; 32], stake: u64, proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+            let mut current = hashv(&[&[0x00], pubkey.as_ref(), &stake.to_le_bytes()]).to_bytes();
+            for sibling in proof {
+                current = if current <= *sibling {
+                    hashv(&[&[0x01], current.as_ref(), sibling.as_ref()]).to_bytes()
+                } else {
+                    hashv(&[&[0x01], sibling.as_ref(), current.as_ref()]).to_bytes()
+                };
+            }
+            current == *root
+        }
+
+        let nodes: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let account = |node: &Pubkey, activated_stake| stakes::VoteAccountInfo {
+            node_pubkey: node.to_string(),
+            activated_stake,
+        };
+        let vote_accounts = stakes::VoteAccounts {
+            // nodes[0] runs two vote accounts; unstaked accounts are skipped
+            current: vec![account(&nodes[0], 400), account(&nodes[1], 250), account(&nodes[0], 100)],
+            delinquent: vec![account(&nodes[2], 50), account(&Pubkey::new_unique(), 0)],
+        };
+
+        let snapshot = stakes::build_snapshot(7, &vote_accounts).unwrap();
+        assert_eq!(snapshot.epoch, 7);
+        assert_eq!(snapshot.total_stake, 800);
+        assert_eq!(snapshot.stakes.len(), 3);
+        assert!(snapshot.stakes.windows(2).all(|w| w[0].pubkey < w[1].pubkey));
+        assert_eq!(snapshot.stakes_root, stakes::stakes_root(&snapshot.stakes));
+
+        for stake in &snapshot.stakes {
+            let proof = stakes::stake_proof(&snapshot.stakes, &stake.pubkey).unwrap();
+            assert!(verify(&stake.pubkey, stake.stake, &proof, &snapshot.stakes_root));
+            assert!(!verify(&stake.pubkey, stake.stake + 1, &proof, &snapshot.stakes_root));
+        }
+        assert!(stakes::stake_proof(&snapshot.stakes, &[9u8; 32]).is_none());
+        assert_eq!(stakes::stakes_root(&[]), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_transactions_root() {
+        assert_eq!(consensus::merkle_root(&[]), [0u8; 32]);
+
+        let sigs = [[1u8; 64], [2u8; 64], [3u8; 64]];
+        let root = consensus::merkle_root(&sigs);
+        assert_ne!(root, [0u8; 32]);
+        assert_eq!(root, consensus::merkle_root(&sigs));
+        assert_ne!(root, consensus::merkle_root(&[[1u8; 64], [3u8; 64], [2u8; 64]]));
+
+        // Leaves and nodes are tagged: a single signature's root is not its bare hash
+        let leaf = consensus::transaction_leaf(&sigs[0]);
+        assert_eq!(consensus::merkle_root(&sigs[..1]), leaf);
+        assert_ne!(leaf, solana_sdk::hash::hashv(&[sigs[0].as_ref()]).to_bytes());
+
+        // A "signature" made of two sibling leaves must not hash to their parent
+        let left = consensus::transaction_leaf(&sigs[0]);
+        let right = consensus::transaction_leaf(&sigs[1]);
+        let pair_root = consensus::merkle_root(&sigs[..2]);
+        let mut forged = [0u8; 64];
+        let (lo, hi) = if left <= right { (left, right) } else { (right, left) };
+        forged[..32].copy_from_slice(&lo);
+        forged[32..].copy_from_slice(&hi);
+        assert_ne!(consensus::transaction_leaf(&forged), pair_root);
+
+        let buffer = ConsensusBuffer::default();
+        for sig in sigs {
+            buffer.record_signature(7, &solana_sdk::signature::Signature::from(sig));
+        }
+        assert_eq!(buffer.take_transactions_root(7), root);
+        assert_eq!(buffer.take_transactions_root(7), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_spool_round_trip_is_bounded() {
+        let path = std::env::temp_dir().join(format!("evmsol-spool-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let stats = Arc::new(PosterStats::default());
+        let spool = Spool::new(Some(path.clone()), 2, stats.clone());
+        let transfer = |slot| {
+            RelayerMessage::Transfer(BridgeTransferEvent {
+                transfer_id: [1u8; 32],
+                slot,
+                signature: [2u8; 64],
+                sender: [3u8; 32],
+                recipient: [4u8; 32],
+                amount: 5,
+                dest_chain: 6,
+            })
+        };
+
+        spool.push(&transfer(1));
+        spool.push(&transfer(2));
+        spool.push(&transfer(3));
+        assert_eq!(stats.pending.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 1);
+
+        let loaded = spool.load();
+        assert_eq!(loaded.len(), 2);
+        assert!(matches!(&loaded[0], RelayerMessage::Transfer(t) if t.slot == 1 && t.signature == [2u8; 64]));
+
+        spool.rewrite(&loaded[1..]);
+        assert_eq!(stats.pending.load(Ordering::Relaxed), 1);
+
+        // A fresh spool picks up the persisted backlog
+        let reopened = Spool::new(Some(path.clone()), 2, Arc::new(PosterStats::default()));
+        assert!(!reopened.is_empty());
+        assert_eq!(reopened.load().len(), 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn transfer_message(slot: Slot) -> RelayerMessage {
+        RelayerMessage::Transfer(BridgeTransferEvent {
+            transfer_id: [1u8; 32],
+            slot,
+            signature: [2u8; 64],
+            sender: [3u8; 32],
+            recipient: [4u8; 32],
+            amount: 5,
+            dest_chain: 6,
+        })
+    }
+
+    /// A relayer answering successive requests with `statuses`, then 200.
+    /// Returns its endpoint and the bodies it received.
+    async fn mock_relayer(statuses: Vec<u16>) -> (String, Arc<std::sync::Mutex<Vec<Vec<u8>>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let bodies = received.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+
+                // Read the headers, then as much body as they announce
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body_start = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+                let content_length: usize = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|len| len.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while request.len() < body_start + content_length {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                bodies.lock().unwrap().push(request[body_start..].to_vec());
+
+                let status = statuses.next().unwrap_or(200);
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (endpoint, received)
+    }
+
+    #[tokio::test]
+    async fn test_refused_message_is_dead_lettered_and_delivery_moves_on() {
+        let dead_letter_path =
+            std::env::temp_dir().join(format!("evmsol-dead-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&dead_letter_path);
+
+        // 400 for the first message; retrying it would hit the 503
+        let (endpoint, received) = mock_relayer(vec![400, 503]).await;
+        let stats = Arc::new(PosterStats::default());
+        let poster = RelayerPoster::new(
+            reqwest::Client::new(),
+            endpoint,
+            3,
+            Spool::new(None, 10, stats.clone()),
+            poster::DeadLetters::new(Some(dead_letter_path.clone()), stats.clone()),
+            poster::RequestAuth::default(),
+        );
+
+        poster.deliver(transfer_message(1)).await;
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(stats.post_retries.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.dead_lettered.load(Ordering::Relaxed), 1);
+
+        // The next message is not held up behind the refused one; the 503 is retried
+        poster.deliver(transfer_message(2)).await;
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        let delivered: BridgeTransferEvent = serde_json::from_slice(&received[2]).unwrap();
+        assert_eq!(delivered.slot, 2);
+        assert_eq!(stats.post_retries.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.dropped.load(Ordering::Relaxed), 0);
+
+        let letters = poster::DeadLetters::new(Some(dead_letter_path.clone()), stats.clone()).load();
+        assert_eq!(letters.len(), 1);
+        assert!(matches!(&letters[0].message, RelayerMessage::Transfer(t) if t.slot == 1));
+        assert!(letters[0].reason.contains("400"));
+
+        let _ = std::fs::remove_file(&dead_letter_path);
+    }
+
+    #[tokio::test]
+    async fn test_spool_replay_skips_refused_messages() {
+        let spool_path =
+            std::env::temp_dir().join(format!("evmsol-replay-{}.jsonl", std::process::id()));
+        let dead_letter_path = spool_path.with_extension("dead.jsonl");
+        let _ = std::fs::remove_file(&spool_path);
+        let _ = std::fs::remove_file(&dead_letter_path);
+
+        let stats = Arc::new(PosterStats::default());
+        let spool = Spool::new(Some(spool_path.clone()), 10, stats.clone());
+        for slot in 1..=3 {
+            spool.push(&transfer_message(slot));
+        }
+
+        let (endpoint, received) = mock_relayer(vec![200, 422]).await;
+        let poster = RelayerPoster::new(
+            reqwest::Client::new(),
+            endpoint,
+            0,
+            spool,
+            poster::DeadLetters::new(Some(dead_letter_path.clone()), stats.clone()),
+            poster::RequestAuth::default(),
+        );
+
+        assert!(poster.replay_spool().await);
+        assert_eq!(received.lock().unwrap().len(), 3);
+        assert_eq!(stats.pending.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.dead_lettered.load(Ordering::Relaxed), 1);
+
+        let _ = std::fs::remove_file(&spool_path);
+        let _ = std::fs::remove_file(&dead_letter_path);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_queues_behind_a_spooled_backlog() {
+        use std::os::unix::fs::MetadataExt;
+
+        let spool_path =
+            std::env::temp_dir().join(format!("evmsol-backlog-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&spool_path);
+
+        let stats = Arc::new(PosterStats::default());
+        let spool = Spool::new(Some(spool_path.clone()), 10, stats.clone());
+        spool.push(&transfer_message(1));
+
+        let (endpoint, received) = mock_relayer(vec![503]).await;
+        let poster = RelayerPoster::new(
+            reqwest::Client::new(),
+            endpoint,
+            3,
+            spool,
+            poster::DeadLetters::new(None, stats.clone()),
+            poster::RequestAuth::default(),
+        );
+
+        // Appended behind the backlog without a request
+        poster.deliver(transfer_message(2)).await;
+        assert!(received.lock().unwrap().is_empty());
+        assert_eq!(stats.pending.load(Ordering::Relaxed), 2);
+
+        // A replay that delivers nothing leaves the file alone
+        let inode = std::fs::metadata(&spool_path).unwrap().ino();
+        assert!(!poster.replay_spool().await);
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(std::fs::metadata(&spool_path).unwrap().ino(), inode);
+
+        assert!(poster.replay_spool().await);
+        let received = received.lock().unwrap();
+        let slots: Vec<Slot> = received[1..]
+            .iter()
+            .map(|body| serde_json::from_slice::<BridgeTransferEvent>(body).unwrap().slot)
+            .collect();
+        assert_eq!(slots, vec![1, 2]);
+        assert_eq!(stats.pending.load(Ordering::Relaxed), 0);
+
+        let _ = std::fs::remove_file(&spool_path);
+    }
+
+    #[test]
+    fn test_spool_replay_backs_off_until_flushed() {
+        let mut delay = poster::REPLAY_BACKOFF;
+        assert_eq!(poster::next_replay_delay(delay, false), poster::REPLAY_BACKOFF * 2);
+        for _ in 0..10 {
+            delay = poster::next_replay_delay(delay, false);
+        }
+        assert_eq!(delay, poster::MAX_REPLAY_BACKOFF);
+        assert_eq!(poster::next_replay_delay(delay, true), poster::REPLAY_BACKOFF);
+    }
+
+    /// Relayer stream that nacks the first envelope and acks the rest
+    struct NackFirst;
+
+    #[tonic::async_trait]
+    impl grpc::proto::relayer_server::Relayer for NackFirst {
+        type PublishStream = tokio_stream::wrappers::ReceiverStream<Result<grpc::proto::Ack, tonic::Status>>;
+
+        async fn publish(
+            &self,
+            request: tonic::Request<tonic::Streaming<grpc::proto::Envelope>>,
+        ) -> Result<tonic::Response<Self::PublishStream>, tonic::Status> {
+            let mut envelopes = request.into_inner();
+            let (acks, stream) = mpsc::channel(1);
+            tokio::spawn(async move {
+                while let Ok(Some(envelope)) = envelopes.message().await {
+                    let ok = envelope.sequence > 0;
+                    let ack = grpc::proto::Ack {
+                        sequence: envelope.sequence,
+                        ok,
+                        error: if ok { String::new() } else { "invalid payload".to_string() },
+                    };
+                    if acks.send(Ok(ack)).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            Ok(tonic::Response::new(tokio_stream::wrappers::ReceiverStream::new(stream)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nacked_stream_message_is_dead_lettered() {
+        let dead_letter_path =
+            std::env::temp_dir().join(format!("evmsol-nack-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&dead_letter_path);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc_endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let incoming = tonic::transport::server::TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(grpc::proto::relayer_server::RelayerServer::new(NackFirst))
+                .serve_with_incoming(incoming),
+        );
+
+        // HTTP stays reachable so a fallback would show up here
+        let (endpoint, received) = mock_relayer(vec![]).await;
+        let stats = Arc::new(PosterStats::default());
+        let poster = RelayerPoster::new(
+            reqwest::Client::new(),
+            endpoint,
+            3,
+            Spool::new(None, 10, stats.clone()),
+            poster::DeadLetters::new(Some(dead_letter_path.clone()), stats.clone()),
+            poster::RequestAuth::default(),
+        )
+        .with_grpc(grpc::GrpcTransport::new(&grpc_endpoint, None, None).unwrap());
+
+        poster.deliver(transfer_message(1)).await;
+        poster.deliver(transfer_message(2)).await;
+
+        assert_eq!(stats.dead_lettered.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.post_retries.load(Ordering::Relaxed), 0);
+        assert!(received.lock().unwrap().is_empty());
+
+        let letters = poster::DeadLetters::new(Some(dead_letter_path.clone()), stats.clone()).load();
+        assert_eq!(letters.len(), 1);
+        assert!(matches!(&letters[0].message, RelayerMessage::Transfer(t) if t.slot == 1));
+        assert!(letters[0].reason.contains("invalid payload"));
+
+        let _ = std::fs::remove_file(&dead_letter_path);
+    }
+
+    #[test]
+    fn test_only_transient_statuses_are_retried() {
+        for status in [408, 429, 500, 502, 503] {
+            assert!(poster::is_retryable_status(status), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 413, 422] {
+            assert!(!poster::is_retryable_status(status), "{}", status);
+        }
+    }
+
+    #[test]
+    fn test_endpoint_requires_https() {
+        assert!(poster::validate_endpoint("https://relayer.jejunetwork.org", false).is_ok());
+        assert!(poster::validate_endpoint("http://127.0.0.1:8081", false).is_err());
+        assert!(poster::validate_endpoint("http://127.0.0.1:8081", true).is_ok());
+        assert!(poster::validate_endpoint("ftp://relayer", true).is_err());
+    }
+
+    #[test]
+    fn test_payload_signature_verifies() {
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let keypair = Keypair::new();
+        let body = br#"{"slot":1}"#;
+        let signed_at = 1_700_000_000;
+        let signed = poster::PayloadSignature {
+            signature: keypair
+                .sign_message(&poster::signing_message(signed_at, "consensus", body))
+                .to_string(),
+            pubkey: keypair.pubkey().to_string(),
+            timestamp: signed_at,
+        };
+
+        assert!(poster::verify_payload(&signed, "consensus", body, signed_at).is_ok());
+        assert!(poster::verify_payload(&signed, "consensus", body, signed_at + 30).is_ok());
+        // Replaying the same body against another endpoint must not verify
+        assert!(poster::verify_payload(&signed, "transfer", body, signed_at).is_err());
+        assert!(poster::verify_payload(&signed, "consensus", br#"{"slot":2}"#, signed_at).is_err());
+    }
+
+    #[test]
+    fn test_replayed_payload_is_rejected() {
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let keypair = Keypair::new();
+        let body = br#"{"slot":1}"#;
+        let signed_at = 1_700_000_000;
+        let captured = poster::PayloadSignature {
+            signature: keypair
+                .sign_message(&poster::signing_message(signed_at, "transfer", body))
+                .to_string(),
+            pubkey: keypair.pubkey().to_string(),
+            timestamp: signed_at,
+        };
+
+        // The captured request, resent once it is outside the skew window
+        let later = signed_at + poster::MAX_CLOCK_SKEW_SECS + 1;
+        assert!(poster::verify_payload(&captured, "transfer", body, later).is_err());
+
+        // Moving the timestamp forward breaks the signature
+        let restamped = poster::PayloadSignature { timestamp: later, ..captured.clone() };
+        assert!(poster::verify_payload(&restamped, "transfer", body, later).is_err());
+
+        // A timestamp from the future is refused too
+        let early = signed_at - poster::MAX_CLOCK_SKEW_SECS - 1;
+        assert!(poster::verify_payload(&captured, "transfer", body, early).is_err());
+    }
+
+    #[test]
+    fn test_parse_initiate_transfer_instruction() {
+        use base64::Engine;
+        use solana_sdk::{instruction::CompiledInstruction, signature::Signature};
+
+        let bridge_program_id = Pubkey::new_unique();
+        let sender = Pubkey::new_unique();
+        let evm_recipient = [0xabu8; 20];
+        let amount = 1_500_000u64;
+        let transfer_id = [7u8; 32];
+
+        // initiate_transfer(evm_recipient, amount, payload)
+        let mut data = bridge_parser::initiate_transfer_discriminator().to_vec();
+        data.extend_from_slice(&evm_recipient);
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&[1, 2, 3]);
+
+        // account_keys: [sender, 7 other accounts..., bridge program]
+        let mut account_keys = vec![sender];
+        account_keys.extend((0..7).map(|_| Pubkey::new_unique()));
+        account_keys.push(bridge_program_id);
+
+        let ix = CompiledInstruction {
+            program_id_index: 8,
+            accounts: vec![1, 2, 3, 4, 5, 6, 0, 7],
+            data,
+        };
+        // An unrelated bridge instruction must be skipped
+        let other_ix = CompiledInstruction {
+            program_id_index: 8,
+            accounts: vec![],
+            data: vec![0u8; 16],
+        };
+
+        let mut event = bridge_parser::transfer_initiated_event_discriminator().to_vec();
+        event.extend_from_slice(&transfer_id);
+        event.extend_from_slice(sender.as_ref());
+        let logs = vec![
+            "Program log: Instruction: InitiateTransfer".to_string(),
+            format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(&event)
+            ),
+        ];
+
+        let signature = Signature::from([9u8; 64]);
+        let transfers = bridge_parser::parse_bridge_transfers(
+            &bridge_program_id,
+            &account_keys,
+            &[other_ix, ix],
+            &logs,
+            &signature,
+            42,
+            420691,
+        );
+
+        assert_eq!(transfers.len(), 1);
+        let transfer = &transfers[0];
+        assert_eq!(transfer.transfer_id, transfer_id);
+        assert_eq!(transfer.slot, 42);
+        assert_eq!(transfer.signature, [9u8; 64]);
+        assert_eq!(transfer.sender, sender.to_bytes());
+        assert_eq!(&transfer.recipient[12..], &evm_recipient);
+        assert_eq!(&transfer.recipient[..12], &[0u8; 12]);
+        assert_eq!(transfer.amount, amount);
+        assert_eq!(transfer.dest_chain, 420691);
+    }
+
+    #[test]
+    fn test_parse_rejects_other_instructions() {
+        assert!(bridge_parser::parse_initiate_transfer(&[0u8; 40]).is_none());
+        assert!(bridge_parser::parse_initiate_transfer(&[]).is_none());
     }
 }
@@ -0,0 +1,199 @@
+//! Prometheus metrics.
+//!
+//! Counters are plain atomics bumped from the validator's callback threads and
+//! the background worker. When `metrics_port` is set, a minimal HTTP listener
+//! serves them in the Prometheus text format on `GET /metrics`; it answers one
+//! request per connection and needs no HTTP framework.
+
+use std::fmt::Write as _;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::poster::PosterStats;
+
+const METRIC_PREFIX: &str = "evmsol_geyser_";
+
+/// Slow or idle scrapers are disconnected after this long
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Plugin-wide metrics; delivery counters live in the shared `PosterStats`
+#[derive(Debug)]
+pub struct Metrics {
+    /// Consensus snapshots handed to the worker
+    pub snapshots_emitted: AtomicU64,
+    /// Bridge transfers parsed from transactions
+    pub transfers_detected: AtomicU64,
+    /// Messages queued for the worker but not yet processed
+    pub queue_depth: AtomicU64,
+    /// Callbacks that found the worker queue full and had to wait
+    pub queue_full_waits: AtomicU64,
+    /// Messages dropped because the worker queue stayed full
+    pub queue_dropped: AtomicU64,
+    /// Slot of the most recent consensus snapshot
+    pub last_snapshot_slot: AtomicU64,
+    pub poster: Arc<PosterStats>,
+}
+
+impl Metrics {
+    pub fn new(poster: Arc<PosterStats>) -> Self {
+        Self {
+            snapshots_emitted: AtomicU64::new(0),
+            transfers_detected: AtomicU64::new(0),
+            queue_depth: AtomicU64::new(0),
+            queue_full_waits: AtomicU64::new(0),
+            queue_dropped: AtomicU64::new(0),
+            last_snapshot_slot: AtomicU64::new(0),
+            poster,
+        }
+    }
+
+    /// Current values in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics: [(&str, &str, &str, &AtomicU64); 11] = [
+            (
+                "snapshots_emitted_total",
+                "counter",
+                "Consensus snapshots emitted",
+                &self.snapshots_emitted,
+            ),
+            (
+                "transfers_detected_total",
+                "counter",
+                "Bridge transfers detected",
+                &self.transfers_detected,
+            ),
+            (
+                "post_failures_total",
+                "counter",
+                "Failed relayer post attempts",
+                &self.poster.post_failures,
+            ),
+            (
+                "post_retries_total",
+                "counter",
+                "Relayer post attempts retried after a failure",
+                &self.poster.post_retries,
+            ),
+            (
+                "dead_lettered_total",
+                "counter",
+                "Messages the relayer refused, dead-lettered instead of retried",
+                &self.poster.dead_lettered,
+            ),
+            (
+                "spool_dropped_total",
+                "counter",
+                "Messages dropped because they could not be spooled",
+                &self.poster.dropped,
+            ),
+            (
+                "queue_full_waits_total",
+                "counter",
+                "Callbacks that waited for room in the worker queue",
+                &self.queue_full_waits,
+            ),
+            (
+                "queue_dropped_total",
+                "counter",
+                "Messages dropped because the worker queue stayed full",
+                &self.queue_dropped,
+            ),
+            (
+                "queue_depth",
+                "gauge",
+                "Messages waiting for the background worker",
+                &self.queue_depth,
+            ),
+            (
+                "spool_pending",
+                "gauge",
+                "Undelivered messages waiting in the spool",
+                &self.poster.pending,
+            ),
+            (
+                "last_snapshot_slot",
+                "gauge",
+                "Slot of the most recent consensus snapshot",
+                &self.last_snapshot_slot,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {}{} {}", METRIC_PREFIX, name, help);
+            let _ = writeln!(out, "# TYPE {}{} {}", METRIC_PREFIX, name, kind);
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                METRIC_PREFIX,
+                name,
+                value.load(Ordering::Relaxed)
+            );
+        }
+        out
+    }
+}
+
+/// Path of an HTTP `GET` request line, without its query string
+pub fn request_path(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    match (parts.next()?, parts.next()?) {
+        ("GET", target) => target.split('?').next(),
+        _ => None,
+    }
+}
+
+/// Serve `metrics` on `port` until the runtime shuts down
+pub async fn serve(port: u16, metrics: Arc<Metrics>) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to bind metrics port {}: {}", port, e);
+            return;
+        }
+    };
+    log::info!("Serving metrics on port {}", port);
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                log::warn!("Failed to accept metrics connection: {}", e);
+                continue;
+            }
+        };
+
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, &metrics).await {
+                log::debug!("Metrics request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+    let request = String::from_utf8_lossy(&buf[..n]);
+
+    let (status, body) = match request_path(&request) {
+        Some("/metrics") => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,585 @@
+//! Delivery of captured data to the relayer.
+//!
+//! Each message is retried with exponential backoff. Messages that still fail are
+//! appended to an on-disk spool (one JSON record per line). While anything is
+//! spooled, new messages are appended behind it to keep their order, and the
+//! worker replays the spool oldest first on a timer that backs off for as long
+//! as the relayer stays down. Only
+//! transport errors, 5xx, 408 and 429 are retried: any other 4xx means the
+//! relayer refused the message itself, so it is dead-lettered and delivery
+//! moves on rather than blocking everything queued behind it.
+//!
+//! Requests carry an optional `Authorization: Bearer` token and, when a signing
+//! key is configured, an ed25519 signature over `<timestamp>:<path>:<body>` so the
+//! relayer can reject payloads that did not come from this validator. The
+//! timestamp (unix seconds, sent alongside) lets it also reject a captured
+//! request replayed later; see `verify_payload`.
+//!
+//! With the gRPC transport configured, messages go over the relayer's ack'd
+//! stream instead (see `grpc`), and plain HTTP is used only while the stream
+//! cannot be opened. A nack is a refusal like a 4xx and is dead-lettered.
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{Keypair, Signer};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::grpc::{GrpcError, GrpcTransport};
+use crate::{BridgeTransferEvent, ConsensusSnapshot, EpochStakeSnapshot};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Wait before replaying the spool after a replay that flushed it
+pub const REPLAY_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait between spool replays while the relayer is down
+pub const MAX_REPLAY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Wait before the next spool replay: back to `REPLAY_BACKOFF` once the spool
+/// is flushed, doubled after each replay that wasn't
+pub fn next_replay_delay(previous: Duration, flushed: bool) -> Duration {
+    if flushed {
+        REPLAY_BACKOFF
+    } else {
+        (previous * 2).min(MAX_REPLAY_BACKOFF)
+    }
+}
+
+pub const SIGNATURE_HEADER: &str = "X-EVMSol-Signature";
+pub const PUBKEY_HEADER: &str = "X-EVMSol-Pubkey";
+pub const TIMESTAMP_HEADER: &str = "X-EVMSol-Timestamp";
+
+/// Largest difference between a signed timestamp and the relayer's clock it
+/// should accept; a replayed request is refused once it is this old
+pub const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+/// Credentials attached to every relayer request
+#[derive(Default)]
+pub struct RequestAuth {
+    pub bearer_token: Option<String>,
+    pub signer: Option<Keypair>,
+}
+
+/// Reject plaintext endpoints unless explicitly allowed (e.g. a local relayer)
+pub fn validate_endpoint(endpoint: &str, allow_insecure: bool) -> Result<(), String> {
+    if endpoint.starts_with("https://") {
+        return Ok(());
+    }
+    if endpoint.starts_with("http://") {
+        if allow_insecure {
+            log::warn!("Posting to insecure relayer endpoint {}", endpoint);
+            return Ok(());
+        }
+        return Err(format!(
+            "Relayer endpoint {} is not HTTPS; set allow_insecure to permit it",
+            endpoint
+        ));
+    }
+    Err(format!("Unsupported relayer endpoint scheme: {}", endpoint))
+}
+
+/// Build the HTTP client. Certificate verification stays enabled; an extra CA
+/// can be trusted for relayers behind a private PKI.
+pub fn build_client(
+    ca_cert_path: Option<&str>,
+    allow_insecure: bool,
+) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder().https_only(!allow_insecure);
+
+    if let Some(path) = ca_cert_path {
+        let pem = fs::read(path).map_err(|e| format!("Failed to read CA cert {}: {}", path, e))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| format!("Invalid CA cert {}: {}", path, e))?;
+        builder = builder.add_root_certificate(cert);
+    }
+
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Bytes covered by the payload signature
+pub fn signing_message(timestamp: i64, path: &str, body: &[u8]) -> Vec<u8> {
+    let timestamp = timestamp.to_string();
+    let mut message = Vec::with_capacity(timestamp.len() + 1 + path.len() + 1 + body.len());
+    message.extend_from_slice(timestamp.as_bytes());
+    message.push(b':');
+    message.extend_from_slice(path.as_bytes());
+    message.push(b':');
+    message.extend_from_slice(body);
+    message
+}
+
+/// A payload signature and what the relayer needs to check it
+#[derive(Debug, Clone, Default)]
+pub struct PayloadSignature {
+    /// Base58 signature over `signing_message(timestamp, path, body)`
+    pub signature: String,
+    /// Base58 public key of the signing keypair
+    pub pubkey: String,
+    /// Unix seconds when the payload was signed
+    pub timestamp: i64,
+}
+
+/// The check a relayer applies to a signed payload: the signature must cover
+/// this timestamp, path and body, and the timestamp must be within
+/// `MAX_CLOCK_SKEW_SECS` of `now`
+pub fn verify_payload(
+    signed: &PayloadSignature,
+    path: &str,
+    body: &[u8],
+    now: i64,
+) -> Result<(), String> {
+    let pubkey: solana_sdk::pubkey::Pubkey =
+        signed.pubkey.parse().map_err(|_| "invalid pubkey".to_string())?;
+    let signature: solana_sdk::signature::Signature =
+        signed.signature.parse().map_err(|_| "invalid signature".to_string())?;
+
+    if !signature.verify(pubkey.as_ref(), &signing_message(signed.timestamp, path, body)) {
+        return Err("signature does not match the payload".to_string());
+    }
+    if (now - signed.timestamp).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(format!("timestamp {} is outside the skew window", signed.timestamp));
+    }
+    Ok(())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// A message destined for the relayer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum RelayerMessage {
+    Consensus(ConsensusSnapshot),
+    Transfer(BridgeTransferEvent),
+    EpochStakes(EpochStakeSnapshot),
+}
+
+impl RelayerMessage {
+    fn path(&self) -> &'static str {
+        match self {
+            RelayerMessage::Consensus(_) => "consensus",
+            RelayerMessage::Transfer(_) => "transfer",
+            RelayerMessage::EpochStakes(_) => "epoch-stakes",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            RelayerMessage::Consensus(snapshot) => {
+                format!("consensus snapshot for slot {}", snapshot.slot)
+            }
+            RelayerMessage::Transfer(transfer) => format!(
+                "bridge transfer {} for slot {}",
+                bs58::encode(&transfer.transfer_id).into_string(),
+                transfer.slot
+            ),
+            RelayerMessage::EpochStakes(snapshot) => {
+                format!("epoch stakes for epoch {}", snapshot.epoch)
+            }
+        }
+    }
+}
+
+/// Counters shared with the plugin for observability
+#[derive(Debug, Default)]
+pub struct PosterStats {
+    /// Messages currently waiting in the spool
+    pub pending: AtomicU64,
+    /// Messages dropped because the spool was full
+    pub dropped: AtomicU64,
+    /// Post attempts that failed, including spool replays
+    pub post_failures: AtomicU64,
+    /// Post attempts made again after a failure
+    pub post_retries: AtomicU64,
+    /// Messages the relayer refused, set aside instead of retried
+    pub dead_lettered: AtomicU64,
+}
+
+/// Why a post failed
+#[derive(Debug)]
+enum PostError {
+    /// The relayer could not take the message right now; retry it
+    Transient(String),
+    /// The relayer refused the message; resending it cannot succeed
+    Rejected(String),
+}
+
+/// Whether a non-success HTTP status is worth retrying: server errors,
+/// request timeouts and rate limiting are, other client errors are not
+pub fn is_retryable_status(status: u16) -> bool {
+    !(400..500).contains(&status) || status == 408 || status == 429
+}
+
+/// Where messages the relayer refused are kept for inspection
+pub struct DeadLetters {
+    path: Option<PathBuf>,
+    stats: Arc<PosterStats>,
+}
+
+/// One dead-lettered message, as written to the dead-letter file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub reason: String,
+    pub message: RelayerMessage,
+}
+
+impl DeadLetters {
+    /// Dead letters appended to `path` as JSON lines, or only logged if unset
+    pub fn new(path: Option<PathBuf>, stats: Arc<PosterStats>) -> Self {
+        Self { path, stats }
+    }
+
+    pub fn push(&self, msg: &RelayerMessage, reason: &str) {
+        self.stats.dead_lettered.fetch_add(1, Ordering::Relaxed);
+        let letter = DeadLetter {
+            reason: reason.to_string(),
+            message: msg.clone(),
+        };
+        let line = match serde_json::to_string(&letter) {
+            Ok(line) => line,
+            Err(e) => {
+                log::error!(
+                    "Relayer refused {} ({}); failed to encode it: {}",
+                    msg.describe(),
+                    reason,
+                    e
+                );
+                return;
+            }
+        };
+
+        let Some(path) = &self.path else {
+            log::error!("Relayer refused {} ({}): {}", msg.describe(), reason, line);
+            return;
+        };
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        match written {
+            Ok(()) => log::error!(
+                "Relayer refused {} ({}); dead-lettered to {}",
+                msg.describe(),
+                reason,
+                path.display()
+            ),
+            Err(e) => log::error!(
+                "Relayer refused {} ({}); failed to dead-letter it: {}: {}",
+                msg.describe(),
+                reason,
+                e,
+                line
+            ),
+        }
+    }
+
+    /// Read all dead letters
+    pub fn load(&self) -> Vec<DeadLetter> {
+        let Some(file) = self.path.as_ref().and_then(|path| File::open(path).ok()) else {
+            return vec![];
+        };
+
+        BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
+    }
+}
+
+/// Bounded append-only spool of undelivered messages
+pub struct Spool {
+    path: Option<PathBuf>,
+    max_entries: usize,
+    stats: Arc<PosterStats>,
+}
+
+impl Spool {
+    pub fn new(path: Option<PathBuf>, max_entries: usize, stats: Arc<PosterStats>) -> Self {
+        let spool = Self {
+            path,
+            max_entries,
+            stats,
+        };
+        let existing = spool.load().len() as u64;
+        spool.stats.pending.store(existing, Ordering::Relaxed);
+        spool
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.pending.load(Ordering::Relaxed) == 0
+    }
+
+    pub fn push(&self, msg: &RelayerMessage) {
+        let Some(path) = &self.path else {
+            log::error!("Dropping {}: no spool configured", msg.describe());
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        if self.stats.pending.load(Ordering::Relaxed) as usize >= self.max_entries {
+            log::error!("Spool full ({} entries), dropping {}", self.max_entries, msg.describe());
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let appended = serde_json::to_string(msg)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|e| e.to_string())
+            });
+
+        match appended {
+            Ok(()) => {
+                self.stats.pending.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                log::error!("Failed to spool {}: {}", msg.describe(), e);
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Read all spooled messages, skipping corrupt lines (e.g. a torn final write)
+    pub fn load(&self) -> Vec<RelayerMessage> {
+        let Some(file) = self.path.as_ref().and_then(|path| File::open(path).ok()) else {
+            return vec![];
+        };
+
+        BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| match serde_json::from_str(&line) {
+                Ok(msg) => Some(msg),
+                Err(e) => {
+                    log::warn!("Skipping corrupt spool entry: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Replace the spool contents with the still-undelivered messages
+    pub fn rewrite(&self, remaining: &[RelayerMessage]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let tmp = path.with_extension("tmp");
+        let written = File::create(&tmp)
+            .and_then(|mut file| {
+                for msg in remaining {
+                    let line = serde_json::to_string(msg)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                    writeln!(file, "{}", line)?;
+                }
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp, path));
+
+        match written {
+            Ok(()) => self.stats.pending.store(remaining.len() as u64, Ordering::Relaxed),
+            Err(e) => log::error!("Failed to rewrite spool {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Posts messages to the relayer with retries, spooling what cannot be delivered
+pub struct RelayerPoster {
+    client: reqwest::Client,
+    endpoint: String,
+    max_retries: u32,
+    spool: Spool,
+    dead_letters: DeadLetters,
+    auth: RequestAuth,
+    grpc: Option<GrpcTransport>,
+}
+
+impl RelayerPoster {
+    pub fn new(
+        client: reqwest::Client,
+        endpoint: String,
+        max_retries: u32,
+        spool: Spool,
+        dead_letters: DeadLetters,
+        auth: RequestAuth,
+    ) -> Self {
+        Self {
+            client,
+            endpoint,
+            max_retries,
+            spool,
+            dead_letters,
+            auth,
+            grpc: None,
+        }
+    }
+
+    /// Prefer the relayer's gRPC stream, keeping HTTP as the fallback
+    pub fn with_grpc(mut self, grpc: GrpcTransport) -> Self {
+        self.grpc = Some(grpc);
+        self
+    }
+
+    /// Deliver a message. While a backlog is spooled it is appended behind it
+    /// instead, keeping order; `replay_spool` drains the backlog.
+    pub async fn deliver(&self, msg: RelayerMessage) {
+        if !self.spool.is_empty() {
+            self.spool.push(&msg);
+            return;
+        }
+
+        if !self.post_with_retry(&msg).await {
+            self.spool.push(&msg);
+        }
+    }
+
+    /// Try to flush the spool. Returns true if it is now empty. The spool is
+    /// only rewritten when some of it was delivered or dead-lettered.
+    pub async fn replay_spool(&self) -> bool {
+        if self.spool.is_empty() {
+            return true;
+        }
+
+        let backlog = self.spool.load();
+
+        log::info!("Replaying {} spooled relayer messages", backlog.len());
+        for (i, msg) in backlog.iter().enumerate() {
+            match self.post_once(msg).await {
+                Ok(()) => {}
+                Err(PostError::Rejected(e)) => {
+                    self.spool.stats.post_failures.fetch_add(1, Ordering::Relaxed);
+                    self.dead_letters.push(msg, &e);
+                }
+                Err(PostError::Transient(_)) => {
+                    self.spool.stats.post_failures.fetch_add(1, Ordering::Relaxed);
+                    if i > 0 {
+                        self.spool.rewrite(&backlog[i..]);
+                    }
+                    return false;
+                }
+            }
+        }
+
+        self.spool.rewrite(&[]);
+        true
+    }
+
+    /// Post with retries. Returns false if the message should be spooled;
+    /// a message the relayer refused is dead-lettered instead.
+    async fn post_with_retry(&self, msg: &RelayerMessage) -> bool {
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 0..=self.max_retries {
+            match self.post_once(msg).await {
+                Ok(()) => return true,
+                Err(PostError::Rejected(e)) => {
+                    self.spool.stats.post_failures.fetch_add(1, Ordering::Relaxed);
+                    self.dead_letters.push(msg, &e);
+                    return true;
+                }
+                Err(PostError::Transient(e)) => {
+                    self.spool.stats.post_failures.fetch_add(1, Ordering::Relaxed);
+                    log::warn!(
+                        "Failed to post {} (attempt {}/{}): {}",
+                        msg.describe(),
+                        attempt + 1,
+                        self.max_retries + 1,
+                        e
+                    );
+                }
+            }
+
+            if attempt < self.max_retries {
+                self.spool.stats.post_retries.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+
+        false
+    }
+
+    async fn post_once(&self, msg: &RelayerMessage) -> Result<(), PostError> {
+        let body = match msg {
+            RelayerMessage::Consensus(snapshot) => serde_json::to_vec(snapshot),
+            RelayerMessage::Transfer(transfer) => serde_json::to_vec(transfer),
+            RelayerMessage::EpochStakes(snapshot) => serde_json::to_vec(snapshot),
+        }
+        .map_err(|e| PostError::Rejected(e.to_string()))?;
+        // Signed per attempt, so a replayed spool entry carries a fresh timestamp
+        let signature = self.auth.signer.as_ref().map(|signer| {
+            let timestamp = unix_now();
+            PayloadSignature {
+                signature: signer
+                    .sign_message(&signing_message(timestamp, msg.path(), &body))
+                    .to_string(),
+                pubkey: signer.pubkey().to_string(),
+                timestamp,
+            }
+        });
+
+        if let Some(grpc) = &self.grpc {
+            match grpc.publish(msg.path(), &body, signature.as_ref()).await {
+                Ok(()) => {
+                    log::info!("Streamed {}", msg.describe());
+                    return Ok(());
+                }
+                Err(GrpcError::Rejected(e)) => {
+                    return Err(PostError::Rejected(format!("relayer rejected: {}", e)))
+                }
+                Err(GrpcError::Unavailable(e)) => {
+                    log::warn!("Relayer gRPC stream unavailable, posting over HTTP: {}", e);
+                }
+            }
+        }
+
+        let url = format!("{}/{}", self.endpoint, msg.path());
+        let mut request = self
+            .client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+        if let Some(token) = &self.auth.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(signed) = signature {
+            request = request
+                .header(SIGNATURE_HEADER, signed.signature)
+                .header(PUBKEY_HEADER, signed.pubkey)
+                .header(TIMESTAMP_HEADER, signed.timestamp.to_string());
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| PostError::Transient(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let reason = format!("relayer returned {}", status);
+            return Err(if is_retryable_status(status.as_u16()) {
+                PostError::Transient(reason)
+            } else {
+                PostError::Rejected(reason)
+            });
+        }
+
+        log::info!("Posted {}", msg.describe());
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Config loading and hot reload.
+//!
+//! The config file is validated as a whole, program IDs and relayer
+//! credentials included, before anything is applied. While the plugin runs,
+//! a background task polls the file's modification time; a changed file that
+//! validates replaces the active config in one swap and hands the worker a
+//! new poster, so a new `relayer_endpoint` or auth setting takes effect on the
+//! next message. An invalid file is logged and ignored.
+
+use solana_sdk::pubkey::Pubkey;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+use crate::grpc::GrpcTransport;
+use crate::poster::{self, DeadLetters, PosterStats, RelayerPoster, RequestAuth, Spool};
+use crate::{PluginConfig, PluginMessage, Transport};
+
+/// How often the config file is checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A validated config with its program IDs parsed
+#[derive(Debug)]
+pub struct ActiveConfig {
+    pub config: PluginConfig,
+    pub bridge_program_id: Pubkey,
+    pub vote_program_id: Pubkey,
+}
+
+/// The active config, shared with the validator's callback threads
+pub type SharedConfig = Arc<RwLock<Arc<ActiveConfig>>>;
+
+impl ActiveConfig {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let config: PluginConfig = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        Self::validate(config)
+    }
+
+    pub fn validate(config: PluginConfig) -> Result<Self, String> {
+        let bridge_program_id = config
+            .bridge_program_id
+            .parse()
+            .map_err(|e| format!("Invalid bridge program ID: {}", e))?;
+        let vote_program_id = config
+            .vote_program_id
+            .parse()
+            .map_err(|e| format!("Invalid vote program ID: {}", e))?;
+
+        if config.slots_per_epoch == 0 {
+            return Err("slots_per_epoch must be positive".to_string());
+        }
+        if config.channel_capacity == 0 {
+            return Err("channel_capacity must be positive".to_string());
+        }
+        if config.worker_threads == 0 {
+            return Err("worker_threads must be positive".to_string());
+        }
+        poster::validate_endpoint(&config.relayer_endpoint, config.allow_insecure)?;
+        if config.transport == Transport::Grpc {
+            let endpoint = config
+                .grpc_endpoint
+                .as_deref()
+                .ok_or("grpc_endpoint is required for the grpc transport")?;
+            poster::validate_endpoint(endpoint, config.allow_insecure)?;
+        }
+
+        Ok(Self {
+            config,
+            bridge_program_id,
+            vote_program_id,
+        })
+    }
+}
+
+/// Build the relayer poster for `config`, loading its TLS and signing material
+pub fn build_poster(config: &PluginConfig, stats: Arc<PosterStats>) -> Result<RelayerPoster, String> {
+    let client = poster::build_client(config.ca_cert_path.as_deref(), config.allow_insecure)?;
+    let signer = config
+        .signing_keypair_path
+        .as_deref()
+        .map(|path| {
+            solana_sdk::signature::read_keypair_file(path)
+                .map_err(|e| format!("Invalid signing keypair {}: {}", path, e))
+        })
+        .transpose()?;
+    let auth = RequestAuth {
+        bearer_token: config.auth_token.clone(),
+        signer,
+    };
+
+    let dead_letters = DeadLetters::new(
+        config.dead_letter_path.as_ref().map(PathBuf::from),
+        stats.clone(),
+    );
+    let spool = Spool::new(
+        config.spool_path.as_ref().map(PathBuf::from),
+        config.max_spool_entries,
+        stats,
+    );
+    let poster = RelayerPoster::new(
+        client,
+        config.relayer_endpoint.clone(),
+        config.max_retries,
+        spool,
+        dead_letters,
+        auth,
+    );
+
+    match (config.transport, config.grpc_endpoint.as_deref()) {
+        (Transport::Grpc, Some(endpoint)) => {
+            let grpc = GrpcTransport::new(
+                endpoint,
+                config.ca_cert_path.as_deref(),
+                config.auth_token.as_deref(),
+            )?;
+            Ok(poster.with_grpc(grpc))
+        }
+        _ => Ok(poster),
+    }
+}
+
+/// Read and fully validate the config file, building its poster
+pub fn load(path: &Path, stats: Arc<PosterStats>) -> Result<(ActiveConfig, RelayerPoster), String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let active = ActiveConfig::parse(&content)?;
+    let poster = build_poster(&active.config, stats)?;
+    Ok((active, poster))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Apply changes to the config file until the worker goes away
+pub(crate) async fn watch(
+    path: PathBuf,
+    shared: SharedConfig,
+    tx: mpsc::WeakSender<PluginMessage>,
+    stats: Arc<PosterStats>,
+) {
+    let mut last_modified = modified(&path);
+    let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let current = modified(&path);
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        let (active, poster) = match load(&path, stats.clone()) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                log::error!("Ignoring invalid config {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let Some(tx) = tx.upgrade() else {
+            return;
+        };
+        let rpc_url = active.config.rpc_url.clone();
+        if tx
+            .send(PluginMessage::Reconfigure {
+                poster: Box::new(poster),
+                rpc_url,
+            })
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        *shared.write().unwrap() = Arc::new(active);
+        log::info!("Reloaded config from {}", path.display());
+    }
+}
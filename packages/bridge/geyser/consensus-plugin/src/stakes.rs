@@ -0,0 +1,191 @@
+//! Epoch stake snapshots.
+//!
+//! At each epoch boundary the worker asks the validator's own RPC for the
+//! epoch's vote accounts, sums activated stake per validator identity, and
+//! commits to the result with a Merkle root in the format the consensus
+//! circuit's `verify_stake_merkle_proof` checks: leaves are
+//! sha256(0x00 || pubkey || stake_le) and each parent is
+//! sha256(0x01 || children in sorted order). An unpaired node is carried up to
+//! the next level unchanged.
+
+use serde::Deserialize;
+use solana_sdk::{clock::Epoch, hash::hashv, pubkey::Pubkey};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{EpochStakeSnapshot, ValidatorStake};
+
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Domain-separation prefixes, matching the consensus circuit
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+
+/// The confirmed bank can trail the slot that crossed the boundary
+const EPOCH_POLL_ATTEMPTS: u32 = 10;
+const EPOCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct EpochInfo {
+    epoch: Epoch,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteAccountInfo {
+    pub node_pubkey: String,
+    pub activated_stake: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VoteAccounts {
+    pub current: Vec<VoteAccountInfo>,
+    pub delinquent: Vec<VoteAccountInfo>,
+}
+
+/// Stake leaf as hashed by the consensus circuit
+pub fn stake_leaf(pubkey: &[u8; 32], stake: u64) -> [u8; 32] {
+    hashv(&[&[LEAF_TAG], pubkey.as_ref(), &stake.to_le_bytes()]).to_bytes()
+}
+
+fn hash_sorted(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[NODE_TAG], left.as_ref(), right.as_ref()]).to_bytes()
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_sorted(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Merkle root over `stakes`, which must be sorted by pubkey. Zero if empty.
+pub fn stakes_root(stakes: &[ValidatorStake]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = stakes.iter().map(|s| stake_leaf(&s.pubkey, s.stake)).collect();
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Sibling path proving `pubkey`'s stake under `stakes_root(stakes)`
+pub fn stake_proof(stakes: &[ValidatorStake], pubkey: &[u8; 32]) -> Option<Vec<[u8; 32]>> {
+    let mut index = stakes.iter().position(|s| &s.pubkey == pubkey)?;
+    let mut level: Vec<[u8; 32]> = stakes.iter().map(|s| stake_leaf(&s.pubkey, s.stake)).collect();
+    let mut proof = Vec::new();
+
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+
+    Some(proof)
+}
+
+/// Build the snapshot for `epoch` from the RPC's vote accounts. A validator
+/// with several vote accounts gets one leaf holding their combined stake.
+pub fn build_snapshot(epoch: Epoch, vote_accounts: &VoteAccounts) -> Result<EpochStakeSnapshot, String> {
+    let mut by_node: BTreeMap<[u8; 32], u64> = BTreeMap::new();
+
+    for account in vote_accounts.current.iter().chain(&vote_accounts.delinquent) {
+        if account.activated_stake == 0 {
+            continue;
+        }
+        let node: Pubkey = account
+            .node_pubkey
+            .parse()
+            .map_err(|e| format!("Invalid node pubkey {}: {}", account.node_pubkey, e))?;
+        let stake = by_node.entry(node.to_bytes()).or_default();
+        *stake = stake
+            .checked_add(account.activated_stake)
+            .ok_or("Stake overflow")?;
+    }
+
+    let stakes: Vec<ValidatorStake> = by_node
+        .into_iter()
+        .map(|(pubkey, stake)| ValidatorStake { pubkey, stake })
+        .collect();
+    let total_stake = stakes
+        .iter()
+        .try_fold(0u64, |total, s| total.checked_add(s.stake))
+        .ok_or("Total stake overflow")?;
+
+    Ok(EpochStakeSnapshot {
+        epoch,
+        total_stake,
+        stakes_root: stakes_root(&stakes),
+        stakes,
+    })
+}
+
+async fn rpc_call<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+) -> Result<T, String> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": [{ "commitment": "confirmed" }],
+    });
+
+    let response: RpcResponse<T> = client
+        .post(rpc_url)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(error) = response.error {
+        return Err(format!("{} failed: {}", method, error));
+    }
+    response.result.ok_or_else(|| format!("{} returned no result", method))
+}
+
+/// Fetch vote-account stakes for `epoch` once the confirmed bank has reached it
+pub async fn fetch_epoch_stakes(rpc_url: &str, epoch: Epoch) -> Result<EpochStakeSnapshot, String> {
+    let client = reqwest::Client::builder()
+        .timeout(RPC_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut attempts = 0;
+    loop {
+        let info: EpochInfo = rpc_call(&client, rpc_url, "getEpochInfo").await?;
+        if info.epoch == epoch {
+            break;
+        }
+        if info.epoch > epoch {
+            return Err(format!("Epoch {} already ended (at {})", epoch, info.epoch));
+        }
+        attempts += 1;
+        if attempts >= EPOCH_POLL_ATTEMPTS {
+            return Err(format!("Confirmed bank still in epoch {}", info.epoch));
+        }
+        tokio::time::sleep(EPOCH_POLL_INTERVAL).await;
+    }
+
+    let vote_accounts: VoteAccounts = rpc_call(&client, rpc_url, "getVoteAccounts").await?;
+    build_snapshot(epoch, &vote_accounts)
+}
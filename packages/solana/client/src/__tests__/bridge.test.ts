@@ -59,12 +59,21 @@ describe('TokenBridgeClient', () => {
       const transferId1 = new Uint8Array(32).fill(1)
       const transferId2 = new Uint8Array(32).fill(2)
 
-      const [pda1] = client.getCompletionPDA(transferId1)
-      const [pda2] = client.getCompletionPDA(transferId2)
+      const [pda1] = client.getCompletionPDA(transferId1, 1n)
+      const [pda2] = client.getCompletionPDA(transferId2, 1n)
 
       expect(pda1.equals(pda2)).toBe(false)
     })
 
+    it('derives distinct completion PDAs per source chain', () => {
+      const transferId = new Uint8Array(32).fill(7)
+
+      const [ethereum] = client.getCompletionPDA(transferId, 1n)
+      const [base] = client.getCompletionPDA(transferId, 8453n)
+
+      expect(ethereum.equals(base)).toBe(false)
+    })
+
     it('derives bridge vault PDA for mint', () => {
       const mint = Keypair.generate().publicKey
       const [vaultPDA, bump] = client.getBridgeVaultPDA(mint)
@@ -73,6 +82,15 @@ describe('TokenBridgeClient', () => {
       expect(bump).toBeGreaterThanOrEqual(0)
     })
 
+    it('derives pending action PDA from action ID', () => {
+      const [pda1] = client.getPendingActionPDA(0n)
+      const [pda2] = client.getPendingActionPDA(1n)
+      const [pda3] = client.getPendingActionPDA(0n)
+
+      expect(pda1.equals(pda2)).toBe(false)
+      expect(pda1.equals(pda3)).toBe(true)
+    })
+
     it('handles large nonce values', () => {
       const largeNonce = 2n ** 60n
       const [pda, bump] = client.getTransferPDA(largeNonce)
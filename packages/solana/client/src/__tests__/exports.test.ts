@@ -204,6 +204,9 @@ describe('LaunchpadClient functionality', () => {
       creatorFeeBps: 100,
       graduated: false,
       createdAt: 0n,
+      feesAccrued: 0n,
+      feesClaimed: 0n,
+      platformFeeOverride: null,
     }
 
     const tokensOut = client.calculateBuyAmount(curve, 1_000_000_000n)
@@ -266,12 +269,12 @@ describe('Type definitions', () => {
 
 describe('EVMLightClientClient functionality', () => {
   it('can instantiate EVMLightClientClient with connection', () => {
-    const client = Solana.createEVMLightClientClient(connection)
+    const client = Solana.createEVMLightClientClient(connection, 1n)
     expect(client).toBeInstanceOf(Solana.EVMLightClientClient)
   })
 
   it('derives state PDA correctly', () => {
-    const client = Solana.createEVMLightClientClient(connection)
+    const client = Solana.createEVMLightClientClient(connection, 1n)
 
     const [statePDA, stateBump] = client.getStatePDA()
     expect(statePDA).toBeDefined()
@@ -279,7 +282,7 @@ describe('EVMLightClientClient functionality', () => {
   })
 
   it('serializes proof nodes correctly', () => {
-    const client = Solana.createEVMLightClientClient(connection)
+    const client = Solana.createEVMLightClientClient(connection, 1n)
 
     const nodes = [new Uint8Array([1, 2, 3]), new Uint8Array([4, 5, 6, 7])]
 
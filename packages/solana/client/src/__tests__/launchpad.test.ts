@@ -71,6 +71,18 @@ describe('LaunchpadClient', () => {
       expect(bump).toBeGreaterThanOrEqual(0)
     })
 
+    it('derives a distinct creator fee vault per token mint', () => {
+      const mint = Keypair.generate().publicKey
+      const [feeVaultPDA] = client.getCreatorFeeVaultPDA(mint)
+      const [vaultPDA] = client.getVaultPDA(mint)
+      const [otherFeeVaultPDA] = client.getCreatorFeeVaultPDA(
+        Keypair.generate().publicKey,
+      )
+
+      expect(feeVaultPDA.equals(vaultPDA)).toBe(false)
+      expect(feeVaultPDA.equals(otherFeeVaultPDA)).toBe(false)
+    })
+
     it('derives contribution PDA from presale and contributor', () => {
       const presale = Keypair.generate().publicKey
       const contributor1 = Keypair.generate().publicKey
@@ -111,6 +123,9 @@ describe('Bonding curve calculations', () => {
     creatorFeeBps: 100,
     graduated: false,
     createdAt: 0n,
+    feesAccrued: 0n,
+    feesClaimed: 0n,
+    platformFeeOverride: null,
   })
 
   describe('calculateBuyAmount', () => {
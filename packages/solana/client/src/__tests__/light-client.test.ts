@@ -4,6 +4,7 @@ import {
   createEVMLightClientClient,
   EVM_LIGHT_CLIENT_PROGRAM_ID,
   EVMLightClientClient,
+  getLightClientStatePDA,
   GROTH16_PROOF_SIZE,
 } from '../light-client'
 
@@ -12,19 +13,19 @@ const connection = new Connection('https://api.devnet.solana.com')
 describe('EVMLightClientClient', () => {
   describe('instantiation', () => {
     it('creates client with default program ID', () => {
-      const client = createEVMLightClientClient(connection)
+      const client = createEVMLightClientClient(connection, 1n)
       expect(client).toBeInstanceOf(EVMLightClientClient)
     })
 
     it('creates client with custom program ID', () => {
       const customProgramId = Keypair.generate().publicKey
-      const client = createEVMLightClientClient(connection, customProgramId)
+      const client = createEVMLightClientClient(connection, 1n, customProgramId)
       expect(client).toBeInstanceOf(EVMLightClientClient)
     })
   })
 
   describe('PDA derivation', () => {
-    const client = createEVMLightClientClient(connection)
+    const client = createEVMLightClientClient(connection, 1n)
 
     it('derives state PDA deterministically', () => {
       const [pda1, bump1] = client.getStatePDA()
@@ -35,6 +36,14 @@ describe('EVMLightClientClient', () => {
       expect(bump1).toBeGreaterThanOrEqual(0)
       expect(bump1).toBeLessThanOrEqual(255)
     })
+
+    it('derives a separate state PDA per chain', () => {
+      const [mainnet] = createEVMLightClientClient(connection, 1n).getStatePDA()
+      const [base] = createEVMLightClientClient(connection, 8453n).getStatePDA()
+
+      expect(mainnet.equals(base)).toBe(false)
+      expect(mainnet.equals(getLightClientStatePDA(1n)[0])).toBe(true)
+    })
   })
 
   describe('constants', () => {
@@ -51,7 +60,7 @@ describe('EVMLightClientClient', () => {
 })
 
 describe('Proof node serialization', () => {
-  const client = createEVMLightClientClient(connection)
+  const client = createEVMLightClientClient(connection, 1n)
 
   describe('serializeProofNodes', () => {
     it('serializes empty array correctly', () => {
@@ -145,7 +154,7 @@ describe('Proof node serialization', () => {
 })
 
 describe('Instruction data building', () => {
-  const client = createEVMLightClientClient(connection)
+  const client = createEVMLightClientClient(connection, 1n)
 
   describe('initializeInstructions', () => {
     it('builds initialize instructions with correct accounts', async () => {
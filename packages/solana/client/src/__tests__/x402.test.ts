@@ -3,6 +3,7 @@ import * as ed25519 from '@noble/ed25519'
 import { sha512 } from '@noble/hashes/sha512'
 import { Connection, Keypair, PublicKey } from '@solana/web3.js'
 import {
+  SOLANA_NETWORK_IDS,
   SolanaX402Client,
   SPL_TOKENS,
   X402_FACILITATOR_PROGRAM_ID,
@@ -251,6 +252,33 @@ describe('Payment creation and encoding', () => {
       const isValid = await client.verifyPayment(tamperedPayment)
       expect(isValid).toBe(false)
     })
+
+    it('rejects payment signed for another program or network', async () => {
+      const payer = Keypair.generate()
+
+      const payment = await client.createPayment(
+        {
+          recipient: Keypair.generate().publicKey,
+          token: SPL_TOKENS.USDC_DEVNET,
+          amount: 1000000n,
+          resource: '/api/premium',
+        },
+        payer,
+      )
+
+      const otherProgram = new SolanaX402Client(
+        connection,
+        Keypair.generate().publicKey,
+      )
+      const otherNetwork = new SolanaX402Client(
+        connection,
+        X402_FACILITATOR_PROGRAM_ID,
+        SOLANA_NETWORK_IDS.devnet,
+      )
+
+      expect(await otherProgram.verifyPayment(payment)).toBe(false)
+      expect(await otherNetwork.verifyPayment(payment)).toBe(false)
+    })
   })
 
   describe('decodePayment', () => {
@@ -277,6 +305,26 @@ describe('Payment creation and encoding', () => {
       expect(decoded.resource).toBe(original.resource)
       expect(decoded.nonce).toBe(original.nonce)
       expect(decoded.timestamp).toBe(original.timestamp)
+      expect(decoded.validUntil).toBe(original.validUntil)
+    })
+
+    it('rejects payment past its deadline', async () => {
+      const payer = Keypair.generate()
+      const timestamp = Math.floor(Date.now() / 1000)
+
+      const payment = await client.createPayment(
+        {
+          recipient: Keypair.generate().publicKey,
+          token: SPL_TOKENS.USDC_DEVNET,
+          amount: 1000n,
+          resource: '/api/test',
+          timestamp,
+          validUntil: timestamp - 1,
+        },
+        payer,
+      )
+
+      expect(() => client.decodePayment(payment.encoded)).toThrow('deadline')
     })
 
     it('throws on invalid base64', () => {
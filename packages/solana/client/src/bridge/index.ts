@@ -5,13 +5,19 @@ import {
   TOKEN_PROGRAM_ID,
 } from '@solana/spl-token'
 import {
+  type AccountMeta,
+  ComputeBudgetProgram,
   type Connection,
   PublicKey,
   SystemProgram,
   TransactionInstruction,
 } from '@solana/web3.js'
 import { bytesToEvmAddress, evmAddressToBytes } from '../dex/utils'
-import { EVM_LIGHT_CLIENT_PROGRAM_ID } from '../light-client'
+import {
+  EVM_LIGHT_CLIENT_PROGRAM_ID,
+  getLightClientStatePDA,
+  getProofCachePDA,
+} from '../light-client'
 
 // NOTE: This must match the program ID in Anchor.toml
 export const TOKEN_BRIDGE_PROGRAM_ID = new PublicKey(
@@ -23,8 +29,18 @@ const TOKEN_CONFIG_SEED = Buffer.from('token_config')
 const TRANSFER_SEED = Buffer.from('transfer')
 const COMPLETION_SEED = Buffer.from('completion')
 const BRIDGE_VAULT_SEED = Buffer.from('bridge_vault')
+const PENDING_ACTION_SEED = Buffer.from('pending_action')
+const PROOF_BUFFER_SEED = Buffer.from('proof_buffer')
 
 export const MAX_PAYLOAD_SIZE = 1024
+/** Longest proof the bridge accepts; see the program's `MAX_PROOF_SIZE` */
+export const MAX_PROOF_SIZE = 10 * 1024 - 128
+/** Proof bytes per `write_proof_buffer`, small enough for one transaction */
+export const PROOF_BUFFER_CHUNK_SIZE = 900
+export const MAX_COMPLETE_BATCH_SIZE = 3
+/** Compute budget per batched transfer; see `complete_batch_compute_units` */
+export const COMPLETE_TRANSFER_COMPUTE_UNITS = 150_000
+export const COMPLETE_BATCH_BASE_COMPUTE_UNITS = 20_000
 
 export type { TransferStatus }
 
@@ -36,14 +52,30 @@ export interface BridgeState {
   transferNonce: bigint
   totalLocked: bigint
   paused: boolean
+  timelockDelay: bigint
+  actionNonce: bigint
+  /** Slots the light client head must be past a transfer's EVM block */
+  minConfirmations: bigint
 }
 
+/** Admin actions that must wait out the bridge timelock */
+export type BridgeAction =
+  | { kind: 'unpause' }
+  | { kind: 'setEvmBridgeAddress'; evmBridgeAddress: Uint8Array }
+  | { kind: 'setTimelockDelay'; delay: bigint }
+  | { kind: 'enableToken'; mint: PublicKey }
+  | { kind: 'setMinConfirmations'; confirmations: bigint }
+
 export interface TokenConfig {
   mint: PublicKey
   evmToken: Uint8Array
   isNativeOnSolana: boolean
   totalBridged: bigint
   enabled: boolean
+  evmDecimals: number
+  dust: bigint
+  /** Native: held in the vault for the EVM side. Wrapped: minted, not burned */
+  outstanding: bigint
 }
 
 export interface TransferRecord {
@@ -62,6 +94,7 @@ export interface CompletionRecord {
   transferId: Uint8Array
   completed: boolean
   completedAt: bigint
+  sourceChainId: bigint
 }
 
 export interface InitializeBridgeParams {
@@ -73,6 +106,7 @@ export interface RegisterTokenParams {
   mint: PublicKey
   evmToken: Uint8Array
   isNativeOnSolana: boolean
+  evmDecimals: number
 }
 
 export interface InitiateTransferParams {
@@ -84,11 +118,19 @@ export interface InitiateTransferParams {
 
 export interface CompleteTransferParams {
   transferId: Uint8Array
+  sourceChainId: bigint
   evmSender: Uint8Array
   mint: PublicKey
+  /** Amount in EVM base units (u128) */
   amount: bigint
-  evmBlockNumber: bigint
+  /** Beacon slot of the light client root the proof is against; see `minConfirmations` */
+  proofSlot: bigint
+  evmNonce: bigint
   proofData: Uint8Array
+  /** Proof buffer holding `proofData`, which is then left out of the instruction */
+  proofBuffer?: PublicKey
+  /** Least SPL amount the recipient accepts; 0 disables the check */
+  minAmountOut?: bigint
 }
 
 export class TokenBridgeClient {
@@ -122,9 +164,24 @@ export class TokenBridgeClient {
     )
   }
 
-  getCompletionPDA(transferId: Uint8Array): [PublicKey, number] {
+  getCompletionPDA(
+    transferId: Uint8Array,
+    sourceChainId: bigint,
+  ): [PublicKey, number] {
+    const chainBuffer = Buffer.alloc(8)
+    chainBuffer.writeBigUInt64LE(sourceChainId)
     return PublicKey.findProgramAddressSync(
-      [COMPLETION_SEED, Buffer.from(transferId)],
+      [COMPLETION_SEED, chainBuffer, Buffer.from(transferId)],
+      this.programId,
+    )
+  }
+
+  getProofBufferPDA(
+    relayer: PublicKey,
+    bufferId: Uint8Array,
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [PROOF_BUFFER_SEED, relayer.toBuffer(), Buffer.from(bufferId)],
       this.programId,
     )
   }
@@ -136,6 +193,15 @@ export class TokenBridgeClient {
     )
   }
 
+  getPendingActionPDA(id: bigint): [PublicKey, number] {
+    const idBuffer = Buffer.alloc(8)
+    idBuffer.writeBigUInt64LE(id)
+    return PublicKey.findProgramAddressSync(
+      [PENDING_ACTION_SEED, idBuffer],
+      this.programId,
+    )
+  }
+
   // Read Operations
 
   async getBridgeState(): Promise<BridgeState | null> {
@@ -161,8 +227,9 @@ export class TokenBridgeClient {
 
   async getCompletionRecord(
     transferId: Uint8Array,
+    sourceChainId: bigint,
   ): Promise<CompletionRecord | null> {
-    const [completionPDA] = this.getCompletionPDA(transferId)
+    const [completionPDA] = this.getCompletionPDA(transferId, sourceChainId)
     const accountInfo = await this.connection.getAccountInfo(completionPDA)
     if (!accountInfo) return null
     return this.deserializeCompletionRecord(accountInfo.data)
@@ -255,19 +322,62 @@ export class TokenBridgeClient {
     ]
   }
 
-  async unpauseInstructions(
+  async queueActionInstructions(
+    action: BridgeAction,
     admin: PublicKey,
   ): Promise<TransactionInstruction[]> {
+    const state = await this.getBridgeState()
+    if (!state) throw new Error('Bridge not initialized')
+
     const [statePDA] = this.getBridgeStatePDA()
+    const [pendingActionPDA] = this.getPendingActionPDA(state.actionNonce)
 
-    // Discriminator for unpause
-    const data = Buffer.from([0x75, 0x6e, 0x70, 0x61, 0x75, 0x73, 0x65, 0x00])
+    const data = Buffer.concat([
+      // Discriminator for queue_action
+      Buffer.from([0x05, 0x0d, 0xae, 0x76, 0xaa, 0xb9, 0x16, 0x07]),
+      this.serializeBridgeAction(action),
+    ])
 
     return [
       new TransactionInstruction({
         keys: [
           { pubkey: statePDA, isSigner: false, isWritable: true },
-          { pubkey: admin, isSigner: true, isWritable: false },
+          { pubkey: pendingActionPDA, isSigner: false, isWritable: true },
+          { pubkey: admin, isSigner: true, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  async executeActionInstructions(
+    id: bigint,
+    admin: PublicKey,
+    tokenMint?: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const [statePDA] = this.getBridgeStatePDA()
+    const [pendingActionPDA] = this.getPendingActionPDA(id)
+    // Anchor treats the program ID as "not provided" for optional accounts
+    const tokenConfig = tokenMint
+      ? this.getTokenConfigPDA(tokenMint)[0]
+      : this.programId
+
+    // Discriminator for execute_action
+    const data = Buffer.from([0xf6, 0x89, 0x69, 0x71, 0xf7, 0x06, 0xdf, 0xae])
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: statePDA, isSigner: false, isWritable: true },
+          { pubkey: pendingActionPDA, isSigner: false, isWritable: true },
+          { pubkey: tokenConfig, isSigner: false, isWritable: !!tokenMint },
+          { pubkey: admin, isSigner: true, isWritable: true },
         ],
         programId: this.programId,
         data,
@@ -315,7 +425,7 @@ export class TokenBridgeClient {
       new TransactionInstruction({
         keys: [
           { pubkey: statePDA, isSigner: false, isWritable: true },
-          { pubkey: tokenConfigPDA, isSigner: false, isWritable: false },
+          { pubkey: tokenConfigPDA, isSigner: false, isWritable: true },
           { pubkey: transferPDA, isSigner: false, isWritable: true },
           { pubkey: params.mint, isSigner: false, isWritable: true },
           { pubkey: bridgeVaultPDA, isSigner: false, isWritable: true },
@@ -346,7 +456,11 @@ export class TokenBridgeClient {
 
     const [statePDA] = this.getBridgeStatePDA()
     const [tokenConfigPDA] = this.getTokenConfigPDA(params.mint)
-    const [completionPDA] = this.getCompletionPDA(params.transferId)
+    const [lightClientState] = getLightClientStatePDA(state.evmChainId)
+    const [completionPDA] = this.getCompletionPDA(
+      params.transferId,
+      params.sourceChainId,
+    )
     const [bridgeVaultPDA] = this.getBridgeVaultPDA(params.mint)
 
     const recipientTokenAccount = await getAssociatedTokenAddress(
@@ -377,14 +491,14 @@ export class TokenBridgeClient {
       new TransactionInstruction({
         keys: [
           { pubkey: statePDA, isSigner: false, isWritable: false },
-          { pubkey: tokenConfigPDA, isSigner: false, isWritable: false },
+          { pubkey: tokenConfigPDA, isSigner: false, isWritable: true },
           { pubkey: completionPDA, isSigner: false, isWritable: true },
           {
             pubkey: EVM_LIGHT_CLIENT_PROGRAM_ID,
             isSigner: false,
             isWritable: false,
           },
-          { pubkey: state.evmLightClient, isSigner: false, isWritable: false },
+          { pubkey: lightClientState, isSigner: false, isWritable: false },
           { pubkey: params.mint, isSigner: false, isWritable: true },
           { pubkey: bridgeVaultPDA, isSigner: false, isWritable: true },
           { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
@@ -396,6 +510,116 @@ export class TokenBridgeClient {
             isSigner: false,
             isWritable: false,
           },
+          await this.proofCacheMeta(state.evmChainId),
+          this.proofBufferMeta(params.proofBuffer),
+        ],
+        programId: this.programId,
+        data,
+      }),
+    )
+
+    return instructions
+  }
+
+  /**
+   * Complete up to MAX_COMPLETE_BATCH_SIZE transfers of the same mint in one
+   * instruction. Any invalid transfer fails the whole batch.
+   *
+   * A full batch only fits in a transaction with its proofs in proof buffers
+   * (see `proofBufferInstructions`); the recipient token account creations
+   * returned ahead of the batch instruction may need their own transaction.
+   * The compute unit limit just before it must stay in the batch's
+   * transaction: a full batch needs more than the default budget.
+   */
+  async completeTransferBatchInstructions(
+    transfers: { params: CompleteTransferParams; recipient: PublicKey }[],
+    relayer: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    if (transfers.length === 0 || transfers.length > MAX_COMPLETE_BATCH_SIZE) {
+      throw new Error(
+        `Batch must contain 1 to ${MAX_COMPLETE_BATCH_SIZE} transfers`,
+      )
+    }
+    const mint = transfers[0].params.mint
+    if (!transfers.every(({ params }) => params.mint.equals(mint))) {
+      throw new Error('All transfers in a batch must use the same mint')
+    }
+
+    const state = await this.getBridgeState()
+    if (!state) throw new Error('Bridge not initialized')
+
+    const [statePDA] = this.getBridgeStatePDA()
+    const [tokenConfigPDA] = this.getTokenConfigPDA(mint)
+    const [bridgeVaultPDA] = this.getBridgeVaultPDA(mint)
+    const [lightClientState] = getLightClientStatePDA(state.evmChainId)
+
+    const instructions: TransactionInstruction[] = []
+    const transferAccounts: AccountMeta[] = []
+
+    for (const { params, recipient } of transfers) {
+      const [completionPDA] = this.getCompletionPDA(
+        params.transferId,
+        params.sourceChainId,
+      )
+      const recipientTokenAccount = await getAssociatedTokenAddress(
+        mint,
+        recipient,
+      )
+
+      // Create recipient token account if needed
+      const recipientAccountInfo = await this.connection.getAccountInfo(
+        recipientTokenAccount,
+      )
+      if (!recipientAccountInfo) {
+        instructions.push(
+          createAssociatedTokenAccountInstruction(
+            relayer,
+            recipientTokenAccount,
+            recipient,
+            mint,
+          ),
+        )
+      }
+
+      transferAccounts.push(
+        { pubkey: completionPDA, isSigner: false, isWritable: true },
+        { pubkey: recipient, isSigner: false, isWritable: false },
+        { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
+        this.proofBufferMeta(params.proofBuffer),
+      )
+    }
+
+    const data = this.buildCompleteTransferBatchData(
+      transfers.map(({ params }) => params),
+    )
+
+    instructions.push(
+      ComputeBudgetProgram.setComputeUnitLimit({
+        units:
+          COMPLETE_BATCH_BASE_COMPUTE_UNITS +
+          COMPLETE_TRANSFER_COMPUTE_UNITS * transfers.length,
+      }),
+      new TransactionInstruction({
+        keys: [
+          { pubkey: statePDA, isSigner: false, isWritable: false },
+          { pubkey: tokenConfigPDA, isSigner: false, isWritable: true },
+          {
+            pubkey: EVM_LIGHT_CLIENT_PROGRAM_ID,
+            isSigner: false,
+            isWritable: false,
+          },
+          { pubkey: lightClientState, isSigner: false, isWritable: false },
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: bridgeVaultPDA, isSigner: false, isWritable: true },
+          { pubkey: relayer, isSigner: true, isWritable: true },
+          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+          await this.proofCacheMeta(state.evmChainId),
+          ...transferAccounts,
         ],
         programId: this.programId,
         data,
@@ -405,6 +629,182 @@ export class TokenBridgeClient {
     return instructions
   }
 
+  /**
+   * Mark a Solana → EVM transfer completed, proving the EVM bridge's
+   * `completedTransfers` entry with a storage proof. Anyone can submit it.
+   */
+  async confirmTransferCompletedInstructions(
+    nonce: bigint,
+    proofData: Uint8Array,
+    proofBuffer?: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const state = await this.getBridgeState()
+    if (!state) throw new Error('Bridge not initialized')
+
+    const [statePDA] = this.getBridgeStatePDA()
+    const [transferPDA] = this.getTransferPDA(nonce)
+    const [lightClientState] = getLightClientStatePDA(state.evmChainId)
+    const proof = proofBuffer ? new Uint8Array(0) : proofData
+
+    const data = Buffer.alloc(8 + 4 + proof.length)
+    // Discriminator for confirm_transfer_completed
+    Buffer.from([0x8b, 0x9f, 0xa2, 0x80, 0xb7, 0x05, 0x5c, 0xc6]).copy(data, 0)
+    data.writeUInt32LE(proof.length, 8)
+    Buffer.from(proof).copy(data, 12)
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: statePDA, isSigner: false, isWritable: false },
+          { pubkey: transferPDA, isSigner: false, isWritable: true },
+          {
+            pubkey: EVM_LIGHT_CLIENT_PROGRAM_ID,
+            isSigner: false,
+            isWritable: false,
+          },
+          { pubkey: lightClientState, isSigner: false, isWritable: false },
+          await this.proofCacheMeta(state.evmChainId),
+          this.proofBufferMeta(proofBuffer),
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  /**
+   * Stage a proof too large for one transaction in the relayer's proof buffer
+   * for `bufferId` (usually the transfer ID). Returns the buffer and the
+   * instructions that create and fill it, each to be sent in its own
+   * transaction; pass the buffer as `proofBuffer` and close it afterwards.
+   */
+  proofBufferInstructions(
+    bufferId: Uint8Array,
+    proofData: Uint8Array,
+    relayer: PublicKey,
+  ): { proofBuffer: PublicKey; instructions: TransactionInstruction[] } {
+    if (proofData.length === 0 || proofData.length > MAX_PROOF_SIZE) {
+      throw new Error(`Proof must be 1 to ${MAX_PROOF_SIZE} bytes`)
+    }
+    const [proofBuffer] = this.getProofBufferPDA(relayer, bufferId)
+
+    // Discriminator for init_proof_buffer
+    const init = Buffer.alloc(8 + 32 + 4)
+    Buffer.from([0x31, 0x1b, 0x1c, 0x58, 0x13, 0x63, 0x85, 0xc2]).copy(init, 0)
+    Buffer.from(bufferId).copy(init, 8)
+    init.writeUInt32LE(proofData.length, 40)
+
+    const instructions = [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: proofBuffer, isSigner: false, isWritable: true },
+          { pubkey: relayer, isSigner: true, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data: init,
+      }),
+    ]
+
+    for (
+      let offset = 0;
+      offset < proofData.length;
+      offset += PROOF_BUFFER_CHUNK_SIZE
+    ) {
+      const chunk = proofData.subarray(offset, offset + PROOF_BUFFER_CHUNK_SIZE)
+      const write = Buffer.alloc(8 + 32 + 4 + 4 + chunk.length)
+      // Discriminator for write_proof_buffer
+      Buffer.from([0x03, 0xe2, 0x9e, 0xe7, 0x7a, 0x9a, 0x0c, 0x31]).copy(
+        write,
+        0,
+      )
+      Buffer.from(bufferId).copy(write, 8)
+      write.writeUInt32LE(offset, 40)
+      write.writeUInt32LE(chunk.length, 44)
+      Buffer.from(chunk).copy(write, 48)
+
+      instructions.push(
+        new TransactionInstruction({
+          keys: [
+            { pubkey: proofBuffer, isSigner: false, isWritable: true },
+            { pubkey: relayer, isSigner: true, isWritable: false },
+          ],
+          programId: this.programId,
+          data: write,
+        }),
+      )
+    }
+
+    return { proofBuffer, instructions }
+  }
+
+  /** Close the relayer's proof buffer for `bufferId`, refunding its rent */
+  closeProofBufferInstruction(
+    bufferId: Uint8Array,
+    relayer: PublicKey,
+  ): TransactionInstruction {
+    const [proofBuffer] = this.getProofBufferPDA(relayer, bufferId)
+
+    // Discriminator for close_proof_buffer
+    const data = Buffer.alloc(8 + 32)
+    Buffer.from([0x82, 0x96, 0x06, 0x23, 0xc1, 0x22, 0xf3, 0x57]).copy(data, 0)
+    Buffer.from(bufferId).copy(data, 8)
+
+    return new TransactionInstruction({
+      keys: [
+        { pubkey: proofBuffer, isSigner: false, isWritable: true },
+        { pubkey: relayer, isSigner: true, isWritable: true },
+      ],
+      programId: this.programId,
+      data,
+    })
+  }
+
+  /** A proof buffer, or the bridge program standing in for an absent one */
+  private proofBufferMeta(proofBuffer?: PublicKey): AccountMeta {
+    return proofBuffer
+      ? { pubkey: proofBuffer, isSigner: false, isWritable: false }
+      : { pubkey: this.programId, isSigner: false, isWritable: false }
+  }
+
+  /**
+   * The light client's proof cache for `evmChainId` if one was created, or
+   * the bridge program itself, which Anchor reads as an absent optional account
+   */
+  private async proofCacheMeta(evmChainId: bigint): Promise<AccountMeta> {
+    const [proofCache] = getProofCachePDA(evmChainId)
+    const accountInfo = await this.connection.getAccountInfo(proofCache)
+    return accountInfo
+      ? { pubkey: proofCache, isSigner: false, isWritable: true }
+      : { pubkey: this.programId, isSigner: false, isWritable: false }
+  }
+
+  /** Close a completed transfer record, refunding its rent */
+  async closeTransferRecordInstructions(
+    nonce: bigint,
+    sender: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const [transferPDA] = this.getTransferPDA(nonce)
+
+    // Discriminator for close_transfer_record
+    const data = Buffer.from([0xb1, 0x2e, 0x11, 0x8e, 0x13, 0x6f, 0x53, 0x46])
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: transferPDA, isSigner: false, isWritable: true },
+          { pubkey: sender, isSigner: true, isWritable: true },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   evmAddressToBytes(address: string): Uint8Array {
     return evmAddressToBytes(address)
   }
@@ -423,13 +823,40 @@ export class TokenBridgeClient {
     return data
   }
 
+  private serializeBridgeAction(action: BridgeAction): Buffer {
+    switch (action.kind) {
+      case 'unpause':
+        return Buffer.from([0])
+      case 'setEvmBridgeAddress':
+        return Buffer.concat([
+          Buffer.from([1]),
+          Buffer.from(action.evmBridgeAddress),
+        ])
+      case 'setTimelockDelay': {
+        const data = Buffer.alloc(1 + 8)
+        data.writeUInt8(2, 0)
+        data.writeBigInt64LE(action.delay, 1)
+        return data
+      }
+      case 'enableToken':
+        return Buffer.concat([Buffer.from([3]), action.mint.toBuffer()])
+      case 'setMinConfirmations': {
+        const data = Buffer.alloc(1 + 8)
+        data.writeUInt8(4, 0)
+        data.writeBigUInt64LE(action.confirmations, 1)
+        return data
+      }
+    }
+  }
+
   private buildRegisterTokenData(params: RegisterTokenParams): Buffer {
-    const data = Buffer.alloc(8 + 20 + 1)
+    const data = Buffer.alloc(8 + 20 + 1 + 1)
 
     // Discriminator for register_token
     Buffer.from([0x72, 0x65, 0x67, 0x69, 0x73, 0x74, 0x65, 0x72]).copy(data, 0)
     Buffer.from(params.evmToken).copy(data, 8)
     data.writeUInt8(params.isNativeOnSolana ? 1 : 0, 28)
+    data.writeUInt8(params.evmDecimals, 29)
 
     return data
   }
@@ -462,31 +889,63 @@ export class TokenBridgeClient {
   }
 
   private buildCompleteTransferData(params: CompleteTransferParams): Buffer {
-    const data = Buffer.alloc(8 + 32 + 20 + 8 + 8 + 4 + params.proofData.length)
-    let offset = 0
-
     // Discriminator for complete_transfer
-    Buffer.from([0x63, 0x6f, 0x6d, 0x70, 0x6c, 0x65, 0x74, 0x65]).copy(
-      data,
-      offset,
+    return Buffer.concat([
+      Buffer.from([0x63, 0x6f, 0x6d, 0x70, 0x6c, 0x65, 0x74, 0x65]),
+      this.serializeTransferDescriptor(params),
+    ])
+  }
+
+  private buildCompleteTransferBatchData(
+    transfers: CompleteTransferParams[],
+  ): Buffer {
+    const header = Buffer.alloc(8 + 4)
+
+    // Discriminator for complete_transfer_batch
+    Buffer.from([0x36, 0x45, 0x36, 0xe2, 0x0f, 0x61, 0x54, 0x0c]).copy(
+      header,
+      0,
     )
-    offset += 8
+    header.writeUInt32LE(transfers.length, 8)
+
+    return Buffer.concat([
+      header,
+      ...transfers.map((params) => this.serializeTransferDescriptor(params)),
+    ])
+  }
+
+  /** Borsh layout of `TransferDescriptor`, the same as `complete_transfer`'s args */
+  private serializeTransferDescriptor(params: CompleteTransferParams): Buffer {
+    const proofData = params.proofBuffer ? new Uint8Array(0) : params.proofData
+    const data = Buffer.alloc(
+      32 + 8 + 20 + 16 + 8 + 8 + 4 + proofData.length + 8,
+    )
+    let offset = 0
 
     Buffer.from(params.transferId).copy(data, offset)
     offset += 32
 
+    data.writeBigUInt64LE(params.sourceChainId, offset)
+    offset += 8
+
     Buffer.from(params.evmSender).copy(data, offset)
     offset += 20
 
-    data.writeBigUInt64LE(params.amount, offset)
+    writeU128LE(data, params.amount, offset)
+    offset += 16
+
+    data.writeBigUInt64LE(params.proofSlot, offset)
     offset += 8
 
-    data.writeBigUInt64LE(params.evmBlockNumber, offset)
+    data.writeBigUInt64LE(params.evmNonce, offset)
     offset += 8
 
-    data.writeUInt32LE(params.proofData.length, offset)
+    data.writeUInt32LE(proofData.length, offset)
     offset += 4
-    Buffer.from(params.proofData).copy(data, offset)
+    Buffer.from(proofData).copy(data, offset)
+    offset += proofData.length
+
+    data.writeBigUInt64LE(params.minAmountOut ?? BigInt(0), offset)
 
     return data
   }
@@ -515,6 +974,15 @@ export class TokenBridgeClient {
     offset += 8
 
     const paused = data.readUInt8(offset) === 1
+    offset += 1
+
+    const timelockDelay = data.readBigInt64LE(offset)
+    offset += 8
+
+    const actionNonce = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const minConfirmations = data.readBigUInt64LE(offset)
 
     return {
       admin,
@@ -524,6 +992,9 @@ export class TokenBridgeClient {
       transferNonce,
       totalLocked,
       paused,
+      timelockDelay,
+      actionNonce,
+      minConfirmations,
     }
   }
 
@@ -543,6 +1014,15 @@ export class TokenBridgeClient {
     offset += 8
 
     const enabled = data.readUInt8(offset) === 1
+    offset += 1
+
+    const evmDecimals = data.readUInt8(offset)
+    offset += 1
+
+    const dust = readU128LE(data, offset)
+    offset += 16
+
+    const outstanding = data.readBigUInt64LE(offset)
 
     return {
       mint,
@@ -550,6 +1030,9 @@ export class TokenBridgeClient {
       isNativeOnSolana,
       totalBridged,
       enabled,
+      evmDecimals,
+      dust,
+      outstanding,
     }
   }
 
@@ -578,8 +1061,7 @@ export class TokenBridgeClient {
     offset += 8
 
     const statusByte = data.readUInt8(offset)
-    const status: TransferStatus =
-      statusByte === 0 ? 'pending' : statusByte === 1 ? 'completed' : 'failed'
+    const status: TransferStatus = statusByte === 0 ? 'pending' : 'completed'
     offset += 1
 
     const payloadLen = data.readUInt32LE(offset)
@@ -609,11 +1091,15 @@ export class TokenBridgeClient {
     offset += 1
 
     const completedAt = data.readBigInt64LE(offset)
+    offset += 8
+
+    const sourceChainId = data.readBigUInt64LE(offset)
 
     return {
       transferId,
       completed,
       completedAt,
+      sourceChainId,
     }
   }
 }
@@ -624,3 +1110,15 @@ export function createTokenBridgeClient(
 ): TokenBridgeClient {
   return new TokenBridgeClient(connection, programId)
 }
+
+function writeU128LE(data: Buffer, value: bigint, offset: number): void {
+  data.writeBigUInt64LE(value & BigInt('0xffffffffffffffff'), offset)
+  data.writeBigUInt64LE(value >> BigInt(64), offset + 8)
+}
+
+function readU128LE(data: Buffer, offset: number): bigint {
+  return (
+    data.readBigUInt64LE(offset) |
+    (data.readBigUInt64LE(offset + 8) << BigInt(64))
+  )
+}
@@ -9,6 +9,7 @@ import {
   type Keypair,
   LAMPORTS_PER_SOL,
   PublicKey,
+  SYSVAR_RENT_PUBKEY,
   SystemProgram,
   TransactionInstruction,
 } from '@solana/web3.js'
@@ -28,7 +29,13 @@ const CONFIG_SEED = Buffer.from('config')
 const BONDING_CURVE_SEED = Buffer.from('bonding-curve')
 const PRESALE_SEED = Buffer.from('presale')
 const VAULT_SEED = Buffer.from('vault')
+const CREATOR_FEE_VAULT_SEED = Buffer.from('creator-fee-vault')
+const PROTOCOL_FEE_VAULT_SEED = Buffer.from('protocol-fee-vault')
 const CONTRIBUTION_SEED = Buffer.from('contribution')
+const LAUNCH_SEED = Buffer.from('launch')
+const CREATOR_PROFILE_SEED = Buffer.from('creator-profile')
+
+export const MAX_CREATOR_PRESALES = 16
 
 // Types
 
@@ -37,6 +44,9 @@ export interface LaunchpadConfig {
   feeRecipient: PublicKey
   platformFeeBps: number
   totalLaunches: bigint
+  protocolFeesClaimed: bigint
+  referralFeeBps: number
+  paused: boolean
 }
 
 export interface BondingCurve {
@@ -51,6 +61,12 @@ export interface BondingCurve {
   creatorFeeBps: number
   graduated: boolean
   createdAt: bigint
+  feesAccrued: bigint
+  feesClaimed: bigint
+  /** Platform fee for this token in place of the global fee */
+  platformFeeOverride: number | null
+  /** Part of this curve's platform fees paid to referrers */
+  referralFeesPaid: bigint
 }
 
 export interface Presale {
@@ -69,6 +85,15 @@ export interface Presale {
   finalized: boolean
   cancelled: boolean
   finalizedAt: bigint
+  /** Contributions stopped by the creator via their profile */
+  paused: boolean
+}
+
+/** A creator's presales, paused and resumed together */
+export interface CreatorProfile {
+  creator: PublicKey
+  presales: PublicKey[]
+  paused: boolean
 }
 
 export interface Contribution {
@@ -79,6 +104,13 @@ export interface Contribution {
   claimed: boolean
 }
 
+export interface LaunchRecord {
+  index: bigint
+  tokenMint: PublicKey
+  creator: PublicKey
+  createdAt: bigint
+}
+
 export interface CreateBondingCurveParams {
   name: string
   symbol: string
@@ -137,6 +169,20 @@ export class LaunchpadClient {
     )
   }
 
+  getCreatorFeeVaultPDA(tokenMint: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [CREATOR_FEE_VAULT_SEED, tokenMint.toBuffer()],
+      this.programId,
+    )
+  }
+
+  getProtocolFeeVaultPDA(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [PROTOCOL_FEE_VAULT_SEED],
+      this.programId,
+    )
+  }
+
   getContributionPDA(
     presale: PublicKey,
     contributor: PublicKey,
@@ -147,6 +193,23 @@ export class LaunchpadClient {
     )
   }
 
+  getCreatorProfilePDA(creator: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [CREATOR_PROFILE_SEED, creator.toBuffer()],
+      this.programId,
+    )
+  }
+
+  /** Launches are numbered from 0 up to `LaunchpadConfig.totalLaunches` */
+  getLaunchPDA(index: bigint): [PublicKey, number] {
+    const indexBuffer = Buffer.alloc(8)
+    indexBuffer.writeBigUInt64LE(index)
+    return PublicKey.findProgramAddressSync(
+      [LAUNCH_SEED, indexBuffer],
+      this.programId,
+    )
+  }
+
   // Read Operations
 
   async getConfig(): Promise<LaunchpadConfig | null> {
@@ -184,6 +247,45 @@ export class LaunchpadClient {
     return this.deserializeContribution(accountInfo.data)
   }
 
+  async getCreatorProfile(creator: PublicKey): Promise<CreatorProfile | null> {
+    const [profilePDA] = this.getCreatorProfilePDA(creator)
+    const accountInfo = await this.connection.getAccountInfo(profilePDA)
+    if (!accountInfo) return null
+
+    return this.deserializeCreatorProfile(accountInfo.data)
+  }
+
+  async getLaunch(index: bigint): Promise<LaunchRecord | null> {
+    const [launchPDA] = this.getLaunchPDA(index)
+    const accountInfo = await this.connection.getAccountInfo(launchPDA)
+    if (!accountInfo) return null
+
+    return this.deserializeLaunch(accountInfo.data)
+  }
+
+  /** Every launch, oldest first */
+  async getLaunches(): Promise<LaunchRecord[]> {
+    const config = await this.getConfig()
+    if (!config) return []
+
+    const pdas: PublicKey[] = []
+    for (let index = 0n; index < config.totalLaunches; index++) {
+      pdas.push(this.getLaunchPDA(index)[0])
+    }
+
+    const launches: LaunchRecord[] = []
+    // getMultipleAccountsInfo takes at most 100 accounts per call
+    for (let i = 0; i < pdas.length; i += 100) {
+      const infos = await this.connection.getMultipleAccountsInfo(
+        pdas.slice(i, i + 100),
+      )
+      for (const info of infos) {
+        if (info) launches.push(this.deserializeLaunch(info.data))
+      }
+    }
+    return launches
+  }
+
   // Bonding Curve Operations
 
   async createBondingCurveInstructions(
@@ -193,6 +295,13 @@ export class LaunchpadClient {
   ): Promise<TransactionInstruction[]> {
     const [configPDA] = this.getConfigPDA()
     const [bondingCurvePDA] = this.getBondingCurvePDA(tokenMint.publicKey)
+    const [vaultPDA] = this.getVaultPDA(tokenMint.publicKey)
+    const [creatorFeeVaultPDA] = this.getCreatorFeeVaultPDA(tokenMint.publicKey)
+
+    // The launch is recorded at the next index
+    const config = await this.getConfig()
+    if (!config) throw new Error('Launchpad not initialized')
+    const [launchPDA] = this.getLaunchPDA(config.totalLaunches)
 
     const curveTokenAccount = await getAssociatedTokenAddress(
       tokenMint.publicKey,
@@ -263,6 +372,9 @@ export class LaunchpadClient {
           { pubkey: tokenMint.publicKey, isSigner: true, isWritable: true },
           { pubkey: bondingCurvePDA, isSigner: false, isWritable: true },
           { pubkey: curveTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: vaultPDA, isSigner: false, isWritable: true },
+          { pubkey: creatorFeeVaultPDA, isSigner: false, isWritable: true },
+          { pubkey: launchPDA, isSigner: false, isWritable: true },
           { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
           {
             pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -274,6 +386,7 @@ export class LaunchpadClient {
             isSigner: false,
             isWritable: false,
           },
+          { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
         ],
         programId: this.programId,
         data,
@@ -292,6 +405,8 @@ export class LaunchpadClient {
     const [configPDA] = this.getConfigPDA()
     const [bondingCurvePDA] = this.getBondingCurvePDA(tokenMint)
     const [vaultPDA] = this.getVaultPDA(tokenMint)
+    const [protocolFeeVaultPDA] = this.getProtocolFeeVaultPDA()
+    const [creatorFeeVaultPDA] = this.getCreatorFeeVaultPDA(tokenMint)
 
     const curveTokenAccount = await getAssociatedTokenAddress(
       tokenMint,
@@ -301,12 +416,6 @@ export class LaunchpadClient {
 
     const buyerTokenAccount = await getAssociatedTokenAddress(tokenMint, buyer)
 
-    const config = await this.getConfig()
-    if (!config) throw new Error('Launchpad not initialized')
-
-    const curve = await this.getBondingCurve(tokenMint)
-    if (!curve) throw new Error('Bonding curve not found')
-
     const instructions: TransactionInstruction[] = []
 
     // Create buyer token account if needed
@@ -334,13 +443,13 @@ export class LaunchpadClient {
       new TransactionInstruction({
         keys: [
           { pubkey: buyer, isSigner: true, isWritable: true },
-          { pubkey: configPDA, isSigner: false, isWritable: false },
+          { pubkey: configPDA, isSigner: false, isWritable: true },
           { pubkey: bondingCurvePDA, isSigner: false, isWritable: true },
           { pubkey: curveTokenAccount, isSigner: false, isWritable: true },
           { pubkey: buyerTokenAccount, isSigner: false, isWritable: true },
           { pubkey: vaultPDA, isSigner: false, isWritable: true },
-          { pubkey: config.feeRecipient, isSigner: false, isWritable: true },
-          { pubkey: curve.creator, isSigner: false, isWritable: true },
+          { pubkey: protocolFeeVaultPDA, isSigner: false, isWritable: true },
+          { pubkey: creatorFeeVaultPDA, isSigner: false, isWritable: true },
           { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
           {
             pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -361,6 +470,70 @@ export class LaunchpadClient {
     return instructions
   }
 
+  async buyWithReferralInstructions(
+    tokenMint: PublicKey,
+    buyer: PublicKey,
+    referrer: PublicKey,
+    solAmount: bigint,
+    minTokensOut: bigint,
+  ): Promise<TransactionInstruction[]> {
+    const instructions = await this.buyInstructions(
+      tokenMint,
+      buyer,
+      solAmount,
+      minTokensOut,
+    )
+
+    // Same accounts and args as buy, plus the referrer
+    const buy = instructions[instructions.length - 1]
+    const data = Buffer.from(buy.data)
+    // Discriminator for buy_with_referral
+    Buffer.from([0x59, 0x71, 0xec, 0x15, 0x24, 0x8d, 0xc3, 0xd3]).copy(data, 0)
+
+    instructions[instructions.length - 1] = new TransactionInstruction({
+      keys: [
+        ...buy.keys,
+        { pubkey: referrer, isSigner: false, isWritable: true },
+      ],
+      programId: this.programId,
+      data,
+    })
+
+    return instructions
+  }
+
+  /**
+   * Spend exactly `solAmount`, failing if the average execution price is
+   * above `maxPrice` (lamports per token scaled by 1e6, like the curve price)
+   */
+  async buyExactSolInstructions(
+    tokenMint: PublicKey,
+    buyer: PublicKey,
+    solAmount: bigint,
+    maxPrice: bigint,
+  ): Promise<TransactionInstruction[]> {
+    const instructions = await this.buyInstructions(
+      tokenMint,
+      buyer,
+      solAmount,
+      maxPrice,
+    )
+
+    // Same accounts and args layout as buy
+    const buy = instructions[instructions.length - 1]
+    const data = Buffer.from(buy.data)
+    // Discriminator for buy_exact_sol
+    Buffer.from([0x96, 0xaf, 0x0a, 0x37, 0x24, 0x3e, 0x37, 0x43]).copy(data, 0)
+
+    instructions[instructions.length - 1] = new TransactionInstruction({
+      keys: buy.keys,
+      programId: this.programId,
+      data,
+    })
+
+    return instructions
+  }
+
   async sellInstructions(
     tokenMint: PublicKey,
     seller: PublicKey,
@@ -370,6 +543,8 @@ export class LaunchpadClient {
     const [configPDA] = this.getConfigPDA()
     const [bondingCurvePDA] = this.getBondingCurvePDA(tokenMint)
     const [vaultPDA] = this.getVaultPDA(tokenMint)
+    const [protocolFeeVaultPDA] = this.getProtocolFeeVaultPDA()
+    const [creatorFeeVaultPDA] = this.getCreatorFeeVaultPDA(tokenMint)
 
     const curveTokenAccount = await getAssociatedTokenAddress(
       tokenMint,
@@ -393,11 +568,13 @@ export class LaunchpadClient {
       new TransactionInstruction({
         keys: [
           { pubkey: seller, isSigner: true, isWritable: true },
-          { pubkey: configPDA, isSigner: false, isWritable: false },
+          { pubkey: configPDA, isSigner: false, isWritable: true },
           { pubkey: bondingCurvePDA, isSigner: false, isWritable: true },
           { pubkey: curveTokenAccount, isSigner: false, isWritable: true },
           { pubkey: sellerTokenAccount, isSigner: false, isWritable: true },
           { pubkey: vaultPDA, isSigner: false, isWritable: true },
+          { pubkey: protocolFeeVaultPDA, isSigner: false, isWritable: true },
+          { pubkey: creatorFeeVaultPDA, isSigner: false, isWritable: true },
           { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
           {
             pubkey: SystemProgram.programId,
@@ -411,6 +588,64 @@ export class LaunchpadClient {
     ]
   }
 
+  claimCreatorFeesInstructions(
+    tokenMint: PublicKey,
+    creator: PublicKey,
+  ): TransactionInstruction[] {
+    const [bondingCurvePDA] = this.getBondingCurvePDA(tokenMint)
+    const [creatorFeeVaultPDA] = this.getCreatorFeeVaultPDA(tokenMint)
+
+    // Discriminator for claim_creator_fees
+    const data = Buffer.from([0x00, 0x17, 0x7d, 0xea, 0x9c, 0x76, 0x86, 0x59])
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: creator, isSigner: true, isWritable: true },
+          { pubkey: bondingCurvePDA, isSigner: false, isWritable: true },
+          { pubkey: creatorFeeVaultPDA, isSigner: false, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  /** Set or clear (null) a token's platform fee override (authority only) */
+  setPlatformFeeOverrideInstructions(
+    tokenMint: PublicKey,
+    authority: PublicKey,
+    platformFeeBps: number | null,
+  ): TransactionInstruction[] {
+    const [configPDA] = this.getConfigPDA()
+    const [bondingCurvePDA] = this.getBondingCurvePDA(tokenMint)
+
+    const data = Buffer.alloc(8 + 1 + (platformFeeBps === null ? 0 : 2))
+    // Discriminator for set_platform_fee_override
+    Buffer.from([0xf4, 0x4a, 0xb6, 0x86, 0xd5, 0xcd, 0x16, 0xfe]).copy(data, 0)
+    if (platformFeeBps !== null) {
+      data.writeUInt8(1, 8)
+      data.writeUInt16LE(platformFeeBps, 9)
+    }
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: authority, isSigner: true, isWritable: false },
+          { pubkey: configPDA, isSigner: false, isWritable: false },
+          { pubkey: bondingCurvePDA, isSigner: false, isWritable: true },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   // Bonding Curve Calculations
 
   calculateBuyAmount(curve: BondingCurve, solAmount: bigint): bigint {
@@ -494,6 +729,7 @@ export class LaunchpadClient {
     contributor: PublicKey,
     amount: bigint,
   ): Promise<TransactionInstruction[]> {
+    const [configPDA] = this.getConfigPDA()
     const [presalePDA] = this.getPresalePDA(tokenMint)
     const [vaultPDA] = this.getVaultPDA(tokenMint)
     const [contributionPDA] = this.getContributionPDA(presalePDA, contributor)
@@ -507,6 +743,7 @@ export class LaunchpadClient {
       new TransactionInstruction({
         keys: [
           { pubkey: contributor, isSigner: true, isWritable: true },
+          { pubkey: configPDA, isSigner: false, isWritable: false },
           { pubkey: presalePDA, isSigner: false, isWritable: true },
           { pubkey: contributionPDA, isSigner: false, isWritable: true },
           { pubkey: vaultPDA, isSigner: false, isWritable: true },
@@ -522,6 +759,95 @@ export class LaunchpadClient {
     ]
   }
 
+  // Creator Operations
+
+  createCreatorProfileInstructions(
+    creator: PublicKey,
+  ): TransactionInstruction[] {
+    const [profilePDA] = this.getCreatorProfilePDA(creator)
+
+    // Discriminator for create_creator_profile
+    const data = Buffer.from([0x8b, 0xf4, 0x7f, 0x91, 0x5f, 0xac, 0x8c, 0x9a])
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: creator, isSigner: true, isWritable: true },
+          { pubkey: profilePDA, isSigner: false, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  /** Add (or with `untrack`, remove) a presale from the creator's profile */
+  trackPresaleInstructions(
+    tokenMint: PublicKey,
+    creator: PublicKey,
+    untrack = false,
+  ): TransactionInstruction[] {
+    const [profilePDA] = this.getCreatorProfilePDA(creator)
+    const [presalePDA] = this.getPresalePDA(tokenMint)
+
+    // Discriminator for track_presale / untrack_presale
+    const data = untrack
+      ? Buffer.from([0x3f, 0x66, 0xd1, 0xab, 0xf0, 0xf3, 0x71, 0x80])
+      : Buffer.from([0x4b, 0xdd, 0xd8, 0x9f, 0xce, 0x5b, 0xb2, 0x0e])
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: creator, isSigner: true, isWritable: false },
+          { pubkey: profilePDA, isSigner: false, isWritable: true },
+          { pubkey: presalePDA, isSigner: false, isWritable: true },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  /**
+   * Pause (or resume) contributions to every presale in the creator's
+   * profile. Settled presales are skipped; refunds and claims keep working.
+   */
+  async setCreatorPresalesPausedInstructions(
+    creator: PublicKey,
+    paused: boolean,
+  ): Promise<TransactionInstruction[]> {
+    const profile = await this.getCreatorProfile(creator)
+    if (!profile) throw new Error('Creator profile not found')
+    const [profilePDA] = this.getCreatorProfilePDA(creator)
+
+    // Discriminator for pause_creator_presales / resume_creator_presales
+    const data = paused
+      ? Buffer.from([0x56, 0x9e, 0xe0, 0xdb, 0xf2, 0x55, 0xea, 0xbe])
+      : Buffer.from([0xb8, 0x1f, 0x22, 0x87, 0xab, 0x5e, 0x32, 0xff])
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: creator, isSigner: true, isWritable: false },
+          { pubkey: profilePDA, isSigner: false, isWritable: true },
+          // Every tracked presale, in profile order
+          ...profile.presales.map((presale) => ({
+            pubkey: presale,
+            isSigner: false,
+            isWritable: true,
+          })),
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   // Deserialization Helpers
 
   private deserializeConfig(data: Buffer): LaunchpadConfig {
@@ -538,12 +864,30 @@ export class LaunchpadClient {
     offset += 2
 
     const totalLaunches = data.readBigUInt64LE(offset)
+    offset += 8
+
+    // Skip bump
+    offset += 1
+
+    const protocolFeesClaimed = data.readBigUInt64LE(offset)
+    offset += 8
+
+    // Skip protocol fee vault bump
+    offset += 1
+
+    const referralFeeBps = data.readUInt16LE(offset)
+    offset += 2
+
+    const paused = data.readUInt8(offset) === 1
 
     return {
       authority,
       feeRecipient,
       platformFeeBps,
       totalLaunches,
+      protocolFeesClaimed,
+      referralFeeBps,
+      paused,
     }
   }
 
@@ -581,6 +925,25 @@ export class LaunchpadClient {
     offset += 1
 
     const createdAt = data.readBigInt64LE(offset)
+    offset += 8
+
+    // Skip bump and vault_bump
+    offset += 2
+
+    const feesAccrued = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const feesClaimed = data.readBigUInt64LE(offset)
+    offset += 8
+
+    // Skip creator fee vault bump
+    offset += 1
+
+    const platformFeeOverride =
+      data.readUInt8(offset) === 1 ? data.readUInt16LE(offset + 1) : null
+    offset += platformFeeOverride === null ? 1 : 3
+
+    const referralFeesPaid = data.readBigUInt64LE(offset)
 
     return {
       creator,
@@ -594,6 +957,10 @@ export class LaunchpadClient {
       creatorFeeBps,
       graduated,
       createdAt,
+      feesAccrued,
+      feesClaimed,
+      platformFeeOverride,
+      referralFeesPaid,
     }
   }
 
@@ -643,6 +1010,12 @@ export class LaunchpadClient {
     offset += 1
 
     const finalizedAt = data.readBigInt64LE(offset)
+    offset += 8
+
+    // Skip bump and vault_bump
+    offset += 2
+
+    const paused = data.readUInt8(offset) === 1
 
     return {
       creator,
@@ -660,7 +1033,28 @@ export class LaunchpadClient {
       finalized,
       cancelled,
       finalizedAt,
+      paused,
+    }
+  }
+
+  private deserializeCreatorProfile(data: Buffer): CreatorProfile {
+    let offset = 8 // Skip discriminator
+
+    const creator = new PublicKey(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const count = data.readUInt32LE(offset)
+    offset += 4
+
+    const presales: PublicKey[] = []
+    for (let i = 0; i < count; i++) {
+      presales.push(new PublicKey(data.subarray(offset, offset + 32)))
+      offset += 32
     }
+
+    const paused = data.readUInt8(offset) === 1
+
+    return { creator, presales, paused }
   }
 
   private deserializeContribution(data: Buffer): Contribution {
@@ -688,6 +1082,28 @@ export class LaunchpadClient {
       claimed,
     }
   }
+
+  private deserializeLaunch(data: Buffer): LaunchRecord {
+    let offset = 8 // Skip discriminator
+
+    const index = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const tokenMint = new PublicKey(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const creator = new PublicKey(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const createdAt = data.readBigInt64LE(offset)
+
+    return {
+      index,
+      tokenMint,
+      creator,
+      createdAt,
+    }
+  }
 }
 
 export function createLaunchpadClient(
@@ -11,12 +11,14 @@ export const EVM_LIGHT_CLIENT_PROGRAM_ID = new PublicKey(
 )
 
 const STATE_SEED = Buffer.from('evm_light_client')
+const PROOF_CACHE_SEED = Buffer.from('proof_cache')
 
 // Groth16 proof size (256 bytes: 2x G1 + 1x G2)
 export const GROTH16_PROOF_SIZE = 256
 
 export interface LightClientState {
   admin: PublicKey
+  chainId: bigint
   latestSlot: bigint
   latestBlockRoot: Uint8Array
   latestStateRoot: Uint8Array
@@ -24,6 +26,23 @@ export interface LightClientState {
   nextSyncCommitteeRoot: Uint8Array
   updateCount: bigint
   initialized: boolean
+  permissionedMode: boolean
+  authorizedRelayer: PublicKey
+  /** Seconds a staged update waits before finalizing; 0 applies updates immediately */
+  challengePeriod: bigint
+  pendingState: PendingUpdate | null
+}
+
+/** Proven update staged in optimistic mode, not yet used for proofs */
+export interface PendingUpdate {
+  slot: bigint
+  blockRoot: Uint8Array
+  stateRoot: Uint8Array
+  /** Sync committees the state holds once the update is applied */
+  currentSyncCommitteeRoot: Uint8Array
+  nextSyncCommitteeRoot: Uint8Array
+  proposer: PublicKey
+  finalizeAt: bigint
 }
 
 export interface LatestState {
@@ -44,8 +63,19 @@ export interface UpdateStateParams {
   newSlot: bigint
   newBlockRoot: Uint8Array
   newStateRoot: Uint8Array
+  /** Expected next sync committee root; must match the one the proof attests */
   newSyncCommitteeRoot?: Uint8Array
   proof: Uint8Array
+  /** Canonical 180-byte encoding of the circuit outputs; the proof commits its keccak digest */
+  publicInputs: Uint8Array
+}
+
+/** A proof of a different state at the pending update's slot */
+export interface ChallengeUpdateParams {
+  slot: bigint
+  blockRoot: Uint8Array
+  stateRoot: Uint8Array
+  proof: Uint8Array
   publicInputs: Uint8Array
 }
 
@@ -56,17 +86,69 @@ export interface VerifyProofParams {
   proofData: Uint8Array
 }
 
+/** Proof that a contract's bytecode hashes to `expectedCodeHash` */
+export interface VerifyCodeHashParams {
+  account: Uint8Array
+  expectedCodeHash: Uint8Array
+  /** Serialized account proof (see `serializeProofNodes`) */
+  proofData: Uint8Array
+}
+
+/** State account tracking EVM chain `chainId`; there is one per chain */
+export function getLightClientStatePDA(
+  chainId: bigint,
+  programId: PublicKey = EVM_LIGHT_CLIENT_PROGRAM_ID,
+): [PublicKey, number] {
+  const chainIdBytes = Buffer.alloc(8)
+  chainIdBytes.writeBigUInt64LE(chainId)
+  return PublicKey.findProgramAddressSync([STATE_SEED, chainIdBytes], programId)
+}
+
+/** Cache of account storage roots proven against chain `chainId`'s state roots */
+export function getProofCachePDA(
+  chainId: bigint,
+  programId: PublicKey = EVM_LIGHT_CLIENT_PROGRAM_ID,
+): [PublicKey, number] {
+  const chainIdBytes = Buffer.alloc(8)
+  chainIdBytes.writeBigUInt64LE(chainId)
+  return PublicKey.findProgramAddressSync(
+    [PROOF_CACHE_SEED, chainIdBytes],
+    programId,
+  )
+}
+
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey(
+  'BPFLoaderUpgradeab1e11111111111111111111111',
+)
+
+/** The program's ProgramData account, which names its upgrade authority */
+export function getProgramDataAddress(
+  programId: PublicKey = EVM_LIGHT_CLIENT_PROGRAM_ID,
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_ID,
+  )[0]
+}
+
+/** Client for the light client instance tracking one EVM chain */
 export class EVMLightClientClient {
   private connection: Connection
+  private chainId: bigint
   private programId: PublicKey
 
-  constructor(connection: Connection, programId?: PublicKey) {
+  constructor(connection: Connection, chainId: bigint, programId?: PublicKey) {
     this.connection = connection
+    this.chainId = chainId
     this.programId = programId ?? EVM_LIGHT_CLIENT_PROGRAM_ID
   }
 
   getStatePDA(): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync([STATE_SEED], this.programId)
+    return getLightClientStatePDA(this.chainId, this.programId)
+  }
+
+  getProofCachePDA(): [PublicKey, number] {
+    return getProofCachePDA(this.chainId, this.programId)
   }
 
   async getState(): Promise<LightClientState | null> {
@@ -96,6 +178,7 @@ export class EVMLightClientClient {
     return state.initialized
   }
 
+  /** `admin` must be the program's upgrade authority */
   async initializeInstructions(
     params: InitializeParams,
     admin: PublicKey,
@@ -109,6 +192,11 @@ export class EVMLightClientClient {
         keys: [
           { pubkey: statePDA, isSigner: false, isWritable: true },
           { pubkey: admin, isSigner: true, isWritable: true },
+          {
+            pubkey: getProgramDataAddress(this.programId),
+            isSigner: false,
+            isWritable: false,
+          },
           {
             pubkey: SystemProgram.programId,
             isSigner: false,
@@ -141,6 +229,44 @@ export class EVMLightClientClient {
     ]
   }
 
+  async challengeUpdateInstructions(
+    params: ChallengeUpdateParams,
+    challenger: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const [statePDA] = this.getStatePDA()
+
+    const data = this.buildChallengeUpdateData(params)
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: statePDA, isSigner: false, isWritable: true },
+          { pubkey: challenger, isSigner: true, isWritable: false },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  async finalizeUpdateInstructions(): Promise<TransactionInstruction[]> {
+    const [statePDA] = this.getStatePDA()
+
+    // Discriminator (8) + chainId (8)
+    const data = Buffer.alloc(8 + 8)
+    // Discriminator for finalize_update
+    Buffer.from([0x64, 0x6f, 0xaf, 0x4e, 0x7a, 0xde, 0xb4, 0x59]).copy(data, 0)
+    data.writeBigUInt64LE(this.chainId, 8)
+
+    return [
+      new TransactionInstruction({
+        keys: [{ pubkey: statePDA, isSigner: false, isWritable: true }],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   async verifyProofInstructions(
     params: VerifyProofParams,
   ): Promise<TransactionInstruction[]> {
@@ -157,6 +283,81 @@ export class EVMLightClientClient {
     ]
   }
 
+  async initProofCacheInstructions(
+    payer: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const [statePDA] = this.getStatePDA()
+    const [proofCachePDA] = this.getProofCachePDA()
+
+    const data = Buffer.alloc(16)
+    // Discriminator for init_proof_cache
+    Buffer.from([0xa1, 0x6b, 0x39, 0xc7, 0x76, 0x96, 0x4b, 0x21]).copy(data, 0)
+    data.writeBigUInt64LE(this.chainId, 8)
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: statePDA, isSigner: false, isWritable: false },
+          { pubkey: proofCachePDA, isSigner: false, isWritable: true },
+          { pubkey: payer, isSigner: true, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  /**
+   * Like `verifyProofInstructions`, but for an account already proven against
+   * the current state root the proof cache supplies its storage root and only
+   * the storage half of the proof is checked. Requires
+   * `initProofCacheInstructions` to have run once.
+   */
+  async verifyProofCachedInstructions(
+    params: VerifyProofParams,
+  ): Promise<TransactionInstruction[]> {
+    const [statePDA] = this.getStatePDA()
+    const [proofCachePDA] = this.getProofCachePDA()
+
+    // Discriminator for verify_account_proof_cached
+    const data = this.buildVerifyProofData(
+      params,
+      Buffer.from([0x29, 0x4d, 0xe9, 0xed, 0x41, 0xff, 0xfc, 0xde]),
+    )
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: statePDA, isSigner: false, isWritable: false },
+          { pubkey: proofCachePDA, isSigner: false, isWritable: true },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  async verifyCodeHashInstructions(
+    params: VerifyCodeHashParams,
+  ): Promise<TransactionInstruction[]> {
+    const [statePDA] = this.getStatePDA()
+
+    const data = this.buildVerifyCodeHashData(params)
+
+    return [
+      new TransactionInstruction({
+        keys: [{ pubkey: statePDA, isSigner: false, isWritable: false }],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   serializeProofNodes(nodes: Uint8Array[]): Uint8Array {
     let totalSize = 2 // num_nodes
     for (const node of nodes) {
@@ -180,8 +381,8 @@ export class EVMLightClientClient {
   }
 
   private buildInitializeData(params: InitializeParams): Buffer {
-    // Discriminator (8 bytes) + genesisSlot (8) + genesisBlockRoot (32) + genesisStateRoot (32) + syncCommitteeRoot (32)
-    const data = Buffer.alloc(8 + 8 + 32 + 32 + 32)
+    // Discriminator (8 bytes) + chainId (8) + genesisSlot (8) + genesisBlockRoot (32) + genesisStateRoot (32) + syncCommitteeRoot (32)
+    const data = Buffer.alloc(8 + 8 + 8 + 32 + 32 + 32)
     let offset = 0
 
     // Discriminator for initialize
@@ -191,6 +392,9 @@ export class EVMLightClientClient {
     )
     offset += 8
 
+    data.writeBigUInt64LE(this.chainId, offset)
+    offset += 8
+
     data.writeBigUInt64LE(params.genesisSlot, offset)
     offset += 8
 
@@ -208,9 +412,10 @@ export class EVMLightClientClient {
   private buildUpdateStateData(params: UpdateStateParams): Buffer {
     const hasNewCommittee = params.newSyncCommitteeRoot !== undefined
 
-    // Discriminator (8) + newSlot (8) + newBlockRoot (32) + newStateRoot (32) +
+    // Discriminator (8) + chainId (8) + newSlot (8) + newBlockRoot (32) + newStateRoot (32) +
     // option flag (1) + [newSyncCommitteeRoot (32)] + proof (256) + publicInputs length (4) + publicInputs
     const dataSize =
+      8 +
       8 +
       8 +
       32 +
@@ -230,6 +435,9 @@ export class EVMLightClientClient {
     )
     offset += 8
 
+    data.writeBigUInt64LE(this.chainId, offset)
+    offset += 8
+
     data.writeBigUInt64LE(params.newSlot, offset)
     offset += 8
 
@@ -262,19 +470,61 @@ export class EVMLightClientClient {
     return data
   }
 
-  private buildVerifyProofData(params: VerifyProofParams): Buffer {
-    // Discriminator (8) + account (20) + storageSlot (32) + expectedValue (32) + proofData length (4) + proofData
-    const dataSize = 8 + 20 + 32 + 32 + 4 + params.proofData.length
-    const data = Buffer.alloc(dataSize)
+  private buildChallengeUpdateData(params: ChallengeUpdateParams): Buffer {
+    // Discriminator (8) + chainId (8) + slot (8) + blockRoot (32) +
+    // stateRoot (32) + proof (256) + publicInputs length (4) + publicInputs
+    const data = Buffer.alloc(
+      8 + 8 + 8 + 32 + 32 + GROTH16_PROOF_SIZE + 4 + params.publicInputs.length,
+    )
     let offset = 0
 
-    // Discriminator for verify_account_proof
-    Buffer.from([0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88]).copy(
+    // Discriminator for challenge_update
+    Buffer.from([0x64, 0x6b, 0xae, 0x37, 0x5e, 0xba, 0x9c, 0xb2]).copy(
       data,
       offset,
     )
     offset += 8
 
+    data.writeBigUInt64LE(this.chainId, offset)
+    offset += 8
+
+    data.writeBigUInt64LE(params.slot, offset)
+    offset += 8
+
+    Buffer.from(params.blockRoot).copy(data, offset)
+    offset += 32
+
+    Buffer.from(params.stateRoot).copy(data, offset)
+    offset += 32
+
+    Buffer.from(params.proof).copy(data, offset)
+    offset += GROTH16_PROOF_SIZE
+
+    data.writeUInt32LE(params.publicInputs.length, offset)
+    offset += 4
+    Buffer.from(params.publicInputs).copy(data, offset)
+
+    return data
+  }
+
+  private buildVerifyProofData(
+    params: VerifyProofParams,
+    // Discriminator for verify_account_proof
+    discriminator = Buffer.from([
+      0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88,
+    ]),
+  ): Buffer {
+    // Discriminator (8) + chainId (8) + account (20) + storageSlot (32) + expectedValue (32) + proofData length (4) + proofData
+    const dataSize = 8 + 8 + 20 + 32 + 32 + 4 + params.proofData.length
+    const data = Buffer.alloc(dataSize)
+    let offset = 0
+
+    discriminator.copy(data, offset)
+    offset += 8
+
+    data.writeBigUInt64LE(this.chainId, offset)
+    offset += 8
+
     Buffer.from(params.account).copy(data, offset)
     offset += 20
 
@@ -291,12 +541,43 @@ export class EVMLightClientClient {
     return data
   }
 
+  private buildVerifyCodeHashData(params: VerifyCodeHashParams): Buffer {
+    // Discriminator (8) + chainId (8) + account (20) + expectedCodeHash (32) + proofData length (4) + proofData
+    const data = Buffer.alloc(8 + 8 + 20 + 32 + 4 + params.proofData.length)
+    let offset = 0
+
+    // Discriminator for verify_code_hash
+    Buffer.from([0x71, 0x53, 0xbe, 0x24, 0x98, 0xcc, 0xa4, 0xe7]).copy(
+      data,
+      offset,
+    )
+    offset += 8
+
+    data.writeBigUInt64LE(this.chainId, offset)
+    offset += 8
+
+    Buffer.from(params.account).copy(data, offset)
+    offset += 20
+
+    Buffer.from(params.expectedCodeHash).copy(data, offset)
+    offset += 32
+
+    data.writeUInt32LE(params.proofData.length, offset)
+    offset += 4
+    Buffer.from(params.proofData).copy(data, offset)
+
+    return data
+  }
+
   private deserializeState(data: Buffer): LightClientState {
     let offset = 8 // Skip discriminator
 
     const admin = new PublicKey(data.subarray(offset, offset + 32))
     offset += 32
 
+    const chainId = data.readBigUInt64LE(offset)
+    offset += 8
+
     const latestSlot = data.readBigUInt64LE(offset)
     offset += 8
 
@@ -320,9 +601,26 @@ export class EVMLightClientClient {
     offset += 8
 
     const initialized = data.readUInt8(offset) === 1
+    offset += 1
+
+    const permissionedMode = data.readUInt8(offset) === 1
+    offset += 1
+
+    const authorizedRelayer = new PublicKey(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const challengePeriod = data.readBigInt64LE(offset)
+    offset += 8
+
+    let pendingState: PendingUpdate | null = null
+    if (data.readUInt8(offset) === 1) {
+      offset += 1
+      pendingState = this.deserializePendingUpdate(data, offset)
+    }
 
     return {
       admin,
+      chainId,
       latestSlot,
       latestBlockRoot,
       latestStateRoot,
@@ -330,13 +628,59 @@ export class EVMLightClientClient {
       nextSyncCommitteeRoot,
       updateCount,
       initialized,
+      permissionedMode,
+      authorizedRelayer,
+      challengePeriod,
+      pendingState,
+    }
+  }
+
+  private deserializePendingUpdate(
+    data: Buffer,
+    start: number,
+  ): PendingUpdate {
+    let offset = start
+
+    const slot = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const blockRoot = new Uint8Array(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const stateRoot = new Uint8Array(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const currentSyncCommitteeRoot = new Uint8Array(
+      data.subarray(offset, offset + 32),
+    )
+    offset += 32
+
+    const nextSyncCommitteeRoot = new Uint8Array(
+      data.subarray(offset, offset + 32),
+    )
+    offset += 32
+
+    const proposer = new PublicKey(data.subarray(offset, offset + 32))
+    offset += 32
+
+    const finalizeAt = data.readBigInt64LE(offset)
+
+    return {
+      slot,
+      blockRoot,
+      stateRoot,
+      currentSyncCommitteeRoot,
+      nextSyncCommitteeRoot,
+      proposer,
+      finalizeAt,
     }
   }
 }
 
 export function createEVMLightClientClient(
   connection: Connection,
+  chainId: bigint,
   programId?: PublicKey,
 ): EVMLightClientClient {
-  return new EVMLightClientClient(connection, programId)
+  return new EVMLightClientClient(connection, chainId, programId)
 }
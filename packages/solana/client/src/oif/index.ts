@@ -2,6 +2,7 @@ import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   createAssociatedTokenAccountInstruction,
   getAssociatedTokenAddress,
+  TOKEN_2022_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
 } from '@solana/spl-token'
 import {
@@ -20,6 +21,7 @@ const CONFIG_SEED = Buffer.from('config')
 const INTENT_SEED = Buffer.from('intent')
 const SOLVER_SEED = Buffer.from('solver')
 const STAKE_VAULT_SEED = Buffer.from('stake-vault')
+const EARNINGS_SEED = Buffer.from('earnings')
 
 export const CHAIN_IDS = {
   SOLANA_MAINNET: 1399811149,
@@ -61,6 +63,9 @@ export interface Intent {
   status: IntentStatus
   createdAt: bigint
   filledAt: bigint
+  minFillAmount: bigint
+  solverTip: bigint
+  tipPaid: boolean
 }
 
 export interface Solver {
@@ -72,6 +77,34 @@ export interface Solver {
   reputationScore: bigint
   active: boolean
   registeredAt: bigint
+  /** Fills credit a per-mint earnings ledger instead of paying out */
+  deferPayout: boolean
+  /** Disputes resolved against the solver by a slash */
+  disputes: bigint
+  /** Sum over fills of seconds from intent creation to fill */
+  totalFillLatency: bigint
+  /** Time of the most recent slash; 0 if never slashed */
+  lastSlashedAt: bigint
+}
+
+/** Rates derived from a solver's counters, as `get_solver_stats` returns */
+export interface SolverStats {
+  intentsFilled: bigint
+  disputes: bigint
+  /** Fills out of fills plus disputes, in basis points; 0 with no history */
+  successRateBps: number
+  /** Mean seconds from intent creation to fill; 0 with no fills */
+  avgFillLatency: bigint
+  reputationScore: bigint
+  totalVolume: bigint
+  active: boolean
+}
+
+export interface SolverEarnings {
+  solver: PublicKey
+  mint: PublicKey
+  accrued: bigint
+  claimed: bigint
 }
 
 export interface CreateIntentParams {
@@ -84,6 +117,10 @@ export interface CreateIntentParams {
   recipient: string | Uint8Array
   expiry?: bigint
   partialFillAllowed?: boolean
+  /** Smallest fill a solver may make, other than the final remainder */
+  minFillAmount?: bigint
+  /** Escrowed on top of sourceAmount for the solver that completes the intent */
+  solverTip?: bigint
 }
 
 export interface FillIntentParams {
@@ -123,6 +160,25 @@ export class OIFClient {
     return PublicKey.findProgramAddressSync([STAKE_VAULT_SEED], this.programId)
   }
 
+  getSolverEarningsPDA(
+    solver: PublicKey,
+    mint: PublicKey,
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [EARNINGS_SEED, solver.toBuffer(), mint.toBuffer()],
+      this.programId,
+    )
+  }
+
+  /** Shared vault holding deferred solver earnings in one mint */
+  async getEarningsVault(
+    mint: PublicKey,
+    tokenProgram: PublicKey,
+  ): Promise<PublicKey> {
+    const [configPDA] = this.getConfigPDA()
+    return getAssociatedTokenAddress(mint, configPDA, true, tokenProgram)
+  }
+
   async getConfig(): Promise<OIFConfig | null> {
     const [configPDA] = this.getConfigPDA()
     const accountInfo = await this.connection.getAccountInfo(configPDA)
@@ -144,6 +200,46 @@ export class OIFClient {
     return this.deserializeSolver(accountInfo.data)
   }
 
+  async getSolverStats(owner: PublicKey): Promise<SolverStats | null> {
+    const solver = await this.getSolver(owner)
+    if (!solver) return null
+
+    const outcomes = solver.intentsFilled + solver.disputes
+    return {
+      intentsFilled: solver.intentsFilled,
+      disputes: solver.disputes,
+      successRateBps:
+        outcomes === 0n
+          ? 0
+          : Number((solver.intentsFilled * 10_000n) / outcomes),
+      avgFillLatency:
+        solver.intentsFilled === 0n
+          ? 0n
+          : solver.totalFillLatency / solver.intentsFilled,
+      reputationScore: solver.reputationScore,
+      totalVolume: solver.totalVolume,
+      active: solver.active,
+    }
+  }
+
+  async getSolverEarnings(
+    owner: PublicKey,
+    mint: PublicKey,
+  ): Promise<SolverEarnings | null> {
+    const [solverPDA] = this.getSolverPDA(owner)
+    const [earningsPDA] = this.getSolverEarningsPDA(solverPDA, mint)
+    const accountInfo = await this.connection.getAccountInfo(earningsPDA)
+    if (!accountInfo) return null
+
+    const data = accountInfo.data
+    return {
+      solver: new PublicKey(data.subarray(8, 40)),
+      mint: new PublicKey(data.subarray(40, 72)),
+      accrued: data.readBigUInt64LE(72),
+      claimed: data.readBigUInt64LE(80),
+    }
+  }
+
   async getOpenIntents(limit: number = 100): Promise<Intent[]> {
     const accounts = await this.connection.getProgramAccounts(this.programId, {
       filters: [{ dataSize: 220 }],
@@ -176,6 +272,15 @@ export class OIFClient {
     return solvers
   }
 
+  /** SPL Token or Token-2022, whichever owns the mint */
+  async getTokenProgram(mint: PublicKey): Promise<PublicKey> {
+    const mintInfo = await this.connection.getAccountInfo(mint)
+    if (!mintInfo) throw new Error('Mint not found')
+    return mintInfo.owner.equals(TOKEN_2022_PROGRAM_ID)
+      ? TOKEN_2022_PROGRAM_ID
+      : TOKEN_PROGRAM_ID
+  }
+
   generateIntentId(): Uint8Array {
     return crypto.getRandomValues(new Uint8Array(32))
   }
@@ -198,15 +303,20 @@ export class OIFClient {
         ? this.addressToBytes(params.recipient)
         : params.recipient
 
+    const tokenProgram = await this.getTokenProgram(params.sourceToken)
+
     const creatorTokenAccount = await getAssociatedTokenAddress(
       params.sourceToken,
       creator,
+      false,
+      tokenProgram,
     )
 
     const escrowTokenAccount = await getAssociatedTokenAddress(
       params.sourceToken,
       intentPDA,
       true,
+      tokenProgram,
     )
 
     const data = this.buildCreateIntentData({
@@ -220,6 +330,8 @@ export class OIFClient {
       recipient,
       expiry: params.expiry ?? BigInt(0),
       partialFillAllowed: params.partialFillAllowed ?? true,
+      minFillAmount: params.minFillAmount ?? BigInt(0),
+      solverTip: params.solverTip ?? BigInt(0),
     })
 
     const instructions: TransactionInstruction[] = []
@@ -233,6 +345,7 @@ export class OIFClient {
           escrowTokenAccount,
           intentPDA,
           params.sourceToken,
+          tokenProgram,
         ),
       )
     }
@@ -246,7 +359,7 @@ export class OIFClient {
           { pubkey: intentPDA, isSigner: false, isWritable: true },
           { pubkey: creatorTokenAccount, isSigner: false, isWritable: true },
           { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
-          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          { pubkey: tokenProgram, isSigner: false, isWritable: false },
           {
             pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
             isSigner: false,
@@ -266,6 +379,85 @@ export class OIFClient {
     return { instructions, intentId }
   }
 
+  /**
+   * Add source tokens to an open intent. A zero (or omitted)
+   * newMinDestinationAmount scales the current minimum proportionally.
+   */
+  async topUpIntentInstructions(
+    intentId: Uint8Array,
+    creator: PublicKey,
+    additionalAmount: bigint,
+    newMinDestinationAmount = BigInt(0),
+  ): Promise<TransactionInstruction[]> {
+    const [intentPDA] = this.getIntentPDA(intentId)
+
+    const intent = await this.getIntent(intentId)
+    if (!intent) throw new Error('Intent not found')
+
+    const tokenProgram = await this.getTokenProgram(intent.sourceToken)
+
+    const escrowTokenAccount = await getAssociatedTokenAddress(
+      intent.sourceToken,
+      intentPDA,
+      true,
+      tokenProgram,
+    )
+
+    const creatorTokenAccount = await getAssociatedTokenAddress(
+      intent.sourceToken,
+      creator,
+      false,
+      tokenProgram,
+    )
+
+    const data = Buffer.alloc(8 + 8 + 8)
+    Buffer.from([0xcc, 0x47, 0xcf, 0x34, 0xfa, 0xd6, 0xba, 0xec]).copy(data, 0)
+    data.writeBigUInt64LE(additionalAmount, 8)
+    data.writeBigUInt64LE(newMinDestinationAmount, 16)
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: creator, isSigner: true, isWritable: false },
+          { pubkey: intentPDA, isSigner: false, isWritable: true },
+          { pubkey: intent.sourceToken, isSigner: false, isWritable: false },
+          { pubkey: creatorTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: tokenProgram, isSigner: false, isWritable: false },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
+  /**
+   * Push an open, unfilled intent's expiry back by extensionSeconds
+   * (at most a day per call, and 7 days from creation in total)
+   */
+  extendIntentInstructions(
+    intentId: Uint8Array,
+    creator: PublicKey,
+    extensionSeconds: bigint,
+  ): TransactionInstruction[] {
+    const [intentPDA] = this.getIntentPDA(intentId)
+
+    const data = Buffer.alloc(8 + 8)
+    Buffer.from([0x73, 0xe9, 0xb0, 0x49, 0x32, 0xcd, 0xb8, 0x31]).copy(data, 0)
+    data.writeBigInt64LE(extensionSeconds, 8)
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: creator, isSigner: true, isWritable: false },
+          { pubkey: intentPDA, isSigner: false, isWritable: true },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   async fillIntentInstructions(
     params: FillIntentParams,
     solver: PublicKey,
@@ -277,15 +469,20 @@ export class OIFClient {
     const intent = await this.getIntent(params.intentId)
     if (!intent) throw new Error('Intent not found')
 
+    const tokenProgram = await this.getTokenProgram(intent.sourceToken)
+
     const escrowTokenAccount = await getAssociatedTokenAddress(
       intent.sourceToken,
       intentPDA,
       true,
+      tokenProgram,
     )
 
     const solverTokenAccount = await getAssociatedTokenAddress(
       intent.sourceToken,
       solver,
+      false,
+      tokenProgram,
     )
 
     const config = await this.getConfig()
@@ -299,15 +496,28 @@ export class OIFClient {
     const data = this.buildFillIntentData(params.fillAmount, destinationTxHash)
     const instructions: TransactionInstruction[] = []
 
-    const solverAccountInfo =
-      await this.connection.getAccountInfo(solverTokenAccount)
-    if (!solverAccountInfo) {
+    // Deferred solvers are credited in the earnings ledger, not paid out
+    const solverAccount = await this.getSolver(solver)
+    const deferred = solverAccount?.deferPayout ?? false
+    const [earningsPDA] = this.getSolverEarningsPDA(
+      solverPDA,
+      intent.sourceToken,
+    )
+    const earningsVault = await this.getEarningsVault(
+      intent.sourceToken,
+      tokenProgram,
+    )
+
+    const needsSolverTokenAccount =
+      !deferred && !(await this.connection.getAccountInfo(solverTokenAccount))
+    if (needsSolverTokenAccount) {
       instructions.push(
         createAssociatedTokenAccountInstruction(
           solver,
           solverTokenAccount,
           solver,
           intent.sourceToken,
+          tokenProgram,
         ),
       )
     }
@@ -318,11 +528,16 @@ export class OIFClient {
           { pubkey: solver, isSigner: true, isWritable: true },
           { pubkey: configPDA, isSigner: false, isWritable: true },
           { pubkey: intentPDA, isSigner: false, isWritable: true },
+          { pubkey: intent.sourceToken, isSigner: false, isWritable: false },
           { pubkey: solverPDA, isSigner: false, isWritable: true },
           { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
-          { pubkey: solverTokenAccount, isSigner: false, isWritable: true },
+          {
+            pubkey: deferred ? this.programId : solverTokenAccount,
+            isSigner: false,
+            isWritable: !deferred,
+          },
           { pubkey: config.authority, isSigner: false, isWritable: true },
-          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          { pubkey: tokenProgram, isSigner: false, isWritable: false },
           {
             pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
             isSigner: false,
@@ -333,6 +548,12 @@ export class OIFClient {
             isSigner: false,
             isWritable: false,
           },
+          ...(deferred
+            ? [
+                { pubkey: earningsPDA, isSigner: false, isWritable: true },
+                { pubkey: earningsVault, isSigner: false, isWritable: true },
+              ]
+            : []),
         ],
         programId: this.programId,
         data,
@@ -342,6 +563,89 @@ export class OIFClient {
     return instructions
   }
 
+  /**
+   * Open a deferred-payout solver's earnings ledger for mint, creating the
+   * shared earnings vault if it does not exist yet
+   */
+  async createSolverEarningsInstructions(
+    owner: PublicKey,
+    mint: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const [configPDA] = this.getConfigPDA()
+    const [solverPDA] = this.getSolverPDA(owner)
+    const [earningsPDA] = this.getSolverEarningsPDA(solverPDA, mint)
+    const tokenProgram = await this.getTokenProgram(mint)
+    const earningsVault = await this.getEarningsVault(mint, tokenProgram)
+
+    const instructions: TransactionInstruction[] = []
+    if (!(await this.connection.getAccountInfo(earningsVault))) {
+      instructions.push(
+        createAssociatedTokenAccountInstruction(
+          owner,
+          earningsVault,
+          configPDA,
+          mint,
+          tokenProgram,
+        ),
+      )
+    }
+
+    instructions.push(
+      new TransactionInstruction({
+        keys: [
+          { pubkey: owner, isSigner: true, isWritable: true },
+          { pubkey: solverPDA, isSigner: false, isWritable: false },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: earningsPDA, isSigner: false, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data: Buffer.from([0xfb, 0x39, 0x4a, 0x9a, 0x98, 0x6d, 0x10, 0xf9]),
+      }),
+    )
+
+    return instructions
+  }
+
+  /** Sweep a solver's unclaimed earnings in mint to its token account */
+  async claimSolverEarningsInstructions(
+    owner: PublicKey,
+    mint: PublicKey,
+  ): Promise<TransactionInstruction[]> {
+    const [configPDA] = this.getConfigPDA()
+    const [solverPDA] = this.getSolverPDA(owner)
+    const [earningsPDA] = this.getSolverEarningsPDA(solverPDA, mint)
+    const tokenProgram = await this.getTokenProgram(mint)
+    const earningsVault = await this.getEarningsVault(mint, tokenProgram)
+    const solverTokenAccount = await getAssociatedTokenAddress(
+      mint,
+      owner,
+      false,
+      tokenProgram,
+    )
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: owner, isSigner: true, isWritable: false },
+          { pubkey: configPDA, isSigner: false, isWritable: false },
+          { pubkey: solverPDA, isSigner: false, isWritable: false },
+          { pubkey: earningsPDA, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: earningsVault, isSigner: false, isWritable: true },
+          { pubkey: solverTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: tokenProgram, isSigner: false, isWritable: false },
+        ],
+        programId: this.programId,
+        data: Buffer.from([0xc2, 0xe6, 0x1b, 0xbe, 0x10, 0xa7, 0x8b, 0x5d]),
+      }),
+    ]
+  }
+
   async cancelIntentInstructions(
     intentId: Uint8Array,
     creator: PublicKey,
@@ -351,15 +655,20 @@ export class OIFClient {
     const intent = await this.getIntent(intentId)
     if (!intent) throw new Error('Intent not found')
 
+    const tokenProgram = await this.getTokenProgram(intent.sourceToken)
+
     const escrowTokenAccount = await getAssociatedTokenAddress(
       intent.sourceToken,
       intentPDA,
       true,
+      tokenProgram,
     )
 
     const creatorTokenAccount = await getAssociatedTokenAddress(
       intent.sourceToken,
       creator,
+      false,
+      tokenProgram,
     )
 
     const discriminator = Buffer.from([
@@ -371,9 +680,10 @@ export class OIFClient {
         keys: [
           { pubkey: creator, isSigner: true, isWritable: false },
           { pubkey: intentPDA, isSigner: false, isWritable: true },
+          { pubkey: intent.sourceToken, isSigner: false, isWritable: false },
           { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
           { pubkey: creatorTokenAccount, isSigner: false, isWritable: true },
-          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          { pubkey: tokenProgram, isSigner: false, isWritable: false },
         ],
         programId: this.programId,
         data: discriminator,
@@ -383,14 +693,19 @@ export class OIFClient {
 
   async registerSolverInstructions(
     owner: PublicKey,
-    _stake: bigint,
+    stake: bigint,
     supportedChains: number[],
+    deferPayout = false,
   ): Promise<TransactionInstruction[]> {
     const [configPDA] = this.getConfigPDA()
     const [solverPDA] = this.getSolverPDA(owner)
     const [stakeVaultPDA] = this.getStakeVaultPDA()
 
-    const data = this.buildRegisterSolverData(supportedChains)
+    const data = this.buildRegisterSolverData(
+      supportedChains,
+      stake,
+      deferPayout,
+    )
 
     return [
       new TransactionInstruction({
@@ -412,6 +727,41 @@ export class OIFClient {
     ]
   }
 
+  /**
+   * Add lamports to a solver's stake, reactivating a slashed solver once
+   * the stake is back above the minimum. Rejected for a day after a slash.
+   */
+  topUpStakeInstructions(
+    owner: PublicKey,
+    amount: bigint,
+  ): TransactionInstruction[] {
+    const [configPDA] = this.getConfigPDA()
+    const [solverPDA] = this.getSolverPDA(owner)
+    const [stakeVaultPDA] = this.getStakeVaultPDA()
+
+    const data = Buffer.alloc(8 + 8)
+    Buffer.from([0x98, 0xde, 0x6e, 0x49, 0x26, 0x12, 0x8b, 0x11]).copy(data, 0)
+    data.writeBigUInt64LE(amount, 8)
+
+    return [
+      new TransactionInstruction({
+        keys: [
+          { pubkey: owner, isSigner: true, isWritable: true },
+          { pubkey: configPDA, isSigner: false, isWritable: false },
+          { pubkey: solverPDA, isSigner: false, isWritable: true },
+          { pubkey: stakeVaultPDA, isSigner: false, isWritable: true },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+        ],
+        programId: this.programId,
+        data,
+      }),
+    ]
+  }
+
   private buildCreateIntentData(params: {
     intentId: Uint8Array
     sourceChain: number
@@ -423,8 +773,12 @@ export class OIFClient {
     recipient: Uint8Array
     expiry: bigint
     partialFillAllowed: boolean
+    minFillAmount: bigint
+    solverTip: bigint
   }): Buffer {
-    const data = Buffer.alloc(8 + 32 + 4 + 4 + 32 + 32 + 8 + 8 + 32 + 8 + 1)
+    const data = Buffer.alloc(
+      8 + 32 + 4 + 4 + 32 + 32 + 8 + 8 + 32 + 8 + 1 + 8 + 8,
+    )
     let offset = 0
 
     Buffer.from([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02]).copy(
@@ -461,6 +815,12 @@ export class OIFClient {
     offset += 8
 
     data.writeUInt8(params.partialFillAllowed ? 1 : 0, offset)
+    offset += 1
+
+    data.writeBigUInt64LE(params.minFillAmount, offset)
+    offset += 8
+
+    data.writeBigUInt64LE(params.solverTip, offset)
 
     return data
   }
@@ -478,8 +838,12 @@ export class OIFClient {
     return data
   }
 
-  private buildRegisterSolverData(supportedChains: number[]): Buffer {
-    const data = Buffer.alloc(8 + 4 + supportedChains.length * 4)
+  private buildRegisterSolverData(
+    supportedChains: number[],
+    stake: bigint,
+    deferPayout: boolean,
+  ): Buffer {
+    const data = Buffer.alloc(8 + 4 + supportedChains.length * 4 + 8 + 1)
 
     Buffer.from([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]).copy(data, 0)
     data.writeUInt32LE(supportedChains.length, 8)
@@ -490,6 +854,10 @@ export class OIFClient {
       offset += 4
     }
 
+    data.writeBigUInt64LE(stake, offset)
+    offset += 8
+    data.writeUInt8(deferPayout ? 1 : 0, offset)
+
     return data
   }
 
@@ -598,6 +966,17 @@ export class OIFClient {
     offset += 8
 
     const filledAt = data.readBigInt64LE(offset)
+    offset += 8
+
+    offset += 1 // bump
+
+    const minFillAmount = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const solverTip = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const tipPaid = data.readUInt8(offset) === 1
 
     return {
       creator,
@@ -615,6 +994,9 @@ export class OIFClient {
       status,
       createdAt,
       filledAt,
+      minFillAmount,
+      solverTip,
+      tipPaid,
     }
   }
 
@@ -651,6 +1033,21 @@ export class OIFClient {
     offset += 1
 
     const registeredAt = data.readBigInt64LE(offset)
+    offset += 8
+
+    // Skip bump
+    offset += 1
+
+    const deferPayout = data.readUInt8(offset) === 1
+    offset += 1
+
+    const disputes = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const totalFillLatency = data.readBigUInt64LE(offset)
+    offset += 8
+
+    const lastSlashedAt = data.readBigInt64LE(offset)
 
     return {
       owner,
@@ -661,6 +1058,10 @@ export class OIFClient {
       reputationScore,
       active,
       registeredAt,
+      deferPayout,
+      disputes,
+      totalFillLatency,
+      lastSlashedAt,
     }
   }
 }
@@ -28,9 +28,12 @@ export const X402_FACILITATOR_PROGRAM_ID = new PublicKey(
 // Maximum resource length to prevent oversized messages
 const MAX_RESOURCE_LENGTH = 256
 
-// Maximum payment age in seconds (5 minutes, matches on-chain MAX_PAYMENT_AGE)
+// Maximum payment age in seconds (5 minutes, the on-chain default max_payment_age)
 const MAX_PAYMENT_AGE_SECONDS = 300
 
+// Allowed clock skew for future timestamps, matches on-chain MAX_CLOCK_SKEW
+const MAX_CLOCK_SKEW_SECONDS = 30
+
 /**
  * Schema for validating encoded payment JSON from external sources
  */
@@ -48,6 +51,7 @@ const X402EncodedPaymentSchema = z.object({
     ),
   nonce: z.string().min(1).max(64, 'Nonce must be <= 64 characters'),
   timestamp: z.number().int().positive(),
+  validUntil: z.number().int().positive(),
   signature: z.string().regex(/^[0-9a-fA-F]+$/, 'Signature must be hex string'),
 })
 
@@ -64,6 +68,8 @@ export interface X402PaymentParams {
   resource: string
   nonce?: string
   timestamp?: number
+  /** Settle deadline (unix seconds); defaults to the end of the freshness window */
+  validUntil?: number
 }
 
 export interface X402Payment {
@@ -74,16 +80,37 @@ export interface X402Payment {
   resource: string
   nonce: string
   timestamp: number
+  validUntil: number
   signature: Uint8Array
   encoded: string
 }
 
-const MESSAGE_PREFIX = Buffer.from('x402:solana:payment:v1:')
+/** Cluster IDs a facilitator deployment binds its signed messages to */
+export const SOLANA_NETWORK_IDS = {
+  mainnet: 101n,
+  testnet: 102n,
+  devnet: 103n,
+} as const
+
+const MESSAGE_PREFIX = Buffer.from('x402:solana:payment:v4:')
+
+/** A string field behind its u16 LE byte length, as the program signs it */
+function lengthPrefixed(field: string): Buffer {
+  const bytes = Buffer.from(field)
+  const length = Buffer.alloc(2)
+  length.writeUInt16LE(bytes.length)
+  return Buffer.concat([length, bytes])
+}
 
 export class SolanaX402Client {
+  /**
+   * `networkId` must match the `network_id` the facilitator was initialized
+   * with; payments signed for another network or program are rejected.
+   */
   constructor(
     private connection: Connection,
     private programId: PublicKey = X402_FACILITATOR_PROGRAM_ID,
+    private networkId: bigint = SOLANA_NETWORK_IDS.mainnet,
   ) {}
 
   async createPayment(
@@ -103,6 +130,7 @@ export class SolanaX402Client {
     const amount = BigInt(params.amount)
     const nonce = params.nonce ?? this.generateNonce()
     const timestamp = params.timestamp ?? Math.floor(Date.now() / 1000)
+    const validUntil = params.validUntil ?? timestamp + MAX_PAYMENT_AGE_SECONDS
 
     const message = this.buildMessage({
       recipient,
@@ -111,6 +139,7 @@ export class SolanaX402Client {
       resource: params.resource,
       nonce,
       timestamp,
+      validUntil,
     })
     const signature = await sign(message, payer.secretKey.slice(0, 32))
 
@@ -122,6 +151,7 @@ export class SolanaX402Client {
       resource: params.resource,
       nonce,
       timestamp,
+      validUntil,
       signature,
     })
 
@@ -133,6 +163,7 @@ export class SolanaX402Client {
       resource: params.resource,
       nonce,
       timestamp,
+      validUntil,
       signature,
       encoded,
     }
@@ -158,6 +189,14 @@ export class SolanaX402Client {
         `Payment expired: timestamp ${json.timestamp} is older than ${MAX_PAYMENT_AGE_SECONDS} seconds`,
       )
     }
+    if (json.timestamp > now + MAX_CLOCK_SKEW_SECONDS) {
+      throw new Error(
+        `Payment timestamp ${json.timestamp} is too far in the future`,
+      )
+    }
+    if (json.validUntil < now) {
+      throw new Error(`Payment deadline ${json.validUntil} has passed`)
+    }
 
     return {
       payer: new PublicKey(json.payer),
@@ -167,6 +206,7 @@ export class SolanaX402Client {
       resource: json.resource,
       nonce: json.nonce,
       timestamp: json.timestamp,
+      validUntil: json.validUntil,
       signature: hexToBytes(json.signature),
       encoded,
     }
@@ -239,6 +279,8 @@ export class SolanaX402Client {
     nonceLenBuf.writeUInt32LE(nonceBuf.length)
     const timestampBuf = Buffer.alloc(8)
     timestampBuf.writeBigInt64LE(BigInt(payment.timestamp))
+    const validUntilBuf = Buffer.alloc(8)
+    validUntilBuf.writeBigInt64LE(BigInt(payment.validUntil))
 
     tx.add(
       new TransactionInstruction({
@@ -274,6 +316,7 @@ export class SolanaX402Client {
           nonceLenBuf,
           nonceBuf,
           timestampBuf,
+          validUntilBuf,
           Buffer.from(payment.signature),
         ]),
       }),
@@ -312,24 +355,35 @@ export class SolanaX402Client {
     resource: string
     nonce: string
     timestamp: number
+    validUntil: number
   }): Uint8Array {
+    const networkBuf = Buffer.alloc(8)
+    networkBuf.writeBigUInt64LE(this.networkId)
     const amountBuf = Buffer.alloc(8)
     amountBuf.writeBigUInt64LE(p.amount)
     const tsBuf = Buffer.alloc(8)
     tsBuf.writeBigInt64LE(BigInt(p.timestamp))
+    const validUntilBuf = Buffer.alloc(8)
+    validUntilBuf.writeBigInt64LE(BigInt(p.validUntil))
     return Buffer.concat([
       MESSAGE_PREFIX,
+      this.programId.toBuffer(),
+      Buffer.from(':'),
+      networkBuf,
+      Buffer.from(':'),
       p.recipient.toBuffer(),
       Buffer.from(':'),
       p.token.toBuffer(),
       Buffer.from(':'),
       amountBuf,
       Buffer.from(':'),
-      Buffer.from(p.resource),
+      lengthPrefixed(p.resource),
       Buffer.from(':'),
-      Buffer.from(p.nonce),
+      lengthPrefixed(p.nonce),
       Buffer.from(':'),
       tsBuf,
+      Buffer.from(':'),
+      validUntilBuf,
     ])
   }
 
@@ -343,6 +397,7 @@ export class SolanaX402Client {
         resource: p.resource,
         nonce: p.nonce,
         timestamp: p.timestamp,
+        validUntil: p.validUntil,
         signature: bytesToHex(p.signature),
       }),
     ).toString('base64')
@@ -8,7 +8,8 @@
 //! ```rust,ignore
 //! use evm_light_client::cpi;
 //!
-//! // Build CPI context
+//! // Build CPI context; the state account is the light client's PDA for the
+//! // chain, see `evm_light_client::state_address`
 //! let cpi_ctx = CpiContext::new(
 //!     ctx.accounts.evm_light_client_program.to_account_info(),
 //!     cpi::accounts::VerifyProof {
@@ -19,6 +20,7 @@
 //! // Verify an EVM storage proof
 //! let is_valid = cpi::verify_account_proof(
 //!     cpi_ctx,
+//!     chain_id,
 //!     account_address,
 //!     storage_slot,
 //!     expected_value,
@@ -27,6 +29,7 @@
 //! ```
 
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 /// Accounts required for verifying an EVM proof via CPI
 #[derive(Accounts)]
@@ -36,6 +39,30 @@ pub struct VerifyProof<'info> {
     pub state: AccountInfo<'info>,
 }
 
+/// Accounts required for verifying an EVM proof through the proof cache via CPI
+#[derive(Accounts)]
+pub struct VerifyProofCached<'info> {
+    /// The EVM light client state account
+    /// CHECK: Validated by the EVM light client program
+    pub state: AccountInfo<'info>,
+    /// The chain's proof cache PDA
+    /// CHECK: Validated by the EVM light client program
+    #[account(mut)]
+    pub proof_cache: AccountInfo<'info>,
+}
+
+/// Accounts required for verifying an EVM proof against a past state root via CPI
+#[derive(Accounts)]
+pub struct VerifyProofAt<'info> {
+    /// The EVM light client state account
+    /// CHECK: Validated by the EVM light client program
+    pub state: AccountInfo<'info>,
+    /// The chain's proof cache PDA, if the caller wants it used
+    /// CHECK: Validated by the EVM light client program
+    #[account(mut)]
+    pub proof_cache: Option<AccountInfo<'info>>,
+}
+
 /// Accounts required for getting the latest EVM state via CPI
 #[derive(Accounts)]
 pub struct GetState<'info> {
@@ -53,14 +80,20 @@ pub struct LatestStateResult {
     pub sync_committee_root: [u8; 32],
 }
 
-// Instruction discriminators (first 8 bytes of sha256("global:instruction_name"))
-const VERIFY_ACCOUNT_PROOF_DISCRIMINATOR: [u8; 8] = [0x5b, 0x9c, 0x8d, 0x3f, 0x1a, 0x2b, 0x4c, 0x5d];
-const GET_LATEST_STATE_DISCRIMINATOR: [u8; 8] = [0x7e, 0x6f, 0x8a, 0x9b, 0x0c, 0x1d, 0x2e, 0x3f];
+// Instruction discriminators, taken from the program's generated `instruction`
+// module so they can't drift from sha256("global:<instruction_name>")[..8]
+const VERIFY_ACCOUNT_PROOF_DISCRIMINATOR: [u8; 8] = crate::instruction::VerifyAccountProof::DISCRIMINATOR;
+const GET_LATEST_STATE_DISCRIMINATOR: [u8; 8] = crate::instruction::GetLatestState::DISCRIMINATOR;
+const VERIFY_EXCLUSION_PROOF_DISCRIMINATOR: [u8; 8] = crate::instruction::VerifyExclusionProof::DISCRIMINATOR;
+const VERIFY_CODE_HASH_DISCRIMINATOR: [u8; 8] = crate::instruction::VerifyCodeHash::DISCRIMINATOR;
+const VERIFY_ACCOUNT_PROOF_CACHED_DISCRIMINATOR: [u8; 8] = crate::instruction::VerifyAccountProofCached::DISCRIMINATOR;
+const VERIFY_ACCOUNT_PROOF_AT_DISCRIMINATOR: [u8; 8] = crate::instruction::VerifyAccountProofAt::DISCRIMINATOR;
 
 /// Verify an EVM account/storage proof against the light client's verified state root
 ///
 /// # Arguments
 /// * `ctx` - CPI context with the light client program and state account
+/// * `chain_id` - EVM chain the state account tracks
 /// * `account` - 20-byte EVM account address
 /// * `storage_slot` - 32-byte storage slot key
 /// * `expected_value` - 32-byte expected value at the storage slot
@@ -72,15 +105,17 @@ const GET_LATEST_STATE_DISCRIMINATOR: [u8; 8] = [0x7e, 0x6f, 0x8a, 0x9b, 0x0c, 0
 /// * `Err(_)` - Verification error
 pub fn verify_account_proof<'info>(
     ctx: CpiContext<'_, '_, '_, 'info, VerifyProof<'info>>,
+    chain_id: u64,
     account: [u8; 20],
     storage_slot: [u8; 32],
     expected_value: [u8; 32],
     proof_data: Vec<u8>,
 ) -> Result<bool> {
     // Build instruction data
-    // Layout: [8 bytes discriminator][20 bytes account][32 bytes slot][32 bytes value][4 bytes len][proof_data]
-    let mut data = Vec::with_capacity(8 + 20 + 32 + 32 + 4 + proof_data.len());
+    // Layout: [8 bytes discriminator][8 bytes chain_id][20 bytes account][32 bytes slot][32 bytes value][4 bytes len][proof_data]
+    let mut data = Vec::with_capacity(8 + 8 + 20 + 32 + 32 + 4 + proof_data.len());
     data.extend_from_slice(&VERIFY_ACCOUNT_PROOF_DISCRIMINATOR);
+    data.extend_from_slice(&chain_id.to_le_bytes());
     data.extend_from_slice(&account);
     data.extend_from_slice(&storage_slot);
     data.extend_from_slice(&expected_value);
@@ -116,18 +151,253 @@ pub fn verify_account_proof<'info>(
     Ok(return_data[0] == 1)
 }
 
+/// Verify an EVM account/storage proof against the state root the light
+/// client finalized at `slot`
+///
+/// Takes the same arguments as `verify_account_proof` plus the slot; the
+/// light client fails the call if it no longer holds a root for that slot.
+/// With a proof cache in the accounts, the account half of the proof is
+/// skipped for accounts already proven against that root.
+///
+/// # Returns
+/// * `Ok(true)` - Proof is valid against the root at `slot`
+/// * `Ok(false)` - Proof is invalid
+/// * `Err(_)` - Verification error, including an unknown slot
+pub fn verify_account_proof_at<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, VerifyProofAt<'info>>,
+    chain_id: u64,
+    slot: u64,
+    account: [u8; 20],
+    storage_slot: [u8; 32],
+    expected_value: [u8; 32],
+    proof_data: &[u8],
+) -> Result<bool> {
+    // Layout: [8 bytes discriminator][8 bytes chain_id][8 bytes slot][20 bytes account][32 bytes slot][32 bytes value][4 bytes len][proof_data]
+    let mut data = Vec::with_capacity(8 + 8 + 8 + 20 + 32 + 32 + 4 + proof_data.len());
+    data.extend_from_slice(&VERIFY_ACCOUNT_PROOF_AT_DISCRIMINATOR);
+    data.extend_from_slice(&chain_id.to_le_bytes());
+    data.extend_from_slice(&slot.to_le_bytes());
+    data.extend_from_slice(&account);
+    data.extend_from_slice(&storage_slot);
+    data.extend_from_slice(&expected_value);
+    data.extend_from_slice(&(proof_data.len() as u32).to_le_bytes());
+    data.extend_from_slice(proof_data);
+
+    // An absent optional account is passed as the program itself
+    let mut accounts = vec![AccountMeta::new_readonly(*ctx.accounts.state.key, false)];
+    let mut account_infos = vec![ctx.accounts.state.clone()];
+    match &ctx.accounts.proof_cache {
+        Some(proof_cache) => {
+            accounts.push(AccountMeta::new(*proof_cache.key, false));
+            account_infos.push(proof_cache.clone());
+        }
+        None => {
+            accounts.push(AccountMeta::new_readonly(*ctx.program.key, false));
+            account_infos.push(ctx.program.clone());
+        }
+    }
+
+    let ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: *ctx.program.key,
+        accounts,
+        data,
+    };
+
+    anchor_lang::solana_program::program::invoke(&ix, &account_infos)?;
+
+    let (program_id, return_data) =
+        anchor_lang::solana_program::program::get_return_data().ok_or(error!(ErrorCode::NoReturnData))?;
+
+    if program_id != *ctx.program.key {
+        return Err(error!(ErrorCode::InvalidReturnProgram));
+    }
+
+    if return_data.is_empty() {
+        return Err(error!(ErrorCode::EmptyReturnData));
+    }
+
+    Ok(return_data[0] == 1)
+}
+
+/// Verify an EVM account/storage proof through the light client's proof
+/// cache, which skips the account half of the proof for accounts already
+/// proven against the current root
+///
+/// Takes the same arguments as `verify_account_proof`.
+///
+/// # Returns
+/// * `Ok(true)` - Proof is valid
+/// * `Ok(false)` - Proof is invalid
+/// * `Err(_)` - Verification error
+pub fn verify_account_proof_cached<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, VerifyProofCached<'info>>,
+    chain_id: u64,
+    account: [u8; 20],
+    storage_slot: [u8; 32],
+    expected_value: [u8; 32],
+    proof_data: Vec<u8>,
+) -> Result<bool> {
+    // Layout: [8 bytes discriminator][8 bytes chain_id][20 bytes account][32 bytes slot][32 bytes value][4 bytes len][proof_data]
+    let mut data = Vec::with_capacity(8 + 8 + 20 + 32 + 32 + 4 + proof_data.len());
+    data.extend_from_slice(&VERIFY_ACCOUNT_PROOF_CACHED_DISCRIMINATOR);
+    data.extend_from_slice(&chain_id.to_le_bytes());
+    data.extend_from_slice(&account);
+    data.extend_from_slice(&storage_slot);
+    data.extend_from_slice(&expected_value);
+    data.extend_from_slice(&(proof_data.len() as u32).to_le_bytes());
+    data.extend_from_slice(&proof_data);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*ctx.accounts.state.key, false),
+        AccountMeta::new(*ctx.accounts.proof_cache.key, false),
+    ];
+
+    let ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: *ctx.program.key,
+        accounts,
+        data,
+    };
+
+    anchor_lang::solana_program::program::invoke(
+        &ix,
+        &[ctx.accounts.state.clone(), ctx.accounts.proof_cache.clone()],
+    )?;
+
+    let (program_id, return_data) =
+        anchor_lang::solana_program::program::get_return_data().ok_or(error!(ErrorCode::NoReturnData))?;
+
+    if program_id != *ctx.program.key {
+        return Err(error!(ErrorCode::InvalidReturnProgram));
+    }
+
+    if return_data.is_empty() {
+        return Err(error!(ErrorCode::EmptyReturnData));
+    }
+
+    Ok(return_data[0] == 1)
+}
+
+/// Verify that an EVM storage slot holds no value
+///
+/// # Arguments
+/// * `ctx` - CPI context with the light client program and state account
+/// * `chain_id` - EVM chain the state account tracks
+/// * `account` - 20-byte EVM account address
+/// * `storage_slot` - 32-byte storage slot key
+/// * `proof_data` - Serialized Merkle-Patricia proof
+///
+/// # Returns
+/// * `Ok(true)` - The slot is proven absent
+/// * `Ok(false)` - Absence could not be proven
+/// * `Err(_)` - Verification error
+pub fn verify_exclusion_proof<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, VerifyProof<'info>>,
+    chain_id: u64,
+    account: [u8; 20],
+    storage_slot: [u8; 32],
+    proof_data: Vec<u8>,
+) -> Result<bool> {
+    // Layout: [8 bytes discriminator][8 bytes chain_id][20 bytes account][32 bytes slot][4 bytes len][proof_data]
+    let mut data = Vec::with_capacity(8 + 8 + 20 + 32 + 4 + proof_data.len());
+    data.extend_from_slice(&VERIFY_EXCLUSION_PROOF_DISCRIMINATOR);
+    data.extend_from_slice(&chain_id.to_le_bytes());
+    data.extend_from_slice(&account);
+    data.extend_from_slice(&storage_slot);
+    data.extend_from_slice(&(proof_data.len() as u32).to_le_bytes());
+    data.extend_from_slice(&proof_data);
+
+    let accounts = vec![AccountMeta::new_readonly(*ctx.accounts.state.key, false)];
+
+    let ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: *ctx.program.key,
+        accounts,
+        data,
+    };
+
+    anchor_lang::solana_program::program::invoke(&ix, &[ctx.accounts.state.clone()])?;
+
+    let (program_id, return_data) =
+        anchor_lang::solana_program::program::get_return_data().ok_or(error!(ErrorCode::NoReturnData))?;
+
+    if program_id != *ctx.program.key {
+        return Err(error!(ErrorCode::InvalidReturnProgram));
+    }
+
+    if return_data.is_empty() {
+        return Err(error!(ErrorCode::EmptyReturnData));
+    }
+
+    Ok(return_data[0] == 1)
+}
+
+/// Verify that an EVM contract's deployed bytecode hashes to `expected_code_hash`
+///
+/// # Arguments
+/// * `ctx` - CPI context with the light client program and state account
+/// * `chain_id` - EVM chain the state account tracks
+/// * `account` - 20-byte EVM contract address
+/// * `expected_code_hash` - keccak256 of the expected bytecode
+/// * `proof_data` - Serialized account proof
+///
+/// # Returns
+/// * `Ok(true)` - The account holds the expected code
+/// * `Ok(false)` - The account is absent or holds different code
+/// * `Err(_)` - Verification error, including an account without code
+pub fn verify_code_hash<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, VerifyProof<'info>>,
+    chain_id: u64,
+    account: [u8; 20],
+    expected_code_hash: [u8; 32],
+    proof_data: Vec<u8>,
+) -> Result<bool> {
+    // Layout: [8 bytes discriminator][8 bytes chain_id][20 bytes account][32 bytes code hash][4 bytes len][proof_data]
+    let mut data = Vec::with_capacity(8 + 8 + 20 + 32 + 4 + proof_data.len());
+    data.extend_from_slice(&VERIFY_CODE_HASH_DISCRIMINATOR);
+    data.extend_from_slice(&chain_id.to_le_bytes());
+    data.extend_from_slice(&account);
+    data.extend_from_slice(&expected_code_hash);
+    data.extend_from_slice(&(proof_data.len() as u32).to_le_bytes());
+    data.extend_from_slice(&proof_data);
+
+    let accounts = vec![AccountMeta::new_readonly(*ctx.accounts.state.key, false)];
+
+    let ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: *ctx.program.key,
+        accounts,
+        data,
+    };
+
+    anchor_lang::solana_program::program::invoke(&ix, &[ctx.accounts.state.clone()])?;
+
+    let (program_id, return_data) =
+        anchor_lang::solana_program::program::get_return_data().ok_or(error!(ErrorCode::NoReturnData))?;
+
+    if program_id != *ctx.program.key {
+        return Err(error!(ErrorCode::InvalidReturnProgram));
+    }
+
+    if return_data.is_empty() {
+        return Err(error!(ErrorCode::EmptyReturnData));
+    }
+
+    Ok(return_data[0] == 1)
+}
+
 /// Get the latest verified EVM state from the light client
 ///
 /// # Arguments
 /// * `ctx` - CPI context with the light client program and state account
+/// * `chain_id` - EVM chain the state account tracks
 ///
 /// # Returns
 /// * Latest state including slot, block root, state root, and sync committee root
 pub fn get_latest_state<'info>(
     ctx: CpiContext<'_, '_, '_, 'info, GetState<'info>>,
+    chain_id: u64,
 ) -> Result<LatestStateResult> {
-    // Build instruction data (just discriminator)
-    let data = GET_LATEST_STATE_DISCRIMINATOR.to_vec();
+    // Layout: [8 bytes discriminator][8 bytes chain_id]
+    let mut data = GET_LATEST_STATE_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&chain_id.to_le_bytes());
 
     // Build accounts meta
     let accounts = vec![AccountMeta::new_readonly(*ctx.accounts.state.key, false)];
@@ -176,6 +446,7 @@ pub fn get_latest_state<'info>(
 ///
 /// # Arguments
 /// * `ctx` - CPI context with the light client program and state account
+/// * `chain_id` - EVM chain the state account tracks
 /// * `account` - 20-byte EVM account address
 /// * `proofs` - Vector of (storage_slot, expected_value, proof_data) tuples
 ///
@@ -183,6 +454,7 @@ pub fn get_latest_state<'info>(
 /// * Vector of booleans indicating validity of each proof
 pub fn verify_batch_proofs<'info>(
     ctx: CpiContext<'_, '_, '_, 'info, VerifyProof<'info>>,
+    chain_id: u64,
     account: [u8; 20],
     proofs: Vec<([u8; 32], [u8; 32], Vec<u8>)>,
 ) -> Result<Vec<bool>> {
@@ -197,7 +469,7 @@ pub fn verify_batch_proofs<'info>(
             },
         );
 
-        let is_valid = verify_account_proof(cpi_ctx, account, storage_slot, expected_value, proof_data)?;
+        let is_valid = verify_account_proof(cpi_ctx, chain_id, account, storage_slot, expected_value, proof_data)?;
         results.push(is_valid);
     }
 
@@ -224,8 +496,27 @@ pub enum ErrorCode {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_discriminators_match_anchor_derivation() {
+        // sha256("global:<instruction_name>")[..8]
+        assert_eq!(VERIFY_ACCOUNT_PROOF_DISCRIMINATOR, [0xf2, 0x93, 0x14, 0x15, 0xdc, 0xb6, 0x90, 0x8f]);
+        assert_eq!(GET_LATEST_STATE_DISCRIMINATOR, [0x00, 0x22, 0x81, 0x8e, 0x28, 0x8b, 0x8a, 0xfc]);
+        assert_eq!(VERIFY_EXCLUSION_PROOF_DISCRIMINATOR, [0x17, 0x96, 0x74, 0x61, 0x3c, 0xf4, 0x40, 0x23]);
+        assert_eq!(VERIFY_CODE_HASH_DISCRIMINATOR, [0x71, 0x53, 0xbe, 0x24, 0x98, 0xcc, 0xa4, 0xe7]);
+        assert_eq!(VERIFY_ACCOUNT_PROOF_CACHED_DISCRIMINATOR, [0x29, 0x4d, 0xe9, 0xed, 0x41, 0xff, 0xfc, 0xde]);
+        assert_eq!(VERIFY_ACCOUNT_PROOF_AT_DISCRIMINATOR, [0x19, 0x0a, 0x5f, 0x73, 0x57, 0x99, 0x8f, 0x11]);
+    }
+
     #[test]
     fn test_discriminator_uniqueness() {
         assert_ne!(VERIFY_ACCOUNT_PROOF_DISCRIMINATOR, GET_LATEST_STATE_DISCRIMINATOR);
+        assert_ne!(VERIFY_EXCLUSION_PROOF_DISCRIMINATOR, VERIFY_ACCOUNT_PROOF_DISCRIMINATOR);
+        assert_ne!(VERIFY_EXCLUSION_PROOF_DISCRIMINATOR, GET_LATEST_STATE_DISCRIMINATOR);
+        assert_ne!(VERIFY_CODE_HASH_DISCRIMINATOR, VERIFY_ACCOUNT_PROOF_DISCRIMINATOR);
+        assert_ne!(VERIFY_CODE_HASH_DISCRIMINATOR, VERIFY_EXCLUSION_PROOF_DISCRIMINATOR);
+        assert_ne!(VERIFY_CODE_HASH_DISCRIMINATOR, GET_LATEST_STATE_DISCRIMINATOR);
+        assert_ne!(VERIFY_ACCOUNT_PROOF_CACHED_DISCRIMINATOR, VERIFY_ACCOUNT_PROOF_DISCRIMINATOR);
+        assert_ne!(VERIFY_ACCOUNT_PROOF_CACHED_DISCRIMINATOR, VERIFY_CODE_HASH_DISCRIMINATOR);
+        assert_ne!(VERIFY_ACCOUNT_PROOF_AT_DISCRIMINATOR, VERIFY_ACCOUNT_PROOF_DISCRIMINATOR);
     }
 }
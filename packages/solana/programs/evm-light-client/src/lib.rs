@@ -8,9 +8,25 @@
 //! - Sync committee updates are verified via Groth16 proofs
 //! - BN254 pairing precompile is used for efficient verification
 //! - State includes latest block, state root, and sync committee
+//! - Recent finalized state roots are kept with their slots, so a proof can
+//!   be checked against a root some depth below the head
+//! - Optionally, updates are optimistic: a proven update is staged for a
+//!   challenge period during which a conflicting proof cancels it, and only
+//!   finalized state is used for proof verification
+//!
+//! Each tracked EVM chain has its own state account, a PDA seeded by
+//! `[b"evm_light_client", chain_id.to_le_bytes()]`; every instruction takes the
+//! chain id first so its accounts can be derived.
+//!
+//! A chain may also have a proof cache PDA (`[b"proof_cache", chain_id]`)
+//! remembering the storage roots of the last few accounts proven against each
+//! state root. Storage proofs checked through it (`verify_account_proof_cached`,
+//! or `verify_account_proof_at` with the cache passed) skip re-walking the
+//! account half of the proof when many slots of one contract are proven
+//! against the same root, as the token bridge does.
 //!
 //! Usage:
-//! 1. Initialize with a trusted sync committee
+//! 1. Initialize with a trusted sync committee (upgrade authority only)
 //! 2. Submit periodic updates with ZK proofs
 //! 3. Other programs can CPI to verify EVM state proofs
 //!
@@ -22,6 +38,7 @@
 //!
 //! let is_valid = cpi::verify_account_proof(
 //!     cpi_ctx,
+//!     chain_id,
 //!     account_address,
 //!     storage_slot,
 //!     expected_value,
@@ -31,6 +48,7 @@
 
 use anchor_lang::prelude::*;
 use solana_program::alt_bn128::prelude::*;
+use solana_program::bpf_loader_upgradeable;
 use solana_program::keccak;
 
 mod verification_key;
@@ -47,6 +65,24 @@ pub use cpi_helpers::{LatestStateResult};
 
 declare_id!("5TMUr2vv5TAUhKo4q8ibfkkw9SeBRzumYaRNo1iWvwsX");
 
+/// Seed prefix of the per-chain state PDA
+pub const STATE_SEED: &[u8] = b"evm_light_client";
+
+/// Seed prefix of the per-chain verified-proof cache PDA
+pub const PROOF_CACHE_SEED: &[u8] = b"proof_cache";
+
+/// Number of account storage roots a proof cache remembers
+pub const PROOF_CACHE_SIZE: usize = 16;
+
+/// Number of recent finalized state roots kept, with their slots, so proofs
+/// can be checked against a root older than the head
+pub const STATE_ROOT_HISTORY: usize = 64;
+
+/// State account tracking EVM chain `chain_id`
+pub fn state_address(chain_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STATE_SEED, &chain_id.to_le_bytes()], &ID)
+}
+
 /// Maximum sync committee size (512 validators)
 pub const SYNC_COMMITTEE_SIZE: usize = 512;
 
@@ -63,13 +99,27 @@ pub const G1_SIZE: usize = 64;
 /// G2 point size (x1, x2, y1, y2 coordinates, 32 bytes each)  
 pub const G2_SIZE: usize = 128;
 
+/// Canonical encoding length of the ethereum circuit's `EthConsensusOutputs`
+pub const PUBLIC_INPUTS_SIZE: usize = 180;
+
+/// keccak256 of empty bytecode: the code hash of every account without code
+pub const EMPTY_CODE_HASH: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
 #[program]
 pub mod evm_light_client {
     use super::*;
 
-    /// Initialize the light client with a trusted sync committee
+    /// Initialize the light client for `chain_id` with a trusted sync committee
+    ///
+    /// Only the program's upgrade authority may do this, and it becomes the
+    /// chain's admin. Otherwise anyone could claim a new chain id first and
+    /// seed it with their own sync committee and state root.
     pub fn initialize(
         ctx: Context<Initialize>,
+        chain_id: u64,
         genesis_slot: u64,
         genesis_block_root: [u8; 32],
         genesis_state_root: [u8; 32],
@@ -81,20 +131,17 @@ pub mod evm_light_client {
             ErrorCode::PlaceholderVerificationKeys
         );
 
-        let state = &mut ctx.accounts.state;
-
-        state.admin = ctx.accounts.admin.key();
-        state.latest_slot = genesis_slot;
-        state.latest_block_root = genesis_block_root;
-        state.latest_state_root = genesis_state_root;
-        state.current_sync_committee_root = sync_committee_root;
-        state.next_sync_committee_root = [0u8; 32];
-        state.update_count = 0;
-        state.initialized = true;
-        state.permissioned_mode = true;  // Default to permissioned mode for security
-        state.authorized_relayer = ctx.accounts.admin.key();
+        init_state(
+            &mut ctx.accounts.state,
+            ctx.accounts.admin.key(),
+            chain_id,
+            genesis_slot,
+            genesis_block_root,
+            genesis_state_root,
+            sync_committee_root,
+        );
 
-        msg!("EVM Light Client initialized at slot {}", genesis_slot);
+        msg!("EVM Light Client initialized for chain {} at slot {}", chain_id, genesis_slot);
 
         Ok(())
     }
@@ -102,6 +149,7 @@ pub mod evm_light_client {
     /// Set the authorized relayer (admin only)
     pub fn set_authorized_relayer(
         ctx: Context<AdminAction>,
+        _chain_id: u64,
         new_relayer: Pubkey,
     ) -> Result<()> {
         require!(
@@ -116,6 +164,7 @@ pub mod evm_light_client {
     /// Toggle permissioned mode (admin only)
     pub fn set_permissioned_mode(
         ctx: Context<AdminAction>,
+        _chain_id: u64,
         permissioned: bool,
     ) -> Result<()> {
         require!(
@@ -127,63 +176,196 @@ pub mod evm_light_client {
         Ok(())
     }
 
+    /// Set how long proposed updates wait before finalizing (admin only)
+    ///
+    /// Zero disables the optimistic mode and applies proven updates immediately.
+    /// An already staged update keeps its original `finalize_at`.
+    pub fn set_challenge_period(
+        ctx: Context<AdminAction>,
+        _chain_id: u64,
+        challenge_period: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.state.admin,
+            ErrorCode::Unauthorized
+        );
+        require!(challenge_period >= 0, ErrorCode::InvalidChallengePeriod);
+        ctx.accounts.state.challenge_period = challenge_period;
+        msg!("Challenge period set to {} seconds", challenge_period);
+        Ok(())
+    }
+
     /// Update the light client with a new verified state
     ///
     /// Requires a ZK proof that:
     /// 1. The sync committee signed the new block root
     /// 2. At least 2/3 of validators participated
-    /// 3. The sync committee matches our stored root
+    /// 3. The sync committee matches our stored current or next root
+    ///
+    /// Sync committee rotation follows the roots the proof attests to; see
+    /// `rotate_sync_committees`. `new_sync_committee_root`, when given, must
+    /// equal the resulting next committee root.
+    ///
+    /// With a nonzero `challenge_period` the update is staged in
+    /// `pending_state` instead and applied by `finalize_update`.
     pub fn update_state(
         ctx: Context<UpdateState>,
+        _chain_id: u64,
         new_slot: u64,
         new_block_root: [u8; 32],
         new_state_root: [u8; 32],
         new_sync_committee_root: Option<[u8; 32]>,
         proof: [u8; GROTH16_PROOF_SIZE],
-        public_inputs: Vec<u8>,
+        public_inputs: Vec<u8>, // Canonical encoding of the circuit outputs
     ) -> Result<()> {
         let state = &mut ctx.accounts.state;
 
         require!(state.initialized, ErrorCode::NotInitialized);
         require!(new_slot > state.latest_slot, ErrorCode::SlotNotAdvanced);
 
-        // Verify the ZK proof using BN254 precompile
-        verify_groth16_proof(&proof, &public_inputs, &state.current_sync_committee_root)?;
-
-        // Validate public inputs encode the expected values
-        validate_public_inputs(
+        // Validate the circuit outputs encode the expected values
+        let (public_inputs_digest, attested) = validate_public_inputs(
             &public_inputs,
             state.latest_slot,
             &state.latest_block_root,
             new_slot,
             &new_block_root,
+            &new_state_root,
+        )?;
+        let (current_committee, next_committee) = rotate_sync_committees(
             &state.current_sync_committee_root,
+            &state.next_sync_committee_root,
+            &attested,
         )?;
+        if let Some(expected) = new_sync_committee_root {
+            require!(expected == next_committee, ErrorCode::SyncCommitteeMismatch);
+        }
 
-        // Update state
-        state.latest_slot = new_slot;
-        state.latest_block_root = new_block_root;
-        state.latest_state_root = new_state_root;
-        state.update_count += 1;
-
-        // Handle sync committee rotation (every ~27 hours)
-        if let Some(next_root) = new_sync_committee_root {
-            if state.next_sync_committee_root != [0u8; 32] {
-                // Rotate: next becomes current
-                state.current_sync_committee_root = state.next_sync_committee_root;
-            }
-            state.next_sync_committee_root = next_root;
+        // Verify the ZK proof using BN254 precompile; the outputs digest is its only public input
+        verify_groth16_proof(&proof, &public_inputs_digest, &attested.signing)?;
+
+        if state.challenge_period > 0 {
+            // One update in flight: each proposal extends the finalized state
+            require!(state.pending_state.is_none(), ErrorCode::UpdatePending);
+
+            let finalize_at = Clock::get()?
+                .unix_timestamp
+                .checked_add(state.challenge_period)
+                .ok_or(ErrorCode::InvalidChallengePeriod)?;
+            state.pending_state = Some(PendingUpdate {
+                slot: new_slot,
+                block_root: new_block_root,
+                state_root: new_state_root,
+                current_sync_committee_root: current_committee,
+                next_sync_committee_root: next_committee,
+                proposer: ctx.accounts.relayer.key(),
+                finalize_at,
+            });
+
+            msg!("Update to slot {} staged until {}", new_slot, finalize_at);
+            return Ok(());
         }
 
+        apply_update(
+            state,
+            new_slot,
+            new_block_root,
+            new_state_root,
+            current_committee,
+            next_committee,
+        );
+
         msg!("EVM Light Client updated to slot {}", new_slot);
 
         Ok(())
     }
 
+    /// Cancel the staged update with a valid proof of a different state at the
+    /// same slot
+    ///
+    /// Two valid proofs for conflicting roots mean the circuit or proving key
+    /// is unsound, so neither is applied. Anyone may challenge while the
+    /// window is open.
+    pub fn challenge_update(
+        ctx: Context<ChallengeUpdate>,
+        _chain_id: u64,
+        slot: u64,
+        block_root: [u8; 32],
+        state_root: [u8; 32],
+        proof: [u8; GROTH16_PROOF_SIZE],
+        public_inputs: Vec<u8>,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let pending = state.pending_state.as_ref().ok_or(ErrorCode::NoPendingUpdate)?;
+
+        require!(
+            !pending.can_finalize(Clock::get()?.unix_timestamp),
+            ErrorCode::ChallengeWindowClosed
+        );
+        require!(
+            pending.conflicts_with(slot, &block_root, &state_root),
+            ErrorCode::NotConflicting
+        );
+
+        // The conflicting proof must hold against the same finalized state
+        let (public_inputs_digest, attested) = validate_public_inputs(
+            &public_inputs,
+            state.latest_slot,
+            &state.latest_block_root,
+            slot,
+            &block_root,
+            &state_root,
+        )?;
+        rotate_sync_committees(
+            &state.current_sync_committee_root,
+            &state.next_sync_committee_root,
+            &attested,
+        )?;
+        verify_groth16_proof(&proof, &public_inputs_digest, &attested.signing)?;
+
+        msg!(
+            "Staged update to slot {} from {} cancelled by {}",
+            pending.slot,
+            pending.proposer,
+            ctx.accounts.challenger.key()
+        );
+        state.pending_state = None;
+
+        Ok(())
+    }
+
+    /// Apply the staged update once its challenge window has passed
+    ///
+    /// Permissionless: the update was proven when it was staged.
+    pub fn finalize_update(ctx: Context<FinalizeUpdate>, _chain_id: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let pending = state.pending_state.clone().ok_or(ErrorCode::NoPendingUpdate)?;
+
+        require!(
+            pending.can_finalize(Clock::get()?.unix_timestamp),
+            ErrorCode::ChallengeWindowOpen
+        );
+
+        state.pending_state = None;
+        apply_update(
+            state,
+            pending.slot,
+            pending.block_root,
+            pending.state_root,
+            pending.current_sync_committee_root,
+            pending.next_sync_committee_root,
+        );
+
+        msg!("EVM Light Client finalized at slot {}", pending.slot);
+
+        Ok(())
+    }
+
     /// Verify an EVM account proof against the current state
     ///
     /// This is a CPI-friendly function that other programs can call
-    /// to verify EVM state proofs.
+    /// to verify EVM state proofs. Only finalized state is used; a staged
+    /// update is never trusted.
     ///
     /// The proof is serialized as: [num_nodes: u16][node1_len: u16][node1_data]...
     ///
@@ -192,6 +374,7 @@ pub mod evm_light_client {
     /// CPI callers can read this via `solana_program::program::get_return_data()`.
     pub fn verify_account_proof(
         ctx: Context<VerifyProof>,
+        _chain_id: u64,
         account: [u8; 20],      // EVM address
         storage_slot: [u8; 32], // Storage key
         expected_value: [u8; 32],
@@ -201,16 +384,13 @@ pub mod evm_light_client {
 
         require!(state.initialized, ErrorCode::NotInitialized);
 
-        // Deserialize proof nodes from serialized format
-        let proof_nodes = deserialize_proof_nodes(&proof_data)?;
-
         // Verify the Merkle-Patricia proof against state root
-        let valid = verify_merkle_patricia_proof(
+        let valid = verify_storage_proof(
+            &state.latest_state_root,
             &account,
             &storage_slot,
             &expected_value,
-            &proof_nodes,
-            &state.latest_state_root,
+            &proof_data,
         )?;
 
         // Set return data for CPI callers
@@ -220,12 +400,183 @@ pub mod evm_light_client {
         Ok(valid)
     }
 
+    /// Verify an EVM account proof against the state root finalized at `slot`
+    ///
+    /// Like `verify_account_proof`, but against a recent root rather than the
+    /// head, so callers can require a value to have been in the state for a
+    /// number of slots. Fails if `slot` is not in the root history. When the
+    /// chain's proof cache is passed, it is used as in
+    /// `verify_account_proof_cached`.
+    ///
+    /// # Return Data
+    /// Sets return data with a single byte: 1 if valid, 0 if invalid.
+    pub fn verify_account_proof_at(
+        ctx: Context<VerifyProofAt>,
+        _chain_id: u64,
+        slot: u64,
+        account: [u8; 20],      // EVM address
+        storage_slot: [u8; 32], // Storage key
+        expected_value: [u8; 32],
+        proof_data: Vec<u8>,    // Serialized Merkle-Patricia proof
+    ) -> Result<bool> {
+        let state = &ctx.accounts.state;
+
+        require!(state.initialized, ErrorCode::NotInitialized);
+        let state_root = state.state_root_at(slot).ok_or(ErrorCode::UnknownStateRoot)?;
+
+        let valid = match ctx.accounts.proof_cache.as_mut() {
+            Some(cache) => verify_storage_proof_cached(
+                cache,
+                &state_root,
+                &account,
+                &storage_slot,
+                &expected_value,
+                &proof_data,
+            )?,
+            None => verify_storage_proof(
+                &state_root,
+                &account,
+                &storage_slot,
+                &expected_value,
+                &proof_data,
+            )?,
+        };
+
+        anchor_lang::solana_program::program::set_return_data(&[if valid { 1u8 } else { 0u8 }]);
+
+        Ok(valid)
+    }
+
+    /// Create the optional verified-proof cache for a chain
+    ///
+    /// Anyone may pay for it; it only ever holds results this program proved
+    /// itself against the finalized state root.
+    pub fn init_proof_cache(ctx: Context<InitProofCache>, chain_id: u64) -> Result<()> {
+        let state = &ctx.accounts.state;
+
+        require!(state.initialized, ErrorCode::NotInitialized);
+
+        let cache = &mut ctx.accounts.proof_cache;
+        cache.chain_id = chain_id;
+        cache.next = 0;
+        cache.len = 0;
+        cache.entries = [CachedAccount::default(); PROOF_CACHE_SIZE];
+
+        Ok(())
+    }
+
+    /// Verify an EVM account proof, skipping the account half for recently
+    /// proven accounts
+    ///
+    /// Behaves like `verify_account_proof`, but first looks
+    /// `(state root, account)` up in the chain's proof cache. A hit takes the
+    /// account's storage root from the cache and only walks the storage half
+    /// of the proof; a miss walks both and remembers the proven storage root.
+    /// Entries are keyed by the state root they were proven against, so a
+    /// root change leaves them unmatched rather than stale.
+    ///
+    /// # Return Data
+    /// Sets return data with a single byte: 1 if valid, 0 if invalid.
+    pub fn verify_account_proof_cached(
+        ctx: Context<VerifyProofCached>,
+        _chain_id: u64,
+        account: [u8; 20],      // EVM address
+        storage_slot: [u8; 32], // Storage key
+        expected_value: [u8; 32],
+        proof_data: Vec<u8>,    // Serialized Merkle-Patricia proof
+    ) -> Result<bool> {
+        let state = &ctx.accounts.state;
+
+        require!(state.initialized, ErrorCode::NotInitialized);
+
+        let valid = verify_storage_proof_cached(
+            &mut ctx.accounts.proof_cache,
+            &state.latest_state_root,
+            &account,
+            &storage_slot,
+            &expected_value,
+            &proof_data,
+        )?;
+
+        anchor_lang::solana_program::program::set_return_data(&[if valid { 1u8 } else { 0u8 }]);
+
+        Ok(valid)
+    }
+
+    /// Verify that an EVM storage slot holds no value (exclusion proof)
+    ///
+    /// Takes the same proof format as `verify_account_proof`. The proof must
+    /// end in an empty branch child or a node whose path diverges from the key.
+    ///
+    /// # Return Data
+    /// Sets return data with a single byte: 1 if absence is proven, 0 otherwise.
+    pub fn verify_exclusion_proof(
+        ctx: Context<VerifyProof>,
+        _chain_id: u64,
+        account: [u8; 20],      // EVM address
+        storage_slot: [u8; 32], // Storage key
+        proof_data: Vec<u8>,    // Serialized Merkle-Patricia proof
+    ) -> Result<bool> {
+        let state = &ctx.accounts.state;
+
+        require!(state.initialized, ErrorCode::NotInitialized);
+
+        let proof_nodes = deserialize_proof_nodes(&proof_data)?;
+
+        let absent = verify_merkle_patricia_exclusion(
+            &account,
+            &storage_slot,
+            &proof_nodes,
+            &state.latest_state_root,
+        )?;
+
+        anchor_lang::solana_program::program::set_return_data(&[if absent { 1u8 } else { 0u8 }]);
+
+        Ok(absent)
+    }
+
+    /// Verify that the EVM account at `account` holds the bytecode hashing to
+    /// `expected_code_hash`
+    ///
+    /// Lets callers confirm a contract is the genuine deployment before
+    /// trusting its storage. The proof is the account proof alone, in the same
+    /// serialized format as `verify_account_proof`. Accounts without code
+    /// (EOAs) are rejected with an error.
+    ///
+    /// # Return Data
+    /// Sets return data with a single byte: 1 if the code hash matches, 0 otherwise.
+    pub fn verify_code_hash(
+        ctx: Context<VerifyProof>,
+        _chain_id: u64,
+        account: [u8; 20],             // EVM address
+        expected_code_hash: [u8; 32],  // keccak256 of the deployed bytecode
+        proof_data: Vec<u8>,           // Serialized account proof
+    ) -> Result<bool> {
+        let state = &ctx.accounts.state;
+
+        require!(state.initialized, ErrorCode::NotInitialized);
+        require!(expected_code_hash != EMPTY_CODE_HASH, ErrorCode::AccountHasNoCode);
+
+        let proof_nodes = deserialize_proof_nodes(&proof_data)?;
+
+        let valid = verify_account_code_hash(
+            &account,
+            &expected_code_hash,
+            &proof_nodes,
+            &state.latest_state_root,
+        )?;
+
+        anchor_lang::solana_program::program::set_return_data(&[if valid { 1u8 } else { 0u8 }]);
+
+        Ok(valid)
+    }
+
     /// Get the latest verified state (for cross-program queries)
     ///
     /// # Return Data
     /// Sets return data with: [8 bytes slot][32 bytes block_root][32 bytes state_root][32 bytes committee_root]
     /// CPI callers can read this via `solana_program::program::get_return_data()`.
-    pub fn get_latest_state(ctx: Context<GetState>) -> Result<LatestState> {
+    pub fn get_latest_state(ctx: Context<GetState>, _chain_id: u64) -> Result<LatestState> {
         let state = &ctx.accounts.state;
 
         // Build return data for CPI callers
@@ -248,12 +599,13 @@ pub mod evm_light_client {
 
 
 #[derive(Accounts)]
+#[instruction(chain_id: u64)]
 pub struct Initialize<'info> {
     #[account(
         init,
         payer = admin,
         space = 8 + LightClientState::INIT_SPACE,
-        seeds = [b"evm_light_client"],
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
         bump
     )]
     pub state: Account<'info, LightClientState>,
@@ -261,14 +613,24 @@ pub struct Initialize<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
 
+    /// This program's ProgramData, naming the upgrade authority
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = can_initialize(&program_data, &admin.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(chain_id: u64)]
 pub struct UpdateState<'info> {
     #[account(
         mut,
-        seeds = [b"evm_light_client"],
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
         bump
     )]
     pub state: Account<'info, LightClientState>,
@@ -277,10 +639,35 @@ pub struct UpdateState<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct ChallengeUpdate<'info> {
+    #[account(
+        mut,
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub state: Account<'info, LightClientState>,
+
+    pub challenger: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct FinalizeUpdate<'info> {
+    #[account(
+        mut,
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub state: Account<'info, LightClientState>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
 pub struct AdminAction<'info> {
     #[account(
         mut,
-        seeds = [b"evm_light_client"],
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
         bump
     )]
     pub state: Account<'info, LightClientState>,
@@ -289,18 +676,78 @@ pub struct AdminAction<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(chain_id: u64)]
 pub struct VerifyProof<'info> {
     #[account(
-        seeds = [b"evm_light_client"],
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
         bump
     )]
     pub state: Account<'info, LightClientState>,
 }
 
 #[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct VerifyProofAt<'info> {
+    #[account(
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub state: Account<'info, LightClientState>,
+
+    #[account(
+        mut,
+        seeds = [PROOF_CACHE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub proof_cache: Option<Account<'info, ProofCache>>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct InitProofCache<'info> {
+    #[account(
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub state: Account<'info, LightClientState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProofCache::INIT_SPACE,
+        seeds = [PROOF_CACHE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub proof_cache: Account<'info, ProofCache>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
+pub struct VerifyProofCached<'info> {
+    #[account(
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub state: Account<'info, LightClientState>,
+
+    #[account(
+        mut,
+        seeds = [PROOF_CACHE_SEED, &chain_id.to_le_bytes()],
+        bump
+    )]
+    pub proof_cache: Account<'info, ProofCache>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: u64)]
 pub struct GetState<'info> {
     #[account(
-        seeds = [b"evm_light_client"],
+        seeds = [STATE_SEED, &chain_id.to_le_bytes()],
         bump
     )]
     pub state: Account<'info, LightClientState>,
@@ -313,6 +760,9 @@ pub struct LightClientState {
     /// Admin who can update configuration
     pub admin: Pubkey,
 
+    /// EVM chain this state tracks; part of the account's seeds
+    pub chain_id: u64,
+
     /// Latest verified beacon slot
     pub latest_slot: u64,
 
@@ -339,6 +789,109 @@ pub struct LightClientState {
 
     /// Authorized relayer (if permissioned_mode is true)
     pub authorized_relayer: Pubkey,
+
+    /// Seconds a staged update waits before it can be finalized (0 = immediate)
+    pub challenge_period: i64,
+
+    /// Proven update waiting out its challenge window
+    pub pending_state: Option<PendingUpdate>,
+
+    /// Recent finalized state roots and their slots, oldest overwritten first
+    pub recent_roots: [VerifiedRoot; STATE_ROOT_HISTORY],
+
+    /// Position in `recent_roots` the next root is written to
+    pub recent_roots_next: u8,
+}
+
+/// A finalized execution state root and the beacon slot it was verified at
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, InitSpace)]
+pub struct VerifiedRoot {
+    pub slot: u64,
+    pub state_root: [u8; 32],
+}
+
+impl LightClientState {
+    /// State root finalized at `slot`, if it is still in the history
+    pub fn state_root_at(&self, slot: u64) -> Option<[u8; 32]> {
+        self.recent_roots
+            .iter()
+            .find(|root| root.slot == slot && root.state_root != [0u8; 32])
+            .map(|root| root.state_root)
+    }
+
+    /// Remember a newly finalized root, evicting the oldest
+    fn record_root(&mut self, slot: u64, state_root: [u8; 32]) {
+        let next = self.recent_roots_next as usize % STATE_ROOT_HISTORY;
+        self.recent_roots[next] = VerifiedRoot { slot, state_root };
+        self.recent_roots_next = ((next + 1) % STATE_ROOT_HISTORY) as u8;
+    }
+}
+
+/// A proven update staged in optimistic mode
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PendingUpdate {
+    pub slot: u64,
+    pub block_root: [u8; 32],
+    pub state_root: [u8; 32],
+    /// Sync committees the state holds once this update is applied
+    pub current_sync_committee_root: [u8; 32],
+    pub next_sync_committee_root: [u8; 32],
+    pub proposer: Pubkey,
+    pub finalize_at: i64,
+}
+
+impl PendingUpdate {
+    /// Whether a proven update for `slot` contradicts this one
+    pub fn conflicts_with(&self, slot: u64, block_root: &[u8; 32], state_root: &[u8; 32]) -> bool {
+        slot == self.slot && (*block_root != self.block_root || *state_root != self.state_root)
+    }
+
+    pub fn can_finalize(&self, now: i64) -> bool {
+        now >= self.finalize_at
+    }
+}
+
+/// Account storage roots recently proven against a chain's finalized state roots
+///
+/// A fixed-size ring: once full, each new entry overwrites the oldest.
+#[account]
+#[derive(InitSpace)]
+pub struct ProofCache {
+    /// EVM chain this cache belongs to; part of the account's seeds
+    pub chain_id: u64,
+
+    /// Ring position the next entry is written to
+    pub next: u8,
+
+    /// Number of entries in use
+    pub len: u8,
+
+    pub entries: [CachedAccount; PROOF_CACHE_SIZE],
+}
+
+/// An account's storage root, proven against `state_root`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, InitSpace)]
+pub struct CachedAccount {
+    pub state_root: [u8; 32],
+    pub account: [u8; 20],
+    pub storage_root: [u8; 32],
+}
+
+impl ProofCache {
+    /// Storage root of `account` if it was proven against `state_root`
+    pub fn storage_root(&self, state_root: &[u8; 32], account: &[u8; 20]) -> Option<[u8; 32]> {
+        self.entries[..self.len as usize]
+            .iter()
+            .find(|entry| entry.state_root == *state_root && entry.account == *account)
+            .map(|entry| entry.storage_root)
+    }
+
+    /// Remember a proven storage root, evicting the oldest entry when full
+    pub fn insert(&mut self, entry: CachedAccount) {
+        self.entries[self.next as usize] = entry;
+        self.next = ((self.next as usize + 1) % PROOF_CACHE_SIZE) as u8;
+        self.len = (self.len as usize + 1).min(PROOF_CACHE_SIZE) as u8;
+    }
 }
 
 // RETURN TYPES
@@ -375,6 +928,9 @@ pub enum ErrorCode {
     #[msg("Invalid state proof")]
     InvalidStateProof,
 
+    #[msg("Account has no code")]
+    AccountHasNoCode,
+
     #[msg("CRITICAL: Verification keys are placeholders - deploy real keys before mainnet")]
     PlaceholderVerificationKeys,
 
@@ -383,6 +939,119 @@ pub enum ErrorCode {
 
     #[msg("Unauthorized relayer - not in authorized relayer list")]
     UnauthorizedRelayer,
+
+    #[msg("Challenge period must not be negative")]
+    InvalidChallengePeriod,
+
+    #[msg("An update is already waiting out its challenge window")]
+    UpdatePending,
+
+    #[msg("No update is pending")]
+    NoPendingUpdate,
+
+    #[msg("Challenge window is still open")]
+    ChallengeWindowOpen,
+
+    #[msg("Challenge window has closed")]
+    ChallengeWindowClosed,
+
+    #[msg("Challenge does not conflict with the pending update")]
+    NotConflicting,
+
+    #[msg("No state root was finalized at this slot, or it has left the history")]
+    UnknownStateRoot,
+}
+
+/// Set up a freshly created state account
+/// Only the upgrade authority brings a chain online; an immutable program
+/// can't add chains at all
+fn can_initialize(program_data: &ProgramData, signer: &Pubkey) -> bool {
+    program_data.upgrade_authority_address == Some(*signer)
+}
+
+fn init_state(
+    state: &mut LightClientState,
+    admin: Pubkey,
+    chain_id: u64,
+    genesis_slot: u64,
+    genesis_block_root: [u8; 32],
+    genesis_state_root: [u8; 32],
+    sync_committee_root: [u8; 32],
+) {
+    state.admin = admin;
+    state.chain_id = chain_id;
+    state.latest_slot = genesis_slot;
+    state.latest_block_root = genesis_block_root;
+    state.latest_state_root = genesis_state_root;
+    state.current_sync_committee_root = sync_committee_root;
+    state.next_sync_committee_root = [0u8; 32];
+    state.update_count = 0;
+    state.initialized = true;
+    state.permissioned_mode = true;  // Default to permissioned mode for security
+    state.authorized_relayer = admin;
+    state.challenge_period = 0;
+    state.pending_state = None;
+    state.recent_roots = [VerifiedRoot::default(); STATE_ROOT_HISTORY];
+    state.recent_roots_next = 0;
+    state.record_root(genesis_slot, genesis_state_root);
+}
+
+/// Make a proven update the latest finalized state
+fn apply_update(
+    state: &mut LightClientState,
+    new_slot: u64,
+    new_block_root: [u8; 32],
+    new_state_root: [u8; 32],
+    current_sync_committee_root: [u8; 32],
+    next_sync_committee_root: [u8; 32],
+) {
+    state.latest_slot = new_slot;
+    state.latest_block_root = new_block_root;
+    state.latest_state_root = new_state_root;
+    state.current_sync_committee_root = current_sync_committee_root;
+    state.next_sync_committee_root = next_sync_committee_root;
+    state.update_count += 1;
+    state.record_root(new_slot, new_state_root);
+}
+
+/// Sync committees the state holds after an update attesting to `attested`
+///
+/// Roots are SSZ `hash_tree_root(SyncCommittee)`, committing to all 512
+/// pubkeys and their aggregate. A zero `next` means it is not known yet.
+///
+/// - Signed by the current committee: the period has not changed, so the
+///   attested next committee is learned if unknown and must otherwise match.
+/// - Signed by the stored next committee: the period rolled over (every ~27
+///   hours), next becomes current and the attested next takes its place.
+///   An attested state from the old period still names the signer as its
+///   next committee; the new next is then left unknown.
+///
+/// Any other signer breaks continuity and is rejected.
+fn rotate_sync_committees(
+    current: &[u8; 32],
+    next: &[u8; 32],
+    attested: &AttestedCommittees,
+) -> Result<([u8; 32], [u8; 32])> {
+    const UNKNOWN: [u8; 32] = [0u8; 32];
+    require!(attested.next != UNKNOWN, ErrorCode::SyncCommitteeMismatch);
+
+    if attested.signing == *current {
+        // An attested state from the previous period names current as next
+        let learned = if attested.next == *current { *next } else { attested.next };
+        if *next != UNKNOWN && learned != *next {
+            msg!("Attested next sync committee differs from the stored one");
+            return Err(ErrorCode::SyncCommitteeMismatch.into());
+        }
+        return Ok((*current, learned));
+    }
+
+    if *next != UNKNOWN && attested.signing == *next {
+        let new_next = if attested.next == *next { UNKNOWN } else { attested.next };
+        return Ok((*next, new_next));
+    }
+
+    msg!("Sync committee root mismatch");
+    Err(ErrorCode::SyncCommitteeMismatch.into())
 }
 
 // VERIFICATION HELPERS
@@ -526,23 +1195,30 @@ fn negate_g1_point(point: &[u8]) -> Result<[u8; 64]> {
     Ok(result)
 }
 
-/// Validate that public inputs encode the expected state transition
+/// Check the circuit outputs against the expected transition and return their digest
+///
+/// `public_inputs` is the canonical encoding of `EthConsensusOutputs` as produced by
+/// the `public-inputs` circuit crate (integers little-endian, no padding):
+/// [0-7]     prev_slot (u64)
+/// [8-39]    prev_block_root
+/// [40-47]   new_slot (u64)
+/// [48-79]   new_block_root
+/// [80-111]  new_state_root
+/// [112-143] sync_committee_root (committee that signed)
+/// [144-147] participation_count (u32)
+/// [148-179] next_sync_committee_root (proven against the attested state)
+///
+/// The circuit commits only `keccak256(public_inputs)` with the top 3 bits cleared,
+/// which is the single field element passed to the Groth16 verifier.
 fn validate_public_inputs(
     public_inputs: &[u8],
     prev_slot: u64,
     prev_block_root: &[u8; 32],
     new_slot: u64,
     new_block_root: &[u8; 32],
-    sync_committee_root: &[u8; 32],
-) -> Result<()> {
-    // Public inputs layout:
-    // [0-7]   prev_slot (u64 le)
-    // [8-39]  prev_block_root
-    // [40-47] new_slot (u64 le)
-    // [48-79] new_block_root
-    // [80-111] sync_committee_root
-
-    if public_inputs.len() < 112 {
+    new_state_root: &[u8; 32],
+) -> Result<([u8; 32], AttestedCommittees)> {
+    if public_inputs.len() != PUBLIC_INPUTS_SIZE {
         return Err(ErrorCode::PublicInputsMismatch.into());
     }
 
@@ -550,7 +1226,9 @@ fn validate_public_inputs(
     let input_prev_root: [u8; 32] = public_inputs[8..40].try_into().unwrap();
     let input_new_slot = u64::from_le_bytes(public_inputs[40..48].try_into().unwrap());
     let input_new_root: [u8; 32] = public_inputs[48..80].try_into().unwrap();
-    let input_committee_root: [u8; 32] = public_inputs[80..112].try_into().unwrap();
+    let input_state_root: [u8; 32] = public_inputs[80..112].try_into().unwrap();
+    let input_committee_root: [u8; 32] = public_inputs[112..144].try_into().unwrap();
+    let input_next_committee_root: [u8; 32] = public_inputs[148..180].try_into().unwrap();
 
     if input_prev_slot != prev_slot {
         msg!("Previous slot mismatch: {} != {}", input_prev_slot, prev_slot);
@@ -572,12 +1250,34 @@ fn validate_public_inputs(
         return Err(ErrorCode::PublicInputsMismatch.into());
     }
 
-    if input_committee_root != *sync_committee_root {
-        msg!("Sync committee root mismatch");
-        return Err(ErrorCode::SyncCommitteeMismatch.into());
+    if input_state_root != *new_state_root {
+        msg!("New state root mismatch");
+        return Err(ErrorCode::PublicInputsMismatch.into());
     }
 
-    Ok(())
+    let attested = AttestedCommittees {
+        signing: input_committee_root,
+        next: input_next_committee_root,
+    };
+
+    Ok((public_inputs_digest(public_inputs), attested))
+}
+
+/// Sync committee roots committed by the circuit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct AttestedCommittees {
+    /// Committee whose signature the proof verified
+    signing: [u8; 32],
+    /// `next_sync_committee` of the attested beacon state
+    next: [u8; 32],
+}
+
+/// keccak256 of the canonical outputs with the top 3 bits cleared, so the
+/// big-endian value is a valid BN254 scalar
+fn public_inputs_digest(public_inputs: &[u8]) -> [u8; 32] {
+    let mut digest = keccak::hash(public_inputs).to_bytes();
+    digest[0] &= 0x1f;
+    digest
 }
 
 /// Verify a Merkle-Patricia proof for an EVM account/storage
@@ -603,56 +1303,195 @@ fn verify_merkle_patricia_proof(
         return Ok(false);
     }
 
-    // Compute the key path (keccak256 of account address + storage slot)
-    // For account proofs: keccak256(address)
-    // For storage proofs: keccak256(storage_slot)
-    let account_key = keccak::hash(account);
-    let storage_key = keccak::hash(storage_slot);
-    
-    // Convert keys to nibbles for trie traversal
-    let account_nibbles = bytes_to_nibbles(&account_key.to_bytes());
-    let storage_nibbles = bytes_to_nibbles(&storage_key.to_bytes());
-
     // First verify account proof to get storage root
-    let account_value = verify_trie_path(
+    let Some(storage_root) = prove_storage_root(account, proof_nodes, state_root)? else {
+        return Ok(false);
+    };
+
+    // Now verify storage proof if we have a storage slot
+    if proof_nodes.len() > 1 && !verify_storage_value(&storage_root, storage_slot, expected_value, proof_nodes)? {
+        return Ok(false);
+    }
+
+    msg!("Merkle-Patricia proof verified");
+    Ok(true)
+}
+
+/// Storage root of `account` proven by the account half of `proof_nodes`, or
+/// `None` if the account is absent
+fn prove_storage_root(
+    account: &[u8; 20],
+    proof_nodes: &[Vec<u8>],
+    state_root: &[u8; 32],
+) -> Result<Option<[u8; 32]>> {
+    let account_nibbles = bytes_to_nibbles(&keccak::hash(account).to_bytes());
+
+    let account_value = match verify_trie_path(
         state_root,
         &account_nibbles,
         proof_nodes,
         0, // Start from first proof node
-    )?;
+    )? {
+        TrieLookup::Found(value) => value,
+        TrieLookup::Absent => {
+            msg!("Account not found");
+            return Ok(None);
+        }
+    };
 
     // Decode RLP account data to extract storage root
     // Account RLP: [nonce, balance, storage_root, code_hash]
-    let storage_root = extract_storage_root_from_account(&account_value)?;
+    extract_storage_root_from_account(&account_value).map(Some)
+}
 
-    // Now verify storage proof if we have a storage slot
-    if proof_nodes.len() > 1 {
-        let storage_value = verify_trie_path(
-            &storage_root,
-            &storage_nibbles,
-            proof_nodes,
-            proof_nodes.len() / 2, // Storage proof starts after account proof
-        )?;
+/// Whether the storage half of `proof_nodes` proves `expected_value` at
+/// `storage_slot` under `storage_root`
+fn verify_storage_value(
+    storage_root: &[u8; 32],
+    storage_slot: &[u8; 32],
+    expected_value: &[u8; 32],
+    proof_nodes: &[Vec<u8>],
+) -> Result<bool> {
+    let storage_nibbles = bytes_to_nibbles(&keccak::hash(storage_slot).to_bytes());
 
-        // Check if the proven value matches expected
-        if storage_value.len() != 32 {
-            msg!("Invalid storage value length");
+    let storage_value = match verify_trie_path(
+        storage_root,
+        &storage_nibbles,
+        proof_nodes,
+        proof_nodes.len() / 2, // Storage proof starts after account proof
+    )? {
+        TrieLookup::Found(value) => value,
+        TrieLookup::Absent => {
+            msg!("Storage slot not found");
             return Ok(false);
         }
+    };
 
-        let proven_value: [u8; 32] = storage_value.try_into()
-            .map_err(|_| ErrorCode::InvalidStateProof)?;
-        
-        if proven_value != *expected_value {
-            msg!("Storage value mismatch");
+    // Check if the proven value matches expected
+    if storage_value.len() != 32 {
+        msg!("Invalid storage value length");
+        return Ok(false);
+    }
+
+    let proven_value: [u8; 32] = storage_value.try_into()
+        .map_err(|_| ErrorCode::InvalidStateProof)?;
+
+    if proven_value != *expected_value {
+        msg!("Storage value mismatch");
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Verify the code hash of an EVM account against the state root
+///
+/// `proof_nodes` is the account proof from the state root to the account's
+/// leaf. Returns false if the account is absent or its code hash differs, and
+/// an error if the account has no code.
+fn verify_account_code_hash(
+    account: &[u8; 20],
+    expected_code_hash: &[u8; 32],
+    proof_nodes: &[Vec<u8>],
+    state_root: &[u8; 32],
+) -> Result<bool> {
+    if proof_nodes.is_empty() {
+        msg!("Empty proof");
+        return Ok(false);
+    }
+
+    let account_nibbles = bytes_to_nibbles(&keccak::hash(account).to_bytes());
+
+    let account_value = match verify_trie_path(state_root, &account_nibbles, proof_nodes, 0)? {
+        TrieLookup::Found(value) => value,
+        TrieLookup::Absent => {
+            msg!("Account not found");
             return Ok(false);
         }
+    };
+
+    let code_hash = extract_code_hash_from_account(&account_value)?;
+    if code_hash == EMPTY_CODE_HASH {
+        msg!("Account has no code");
+        return Err(ErrorCode::AccountHasNoCode.into());
     }
 
-    msg!("Merkle-Patricia proof verified");
+    if code_hash != *expected_code_hash {
+        msg!("Code hash mismatch");
+        return Ok(false);
+    }
+
+    msg!("Code hash verified");
     Ok(true)
 }
 
+/// Verify that a storage slot is absent from an EVM account's storage trie
+///
+/// Uses the same proof layout as `verify_merkle_patricia_proof`. An account
+/// that is itself absent, or has an empty storage trie, has no storage, so
+/// that also proves exclusion.
+fn verify_merkle_patricia_exclusion(
+    account: &[u8; 20],
+    storage_slot: &[u8; 32],
+    proof_nodes: &[Vec<u8>],
+    state_root: &[u8; 32],
+) -> Result<bool> {
+    if proof_nodes.is_empty() {
+        msg!("Empty proof");
+        return Ok(false);
+    }
+
+    let account_nibbles = bytes_to_nibbles(&keccak::hash(account).to_bytes());
+    let storage_nibbles = bytes_to_nibbles(&keccak::hash(storage_slot).to_bytes());
+
+    let account_value = match verify_trie_path(state_root, &account_nibbles, proof_nodes, 0)? {
+        TrieLookup::Found(value) => value,
+        TrieLookup::Absent => {
+            msg!("Account absent, storage slot excluded");
+            return Ok(true);
+        }
+    };
+
+    let storage_root = extract_storage_root_from_account(&account_value)?;
+    if storage_root == EMPTY_TRIE_ROOT {
+        msg!("Empty storage trie, storage slot excluded");
+        return Ok(true);
+    }
+
+    match verify_trie_path(
+        &storage_root,
+        &storage_nibbles,
+        proof_nodes,
+        proof_nodes.len() / 2, // Storage proof starts after account proof
+    )? {
+        TrieLookup::Found(_) => {
+            msg!("Storage slot is present");
+            Ok(false)
+        }
+        TrieLookup::Absent => {
+            msg!("Merkle-Patricia exclusion proof verified");
+            Ok(true)
+        }
+    }
+}
+
+/// Root of an empty Merkle-Patricia trie: keccak256(rlp(""))
+const EMPTY_TRIE_ROOT: [u8; 32] = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6,
+    0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0,
+    0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// Where a key's path through the trie ended
+#[derive(Debug, PartialEq)]
+enum TrieLookup {
+    /// The key is present with this value
+    Found(Vec<u8>),
+    /// The path ends in an empty branch child or diverges from the key
+    Absent,
+}
+
 /// Convert bytes to nibbles (4-bit values)
 fn bytes_to_nibbles(bytes: &[u8]) -> Vec<u8> {
     let mut nibbles = Vec::with_capacity(bytes.len() * 2);
@@ -664,81 +1503,84 @@ fn bytes_to_nibbles(bytes: &[u8]) -> Vec<u8> {
 }
 
 /// Verify a path through the Merkle-Patricia trie
+///
+/// Proves either inclusion (`Found`) or exclusion (`Absent`) of the key. A
+/// proof that runs out of nodes before the key resolves proves neither.
 fn verify_trie_path(
     root: &[u8; 32],
     key_nibbles: &[u8],
     proof_nodes: &[Vec<u8>],
     start_index: usize,
-) -> Result<Vec<u8>> {
-    let mut current_hash = *root;
+) -> Result<TrieLookup> {
+    let mut next = NodeRef::Hash(*root);
+    let mut proof_index = start_index;
     let mut nibble_index = 0;
 
-    for (i, node) in proof_nodes.iter().enumerate().skip(start_index) {
-        // Verify node hash matches expected
-        let node_hash = keccak::hash(node);
-        if node_hash.to_bytes() != current_hash {
-            msg!("Node hash mismatch at index {}", i);
-            return Err(ErrorCode::InvalidStateProof.into());
-        }
+    loop {
+        // Resolve the next node like go-ethereum's `resolveNode`: hashed children must be
+        // the next proof node, embedded children are already authenticated by their parent
+        let node = match next {
+            NodeRef::Hash(expected_hash) => {
+                let Some(node) = proof_nodes.get(proof_index) else {
+                    msg!("Proof ended before the key was resolved");
+                    return Err(ErrorCode::InvalidStateProof.into());
+                };
+                if keccak::hash(node).to_bytes() != expected_hash {
+                    msg!("Node hash mismatch at index {}", proof_index);
+                    return Err(ErrorCode::InvalidStateProof.into());
+                }
+                proof_index += 1;
+                node.clone()
+            }
+            NodeRef::Embedded(node) => node,
+        };
 
         // Decode RLP node
-        let decoded = decode_rlp_node(node)?;
+        let decoded = decode_rlp_node(&node)?;
 
         match decoded.len() {
             // Branch node: 17 elements (16 children + value)
             17 => {
                 if nibble_index >= key_nibbles.len() {
-                    // We've consumed all nibbles, return the value
-                    return Ok(decoded[16].clone());
+                    // We've consumed all nibbles, the value lives in the branch
+                    if decoded[16].is_empty() {
+                        return Ok(TrieLookup::Absent);
+                    }
+                    return Ok(TrieLookup::Found(decoded[16].clone()));
                 }
                 let child_index = key_nibbles[nibble_index] as usize;
                 if child_index >= 16 {
                     return Err(ErrorCode::InvalidStateProof.into());
                 }
-                
-                // Get next node hash from branch
-                if decoded[child_index].len() == 32 {
-                    current_hash = decoded[child_index].clone().try_into()
-                        .map_err(|_| ErrorCode::InvalidStateProof)?;
-                } else if decoded[child_index].is_empty() {
+
+                if decoded[child_index].is_empty() {
                     // Empty branch means key not found
-                    return Ok(Vec::new());
-                } else {
-                    // Embedded node (for small nodes)
-                    return Ok(decoded[child_index].clone());
+                    return Ok(TrieLookup::Absent);
                 }
+                next = NodeRef::decode(&decoded[child_index])?;
                 nibble_index += 1;
             }
             // Extension or Leaf node: 2 elements (path + value/hash)
             2 => {
                 let (node_path, is_leaf) = decode_compact_path(&decoded[0])?;
-                
-                // Verify path matches our key
-                for (j, &nibble) in node_path.iter().enumerate() {
-                    if nibble_index + j >= key_nibbles.len() {
-                        if is_leaf {
-                            return Ok(decoded[1].clone());
-                        }
-                        return Err(ErrorCode::InvalidStateProof.into());
-                    }
-                    if key_nibbles[nibble_index + j] != nibble {
-                        return Ok(Vec::new()); // Key not found
-                    }
-                }
-                nibble_index += node_path.len();
+                let remaining = &key_nibbles[nibble_index..];
 
                 if is_leaf {
-                    // Leaf node: return the value
-                    return Ok(decoded[1].clone());
-                } else {
-                    // Extension node: follow to next node
-                    if decoded[1].len() == 32 {
-                        current_hash = decoded[1].clone().try_into()
-                            .map_err(|_| ErrorCode::InvalidStateProof)?;
-                    } else {
-                        return Err(ErrorCode::InvalidStateProof.into());
+                    // A leaf for a different key proves ours is absent
+                    if remaining != node_path.as_slice() {
+                        return Ok(TrieLookup::Absent);
                     }
+                    return Ok(TrieLookup::Found(decoded[1].clone()));
+                }
+
+                // An extension that diverges from our key proves it is absent
+                if !remaining.starts_with(&node_path) {
+                    return Ok(TrieLookup::Absent);
                 }
+                nibble_index += node_path.len();
+
+                // Extension node: follow to next node
+                next = NodeRef::decode(&decoded[1])?;
             }
             _ => {
                 msg!("Invalid node length: {}", decoded.len());
@@ -746,8 +1588,29 @@ fn verify_trie_path(
             }
         }
     }
+}
 
-    Ok(Vec::new())
+/// Reference from a branch or extension node to a child node
+enum NodeRef {
+    /// keccak256 of the child, which must be the next proof node
+    Hash([u8; 32]),
+    /// Child shorter than 32 bytes, stored inline as an RLP list
+    Embedded(Vec<u8>),
+}
+
+impl NodeRef {
+    fn decode(item: &[u8]) -> Result<Self> {
+        match item.len() {
+            32 => Ok(Self::Hash(
+                item.try_into().map_err(|_| ErrorCode::InvalidStateProof)?,
+            )),
+            1..=31 if item[0] >= 0xc0 => Ok(Self::Embedded(item.to_vec())),
+            len => {
+                msg!("Invalid child reference length: {}", len);
+                Err(ErrorCode::InvalidStateProof.into())
+            }
+        }
+    }
 }
 
 /// Decode a simple RLP node (list of items)
@@ -895,6 +1758,16 @@ fn decode_compact_path(encoded: &[u8]) -> Result<(Vec<u8>, bool)> {
 
 /// Extract storage root from RLP-encoded account data
 fn extract_storage_root_from_account(account_rlp: &[u8]) -> Result<[u8; 32]> {
+    extract_account_hash(account_rlp, 2, "storage root")
+}
+
+/// Extract the code hash (keccak256 of the bytecode) from an RLP-encoded account
+fn extract_code_hash_from_account(account_rlp: &[u8]) -> Result<[u8; 32]> {
+    extract_account_hash(account_rlp, 3, "code hash")
+}
+
+/// Extract the 32-byte field at `index` from an RLP-encoded account
+fn extract_account_hash(account_rlp: &[u8], index: usize, field: &str) -> Result<[u8; 32]> {
     let items = decode_rlp_node(account_rlp)?;
     
     // Account RLP: [nonce, balance, storage_root, code_hash]
@@ -903,15 +1776,68 @@ fn extract_storage_root_from_account(account_rlp: &[u8]) -> Result<[u8; 32]> {
         return Err(ErrorCode::InvalidStateProof.into());
     }
 
-    if items[2].len() != 32 {
-        msg!("Invalid storage root length: {}", items[2].len());
+    if items[index].len() != 32 {
+        msg!("Invalid {} length: {}", field, items[index].len());
         return Err(ErrorCode::InvalidStateProof.into());
     }
 
-    items[2].clone().try_into()
+    items[index].clone().try_into()
         .map_err(|_| ErrorCode::InvalidStateProof.into())
 }
 
+/// Check a serialized storage proof (the `verify_account_proof` format)
+/// against `state_root`
+pub fn verify_storage_proof(
+    state_root: &[u8; 32],
+    account: &[u8; 20],
+    storage_slot: &[u8; 32],
+    expected_value: &[u8; 32],
+    proof_data: &[u8],
+) -> Result<bool> {
+    let proof_nodes = deserialize_proof_nodes(proof_data)?;
+    verify_merkle_patricia_proof(account, storage_slot, expected_value, &proof_nodes, state_root)
+}
+
+/// `verify_storage_proof`, taking the account's storage root from `cache`
+/// when it was already proven against `state_root`
+///
+/// On a hit the account half of the proof is not checked at all; on a miss
+/// it is, and the storage root it proves is remembered.
+fn verify_storage_proof_cached(
+    cache: &mut ProofCache,
+    state_root: &[u8; 32],
+    account: &[u8; 20],
+    storage_slot: &[u8; 32],
+    expected_value: &[u8; 32],
+    proof_data: &[u8],
+) -> Result<bool> {
+    let proof_nodes = deserialize_proof_nodes(proof_data)?;
+    if proof_nodes.is_empty() {
+        msg!("Empty proof");
+        return Ok(false);
+    }
+
+    let storage_root = match cache.storage_root(state_root, account) {
+        Some(storage_root) => {
+            msg!("Proof cache hit");
+            storage_root
+        }
+        None => {
+            let Some(storage_root) = prove_storage_root(account, &proof_nodes, state_root)? else {
+                return Ok(false);
+            };
+            cache.insert(CachedAccount {
+                state_root: *state_root,
+                account: *account,
+                storage_root,
+            });
+            storage_root
+        }
+    };
+
+    verify_storage_value(&storage_root, storage_slot, expected_value, &proof_nodes)
+}
+
 /// Deserialize proof nodes from a serialized format
 /// Format: [num_nodes: u16][node1_len: u16][node1_data][node2_len: u16][node2_data]...
 fn deserialize_proof_nodes(data: &[u8]) -> Result<Vec<Vec<u8>>> {
@@ -941,3 +1867,512 @@ fn deserialize_proof_nodes(data: &[u8]) -> Result<Vec<Vec<u8>>> {
 
     Ok(nodes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rlp_bytes(data: &[u8]) -> Vec<u8> {
+        if data.len() == 1 && data[0] < 0x80 {
+            return data.to_vec();
+        }
+        let mut out = if data.len() <= 55 {
+            vec![0x80 + data.len() as u8]
+        } else {
+            vec![0xb8, data.len() as u8]
+        };
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload = items.concat();
+        let mut out = if payload.len() <= 55 {
+            vec![0xc0 + payload.len() as u8]
+        } else {
+            vec![0xf8, payload.len() as u8]
+        };
+        out.extend(payload);
+        out
+    }
+
+    /// Compact (hex-prefix) encoding of a leaf path
+    fn leaf_path(nibbles: &[u8]) -> Vec<u8> {
+        let mut out = if nibbles.len() % 2 == 1 {
+            vec![0x30 | nibbles[0]]
+        } else {
+            vec![0x20]
+        };
+        let rest = &nibbles[nibbles.len() % 2..];
+        out.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+        out
+    }
+
+    /// A branch root with a single leaf under the key's first nibble
+    fn sample_trie(key: &[u8], value: &[u8]) -> (Vec<Vec<u8>>, [u8; 32]) {
+        let leaf = rlp_list(&[rlp_bytes(&leaf_path(&key[1..])), rlp_bytes(value)]);
+        let mut children: Vec<Vec<u8>> = vec![vec![0x80]; 17];
+        children[key[0] as usize] = rlp_bytes(&keccak::hash(&leaf).to_bytes());
+        let branch = rlp_list(&children);
+        let root = keccak::hash(&branch).to_bytes();
+        (vec![branch, leaf], root)
+    }
+
+    #[test]
+    fn test_included_key_is_found() {
+        let key = bytes_to_nibbles(&[0x12, 0x34, 0x56, 0x78]);
+        let (proof, root) = sample_trie(&key, &[0xaa; 32]);
+
+        assert_eq!(
+            verify_trie_path(&root, &key, &proof, 0).unwrap(),
+            TrieLookup::Found(vec![0xaa; 32])
+        );
+    }
+
+    #[test]
+    fn test_missing_key_at_empty_branch_child_is_absent() {
+        let key = bytes_to_nibbles(&[0x12, 0x34, 0x56, 0x78]);
+        let missing = bytes_to_nibbles(&[0x92, 0x34, 0x56, 0x78]);
+        let (proof, root) = sample_trie(&key, &[0xaa; 32]);
+
+        assert_eq!(verify_trie_path(&root, &missing, &proof, 0).unwrap(), TrieLookup::Absent);
+    }
+
+    #[test]
+    fn test_missing_key_at_divergent_leaf_is_absent() {
+        let key = bytes_to_nibbles(&[0x12, 0x34, 0x56, 0x78]);
+        let missing = bytes_to_nibbles(&[0x12, 0x34, 0x56, 0x79]);
+        let (proof, root) = sample_trie(&key, &[0xaa; 32]);
+
+        assert_eq!(verify_trie_path(&root, &missing, &proof, 0).unwrap(), TrieLookup::Absent);
+    }
+
+    #[test]
+    fn test_truncated_proof_proves_nothing() {
+        let key = bytes_to_nibbles(&[0x12, 0x34, 0x56, 0x78]);
+        let (proof, root) = sample_trie(&key, &[0xaa; 32]);
+
+        assert!(verify_trie_path(&root, &key, &proof[..1], 0).is_err());
+    }
+
+    #[test]
+    fn test_embedded_leaf_in_branch_is_resolved() {
+        // Leaf short enough to be stored inline in its parent branch
+        let key = bytes_to_nibbles(&[0x12, 0x34]);
+        let leaf = rlp_list(&[rlp_bytes(&leaf_path(&key[1..])), rlp_bytes(&[0x05])]);
+        assert!(leaf.len() < 32);
+
+        let mut children: Vec<Vec<u8>> = vec![vec![0x80]; 17];
+        children[key[0] as usize] = leaf;
+        let branch = rlp_list(&children);
+        let root = keccak::hash(&branch).to_bytes();
+
+        assert_eq!(
+            verify_trie_path(&root, &key, &[branch.clone()], 0).unwrap(),
+            TrieLookup::Found(vec![0x05])
+        );
+
+        let divergent = bytes_to_nibbles(&[0x12, 0x35]);
+        assert_eq!(verify_trie_path(&root, &divergent, &[branch], 0).unwrap(), TrieLookup::Absent);
+    }
+
+    #[test]
+    fn test_embedded_branch_under_extension_is_resolved() {
+        // extension [1, 2] -> inline branch -> inline leaf [4] at nibble 3
+        let key = bytes_to_nibbles(&[0x12, 0x34]);
+        let leaf = rlp_list(&[rlp_bytes(&leaf_path(&key[3..])), rlp_bytes(&[0x05])]);
+
+        let mut children: Vec<Vec<u8>> = vec![vec![0x80]; 17];
+        children[key[2] as usize] = leaf;
+        let branch = rlp_list(&children);
+        assert!(branch.len() < 32);
+
+        let extension = rlp_list(&[rlp_bytes(&[0x00, 0x12]), branch]);
+        let root = keccak::hash(&extension).to_bytes();
+
+        assert_eq!(
+            verify_trie_path(&root, &key, &[extension], 0).unwrap(),
+            TrieLookup::Found(vec![0x05])
+        );
+    }
+
+    fn pending_update(finalize_at: i64) -> PendingUpdate {
+        PendingUpdate {
+            slot: 100,
+            block_root: [1u8; 32],
+            state_root: [2u8; 32],
+            current_sync_committee_root: [5u8; 32],
+            next_sync_committee_root: [3u8; 32],
+            proposer: Pubkey::new_unique(),
+            finalize_at,
+        }
+    }
+
+    #[test]
+    fn test_only_a_different_state_at_the_same_slot_conflicts() {
+        let pending = pending_update(1_000);
+
+        assert!(!pending.conflicts_with(100, &[1u8; 32], &[2u8; 32]));
+        assert!(pending.conflicts_with(100, &[9u8; 32], &[2u8; 32]));
+        assert!(pending.conflicts_with(100, &[1u8; 32], &[9u8; 32]));
+        // A later slot is a different update, not a contradiction
+        assert!(!pending.conflicts_with(101, &[9u8; 32], &[9u8; 32]));
+    }
+
+    #[test]
+    fn test_pending_update_finalizes_after_window() {
+        let pending = pending_update(1_000);
+        assert!(!pending.can_finalize(999));
+        assert!(pending.can_finalize(1_000));
+
+        let mut state = LightClientState {
+            admin: Pubkey::new_unique(),
+            chain_id: 1,
+            latest_slot: 50,
+            latest_block_root: [0u8; 32],
+            latest_state_root: [0u8; 32],
+            current_sync_committee_root: [4u8; 32],
+            next_sync_committee_root: [5u8; 32],
+            update_count: 0,
+            initialized: true,
+            permissioned_mode: true,
+            authorized_relayer: Pubkey::new_unique(),
+            challenge_period: 600,
+            pending_state: Some(pending.clone()),
+            recent_roots: [VerifiedRoot::default(); STATE_ROOT_HISTORY],
+            recent_roots_next: 0,
+        };
+
+        state.pending_state = None;
+        apply_update(
+            &mut state,
+            pending.slot,
+            pending.block_root,
+            pending.state_root,
+            pending.current_sync_committee_root,
+            pending.next_sync_committee_root,
+        );
+
+        assert_eq!(state.latest_slot, 100);
+        assert_eq!(state.latest_state_root, [2u8; 32]);
+        assert_eq!(state.current_sync_committee_root, [5u8; 32]);
+        assert_eq!(state.next_sync_committee_root, [3u8; 32]);
+        assert_eq!(state.update_count, 1);
+    }
+
+    fn empty_state() -> LightClientState {
+        LightClientState {
+            admin: Pubkey::default(),
+            chain_id: 0,
+            latest_slot: 0,
+            latest_block_root: [0u8; 32],
+            latest_state_root: [0u8; 32],
+            current_sync_committee_root: [0u8; 32],
+            next_sync_committee_root: [0u8; 32],
+            update_count: 0,
+            initialized: false,
+            permissioned_mode: false,
+            authorized_relayer: Pubkey::default(),
+            challenge_period: 0,
+            pending_state: None,
+            recent_roots: [VerifiedRoot::default(); STATE_ROOT_HISTORY],
+            recent_roots_next: 0,
+        }
+    }
+
+    #[test]
+    fn test_two_chains_are_initialized_independently() {
+        let admin = Pubkey::new_unique();
+        let (mainnet_address, _) = state_address(1);
+        let (base_address, _) = state_address(8453);
+        assert_ne!(mainnet_address, base_address);
+        assert_eq!(state_address(1).0, mainnet_address);
+
+        let mut mainnet = empty_state();
+        let mut base = empty_state();
+        init_state(&mut mainnet, admin, 1, 100, [1u8; 32], [2u8; 32], [3u8; 32]);
+        init_state(&mut base, admin, 8453, 7_000, [4u8; 32], [5u8; 32], [6u8; 32]);
+
+        apply_update(&mut mainnet, 200, [7u8; 32], [8u8; 32], [3u8; 32], [9u8; 32]);
+
+        assert_eq!(mainnet.chain_id, 1);
+        assert_eq!(mainnet.latest_slot, 200);
+        assert_eq!(mainnet.latest_state_root, [8u8; 32]);
+
+        assert_eq!(base.chain_id, 8453);
+        assert_eq!(base.latest_slot, 7_000);
+        assert_eq!(base.latest_state_root, [5u8; 32]);
+        assert_eq!(base.current_sync_committee_root, [6u8; 32]);
+        assert_eq!(base.update_count, 0);
+    }
+
+    #[test]
+    fn test_only_the_upgrade_authority_initializes_a_chain() {
+        let authority = Pubkey::new_unique();
+        let program_data = ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(authority),
+        };
+        assert!(can_initialize(&program_data, &authority));
+        assert!(!can_initialize(&program_data, &Pubkey::new_unique()));
+
+        let immutable = ProgramData {
+            slot: 0,
+            upgrade_authority_address: None,
+        };
+        assert!(!can_initialize(&immutable, &authority));
+    }
+
+    fn attested(signing: u8, next: u8) -> AttestedCommittees {
+        AttestedCommittees {
+            signing: [signing; 32],
+            next: [next; 32],
+        }
+    }
+
+    #[test]
+    fn test_first_update_learns_next_committee() {
+        let (current, next) = rotate_sync_committees(&[1u8; 32], &[0u8; 32], &attested(1, 2)).unwrap();
+        assert_eq!((current, next), ([1u8; 32], [2u8; 32]));
+    }
+
+    #[test]
+    fn test_next_committee_cannot_change_within_a_period() {
+        assert!(rotate_sync_committees(&[1u8; 32], &[2u8; 32], &attested(1, 2)).is_ok());
+        assert!(rotate_sync_committees(&[1u8; 32], &[2u8; 32], &attested(1, 7)).is_err());
+    }
+
+    #[test]
+    fn test_rotation_boundary_promotes_next_committee() {
+        // First update of period N+1, signed by the committee stored as next
+        let (current, next) = rotate_sync_committees(&[1u8; 32], &[2u8; 32], &attested(2, 3)).unwrap();
+        assert_eq!((current, next), ([2u8; 32], [3u8; 32]));
+
+        // The old committee can no longer sign
+        assert!(rotate_sync_committees(&current, &next, &attested(1, 3)).is_err());
+        // Nor can a committee that was never attested
+        assert!(rotate_sync_committees(&current, &next, &attested(9, 3)).is_err());
+    }
+
+    #[test]
+    fn test_rotation_from_old_period_state_leaves_next_unknown() {
+        // Signed in period N+1 over a state from period N, whose next is the signer
+        let (current, next) = rotate_sync_committees(&[1u8; 32], &[2u8; 32], &attested(2, 2)).unwrap();
+        assert_eq!((current, next), ([2u8; 32], [0u8; 32]));
+
+        // The next update in the new period teaches the real next committee
+        let (current, next) = rotate_sync_committees(&current, &next, &attested(2, 3)).unwrap();
+        assert_eq!((current, next), ([2u8; 32], [3u8; 32]));
+    }
+
+    #[test]
+    fn test_unattested_next_committee_is_rejected() {
+        assert!(rotate_sync_committees(&[1u8; 32], &[2u8; 32], &attested(1, 0)).is_err());
+    }
+
+    #[test]
+    fn test_public_inputs_expose_both_committees() {
+        let mut inputs = vec![0u8; PUBLIC_INPUTS_SIZE];
+        inputs[0..8].copy_from_slice(&10u64.to_le_bytes());
+        inputs[40..48].copy_from_slice(&20u64.to_le_bytes());
+        inputs[112..144].copy_from_slice(&[1u8; 32]);
+        inputs[148..180].copy_from_slice(&[2u8; 32]);
+
+        let (_, committees) =
+            validate_public_inputs(&inputs, 10, &[0u8; 32], 20, &[0u8; 32], &[0u8; 32]).unwrap();
+        assert_eq!(committees, attested(1, 2));
+
+        assert!(validate_public_inputs(&inputs[..148], 10, &[0u8; 32], 20, &[0u8; 32], &[0u8; 32]).is_err());
+    }
+
+    /// RLP account: [nonce, balance, storage_root, code_hash]
+    fn account_rlp(code_hash: &[u8; 32]) -> Vec<u8> {
+        rlp_list(&[
+            rlp_bytes(&[0x01]),
+            rlp_bytes(&[]),
+            rlp_bytes(&[0x56; 32]),
+            rlp_bytes(code_hash),
+        ])
+    }
+
+    #[test]
+    fn test_empty_code_hash_is_keccak_of_nothing() {
+        assert_eq!(keccak::hash(&[]).to_bytes(), EMPTY_CODE_HASH);
+    }
+
+    #[test]
+    fn test_contract_code_hash_is_verified() {
+        let contract = [0x42u8; 20];
+        let code_hash = keccak::hash(b"bridge bytecode").to_bytes();
+        let key = bytes_to_nibbles(&keccak::hash(&contract).to_bytes());
+        let (proof, root) = sample_trie(&key, &account_rlp(&code_hash));
+
+        assert!(verify_account_code_hash(&contract, &code_hash, &proof, &root).unwrap());
+        assert!(!verify_account_code_hash(&contract, &[0x99; 32], &proof, &root).unwrap());
+
+        // A different address is not in the trie
+        assert!(!verify_account_code_hash(&[0x43u8; 20], &code_hash, &proof, &root).unwrap());
+    }
+
+    #[test]
+    fn test_account_without_code_is_rejected() {
+        let eoa = [0x42u8; 20];
+        let key = bytes_to_nibbles(&keccak::hash(&eoa).to_bytes());
+        let (proof, root) = sample_trie(&key, &account_rlp(&EMPTY_CODE_HASH));
+
+        assert!(verify_account_code_hash(&eoa, &[0x99; 32], &proof, &root).is_err());
+    }
+
+    /// Two-level proof of `value` at `storage_slot` of `account`
+    fn storage_proof(
+        account: &[u8; 20],
+        storage_slot: &[u8; 32],
+        value: &[u8],
+    ) -> (Vec<Vec<u8>>, [u8; 32]) {
+        let storage_key = bytes_to_nibbles(&keccak::hash(storage_slot).to_bytes());
+        let (storage_nodes, storage_root) = sample_trie(&storage_key, value);
+        let account_value = rlp_list(&[
+            rlp_bytes(&[0x01]),
+            rlp_bytes(&[]),
+            rlp_bytes(&storage_root),
+            rlp_bytes(&EMPTY_CODE_HASH),
+        ]);
+        let account_key = bytes_to_nibbles(&keccak::hash(account).to_bytes());
+        let (mut nodes, state_root) = sample_trie(&account_key, &account_value);
+        nodes.extend(storage_nodes);
+        (nodes, state_root)
+    }
+
+    fn empty_cache() -> ProofCache {
+        ProofCache {
+            chain_id: 1,
+            next: 0,
+            len: 0,
+            entries: [CachedAccount::default(); PROOF_CACHE_SIZE],
+        }
+    }
+
+    fn cached(root: u8, account: u8) -> CachedAccount {
+        CachedAccount {
+            state_root: [root; 32],
+            account: [account; 20],
+            storage_root: [0x56; 32],
+        }
+    }
+
+    fn serialize(nodes: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = (nodes.len() as u16).to_le_bytes().to_vec();
+        for node in nodes {
+            data.extend_from_slice(&(node.len() as u16).to_le_bytes());
+            data.extend_from_slice(node);
+        }
+        data
+    }
+
+    #[test]
+    fn test_cache_hit_skips_the_account_proof() {
+        let account = [0x42u8; 20];
+        let slot = [0x07u8; 32];
+        let (nodes, root) = storage_proof(&account, &slot, &[0xaa; 32]);
+        let proof = serialize(&nodes);
+
+        // Same storage half, account half replaced with junk
+        let mut tampered = nodes.clone();
+        tampered[0] = vec![0xc0; 40];
+        tampered[1] = vec![0xc1; 40];
+        let tampered = serialize(&tampered);
+
+        let mut cache = empty_cache();
+        assert!(verify_storage_proof_cached(&mut cache, &root, &account, &slot, &[0xaa; 32], &tampered).is_err());
+        assert_eq!(cache.len, 0);
+
+        // A genuine proof warms the cache, after which the account half isn't re-walked
+        assert!(verify_storage_proof_cached(&mut cache, &root, &account, &slot, &[0xaa; 32], &proof).unwrap());
+        assert_eq!(cache.len, 1);
+        assert!(verify_storage_proof_cached(&mut cache, &root, &account, &slot, &[0xaa; 32], &tampered).unwrap());
+        assert_eq!(cache.len, 1);
+
+        // The storage half is still checked on a hit
+        assert!(!verify_storage_proof_cached(&mut cache, &root, &account, &slot, &[0xbb; 32], &tampered).unwrap());
+
+        // Without the cache the tampered proof still fails
+        assert!(verify_storage_proof(&root, &account, &slot, &[0xaa; 32], &tampered).is_err());
+    }
+
+    #[test]
+    fn test_cache_entries_only_match_their_state_root() {
+        let mut cache = empty_cache();
+        cache.insert(cached(1, 0x42));
+
+        assert_eq!(cache.storage_root(&[1u8; 32], &[0x42; 20]), Some([0x56; 32]));
+        assert_eq!(cache.storage_root(&[2u8; 32], &[0x42; 20]), None);
+        assert_eq!(cache.storage_root(&[1u8; 32], &[0x43; 20]), None);
+    }
+
+    #[test]
+    fn test_full_cache_evicts_the_oldest_entry() {
+        let mut cache = empty_cache();
+        for account in 0..PROOF_CACHE_SIZE as u8 {
+            cache.insert(cached(1, account));
+        }
+        assert!(cache.storage_root(&[1u8; 32], &[0; 20]).is_some());
+
+        cache.insert(cached(1, PROOF_CACHE_SIZE as u8));
+        assert_eq!(cache.len as usize, PROOF_CACHE_SIZE);
+        assert!(cache.storage_root(&[1u8; 32], &[0; 20]).is_none());
+        assert!(cache.storage_root(&[1u8; 32], &[1; 20]).is_some());
+        assert!(cache.storage_root(&[1u8; 32], &[PROOF_CACHE_SIZE as u8; 20]).is_some());
+    }
+
+    #[test]
+    fn test_unused_entries_never_match() {
+        // A zeroed entry must not vouch for the zero address under a zero root
+        let cache = empty_cache();
+        assert!(cache.storage_root(&[0u8; 32], &[0u8; 20]).is_none());
+    }
+
+    #[test]
+    fn test_finalized_roots_are_kept_by_slot() {
+        let mut state = empty_state();
+        init_state(&mut state, Pubkey::new_unique(), 1, 100, [1u8; 32], [2u8; 32], [3u8; 32]);
+        apply_update(&mut state, 200, [4u8; 32], [5u8; 32], [3u8; 32], [6u8; 32]);
+
+        assert_eq!(state.state_root_at(100), Some([2u8; 32]));
+        assert_eq!(state.state_root_at(200), Some([5u8; 32]));
+        assert_eq!(state.state_root_at(150), None);
+
+        // Once the history wraps, the genesis root is gone
+        for i in 0..STATE_ROOT_HISTORY as u64 - 1 {
+            apply_update(&mut state, 300 + i, [4u8; 32], [7u8; 32], [3u8; 32], [6u8; 32]);
+        }
+        assert_eq!(state.state_root_at(100), None);
+        assert_eq!(state.state_root_at(200), Some([5u8; 32]));
+    }
+
+    #[test]
+    fn test_unused_history_entries_never_match() {
+        // A zeroed entry must not vouch for a zero root at slot 0
+        let state = empty_state();
+        assert_eq!(state.state_root_at(0), None);
+    }
+
+    #[test]
+    fn test_proof_is_checked_against_the_root_at_its_slot() {
+        let account = [0x42u8; 20];
+        let slot = [0x07u8; 32];
+        let (nodes, root) = storage_proof(&account, &slot, &[0xaa; 32]);
+        let proof_data = serialize(&nodes);
+
+        assert!(verify_storage_proof(&root, &account, &slot, &[0xaa; 32], &proof_data).unwrap());
+        assert!(verify_storage_proof(&[9u8; 32], &account, &slot, &[0xaa; 32], &proof_data).is_err());
+    }
+
+    #[test]
+    fn test_tampered_root_is_rejected() {
+        let key = bytes_to_nibbles(&[0x12, 0x34, 0x56, 0x78]);
+        let missing = bytes_to_nibbles(&[0x92, 0x34, 0x56, 0x78]);
+        let (proof, _) = sample_trie(&key, &[0xaa; 32]);
+
+        assert!(verify_trie_path(&[0u8; 32], &missing, &proof, 0).is_err());
+    }
+}
@@ -15,6 +15,13 @@ pub const PRESALE_SEED: &[u8] = b"presale";
 pub const VAULT_SEED: &[u8] = b"vault";  // For bonding curve SOL vault
 pub const PRESALE_VAULT_SEED: &[u8] = b"presale-vault";  // Separate seed for presale vault
 pub const LP_LOCK_SEED: &[u8] = b"lp-lock";
+pub const CREATOR_FEE_VAULT_SEED: &[u8] = b"creator-fee-vault";
+pub const PROTOCOL_FEE_VAULT_SEED: &[u8] = b"protocol-fee-vault";
+pub const LAUNCH_SEED: &[u8] = b"launch";
+pub const CREATOR_PROFILE_SEED: &[u8] = b"creator-profile";
+
+/// Most presales one creator profile can track at a time
+pub const MAX_CREATOR_PRESALES: usize = 16;
 
 // Default bonding curve parameters
 pub const DEFAULT_VIRTUAL_SOL_RESERVES: u64 = 30_000_000_000; // 30 SOL
@@ -24,6 +31,12 @@ pub const DEFAULT_GRADUATION_THRESHOLD: u64 = 85_000_000_000; // 85 SOL
 // Fee basis points
 pub const PLATFORM_FEE_BPS: u16 = 100; // 1%
 pub const MAX_CREATOR_FEE_BPS: u16 = 1000; // 10%
+pub const MAX_REFERRAL_FEE_BPS: u16 = 50; // 0.5%, further capped at the platform fee
+pub const MAX_POOL_FEE_BPS: u16 = 1000; // 10%
+pub const MAX_MIGRATION_FEE_BPS: u16 = 1000; // 10%
+
+/// A presale left unfinalized this long past its end time is treated as abandoned
+pub const FORCE_REFUND_DELAY: i64 = 90 * 24 * 60 * 60; // 90 days
 
 #[program]
 pub mod jeju_launchpad {
@@ -41,10 +54,30 @@ pub mod jeju_launchpad {
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
         config.authority = ctx.accounts.authority.key();
+        config.pending_authority = None;
         config.fee_recipient = fee_recipient;
         config.platform_fee_bps = platform_fee_bps;
         config.total_launches = 0;
         config.bump = ctx.bumps.config;
+        config.protocol_fees_claimed = 0;
+        config.protocol_fee_vault_bump = ctx.bumps.protocol_fee_vault;
+        config.referral_fee_bps = 0;
+        config.paused = false;
+        config.migration = None;
+
+        // Fund the fee vault up to rent exemption so small fees can land in it
+        let rent = Rent::get()?.minimum_balance(0);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.protocol_fee_vault.to_account_info(),
+                },
+            ),
+            rent,
+        )?;
+
         Ok(())
     }
 
@@ -57,6 +90,7 @@ pub mod jeju_launchpad {
         creator_fee_bps: u16,
         graduation_threshold: u64,
     ) -> Result<()> {
+        require!(!ctx.accounts.config.paused, LaunchpadError::Paused);
         require!(name.len() <= 32, LaunchpadError::NameTooLong);
         require!(symbol.len() <= 10, LaunchpadError::SymbolTooLong);
         require!(uri.len() <= 200, LaunchpadError::UriTooLong);
@@ -67,6 +101,7 @@ pub mod jeju_launchpad {
         let creator_key = ctx.accounts.creator.key();
         let bump = ctx.bumps.bonding_curve;
         let vault_bump = ctx.bumps.sol_vault;
+        let creator_fee_vault_bump = ctx.bumps.creator_fee_vault;
         let token_program_info = ctx.accounts.token_program.to_account_info();
         let token_mint_info = ctx.accounts.token_mint.to_account_info();
         let curve_token_account_info = ctx.accounts.curve_token_account.to_account_info();
@@ -93,6 +128,24 @@ pub mod jeju_launchpad {
         curve.created_at = Clock::get()?.unix_timestamp;
         curve.bump = bump;
         curve.vault_bump = vault_bump;
+        curve.fees_accrued = 0;
+        curve.fees_claimed = 0;
+        curve.creator_fee_vault_bump = creator_fee_vault_bump;
+        curve.platform_fee_override = None;
+        curve.referral_fees_paid = 0;
+
+        // Fund the creator fee vault up to rent exemption so small fees can land in it
+        let rent = Rent::get()?.minimum_balance(0);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.creator.to_account_info(),
+                    to: ctx.accounts.creator_fee_vault.to_account_info(),
+                },
+            ),
+            rent,
+        )?;
 
         // Mint initial supply to curve vault
         let seeds = &[
@@ -115,8 +168,15 @@ pub mod jeju_launchpad {
             DEFAULT_VIRTUAL_TOKEN_RESERVES,
         )?;
 
-        // Update config
+        // Record the launch at the next index so launches can be enumerated
         let config = &mut ctx.accounts.config;
+        let launch = &mut ctx.accounts.launch;
+        launch.index = config.total_launches;
+        launch.token_mint = token_mint_key;
+        launch.creator = creator_key;
+        launch.created_at = curve.created_at;
+        launch.bump = ctx.bumps.launch;
+
         config.total_launches += 1;
 
         emit!(TokenCreated {
@@ -136,273 +196,262 @@ pub mod jeju_launchpad {
         sol_amount: u64,
         min_tokens_out: u64,
     ) -> Result<()> {
-        // Get values before mutable borrow
-        let token_mint = ctx.accounts.bonding_curve.token_mint;
-        let bump = ctx.accounts.bonding_curve.bump;
-        let creator_fee_bps = ctx.accounts.bonding_curve.creator_fee_bps;
-        let platform_fee_bps = ctx.accounts.config.platform_fee_bps;
-        let bonding_curve_info = ctx.accounts.bonding_curve.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
-        let curve_token_account_info = ctx.accounts.curve_token_account.to_account_info();
-        let buyer_token_account_info = ctx.accounts.buyer_token_account.to_account_info();
-
-        let curve = &ctx.accounts.bonding_curve;
-        require!(!curve.graduated, LaunchpadError::AlreadyGraduated);
-        require!(sol_amount > 0, LaunchpadError::InvalidAmount);
-
-        // Calculate tokens out using constant product formula
-        // (virtualSol + solIn) * (virtualToken - tokenOut) = k
-        let k = (curve.virtual_sol_reserves as u128)
-            .checked_mul(curve.virtual_token_reserves as u128)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let new_virtual_sol = curve.virtual_sol_reserves
-            .checked_add(sol_amount)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let new_virtual_token = k
-            .checked_div(new_virtual_sol as u128)
-            .ok_or(LaunchpadError::MathOverflow)? as u64;
-
-        let tokens_out = curve.virtual_token_reserves
-            .checked_sub(new_virtual_token)
-            .ok_or(LaunchpadError::MathOverflow)?;
+        process_buy(ctx.accounts, None, sol_amount, BuyLimit::MinTokensOut(min_tokens_out))
+    }
 
-        // Apply fees
-        let platform_fee = sol_amount
-            .checked_mul(platform_fee_bps as u64)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(LaunchpadError::MathOverflow)?;
+    /// Spend exactly `sol_amount` on the bonding curve, failing if the
+    /// average execution price (SOL spent over tokens received, scaled like
+    /// `calculate_price`) would be above `max_price`
+    pub fn buy_exact_sol(
+        ctx: Context<BuyTokens>,
+        sol_amount: u64,
+        max_price: u64,
+    ) -> Result<()> {
+        process_buy(ctx.accounts, None, sol_amount, BuyLimit::MaxPrice(max_price))
+    }
 
-        let creator_fee = sol_amount
-            .checked_mul(creator_fee_bps as u64)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(LaunchpadError::MathOverflow)?;
+    /// Buy tokens, paying part of the platform fee to a referrer
+    /// No referral is paid when the referrer is the buyer or the curve creator,
+    /// or when the cut would leave an unfunded referrer below rent exemption
+    pub fn buy_with_referral(
+        ctx: Context<BuyWithReferral>,
+        sol_amount: u64,
+        min_tokens_out: u64,
+    ) -> Result<()> {
+        let referrer = ctx.accounts.referrer.to_account_info();
+        process_buy(
+            &mut ctx.accounts.buy,
+            Some(&referrer),
+            sol_amount,
+            BuyLimit::MinTokensOut(min_tokens_out),
+        )
+    }
 
-        let total_fees = platform_fee.checked_add(creator_fee).ok_or(LaunchpadError::MathOverflow)?;
-        let net_sol = sol_amount.checked_sub(total_fees).ok_or(LaunchpadError::MathOverflow)?;
+    /// Sell tokens back to the bonding curve
+    pub fn sell(
+        ctx: Context<SellTokens>,
+        token_amount: u64,
+        min_sol_out: u64,
+    ) -> Result<()> {
+        // Get values before mutable borrow
+        let token_mint = ctx.accounts.bonding_curve.token_mint;
+        let vault_bump = ctx.accounts.bonding_curve.vault_bump;
+        let platform_fee_bps = effective_platform_fee_bps(
+            ctx.accounts.config.platform_fee_bps,
+            ctx.accounts.bonding_curve.platform_fee_override,
+        );
+        
+        require!(!ctx.accounts.config.paused, LaunchpadError::Paused);
+        let quote = quote_sell(&ctx.accounts.bonding_curve, token_amount, min_sol_out, platform_fee_bps)?;
+        let SellQuote { new_virtual_sol, new_virtual_token, platform_fee, creator_fee, net_sol, .. } = quote;
 
-        // Apply slippage check on tokens after fee
-        let tokens_after_fee = tokens_out
-            .checked_mul(10000 - PLATFORM_FEE_BPS as u64)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(LaunchpadError::MathOverflow)?;
+        // Transfer tokens from seller to curve
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.seller_token_account.to_account_info(),
+                    to: ctx.accounts.curve_token_account.to_account_info(),
+                    authority: ctx.accounts.seller.to_account_info(),
+                },
+            ),
+            token_amount,
+        )?;
 
-        require!(tokens_after_fee >= min_tokens_out, LaunchpadError::SlippageExceeded);
+        // Transfer SOL to seller
+        let vault_seeds = &[
+            VAULT_SEED,
+            token_mint.as_ref(),
+            &[vault_bump],
+        ];
+        let vault_signer = &[&vault_seeds[..]];
 
-        // Transfer SOL from buyer to vault
         anchor_lang::system_program::transfer(
-            CpiContext::new(
+            CpiContext::new_with_signer(
                 ctx.accounts.system_program.to_account_info(),
                 anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.buyer.to_account_info(),
-                    to: ctx.accounts.sol_vault.to_account_info(),
+                    from: ctx.accounts.sol_vault.to_account_info(),
+                    to: ctx.accounts.seller.to_account_info(),
                 },
+                vault_signer,
             ),
             net_sol,
         )?;
 
-        // Transfer platform fee
+        // Move fees out of the reserves into their vaults
         if platform_fee > 0 {
             anchor_lang::system_program::transfer(
-                CpiContext::new(
+                CpiContext::new_with_signer(
                     ctx.accounts.system_program.to_account_info(),
                     anchor_lang::system_program::Transfer {
-                        from: ctx.accounts.buyer.to_account_info(),
-                        to: ctx.accounts.fee_recipient.to_account_info(),
+                        from: ctx.accounts.sol_vault.to_account_info(),
+                        to: ctx.accounts.protocol_fee_vault.to_account_info(),
                     },
+                    vault_signer,
                 ),
                 platform_fee,
             )?;
         }
 
-        // Transfer creator fee
         if creator_fee > 0 {
             anchor_lang::system_program::transfer(
-                CpiContext::new(
+                CpiContext::new_with_signer(
                     ctx.accounts.system_program.to_account_info(),
                     anchor_lang::system_program::Transfer {
-                        from: ctx.accounts.buyer.to_account_info(),
-                        to: ctx.accounts.creator.to_account_info(),
+                        from: ctx.accounts.sol_vault.to_account_info(),
+                        to: ctx.accounts.creator_fee_vault.to_account_info(),
                     },
+                    vault_signer,
                 ),
                 creator_fee,
             )?;
         }
 
-        // Transfer tokens to buyer
+        // Update curve state (need mutable borrow now)
+        apply_sell(&mut ctx.accounts.bonding_curve, &quote, token_amount)?;
+
+        emit!(TokensSold {
+            token_mint,
+            seller: ctx.accounts.seller.key(),
+            tokens_sold: token_amount,
+            sol_received: net_sol,
+            new_price: calculate_price(new_virtual_sol, new_virtual_token),
+            platform_fee_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Look up the `index`th launch (read-only, for indexers). Launches are
+    /// numbered from 0 up to `LaunchpadConfig.total_launches`.
+    ///
+    /// # Return Data
+    /// The Borsh-encoded `LaunchRecord`, readable via `get_return_data()` or
+    /// transaction simulation.
+    pub fn get_launch(ctx: Context<GetLaunch>, _index: u64) -> Result<LaunchRecord> {
+        Ok((*ctx.accounts.launch).clone())
+    }
+
+    /// Snapshot a bonding curve's market state (read-only, for indexers)
+    ///
+    /// # Return Data
+    /// The Borsh-encoded `CurveState`, readable via `get_return_data()` or
+    /// transaction simulation.
+    pub fn get_curve_state(ctx: Context<GetCurveState>) -> Result<CurveState> {
+        let curve = &ctx.accounts.bonding_curve;
+
+        let progress_bps = if curve.graduation_threshold == 0 {
+            10000
+        } else {
+            (curve.real_sol_reserves as u128 * 10000 / curve.graduation_threshold as u128)
+                .min(10000) as u16
+        };
+
+        Ok(CurveState {
+            token_mint: curve.token_mint,
+            virtual_sol_reserves: curve.virtual_sol_reserves,
+            virtual_token_reserves: curve.virtual_token_reserves,
+            real_sol_reserves: curve.real_sol_reserves,
+            tokens_sold: curve.tokens_sold,
+            price: calculate_price(curve.virtual_sol_reserves, curve.virtual_token_reserves),
+            graduation_threshold: curve.graduation_threshold,
+            progress_bps,
+            graduated: curve.graduated,
+        })
+    }
+
+    /// Preview the liquidity a curve would migrate to the AMM at graduation:
+    /// the SOL and tokens seeded into the pool, the tokens burned, and the
+    /// pool's opening price. Works before graduation on the current reserves.
+    ///
+    /// # Return Data
+    /// The Borsh-encoded `MigrationPreview`, readable via `get_return_data()` or
+    /// transaction simulation.
+    pub fn preview_migration(ctx: Context<PreviewMigration>) -> Result<MigrationPreview> {
+        let migration = ctx.accounts.config.migration
+            .ok_or(LaunchpadError::MigrationNotConfigured)?;
+        migration_preview(&ctx.accounts.bonding_curve, &migration)
+    }
+
+    /// Sweep accrued creator fees to the curve creator
+    pub fn claim_creator_fees(ctx: Context<ClaimCreatorFees>) -> Result<()> {
+        let curve = &ctx.accounts.bonding_curve;
+        let amount = curve.fees_accrued
+            .checked_sub(curve.fees_claimed)
+            .ok_or(LaunchpadError::MathOverflow)?;
+        require!(amount > 0, LaunchpadError::NothingToClaim);
+
+        let token_mint = curve.token_mint;
         let seeds = &[
-            BONDING_CURVE_SEED,
+            CREATOR_FEE_VAULT_SEED,
             token_mint.as_ref(),
-            &[bump],
+            &[curve.creator_fee_vault_bump],
         ];
         let signer = &[&seeds[..]];
 
-        token::transfer(
+        anchor_lang::system_program::transfer(
             CpiContext::new_with_signer(
-                token_program_info,
-                Transfer {
-                    from: curve_token_account_info,
-                    to: buyer_token_account_info,
-                    authority: bonding_curve_info,
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.creator_fee_vault.to_account_info(),
+                    to: ctx.accounts.creator.to_account_info(),
                 },
                 signer,
             ),
-            tokens_after_fee,
+            amount,
         )?;
 
-        // Update curve state (mutable borrow now)
         let curve = &mut ctx.accounts.bonding_curve;
-        let old_real_sol = curve.real_sol_reserves;
-        let old_real_token = curve.real_token_reserves;
-        let old_tokens_sold = curve.tokens_sold;
-
-        curve.virtual_sol_reserves = new_virtual_sol;
-        curve.virtual_token_reserves = new_virtual_token;
-        curve.real_sol_reserves = old_real_sol
-            .checked_add(net_sol)
-            .ok_or(LaunchpadError::MathOverflow)?;
-        curve.real_token_reserves = old_real_token
-            .checked_sub(tokens_after_fee)
-            .ok_or(LaunchpadError::MathOverflow)?;
-        curve.tokens_sold = old_tokens_sold
-            .checked_add(tokens_after_fee)
-            .ok_or(LaunchpadError::MathOverflow)?;
+        curve.fees_claimed = curve.fees_accrued;
 
-        emit!(TokensBought {
-            token_mint: curve.token_mint,
-            buyer: ctx.accounts.buyer.key(),
-            sol_amount,
-            tokens_bought: tokens_after_fee,
-            new_price: calculate_price(curve.virtual_sol_reserves, curve.virtual_token_reserves),
+        emit!(CreatorFeesClaimed {
+            token_mint,
+            creator: ctx.accounts.creator.key(),
+            amount,
+            total_claimed: curve.fees_claimed,
         });
 
-        // Check graduation
-        if curve.real_sol_reserves >= curve.graduation_threshold {
-            curve.graduated = true;
-            emit!(TokenGraduated {
-                token_mint: curve.token_mint,
-                sol_raised: curve.real_sol_reserves,
-                tokens_sold: curve.tokens_sold,
-            });
-        }
-
         Ok(())
     }
 
-    /// Sell tokens back to the bonding curve
-    pub fn sell(
-        ctx: Context<SellTokens>,
-        token_amount: u64,
-        min_sol_out: u64,
-    ) -> Result<()> {
-        // Get values before mutable borrow
-        let token_mint = ctx.accounts.bonding_curve.token_mint;
-        let vault_bump = ctx.accounts.bonding_curve.vault_bump;
-        let creator_fee_bps = ctx.accounts.bonding_curve.creator_fee_bps;
-        let platform_fee_bps = ctx.accounts.config.platform_fee_bps;
-        
-        let curve = &ctx.accounts.bonding_curve;
-        require!(!curve.graduated, LaunchpadError::AlreadyGraduated);
-        require!(token_amount > 0, LaunchpadError::InvalidAmount);
-
-        // Calculate SOL out using constant product formula
-        // (virtualToken + tokenIn) * (virtualSol - solOut) = k
-        let k = (curve.virtual_sol_reserves as u128)
-            .checked_mul(curve.virtual_token_reserves as u128)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let new_virtual_token = curve.virtual_token_reserves
-            .checked_add(token_amount)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let new_virtual_sol = k
-            .checked_div(new_virtual_token as u128)
-            .ok_or(LaunchpadError::MathOverflow)? as u64;
-
-        let sol_out = curve.virtual_sol_reserves
-            .checked_sub(new_virtual_sol)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        // Apply fees
-        let platform_fee = sol_out
-            .checked_mul(platform_fee_bps as u64)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let creator_fee = sol_out
-            .checked_mul(creator_fee_bps as u64)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let total_fees = platform_fee.checked_add(creator_fee).ok_or(LaunchpadError::MathOverflow)?;
-        let net_sol = sol_out.checked_sub(total_fees).ok_or(LaunchpadError::MathOverflow)?;
-
-        require!(net_sol >= min_sol_out, LaunchpadError::SlippageExceeded);
-        require!(curve.real_sol_reserves >= net_sol, LaunchpadError::InsufficientLiquidity);
-
-        // Transfer tokens from seller to curve
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.seller_token_account.to_account_info(),
-                    to: ctx.accounts.curve_token_account.to_account_info(),
-                    authority: ctx.accounts.seller.to_account_info(),
-                },
-            ),
-            token_amount,
-        )?;
+    /// Sweep accrued platform fees to the fee recipient (authority only).
+    /// Trades pay fees straight into the vault without touching the config, so
+    /// what is claimable is the vault's balance above rent exemption.
+    pub fn claim_protocol_fees(ctx: Context<ClaimProtocolFees>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let amount = claimable_protocol_fees(
+            ctx.accounts.protocol_fee_vault.lamports(),
+            Rent::get()?.minimum_balance(0),
+        );
+        require!(amount > 0, LaunchpadError::NothingToClaim);
 
-        // Transfer SOL to seller
-        let vault_seeds = &[
-            VAULT_SEED,
-            token_mint.as_ref(),
-            &[vault_bump],
+        let seeds = &[
+            PROTOCOL_FEE_VAULT_SEED,
+            &[config.protocol_fee_vault_bump],
         ];
-        let vault_signer = &[&vault_seeds[..]];
+        let signer = &[&seeds[..]];
 
         anchor_lang::system_program::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.system_program.to_account_info(),
                 anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.sol_vault.to_account_info(),
-                    to: ctx.accounts.seller.to_account_info(),
+                    from: ctx.accounts.protocol_fee_vault.to_account_info(),
+                    to: ctx.accounts.fee_recipient.to_account_info(),
                 },
-                vault_signer,
+                signer,
             ),
-            net_sol,
+            amount,
         )?;
 
-        // Update curve state (need mutable borrow now)
-        let curve = &mut ctx.accounts.bonding_curve;
-        let old_real_sol = curve.real_sol_reserves;
-        let old_real_token = curve.real_token_reserves;
-        let old_tokens_sold = curve.tokens_sold;
-        
-        curve.virtual_sol_reserves = new_virtual_sol;
-        curve.virtual_token_reserves = new_virtual_token;
-        curve.real_sol_reserves = old_real_sol
-            .checked_sub(sol_out)
-            .ok_or(LaunchpadError::MathOverflow)?;
-        curve.real_token_reserves = old_real_token
-            .checked_add(token_amount)
-            .ok_or(LaunchpadError::MathOverflow)?;
-        curve.tokens_sold = old_tokens_sold
-            .checked_sub(token_amount)
+        let config = &mut ctx.accounts.config;
+        config.protocol_fees_claimed = config.protocol_fees_claimed
+            .checked_add(amount)
             .ok_or(LaunchpadError::MathOverflow)?;
 
-        emit!(TokensSold {
-            token_mint,
-            seller: ctx.accounts.seller.key(),
-            tokens_sold: token_amount,
-            sol_received: net_sol,
-            new_price: calculate_price(new_virtual_sol, new_virtual_token),
+        emit!(ProtocolFeesClaimed {
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            amount,
+            total_claimed: config.protocol_fees_claimed,
         });
 
         Ok(())
@@ -448,6 +497,7 @@ pub mod jeju_launchpad {
         presale.cancelled = false;
         presale.bump = ctx.bumps.presale;
         presale.vault_bump = ctx.bumps.presale_vault;
+        presale.paused = false;
 
         emit!(PresaleCreated {
             presale: presale.key(),
@@ -483,19 +533,26 @@ pub mod jeju_launchpad {
         ctx: Context<Contribute>,
         amount: u64,
     ) -> Result<()> {
+        require!(!ctx.accounts.config.paused, LaunchpadError::Paused);
         let presale = &mut ctx.accounts.presale;
         let clock = Clock::get()?;
 
         require!(!presale.finalized, LaunchpadError::PresaleFinalized);
         require!(!presale.cancelled, LaunchpadError::PresaleCancelled);
+        require!(!presale.paused, LaunchpadError::PresalePaused);
         require!(clock.unix_timestamp >= presale.start_time, LaunchpadError::PresaleNotStarted);
         require!(clock.unix_timestamp <= presale.end_time, LaunchpadError::PresaleEnded);
         require!(amount >= presale.min_contribution, LaunchpadError::BelowMinContribution);
-        
+
+        // Fill up to the hard cap; the excess is never taken from the contributor
+        let remaining_cap = presale.hard_cap.saturating_sub(presale.total_raised);
+        require!(remaining_cap > 0, LaunchpadError::HardCapReached);
+        let accepted = amount.min(remaining_cap);
+        let refunded = amount - accepted;
+
         let new_total = presale.total_raised
-            .checked_add(amount)
+            .checked_add(accepted)
             .ok_or(LaunchpadError::MathOverflow)?;
-        require!(new_total <= presale.hard_cap, LaunchpadError::HardCapReached);
 
         // Check user's total contribution
         let contribution = &mut ctx.accounts.contribution;
@@ -506,7 +563,7 @@ pub mod jeju_launchpad {
         );
         
         let new_user_total = contribution.amount
-            .checked_add(amount)
+            .checked_add(accepted)
             .ok_or(LaunchpadError::MathOverflow)?;
         require!(new_user_total <= presale.max_contribution, LaunchpadError::AboveMaxContribution);
 
@@ -519,7 +576,7 @@ pub mod jeju_launchpad {
                     to: ctx.accounts.presale_vault.to_account_info(),
                 },
             ),
-            amount,
+            accepted,
         )?;
 
         contribution.amount = new_user_total;
@@ -530,8 +587,9 @@ pub mod jeju_launchpad {
         emit!(ContributionMade {
             presale: presale.key(),
             contributor: ctx.accounts.contributor.key(),
-            amount,
+            amount: accepted,
             total_contribution: new_user_total,
+            refunded,
         });
 
         Ok(())
@@ -540,18 +598,7 @@ pub mod jeju_launchpad {
     /// Finalize presale (creator only)
     pub fn finalize_presale(ctx: Context<FinalizePresale>) -> Result<()> {
         let presale = &mut ctx.accounts.presale;
-        let clock = Clock::get()?;
-
-        require!(!presale.finalized, LaunchpadError::PresaleFinalized);
-        require!(!presale.cancelled, LaunchpadError::PresaleCancelled);
-        require!(
-            clock.unix_timestamp > presale.end_time || presale.total_raised >= presale.hard_cap,
-            LaunchpadError::PresaleNotEnded
-        );
-        require!(presale.total_raised >= presale.soft_cap, LaunchpadError::SoftCapNotReached);
-
-        presale.finalized = true;
-        presale.finalized_at = clock.unix_timestamp;
+        finalize(presale, Clock::get()?.unix_timestamp)?;
 
         emit!(PresaleFinalized {
             presale: presale.key(),
@@ -571,30 +618,8 @@ pub mod jeju_launchpad {
         require!(presale.finalized, LaunchpadError::PresaleNotFinalized);
         require!(!contribution.claimed, LaunchpadError::AlreadyClaimed);
 
-        // Calculate vesting
-        let vesting_start = presale.finalized_at;
-        let vesting_end = vesting_start + presale.vesting_duration;
-        let elapsed = clock.unix_timestamp - vesting_start;
-        
-        let claimable_pct = if presale.vesting_duration == 0 || elapsed >= presale.vesting_duration {
-            100u64
-        } else {
-            (elapsed as u64).checked_mul(100).ok_or(LaunchpadError::MathOverflow)?
-                .checked_div(presale.vesting_duration as u64).ok_or(LaunchpadError::MathOverflow)?
-        };
-
-        // Calculate tokens
-        let total_tokens = contribution.amount
-            .checked_mul(presale.token_price)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(1_000_000_000) // Assuming SOL decimals
-            .ok_or(LaunchpadError::MathOverflow)?;
-
-        let claimable_tokens = total_tokens
-            .checked_mul(claimable_pct)
-            .ok_or(LaunchpadError::MathOverflow)?
-            .checked_div(100)
-            .ok_or(LaunchpadError::MathOverflow)?;
+        let claimable_pct = vested_pct(presale, clock.unix_timestamp)?;
+        let claimable_tokens = vested_tokens(presale_allocation(presale, contribution)?, claimable_pct)?;
 
         let already_claimed = contribution.tokens_claimed;
         let to_claim = claimable_tokens.checked_sub(already_claimed).ok_or(LaunchpadError::MathOverflow)?;
@@ -636,6 +661,21 @@ pub mod jeju_launchpad {
         Ok(())
     }
 
+    /// Read-only view of a contribution's vesting: its full allocation, what
+    /// has been claimed, what `claim_presale` would pay out now, and when the
+    /// next portion unlocks. Uses the same vesting math as `claim_presale`.
+    ///
+    /// # Return Data
+    /// The Borsh-encoded `ClaimableTokens`, readable via `get_return_data()` or
+    /// transaction simulation.
+    pub fn get_claimable(ctx: Context<GetClaimable>) -> Result<ClaimableTokens> {
+        vesting_view(
+            &ctx.accounts.presale,
+            &ctx.accounts.contribution,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
     /// Cancel presale and enable refunds
     pub fn cancel_presale(ctx: Context<CancelPresale>) -> Result<()> {
         let presale = &mut ctx.accounts.presale;
@@ -697,27 +737,844 @@ pub mod jeju_launchpad {
 
         Ok(())
     }
-}
 
-// ============================================================================
-// Helper Functions
-// ============================================================================
+    /// Refund a contribution to a presale that was never finalized or
+    /// cancelled within FORCE_REFUND_DELAY of its end time
+    /// The first call cancels the presale so it can no longer be finalized
+    pub fn force_refund(ctx: Context<ForceRefund>) -> Result<()> {
+        let presale = &mut ctx.accounts.presale;
+        let contribution = &mut ctx.accounts.contribution;
 
-fn calculate_price(virtual_sol: u64, virtual_token: u64) -> u64 {
-    // Price = virtual_sol / virtual_token (in lamports per token)
-    if virtual_token == 0 {
-        return 0;
-    }
-    (virtual_sol as u128)
-        .checked_mul(1_000_000) // Scale for precision
-        .unwrap_or(0)
-        .checked_div(virtual_token as u128)
-        .unwrap_or(0) as u64
-}
+        let refund_amount = take_force_refund(presale, contribution, Clock::get()?.unix_timestamp)?;
+        let token_mint = presale.token_mint;
+        let vault_bump = presale.vault_bump;
 
-// ============================================================================
-// Account Structures
-// ============================================================================
+        let seeds = &[
+            PRESALE_VAULT_SEED,
+            token_mint.as_ref(),
+            &[vault_bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.presale_vault.to_account_info(),
+                    to: ctx.accounts.contributor.to_account_info(),
+                },
+                signer,
+            ),
+            refund_amount,
+        )?;
+
+        emit!(PresaleForceRefunded {
+            presale: presale.key(),
+            contributor: ctx.accounts.contributor.key(),
+            amount: refund_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Recover the sale tokens of an abandoned presale (platform authority only)
+    /// Same availability as force_refund; cancels the presale
+    pub fn reclaim_abandoned_tokens(ctx: Context<ReclaimAbandonedTokens>) -> Result<()> {
+        require!(
+            force_refund_available(&ctx.accounts.presale, Clock::get()?.unix_timestamp),
+            LaunchpadError::ForceRefundNotAvailable
+        );
+
+        let amount = ctx.accounts.presale_token_account.amount;
+        require!(amount > 0, LaunchpadError::NothingToClaim);
+
+        let token_mint = ctx.accounts.presale.token_mint;
+        let seeds = &[
+            PRESALE_SEED,
+            token_mint.as_ref(),
+            &[ctx.accounts.presale.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.presale_token_account.to_account_info(),
+                    to: ctx.accounts.authority_token_account.to_account_info(),
+                    authority: ctx.accounts.presale.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+        )?;
+
+        let presale = &mut ctx.accounts.presale;
+        presale.cancelled = true;
+
+        emit!(AbandonedTokensReclaimed {
+            presale: presale.key(),
+            authority: ctx.accounts.authority.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================================
+    // Creator Instructions
+    // ============================================================================
+
+    /// Create the profile through which a creator manages all their presales
+    pub fn create_creator_profile(ctx: Context<CreateCreatorProfile>) -> Result<()> {
+        let profile = &mut ctx.accounts.creator_profile;
+        profile.creator = ctx.accounts.creator.key();
+        profile.presales = Vec::new();
+        profile.paused = false;
+        profile.bump = ctx.bumps.creator_profile;
+        Ok(())
+    }
+
+    /// Add one of the creator's presales to their profile (creator only)
+    /// A presale tracked while the profile is paused is paused too
+    pub fn track_presale(ctx: Context<TrackPresale>) -> Result<()> {
+        let profile = &mut ctx.accounts.creator_profile;
+        let presale = &mut ctx.accounts.presale;
+        profile.track(presale.key())?;
+
+        if profile.paused && set_presale_paused(presale, true) {
+            emit!(PresalePaused {
+                presale: presale.key(),
+                creator: profile.creator,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Remove a presale from the creator's profile (creator only)
+    /// The presale keeps its current paused state
+    pub fn untrack_presale(ctx: Context<TrackPresale>) -> Result<()> {
+        ctx.accounts.creator_profile.untrack(&ctx.accounts.presale.key())
+    }
+
+    /// Stop contributions to every presale in the creator's profile at once
+    /// Pass each tracked presale, writable and in profile order, as remaining
+    /// accounts. Refunds, finalization and claims are unaffected.
+    pub fn pause_creator_presales<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetCreatorPresalesPaused<'info>>,
+    ) -> Result<()> {
+        set_creator_presales_paused(
+            &mut ctx.accounts.creator_profile,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            true,
+        )
+    }
+
+    /// Reopen contributions to every presale in the creator's profile
+    /// Takes the same remaining accounts as pause_creator_presales
+    pub fn resume_creator_presales<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetCreatorPresalesPaused<'info>>,
+    ) -> Result<()> {
+        set_creator_presales_paused(
+            &mut ctx.accounts.creator_profile,
+            ctx.remaining_accounts,
+            ctx.program_id,
+            false,
+        )
+    }
+
+    // ============================================================================
+    // Admin Instructions
+    // ============================================================================
+
+    /// Propose a new authority (current authority only)
+    /// The transfer only takes effect once the new authority calls accept_authority
+    pub fn transfer_authority(
+        ctx: Context<TransferAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_authority = Some(new_authority);
+
+        emit!(AuthorityTransferStarted {
+            authority: config.authority,
+            pending_authority: new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Accept a pending authority transfer (pending authority only)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let new_authority = ctx.accounts.new_authority.key();
+
+        require!(
+            config.pending_authority == Some(new_authority),
+            LaunchpadError::NotPendingAuthority
+        );
+
+        let previous_authority = config.authority;
+        config.authority = new_authority;
+        config.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            previous_authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Set the share of each buy paid to referrers (authority only)
+    pub fn set_referral_fee(ctx: Context<UpdateConfig>, referral_fee_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            referral_fee_bps <= MAX_REFERRAL_FEE_BPS && referral_fee_bps <= config.platform_fee_bps,
+            LaunchpadError::FeeTooHigh
+        );
+
+        config.referral_fee_bps = referral_fee_bps;
+        Ok(())
+    }
+
+    /// Give one token a lower platform fee than the global one (authority only)
+    /// None restores the global fee
+    pub fn set_platform_fee_override(
+        ctx: Context<SetPlatformFeeOverride>,
+        platform_fee_bps: Option<u16>,
+    ) -> Result<()> {
+        if let Some(fee) = platform_fee_bps {
+            require!(fee <= ctx.accounts.config.platform_fee_bps, LaunchpadError::FeeTooHigh);
+        }
+
+        ctx.accounts.bonding_curve.platform_fee_override = platform_fee_bps;
+        Ok(())
+    }
+
+    /// Set where graduated liquidity migrates to (authority only)
+    /// None clears it until a destination is chosen
+    pub fn set_migration_config(
+        ctx: Context<UpdateConfig>,
+        migration: Option<MigrationConfig>,
+    ) -> Result<()> {
+        if let Some(migration) = &migration {
+            require!(
+                migration.pool_fee_bps <= MAX_POOL_FEE_BPS
+                    && migration.migration_fee_bps <= MAX_MIGRATION_FEE_BPS,
+                LaunchpadError::FeeTooHigh
+            );
+        }
+
+        ctx.accounts.config.migration = migration;
+        Ok(())
+    }
+
+    /// Pause or resume trading, launches and presale contributions (authority only)
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.paused = paused;
+
+        if paused {
+            emit!(LaunchpadPaused {
+                authority: ctx.accounts.authority.key(),
+            });
+        } else {
+            emit!(LaunchpadUnpaused {
+                authority: ctx.accounts.authority.key(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Slippage bound on a buy
+#[derive(Clone, Copy)]
+enum BuyLimit {
+    /// Fewest tokens the buyer accepts
+    MinTokensOut(u64),
+    /// Highest average price the buyer pays, scaled like `calculate_price`
+    MaxPrice(u64),
+}
+
+impl BuyLimit {
+    fn check(self, sol_amount: u64, tokens_out: u64) -> Result<()> {
+        match self {
+            BuyLimit::MinTokensOut(min_tokens_out) => {
+                require!(tokens_out >= min_tokens_out, LaunchpadError::SlippageExceeded);
+            }
+            BuyLimit::MaxPrice(max_price) => {
+                require!(
+                    tokens_out > 0 && calculate_price(sol_amount, tokens_out) <= max_price,
+                    LaunchpadError::SlippageExceeded
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shared by buy, buy_exact_sol and buy_with_referral
+fn process_buy<'info>(
+    accounts: &mut BuyTokens<'info>,
+    referrer: Option<&AccountInfo<'info>>,
+    sol_amount: u64,
+    limit: BuyLimit,
+) -> Result<()> {
+    // Get values before mutable borrow
+    let token_mint = accounts.bonding_curve.token_mint;
+    let bump = accounts.bonding_curve.bump;
+    let creator_fee_bps = accounts.bonding_curve.creator_fee_bps;
+    let platform_fee_bps = effective_platform_fee_bps(
+        accounts.config.platform_fee_bps,
+        accounts.bonding_curve.platform_fee_override,
+    );
+    let bonding_curve_info = accounts.bonding_curve.to_account_info();
+    let token_program_info = accounts.token_program.to_account_info();
+    let curve_token_account_info = accounts.curve_token_account.to_account_info();
+    let buyer_token_account_info = accounts.buyer_token_account.to_account_info();
+
+    require!(!accounts.config.paused, LaunchpadError::Paused);
+    let curve = &accounts.bonding_curve;
+    ensure_tradable(curve)?;
+    require!(sol_amount > 0, LaunchpadError::InvalidAmount);
+
+    // Calculate tokens out using constant product formula
+    // (virtualSol + solIn) * (virtualToken - tokenOut) = k
+    let k = (curve.virtual_sol_reserves as u128)
+        .checked_mul(curve.virtual_token_reserves as u128)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let new_virtual_sol = curve.virtual_sol_reserves
+        .checked_add(sol_amount)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let new_virtual_token = k
+        .checked_div(new_virtual_sol as u128)
+        .ok_or(LaunchpadError::MathOverflow)? as u64;
+
+    let tokens_out = curve.virtual_token_reserves
+        .checked_sub(new_virtual_token)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    // Apply fees
+    let platform_fee = sol_amount
+        .checked_mul(platform_fee_bps as u64)
+        .ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(10000)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let creator_fee = sol_amount
+        .checked_mul(creator_fee_bps as u64)
+        .ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(10000)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let total_fees = platform_fee.checked_add(creator_fee).ok_or(LaunchpadError::MathOverflow)?;
+    let net_sol = sol_amount.checked_sub(total_fees).ok_or(LaunchpadError::MathOverflow)?;
+
+    // Referral cut comes out of the platform fee, never on top of it
+    let referral_fee = match referrer {
+        Some(referrer)
+            if referrer.key() != accounts.buyer.key()
+                && referrer.key() != accounts.bonding_curve.creator =>
+        {
+            let cut = sol_amount
+                .checked_mul(accounts.config.referral_fee_bps as u64)
+                .ok_or(LaunchpadError::MathOverflow)?
+                .checked_div(10000)
+                .ok_or(LaunchpadError::MathOverflow)?
+                .min(platform_fee);
+            referral_payout(
+                cut,
+                referrer.lamports(),
+                Rent::get()?.minimum_balance(referrer.data_len()),
+            )
+        }
+        _ => 0,
+    };
+    let platform_fee = platform_fee - referral_fee;
+
+    // Apply slippage check on tokens after fee
+    let tokens_after_fee = tokens_after_fee(tokens_out, platform_fee_bps);
+
+    limit.check(sol_amount, tokens_after_fee)?;
+
+    // Transfer SOL from buyer to vault
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: accounts.buyer.to_account_info(),
+                to: accounts.sol_vault.to_account_info(),
+            },
+        ),
+        net_sol,
+    )?;
+
+    // Accrue platform fee in the protocol vault
+    if platform_fee > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: accounts.buyer.to_account_info(),
+                    to: accounts.protocol_fee_vault.to_account_info(),
+                },
+            ),
+            platform_fee,
+        )?;
+    }
+
+    // Pay the referrer
+    if let (Some(referrer), true) = (referrer, referral_fee > 0) {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: accounts.buyer.to_account_info(),
+                    to: referrer.clone(),
+                },
+            ),
+            referral_fee,
+        )?;
+
+        emit!(ReferralPaid {
+            token_mint,
+            buyer: accounts.buyer.key(),
+            referrer: referrer.key(),
+            amount: referral_fee,
+        });
+    }
+
+    // Accrue creator fee in the curve's fee vault
+    if creator_fee > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: accounts.buyer.to_account_info(),
+                    to: accounts.creator_fee_vault.to_account_info(),
+                },
+            ),
+            creator_fee,
+        )?;
+    }
+
+    // Transfer tokens to buyer
+    let seeds = &[
+        BONDING_CURVE_SEED,
+        token_mint.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program_info,
+            Transfer {
+                from: curve_token_account_info,
+                to: buyer_token_account_info,
+                authority: bonding_curve_info,
+            },
+            signer,
+        ),
+        tokens_after_fee,
+    )?;
+
+    // Update curve state (mutable borrow now). Reserves are never touched
+    // once the curve has graduated.
+    let curve = &mut accounts.bonding_curve;
+    ensure_tradable(curve)?;
+    let old_real_sol = curve.real_sol_reserves;
+    let old_real_token = curve.real_token_reserves;
+    let old_tokens_sold = curve.tokens_sold;
+
+    curve.virtual_sol_reserves = new_virtual_sol;
+    curve.virtual_token_reserves = new_virtual_token;
+    curve.real_sol_reserves = old_real_sol
+        .checked_add(net_sol)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.real_token_reserves = old_real_token
+        .checked_sub(tokens_after_fee)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.tokens_sold = old_tokens_sold
+        .checked_add(tokens_after_fee)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.fees_accrued = curve.fees_accrued
+        .checked_add(creator_fee)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.referral_fees_paid = curve.referral_fees_paid
+        .checked_add(referral_fee)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    emit!(TokensBought {
+        token_mint: curve.token_mint,
+        buyer: accounts.buyer.key(),
+        sol_amount,
+        tokens_bought: tokens_after_fee,
+        new_price: calculate_price(curve.virtual_sol_reserves, curve.virtual_token_reserves),
+        platform_fee_bps,
+    });
+
+    // Graduation fires once, on the buy that reaches the threshold
+    if mark_graduated(curve) {
+        emit!(TokenGraduated {
+            token_mint: curve.token_mint,
+            sol_raised: curve.real_sol_reserves,
+            tokens_sold: curve.tokens_sold,
+        });
+    }
+
+Ok(())
+}
+
+/// Flag the curve as graduated if it has reached its threshold. Returns true
+/// only on the transition, so `TokenGraduated` is emitted exactly once.
+fn mark_graduated(curve: &mut BondingCurve) -> bool {
+    if curve.graduated || curve.real_sol_reserves < curve.graduation_threshold {
+        return false;
+    }
+    curve.graduated = true;
+    true
+}
+
+/// Buys and sells stop for good at graduation. Since the buy that reaches the
+/// threshold graduates the curve, no sell can take the reserves back below it.
+fn ensure_tradable(curve: &BondingCurve) -> Result<()> {
+    require!(!curve.graduated, LaunchpadError::AlreadyGraduated);
+    Ok(())
+}
+
+/// Outcome of selling tokens back into a curve
+struct SellQuote {
+    new_virtual_sol: u64,
+    new_virtual_token: u64,
+    sol_out: u64,
+    platform_fee: u64,
+    creator_fee: u64,
+    net_sol: u64,
+}
+
+/// Price a sell of `token_amount` against the curve's current reserves
+fn quote_sell(
+    curve: &BondingCurve,
+    token_amount: u64,
+    min_sol_out: u64,
+    platform_fee_bps: u16,
+) -> Result<SellQuote> {
+    ensure_tradable(curve)?;
+    require!(token_amount > 0, LaunchpadError::InvalidAmount);
+
+    // Calculate SOL out using constant product formula
+    // (virtualToken + tokenIn) * (virtualSol - solOut) = k
+    let k = (curve.virtual_sol_reserves as u128)
+        .checked_mul(curve.virtual_token_reserves as u128)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let new_virtual_token = curve.virtual_token_reserves
+        .checked_add(token_amount)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let new_virtual_sol = k
+        .checked_div(new_virtual_token as u128)
+        .ok_or(LaunchpadError::MathOverflow)? as u64;
+
+    let sol_out = curve.virtual_sol_reserves
+        .checked_sub(new_virtual_sol)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    // Apply fees
+    let platform_fee = sol_out
+        .checked_mul(platform_fee_bps as u64)
+        .ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(10000)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let creator_fee = sol_out
+        .checked_mul(curve.creator_fee_bps as u64)
+        .ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(10000)
+        .ok_or(LaunchpadError::MathOverflow)?;
+
+    let total_fees = platform_fee.checked_add(creator_fee).ok_or(LaunchpadError::MathOverflow)?;
+    let net_sol = sol_out.checked_sub(total_fees).ok_or(LaunchpadError::MathOverflow)?;
+
+    require!(net_sol >= min_sol_out, LaunchpadError::SlippageExceeded);
+    require!(curve.real_sol_reserves >= sol_out, LaunchpadError::InsufficientLiquidity);
+
+    Ok(SellQuote { new_virtual_sol, new_virtual_token, sol_out, platform_fee, creator_fee, net_sol })
+}
+
+/// Move a priced sell into the curve's reserves. Reserves are never touched
+/// once the curve has graduated.
+fn apply_sell(curve: &mut BondingCurve, quote: &SellQuote, token_amount: u64) -> Result<()> {
+    ensure_tradable(curve)?;
+
+    curve.virtual_sol_reserves = quote.new_virtual_sol;
+    curve.virtual_token_reserves = quote.new_virtual_token;
+    curve.real_sol_reserves = curve.real_sol_reserves
+        .checked_sub(quote.sol_out)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.real_token_reserves = curve.real_token_reserves
+        .checked_add(token_amount)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.tokens_sold = curve.tokens_sold
+        .checked_sub(token_amount)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    curve.fees_accrued = curve.fees_accrued
+        .checked_add(quote.creator_fee)
+        .ok_or(LaunchpadError::MathOverflow)?;
+    Ok(())
+}
+
+/// Tokens a contribution buys at the presale price
+fn presale_allocation(presale: &Presale, contribution: &Contribution) -> Result<u64> {
+    Ok(contribution.amount
+        .checked_mul(presale.token_price)
+        .ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(1_000_000_000) // Assuming SOL decimals
+        .ok_or(LaunchpadError::MathOverflow)?)
+}
+
+/// Whole percent of allocations vested at `now`, linear from finalization.
+/// A zero vesting duration unlocks everything at once.
+fn vested_pct(presale: &Presale, now: i64) -> Result<u64> {
+    let elapsed = now.saturating_sub(presale.finalized_at).max(0);
+    if presale.vesting_duration == 0 || elapsed >= presale.vesting_duration {
+        return Ok(100);
+    }
+    Ok((elapsed as u64).checked_mul(100).ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(presale.vesting_duration as u64).ok_or(LaunchpadError::MathOverflow)?)
+}
+
+fn vested_tokens(allocation: u64, pct: u64) -> Result<u64> {
+    Ok(allocation
+        .checked_mul(pct)
+        .ok_or(LaunchpadError::MathOverflow)?
+        .checked_div(100)
+        .ok_or(LaunchpadError::MathOverflow)?)
+}
+
+/// When `vested_pct` next rises above `pct`, or None once fully vested
+fn next_unlock_ts(presale: &Presale, pct: u64) -> Option<i64> {
+    if pct >= 100 || presale.vesting_duration <= 0 {
+        return None;
+    }
+    // Smallest elapsed with elapsed * 100 / duration >= pct + 1
+    let needed = ((pct as i128 + 1) * presale.vesting_duration as i128 + 99) / 100;
+    i64::try_from(needed).ok().and_then(|needed| presale.finalized_at.checked_add(needed))
+}
+
+fn vesting_view(presale: &Presale, contribution: &Contribution, now: i64) -> Result<ClaimableTokens> {
+    let total_allocation = presale_allocation(presale, contribution)?;
+    let claimed = contribution.tokens_claimed;
+
+    // Nothing vests before finalization, and a cancelled presale refunds instead
+    if !presale.finalized || presale.cancelled {
+        return Ok(ClaimableTokens {
+            total_allocation,
+            claimed,
+            claimable_now: 0,
+            locked: total_allocation.saturating_sub(claimed),
+            next_unlock_ts: None,
+        });
+    }
+
+    let pct = vested_pct(presale, now)?;
+    let vested = vested_tokens(total_allocation, pct)?;
+    let claimable_now = if contribution.claimed { 0 } else { vested.saturating_sub(claimed) };
+
+    Ok(ClaimableTokens {
+        total_allocation,
+        claimed,
+        claimable_now,
+        locked: total_allocation.saturating_sub(vested),
+        next_unlock_ts: next_unlock_ts(presale, pct),
+    })
+}
+
+/// Abandoned presales: never finalized, and FORCE_REFUND_DELAY past the end.
+/// A finalized presale is never eligible, so this can't undo a finalize.
+fn force_refund_available(presale: &Presale, now: i64) -> bool {
+    !presale.finalized && now >= presale.end_time.saturating_add(FORCE_REFUND_DELAY)
+}
+
+/// Close a presale that ended (or sold out) having met its soft cap
+fn finalize(presale: &mut Presale, now: i64) -> Result<()> {
+    require!(!presale.finalized, LaunchpadError::PresaleFinalized);
+    require!(!presale.cancelled, LaunchpadError::PresaleCancelled);
+    require!(
+        now > presale.end_time || presale.total_raised >= presale.hard_cap,
+        LaunchpadError::PresaleNotEnded
+    );
+    require!(presale.total_raised >= presale.soft_cap, LaunchpadError::SoftCapNotReached);
+
+    presale.finalized = true;
+    presale.finalized_at = now;
+    Ok(())
+}
+
+/// Settle a force refund: the contribution is paid out in full and the
+/// presale is cancelled so it can no longer be finalized. Returns the lamports
+/// to send back.
+fn take_force_refund(presale: &mut Presale, contribution: &mut Contribution, now: i64) -> Result<u64> {
+    require!(force_refund_available(presale, now), LaunchpadError::ForceRefundNotAvailable);
+    require!(!contribution.claimed, LaunchpadError::AlreadyClaimed);
+    require!(contribution.amount > 0, LaunchpadError::NothingToClaim);
+
+    let refund_amount = contribution.amount;
+    contribution.claimed = true;
+    contribution.amount = 0;
+    presale.cancelled = true;
+    Ok(refund_amount)
+}
+
+/// Pause or resume contributions to a presale; returns whether it changed.
+/// Finalized and cancelled presales take no more contributions either way.
+fn set_presale_paused(presale: &mut Presale, paused: bool) -> bool {
+    if presale.finalized || presale.cancelled || presale.paused == paused {
+        return false;
+    }
+    presale.paused = paused;
+    true
+}
+
+/// Apply a creator-wide pause or resume to `presales`, which must be exactly
+/// the profile's tracked presales in order
+fn set_creator_presales_paused<'info>(
+    profile: &mut CreatorProfile,
+    presales: &'info [AccountInfo<'info>],
+    program_id: &Pubkey,
+    paused: bool,
+) -> Result<()> {
+    require!(
+        presales.len() == profile.presales.len(),
+        LaunchpadError::CreatorPresalesMismatch
+    );
+
+    for (info, tracked) in presales.iter().zip(profile.presales.iter()) {
+        require_keys_eq!(info.key(), *tracked, LaunchpadError::CreatorPresalesMismatch);
+
+        let mut presale = Account::<Presale>::try_from(info)?;
+        require_keys_eq!(presale.creator, profile.creator, LaunchpadError::Unauthorized);
+
+        if !set_presale_paused(&mut presale, paused) {
+            continue;
+        }
+        presale.exit(program_id)?;
+
+        if paused {
+            emit!(PresalePaused {
+                presale: info.key(),
+                creator: profile.creator,
+            });
+        } else {
+            emit!(PresaleResumed {
+                presale: info.key(),
+                creator: profile.creator,
+            });
+        }
+    }
+
+    profile.paused = paused;
+    Ok(())
+}
+
+/// Platform fee charged on a curve. An override can only lower the global fee,
+/// including after the global fee itself has been lowered.
+fn effective_platform_fee_bps(global_bps: u16, override_bps: Option<u16>) -> u16 {
+    override_bps.map_or(global_bps, |fee| fee.min(global_bps))
+}
+
+/// Referral cut actually paid out. Paying less than rent exemption to an
+/// unfunded referrer would fail the whole buy, so the cut stays in the platform
+/// fee instead.
+fn referral_payout(cut: u64, referrer_lamports: u64, rent_exempt_minimum: u64) -> u64 {
+    if referrer_lamports.saturating_add(cut) < rent_exempt_minimum {
+        0
+    } else {
+        cut
+    }
+}
+
+/// Platform fees waiting in the protocol fee vault; the rent-exempt minimum
+/// funded at initialize stays behind
+fn claimable_protocol_fees(vault_lamports: u64, rent_exempt_minimum: u64) -> u64 {
+    vault_lamports.saturating_sub(rent_exempt_minimum)
+}
+
+/// Tokens delivered for a buy once the curve's platform fee is taken out of
+/// `tokens_out`
+fn tokens_after_fee(tokens_out: u64, platform_fee_bps: u16) -> u64 {
+    ((tokens_out as u128) * (10000 - platform_fee_bps as u128) / 10000) as u64
+}
+
+fn calculate_price(virtual_sol: u64, virtual_token: u64) -> u64 {
+    // Price = virtual_sol / virtual_token (in lamports per token)
+    if virtual_token == 0 {
+        return 0;
+    }
+    (virtual_sol as u128)
+        .checked_mul(1_000_000) // Scale for precision
+        .unwrap_or(0)
+        .checked_div(virtual_token as u128)
+        .unwrap_or(0) as u64
+}
+
+/// Split a curve's reserves for migration. The pool opens at the curve's
+/// last price, so it takes as many tokens as the migrated SOL buys there and
+/// the curve's leftover tokens are burned.
+fn migration_preview(curve: &BondingCurve, migration: &MigrationConfig) -> Result<MigrationPreview> {
+    let migration_fee = (curve.real_sol_reserves as u128)
+        .checked_mul(migration.migration_fee_bps as u128)
+        .ok_or(LaunchpadError::MathOverflow)?
+        / 10000;
+    let sol_amount = curve.real_sol_reserves - migration_fee as u64;
+
+    let token_amount = if curve.virtual_sol_reserves == 0 {
+        0
+    } else {
+        ((sol_amount as u128)
+            .checked_mul(curve.virtual_token_reserves as u128)
+            .ok_or(LaunchpadError::MathOverflow)?
+            / curve.virtual_sol_reserves as u128)
+            .min(curve.real_token_reserves as u128) as u64
+    };
+
+    Ok(MigrationPreview {
+        amm_program: migration.amm_program,
+        pool_fee_bps: migration.pool_fee_bps,
+        sol_amount,
+        token_amount,
+        tokens_burned: curve.real_token_reserves - token_amount,
+        migration_fee: migration_fee as u64,
+        initial_price: calculate_price(sol_amount, token_amount),
+        graduated: curve.graduated,
+    })
+}
+
+// ============================================================================
+// Account Structures
+// ============================================================================
+
+/// Where graduated curves' liquidity goes
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MigrationConfig {
+    /// AMM program the pool is created on
+    pub amm_program: Pubkey,
+    /// Swap fee of the created pool
+    pub pool_fee_bps: u16,
+    /// Share of the raised SOL kept by the protocol at migration
+    pub migration_fee_bps: u16,
+}
 
 #[account]
 pub struct LaunchpadConfig {
@@ -726,6 +1583,17 @@ pub struct LaunchpadConfig {
     pub platform_fee_bps: u16,
     pub total_launches: u64,
     pub bump: u8,
+    /// Platform fees swept to the fee recipient
+    pub protocol_fees_claimed: u64,
+    pub protocol_fee_vault_bump: u8,
+    /// Part of the platform fee paid to referrers
+    pub referral_fee_bps: u16,
+    /// Halts trading, launches and contributions; refunds and claims stay open
+    pub paused: bool,
+    /// Proposed authority awaiting accept_authority
+    pub pending_authority: Option<Pubkey>,
+    /// Graduation target; None until the authority sets one
+    pub migration: Option<MigrationConfig>,
 }
 
 #[account]
@@ -743,6 +1611,15 @@ pub struct BondingCurve {
     pub created_at: i64,
     pub bump: u8,
     pub vault_bump: u8,
+    /// Creator fees paid into the creator fee vault
+    pub fees_accrued: u64,
+    /// Creator fees swept to the creator
+    pub fees_claimed: u64,
+    pub creator_fee_vault_bump: u8,
+    /// Platform fee for this token in place of config.platform_fee_bps
+    pub platform_fee_override: Option<u16>,
+    /// Part of this curve's platform fees paid to referrers
+    pub referral_fees_paid: u64,
 }
 
 #[account]
@@ -764,6 +1641,8 @@ pub struct Presale {
     pub finalized_at: i64,
     pub bump: u8,
     pub vault_bump: u8,
+    /// Contributions are stopped by the creator via their profile
+    pub paused: bool,
 }
 
 #[account]
@@ -776,6 +1655,98 @@ pub struct Contribution {
     pub bump: u8,
 }
 
+/// A creator's presales, at `[CREATOR_PROFILE_SEED, creator]`, so they can be
+/// paused and resumed together
+#[account]
+pub struct CreatorProfile {
+    pub creator: Pubkey,
+    pub presales: Vec<Pubkey>,
+    /// Whether the last creator-wide action was a pause
+    pub paused: bool,
+    pub bump: u8,
+}
+
+impl CreatorProfile {
+    pub const SPACE: usize = 8 + 32 + 4 + 32 * MAX_CREATOR_PRESALES + 1 + 1;
+
+    fn track(&mut self, presale: Pubkey) -> Result<()> {
+        require!(!self.presales.contains(&presale), LaunchpadError::PresaleAlreadyTracked);
+        require!(self.presales.len() < MAX_CREATOR_PRESALES, LaunchpadError::TooManyPresales);
+        self.presales.push(presale);
+        Ok(())
+    }
+
+    fn untrack(&mut self, presale: &Pubkey) -> Result<()> {
+        let index = self
+            .presales
+            .iter()
+            .position(|tracked| tracked == presale)
+            .ok_or(LaunchpadError::PresaleNotTracked)?;
+        self.presales.remove(index);
+        Ok(())
+    }
+}
+
+/// One entry per launch, at `[LAUNCH_SEED, index]` for every index below
+/// `LaunchpadConfig.total_launches`
+#[account]
+pub struct LaunchRecord {
+    pub index: u64,
+    pub token_mint: Pubkey,
+    pub creator: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+// ============================================================================
+// Return Types
+// ============================================================================
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CurveState {
+    pub token_mint: Pubkey,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub tokens_sold: u64,
+    /// Lamports per token, scaled by 1e6 (see `calculate_price`)
+    pub price: u64,
+    pub graduation_threshold: u64,
+    /// real_sol_reserves / graduation_threshold in bps, capped at 10000
+    pub progress_bps: u16,
+    pub graduated: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, PartialEq)]
+pub struct ClaimableTokens {
+    /// Tokens bought by the contribution
+    pub total_allocation: u64,
+    pub claimed: u64,
+    /// Vested but not yet claimed
+    pub claimable_now: u64,
+    /// Not yet vested
+    pub locked: u64,
+    /// When the next portion vests; None once fully vested or before finalization
+    pub next_unlock_ts: Option<i64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, PartialEq)]
+pub struct MigrationPreview {
+    pub amm_program: Pubkey,
+    pub pool_fee_bps: u16,
+    /// Lamports seeded into the pool
+    pub sol_amount: u64,
+    /// Tokens seeded into the pool
+    pub token_amount: u64,
+    /// Curve tokens left over after seeding the pool
+    pub tokens_burned: u64,
+    /// Lamports kept by the protocol
+    pub migration_fee: u64,
+    /// Opening pool price, scaled like `CurveState::price`
+    pub initial_price: u64,
+    pub graduated: bool,
+}
+
 // ============================================================================
 // Context Structures
 // ============================================================================
@@ -788,12 +1759,20 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + 32 + 32 + 2 + 8 + 1,
+        space = 8 + 32 + 32 + 2 + 8 + 1 + 8 + 1 + 2 + 1 + 33 + 37,
         seeds = [b"config"],
         bump
     )]
     pub config: Account<'info, LaunchpadConfig>,
 
+    /// CHECK: Protocol fee vault PDA - funded here
+    #[account(
+        mut,
+        seeds = [PROTOCOL_FEE_VAULT_SEED],
+        bump
+    )]
+    pub protocol_fee_vault: SystemAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -821,38 +1800,125 @@ pub struct CreateBondingCurve<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 1 + 8 + 1 + 1, // +1 for vault_bump
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 1 + 8 + 1 + 1 + 8 + 8 + 1 + 3 + 8, // +1 for vault_bump
         seeds = [BONDING_CURVE_SEED, token_mint.key().as_ref()],
         bump
     )]
-    pub bonding_curve: Account<'info, BondingCurve>,
+    pub bonding_curve: Account<'info, BondingCurve>,
+
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = token_mint,
+        associated_token::authority = bonding_curve,
+    )]
+    pub curve_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: SOL vault PDA - initialized here
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, token_mint.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// CHECK: Creator fee vault PDA - funded here
+    #[account(
+        mut,
+        seeds = [CREATOR_FEE_VAULT_SEED, token_mint.key().as_ref()],
+        bump
+    )]
+    pub creator_fee_vault: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + 8 + 32 + 32 + 8 + 1,
+        seeds = [LAUNCH_SEED, &config.total_launches.to_le_bytes()],
+        bump
+    )]
+    pub launch: Account<'info, LaunchRecord>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, LaunchpadConfig>,
+
+    #[account(
+        mut,
+        seeds = [BONDING_CURVE_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+
+    #[account(
+        mut,
+        associated_token::mint = bonding_curve.token_mint,
+        associated_token::authority = bonding_curve,
+    )]
+    pub curve_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = bonding_curve.token_mint,
+        associated_token::authority = buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: SOL vault PDA
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.vault_bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
 
+    /// CHECK: Protocol fee vault PDA
     #[account(
-        init,
-        payer = creator,
-        associated_token::mint = token_mint,
-        associated_token::authority = bonding_curve,
+        mut,
+        seeds = [PROTOCOL_FEE_VAULT_SEED],
+        bump = config.protocol_fee_vault_bump
     )]
-    pub curve_token_account: Account<'info, TokenAccount>,
+    pub protocol_fee_vault: SystemAccount<'info>,
 
-    /// CHECK: SOL vault PDA - initialized here
+    /// CHECK: Creator fee vault PDA
     #[account(
         mut,
-        seeds = [VAULT_SEED, token_mint.key().as_ref()],
-        bump
+        seeds = [CREATOR_FEE_VAULT_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.creator_fee_vault_bump
     )]
-    pub sol_vault: SystemAccount<'info>,
+    pub creator_fee_vault: SystemAccount<'info>,
 
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct BuyTokens<'info> {
+pub struct BuyWithReferral<'info> {
+    pub buy: BuyTokens<'info>,
+
+    /// CHECK: Referrer receiving part of the platform fee
     #[account(mut)]
-    pub buyer: Signer<'info>,
+    pub referrer: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SellTokens<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
 
     #[account(
         seeds = [b"config"],
@@ -877,9 +1943,9 @@ pub struct BuyTokens<'info> {
     #[account(
         mut,
         associated_token::mint = bonding_curve.token_mint,
-        associated_token::authority = buyer,
+        associated_token::authority = seller,
     )]
-    pub buyer_token_account: Account<'info, TokenAccount>,
+    pub seller_token_account: Account<'info, TokenAccount>,
 
     /// CHECK: SOL vault PDA
     #[account(
@@ -889,30 +1955,65 @@ pub struct BuyTokens<'info> {
     )]
     pub sol_vault: SystemAccount<'info>,
 
-    /// CHECK: Fee recipient from config
-    #[account(mut, address = config.fee_recipient)]
-    pub fee_recipient: SystemAccount<'info>,
+    /// CHECK: Protocol fee vault PDA
+    #[account(
+        mut,
+        seeds = [PROTOCOL_FEE_VAULT_SEED],
+        bump = config.protocol_fee_vault_bump
+    )]
+    pub protocol_fee_vault: SystemAccount<'info>,
 
-    /// CHECK: Creator address
-    #[account(mut, address = bonding_curve.creator)]
-    pub creator: SystemAccount<'info>,
+    /// CHECK: Creator fee vault PDA
+    #[account(
+        mut,
+        seeds = [CREATOR_FEE_VAULT_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.creator_fee_vault_bump
+    )]
+    pub creator_fee_vault: SystemAccount<'info>,
 
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SellTokens<'info> {
-    #[account(mut)]
-    pub seller: Signer<'info>,
+pub struct GetCurveState<'info> {
+    #[account(
+        seeds = [BONDING_CURVE_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct GetLaunch<'info> {
+    #[account(
+        seeds = [LAUNCH_SEED, &index.to_le_bytes()],
+        bump = launch.bump
+    )]
+    pub launch: Account<'info, LaunchRecord>,
+}
 
+#[derive(Accounts)]
+pub struct PreviewMigration<'info> {
     #[account(
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, LaunchpadConfig>,
 
+    #[account(
+        seeds = [BONDING_CURVE_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCreatorFees<'info> {
+    #[account(mut, address = bonding_curve.creator @ LaunchpadError::Unauthorized)]
+    pub creator: Signer<'info>,
+
     #[account(
         mut,
         seeds = [BONDING_CURVE_SEED, bonding_curve.token_mint.as_ref()],
@@ -920,29 +2021,41 @@ pub struct SellTokens<'info> {
     )]
     pub bonding_curve: Account<'info, BondingCurve>,
 
+    /// CHECK: Creator fee vault PDA
     #[account(
         mut,
-        associated_token::mint = bonding_curve.token_mint,
-        associated_token::authority = bonding_curve,
+        seeds = [CREATOR_FEE_VAULT_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.creator_fee_vault_bump
     )]
-    pub curve_token_account: Account<'info, TokenAccount>,
+    pub creator_fee_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimProtocolFees<'info> {
+    #[account(address = config.authority @ LaunchpadError::Unauthorized)]
+    pub authority: Signer<'info>,
 
     #[account(
         mut,
-        associated_token::mint = bonding_curve.token_mint,
-        associated_token::authority = seller,
+        seeds = [b"config"],
+        bump = config.bump
     )]
-    pub seller_token_account: Account<'info, TokenAccount>,
+    pub config: Account<'info, LaunchpadConfig>,
 
-    /// CHECK: SOL vault PDA
+    /// CHECK: Protocol fee vault PDA
     #[account(
         mut,
-        seeds = [VAULT_SEED, bonding_curve.token_mint.as_ref()],
-        bump = bonding_curve.vault_bump
+        seeds = [PROTOCOL_FEE_VAULT_SEED],
+        bump = config.protocol_fee_vault_bump
     )]
-    pub sol_vault: SystemAccount<'info>,
+    pub protocol_fee_vault: SystemAccount<'info>,
+
+    /// CHECK: Fee recipient from config
+    #[account(mut, address = config.fee_recipient)]
+    pub fee_recipient: SystemAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
@@ -956,7 +2069,7 @@ pub struct CreatePresale<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 1 + 1, // +1 for vault_bump
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 1 + 1 + 1, // vault_bump, paused
         seeds = [PRESALE_SEED, token_mint.key().as_ref()],
         bump
     )]
@@ -970,35 +2083,231 @@ pub struct CreatePresale<'info> {
     )]
     pub presale_vault: SystemAccount<'info>,
 
-    pub system_program: Program<'info, System>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateContribution<'info> {
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        init,
+        payer = contributor,
+        space = 8 + 32 + 32 + 8 + 8 + 1 + 1,
+        seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Contribute<'info> {
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, LaunchpadConfig>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
+
+    /// SECURITY: Use init constraint with a check instead of init_if_needed
+    /// to prevent front-running attacks. First-time contributors use create_contribution first.
+    #[account(
+        mut,
+        seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
+        bump = contribution.bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    /// CHECK: Presale vault PDA - uses different seed prefix to avoid collision with bonding curve
+    #[account(
+        mut,
+        seeds = [PRESALE_VAULT_SEED, presale.token_mint.as_ref()],
+        bump = presale.vault_bump
+    )]
+    pub presale_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizePresale<'info> {
+    #[account(mut, address = presale.creator)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPresale<'info> {
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+
+    #[account(
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        mut,
+        seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
+        bump = contribution.bump,
+        constraint = contribution.contributor == contributor.key()
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    #[account(
+        mut,
+        associated_token::mint = presale.token_mint,
+        associated_token::authority = presale,
+    )]
+    pub presale_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = presale.token_mint,
+        associated_token::authority = contributor,
+    )]
+    pub contributor_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetClaimable<'info> {
+    #[account(
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        seeds = [b"contribution", presale.key().as_ref(), contribution.contributor.as_ref()],
+        bump = contribution.bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCreatorProfile<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = CreatorProfile::SPACE,
+        seeds = [CREATOR_PROFILE_SEED, creator.key().as_ref()],
+        bump
+    )]
+    pub creator_profile: Account<'info, CreatorProfile>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TrackPresale<'info> {
+    #[account(address = presale.creator)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CREATOR_PROFILE_SEED, creator.key().as_ref()],
+        bump = creator_profile.bump
+    )]
+    pub creator_profile: Account<'info, CreatorProfile>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
+}
+
+#[derive(Accounts)]
+pub struct SetCreatorPresalesPaused<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CREATOR_PROFILE_SEED, creator.key().as_ref()],
+        bump = creator_profile.bump
+    )]
+    pub creator_profile: Account<'info, CreatorProfile>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPresale<'info> {
+    #[account(address = presale.creator)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
+        bump = presale.bump
+    )]
+    pub presale: Account<'info, Presale>,
 }
 
 #[derive(Accounts)]
-pub struct CreateContribution<'info> {
+pub struct ClaimRefund<'info> {
     #[account(mut)]
     pub contributor: Signer<'info>,
 
     #[account(
-        mut,
         seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
         bump = presale.bump
     )]
     pub presale: Account<'info, Presale>,
 
     #[account(
-        init,
-        payer = contributor,
-        space = 8 + 32 + 32 + 8 + 8 + 1 + 1,
+        mut,
         seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
-        bump
+        bump = contribution.bump,
+        constraint = contribution.contributor == contributor.key()
     )]
     pub contribution: Account<'info, Contribution>,
 
+    /// CHECK: Presale vault PDA
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, presale.token_mint.as_ref()],
+        bump = presale.vault_bump
+    )]
+    pub presale_vault: SystemAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Contribute<'info> {
+pub struct ForceRefund<'info> {
     #[account(mut)]
     pub contributor: Signer<'info>,
 
@@ -1009,16 +2318,15 @@ pub struct Contribute<'info> {
     )]
     pub presale: Account<'info, Presale>,
 
-    /// SECURITY: Use init constraint with a check instead of init_if_needed
-    /// to prevent front-running attacks. First-time contributors use create_contribution first.
     #[account(
         mut,
         seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
-        bump = contribution.bump
+        bump = contribution.bump,
+        constraint = contribution.contributor == contributor.key()
     )]
     pub contribution: Account<'info, Contribution>,
 
-    /// CHECK: Presale vault PDA - uses different seed prefix to avoid collision with bonding curve
+    /// CHECK: Presale vault PDA
     #[account(
         mut,
         seeds = [PRESALE_VAULT_SEED, presale.token_mint.as_ref()],
@@ -1030,37 +2338,23 @@ pub struct Contribute<'info> {
 }
 
 #[derive(Accounts)]
-pub struct FinalizePresale<'info> {
-    #[account(mut, address = presale.creator)]
-    pub creator: Signer<'info>,
+pub struct ReclaimAbandonedTokens<'info> {
+    #[account(address = config.authority @ LaunchpadError::Unauthorized)]
+    pub authority: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
-        bump = presale.bump
+        seeds = [b"config"],
+        bump = config.bump
     )]
-    pub presale: Account<'info, Presale>,
-}
-
-#[derive(Accounts)]
-pub struct ClaimPresale<'info> {
-    #[account(mut)]
-    pub contributor: Signer<'info>,
+    pub config: Account<'info, LaunchpadConfig>,
 
     #[account(
+        mut,
         seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
         bump = presale.bump
     )]
     pub presale: Account<'info, Presale>,
 
-    #[account(
-        mut,
-        seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
-        bump = contribution.bump,
-        constraint = contribution.contributor == contributor.key()
-    )]
-    pub contribution: Account<'info, Contribution>,
-
     #[account(
         mut,
         associated_token::mint = presale.token_mint,
@@ -1070,57 +2364,68 @@ pub struct ClaimPresale<'info> {
 
     #[account(
         mut,
-        associated_token::mint = presale.token_mint,
-        associated_token::authority = contributor,
+        token::mint = presale.token_mint,
     )]
-    pub contributor_token_account: Account<'info, TokenAccount>,
+    pub authority_token_account: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CancelPresale<'info> {
-    #[account(address = presale.creator)]
-    pub creator: Signer<'info>,
+pub struct TransferAuthority<'info> {
+    #[account(address = config.authority @ LaunchpadError::Unauthorized)]
+    pub authority: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
-        bump = presale.bump
+        seeds = [b"config"],
+        bump = config.bump
     )]
-    pub presale: Account<'info, Presale>,
+    pub config: Account<'info, LaunchpadConfig>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimRefund<'info> {
-    #[account(mut)]
-    pub contributor: Signer<'info>,
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
 
     #[account(
-        seeds = [PRESALE_SEED, presale.token_mint.as_ref()],
-        bump = presale.bump
+        mut,
+        seeds = [b"config"],
+        bump = config.bump
     )]
-    pub presale: Account<'info, Presale>,
+    pub config: Account<'info, LaunchpadConfig>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(address = config.authority @ LaunchpadError::Unauthorized)]
+    pub authority: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"contribution", presale.key().as_ref(), contributor.key().as_ref()],
-        bump = contribution.bump,
-        constraint = contribution.contributor == contributor.key()
+        seeds = [b"config"],
+        bump = config.bump
     )]
-    pub contribution: Account<'info, Contribution>,
+    pub config: Account<'info, LaunchpadConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetPlatformFeeOverride<'info> {
+    #[account(address = config.authority @ LaunchpadError::Unauthorized)]
+    pub authority: Signer<'info>,
 
-    /// CHECK: Presale vault PDA
     #[account(
-        mut,
-        seeds = [VAULT_SEED, presale.token_mint.as_ref()],
-        bump = presale.vault_bump
+        seeds = [b"config"],
+        bump = config.bump
     )]
-    pub presale_vault: SystemAccount<'info>,
+    pub config: Account<'info, LaunchpadConfig>,
 
-    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [BONDING_CURVE_SEED, bonding_curve.token_mint.as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
 }
 
 // ============================================================================
@@ -1143,6 +2448,16 @@ pub struct TokensBought {
     pub sol_amount: u64,
     pub tokens_bought: u64,
     pub new_price: u64,
+    /// Platform fee applied to this trade
+    pub platform_fee_bps: u16,
+}
+
+#[event]
+pub struct ReferralPaid {
+    pub token_mint: Pubkey,
+    pub buyer: Pubkey,
+    pub referrer: Pubkey,
+    pub amount: u64,
 }
 
 #[event]
@@ -1152,6 +2467,8 @@ pub struct TokensSold {
     pub tokens_sold: u64,
     pub sol_received: u64,
     pub new_price: u64,
+    /// Platform fee applied to this trade
+    pub platform_fee_bps: u16,
 }
 
 #[event]
@@ -1161,6 +2478,21 @@ pub struct TokenGraduated {
     pub tokens_sold: u64,
 }
 
+#[event]
+pub struct CreatorFeesClaimed {
+    pub token_mint: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+}
+
+#[event]
+pub struct ProtocolFeesClaimed {
+    pub fee_recipient: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+}
+
 #[event]
 pub struct PresaleCreated {
     pub presale: Pubkey,
@@ -1176,8 +2508,11 @@ pub struct PresaleCreated {
 pub struct ContributionMade {
     pub presale: Pubkey,
     pub contributor: Pubkey,
+    /// Amount accepted into the presale
     pub amount: u64,
     pub total_contribution: u64,
+    /// Part of the requested amount over the hard cap, left with the contributor
+    pub refunded: u64,
 }
 
 #[event]
@@ -1192,6 +2527,18 @@ pub struct PresaleCancelled {
     pub presale: Pubkey,
 }
 
+#[event]
+pub struct PresalePaused {
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+}
+
+#[event]
+pub struct PresaleResumed {
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+}
+
 #[event]
 pub struct TokensClaimed {
     pub presale: Pubkey,
@@ -1206,6 +2553,42 @@ pub struct RefundClaimed {
     pub amount: u64,
 }
 
+#[event]
+pub struct PresaleForceRefunded {
+    pub presale: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AbandonedTokensReclaimed {
+    pub presale: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AuthorityTransferStarted {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct LaunchpadPaused {
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct LaunchpadUnpaused {
+    pub authority: Pubkey,
+}
+
 // ============================================================================
 // Errors
 // ============================================================================
@@ -1266,5 +2649,429 @@ pub enum LaunchpadError {
     RefundsNotEnabled,
     #[msg("Invalid contribution owner")]
     InvalidContributionOwner,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Launchpad is paused")]
+    Paused,
+    #[msg("Presale is not abandoned long enough for a force refund")]
+    ForceRefundNotAvailable,
+    #[msg("No migration destination configured")]
+    MigrationNotConfigured,
+    #[msg("Presale is paused by its creator")]
+    PresalePaused,
+    #[msg("Creator profile already tracks this presale")]
+    PresaleAlreadyTracked,
+    #[msg("Creator profile does not track this presale")]
+    PresaleNotTracked,
+    #[msg("Creator profile tracks too many presales")]
+    TooManyPresales,
+    #[msg("Accounts must be the creator profile's presales, in order")]
+    CreatorPresalesMismatch,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fresh_curve() -> BondingCurve {
+        BondingCurve {
+            creator: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            virtual_sol_reserves: DEFAULT_VIRTUAL_SOL_RESERVES,
+            virtual_token_reserves: DEFAULT_VIRTUAL_TOKEN_RESERVES,
+            real_sol_reserves: 0,
+            real_token_reserves: DEFAULT_VIRTUAL_TOKEN_RESERVES,
+            tokens_sold: 0,
+            graduation_threshold: DEFAULT_GRADUATION_THRESHOLD,
+            creator_fee_bps: 0,
+            graduated: false,
+            created_at: 0,
+            bump: 0,
+            vault_bump: 0,
+            fees_accrued: 0,
+            fees_claimed: 0,
+            creator_fee_vault_bump: 0,
+            platform_fee_override: None,
+            referral_fees_paid: 0,
+        }
+    }
+
+    fn ended_presale(end_time: i64) -> Presale {
+        Presale {
+            creator: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            soft_cap: 10,
+            hard_cap: 100,
+            min_contribution: 1,
+            max_contribution: 100,
+            start_time: end_time - 1000,
+            end_time,
+            token_price: 1,
+            vesting_duration: 0,
+            total_raised: 50,
+            total_contributors: 1,
+            finalized: false,
+            cancelled: false,
+            finalized_at: 0,
+            bump: 0,
+            vault_bump: 0,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_large_buy_crossing_threshold_exactly_graduates_once() {
+        let mut curve = fresh_curve();
+
+        // One buy landing a lamport short does not graduate
+        curve.real_sol_reserves = DEFAULT_GRADUATION_THRESHOLD - 1;
+        assert!(!mark_graduated(&mut curve));
+        assert!(!curve.graduated);
+
+        // A single large buy whose net SOL lands exactly on the threshold
+        curve.real_sol_reserves = 0;
+        curve.real_sol_reserves += DEFAULT_GRADUATION_THRESHOLD;
+        assert!(mark_graduated(&mut curve));
+        assert!(curve.graduated);
+
+        // Re-evaluating the graduated curve is not a second transition
+        assert!(!mark_graduated(&mut curve));
+        curve.real_sol_reserves += 1;
+        assert!(!mark_graduated(&mut curve));
+        assert!(curve.graduated);
+    }
+
+    #[test]
+    fn test_sell_below_threshold_after_graduation_is_rejected() {
+        let mut curve = fresh_curve();
+        let tokens_sold = 100_000_000_000;
+        curve.tokens_sold = tokens_sold;
+        curve.real_token_reserves -= tokens_sold;
+        curve.virtual_token_reserves -= tokens_sold;
+        let sell_amount = 1_000_000_000;
+
+        // Before graduation a sell goes through
+        curve.real_sol_reserves = DEFAULT_GRADUATION_THRESHOLD - 1;
+        let quote = quote_sell(&curve, sell_amount, 0, PLATFORM_FEE_BPS).unwrap();
+        assert!(quote.sol_out > 0);
+
+        // Cross the threshold
+        curve.real_sol_reserves = DEFAULT_GRADUATION_THRESHOLD;
+        assert!(mark_graduated(&mut curve));
+        let before = (curve.real_sol_reserves, curve.real_token_reserves, curve.tokens_sold);
+
+        // A sell that would drop the reserves back below is rejected
+        assert_eq!(
+            quote_sell(&curve, sell_amount, 0, PLATFORM_FEE_BPS).unwrap_err(),
+            LaunchpadError::AlreadyGraduated.into()
+        );
+
+        // Even a quote taken before graduation can't be applied after it
+        assert_eq!(
+            apply_sell(&mut curve, &quote, sell_amount).unwrap_err(),
+            LaunchpadError::AlreadyGraduated.into()
+        );
+        assert_eq!(
+            (curve.real_sol_reserves, curve.real_token_reserves, curve.tokens_sold),
+            before
+        );
+        assert!(!mark_graduated(&mut curve));
+        assert!(curve.graduated);
+    }
+
+    fn contribution(amount: u64) -> Contribution {
+        Contribution {
+            contributor: Pubkey::new_unique(),
+            presale: Pubkey::new_unique(),
+            amount,
+            tokens_claimed: 0,
+            claimed: false,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_claimable_follows_vesting() {
+        let mut presale = ended_presale(1_000);
+        presale.token_price = 1_000_000_000;
+        presale.vesting_duration = 1_000;
+        let mut contribution = contribution(1_000);
+
+        // Locked until finalized
+        let view = vesting_view(&presale, &contribution, 5_000).unwrap();
+        assert_eq!((view.total_allocation, view.claimable_now, view.locked), (1_000, 0, 1_000));
+        assert_eq!(view.next_unlock_ts, None);
+
+        presale.finalized = true;
+        presale.finalized_at = 2_000;
+
+        // 25% vested, next percent at 260s in
+        let view = vesting_view(&presale, &contribution, 2_255).unwrap();
+        assert_eq!(view.claimable_now, 250);
+        assert_eq!(view.locked, 750);
+        assert_eq!(view.next_unlock_ts, Some(2_260));
+        assert_eq!(vested_pct(&presale, view.next_unlock_ts.unwrap()).unwrap(), 26);
+        assert_eq!(vested_pct(&presale, view.next_unlock_ts.unwrap() - 1).unwrap(), 25);
+
+        // Claimed tokens are no longer claimable
+        contribution.tokens_claimed = 250;
+        let view = vesting_view(&presale, &contribution, 2_255).unwrap();
+        assert_eq!((view.claimed, view.claimable_now, view.locked), (250, 0, 750));
+
+        // Fully vested
+        let view = vesting_view(&presale, &contribution, 3_000).unwrap();
+        assert_eq!((view.claimable_now, view.locked, view.next_unlock_ts), (750, 0, None));
+    }
+
+    #[test]
+    fn test_claimable_without_vesting_unlocks_at_finalize() {
+        let mut presale = ended_presale(1_000);
+        presale.token_price = 2_000_000_000;
+        presale.finalized = true;
+        presale.finalized_at = 2_000;
+
+        let view = vesting_view(&presale, &contribution(500), 2_000).unwrap();
+        assert_eq!(view, ClaimableTokens {
+            total_allocation: 1_000,
+            claimed: 0,
+            claimable_now: 1_000,
+            locked: 0,
+            next_unlock_ts: None,
+        });
+    }
+
+    #[test]
+    fn test_migration_preview_opens_pool_at_curve_price() {
+        // A curve that just raised the threshold: 30 SOL virtual + 85 SOL real
+        let mut curve = fresh_curve();
+        curve.virtual_sol_reserves = DEFAULT_VIRTUAL_SOL_RESERVES + DEFAULT_GRADUATION_THRESHOLD;
+        curve.virtual_token_reserves = 260_869_565_217_391;
+        curve.real_sol_reserves = DEFAULT_GRADUATION_THRESHOLD;
+        curve.real_token_reserves = 268_260_869_565_218;
+        curve.graduated = true;
+
+        let migration = MigrationConfig {
+            amm_program: Pubkey::new_unique(),
+            pool_fee_bps: 30,
+            migration_fee_bps: 100,
+        };
+        let preview = migration_preview(&curve, &migration).unwrap();
+
+        assert_eq!(preview.migration_fee, 850_000_000);
+        assert_eq!(preview.sol_amount + preview.migration_fee, curve.real_sol_reserves);
+        assert_eq!(preview.token_amount + preview.tokens_burned, curve.real_token_reserves);
+        assert!(preview.tokens_burned > 0);
+
+        // Same price as the curve, up to rounding
+        let curve_price = calculate_price(curve.virtual_sol_reserves, curve.virtual_token_reserves);
+        assert!(preview.initial_price.abs_diff(curve_price) <= 1);
+        assert_eq!(preview.amm_program, migration.amm_program);
+        assert!(preview.graduated);
+    }
+
+    #[test]
+    fn test_migration_preview_caps_tokens_at_reserves() {
+        let mut curve = fresh_curve();
+        curve.real_sol_reserves = 1_000_000_000_000;
+        curve.real_token_reserves = 1_000;
+
+        let migration = MigrationConfig {
+            amm_program: Pubkey::new_unique(),
+            pool_fee_bps: 0,
+            migration_fee_bps: 0,
+        };
+        let preview = migration_preview(&curve, &migration).unwrap();
+        assert_eq!((preview.token_amount, preview.tokens_burned), (1_000, 0));
+        assert_eq!(preview.sol_amount, curve.real_sol_reserves);
+    }
+
+    #[test]
+    fn test_force_refund_opens_after_delay() {
+        let end_time = 1_000_000;
+        let presale = ended_presale(end_time);
+
+        assert!(!force_refund_available(&presale, end_time));
+        assert!(!force_refund_available(&presale, end_time + FORCE_REFUND_DELAY - 1));
+        assert!(force_refund_available(&presale, end_time + FORCE_REFUND_DELAY));
+    }
+
+    #[test]
+    fn test_force_refund_of_unfinalized_soft_cap_presale() {
+        let end_time = 1_000_000;
+        let now = end_time + FORCE_REFUND_DELAY;
+        // Soft cap met, but the creator never finalized
+        let mut presale = ended_presale(end_time);
+        let mut contribution = contribution(40);
+
+        assert_eq!(
+            take_force_refund(&mut presale, &mut contribution, now - 1).unwrap_err(),
+            LaunchpadError::ForceRefundNotAvailable.into()
+        );
+
+        assert_eq!(take_force_refund(&mut presale, &mut contribution, now).unwrap(), 40);
+        assert!(presale.cancelled);
+        assert!(contribution.claimed);
+        assert_eq!(contribution.amount, 0);
+
+        // The creator turning up later can no longer finalize
+        assert_eq!(
+            finalize(&mut presale, now).unwrap_err(),
+            LaunchpadError::PresaleCancelled.into()
+        );
+        assert!(!presale.finalized);
+
+        // And the same contribution isn't refunded twice
+        assert_eq!(
+            take_force_refund(&mut presale, &mut contribution, now).unwrap_err(),
+            LaunchpadError::AlreadyClaimed.into()
+        );
+    }
+
+    #[test]
+    fn test_force_refund_never_overrides_finalize() {
+        let end_time = 1_000_000;
+        let mut presale = ended_presale(end_time);
+        presale.finalized = true;
+        presale.finalized_at = end_time + 1;
+
+        assert!(!force_refund_available(&presale, end_time + FORCE_REFUND_DELAY));
+        assert!(!force_refund_available(&presale, i64::MAX));
+
+        // Finalizing first closes the force-refund path for contributors
+        let mut presale = ended_presale(end_time);
+        finalize(&mut presale, end_time + 1).unwrap();
+        assert_eq!(
+            take_force_refund(&mut presale, &mut contribution(40), end_time + FORCE_REFUND_DELAY)
+                .unwrap_err(),
+            LaunchpadError::ForceRefundNotAvailable.into()
+        );
+        assert!(!presale.cancelled);
+    }
+
+    #[test]
+    fn test_buy_limit_max_price() {
+        // 1 SOL for 1_000 tokens averages 1e6 lamports per token, scaled by 1e6
+        let price = calculate_price(1_000_000_000, 1_000);
+        assert_eq!(price, 1_000_000_000_000);
+
+        assert!(BuyLimit::MaxPrice(price).check(1_000_000_000, 1_000).is_ok());
+        assert!(BuyLimit::MaxPrice(price - 1).check(1_000_000_000, 1_000).is_err());
+        // Price moved: the same SOL now buys fewer tokens
+        assert!(BuyLimit::MaxPrice(price).check(1_000_000_000, 999).is_err());
+        assert!(BuyLimit::MaxPrice(u64::MAX).check(1_000_000_000, 0).is_err());
+
+        assert!(BuyLimit::MinTokensOut(1_000).check(1_000_000_000, 1_000).is_ok());
+        assert!(BuyLimit::MinTokensOut(1_001).check(1_000_000_000, 1_000).is_err());
+    }
+
+    fn launch_pda(index: u64) -> Pubkey {
+        Pubkey::find_program_address(&[LAUNCH_SEED, &index.to_le_bytes()], &ID).0
+    }
+
+    #[test]
+    fn test_launch_pdas_are_distinct_per_index() {
+        assert_ne!(launch_pda(0), launch_pda(1));
+        assert_ne!(launch_pda(1), launch_pda(256));
+        assert_eq!(launch_pda(7), launch_pda(7));
+    }
+
+    #[test]
+    fn test_platform_fee_override_only_lowers_fee() {
+        assert_eq!(effective_platform_fee_bps(100, None), 100);
+        assert_eq!(effective_platform_fee_bps(100, Some(25)), 25);
+        assert_eq!(effective_platform_fee_bps(100, Some(0)), 0);
+
+        // Global fee cut below an existing override wins
+        assert_eq!(effective_platform_fee_bps(10, Some(25)), 10);
+    }
+
+    #[test]
+    fn test_referral_below_rent_stays_with_the_protocol() {
+        let rent = 890_880;
+        // An unfunded referrer can't receive less than rent exemption
+        assert_eq!(referral_payout(5_000, 0, rent), 0);
+        assert_eq!(referral_payout(rent, 0, rent), rent);
+        // A funded referrer takes any cut
+        assert_eq!(referral_payout(5_000, rent, rent), 5_000);
+        assert_eq!(referral_payout(0, 0, rent), 0);
+    }
+
+    #[test]
+    fn test_claimable_protocol_fees_leave_rent_in_the_vault() {
+        let rent = 890_880;
+        assert_eq!(claimable_protocol_fees(rent, rent), 0);
+        assert_eq!(claimable_protocol_fees(rent + 12_345, rent), 12_345);
+        assert_eq!(claimable_protocol_fees(rent - 1, rent), 0);
+    }
+
+    #[test]
+    fn test_tokens_after_fee_uses_curve_override() {
+        let mut curve = fresh_curve();
+        let global_bps = PLATFORM_FEE_BPS;
+        let tokens_out = 1_000_000;
+
+        let fee_bps = effective_platform_fee_bps(global_bps, curve.platform_fee_override);
+        assert_eq!(tokens_after_fee(tokens_out, fee_bps), 990_000);
+
+        // A fee-free launch delivers everything the curve quotes
+        curve.platform_fee_override = Some(0);
+        let fee_bps = effective_platform_fee_bps(global_bps, curve.platform_fee_override);
+        assert_eq!(tokens_after_fee(tokens_out, fee_bps), tokens_out);
+
+        curve.platform_fee_override = Some(25);
+        let fee_bps = effective_platform_fee_bps(global_bps, curve.platform_fee_override);
+        assert_eq!(tokens_after_fee(tokens_out, fee_bps), 997_500);
+
+        assert_eq!(tokens_after_fee(u64::MAX, 0), u64::MAX);
+    }
+
+    #[test]
+    fn test_creator_pause_skips_settled_presales() {
+        let mut live = ended_presale(1_000);
+        let mut finalized = ended_presale(1_000);
+        finalized.finalized = true;
+        let mut cancelled = ended_presale(1_000);
+        cancelled.cancelled = true;
+
+        assert!(set_presale_paused(&mut live, true));
+        assert!(live.paused);
+        // Pausing twice affects nothing, so no second event
+        assert!(!set_presale_paused(&mut live, true));
+
+        assert!(!set_presale_paused(&mut finalized, true));
+        assert!(!set_presale_paused(&mut cancelled, true));
+        assert!(!finalized.paused && !cancelled.paused);
+
+        assert!(set_presale_paused(&mut live, false));
+        assert!(!live.paused);
+    }
+
+    #[test]
+    fn test_creator_profile_tracks_presales() {
+        let mut profile = CreatorProfile {
+            creator: Pubkey::new_unique(),
+            presales: Vec::new(),
+            paused: false,
+            bump: 0,
+        };
+        let first = Pubkey::new_unique();
+
+        profile.track(first).unwrap();
+        assert!(profile.track(first).is_err());
+        for _ in 1..MAX_CREATOR_PRESALES {
+            profile.track(Pubkey::new_unique()).unwrap();
+        }
+        assert!(profile.track(Pubkey::new_unique()).is_err());
+
+        profile.untrack(&first).unwrap();
+        assert!(profile.untrack(&first).is_err());
+        assert_eq!(profile.presales.len(), MAX_CREATOR_PRESALES - 1);
+
+        // A full profile still fits in its account
+        profile.track(first).unwrap();
+        assert!(profile.try_to_vec().unwrap().len() + 8 <= CreatorProfile::SPACE);
+    }
+}
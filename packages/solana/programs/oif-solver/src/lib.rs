@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{self, Mint, Token, TokenAccount, Transfer},
+    token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 
 declare_id!("GYSFWUUKUFAdtv1TgZ3GkGfdCxPNbyRj1jsW8VRK7hBs");
@@ -14,16 +14,26 @@ pub const INTENT_SEED: &[u8] = b"intent";
 pub const ESCROW_SEED: &[u8] = b"escrow";
 pub const SOLVER_SEED: &[u8] = b"solver";
 pub const CONFIG_SEED: &[u8] = b"config";
+pub const EARNINGS_SEED: &[u8] = b"earnings";
 
 // Intent expiry: 24 hours default
 pub const DEFAULT_INTENT_EXPIRY: i64 = 86400;
 
+// Longest single expiry extension: 24 hours
+pub const MAX_EXPIRY_EXTENSION: i64 = 86400;
+
+// Intents never live longer than 7 days from creation, extensions included
+pub const MAX_INTENT_LIFETIME: i64 = 7 * 86400;
+
 // Maximum fill operations per intent
 pub const MAX_FILLS: usize = 10;
 
 // Minimum solver stake: 1 SOL
 pub const MIN_SOLVER_STAKE: u64 = 1_000_000_000;
 
+// A slashed solver cannot top up its stake for 24 hours after the slash
+pub const SLASH_COOLDOWN: i64 = 86400;
+
 #[program]
 pub mod oif_solver {
     use super::*;
@@ -40,6 +50,7 @@ pub mod oif_solver {
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
         config.authority = ctx.accounts.authority.key();
+        config.pending_authority = None;
         config.protocol_fee_bps = protocol_fee_bps;
         config.min_solver_stake = if min_solver_stake > 0 {
             min_solver_stake
@@ -53,12 +64,56 @@ pub mod oif_solver {
         Ok(())
     }
 
+    /// Propose a new authority (current authority only)
+    /// The transfer only takes effect once the new authority calls accept_authority
+    pub fn transfer_authority(
+        ctx: Context<TransferAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_authority = Some(new_authority);
+
+        emit!(AuthorityTransferStarted {
+            authority: config.authority,
+            pending_authority: new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Accept a pending authority transfer (pending authority only)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let new_authority = ctx.accounts.new_authority.key();
+
+        require!(
+            config.pending_authority == Some(new_authority),
+            OIFError::NotPendingAuthority
+        );
+
+        let previous_authority = config.authority;
+        config.authority = new_authority;
+        config.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            previous_authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
     /// Register a solver
     /// stake_amount is passed as an instruction parameter for validation
+    ///
+    /// With `defer_payout`, fills credit a per-mint `SolverEarnings` ledger
+    /// instead of paying the solver's token account; the balance is swept
+    /// with `claim_solver_earnings`.
     pub fn register_solver(
         ctx: Context<RegisterSolver>,
         supported_chains: Vec<u32>,
         stake_amount: u64,
+        defer_payout: bool,
     ) -> Result<()> {
         require!(
             stake_amount >= ctx.accounts.config.min_solver_stake,
@@ -76,6 +131,10 @@ pub mod oif_solver {
         solver.active = true;
         solver.registered_at = Clock::get()?.unix_timestamp;
         solver.bump = ctx.bumps.solver;
+        solver.defer_payout = defer_payout;
+        solver.disputes = 0;
+        solver.total_fill_latency = 0;
+        solver.last_slashed_at = 0;
 
         // Transfer stake from owner to vault
         anchor_lang::system_program::transfer(
@@ -104,6 +163,13 @@ pub mod oif_solver {
     // ============================================================================
 
     /// Create a cross-chain swap intent
+    ///
+    /// `solver_tip` is escrowed on top of `source_amount` and paid to the solver
+    /// whose fill completes the intent.
+    ///
+    /// Token-2022 mints with a transfer fee are supported: the fee is taken out
+    /// of `source_amount`, and the intent records what the escrow actually
+    /// received. Payouts from the escrow are charged the fee again on receipt.
     pub fn create_intent(
         ctx: Context<CreateIntent>,
         intent_id: [u8; 32],
@@ -116,8 +182,14 @@ pub mod oif_solver {
         recipient: [u8; 32], // Can be non-Solana address
         expiry: i64,
         partial_fill_allowed: bool,
+        min_fill_amount: u64,
+        solver_tip: u64,
     ) -> Result<()> {
         let clock = Clock::get()?;
+
+        // The argument is what the intent and its event record; it must be the
+        // mint actually escrowed
+        check_source_token(&source_token, &ctx.accounts.source_token.key())?;
         
         let intent_expiry = if expiry > 0 {
             expiry
@@ -128,6 +200,30 @@ pub mod oif_solver {
         require!(intent_expiry > clock.unix_timestamp, OIFError::InvalidExpiry);
         require!(source_amount > 0, OIFError::InvalidAmount);
         require!(min_destination_amount > 0, OIFError::InvalidAmount);
+        require!(min_fill_amount <= source_amount, OIFError::InvalidMinFill);
+
+        let escrow_amount = source_amount.checked_add(solver_tip)
+            .ok_or(OIFError::MathOverflow)?;
+
+        // Transfer source tokens and tip to escrow
+        let escrow_before = ctx.accounts.escrow_token_account.amount;
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.creator_token_account.to_account_info(),
+                    mint: ctx.accounts.source_token.to_account_info(),
+                    to: ctx.accounts.escrow_token_account.to_account_info(),
+                    authority: ctx.accounts.creator.to_account_info(),
+                },
+            ),
+            escrow_amount,
+            ctx.accounts.source_token.decimals,
+        )?;
+
+        let received = escrow_received(&mut ctx.accounts.escrow_token_account, escrow_before)?;
+        let source_amount = net_source_amount(received, solver_tip)?;
+        require!(min_fill_amount <= source_amount, OIFError::InvalidMinFill);
 
         let intent = &mut ctx.accounts.intent;
         intent.creator = ctx.accounts.creator.key();
@@ -145,19 +241,9 @@ pub mod oif_solver {
         intent.status = IntentStatus::Open;
         intent.created_at = clock.unix_timestamp;
         intent.bump = ctx.bumps.intent;
-
-        // Transfer source tokens to escrow
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.creator_token_account.to_account_info(),
-                    to: ctx.accounts.escrow_token_account.to_account_info(),
-                    authority: ctx.accounts.creator.to_account_info(),
-                },
-            ),
-            source_amount,
-        )?;
+        intent.min_fill_amount = min_fill_amount;
+        intent.solver_tip = solver_tip;
+        intent.tip_paid = false;
 
         // Update config stats
         let config = &mut ctx.accounts.config;
@@ -169,9 +255,113 @@ pub mod oif_solver {
             intent_id,
             source_chain,
             destination_chain,
+            source_token,
+            destination_token,
             source_amount,
             min_destination_amount,
+            recipient,
             expiry: intent_expiry,
+            partial_fill_allowed,
+            min_fill_amount,
+            solver_tip,
+        });
+
+        Ok(())
+    }
+
+    /// Add source tokens to an open intent (creator only)
+    ///
+    /// `new_min_destination_amount` of zero scales the current minimum by the
+    /// same ratio as the source amount, rounding up.
+    pub fn top_up_intent(
+        ctx: Context<TopUpIntent>,
+        additional_amount: u64,
+        new_min_destination_amount: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let intent = &ctx.accounts.intent;
+
+        require!(intent.status == IntentStatus::Open, OIFError::IntentNotOpen);
+        require!(clock.unix_timestamp < intent.expiry, OIFError::IntentExpired);
+        require!(additional_amount > 0, OIFError::InvalidAmount);
+        require!(
+            intent.partial_fill_allowed || intent.amount_filled == 0,
+            OIFError::PartialFillNotAllowed
+        );
+
+        let escrow_before = ctx.accounts.escrow_token_account.amount;
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.creator_token_account.to_account_info(),
+                    mint: ctx.accounts.source_token.to_account_info(),
+                    to: ctx.accounts.escrow_token_account.to_account_info(),
+                    authority: ctx.accounts.creator.to_account_info(),
+                },
+            ),
+            additional_amount,
+            ctx.accounts.source_token.decimals,
+        )?;
+
+        // Net of any transfer fee
+        let additional_amount =
+            escrow_received(&mut ctx.accounts.escrow_token_account, escrow_before)?;
+        require!(additional_amount > 0, OIFError::InvalidAmount);
+
+        let intent = &ctx.accounts.intent;
+        let source_amount = intent.source_amount.checked_add(additional_amount)
+            .ok_or(OIFError::MathOverflow)?;
+
+        let min_destination_amount = if new_min_destination_amount > 0 {
+            require!(
+                new_min_destination_amount >= intent.min_destination_amount,
+                OIFError::InvalidAmount
+            );
+            new_min_destination_amount
+        } else {
+            let scaled = (intent.min_destination_amount as u128)
+                .checked_mul(source_amount as u128)
+                .ok_or(OIFError::MathOverflow)?
+                .div_ceil(intent.source_amount as u128);
+            u64::try_from(scaled).map_err(|_| OIFError::MathOverflow)?
+        };
+
+        let intent = &mut ctx.accounts.intent;
+        intent.source_amount = source_amount;
+        intent.min_destination_amount = min_destination_amount;
+
+        emit!(IntentToppedUp {
+            intent: intent.key(),
+            additional_amount,
+            source_amount,
+            min_destination_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Push an open intent's expiry back by `extension` seconds (creator only)
+    ///
+    /// At most MAX_EXPIRY_EXTENSION per call, and never past
+    /// MAX_INTENT_LIFETIME from `created_at`. Only intents without fills can
+    /// be extended, so a solver partway through a fill keeps the terms it
+    /// started under.
+    pub fn extend_intent(ctx: Context<ExtendIntent>, extension: i64) -> Result<()> {
+        let clock = Clock::get()?;
+        let intent = &mut ctx.accounts.intent;
+
+        require!(intent.status == IntentStatus::Open, OIFError::IntentNotOpen);
+        require!(clock.unix_timestamp < intent.expiry, OIFError::IntentExpired);
+        require!(intent.amount_filled == 0, OIFError::IntentPartiallyFilled);
+
+        let previous_expiry = intent.expiry;
+        intent.expiry = extended_expiry(intent.created_at, previous_expiry, extension)?;
+
+        emit!(IntentExtended {
+            intent: intent.key(),
+            previous_expiry,
+            expiry: intent.expiry,
         });
 
         Ok(())
@@ -221,6 +411,18 @@ pub mod oif_solver {
         
         let actual_fill = fill_amount.min(remaining);
         require!(actual_fill > 0, OIFError::NothingToFill);
+        // Dust fills are rejected, except for whatever is left at the end
+        require!(
+            actual_fill >= intent.min_fill_amount || actual_fill == remaining,
+            OIFError::FillBelowMinimum
+        );
+
+        // The tip goes to whichever solver completes the intent
+        let tip = if actual_fill == remaining && !intent.tip_paid {
+            intent.solver_tip
+        } else {
+            0
+        };
 
         // Calculate fee
         let fee = actual_fill
@@ -229,7 +431,10 @@ pub mod oif_solver {
             .checked_div(10000)
             .ok_or(OIFError::MathOverflow)?;
 
-        let solver_receives = actual_fill.checked_sub(fee).ok_or(OIFError::MathOverflow)?;
+        let solver_receives = actual_fill.checked_sub(fee)
+            .ok_or(OIFError::MathOverflow)?
+            .checked_add(tip)
+            .ok_or(OIFError::MathOverflow)?;
 
         // Get account infos and keys before mutable borrow
         let intent_id = intent.intent_id;
@@ -240,8 +445,9 @@ pub mod oif_solver {
         let intent_account_info = ctx.accounts.intent.to_account_info();
         let token_program_info = ctx.accounts.token_program.to_account_info();
         let escrow_account_info = ctx.accounts.escrow_token_account.to_account_info();
-        let solver_token_info = ctx.accounts.solver_token_account.to_account_info();
         let fee_account_info = ctx.accounts.fee_account.to_account_info();
+        let mint_info = ctx.accounts.source_token.to_account_info();
+        let decimals = ctx.accounts.source_token.decimals;
 
         // Transfer tokens from escrow to solver
         let intent_seeds = &[
@@ -251,32 +457,68 @@ pub mod oif_solver {
         ];
         let signer = &[&intent_seeds[..]];
 
-        token::transfer(
+        // Deferred solvers are paid into the shared earnings vault and credited
+        let payout_account_info = if ctx.accounts.solver.defer_payout {
+            ctx.accounts.earnings_vault.as_ref()
+                .ok_or(OIFError::MissingPayoutAccount)?
+                .to_account_info()
+        } else {
+            ctx.accounts.solver_token_account.as_ref()
+                .ok_or(OIFError::MissingPayoutAccount)?
+                .to_account_info()
+        };
+        let vault_before = ctx.accounts.earnings_vault.as_ref().map(|vault| vault.amount);
+
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
                 token_program_info.clone(),
-                Transfer {
+                TransferChecked {
                     from: escrow_account_info.clone(),
-                    to: solver_token_info,
+                    mint: mint_info.clone(),
+                    to: payout_account_info,
                     authority: intent_account_info.clone(),
                 },
                 signer,
             ),
             solver_receives,
+            decimals,
         )?;
 
+        if ctx.accounts.solver.defer_payout {
+            let (Some(vault), Some(earnings), Some(before)) = (
+                ctx.accounts.earnings_vault.as_mut(),
+                ctx.accounts.solver_earnings.as_mut(),
+                vault_before,
+            ) else {
+                return err!(OIFError::MissingPayoutAccount);
+            };
+            // Net of any transfer fee into the vault
+            let credited = escrow_received(vault, before)?;
+            earnings.credit(credited)?;
+
+            emit!(SolverEarningsCredited {
+                solver: solver_key,
+                mint: earnings.mint,
+                amount: credited,
+                accrued: earnings.accrued,
+            });
+        }
+
         // Transfer fee to protocol
         if fee > 0 {
-            token::transfer(
+            token_interface::transfer_checked(
                 CpiContext::new_with_signer(
                     token_program_info,
-                    Transfer {
+                    TransferChecked {
                         from: escrow_account_info,
+                        mint: mint_info,
                         to: fee_account_info,
                         authority: intent_account_info,
                     },
                     signer,
                 ),
                 fee,
+                decimals,
             )?;
         }
 
@@ -289,10 +531,15 @@ pub mod oif_solver {
             intent.status = IntentStatus::Filled;
             intent.filled_at = clock.unix_timestamp;
         }
+        if tip > 0 {
+            intent.tip_paid = true;
+        }
+
+        let fill_latency = clock.unix_timestamp.saturating_sub(intent.created_at).max(0) as u64;
 
         // Update solver stats
         let solver = &mut ctx.accounts.solver;
-        solver.intents_filled += 1;
+        solver.record_fill(fill_latency)?;
         solver.total_volume = solver.total_volume.checked_add(actual_fill as u128)
             .ok_or(OIFError::MathOverflow)?;
         solver.reputation_score = solver.reputation_score.saturating_add(10);
@@ -311,6 +558,51 @@ pub mod oif_solver {
             fill_amount: actual_fill,
             destination_tx_hash,
             remaining: final_remaining,
+            tip_paid: tip,
+        });
+
+        Ok(())
+    }
+
+    /// Open the earnings ledger a deferred-payout solver needs for `mint`
+    /// SECURITY: Separate instruction instead of init_if_needed in fill_intent
+    pub fn create_solver_earnings(ctx: Context<CreateSolverEarnings>) -> Result<()> {
+        let earnings = &mut ctx.accounts.solver_earnings;
+        earnings.solver = ctx.accounts.solver.key();
+        earnings.mint = ctx.accounts.mint.key();
+        earnings.accrued = 0;
+        earnings.claimed = 0;
+        earnings.bump = ctx.bumps.solver_earnings;
+        Ok(())
+    }
+
+    /// Sweep a solver's unclaimed earnings in one mint to its token account
+    pub fn claim_solver_earnings(ctx: Context<ClaimSolverEarnings>) -> Result<()> {
+        let amount = ctx.accounts.solver_earnings.claim()?;
+
+        let config_seeds = &[CONFIG_SEED, &[ctx.accounts.config.bump]];
+        let signer = &[&config_seeds[..]];
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.earnings_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.solver_token_account.to_account_info(),
+                    authority: ctx.accounts.config.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let earnings = &ctx.accounts.solver_earnings;
+        emit!(SolverEarningsClaimed {
+            solver: earnings.solver,
+            mint: earnings.mint,
+            amount,
         });
 
         Ok(())
@@ -327,9 +619,12 @@ pub mod oif_solver {
 
         let remaining = intent.source_amount.checked_sub(intent.amount_filled)
             .ok_or(OIFError::MathOverflow)?;
+        let unclaimed_tip = if intent.tip_paid { 0 } else { intent.solver_tip };
+        let refund = remaining.checked_add(unclaimed_tip)
+            .ok_or(OIFError::MathOverflow)?;
 
-        // Return remaining tokens to creator
-        if remaining > 0 {
+        // Return remaining tokens and any unclaimed tip to creator
+        if refund > 0 {
             let intent_seeds = &[
                 INTENT_SEED,
                 intent.intent_id.as_ref(),
@@ -337,17 +632,19 @@ pub mod oif_solver {
             ];
             let signer = &[&intent_seeds[..]];
 
-            token::transfer(
+            token_interface::transfer_checked(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
+                    TransferChecked {
                         from: ctx.accounts.escrow_token_account.to_account_info(),
+                        mint: ctx.accounts.source_token.to_account_info(),
                         to: ctx.accounts.creator_token_account.to_account_info(),
                         authority: intent.to_account_info(),
                     },
                     signer,
                 ),
-                remaining,
+                refund,
+                ctx.accounts.source_token.decimals,
             )?;
         }
 
@@ -355,7 +652,7 @@ pub mod oif_solver {
 
         emit!(IntentCancelled {
             intent: intent.key(),
-            refunded: remaining,
+            refunded: refund,
         });
 
         Ok(())
@@ -371,9 +668,12 @@ pub mod oif_solver {
 
         let remaining = intent.source_amount.checked_sub(intent.amount_filled)
             .ok_or(OIFError::MathOverflow)?;
+        let unclaimed_tip = if intent.tip_paid { 0 } else { intent.solver_tip };
+        let refund = remaining.checked_add(unclaimed_tip)
+            .ok_or(OIFError::MathOverflow)?;
 
-        // Return remaining tokens to creator
-        if remaining > 0 {
+        // Return remaining tokens and any unclaimed tip to creator
+        if refund > 0 {
             let intent_seeds = &[
                 INTENT_SEED,
                 intent.intent_id.as_ref(),
@@ -381,17 +681,19 @@ pub mod oif_solver {
             ];
             let signer = &[&intent_seeds[..]];
 
-            token::transfer(
+            token_interface::transfer_checked(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
+                    TransferChecked {
                         from: ctx.accounts.escrow_token_account.to_account_info(),
+                        mint: ctx.accounts.source_token.to_account_info(),
                         to: ctx.accounts.creator_token_account.to_account_info(),
                         authority: intent.to_account_info(),
                     },
                     signer,
                 ),
-                remaining,
+                refund,
+                ctx.accounts.source_token.decimals,
             )?;
         }
 
@@ -399,7 +701,7 @@ pub mod oif_solver {
 
         emit!(IntentExpired {
             intent: intent.key(),
-            refunded: remaining,
+            refunded: refund,
         });
 
         Ok(())
@@ -409,7 +711,17 @@ pub mod oif_solver {
     // Solver Management
     // ============================================================================
 
-    /// Slash solver stake for misbehavior
+    /// Fill record of a solver (read-only, for routers ranking solvers)
+    ///
+    /// # Return Data
+    /// The Borsh-encoded `SolverStats`, readable via `get_return_data()` or
+    /// transaction simulation.
+    pub fn get_solver_stats(ctx: Context<GetSolverStats>) -> Result<SolverStats> {
+        Ok(ctx.accounts.solver.stats())
+    }
+
+    /// Slash solver stake for misbehavior. Each slash resolves a dispute
+    /// against the solver and counts towards its `SolverStats`.
     pub fn slash_solver(
         ctx: Context<SlashSolver>,
         slash_amount: u64,
@@ -422,6 +734,8 @@ pub mod oif_solver {
         let actual_slash = slash_amount.min(solver.stake);
         solver.stake = solver.stake.saturating_sub(actual_slash);
         solver.reputation_score = solver.reputation_score.saturating_sub(100);
+        solver.disputes = solver.disputes.saturating_add(1);
+        solver.last_slashed_at = Clock::get()?.unix_timestamp;
 
         // If stake falls below minimum, deactivate solver
         if solver.stake < ctx.accounts.config.min_solver_stake {
@@ -458,6 +772,43 @@ pub mod oif_solver {
         Ok(())
     }
 
+    /// Add lamports to a solver's stake. A solver that a slash left below
+    /// `min_solver_stake` is reactivated once the stake is back above it.
+    /// Not allowed until `SLASH_COOLDOWN` has passed since the last slash.
+    pub fn top_up_stake(ctx: Context<TopUpStake>, amount: u64) -> Result<()> {
+        require!(amount > 0, OIFError::InvalidAmount);
+
+        let solver = &mut ctx.accounts.solver;
+        let now = Clock::get()?.unix_timestamp;
+        let reactivated = solver.top_up(amount, ctx.accounts.config.min_solver_stake, now)?;
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        emit!(StakeToppedUp {
+            solver: solver.key(),
+            amount,
+            stake: solver.stake,
+        });
+
+        if reactivated {
+            emit!(SolverReactivated {
+                solver: solver.key(),
+                stake: solver.stake,
+            });
+        }
+
+        Ok(())
+    }
+
     /// Withdraw solver stake (deactivates solver)
     pub fn withdraw_stake(ctx: Context<WithdrawStake>) -> Result<()> {
         let solver = &mut ctx.accounts.solver;
@@ -497,6 +848,48 @@ pub mod oif_solver {
     }
 }
 
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Tokens that landed in the escrow since `before`, net of any transfer fee
+fn escrow_received(
+    escrow: &mut InterfaceAccount<TokenAccount>,
+    before: u64,
+) -> Result<u64> {
+    escrow.reload()?;
+    Ok(escrow.amount.checked_sub(before).ok_or(OIFError::MathOverflow)?)
+}
+
+fn check_source_token(source_token: &Pubkey, escrowed_mint: &Pubkey) -> Result<()> {
+    require_keys_eq!(*source_token, *escrowed_mint, OIFError::SourceTokenMismatch);
+    Ok(())
+}
+
+/// Source amount backed by `received` escrowed tokens. Transfer fees come out
+/// of the source amount so the promised tip stays whole.
+fn net_source_amount(received: u64, solver_tip: u64) -> Result<u64> {
+    let source_amount = received.checked_sub(solver_tip)
+        .ok_or(OIFError::TransferFeeExceedsAmount)?;
+    require!(source_amount > 0, OIFError::TransferFeeExceedsAmount);
+    Ok(source_amount)
+}
+
+/// New expiry after extending `expiry` by `extension`, bounded per call and by
+/// the intent's total lifetime
+fn extended_expiry(created_at: i64, expiry: i64, extension: i64) -> Result<i64> {
+    require!(
+        extension > 0 && extension <= MAX_EXPIRY_EXTENSION,
+        OIFError::InvalidExpiry
+    );
+    let new_expiry = expiry.checked_add(extension).ok_or(OIFError::MathOverflow)?;
+    require!(
+        new_expiry - created_at <= MAX_INTENT_LIFETIME,
+        OIFError::IntentLifetimeExceeded
+    );
+    Ok(new_expiry)
+}
+
 // ============================================================================
 // Enums
 // ============================================================================
@@ -522,6 +915,8 @@ pub struct OIFConfig {
     pub total_filled: u64,
     pub total_volume: u128,
     pub bump: u8,
+    /// Proposed authority awaiting accept_authority
+    pub pending_authority: Option<Pubkey>,
 }
 
 #[account]
@@ -542,6 +937,11 @@ pub struct Intent {
     pub created_at: i64,
     pub filled_at: i64,
     pub bump: u8,
+    /// Smallest fill accepted, unless it fills the rest of the intent
+    pub min_fill_amount: u64,
+    /// Escrowed on top of source_amount for the solver that completes the intent
+    pub solver_tip: u64,
+    pub tip_paid: bool,
 }
 
 #[account]
@@ -555,6 +955,111 @@ pub struct Solver {
     pub active: bool,
     pub registered_at: i64,
     pub bump: u8,
+    /// Fills credit SolverEarnings instead of paying out immediately
+    pub defer_payout: bool,
+    /// Disputes resolved against the solver by slash_solver
+    pub disputes: u64,
+    /// Sum over fills of seconds between intent creation and the fill
+    pub total_fill_latency: u64,
+    /// Time of the most recent slash; zero if never slashed
+    pub last_slashed_at: i64,
+}
+
+impl Solver {
+    pub fn record_fill(&mut self, latency: u64) -> Result<()> {
+        self.intents_filled = self.intents_filled.checked_add(1)
+            .ok_or(OIFError::MathOverflow)?;
+        self.total_fill_latency = self.total_fill_latency.saturating_add(latency);
+        Ok(())
+    }
+
+    /// Add `amount` to the stake, reactivating the solver if that brings an
+    /// inactive solver back to `min_stake`. Returns whether it was reactivated.
+    pub fn top_up(&mut self, amount: u64, min_stake: u64, now: i64) -> Result<bool> {
+        require!(
+            self.last_slashed_at == 0
+                || now >= self.last_slashed_at.saturating_add(SLASH_COOLDOWN),
+            OIFError::SlashCooldownActive
+        );
+        self.stake = self.stake.checked_add(amount).ok_or(OIFError::MathOverflow)?;
+
+        let reactivated = !self.active && self.stake >= min_stake;
+        if reactivated {
+            self.active = true;
+        }
+        Ok(reactivated)
+    }
+
+    /// Rates derived from the raw counters, so they are never stale
+    pub fn stats(&self) -> SolverStats {
+        let outcomes = self.intents_filled.saturating_add(self.disputes);
+        let success_rate_bps = if outcomes == 0 {
+            0
+        } else {
+            (self.intents_filled as u128 * 10_000 / outcomes as u128) as u16
+        };
+        let avg_fill_latency = self.total_fill_latency
+            .checked_div(self.intents_filled)
+            .unwrap_or(0);
+
+        SolverStats {
+            intents_filled: self.intents_filled,
+            disputes: self.disputes,
+            success_rate_bps,
+            avg_fill_latency,
+            reputation_score: self.reputation_score,
+            total_volume: self.total_volume,
+            active: self.active,
+        }
+    }
+}
+
+/// Snapshot returned by get_solver_stats
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SolverStats {
+    pub intents_filled: u64,
+    pub disputes: u64,
+    /// Fills out of fills plus disputes, in basis points; zero with no history
+    pub success_rate_bps: u16,
+    /// Mean seconds from intent creation to fill; zero with no fills
+    pub avg_fill_latency: u64,
+    pub reputation_score: u64,
+    pub total_volume: u128,
+    pub active: bool,
+}
+
+/// Fill proceeds owed to a deferred-payout solver in one mint, held in the
+/// config's earnings vault for that mint
+#[account]
+pub struct SolverEarnings {
+    pub solver: Pubkey,
+    pub mint: Pubkey,
+    /// Credited by fills, net of any transfer fee into the vault
+    pub accrued: u64,
+    /// Swept to the solver by claim_solver_earnings
+    pub claimed: u64,
+    pub bump: u8,
+}
+
+impl SolverEarnings {
+    pub fn credit(&mut self, amount: u64) -> Result<()> {
+        self.accrued = self.accrued.checked_add(amount)
+            .ok_or(OIFError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn claimable(&self) -> u64 {
+        self.accrued.saturating_sub(self.claimed)
+    }
+
+    /// Mark everything accrued so far as claimed and return the amount to pay out
+    pub fn claim(&mut self) -> Result<u64> {
+        let amount = self.claimable();
+        require!(amount > 0, OIFError::NothingToClaim);
+        self.claimed = self.claimed.checked_add(amount)
+            .ok_or(OIFError::MathOverflow)?;
+        Ok(amount)
+    }
 }
 
 // ============================================================================
@@ -569,7 +1074,7 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + 32 + 2 + 8 + 8 + 8 + 16 + 1,
+        space = 8 + 32 + 2 + 8 + 8 + 8 + 16 + 1 + 33,
         seeds = [CONFIG_SEED],
         bump
     )]
@@ -578,6 +1083,31 @@ pub struct Initialize<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct TransferAuthority<'info> {
+    #[account(address = config.authority @ OIFError::Unauthorized)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, OIFConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, OIFConfig>,
+}
+
 #[derive(Accounts)]
 pub struct RegisterSolver<'info> {
     #[account(mut)]
@@ -592,7 +1122,7 @@ pub struct RegisterSolver<'info> {
     #[account(
         init,
         payer = owner,
-        space = 8 + 32 + 8 + 4 + (4 * 20) + 8 + 16 + 8 + 1 + 8 + 1,
+        space = 8 + 32 + 8 + 4 + (4 * 20) + 8 + 16 + 8 + 1 + 8 + 1 + 1 + 8 + 8 + 8,
         seeds = [SOLVER_SEED, owner.key().as_ref()],
         bump
     )]
@@ -622,12 +1152,12 @@ pub struct CreateIntent<'info> {
     )]
     pub config: Account<'info, OIFConfig>,
 
-    pub source_token: Account<'info, Mint>,
+    pub source_token: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
         payer = creator,
-        space = 8 + 32 + 32 + 4 + 4 + 32 + 32 + 8 + 8 + 32 + 8 + 1 + 8 + 1 + 8 + 8 + 1,
+        space = 8 + 32 + 32 + 4 + 4 + 32 + 32 + 8 + 8 + 32 + 8 + 1 + 8 + 1 + 8 + 8 + 1 + 8 + 8 + 1,
         seeds = [INTENT_SEED, intent_id.as_ref()],
         bump
     )]
@@ -637,22 +1167,71 @@ pub struct CreateIntent<'info> {
         mut,
         associated_token::mint = source_token,
         associated_token::authority = creator,
+        associated_token::token_program = token_program,
     )]
-    pub creator_token_account: Account<'info, TokenAccount>,
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
         payer = creator,
         associated_token::mint = source_token,
         associated_token::authority = intent,
+        associated_token::token_program = token_program,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct TopUpIntent<'info> {
+    #[account(address = intent.creator @ OIFError::Unauthorized)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [INTENT_SEED, intent.intent_id.as_ref()],
+        bump = intent.bump
+    )]
+    pub intent: Account<'info, Intent>,
+
+    #[account(address = intent.source_token)]
+    pub source_token: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = intent.source_token,
+        associated_token::authority = creator,
+        associated_token::token_program = token_program,
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = intent.source_token,
+        associated_token::authority = intent,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendIntent<'info> {
+    #[account(address = intent.creator @ OIFError::Unauthorized)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [INTENT_SEED, intent.intent_id.as_ref()],
+        bump = intent.bump
+    )]
+    pub intent: Account<'info, Intent>,
+}
+
 #[derive(Accounts)]
 pub struct FillIntent<'info> {
     #[account(mut)]
@@ -672,6 +1251,9 @@ pub struct FillIntent<'info> {
     )]
     pub intent: Account<'info, Intent>,
 
+    #[account(address = intent.source_token)]
+    pub source_token: InterfaceAccount<'info, Mint>,
+
     #[account(
         mut,
         seeds = [SOLVER_SEED, solver_owner.key().as_ref()],
@@ -683,28 +1265,119 @@ pub struct FillIntent<'info> {
         mut,
         associated_token::mint = intent.source_token,
         associated_token::authority = intent,
+        associated_token::token_program = token_program,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// SECURITY: Removed init_if_needed to prevent front-running attacks
     /// Solver must create their token account before calling fill_intent
+    /// Required unless the solver defers payouts
     #[account(
         mut,
         associated_token::mint = intent.source_token,
         associated_token::authority = solver_owner,
+        associated_token::token_program = token_program,
     )]
-    pub solver_token_account: Account<'info, TokenAccount>,
+    pub solver_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
     /// Fee recipient token account - must be owned by config authority
     #[account(
         mut,
         token::mint = intent.source_token,
-        token::authority = config.authority
+        token::authority = config.authority,
+        token::token_program = token_program,
     )]
-    pub fee_account: Account<'info, TokenAccount>,
+    pub fee_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
+
+    /// Required when the solver defers payouts
+    #[account(
+        mut,
+        seeds = [EARNINGS_SEED, solver.key().as_ref(), intent.source_token.as_ref()],
+        bump = solver_earnings.bump
+    )]
+    pub solver_earnings: Option<Account<'info, SolverEarnings>>,
+
+    /// Required when the solver defers payouts
+    #[account(
+        mut,
+        associated_token::mint = intent.source_token,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub earnings_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSolverEarnings<'info> {
+    #[account(mut)]
+    pub solver_owner: Signer<'info>,
+
+    #[account(
+        seeds = [SOLVER_SEED, solver_owner.key().as_ref()],
+        bump = solver.bump
+    )]
+    pub solver: Account<'info, Solver>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = solver_owner,
+        space = 8 + 32 + 32 + 8 + 8 + 1,
+        seeds = [EARNINGS_SEED, solver.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub solver_earnings: Account<'info, SolverEarnings>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSolverEarnings<'info> {
+    pub solver_owner: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, OIFConfig>,
+
+    #[account(
+        seeds = [SOLVER_SEED, solver_owner.key().as_ref()],
+        bump = solver.bump
+    )]
+    pub solver: Account<'info, Solver>,
+
+    #[account(
+        mut,
+        seeds = [EARNINGS_SEED, solver.key().as_ref(), mint.key().as_ref()],
+        bump = solver_earnings.bump
+    )]
+    pub solver_earnings: Account<'info, SolverEarnings>,
+
+    #[account(address = solver_earnings.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub earnings_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = solver_owner,
+        associated_token::token_program = token_program,
+    )]
+    pub solver_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -719,21 +1392,26 @@ pub struct CancelIntent<'info> {
     )]
     pub intent: Account<'info, Intent>,
 
+    #[account(address = intent.source_token)]
+    pub source_token: InterfaceAccount<'info, Mint>,
+
     #[account(
         mut,
         associated_token::mint = intent.source_token,
         associated_token::authority = intent,
+        associated_token::token_program = token_program,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = intent.source_token,
         associated_token::authority = creator,
+        associated_token::token_program = token_program,
     )]
-    pub creator_token_account: Account<'info, TokenAccount>,
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -748,12 +1426,16 @@ pub struct ExpireIntent<'info> {
     )]
     pub intent: Account<'info, Intent>,
 
+    #[account(address = intent.source_token)]
+    pub source_token: InterfaceAccount<'info, Mint>,
+
     #[account(
         mut,
         associated_token::mint = intent.source_token,
         associated_token::authority = intent,
+        associated_token::token_program = token_program,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: Intent creator
     #[account(mut, address = intent.creator)]
@@ -765,13 +1447,23 @@ pub struct ExpireIntent<'info> {
         mut,
         associated_token::mint = intent.source_token,
         associated_token::authority = creator,
+        associated_token::token_program = token_program,
     )]
-    pub creator_token_account: Account<'info, TokenAccount>,
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct GetSolverStats<'info> {
+    #[account(
+        seeds = [SOLVER_SEED, solver.owner.as_ref()],
+        bump = solver.bump
+    )]
+    pub solver: Account<'info, Solver>,
+}
+
 #[derive(Accounts)]
 pub struct SlashSolver<'info> {
     #[account(address = config.authority)]
@@ -801,6 +1493,35 @@ pub struct SlashSolver<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct TopUpStake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, OIFConfig>,
+
+    #[account(
+        mut,
+        seeds = [SOLVER_SEED, owner.key().as_ref()],
+        bump = solver.bump
+    )]
+    pub solver: Account<'info, Solver>,
+
+    /// CHECK: Stake vault
+    #[account(
+        mut,
+        seeds = [b"stake-vault"],
+        bump
+    )]
+    pub stake_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct WithdrawStake<'info> {
     #[account(mut)]
@@ -836,6 +1557,7 @@ pub struct SolverRegistered {
     pub supported_chains: Vec<u32>,
 }
 
+/// Self-contained so solvers can screen intents without fetching the account
 #[event]
 pub struct IntentCreated {
     pub intent: Pubkey,
@@ -843,8 +1565,30 @@ pub struct IntentCreated {
     pub intent_id: [u8; 32],
     pub source_chain: u32,
     pub destination_chain: u32,
+    pub source_token: Pubkey,
+    pub destination_token: [u8; 32],
+    /// Net of any Token-2022 transfer fee, as recorded on the intent
+    pub source_amount: u64,
+    pub min_destination_amount: u64,
+    pub recipient: [u8; 32],
+    pub expiry: i64,
+    pub partial_fill_allowed: bool,
+    pub min_fill_amount: u64,
+    pub solver_tip: u64,
+}
+
+#[event]
+pub struct IntentToppedUp {
+    pub intent: Pubkey,
+    pub additional_amount: u64,
     pub source_amount: u64,
     pub min_destination_amount: u64,
+}
+
+#[event]
+pub struct IntentExtended {
+    pub intent: Pubkey,
+    pub previous_expiry: i64,
     pub expiry: i64,
 }
 
@@ -855,6 +1599,24 @@ pub struct IntentFilled {
     pub fill_amount: u64,
     pub destination_tx_hash: [u8; 32],
     pub remaining: u64,
+    /// Tip paid with this fill, zero unless it completed the intent
+    pub tip_paid: u64,
+}
+
+#[event]
+pub struct SolverEarningsCredited {
+    pub solver: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Lifetime credits, claimed or not
+    pub accrued: u64,
+}
+
+#[event]
+pub struct SolverEarningsClaimed {
+    pub solver: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
 }
 
 #[event]
@@ -877,12 +1639,37 @@ pub struct SolverSlashed {
     pub remaining_stake: u64,
 }
 
+#[event]
+pub struct StakeToppedUp {
+    pub solver: Pubkey,
+    pub amount: u64,
+    pub stake: u64,
+}
+
+#[event]
+pub struct SolverReactivated {
+    pub solver: Pubkey,
+    pub stake: u64,
+}
+
 #[event]
 pub struct SolverWithdrawn {
     pub solver: Pubkey,
     pub amount: u64,
 }
 
+#[event]
+pub struct AuthorityTransferStarted {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
 // ============================================================================
 // Errors
 // ============================================================================
@@ -917,5 +1704,223 @@ pub enum OIFError {
     Unauthorized,
     #[msg("Reason too long (max 200 characters)")]
     ReasonTooLong,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Minimum fill exceeds the source amount")]
+    InvalidMinFill,
+    #[msg("Fill is below the intent's minimum and does not complete it")]
+    FillBelowMinimum,
+    #[msg("Transfer fee leaves nothing of the source amount in escrow")]
+    TransferFeeExceedsAmount,
+    #[msg("Payout account for the solver's payout mode is missing")]
+    MissingPayoutAccount,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Intent already has fills")]
+    IntentPartiallyFilled,
+    #[msg("Extension exceeds the maximum intent lifetime")]
+    IntentLifetimeExceeded,
+    #[msg("Stake cannot be topped up during the post-slash cooldown")]
+    SlashCooldownActive,
+    #[msg("Source token does not match the escrowed mint")]
+    SourceTokenMismatch,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFee;
+
+    fn transfer_fee(basis_points: u16, maximum_fee: u64) -> TransferFee {
+        TransferFee {
+            epoch: 0.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: basis_points.into(),
+        }
+    }
+
+    /// What a fee-bearing mint delivers to the escrow for `amount`
+    fn received(fee: &TransferFee, amount: u64) -> u64 {
+        amount - fee.calculate_fee(amount).unwrap()
+    }
+
+    #[test]
+    fn test_fee_bearing_mint_records_net_amount() {
+        // 1% fee on the 1_005_000 escrowed is 10_050
+        let fee = transfer_fee(100, u64::MAX);
+        let source_amount = net_source_amount(received(&fee, 1_005_000), 5_000).unwrap();
+
+        assert_eq!(source_amount, 1_000_000 - 10_050);
+        // Fills and refunds can pay out exactly what the escrow holds
+        assert_eq!(source_amount + 5_000, received(&fee, 1_005_000));
+    }
+
+    #[test]
+    fn test_capped_transfer_fee() {
+        let fee = transfer_fee(500, 1_000);
+        assert_eq!(net_source_amount(received(&fee, 1_000_000), 0).unwrap(), 999_000);
+    }
+
+    #[test]
+    fn test_no_transfer_fee_is_identity() {
+        let fee = transfer_fee(0, 0);
+        assert_eq!(net_source_amount(received(&fee, 1_005_000), 5_000).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn test_fee_consuming_source_amount_is_rejected() {
+        let fee = transfer_fee(1_000, u64::MAX);
+        // 10% of 1_100 is 110, leaving less than the 1_000 tip
+        assert!(net_source_amount(received(&fee, 1_100), 1_000).is_err());
+        assert!(net_source_amount(1_000, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_source_token_must_be_escrowed_mint() {
+        let escrowed = Pubkey::new_unique();
+        assert!(check_source_token(&escrowed, &escrowed).is_ok());
+
+        // An intent advertising one mint while escrowing another
+        let advertised = Pubkey::new_unique();
+        assert_eq!(
+            check_source_token(&advertised, &escrowed).unwrap_err(),
+            OIFError::SourceTokenMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_extension_is_bounded_per_call() {
+        assert_eq!(extended_expiry(0, 1_000, 500).unwrap(), 1_500);
+        assert_eq!(
+            extended_expiry(0, 1_000, MAX_EXPIRY_EXTENSION).unwrap(),
+            1_000 + MAX_EXPIRY_EXTENSION
+        );
+        assert!(extended_expiry(0, 1_000, MAX_EXPIRY_EXTENSION + 1).is_err());
+        assert!(extended_expiry(0, 1_000, 0).is_err());
+        assert!(extended_expiry(0, 1_000, -1).is_err());
+    }
+
+    #[test]
+    fn test_extension_is_capped_at_max_lifetime() {
+        let created_at = 10_000;
+        let expiry = created_at + MAX_INTENT_LIFETIME - 100;
+
+        assert_eq!(
+            extended_expiry(created_at, expiry, 100).unwrap(),
+            created_at + MAX_INTENT_LIFETIME
+        );
+        assert!(extended_expiry(created_at, expiry, 101).is_err());
+    }
+
+    #[test]
+    fn test_solver_earnings_accrue_until_claimed() {
+        let mut earnings = SolverEarnings {
+            solver: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            accrued: 0,
+            claimed: 0,
+            bump: 0,
+        };
+
+        earnings.credit(700).unwrap();
+        earnings.credit(300).unwrap();
+        assert_eq!(earnings.claimable(), 1_000);
+
+        // A claim sweeps everything accrued so far
+        assert_eq!(earnings.claim().unwrap(), 1_000);
+        assert_eq!(earnings.claimable(), 0);
+        assert_eq!(
+            earnings.claim().unwrap_err(),
+            OIFError::NothingToClaim.into()
+        );
+
+        earnings.credit(50).unwrap();
+        assert_eq!(earnings.claimable(), 50);
+        assert_eq!(earnings.claim().unwrap(), 50);
+        assert_eq!((earnings.accrued, earnings.claimed), (1_050, 1_050));
+
+        earnings.accrued = u64::MAX;
+        assert!(earnings.credit(1).is_err());
+    }
+
+    #[test]
+    fn test_solver_stats_mix_fills_and_disputes() {
+        let mut solver = Solver {
+            owner: Pubkey::new_unique(),
+            stake: 0,
+            supported_chains: vec![],
+            intents_filled: 0,
+            total_volume: 0,
+            reputation_score: 1000,
+            active: true,
+            registered_at: 0,
+            bump: 0,
+            defer_payout: false,
+            disputes: 0,
+            total_fill_latency: 0,
+            last_slashed_at: 0,
+        };
+
+        // No history yet
+        let stats = solver.stats();
+        assert_eq!((stats.success_rate_bps, stats.avg_fill_latency), (0, 0));
+
+        for latency in [30, 60, 90] {
+            solver.record_fill(latency).unwrap();
+        }
+        assert_eq!(solver.stats().success_rate_bps, 10_000);
+        assert_eq!(solver.stats().avg_fill_latency, 60);
+
+        // One dispute among three fills
+        solver.disputes += 1;
+        let stats = solver.stats();
+        assert_eq!(stats.intents_filled, 3);
+        assert_eq!(stats.disputes, 1);
+        assert_eq!(stats.success_rate_bps, 7_500);
+        assert_eq!(stats.avg_fill_latency, 60);
+
+        // Disputes with no fills at all
+        solver.intents_filled = 0;
+        solver.total_fill_latency = 0;
+        let stats = solver.stats();
+        assert_eq!((stats.success_rate_bps, stats.avg_fill_latency), (0, 0));
+    }
+
+    #[test]
+    fn test_top_up_reactivates_slashed_solver_after_cooldown() {
+        let slashed_at = 1_700_000_000;
+        let mut solver = Solver {
+            owner: Pubkey::new_unique(),
+            stake: MIN_SOLVER_STAKE / 2,
+            supported_chains: vec![],
+            intents_filled: 0,
+            total_volume: 0,
+            reputation_score: 900,
+            active: false,
+            registered_at: 0,
+            bump: 0,
+            defer_payout: false,
+            disputes: 1,
+            total_fill_latency: 0,
+            last_slashed_at: slashed_at,
+        };
+
+        // Still cooling down from the slash
+        let now = slashed_at + SLASH_COOLDOWN - 1;
+        assert!(solver.top_up(MIN_SOLVER_STAKE, MIN_SOLVER_STAKE, now).is_err());
+        assert_eq!(solver.stake, MIN_SOLVER_STAKE / 2);
+
+        // Not enough to clear the minimum: stays inactive
+        let now = slashed_at + SLASH_COOLDOWN;
+        assert!(!solver.top_up(1, MIN_SOLVER_STAKE, now).unwrap());
+        assert!(!solver.active);
+
+        assert!(solver.top_up(MIN_SOLVER_STAKE / 2, MIN_SOLVER_STAKE, now).unwrap());
+        assert!(solver.active);
+        assert_eq!(solver.stake, MIN_SOLVER_STAKE + 1);
+
+        // Topping up an active solver is not a reactivation
+        assert!(!solver.top_up(1, MIN_SOLVER_STAKE, now).unwrap());
+        assert!(solver.active);
+    }
+}
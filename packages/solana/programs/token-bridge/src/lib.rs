@@ -30,6 +30,59 @@ pub const MAX_PAYLOAD_SIZE: usize = 1024;
 /// Groth16 proof size
 pub const GROTH16_PROOF_SIZE: usize = 256;
 
+/// Most transfers `complete_transfer_batch` accepts. Proofs travel in proof
+/// buffers, but each transfer still adds four account keys and a 104-byte
+/// descriptor, and a fourth one would take the transaction, with its compute
+/// unit limit request, past 1232 bytes.
+pub const MAX_COMPLETE_BATCH_SIZE: usize = 3;
+
+/// Compute units budgeted per transfer in `complete_transfer_batch`, sized for
+/// a `MAX_PROOF_SIZE` proof: the light client's keccak of every node (about
+/// 16k), walking and RLP-decoding the proof at ~10 units a byte (about 100k),
+/// the CPI into it, the completion record's address derivation and creation,
+/// and the mint or vault transfer (about 20k together), rounded up.
+pub const COMPLETE_TRANSFER_COMPUTE_UNITS: u32 = 150_000;
+
+/// Compute units for a batch's own work: deserializing and checking the
+/// bridge, token config and recipient token accounts
+pub const COMPLETE_BATCH_BASE_COMPUTE_UNITS: u32 = 20_000;
+
+/// Compute unit limit a relayer requests for a batch of `count` transfers.
+/// One transfer fits the default 200k budget; a full batch doesn't, so the
+/// transaction has to carry a `SetComputeUnitLimit` for this many.
+pub fn complete_batch_compute_units(count: usize) -> u32 {
+    COMPLETE_BATCH_BASE_COMPUTE_UNITS + COMPLETE_TRANSFER_COMPUTE_UNITS * count as u32
+}
+
+/// Chain ID the EVM bridge uses for Solana as a transfer destination
+pub const SOLANA_CHAIN_ID: u64 = 101;
+
+/// Largest trie node a proof may contain: a branch node, which RLP-encodes as
+/// a 3-byte list header, sixteen 33-byte child hashes and an empty value
+pub const MAX_PROOF_NODE_SIZE: usize = 3 + 16 * 33 + 1;
+
+/// Most nodes a proof may contain. Trie keys are 32-byte hashes, so a path
+/// from the root is at most 64 nibble-consuming nodes plus the leaf, for
+/// both the account and the storage half of the proof.
+pub const MAX_PROOF_NODES: usize = 2 * (64 + 1);
+
+/// Longest serialized proof accepted. Proofs reach the light client as CPI
+/// instruction data, which the runtime caps at 10 KiB, and this leaves room
+/// for the other arguments. Mainnet account proofs are 8-9 nodes, the upper
+/// ones full branch nodes, so about 4.5 KB; the bridge's storage proofs are
+/// shallower. Anything over a transaction's size goes through a `ProofBuffer`.
+pub const MAX_PROOF_SIZE: usize = 10 * 1024 - 128;
+
+/// Storage slot of the EVM bridge's `completedTransfers` mapping, after
+/// ReentrancyGuard's and Pausable's slots and the contract's own state
+/// variables. Must follow the contract if its layout changes.
+pub const EVM_COMPLETED_TRANSFERS_SLOT: u64 = 13;
+
+/// Slots the light client's head must be past the root a transfer is proven
+/// against before it can be completed; two epochs, the usual depth for beacon
+/// finality
+pub const DEFAULT_MIN_CONFIRMATIONS: u64 = 64;
+
 #[program]
 pub mod token_bridge {
     use super::*;
@@ -39,27 +92,35 @@ pub mod token_bridge {
         ctx: Context<Initialize>,
         evm_chain_id: u64,
         evm_bridge_address: [u8; 20],
+        timelock_delay: i64,
     ) -> Result<()> {
+        require!(timelock_delay >= 0, ErrorCode::InvalidTimelockDelay);
         let state = &mut ctx.accounts.state;
 
         state.admin = ctx.accounts.admin.key();
+        state.pending_admin = None;
         state.evm_light_client = ctx.accounts.evm_light_client.key();
         state.evm_bridge_address = evm_bridge_address;
         state.evm_chain_id = evm_chain_id;
         state.transfer_nonce = 0;
         state.total_locked = 0;
         state.paused = false;
+        state.timelock_delay = timelock_delay;
+        state.action_nonce = 0;
+        state.min_confirmations = DEFAULT_MIN_CONFIRMATIONS;
 
         msg!("Token Bridge initialized for EVM chain {}", evm_chain_id);
 
         Ok(())
     }
 
-    /// Register a token for bridging
+    /// Register a token for bridging. The token stays disabled until an
+    /// `EnableToken` action is queued and executed.
     pub fn register_token(
         ctx: Context<RegisterToken>,
         evm_token: [u8; 20],
         is_native_on_solana: bool,
+        evm_decimals: u8,
     ) -> Result<()> {
         let token_config = &mut ctx.accounts.token_config;
 
@@ -67,7 +128,11 @@ pub mod token_bridge {
         token_config.evm_token = evm_token;
         token_config.is_native_on_solana = is_native_on_solana;
         token_config.total_bridged = 0;
-        token_config.enabled = true;
+        token_config.enabled = false;
+        token_config.evm_decimals = evm_decimals;
+        token_config.dust = 0;
+        // Wrapped supply that already exists counts as minted by the bridge
+        token_config.outstanding = if is_native_on_solana { 0 } else { ctx.accounts.mint.supply };
 
         msg!("Token registered: {} <-> 0x{}", 
             ctx.accounts.mint.key(),
@@ -84,95 +149,97 @@ pub mod token_bridge {
         amount: u64,
         payload: Vec<u8>,
     ) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        let token_config = &ctx.accounts.token_config;
-
-        require!(!state.paused, ErrorCode::BridgePaused);
-        require!(token_config.enabled, ErrorCode::TokenNotEnabled);
-        require!(amount > 0, ErrorCode::ZeroAmount);
-        require!(payload.len() <= MAX_PAYLOAD_SIZE, ErrorCode::PayloadTooLarge);
-
-        // Generate transfer ID
-        state.transfer_nonce += 1;
-        let transfer_id = generate_transfer_id(
-            &ctx.accounts.sender.key(),
-            &evm_recipient,
+        let accounts = InitiateAccounts {
+            state: &mut ctx.accounts.state,
+            token_config: &mut ctx.accounts.token_config,
+            mint: &ctx.accounts.mint,
+            bridge_vault: ctx.accounts.bridge_vault.to_account_info(),
+            sender_token_account: ctx.accounts.sender_token_account.to_account_info(),
+            sender: ctx.accounts.sender.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        lock_transfer(
+            accounts,
+            &mut ctx.accounts.transfer_record,
+            evm_recipient,
             amount,
-            state.transfer_nonce,
-        );
+            payload,
+        )?;
 
-        // Lock or burn tokens
-        if token_config.is_native_on_solana {
-            // Lock tokens in bridge vault
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.bridge_vault.to_account_info(),
-                authority: ctx.accounts.sender.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-            );
-            token::transfer(cpi_ctx, amount)?;
+        Ok(())
+    }
 
-            state.total_locked += amount;
-        } else {
-            // Burn wrapped tokens
-            let cpi_accounts = Burn {
-                mint: ctx.accounts.mint.to_account_info(),
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                authority: ctx.accounts.sender.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-            );
-            token::burn(cpi_ctx, amount)?;
+    /// Initiate a transfer from Solana to EVM that is safe to retry
+    ///
+    /// The transfer record is keyed by the sender and a client-chosen
+    /// `idempotency_key`, so resubmitting a transaction that may already have
+    /// landed can't lock or burn twice. A retry with the same key and the same
+    /// transfer details moves nothing and returns the original transfer ID; the
+    /// same key with different details fails.
+    ///
+    /// # Return Data
+    /// The transfer ID, whether this call created it or found it
+    pub fn initiate_transfer_with_key(
+        ctx: Context<InitiateTransferWithKey>,
+        idempotency_key: [u8; 32],
+        evm_recipient: [u8; 20],
+        amount: u64,
+        payload: Vec<u8>,
+    ) -> Result<[u8; 32]> {
+        let (spl_amount, _) = bridged_amounts(
+            amount,
+            ctx.accounts.mint.decimals,
+            ctx.accounts.token_config.evm_decimals,
+        )?;
+        if let Some(transfer_id) = replayed_transfer(
+            &ctx.accounts.transfer_record,
+            &ctx.accounts.mint.key(),
+            &evm_recipient,
+            spl_amount,
+            &payload,
+        )? {
+            msg!("Transfer already initiated for key 0x{}", hex::encode(&idempotency_key));
+            return Ok(transfer_id);
         }
 
-        // Create transfer record
-        let transfer_record = &mut ctx.accounts.transfer_record;
-        transfer_record.transfer_id = transfer_id;
-        transfer_record.sender = ctx.accounts.sender.key();
-        transfer_record.evm_recipient = evm_recipient;
-        transfer_record.mint = ctx.accounts.mint.key();
-        transfer_record.amount = amount;
-        transfer_record.nonce = state.transfer_nonce;
-        transfer_record.timestamp = Clock::get()?.unix_timestamp;
-        transfer_record.status = TransferStatus::Pending;
-        transfer_record.payload = payload.clone();
-
-        // Emit event for relayers
-        emit!(TransferInitiated {
-            transfer_id,
-            sender: ctx.accounts.sender.key(),
+        let accounts = InitiateAccounts {
+            state: &mut ctx.accounts.state,
+            token_config: &mut ctx.accounts.token_config,
+            mint: &ctx.accounts.mint,
+            bridge_vault: ctx.accounts.bridge_vault.to_account_info(),
+            sender_token_account: ctx.accounts.sender_token_account.to_account_info(),
+            sender: ctx.accounts.sender.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        lock_transfer(
+            accounts,
+            &mut ctx.accounts.transfer_record,
             evm_recipient,
-            mint: ctx.accounts.mint.key(),
             amount,
-            nonce: state.transfer_nonce,
             payload,
-        });
-
-        msg!("Transfer initiated: {} tokens to 0x{}", 
-            amount,
-            hex::encode(&evm_recipient)
-        );
-
-        Ok(())
+        )
     }
 
     /// Complete a transfer from EVM to Solana
     /// 
     /// The relayer provides a Merkle-Patricia proof showing the transfer exists
     /// in the EVM bridge contract's storage. The proof is verified against the
-    /// state root maintained by the EVM light client.
+    /// state root the EVM light client finalized at `proof_slot`, which must be
+    /// at least `min_confirmations` slots below its head. A proof too large for
+    /// the transaction is staged in a `ProofBuffer` and `proof_data` left empty.
+    ///
+    /// `min_amount_out` is passed through from the user's intent; the transfer
+    /// fails if the recipient would receive fewer SPL tokens. Zero disables it.
     pub fn complete_transfer(
         ctx: Context<CompleteTransfer>,
         transfer_id: [u8; 32],
+        source_chain_id: u64,
         evm_sender: [u8; 20],
-        amount: u64,
-        evm_block_number: u64,
+        amount: u128,
+        proof_slot: u64,
+        evm_nonce: u64,
         proof_data: Vec<u8>, // Serialized Merkle-Patricia proof
+        min_amount_out: u64,
     ) -> Result<()> {
         let state = &ctx.accounts.state;
         let token_config = &ctx.accounts.token_config;
@@ -184,59 +251,53 @@ pub mod token_bridge {
         let completion_record = &ctx.accounts.completion_record;
         require!(!completion_record.completed, ErrorCode::TransferAlreadyCompleted);
 
-        // Verify the Merkle proof via EVM light client CPI
-        // This proves the transfer was included in the verified EVM state
-        verify_evm_transfer(
-            &ctx.accounts.evm_light_client_program,
-            &ctx.accounts.light_client_state,
-            &state.evm_bridge_address,
-            &transfer_id,
-            &evm_sender,
-            &ctx.accounts.recipient.key().to_bytes(),
+        let transfer = TransferDescriptor {
+            transfer_id,
+            source_chain_id,
+            evm_sender,
             amount,
-            evm_block_number,
-            &proof_data,
+            proof_slot,
+            evm_nonce,
+            proof_data,
+            min_amount_out,
+        };
+        let buffered = ctx.accounts.proof_buffer.as_ref().map(|buffer| buffered_proof(buffer)).transpose()?;
+        let proof_data = select_proof(&transfer.proof_data, buffered.as_deref())?;
+        let release = ReleaseAccounts {
+            state,
+            state_bump: ctx.bumps.state,
+            token_config,
+            mint: &ctx.accounts.mint,
+            bridge_vault: ctx.accounts.bridge_vault.to_account_info(),
+            evm_light_client_program: ctx.accounts.evm_light_client_program.clone(),
+            light_client_state: ctx.accounts.light_client_state.clone(),
+            proof_cache: ctx.accounts.proof_cache.as_ref().map(|cache| cache.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let (spl_amount, dust) = release_transfer(
+            &release,
+            &transfer,
+            proof_data,
+            &ctx.accounts.recipient.key(),
+            &ctx.accounts.recipient_token_account.to_account_info(),
         )?;
+        drop(buffered);
 
-        // Mint or unlock tokens
-        if token_config.is_native_on_solana {
-            // Unlock from bridge vault
-            let seeds = &[
-                b"bridge_state".as_ref(),
-                &[ctx.bumps.state],
-            ];
-            let signer = &[&seeds[..]];
-
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.bridge_vault.to_account_info(),
-                to: ctx.accounts.recipient_token_account.to_account_info(),
-                authority: ctx.accounts.state.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-                signer,
-            );
-            token::transfer(cpi_ctx, amount)?;
-        } else {
-            // Mint wrapped tokens
-            let seeds = &[
-                b"bridge_state".as_ref(),
-                &[ctx.bumps.state],
-            ];
-            let signer = &[&seeds[..]];
-
-            let cpi_accounts = MintTo {
-                mint: ctx.accounts.mint.to_account_info(),
-                to: ctx.accounts.recipient_token_account.to_account_info(),
-                authority: ctx.accounts.state.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-                signer,
-            );
-            token::mint_to(cpi_ctx, amount)?;
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.outstanding = outstanding_after_release(
+            token_config.is_native_on_solana,
+            token_config.outstanding,
+            spl_amount,
+        )?;
+        verify_reserves(
+            token_config,
+            &ctx.accounts.bridge_vault.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+        )?;
+
+        // Track the EVM-side remainder that couldn't be represented on Solana
+        if dust > 0 {
+            token_config.dust = token_config.dust.checked_add(dust).ok_or(ErrorCode::MathOverflow)?;
         }
 
         // Mark as completed
@@ -244,24 +305,230 @@ pub mod token_bridge {
         completion_record.transfer_id = transfer_id;
         completion_record.completed = true;
         completion_record.completed_at = Clock::get()?.unix_timestamp;
+        completion_record.source_chain_id = source_chain_id;
 
         emit!(TransferCompleted {
             transfer_id,
+            source_chain_id,
             evm_sender,
             recipient: ctx.accounts.recipient.key(),
             mint: ctx.accounts.mint.key(),
-            amount,
-            evm_block_number,
+            requested_amount: amount,
+            min_amount_out,
+            amount: spl_amount,
+            proof_slot,
         });
 
         msg!("Transfer completed: {} tokens from 0x{}", 
-            amount,
+            spl_amount,
             hex::encode(&evm_sender)
         );
 
         Ok(())
     }
 
+    /// Complete several EVM → Solana transfers of the same token in one transaction
+    ///
+    /// Each transfer takes four remaining accounts, in order: its completion
+    /// record PDA (writable, not yet created), the recipient, the recipient's
+    /// token account (writable), and the `ProofBuffer` holding its proof, or
+    /// this program when the proof is inline. Any invalid transfer fails the
+    /// whole batch.
+    pub fn complete_transfer_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, CompleteTransferBatch<'info>>,
+        transfers: Vec<TransferDescriptor>,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let token_config = &ctx.accounts.token_config;
+
+        require!(!state.paused, ErrorCode::BridgePaused);
+        require!(token_config.enabled, ErrorCode::TokenNotEnabled);
+        require!(
+            !transfers.is_empty() && transfers.len() <= MAX_COMPLETE_BATCH_SIZE,
+            ErrorCode::InvalidBatchSize
+        );
+        let completion_bumps = check_batch_accounts(
+            ctx.program_id,
+            &ctx.accounts.mint.key(),
+            &transfers,
+            ctx.remaining_accounts,
+        )?;
+
+        let release = ReleaseAccounts {
+            state,
+            state_bump: ctx.bumps.state,
+            token_config,
+            mint: &ctx.accounts.mint,
+            bridge_vault: ctx.accounts.bridge_vault.to_account_info(),
+            evm_light_client_program: ctx.accounts.evm_light_client_program.clone(),
+            light_client_state: ctx.accounts.light_client_state.clone(),
+            proof_cache: ctx.accounts.proof_cache.as_ref().map(|cache| cache.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut total_dust: u128 = 0;
+        let mut total_released: u64 = 0;
+
+        let entries = transfers.iter().zip(ctx.remaining_accounts.chunks(4)).zip(completion_bumps);
+        for ((transfer, accounts), completion_bump) in entries {
+            let [completion_info, recipient, recipient_token_account, proof_buffer] = accounts else {
+                return Err(ErrorCode::InvalidBatchAccounts.into());
+            };
+
+            let chain_bytes = transfer.source_chain_id.to_le_bytes();
+            create_pda_account(
+                &ctx.accounts.relayer.to_account_info(),
+                completion_info,
+                &ctx.accounts.system_program.to_account_info(),
+                8 + CompletionRecord::INIT_SPACE,
+                ctx.program_id,
+                &[b"completion", &chain_bytes, transfer.transfer_id.as_ref(), &[completion_bump]],
+            )?;
+            CompletionRecord {
+                transfer_id: transfer.transfer_id,
+                completed: true,
+                completed_at: now,
+                source_chain_id: transfer.source_chain_id,
+            }
+            .try_serialize(&mut &mut completion_info.try_borrow_mut_data()?[..])?;
+
+            let buffered =
+                (proof_buffer.key != ctx.program_id).then(|| buffered_proof(proof_buffer)).transpose()?;
+            let proof_data = select_proof(&transfer.proof_data, buffered.as_deref())?;
+            let (spl_amount, dust) =
+                release_transfer(&release, transfer, proof_data, recipient.key, recipient_token_account)?;
+            total_dust = total_dust.checked_add(dust).ok_or(ErrorCode::MathOverflow)?;
+            total_released = total_released.checked_add(spl_amount).ok_or(ErrorCode::MathOverflow)?;
+
+            emit!(TransferCompleted {
+                transfer_id: transfer.transfer_id,
+                source_chain_id: transfer.source_chain_id,
+                evm_sender: transfer.evm_sender,
+                recipient: recipient.key(),
+                mint: ctx.accounts.mint.key(),
+                requested_amount: transfer.amount,
+                min_amount_out: transfer.min_amount_out,
+                amount: spl_amount,
+                proof_slot: transfer.proof_slot,
+            });
+        }
+
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.outstanding = outstanding_after_release(
+            token_config.is_native_on_solana,
+            token_config.outstanding,
+            total_released,
+        )?;
+        verify_reserves(
+            token_config,
+            &ctx.accounts.bridge_vault.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+        )?;
+
+        if total_dust > 0 {
+            token_config.dust = token_config.dust.checked_add(total_dust).ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        msg!("Batch completed: {} transfers", transfers.len());
+
+        Ok(())
+    }
+
+    /// Create a buffer for a proof too large to send in one transaction
+    ///
+    /// The relayer fills it with `write_proof_buffer`, passes it to a
+    /// completion with an empty `proof_data`, then closes it.
+    pub fn init_proof_buffer(ctx: Context<InitProofBuffer>, _buffer_id: [u8; 32], len: u32) -> Result<()> {
+        require!(len > 0 && len as usize <= MAX_PROOF_SIZE, ErrorCode::MalformedProof);
+        let proof_buffer = &mut ctx.accounts.proof_buffer;
+        proof_buffer.relayer = ctx.accounts.relayer.key();
+        proof_buffer.data = vec![0; len as usize];
+        Ok(())
+    }
+
+    /// Write `bytes` into a proof buffer at `offset`
+    pub fn write_proof_buffer(
+        ctx: Context<WriteProofBuffer>,
+        _buffer_id: [u8; 32],
+        offset: u32,
+        bytes: Vec<u8>,
+    ) -> Result<()> {
+        let data = &mut ctx.accounts.proof_buffer.data;
+        let start = offset as usize;
+        let end = start.checked_add(bytes.len()).ok_or(ErrorCode::MathOverflow)?;
+        require!(end <= data.len(), ErrorCode::InvalidProofBuffer);
+        data[start..end].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Close a proof buffer and return its rent to the relayer
+    pub fn close_proof_buffer(_ctx: Context<CloseProofBuffer>, _buffer_id: [u8; 32]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Look up a Solana → EVM transfer by nonce (read-only). Keyed transfers
+    /// live at their `transfer_key` PDA and are read from that account.
+    ///
+    /// # Return Data
+    /// The Borsh-encoded `TransferRecord`, readable via `get_return_data()` or
+    /// transaction simulation.
+    pub fn get_transfer(ctx: Context<GetTransfer>, _nonce: u64) -> Result<TransferRecord> {
+        Ok((*ctx.accounts.transfer_record).clone())
+    }
+
+    /// Mark a Solana → EVM transfer `Completed` once the EVM bridge has
+    /// released it (anyone can call). `proof_data` is a storage proof of
+    /// `completedTransfers[transfer_id]` against the light client's state root,
+    /// or empty when the proof is in the `proof_buffer` account.
+    pub fn confirm_transfer_completed(
+        ctx: Context<ConfirmTransferCompleted>,
+        proof_data: Vec<u8>,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let transfer_record = &mut ctx.accounts.transfer_record;
+        require!(transfer_record.status == TransferStatus::Pending, ErrorCode::TransferNotPending);
+
+        let buffered = ctx.accounts.proof_buffer.as_ref().map(|buffer| buffered_proof(buffer)).transpose()?;
+        let proof_data = select_proof(&proof_data, buffered.as_deref())?;
+        validate_proof_data(proof_data)?;
+        let head_slot = light_client_view(&ctx.accounts.light_client_state)?.latest_slot;
+        verify_evm_storage(
+            &ctx.accounts.evm_light_client_program,
+            &ctx.accounts.light_client_state,
+            ctx.accounts.proof_cache.as_ref().map(|cache| &**cache),
+            state.evm_chain_id,
+            &state.evm_bridge_address,
+            &compute_evm_storage_slot(&transfer_record.transfer_id, EVM_COMPLETED_TRANSFERS_SLOT),
+            &u64_to_word(1),
+            head_slot,
+            proof_data,
+        )?;
+
+        transfer_record.status = TransferStatus::Completed;
+        msg!("Transfer {} completed on EVM", transfer_record.nonce);
+
+        Ok(())
+    }
+
+    /// Close a finished transfer's record and return its rent to the sender
+    ///
+    /// Only `Completed` records can be closed. Closing a keyed record frees its
+    /// idempotency key, so a later call with the same key starts a new transfer.
+    pub fn close_transfer_record(ctx: Context<CloseTransferRecord>) -> Result<()> {
+        let transfer_record = &ctx.accounts.transfer_record;
+        require!(is_terminal(transfer_record.status), ErrorCode::TransferNotTerminal);
+
+        emit!(TransferRecordClosed {
+            transfer_id: transfer_record.transfer_id,
+            sender: transfer_record.sender,
+            nonce: transfer_record.nonce,
+            status: transfer_record.status,
+        });
+
+        Ok(())
+    }
+
     /// Pause the bridge (admin only)
     pub fn pause(ctx: Context<AdminAction>) -> Result<()> {
         let state = &mut ctx.accounts.state;
@@ -271,12 +538,108 @@ pub mod token_bridge {
         Ok(())
     }
 
-    /// Unpause the bridge (admin only)
-    pub fn unpause(ctx: Context<AdminAction>) -> Result<()> {
+    /// Queue a timelocked admin action (admin only). Unpausing, changing the
+    /// EVM bridge address and enabling tokens all go through here; pausing
+    /// stays instant for emergencies.
+    pub fn queue_action(ctx: Context<QueueAction>, action: BridgeAction) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = Clock::get()?.unix_timestamp;
+        let executable_at = now
+            .checked_add(state.timelock_delay)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let pending_action = &mut ctx.accounts.pending_action;
+        pending_action.id = state.action_nonce;
+        pending_action.action = action.clone();
+        pending_action.queued_at = now;
+        pending_action.executable_at = executable_at;
+
+        state.action_nonce += 1;
+
+        emit!(ActionQueued {
+            id: pending_action.id,
+            action,
+            executable_at,
+        });
+
+        Ok(())
+    }
+
+    /// Apply a queued action once its delay has passed (admin only)
+    pub fn execute_action(ctx: Context<ExecuteAction>) -> Result<()> {
+        let pending_action = &ctx.accounts.pending_action;
+        require!(
+            Clock::get()?.unix_timestamp >= pending_action.executable_at,
+            ErrorCode::TimelockNotExpired
+        );
+
+        let id = pending_action.id;
+        let action = pending_action.action.clone();
+        let state = &mut ctx.accounts.state;
+
+        match action {
+            BridgeAction::Unpause => {
+                state.paused = false;
+                msg!("Bridge unpaused");
+            }
+            BridgeAction::SetEvmBridgeAddress { evm_bridge_address } => {
+                state.evm_bridge_address = evm_bridge_address;
+                msg!("EVM bridge address set to 0x{}", hex::encode(&evm_bridge_address));
+            }
+            BridgeAction::SetTimelockDelay { delay } => {
+                require!(delay >= 0, ErrorCode::InvalidTimelockDelay);
+                state.timelock_delay = delay;
+            }
+            BridgeAction::SetMinConfirmations { confirmations } => {
+                state.min_confirmations = confirmations;
+                msg!("Minimum confirmations set to {}", confirmations);
+            }
+            BridgeAction::EnableToken { mint } => {
+                let token_config = ctx
+                    .accounts
+                    .token_config
+                    .as_mut()
+                    .ok_or(ErrorCode::MissingTokenConfig)?;
+                require!(token_config.mint == mint, ErrorCode::MissingTokenConfig);
+                token_config.enabled = true;
+                msg!("Token enabled: {}", mint);
+            }
+        }
+
+        emit!(ActionExecuted { id, action });
+
+        Ok(())
+    }
+
+    /// Propose a new admin (admin only). Takes effect once accepted.
+    pub fn transfer_authority(ctx: Context<AdminAction>, new_admin: Pubkey) -> Result<()> {
         let state = &mut ctx.accounts.state;
         require!(ctx.accounts.admin.key() == state.admin, ErrorCode::Unauthorized);
-        state.paused = false;
-        msg!("Bridge unpaused");
+        state.pending_admin = Some(new_admin);
+
+        emit!(AuthorityTransferStarted {
+            admin: state.admin,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Accept a pending admin transfer (pending admin only)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let new_admin = ctx.accounts.new_admin.key();
+        require!(state.pending_admin == Some(new_admin), ErrorCode::NotPendingAdmin);
+
+        let previous_admin = state.admin;
+        state.admin = new_admin;
+        state.pending_admin = None;
+
+        emit!(AuthorityTransferred {
+            previous_admin,
+            new_admin,
+        });
+
         Ok(())
     }
 }
@@ -340,6 +703,7 @@ pub struct InitiateTransfer<'info> {
     pub state: Account<'info, BridgeState>,
 
     #[account(
+        mut,
         seeds = [b"token_config", mint.key().as_ref()],
         bump
     )]
@@ -349,7 +713,62 @@ pub struct InitiateTransfer<'info> {
         init,
         payer = sender,
         space = 8 + TransferRecord::INIT_SPACE,
-        seeds = [b"transfer", &state.transfer_nonce.to_le_bytes()],
+        // Keyed by the nonce the record will hold, which `lock_transfer` assigns
+        seeds = [b"transfer", &(state.transfer_nonce + 1).to_le_bytes()],
+        bump
+    )]
+    pub transfer_record: Account<'info, TransferRecord>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub bridge_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = sender
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(idempotency_key: [u8; 32])]
+pub struct InitiateTransferWithKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"token_config", mint.key().as_ref()],
+        bump
+    )]
+    pub token_config: Account<'info, TokenConfig>,
+
+    /// `init_if_needed` is what makes a retry a no-op. It can't be front-run:
+    /// the seeds include the signing sender, so nobody else can create or
+    /// pre-fill this record, and an existing one is checked against the
+    /// request before anything moves.
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + TransferRecord::INIT_SPACE,
+        seeds = [b"transfer_key", sender.key().as_ref(), idempotency_key.as_ref()],
         bump
     )]
     pub transfer_record: Account<'info, TransferRecord>,
@@ -379,7 +798,7 @@ pub struct InitiateTransfer<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(transfer_id: [u8; 32])]
+#[instruction(transfer_id: [u8; 32], source_chain_id: u64)]
 pub struct CompleteTransfer<'info> {
     #[account(
         seeds = [b"bridge_state"],
@@ -388,6 +807,7 @@ pub struct CompleteTransfer<'info> {
     pub state: Account<'info, BridgeState>,
 
     #[account(
+        mut,
         seeds = [b"token_config", mint.key().as_ref()],
         bump
     )]
@@ -397,7 +817,7 @@ pub struct CompleteTransfer<'info> {
         init,
         payer = relayer,
         space = 8 + CompletionRecord::INIT_SPACE,
-        seeds = [b"completion", transfer_id.as_ref()],
+        seeds = [b"completion", &source_chain_id.to_le_bytes(), transfer_id.as_ref()],
         bump
     )]
     pub completion_record: Account<'info, CompletionRecord>,
@@ -436,44 +856,255 @@ pub struct CompleteTransfer<'info> {
 
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
+
+    /// CHECK: The light client's proof cache for the source chain; optional,
+    /// validated by the light client
+    #[account(mut)]
+    pub proof_cache: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The `ProofBuffer` holding the proof when `proof_data` is empty;
+    /// checked by `buffered_proof`
+    pub proof_buffer: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
-pub struct AdminAction<'info> {
-    #[account(mut, seeds = [b"bridge_state"], bump)]
+pub struct CompleteTransferBatch<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
     pub state: Account<'info, BridgeState>,
 
-    pub admin: Signer<'info>,
-}
+    #[account(
+        mut,
+        seeds = [b"token_config", mint.key().as_ref()],
+        bump
+    )]
+    pub token_config: Account<'info, TokenConfig>,
 
-// =============================================================================
-// STATE
-// =============================================================================
+    /// CHECK: EVM light client program for CPI
+    pub evm_light_client_program: AccountInfo<'info>,
 
-#[account]
-#[derive(InitSpace)]
-pub struct BridgeState {
-    pub admin: Pubkey,
-    pub evm_light_client: Pubkey,
-    pub evm_bridge_address: [u8; 20], // Address of bridge contract on EVM
-    pub evm_chain_id: u64,
-    pub transfer_nonce: u64,
-    pub total_locked: u64,
-    pub paused: bool,
+    /// CHECK: EVM light client state account for verification
+    #[account(
+        constraint = light_client_state.owner == &state.evm_light_client @ ErrorCode::InvalidLightClient
+    )]
+    pub light_client_state: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub bridge_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The light client's proof cache for the source chain; optional,
+    /// validated by the light client
+    #[account(mut)]
+    pub proof_cache: Option<UncheckedAccount<'info>>,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct TokenConfig {
-    pub mint: Pubkey,
-    pub evm_token: [u8; 20],
-    pub is_native_on_solana: bool,
-    pub total_bridged: u64,
-    pub enabled: bool,
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct GetTransfer<'info> {
+    #[account(seeds = [b"transfer", &nonce.to_le_bytes()], bump)]
+    pub transfer_record: Account<'info, TransferRecord>,
 }
 
-#[account]
-#[derive(InitSpace)]
+#[derive(Accounts)]
+pub struct ConfirmTransferCompleted<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub state: Account<'info, BridgeState>,
+
+    #[account(mut)]
+    pub transfer_record: Account<'info, TransferRecord>,
+
+    /// CHECK: EVM light client program for CPI
+    pub evm_light_client_program: AccountInfo<'info>,
+
+    /// CHECK: EVM light client state account for verification
+    #[account(
+        constraint = light_client_state.owner == &state.evm_light_client @ ErrorCode::InvalidLightClient
+    )]
+    pub light_client_state: AccountInfo<'info>,
+
+    /// CHECK: The light client's proof cache for the source chain; optional,
+    /// validated by the light client
+    #[account(mut)]
+    pub proof_cache: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The `ProofBuffer` holding the proof when `proof_data` is empty;
+    /// checked by `buffered_proof`
+    pub proof_buffer: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(buffer_id: [u8; 32], len: u32)]
+pub struct InitProofBuffer<'info> {
+    #[account(
+        init,
+        payer = relayer,
+        space = ProofBuffer::HEADER_SIZE + len as usize,
+        seeds = [b"proof_buffer", relayer.key().as_ref(), buffer_id.as_ref()],
+        bump
+    )]
+    pub proof_buffer: Account<'info, ProofBuffer>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(buffer_id: [u8; 32])]
+pub struct WriteProofBuffer<'info> {
+    #[account(
+        mut,
+        seeds = [b"proof_buffer", relayer.key().as_ref(), buffer_id.as_ref()],
+        bump
+    )]
+    pub proof_buffer: Account<'info, ProofBuffer>,
+
+    pub relayer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(buffer_id: [u8; 32])]
+pub struct CloseProofBuffer<'info> {
+    #[account(
+        mut,
+        close = relayer,
+        seeds = [b"proof_buffer", relayer.key().as_ref(), buffer_id.as_ref()],
+        bump
+    )]
+    pub proof_buffer: Account<'info, ProofBuffer>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTransferRecord<'info> {
+    #[account(mut, close = sender, has_one = sender @ ErrorCode::Unauthorized)]
+    pub transfer_record: Account<'info, TransferRecord>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdminAction<'info> {
+    #[account(mut, seeds = [b"bridge_state"], bump)]
+    pub state: Account<'info, BridgeState>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueAction<'info> {
+    #[account(mut, seeds = [b"bridge_state"], bump)]
+    pub state: Account<'info, BridgeState>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PendingAction::INIT_SPACE,
+        seeds = [b"pending_action", &state.action_nonce.to_le_bytes()],
+        bump
+    )]
+    pub pending_action: Account<'info, PendingAction>,
+
+    #[account(mut, constraint = admin.key() == state.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(mut, seeds = [b"bridge_state"], bump)]
+    pub state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"pending_action", &pending_action.id.to_le_bytes()],
+        bump
+    )]
+    pub pending_action: Account<'info, PendingAction>,
+
+    /// Required for `EnableToken`
+    #[account(mut)]
+    pub token_config: Option<Account<'info, TokenConfig>>,
+
+    #[account(mut, constraint = admin.key() == state.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(mut, seeds = [b"bridge_state"], bump)]
+    pub state: Account<'info, BridgeState>,
+
+    pub new_admin: Signer<'info>,
+}
+
+// =============================================================================
+// STATE
+// =============================================================================
+
+#[account]
+#[derive(InitSpace)]
+pub struct BridgeState {
+    pub admin: Pubkey,
+    pub evm_light_client: Pubkey,
+    pub evm_bridge_address: [u8; 20], // Address of bridge contract on EVM
+    pub evm_chain_id: u64,
+    pub transfer_nonce: u64,
+    pub total_locked: u64,
+    pub paused: bool,
+    /// Seconds a queued admin action must wait before it can execute
+    pub timelock_delay: i64,
+    pub action_nonce: u64,
+    /// Slots the light client's head must be past the root an EVM → Solana
+    /// transfer is proven against before `complete_transfer` accepts it
+    pub min_confirmations: u64,
+    /// Proposed admin awaiting accept_authority
+    pub pending_admin: Option<Pubkey>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct TokenConfig {
+    pub mint: Pubkey,
+    pub evm_token: [u8; 20],
+    pub is_native_on_solana: bool,
+    pub total_bridged: u64,
+    pub enabled: bool,
+    /// Decimals of the token on the EVM chain
+    pub evm_decimals: u8,
+    /// EVM base units received but too small to represent in the mint's decimals
+    pub dust: u128,
+    /// Native tokens: what the bridge vault must hold for the EVM side.
+    /// Wrapped tokens: what the bridge has minted and not seen burned back.
+    pub outstanding: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
 pub struct TransferRecord {
     pub transfer_id: [u8; 32],
     pub sender: Pubkey,
@@ -493,13 +1124,60 @@ pub struct CompletionRecord {
     pub transfer_id: [u8; 32],
     pub completed: bool,
     pub completed_at: i64,
+    pub source_chain_id: u64,
+}
+
+/// A proof staged over several transactions, for proofs that don't fit in one
+#[account]
+pub struct ProofBuffer {
+    pub relayer: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl ProofBuffer {
+    /// Discriminator, relayer and the proof's length prefix
+    pub const HEADER_SIZE: usize = 8 + 32 + 4;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PendingAction {
+    pub id: u64,
+    pub action: BridgeAction,
+    pub queued_at: i64,
+    pub executable_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum BridgeAction {
+    Unpause,
+    SetEvmBridgeAddress { evm_bridge_address: [u8; 20] },
+    SetTimelockDelay { delay: i64 },
+    EnableToken { mint: Pubkey },
+    SetMinConfirmations { confirmations: u64 },
+}
+
+/// An EVM → Solana transfer as submitted by a relayer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransferDescriptor {
+    pub transfer_id: [u8; 32],
+    pub source_chain_id: u64,
+    pub evm_sender: [u8; 20],
+    /// Amount in EVM base units
+    pub amount: u128,
+    /// Beacon slot of the light client root the proof is against
+    pub proof_slot: u64,
+    pub evm_nonce: u64,
+    /// Serialized Merkle-Patricia proof
+    pub proof_data: Vec<u8>,
+    /// Least SPL amount the recipient accepts; zero disables the check
+    pub min_amount_out: u64,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
 pub enum TransferStatus {
     Pending,
     Completed,
-    Failed,
 }
 
 // =============================================================================
@@ -513,6 +1191,8 @@ pub struct TransferInitiated {
     pub evm_recipient: [u8; 20],
     pub mint: Pubkey,
     pub amount: u64,
+    /// `amount` in EVM base units, to be released on the EVM side
+    pub evm_amount: u128,
     pub nonce: u64,
     pub payload: Vec<u8>,
 }
@@ -520,11 +1200,50 @@ pub struct TransferInitiated {
 #[event]
 pub struct TransferCompleted {
     pub transfer_id: [u8; 32],
+    pub source_chain_id: u64,
     pub evm_sender: [u8; 20],
     pub recipient: Pubkey,
     pub mint: Pubkey,
+    /// Amount sent on the EVM side, in EVM base units
+    pub requested_amount: u128,
+    pub min_amount_out: u64,
+    /// SPL amount delivered to the recipient
     pub amount: u64,
-    pub evm_block_number: u64,
+    /// Beacon slot of the light client root the transfer was proven against
+    pub proof_slot: u64,
+}
+
+#[event]
+pub struct TransferRecordClosed {
+    pub transfer_id: [u8; 32],
+    pub sender: Pubkey,
+    pub nonce: u64,
+    pub status: TransferStatus,
+}
+
+#[event]
+pub struct ActionQueued {
+    pub id: u64,
+    pub action: BridgeAction,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct ActionExecuted {
+    pub id: u64,
+    pub action: BridgeAction,
+}
+
+#[event]
+pub struct AuthorityTransferStarted {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
 }
 
 // =============================================================================
@@ -565,6 +1284,60 @@ pub enum ErrorCode {
 
     #[msg("Math overflow")]
     MathOverflow,
+
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+
+    #[msg("Amount rounds to zero after decimal conversion")]
+    AmountTooSmall,
+
+    #[msg("Timelock delay has not passed")]
+    TimelockNotExpired,
+
+    #[msg("Timelock delay cannot be negative")]
+    InvalidTimelockDelay,
+
+    #[msg("Token config missing or does not match the action")]
+    MissingTokenConfig,
+
+    #[msg("Transfers from this source chain are not supported")]
+    UnsupportedSourceChain,
+
+    #[msg("Transfer ID does not match the transfer details")]
+    TransferIdMismatch,
+
+    #[msg("Batch must contain between 1 and MAX_COMPLETE_BATCH_SIZE transfers")]
+    InvalidBatchSize,
+
+    #[msg("Batch accounts missing or do not match the transfers")]
+    InvalidBatchAccounts,
+
+    #[msg("Proof is empty, too large or not a valid list of proof nodes")]
+    MalformedProof,
+
+    #[msg("Delivered amount is below the transfer's minimum")]
+    SlippageExceeded,
+
+    #[msg("Idempotency key was already used for a different transfer")]
+    IdempotencyKeyReused,
+
+    #[msg("Bridge reserves no longer match the tokens it has bridged")]
+    ReserveInvariantViolated,
+
+    #[msg("Transfer is no longer pending")]
+    TransferNotPending,
+
+    #[msg("Transfer is still pending")]
+    TransferNotTerminal,
+
+    #[msg("Proof's state root is not yet deep enough below the light client head")]
+    InsufficientConfirmations,
+
+    #[msg("Light client holds no state root for the proof slot")]
+    UnknownProofSlot,
+
+    #[msg("Proof buffer is not a proof buffer or the write is out of bounds")]
+    InvalidProofBuffer,
 }
 
 // =============================================================================
@@ -588,26 +1361,585 @@ fn generate_transfer_id(
     keccak::hash(&data).to_bytes()
 }
 
+/// Accounts every Solana → EVM transfer needs, keyed or not
+struct InitiateAccounts<'a, 'info> {
+    state: &'a mut Account<'info, BridgeState>,
+    token_config: &'a mut TokenConfig,
+    mint: &'a Account<'info, Mint>,
+    bridge_vault: AccountInfo<'info>,
+    sender_token_account: AccountInfo<'info>,
+    sender: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+}
+
+/// Lock or burn a Solana → EVM transfer and fill in its record
+///
+/// Returns the new transfer ID.
+fn lock_transfer<'info>(
+    accounts: InitiateAccounts<'_, 'info>,
+    transfer_record: &mut TransferRecord,
+    evm_recipient: [u8; 20],
+    amount: u64,
+    payload: Vec<u8>,
+) -> Result<[u8; 32]> {
+    let state = accounts.state;
+    let token_config = accounts.token_config;
+
+    require!(!state.paused, ErrorCode::BridgePaused);
+    require!(token_config.enabled, ErrorCode::TokenNotEnabled);
+    require!(amount > 0, ErrorCode::ZeroAmount);
+    require!(payload.len() <= MAX_PAYLOAD_SIZE, ErrorCode::PayloadTooLarge);
+
+    // Only move what the EVM side can represent; any remainder stays with the sender
+    let (amount, evm_amount) =
+        bridged_amounts(amount, accounts.mint.decimals, token_config.evm_decimals)?;
+
+    // Generate transfer ID
+    state.transfer_nonce += 1;
+    let transfer_id = generate_transfer_id(
+        &accounts.sender.key(),
+        &evm_recipient,
+        amount,
+        state.transfer_nonce,
+    );
+
+    // Lock or burn tokens
+    if token_config.is_native_on_solana {
+        // Lock tokens in bridge vault
+        let cpi_accounts = Transfer {
+            from: accounts.sender_token_account,
+            to: accounts.bridge_vault,
+            authority: accounts.sender.clone(),
+        };
+        let cpi_ctx = CpiContext::new(accounts.token_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        state.total_locked += amount;
+        token_config.outstanding = token_config.outstanding
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    } else {
+        // Burn wrapped tokens
+        let cpi_accounts = Burn {
+            mint: accounts.mint.to_account_info(),
+            from: accounts.sender_token_account,
+            authority: accounts.sender.clone(),
+        };
+        let cpi_ctx = CpiContext::new(accounts.token_program, cpi_accounts);
+        token::burn(cpi_ctx, amount)?;
+
+        token_config.outstanding = token_config.outstanding
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ReserveInvariantViolated)?;
+    }
+
+    // Create transfer record
+    transfer_record.transfer_id = transfer_id;
+    transfer_record.sender = accounts.sender.key();
+    transfer_record.evm_recipient = evm_recipient;
+    transfer_record.mint = accounts.mint.key();
+    transfer_record.amount = amount;
+    transfer_record.nonce = state.transfer_nonce;
+    transfer_record.timestamp = Clock::get()?.unix_timestamp;
+    transfer_record.status = TransferStatus::Pending;
+    transfer_record.payload = payload.clone();
+
+    // Emit event for relayers
+    emit!(TransferInitiated {
+        transfer_id,
+        sender: accounts.sender.key(),
+        evm_recipient,
+        mint: accounts.mint.key(),
+        amount,
+        evm_amount,
+        nonce: state.transfer_nonce,
+        payload,
+    });
+
+    msg!("Transfer initiated: {} tokens to 0x{}", 
+        amount,
+        hex::encode(&evm_recipient)
+    );
+
+    Ok(transfer_id)
+}
+
+/// SPL amount actually moved for a requested `amount`, and what it is in EVM
+/// base units; the part the EVM side can't represent is left out
+fn bridged_amounts(amount: u64, mint_decimals: u8, evm_decimals: u8) -> Result<(u64, u128)> {
+    let evm_amount = scale_amount(u128::from(amount), mint_decimals, evm_decimals)?;
+    require!(evm_amount > 0, ErrorCode::AmountTooSmall);
+    let amount = spl_amount(scale_amount(evm_amount, evm_decimals, mint_decimals)?)?;
+    Ok((amount, evm_amount))
+}
+
+/// Transfer ID a keyed record already holds, or `None` if the record is new
+///
+/// A record created by an earlier call must describe the same transfer
+/// (`amount` after scaling); otherwise the key is being reused.
+fn replayed_transfer(
+    record: &TransferRecord,
+    mint: &Pubkey,
+    evm_recipient: &[u8; 20],
+    amount: u64,
+    payload: &[u8],
+) -> Result<Option<[u8; 32]>> {
+    if record.transfer_id == [0u8; 32] {
+        return Ok(None);
+    }
+    require!(
+        record.mint == *mint
+            && record.evm_recipient == *evm_recipient
+            && record.amount == amount
+            && record.payload == payload,
+        ErrorCode::IdempotencyKeyReused
+    );
+    Ok(Some(record.transfer_id))
+}
+
+/// Whether a transfer is finished and its record can be closed. Solana → EVM
+/// transfers have no refund path, so completion on EVM is the only way out
+/// of `Pending`.
+fn is_terminal(status: TransferStatus) -> bool {
+    status == TransferStatus::Completed
+}
+
+/// Accounts every completion needs, single or batched
+struct ReleaseAccounts<'a, 'info> {
+    state: &'a Account<'info, BridgeState>,
+    state_bump: u8,
+    token_config: &'a TokenConfig,
+    mint: &'a Account<'info, Mint>,
+    bridge_vault: AccountInfo<'info>,
+    evm_light_client_program: AccountInfo<'info>,
+    light_client_state: AccountInfo<'info>,
+    proof_cache: Option<AccountInfo<'info>>,
+    token_program: AccountInfo<'info>,
+}
+
+/// Check a batch's remaining accounts against its transfers before anything
+/// is created or released, returning each completion record's bump
+///
+/// A transfer whose completion record exists, or that appears twice in the
+/// batch, fails with `TransferAlreadyCompleted`.
+fn check_batch_accounts(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    transfers: &[TransferDescriptor],
+    remaining_accounts: &[AccountInfo],
+) -> Result<Vec<u8>> {
+    require!(remaining_accounts.len() == transfers.len() * 4, ErrorCode::InvalidBatchAccounts);
+
+    let mut completions: Vec<Pubkey> = Vec::with_capacity(transfers.len());
+    let mut bumps = Vec::with_capacity(transfers.len());
+    for (transfer, accounts) in transfers.iter().zip(remaining_accounts.chunks(4)) {
+        let [completion_info, recipient, recipient_token_account, _proof_buffer] = accounts else {
+            return Err(ErrorCode::InvalidBatchAccounts.into());
+        };
+
+        let (completion_key, completion_bump) = Pubkey::find_program_address(
+            &[b"completion", &transfer.source_chain_id.to_le_bytes(), transfer.transfer_id.as_ref()],
+            program_id,
+        );
+        require_keys_eq!(completion_info.key(), completion_key, ErrorCode::InvalidBatchAccounts);
+        require!(
+            completion_info.data_is_empty() && !completions.contains(&completion_key),
+            ErrorCode::TransferAlreadyCompleted
+        );
+
+        let token_account = Account::<TokenAccount>::try_from(recipient_token_account)?;
+        require!(
+            token_account.mint == *mint && token_account.owner == recipient.key(),
+            ErrorCode::InvalidBatchAccounts
+        );
+
+        completions.push(completion_key);
+        bumps.push(completion_bump);
+    }
+    Ok(bumps)
+}
+
+/// Create a program-owned PDA the way Anchor's `init` does: an address that
+/// already holds lamports is topped up to rent exemption, then allocated and
+/// assigned, so funding it beforehand can't block creation
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let current = target.lamports();
+    if current == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount { from: payer.clone(), to: target.clone() },
+                &[seeds],
+            ),
+            rent,
+            space as u64,
+            owner,
+        );
+    }
+
+    let top_up = rent.saturating_sub(current);
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer { from: payer.clone(), to: target.clone() },
+            ),
+            top_up,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate { account_to_allocate: target.clone() },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Assign { account_to_assign: target.clone() },
+            &[seeds],
+        ),
+        owner,
+    )
+}
+
+/// Verify an EVM → Solana transfer and mint or unlock it to the recipient
+///
+/// Returns the SPL amount released and the EVM-side dust left behind.
+fn release_transfer<'info>(
+    accounts: &ReleaseAccounts<'_, 'info>,
+    transfer: &TransferDescriptor,
+    proof_data: &[u8],
+    recipient: &Pubkey,
+    recipient_token_account: &AccountInfo<'info>,
+) -> Result<(u64, u128)> {
+    let state = accounts.state;
+    let token_config = accounts.token_config;
+
+    validate_proof_data(proof_data)?;
+    require!(transfer.source_chain_id == state.evm_chain_id, ErrorCode::UnsupportedSourceChain);
+
+    // Completions are keyed by (source chain, transfer ID), so the ID must commit to
+    // the source chain the way the EVM bridge derives it
+    let expected_id = compute_evm_transfer_id(
+        transfer.source_chain_id,
+        &token_config.evm_token,
+        &transfer.evm_sender,
+        &recipient.to_bytes(),
+        transfer.amount,
+        transfer.evm_nonce,
+    );
+    require!(transfer.transfer_id == expected_id, ErrorCode::TransferIdMismatch);
+
+    let (spl_amount, dust) = incoming_amount(
+        transfer.amount,
+        token_config.evm_decimals,
+        accounts.mint.decimals,
+        transfer.min_amount_out,
+    )?;
+
+    let light_client = light_client_view(&accounts.light_client_state)?;
+    confirmed_state_root(&light_client, transfer.proof_slot, state.min_confirmations)?;
+
+    // Verify the Merkle proof via EVM light client CPI
+    // This proves the transfer was included in the verified EVM state
+    verify_evm_transfer(
+        &accounts.evm_light_client_program,
+        &accounts.light_client_state,
+        accounts.proof_cache.as_ref(),
+        transfer.source_chain_id,
+        &state.evm_bridge_address,
+        &transfer.transfer_id,
+        &transfer.evm_sender,
+        &recipient.to_bytes(),
+        transfer.amount,
+        transfer.proof_slot,
+        proof_data,
+    )?;
+
+    let seeds = &[
+        b"bridge_state".as_ref(),
+        &[accounts.state_bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Mint or unlock tokens
+    if token_config.is_native_on_solana {
+        // Unlock from bridge vault
+        let cpi_accounts = Transfer {
+            from: accounts.bridge_vault.clone(),
+            to: recipient_token_account.clone(),
+            authority: state.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.clone(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, spl_amount)?;
+    } else {
+        // Mint wrapped tokens
+        let cpi_accounts = MintTo {
+            mint: accounts.mint.to_account_info(),
+            to: recipient_token_account.clone(),
+            authority: state.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.clone(),
+            cpi_accounts,
+            signer,
+        );
+        token::mint_to(cpi_ctx, spl_amount)?;
+    }
+
+    Ok((spl_amount, dust))
+}
+
+/// `outstanding` after completions released `amount`: an unlock draws down what
+/// the vault holds, a mint adds to what the bridge owes on the way back. An
+/// unlock of more than was ever locked is a drain and fails.
+fn outstanding_after_release(is_native: bool, outstanding: u64, amount: u64) -> Result<u64> {
+    if is_native {
+        outstanding.checked_sub(amount).ok_or(error!(ErrorCode::ReserveInvariantViolated))
+    } else {
+        outstanding.checked_add(amount).ok_or(error!(ErrorCode::MathOverflow))
+    }
+}
+
+/// Post-condition of every completion, so accounting drift aborts the
+/// instruction instead of surfacing after funds are gone
+///
+/// The vault must still cover every locked token, and the wrapped supply must
+/// not exceed what the bridge minted. Neither is an equality: anyone can send
+/// tokens to the vault or burn wrapped tokens outside the bridge, and that
+/// must not halt completions.
+fn check_reserves(is_native: bool, outstanding: u64, vault_balance: u64, mint_supply: u64) -> Result<()> {
+    if is_native {
+        require!(vault_balance >= outstanding, ErrorCode::ReserveInvariantViolated);
+    } else {
+        require!(mint_supply <= outstanding, ErrorCode::ReserveInvariantViolated);
+    }
+    Ok(())
+}
+
+/// `check_reserves` against the vault and mint as the token CPIs left them
+fn verify_reserves(token_config: &TokenConfig, bridge_vault: &AccountInfo, mint: &AccountInfo) -> Result<()> {
+    if token_config.is_native_on_solana {
+        let vault = TokenAccount::try_deserialize(&mut &bridge_vault.try_borrow_data()?[..])?;
+        check_reserves(true, token_config.outstanding, vault.amount, 0)
+    } else {
+        let mint = Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?;
+        check_reserves(false, token_config.outstanding, 0, mint.supply)
+    }
+}
+
+/// SPL amount to release for an incoming transfer of `amount` EVM base units,
+/// and the EVM-side dust left behind. Fails if the recipient would receive
+/// less than `min_amount_out`.
+fn incoming_amount(
+    amount: u128,
+    evm_decimals: u8,
+    mint_decimals: u8,
+    min_amount_out: u64,
+) -> Result<(u64, u128)> {
+    // Convert to the mint's decimals, rounding down
+    let released = spl_amount(scale_amount(amount, evm_decimals, mint_decimals)?)?;
+    require!(released > 0, ErrorCode::AmountTooSmall);
+    require!(released >= min_amount_out, ErrorCode::SlippageExceeded);
+
+    let dust = amount
+        .checked_sub(scale_amount(u128::from(released), mint_decimals, evm_decimals)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok((released, dust))
+}
+
+/// Transfer ID as derived by the EVM bridge's `initiateTransfer`:
+/// keccak256(abi.encodePacked(chainId, destChainId, token, sender, recipient, amount, nonce))
+fn compute_evm_transfer_id(
+    source_chain_id: u64,
+    evm_token: &[u8; 20],
+    evm_sender: &[u8; 20],
+    recipient: &[u8; 32],
+    amount: u128,
+    nonce: u64,
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(32 + 32 + 20 + 20 + 32 + 32 + 32);
+    data.extend_from_slice(&u64_to_word(source_chain_id));
+    data.extend_from_slice(&u64_to_word(SOLANA_CHAIN_ID));
+    data.extend_from_slice(evm_token);
+    data.extend_from_slice(evm_sender);
+    data.extend_from_slice(recipient);
+    data.extend_from_slice(&u128_to_word(amount));
+    data.extend_from_slice(&u64_to_word(nonce));
+
+    keccak::hash(&data).to_bytes()
+}
+
+/// Encode a u64 as a 32-byte big-endian EVM word
+fn u64_to_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Encode a u128 as a 32-byte big-endian EVM word
+fn u128_to_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Convert an amount between decimal precisions, rounding down.
+///
+/// Amounts are u128 because an 18-decimal EVM amount overflows u64 past
+/// ~18.4 whole tokens.
+fn scale_amount(amount: u128, from_decimals: u8, to_decimals: u8) -> Result<u128> {
+    let scaled = if to_decimals >= from_decimals {
+        10u128
+            .checked_pow(u32::from(to_decimals - from_decimals))
+            .and_then(|factor| amount.checked_mul(factor))
+    } else {
+        // Past 10^38 every u128 rounds down to zero
+        Some(
+            10u128
+                .checked_pow(u32::from(from_decimals - to_decimals))
+                .map_or(0, |factor| amount / factor),
+        )
+    };
+
+    scaled.ok_or_else(|| ErrorCode::MathOverflow.into())
+}
+
+/// Narrow a mint-decimals amount to an SPL token amount
+fn spl_amount(amount: u128) -> Result<u64> {
+    u64::try_from(amount).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Check a proof's framing before it is sent to the light client, which
+/// expects `[num_nodes: u16][node_len: u16][node]...` with nothing trailing
+fn validate_proof_data(proof_data: &[u8]) -> Result<()> {
+    require!(
+        proof_data.len() >= 2 && proof_data.len() <= MAX_PROOF_SIZE,
+        ErrorCode::MalformedProof
+    );
+
+    let num_nodes = u16::from_le_bytes([proof_data[0], proof_data[1]]) as usize;
+    require!(num_nodes > 0 && num_nodes <= MAX_PROOF_NODES, ErrorCode::MalformedProof);
+
+    let mut pos = 2;
+    for _ in 0..num_nodes {
+        require!(pos + 2 <= proof_data.len(), ErrorCode::MalformedProof);
+        let node_len = u16::from_le_bytes([proof_data[pos], proof_data[pos + 1]]) as usize;
+        pos += 2;
+
+        require!(
+            node_len > 0 && node_len <= MAX_PROOF_NODE_SIZE && pos + node_len <= proof_data.len(),
+            ErrorCode::MalformedProof
+        );
+        pos += node_len;
+    }
+
+    require!(pos == proof_data.len(), ErrorCode::MalformedProof);
+    Ok(())
+}
+
+/// The proof staged in a `ProofBuffer`, read in place rather than
+/// deserialized so a batch doesn't copy every buffer onto the heap
+fn buffered_proof<'a>(buffer: &'a AccountInfo) -> Result<std::cell::Ref<'a, [u8]>> {
+    require_keys_eq!(*buffer.owner, crate::ID, ErrorCode::InvalidProofBuffer);
+    let data = buffer.try_borrow_data()?;
+    let discriminator = <ProofBuffer as anchor_lang::Discriminator>::DISCRIMINATOR;
+    require!(
+        data.len() >= ProofBuffer::HEADER_SIZE && data[..8] == discriminator,
+        ErrorCode::InvalidProofBuffer
+    );
+
+    let len_at = ProofBuffer::HEADER_SIZE - 4;
+    let len = u32::from_le_bytes(data[len_at..ProofBuffer::HEADER_SIZE].try_into().unwrap()) as usize;
+    require!(data.len() - ProofBuffer::HEADER_SIZE >= len, ErrorCode::InvalidProofBuffer);
+
+    Ok(std::cell::Ref::map(data, |data| {
+        &data[ProofBuffer::HEADER_SIZE..ProofBuffer::HEADER_SIZE + len]
+    }))
+}
+
+/// A transfer's proof travels either inline or in a proof buffer, never both
+fn select_proof<'a>(inline: &'a [u8], buffered: Option<&'a [u8]>) -> Result<&'a [u8]> {
+    match (inline.is_empty(), buffered) {
+        (false, None) => Ok(inline),
+        (true, Some(buffered)) => Ok(buffered),
+        _ => Err(ErrorCode::MalformedProof.into()),
+    }
+}
+
+/// Deserialized light client state account
+fn light_client_view(light_client_state: &AccountInfo) -> Result<evm_light_client::LightClientState> {
+    let data = light_client_state.try_borrow_data()?;
+    evm_light_client::LightClientState::try_deserialize(&mut &data[..])
+        .map_err(|_| ErrorCode::InvalidLightClient.into())
+}
+
+/// Require the light client's head to be at least `min_confirmations` slots
+/// past `proof_slot`
+fn check_confirmations(head_slot: u64, proof_slot: u64, min_confirmations: u64) -> Result<()> {
+    let depth = head_slot
+        .checked_sub(proof_slot)
+        .ok_or(ErrorCode::InsufficientConfirmations)?;
+    require!(depth >= min_confirmations, ErrorCode::InsufficientConfirmations);
+    Ok(())
+}
+
+/// State root a transfer's proof is checked against: the one the light client
+/// finalized at `proof_slot`, which must be `min_confirmations` below its head
+///
+/// The depth is that of the root, not of anything the relayer claims about
+/// the transfer's block: a transfer only appears in roots finalized after its
+/// block, so a proof against a deep enough root shows the transfer is at
+/// least that deep. Naming an older slot than the transfer's just makes the
+/// proof fail against that slot's root.
+fn confirmed_state_root(
+    light_client: &evm_light_client::LightClientState,
+    proof_slot: u64,
+    min_confirmations: u64,
+) -> Result<[u8; 32]> {
+    check_confirmations(light_client.latest_slot, proof_slot, min_confirmations)?;
+    light_client
+        .state_root_at(proof_slot)
+        .ok_or(error!(ErrorCode::UnknownProofSlot))
+}
+
 /// Verify an EVM transfer by checking the bridge contract's storage via the light client
 ///
 /// This function verifies that a transfer was initiated on the EVM chain by:
 /// 1. Computing the storage slot where the transfer is recorded in the EVM bridge contract
 /// 2. Verifying the storage value matches the expected transfer details
 /// 3. Using the EVM light client's state proof verification via CPI
+///
+/// The light client keeps one state account per EVM chain, so
+/// `light_client_state` must be the one for `source_chain_id`.
 fn verify_evm_transfer<'info>(
     evm_light_client: &AccountInfo<'info>,
     light_client_state: &AccountInfo<'info>,
+    proof_cache: Option<&AccountInfo<'info>>,
+    source_chain_id: u64,
     evm_bridge_address: &[u8; 20],
     transfer_id: &[u8; 32],
     evm_sender: &[u8; 20],
     recipient: &[u8; 32],
-    amount: u64,
-    _evm_block_number: u64,
+    amount: u128,
+    proof_slot: u64,
     proof_data: &[u8],
 ) -> Result<()> {
-    use solana_program::instruction::{AccountMeta, Instruction};
-    use solana_program::program::invoke;
-
     // Compute the storage slot for this transfer in the EVM bridge contract
     // The EVM bridge stores transfers at: keccak256(transfer_id . TRANSFERS_MAPPING_SLOT)
     // TRANSFERS_MAPPING_SLOT is typically 0 for the first storage mapping
@@ -617,57 +1949,69 @@ fn verify_evm_transfer<'info>(
     // The EVM bridge stores: keccak256(sender, recipient, amount)
     let expected_value = compute_transfer_hash(evm_sender, recipient, amount);
 
-    // Build instruction data for verify_account_proof
-    // Discriminator for verify_account_proof (first 8 bytes of sha256("global:verify_account_proof"))
-    const VERIFY_ACCOUNT_PROOF_DISCRIMINATOR: [u8; 8] = [0x5b, 0x9c, 0x8d, 0x3f, 0x1a, 0x2b, 0x4c, 0x5d];
-    
-    // Layout: [8 bytes discriminator][20 bytes account][32 bytes slot][32 bytes value][4 bytes len][proof_data]
-    let mut data = Vec::with_capacity(8 + 20 + 32 + 32 + 4 + proof_data.len());
-    data.extend_from_slice(&VERIFY_ACCOUNT_PROOF_DISCRIMINATOR);
-    data.extend_from_slice(evm_bridge_address);
-    data.extend_from_slice(&storage_slot);
-    data.extend_from_slice(&expected_value);
-    data.extend_from_slice(&(proof_data.len() as u32).to_le_bytes());
-    data.extend_from_slice(proof_data);
-
-    // Build accounts for CPI
-    let accounts = vec![
-        AccountMeta::new_readonly(*light_client_state.key, false),
-    ];
-
-    // Create instruction
-    let ix = Instruction {
-        program_id: *evm_light_client.key,
-        accounts,
-        data,
-    };
+    verify_evm_storage(
+        evm_light_client,
+        light_client_state,
+        proof_cache,
+        source_chain_id,
+        evm_bridge_address,
+        &storage_slot,
+        &expected_value,
+        proof_slot,
+        proof_data,
+    )?;
 
-    // Invoke CPI
-    invoke(&ix, &[light_client_state.clone()])?;
+    msg!("EVM transfer verified successfully");
+    Ok(())
+}
 
-    // Parse return data from the EVM light client
-    // The program returns a boolean (1 byte: 0 = false, 1 = true)
-    let return_data = solana_program::program::get_return_data();
-    
-    match return_data {
-        Some((program_id, data)) => {
-            if program_id != *evm_light_client.key {
-                msg!("Return data from wrong program");
-                return Err(ErrorCode::EVMProofFailed.into());
-            }
-            
-            if data.is_empty() || data[0] != 1 {
-                msg!("EVM transfer verification failed: state proof invalid");
-                return Err(ErrorCode::EVMProofFailed.into());
-            }
-        }
-        None => {
-            msg!("No return data from EVM light client");
-            return Err(ErrorCode::EVMProofFailed.into());
-        }
+/// Check, via the EVM light client, that `slot` of `evm_address` holds
+/// `expected_value` in the state of `source_chain_id` finalized at `proof_slot`
+///
+/// With the chain's proof cache, proofs after the first against the same root
+/// skip re-walking the bridge contract's account proof.
+fn verify_evm_storage<'info>(
+    evm_light_client: &AccountInfo<'info>,
+    light_client_state: &AccountInfo<'info>,
+    proof_cache: Option<&AccountInfo<'info>>,
+    source_chain_id: u64,
+    evm_address: &[u8; 20],
+    storage_slot: &[u8; 32],
+    expected_value: &[u8; 32],
+    proof_slot: u64,
+    proof_data: &[u8],
+) -> Result<()> {
+    use evm_light_client::cpi_helpers;
+
+    let (expected_state, _) = Pubkey::find_program_address(
+        &[evm_light_client::STATE_SEED, &source_chain_id.to_le_bytes()],
+        evm_light_client.key,
+    );
+    require!(light_client_state.key() == expected_state, ErrorCode::InvalidLightClient);
+
+    let cpi_ctx = CpiContext::new(
+        evm_light_client.clone(),
+        cpi_helpers::VerifyProofAt {
+            state: light_client_state.clone(),
+            proof_cache: proof_cache.cloned(),
+        },
+    );
+    let valid = cpi_helpers::verify_account_proof_at(
+        cpi_ctx,
+        source_chain_id,
+        proof_slot,
+        *evm_address,
+        *storage_slot,
+        *expected_value,
+        proof_data,
+    )
+    .map_err(|_| ErrorCode::EVMProofFailed)?;
+
+    if !valid {
+        msg!("EVM state proof invalid");
+        return Err(ErrorCode::EVMProofFailed.into());
     }
 
-    msg!("EVM transfer verified successfully");
     Ok(())
 }
 
@@ -686,7 +2030,7 @@ fn compute_evm_storage_slot(key: &[u8; 32], mapping_slot: u64) -> [u8; 32] {
 }
 
 /// Compute the hash of transfer details as stored in EVM bridge
-fn compute_transfer_hash(sender: &[u8; 20], recipient: &[u8; 32], amount: u64) -> [u8; 32] {
+fn compute_transfer_hash(sender: &[u8; 20], recipient: &[u8; 32], amount: u128) -> [u8; 32] {
     let mut data = Vec::with_capacity(60);
     
     // Pad sender to 32 bytes (left-pad with zeros for EVM address)
@@ -697,9 +2041,7 @@ fn compute_transfer_hash(sender: &[u8; 20], recipient: &[u8; 32], amount: u64) -
     data.extend_from_slice(recipient);
     
     // Amount as 32-byte big-endian
-    let mut amount_bytes = [0u8; 32];
-    amount_bytes[24..32].copy_from_slice(&amount.to_be_bytes());
-    data.extend_from_slice(&amount_bytes);
+    data.extend_from_slice(&u128_to_word(amount));
 
     keccak::hash(&data).to_bytes()
 }
@@ -710,3 +2052,647 @@ mod hex {
         bytes.iter().map(|b| format!("{:02x}", b)).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_18_to_6_rounds_down() {
+        assert_eq!(scale_amount(1_000_000_000_000_000_000, 18, 6).unwrap(), 1_000_000);
+        assert_eq!(scale_amount(1_500_000_000_000_999_999, 18, 6).unwrap(), 1_500_000);
+        assert_eq!(scale_amount(999_999_999_999, 18, 6).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_scale_6_to_18() {
+        assert_eq!(scale_amount(1_000_000, 6, 18).unwrap(), 1_000_000_000_000_000_000);
+        assert_eq!(scale_amount(1, 6, 18).unwrap(), 1_000_000_000_000);
+    }
+
+    #[test]
+    fn test_scale_6_to_6_is_identity() {
+        assert_eq!(scale_amount(0, 6, 6).unwrap(), 0);
+        assert_eq!(scale_amount(123_456_789, 6, 6).unwrap(), 123_456_789);
+        assert_eq!(scale_amount(u128::MAX, 6, 6).unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn test_scale_up_overflow() {
+        assert!(scale_amount(u128::MAX, 6, 18).is_err());
+        assert!(scale_amount(1, 0, 39).is_err());
+    }
+
+    #[test]
+    fn test_million_usdc_round_trips_through_18_decimals() {
+        // 1,000,000 USDC is 10^24 base units at 18 decimals, well past u64::MAX
+        let usdc = 1_000_000_000_000u64;
+        let evm_amount = 1_000_000_000_000_000_000_000_000u128;
+        assert!(evm_amount > u128::from(u64::MAX));
+
+        assert_eq!(bridged_amounts(usdc, 6, 18).unwrap(), (usdc, evm_amount));
+        assert_eq!(incoming_amount(evm_amount, 18, 6, usdc).unwrap(), (usdc, 0));
+        assert_eq!(incoming_amount(evm_amount + 999, 18, 6, 0).unwrap(), (usdc, 999));
+    }
+
+    #[test]
+    fn test_incoming_amount_too_large_for_the_mint_is_rejected() {
+        // Fits in u128 at 18 decimals but not in a u64 SPL amount at 6
+        let amount = (u128::from(u64::MAX) + 1) * 1_000_000_000_000;
+        assert!(incoming_amount(amount, 18, 6, 0).is_err());
+    }
+
+    fn empty_record() -> TransferRecord {
+        TransferRecord {
+            transfer_id: [0u8; 32],
+            sender: Pubkey::default(),
+            evm_recipient: [0u8; 20],
+            mint: Pubkey::default(),
+            amount: 0,
+            nonce: 0,
+            timestamp: 0,
+            status: TransferStatus::Pending,
+            payload: vec![],
+        }
+    }
+
+    #[test]
+    fn test_same_idempotency_key_locks_once() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipient = [9u8; 20];
+        let mut record = empty_record();
+        let mut nonce = 0u64;
+        let mut locks = 0;
+        let mut ids = vec![];
+
+        for _ in 0..2 {
+            let id = match replayed_transfer(&record, &mint, &recipient, 1_000, &[]).unwrap() {
+                Some(id) => id,
+                None => {
+                    locks += 1;
+                    nonce += 1;
+                    let id = generate_transfer_id(&sender, &recipient, 1_000, nonce);
+                    record.transfer_id = id;
+                    record.sender = sender;
+                    record.evm_recipient = recipient;
+                    record.mint = mint;
+                    record.amount = 1_000;
+                    record.nonce = nonce;
+                    id
+                }
+            };
+            ids.push(id);
+        }
+
+        assert_eq!(locks, 1);
+        assert_eq!(ids[0], ids[1]);
+
+        // Same key, different transfer
+        assert!(replayed_transfer(&record, &mint, &recipient, 2_000, &[]).is_err());
+        assert!(replayed_transfer(&record, &mint, &[1u8; 20], 1_000, &[]).is_err());
+        assert!(replayed_transfer(&record, &mint, &recipient, 1_000, &[1]).is_err());
+    }
+
+    #[test]
+    fn test_only_terminal_transfers_can_be_closed() {
+        assert!(!is_terminal(TransferStatus::Pending));
+        assert!(is_terminal(TransferStatus::Completed));
+    }
+
+    #[test]
+    fn test_reserve_invariant_trips_on_drift() {
+        // Native: 1_000 locked, 400 unlocked, vault holds exactly the rest
+        let outstanding = outstanding_after_release(true, 1_000, 400).unwrap();
+        assert_eq!(outstanding, 600);
+        assert!(check_reserves(true, outstanding, 600, 0).is_ok());
+        // Tokens sent straight to the vault are harmless
+        assert!(check_reserves(true, outstanding, 700, 0).is_ok());
+        // Corrupted: the vault lost tokens the accounting still owes
+        assert!(check_reserves(true, outstanding, 599, 0).is_err());
+        // Unlocking more than was ever locked
+        assert!(outstanding_after_release(true, outstanding, 601).is_err());
+
+        // Wrapped: 1_000 minted, supply may shrink through outside burns
+        let outstanding = outstanding_after_release(false, 0, 1_000).unwrap();
+        assert!(check_reserves(false, outstanding, 0, 1_000).is_ok());
+        assert!(check_reserves(false, outstanding, 0, 900).is_ok());
+        // Corrupted: supply exists that the bridge never minted
+        assert!(check_reserves(false, outstanding, 0, 1_001).is_err());
+    }
+
+    #[test]
+    fn test_bridged_amounts_drop_unrepresentable_remainder() {
+        assert_eq!(bridged_amounts(1_500_000_000_999, 12, 6).unwrap(), (1_500_000_000_000, 1_500_000));
+        assert!(bridged_amounts(999_999, 12, 6).is_err());
+    }
+
+    fn completion_pda(source_chain_id: u64, transfer_id: &[u8; 32]) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"completion", &source_chain_id.to_le_bytes(), transfer_id.as_ref()],
+            &ID,
+        )
+        .0
+    }
+
+    #[test]
+    fn test_same_transfer_id_on_two_chains_has_distinct_completions() {
+        let transfer_id = [7u8; 32];
+        assert_ne!(completion_pda(1, &transfer_id), completion_pda(8453, &transfer_id));
+        assert_eq!(completion_pda(1, &transfer_id), completion_pda(1, &transfer_id));
+    }
+
+    #[test]
+    fn test_transfer_id_commits_to_source_chain() {
+        let token = [0x11; 20];
+        let sender = [0x22; 20];
+        let recipient = [0x33; 32];
+
+        let ethereum = compute_evm_transfer_id(1, &token, &sender, &recipient, 5_000, 1);
+        let base = compute_evm_transfer_id(8453, &token, &sender, &recipient, 5_000, 1);
+        assert_ne!(ethereum, base);
+        assert_eq!(ethereum, compute_evm_transfer_id(1, &token, &sender, &recipient, 5_000, 1));
+    }
+
+    #[test]
+    fn test_incoming_amount_enforces_min_amount_out() {
+        // 1.5 tokens at 18 decimals delivers 1_500_000 at 6
+        let amount = 1_500_000_000_000_000_000;
+        assert_eq!(incoming_amount(amount, 18, 6, 1_500_000).unwrap(), (1_500_000, 0));
+        assert_eq!(incoming_amount(amount, 18, 6, 0).unwrap(), (1_500_000, 0));
+        assert!(incoming_amount(amount, 18, 6, 1_500_001).is_err());
+    }
+
+    #[test]
+    fn test_incoming_amount_checks_post_scaling_amount() {
+        // Rounding down to 6 decimals can cost the recipient the last unit
+        let amount = 1_999_999_999_999;
+        assert_eq!(incoming_amount(amount, 18, 6, 1).unwrap(), (1, 999_999_999_999));
+        assert!(incoming_amount(amount, 18, 6, 2).is_err());
+    }
+
+    fn proof(nodes: &[&[u8]]) -> Vec<u8> {
+        let mut data = (nodes.len() as u16).to_le_bytes().to_vec();
+        for node in nodes {
+            data.extend_from_slice(&(node.len() as u16).to_le_bytes());
+            data.extend_from_slice(node);
+        }
+        data
+    }
+
+    #[test]
+    fn test_validate_proof_data() {
+        let valid = proof(&[&[0xc0; 40], &[0xc1; 70]]);
+        assert!(validate_proof_data(&valid).is_ok());
+
+        // Empty
+        assert!(validate_proof_data(&[]).is_err());
+        assert!(validate_proof_data(&proof(&[])).is_err());
+
+        // Oversized
+        let node = vec![0xc0; MAX_PROOF_SIZE];
+        assert!(validate_proof_data(&proof(&[&node])).is_err());
+
+        // Truncated inside a node, inside a length prefix, and inside the header
+        assert!(validate_proof_data(&valid[..valid.len() - 1]).is_err());
+        assert!(validate_proof_data(&valid[..2 + 2 + 40 + 1]).is_err());
+        assert!(validate_proof_data(&valid[..1]).is_err());
+
+        // Trailing bytes and zero-length nodes
+        let mut trailing = valid.clone();
+        trailing.push(0);
+        assert!(validate_proof_data(&trailing).is_err());
+        assert!(validate_proof_data(&proof(&[&[]])).is_err());
+    }
+
+    #[test]
+    fn test_validate_proof_data_bounds_nodes() {
+        // A mainnet-sized account proof: nine full branch nodes
+        let branch = vec![0xf9; MAX_PROOF_NODE_SIZE];
+        let nodes: Vec<&[u8]> = (0..9).map(|_| branch.as_slice()).collect();
+        let mainnet = proof(&nodes);
+        assert!(mainnet.len() > 4 * 1024);
+        assert!(validate_proof_data(&mainnet).is_ok());
+
+        // One byte past the largest possible trie node
+        let oversized = vec![0xf9; MAX_PROOF_NODE_SIZE + 1];
+        assert!(validate_proof_data(&proof(&[&oversized])).is_err());
+
+        // More nodes than two 32-byte-key paths can hold
+        let leaf = [0xc1, 0x80];
+        assert!(validate_proof_data(&proof(&vec![&leaf[..]; MAX_PROOF_NODES])).is_ok());
+        assert!(validate_proof_data(&proof(&vec![&leaf[..]; MAX_PROOF_NODES + 1])).is_err());
+    }
+
+    fn proof_buffer_data(relayer: Pubkey, proof: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        ProofBuffer { relayer, data: proof.to_vec() }.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_buffered_proof_reads_the_staged_proof() {
+        let key = Pubkey::new_unique();
+        let staged = proof(&[&[0xc0; 600], &[0xc1; 600]]);
+        let mut data = proof_buffer_data(Pubkey::new_unique(), &staged);
+        let mut lamports = 0;
+        let buffer = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ID, false, 0);
+
+        assert_eq!(&*buffered_proof(&buffer).unwrap(), staged.as_slice());
+    }
+
+    #[test]
+    fn test_buffered_proof_rejects_foreign_accounts() {
+        let key = Pubkey::new_unique();
+        let staged = proof(&[&[0xc0; 40]]);
+
+        // Owned by another program
+        let mut data = proof_buffer_data(Pubkey::new_unique(), &staged);
+        let mut lamports = 0;
+        let other = Pubkey::new_unique();
+        let buffer = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &other, false, 0);
+        assert!(buffered_proof(&buffer).is_err());
+
+        // Owned by the bridge but not a proof buffer
+        let mut data = proof_buffer_data(Pubkey::new_unique(), &staged);
+        data[..8].copy_from_slice(&<CompletionRecord as anchor_lang::Discriminator>::DISCRIMINATOR);
+        let mut lamports = 0;
+        let buffer = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ID, false, 0);
+        assert!(buffered_proof(&buffer).is_err());
+
+        // Length prefix past the end of the account
+        let mut data = proof_buffer_data(Pubkey::new_unique(), &staged);
+        data.truncate(data.len() - 1);
+        let mut lamports = 0;
+        let buffer = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ID, false, 0);
+        assert!(buffered_proof(&buffer).is_err());
+    }
+
+    #[test]
+    fn test_select_proof_takes_exactly_one_source() {
+        let inline = [1u8, 2, 3];
+        let buffered = [4u8, 5, 6];
+        assert_eq!(select_proof(&inline, None).unwrap(), &inline);
+        assert_eq!(select_proof(&[], Some(&buffered[..])).unwrap(), &buffered);
+        assert!(select_proof(&inline, Some(&buffered[..])).is_err());
+        assert!(select_proof(&[], None).is_err());
+    }
+
+    fn descriptor(transfer_id: [u8; 32]) -> TransferDescriptor {
+        TransferDescriptor {
+            transfer_id,
+            source_chain_id: 1,
+            evm_sender: [0x22; 20],
+            amount: 1_000_000_000_000_000_000,
+            proof_slot: 100,
+            evm_nonce: 1,
+            proof_data: vec![],
+            min_amount_out: 0,
+        }
+    }
+
+    /// Owned state behind one remaining account
+    struct TestAccount {
+        key: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        owner: Pubkey,
+    }
+
+    fn token_account(mint: Pubkey, owner: Pubkey) -> TestAccount {
+        use solana_program::{program_option::COption, program_pack::Pack};
+        use anchor_spl::token::spl_token::state::{Account as SplAccount, AccountState};
+
+        let mut data = vec![0; SplAccount::LEN];
+        SplAccount {
+            mint,
+            owner,
+            amount: 0,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        TestAccount { key: Pubkey::new_unique(), lamports: 0, data, owner: anchor_spl::token::ID }
+    }
+
+    /// Remaining accounts for a transfer of `mint` to a fresh recipient
+    fn batch_entry(transfer: &TransferDescriptor, mint: Pubkey) -> Vec<TestAccount> {
+        let recipient = Pubkey::new_unique();
+        vec![
+            TestAccount {
+                key: completion_pda(transfer.source_chain_id, &transfer.transfer_id),
+                lamports: 0,
+                data: vec![],
+                owner: solana_program::system_program::ID,
+            },
+            TestAccount { key: recipient, lamports: 0, data: vec![], owner: solana_program::system_program::ID },
+            token_account(mint, recipient),
+            TestAccount { key: ID, lamports: 0, data: vec![], owner: solana_program::bpf_loader::ID },
+        ]
+    }
+
+    fn check_batch(mint: &Pubkey, transfers: &[TransferDescriptor], accounts: &mut [TestAccount]) -> Result<Vec<u8>> {
+        let infos: Vec<AccountInfo> = accounts
+            .iter_mut()
+            .map(|a| AccountInfo::new(&a.key, false, true, &mut a.lamports, &mut a.data, &a.owner, false, 0))
+            .collect();
+        check_batch_accounts(&ID, mint, transfers, &infos)
+    }
+
+    #[test]
+    fn test_batch_accounts_accept_a_valid_batch() {
+        let mint = Pubkey::new_unique();
+        let transfers = [descriptor([1; 32]), descriptor([2; 32]), descriptor([3; 32])];
+        let mut accounts: Vec<TestAccount> =
+            transfers.iter().flat_map(|transfer| batch_entry(transfer, mint)).collect();
+
+        // Lamports sent to a completion address ahead of time don't block the batch
+        accounts[0].lamports = 1;
+
+        let bumps = check_batch(&mint, &transfers, &mut accounts).unwrap();
+        let expected: Vec<u8> = transfers
+            .iter()
+            .map(|t| {
+                Pubkey::find_program_address(
+                    &[b"completion", &t.source_chain_id.to_le_bytes(), t.transfer_id.as_ref()],
+                    &ID,
+                )
+                .1
+            })
+            .collect();
+        assert_eq!(bumps, expected);
+    }
+
+    #[test]
+    fn test_batch_accounts_reject_duplicate_transfers() {
+        let mint = Pubkey::new_unique();
+
+        // The same transfer twice in one batch
+        let transfers = [descriptor([1; 32]), descriptor([1; 32])];
+        let mut accounts: Vec<TestAccount> =
+            transfers.iter().flat_map(|transfer| batch_entry(transfer, mint)).collect();
+        assert_eq!(
+            check_batch(&mint, &transfers, &mut accounts).unwrap_err(),
+            ErrorCode::TransferAlreadyCompleted.into()
+        );
+
+        // A transfer completed by an earlier transaction
+        let transfers = [descriptor([1; 32])];
+        let mut accounts = batch_entry(&transfers[0], mint);
+        accounts[0].data = vec![0; 8 + CompletionRecord::INIT_SPACE];
+        accounts[0].owner = ID;
+        assert_eq!(
+            check_batch(&mint, &transfers, &mut accounts).unwrap_err(),
+            ErrorCode::TransferAlreadyCompleted.into()
+        );
+    }
+
+    #[test]
+    fn test_batch_accounts_reject_the_whole_batch_for_one_bad_transfer() {
+        let mint = Pubkey::new_unique();
+        let transfers = [descriptor([1; 32]), descriptor([2; 32])];
+
+        // Second recipient's token account holds another mint
+        let mut accounts = batch_entry(&transfers[0], mint);
+        accounts.extend(batch_entry(&transfers[1], Pubkey::new_unique()));
+        assert_eq!(
+            check_batch(&mint, &transfers, &mut accounts).unwrap_err(),
+            ErrorCode::InvalidBatchAccounts.into()
+        );
+
+        // Second completion record isn't the transfer's PDA
+        let mut accounts: Vec<TestAccount> =
+            transfers.iter().flat_map(|transfer| batch_entry(transfer, mint)).collect();
+        accounts[4].key = Pubkey::new_unique();
+        assert!(check_batch(&mint, &transfers, &mut accounts).is_err());
+
+        // Second transfer's accounts missing
+        let mut accounts = batch_entry(&transfers[0], mint);
+        assert_eq!(
+            check_batch(&mint, &transfers, &mut accounts).unwrap_err(),
+            ErrorCode::InvalidBatchAccounts.into()
+        );
+    }
+
+    /// Serialized size of a legacy transaction completing `count` transfers
+    /// with buffered proofs, including its compute unit limit request
+    fn batch_transaction_size(count: usize) -> usize {
+        use anchor_lang::InstructionData;
+        use solana_program::{instruction::{AccountMeta, Instruction}, message::Message};
+
+        let relayer = Pubkey::new_unique();
+        let transfers: Vec<TransferDescriptor> = (0..count).map(|i| descriptor([i as u8; 32])).collect();
+        let mut accounts: Vec<AccountMeta> = (0..6)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .chain([
+                AccountMeta::new(relayer, true),
+                AccountMeta::new_readonly(anchor_spl::token::ID, false),
+                AccountMeta::new_readonly(solana_program::system_program::ID, false),
+                AccountMeta::new(Pubkey::new_unique(), false),
+            ])
+            .collect();
+        for _ in 0..count * 4 {
+            accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+        }
+
+        // ComputeBudgetInstruction::SetComputeUnitLimit
+        let mut limit = vec![2u8];
+        limit.extend_from_slice(&complete_batch_compute_units(count).to_le_bytes());
+        let set_limit = Instruction {
+            program_id: solana_program::compute_budget::ID,
+            accounts: vec![],
+            data: limit,
+        };
+        let ix = Instruction {
+            program_id: ID,
+            accounts,
+            data: instruction::CompleteTransferBatch { transfers }.data(),
+        };
+        // One signature and its compact-array length
+        1 + 64 + Message::new(&[set_limit, ix], Some(&relayer)).serialize().len()
+    }
+
+    #[test]
+    fn test_max_batch_fits_in_a_transaction() {
+        const PACKET_DATA_SIZE: usize = 1232;
+        assert!(batch_transaction_size(MAX_COMPLETE_BATCH_SIZE) <= PACKET_DATA_SIZE);
+        assert!(batch_transaction_size(MAX_COMPLETE_BATCH_SIZE + 1) > PACKET_DATA_SIZE);
+    }
+
+    #[test]
+    fn test_max_batch_fits_the_requested_compute_budget() {
+        const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+        const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+        assert!(complete_batch_compute_units(1) <= DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT);
+
+        // A full batch only runs with the limit the relayer requests
+        let full_batch = complete_batch_compute_units(MAX_COMPLETE_BATCH_SIZE);
+        assert!(full_batch > DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT);
+        assert!(full_batch <= MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_largest_proof_fits_a_buffer_and_the_light_client_cpi() {
+        // Accounts created by CPI and CPI instruction data are both capped at 10 KiB
+        const MAX_CPI_SIZE: usize = 10 * 1024;
+        assert!(ProofBuffer::HEADER_SIZE + MAX_PROOF_SIZE <= MAX_CPI_SIZE);
+        assert!(8 + 8 + 8 + 20 + 32 + 32 + 4 + MAX_PROOF_SIZE <= MAX_CPI_SIZE);
+    }
+
+    #[test]
+    fn test_light_client_cpi_uses_anchor_discriminator() {
+        use anchor_lang::{Discriminator, InstructionData};
+
+        // sha256("global:verify_account_proof")[..8]
+        let expected = [0xf2, 0x93, 0x14, 0x15, 0xdc, 0xb6, 0x90, 0x8f];
+        assert_eq!(evm_light_client::instruction::VerifyAccountProof::DISCRIMINATOR, expected);
+
+        let data = evm_light_client::instruction::VerifyAccountProof {
+            _chain_id: 1,
+            account: [0u8; 20],
+            storage_slot: [0u8; 32],
+            expected_value: [0u8; 32],
+            proof_data: vec![],
+        }
+        .data();
+        assert_eq!(data[..8], expected);
+    }
+
+    #[test]
+    fn test_round_trip_dust() {
+        let evm_amount = 1_234_567_890_123_456_789u128;
+        let spl_amount = scale_amount(evm_amount, 18, 6).unwrap();
+        let dust = evm_amount - scale_amount(spl_amount, 6, 18).unwrap();
+        assert_eq!(spl_amount, 1_234_567);
+        assert_eq!(dust, 890_123_456_789);
+    }
+
+    #[test]
+    fn test_too_recent_block_is_rejected() {
+        let head = 10_000;
+
+        // 63 slots deep with the default threshold of 64
+        assert!(check_confirmations(head, head - 63, DEFAULT_MIN_CONFIRMATIONS).is_err());
+        assert!(check_confirmations(head, head - 64, DEFAULT_MIN_CONFIRMATIONS).is_ok());
+
+        // A block the light client hasn't reached yet
+        assert!(check_confirmations(head, head + 1, 0).is_err());
+        assert!(check_confirmations(head, head, 0).is_ok());
+    }
+
+    fn rlp_bytes(data: &[u8]) -> Vec<u8> {
+        if data.len() == 1 && data[0] < 0x80 {
+            return data.to_vec();
+        }
+        let mut out = if data.len() <= 55 {
+            vec![0x80 + data.len() as u8]
+        } else {
+            vec![0xb8, data.len() as u8]
+        };
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload = items.concat();
+        let mut out = if payload.len() <= 55 {
+            vec![0xc0 + payload.len() as u8]
+        } else {
+            vec![0xf8, payload.len() as u8]
+        };
+        out.extend(payload);
+        out
+    }
+
+    /// A branch root with a single leaf under `key`, whose value is `value`
+    fn single_leaf_trie(key: &[u8; 32], value: &[u8]) -> (Vec<Vec<u8>>, [u8; 32]) {
+        let nibbles: Vec<u8> = key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect();
+        // 63 nibbles remain below the branch: odd-length leaf path
+        let mut path = vec![0x30 | nibbles[1]];
+        path.extend(nibbles[2..].chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+
+        let leaf = rlp_list(&[rlp_bytes(&path), rlp_bytes(value)]);
+        let mut children: Vec<Vec<u8>> = vec![vec![0x80]; 17];
+        children[nibbles[0] as usize] = rlp_bytes(&keccak::hash(&leaf).to_bytes());
+        let branch = rlp_list(&children);
+        let root = keccak::hash(&branch).to_bytes();
+        (vec![branch, leaf], root)
+    }
+
+    /// State root holding `value` at `storage_slot` of `account`, and the
+    /// serialized proof of it
+    fn state_with_storage(account: &[u8; 20], storage_slot: &[u8; 32], value: &[u8; 32]) -> ([u8; 32], Vec<u8>) {
+        let (storage_nodes, storage_root) = single_leaf_trie(&keccak::hash(storage_slot).to_bytes(), value);
+        let account_value = rlp_list(&[
+            rlp_bytes(&[0x01]),
+            rlp_bytes(&[]),
+            rlp_bytes(&storage_root),
+            rlp_bytes(&evm_light_client::EMPTY_CODE_HASH),
+        ]);
+        let (mut nodes, state_root) = single_leaf_trie(&keccak::hash(account).to_bytes(), &account_value);
+        nodes.extend(storage_nodes);
+        let node_refs: Vec<&[u8]> = nodes.iter().map(|node| node.as_slice()).collect();
+        (state_root, proof(&node_refs))
+    }
+
+    fn light_client_with_roots(roots: &[(u64, [u8; 32])]) -> evm_light_client::LightClientState {
+        let mut recent_roots = [evm_light_client::VerifiedRoot::default(); evm_light_client::STATE_ROOT_HISTORY];
+        for (entry, (slot, state_root)) in recent_roots.iter_mut().zip(roots) {
+            *entry = evm_light_client::VerifiedRoot { slot: *slot, state_root: *state_root };
+        }
+        let (latest_slot, latest_state_root) = *roots.last().unwrap();
+        evm_light_client::LightClientState {
+            admin: Pubkey::default(),
+            chain_id: 1,
+            latest_slot,
+            latest_block_root: [0u8; 32],
+            latest_state_root,
+            current_sync_committee_root: [0u8; 32],
+            next_sync_committee_root: [0u8; 32],
+            update_count: roots.len() as u64,
+            initialized: true,
+            permissioned_mode: true,
+            authorized_relayer: Pubkey::default(),
+            challenge_period: 0,
+            pending_state: None,
+            recent_roots,
+            recent_roots_next: roots.len() as u8,
+        }
+    }
+
+    #[test]
+    fn test_too_recent_transfer_is_rejected_whatever_slot_is_claimed() {
+        let bridge = [0xb1u8; 20];
+        let transfer_slot = compute_evm_storage_slot(&[7u8; 32], 0);
+        let transfer_value = [0xaau8; 32];
+
+        // The transfer lands after slot 1_000 and is first in the root finalized at 1_040
+        let (old_root, _) = state_with_storage(&bridge, &transfer_slot, &[0u8; 32]);
+        let (new_root, transfer_proof) = state_with_storage(&bridge, &transfer_slot, &transfer_value);
+        let light_client = light_client_with_roots(&[(1_000, old_root), (1_040, new_root), (1_070, new_root)]);
+        let verify = |root: &[u8; 32]| {
+            evm_light_client::verify_storage_proof(root, &bridge, &transfer_slot, &transfer_value, &transfer_proof)
+        };
+
+        // Honest: the only root holding the transfer is 30 slots deep
+        assert!(confirmed_state_root(&light_client, 1_040, DEFAULT_MIN_CONFIRMATIONS).is_err());
+
+        // Lying with an older slot passes the depth check but binds the proof
+        // to that slot's root, which doesn't hold the transfer
+        let root = confirmed_state_root(&light_client, 1_000, DEFAULT_MIN_CONFIRMATIONS).unwrap();
+        assert_eq!(root, old_root);
+        assert!(!matches!(verify(&root), Ok(true)));
+
+        // A slot the light client never finalized, e.g. zero, has no root at all
+        assert!(confirmed_state_root(&light_client, 0, DEFAULT_MIN_CONFIRMATIONS).is_err());
+
+        // Once deep enough, the honest slot goes through
+        assert!(verify(&new_root).unwrap());
+        let deeper = light_client_with_roots(&[(1_000, old_root), (1_040, new_root), (1_104, new_root)]);
+        assert_eq!(confirmed_state_root(&deeper, 1_040, DEFAULT_MIN_CONFIRMATIONS).unwrap(), new_root);
+    }
+}
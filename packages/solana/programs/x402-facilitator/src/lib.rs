@@ -10,9 +10,41 @@ use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 declare_id!("FEsMtN2PfdzMPYCFLDLth3VxjfrozuzHodV24znt22B7");
 
-pub const MAX_PAYMENT_AGE: i64 = 300;
+pub const DEFAULT_MAX_PAYMENT_AGE: i64 = 300;
+/// Upper bound for `max_payment_age`; nonce accounts stay open at least this long
+pub const MAX_PAYMENT_AGE_LIMIT: i64 = 86400;
+/// How far in the future a signed timestamp may be, to absorb clock drift
+pub const MAX_CLOCK_SKEW: i64 = 30;
 pub const MAX_FEE_BPS: u16 = 1000;
-pub const PAYMENT_MESSAGE_PREFIX: &[u8] = b"x402:solana:payment:v1:";
+/// Every signed message starts with its prefix, then this program's id and
+/// the deployment's `network_id`, so it can't be replayed on another cluster
+/// or facilitator deployment
+pub const PAYMENT_MESSAGE_PREFIX: &[u8] = b"x402:solana:payment:v4:";
+pub const SPLIT_MESSAGE_PREFIX: &[u8] = b"x402:solana:split:v4:";
+pub const DELEGATION_MESSAGE_PREFIX: &[u8] = b"x402:solana:delegation:v2:";
+pub const DELEGATED_PAYMENT_MESSAGE_PREFIX: &[u8] = b"x402:solana:delegated:v4:";
+/// Superseded message versions, rejected outright: v1 messages had no domain,
+/// v2 payment, split and delegated payment messages had no settle deadline,
+/// and v3 ones didn't length-prefix the resource and nonce
+const RETIRED_MESSAGE_PREFIXES: [&[u8]; 10] = [
+    b"x402:solana:payment:v1:",
+    b"x402:solana:split:v1:",
+    b"x402:solana:delegation:v1:",
+    b"x402:solana:delegated:v1:",
+    b"x402:solana:payment:v2:",
+    b"x402:solana:split:v2:",
+    b"x402:solana:delegated:v2:",
+    b"x402:solana:payment:v3:",
+    b"x402:solana:split:v3:",
+    b"x402:solana:delegated:v3:",
+];
+/// Conventional `network_id`s for the public clusters
+pub const NETWORK_ID_MAINNET: u64 = 101;
+pub const NETWORK_ID_TESTNET: u64 = 102;
+pub const NETWORK_ID_DEVNET: u64 = 103;
+pub const MAX_SPLIT_RECIPIENTS: usize = 8;
+pub const BPS_DENOMINATOR: u16 = 10000;
+pub const MAX_NONCE_CLOSE_BATCH: usize = 16;
 
 // Ed25519 instruction data offsets (from Solana's ed25519 program spec)
 const ED25519_PUBKEY_OFFSET: usize = 16;
@@ -22,8 +54,22 @@ const ED25519_MESSAGE_DATA_OFFSET: usize = 112;
 pub mod x402_facilitator {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, protocol_fee_bps: u16) -> Result<()> {
+    /// `max_payment_age` of zero uses `DEFAULT_MAX_PAYMENT_AGE`. `network_id`
+    /// identifies the cluster (e.g. `NETWORK_ID_MAINNET`) in signed messages.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        protocol_fee_bps: u16,
+        max_payment_age: i64,
+        network_id: u64,
+    ) -> Result<()> {
         require!(protocol_fee_bps <= MAX_FEE_BPS, ErrorCode::FeeTooHigh);
+        require!(network_id != 0, ErrorCode::InvalidNetworkId);
+        let max_payment_age = if max_payment_age == 0 {
+            DEFAULT_MAX_PAYMENT_AGE
+        } else {
+            max_payment_age
+        };
+        require!(valid_payment_age(max_payment_age), ErrorCode::InvalidPaymentAge);
 
         let state = &mut ctx.accounts.state;
         state.admin = ctx.accounts.admin.key();
@@ -33,24 +79,35 @@ pub mod x402_facilitator {
         state.total_volume = 0;
         state.total_fees = 0;
         state.paused = false;
+        state.max_payment_age = max_payment_age;
+        state.network_id = network_id;
         Ok(())
     }
 
-    pub fn register_token(ctx: Context<RegisterToken>, decimals: u8) -> Result<()> {
+    /// `max_settlement_amount` of zero means no per-settlement limit
+    pub fn register_token(
+        ctx: Context<RegisterToken>,
+        decimals: u8,
+        max_settlement_amount: u64,
+    ) -> Result<()> {
         let token_config = &mut ctx.accounts.token_config;
         token_config.mint = ctx.accounts.mint.key();
         token_config.decimals = decimals;
         token_config.enabled = true;
         token_config.volume = 0;
+        token_config.max_settlement_amount = max_settlement_amount;
         Ok(())
     }
 
+    /// `valid_until` is a deadline the payer signs alongside `timestamp`: the
+    /// payment must be both fresh and not past its deadline to settle.
     pub fn settle(
         ctx: Context<Settle>,
         amount: u64,
         resource: String,
         nonce: String,
         timestamp: i64,
+        valid_until: i64,
         _signature: [u8; 64], // Signature is verified via Ed25519 instruction, kept for message construction
     ) -> Result<()> {
         let state = &ctx.accounts.state;
@@ -59,21 +116,28 @@ pub mod x402_facilitator {
         require!(!state.paused, ErrorCode::FacilitatorPaused);
         require!(token_config.enabled, ErrorCode::TokenNotSupported);
         require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            within_settlement_limit(token_config, amount),
+            ErrorCode::AmountExceedsLimit
+        );
 
         let clock = Clock::get()?;
         // SECURITY: Prevent future timestamps that could keep payments valid indefinitely
-        require!(timestamp <= clock.unix_timestamp, ErrorCode::FutureTimestamp);
-        require!(clock.unix_timestamp <= timestamp + MAX_PAYMENT_AGE, ErrorCode::PaymentExpired);
+        check_payment_window(timestamp, clock.unix_timestamp, state.max_payment_age)?;
+        check_settle_deadline(valid_until, clock.unix_timestamp)?;
         require!(!ctx.accounts.nonce_account.used, ErrorCode::NonceAlreadyUsed);
+        check_payer_balance(ctx.accounts.payer_token_account.amount, amount)?;
 
         // Build expected message for verification
         let expected_message = build_payment_message(
+            &MessageDomain::new(state.network_id),
             &ctx.accounts.recipient.key(),
             &ctx.accounts.mint.key(),
             amount,
             &resource,
             &nonce,
             timestamp,
+            valid_until,
         );
 
         // Verify Ed25519 signature via instructions sysvar
@@ -114,6 +178,7 @@ pub mod x402_facilitator {
         let nonce_account = &mut ctx.accounts.nonce_account;
         nonce_account.used = true;
         nonce_account.used_at = clock.unix_timestamp;
+        nonce_account.submitter = ctx.accounts.submitter.key();
 
         // Update stats
         let state = &mut ctx.accounts.state;
@@ -130,20 +195,385 @@ pub mod x402_facilitator {
             mint: ctx.accounts.mint.key(),
             amount,
             protocol_fee,
+            max_settlement_amount: ctx.accounts.token_config.max_settlement_amount,
+            resource,
+            nonce,
+            timestamp,
+            valid_until,
+        });
+
+        Ok(())
+    }
+
+    /// Settle a single authorized payment split across several recipients.
+    /// Recipient token accounts are passed as remaining accounts, in the same order as `recipients`.
+    /// The recipients' bps plus the protocol fee may not exceed 10000; the fee
+    /// account receives everything the recipients' shares leave over: the fee,
+    /// any unallocated bps the payer signed for, and rounding dust.
+    /// `valid_until` is the payer's signed settle deadline, as in `settle`.
+    pub fn settle_split<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleSplit<'info>>,
+        amount: u64,
+        recipients: Vec<SplitRecipient>,
+        resource: String,
+        nonce: String,
+        timestamp: i64,
+        valid_until: i64,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let token_config = &ctx.accounts.token_config;
+
+        require!(!state.paused, ErrorCode::FacilitatorPaused);
+        require!(token_config.enabled, ErrorCode::TokenNotSupported);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            within_settlement_limit(token_config, amount),
+            ErrorCode::AmountExceedsLimit
+        );
+        require!(
+            !recipients.is_empty() && recipients.len() <= MAX_SPLIT_RECIPIENTS,
+            ErrorCode::InvalidSplitRecipients
+        );
+        require!(
+            ctx.remaining_accounts.len() == recipients.len(),
+            ErrorCode::InvalidSplitRecipients
+        );
+
+        check_split_bps(&recipients, state.protocol_fee_bps)?;
+
+        let clock = Clock::get()?;
+        check_payment_window(timestamp, clock.unix_timestamp, state.max_payment_age)?;
+        check_settle_deadline(valid_until, clock.unix_timestamp)?;
+        require!(!ctx.accounts.nonce_account.used, ErrorCode::NonceAlreadyUsed);
+        check_payer_balance(ctx.accounts.payer_token_account.amount, amount)?;
+
+        let expected_message = build_split_payment_message(
+            &MessageDomain::new(state.network_id),
+            &ctx.accounts.mint.key(),
+            amount,
+            &recipients,
+            &resource,
+            &nonce,
+            timestamp,
+            valid_until,
+        );
+
+        verify_ed25519_via_sysvar(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.payer.key(),
+            &expected_message,
+        )?;
+
+        let (shares, protocol_fee) = split_shares(amount, &recipients)?;
+        let mut payouts = Vec::with_capacity(recipients.len());
+        for ((split, share), recipient_token_account) in
+            recipients.iter().zip(shares).zip(ctx.remaining_accounts.iter())
+        {
+            require!(
+                recipient_token_account.owner == &token::ID,
+                ErrorCode::InvalidRecipientAccount
+            );
+            let token_account =
+                TokenAccount::try_deserialize(&mut &recipient_token_account.try_borrow_data()?[..])?;
+            require!(
+                token_account.mint == ctx.accounts.mint.key() && token_account.owner == split.recipient,
+                ErrorCode::InvalidRecipientAccount
+            );
+
+            if share > 0 {
+                let cpi_accounts = Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: recipient_token_account.clone(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                };
+                token::transfer(
+                    CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+                    share,
+                )?;
+            }
+
+            payouts.push(SplitPayout {
+                recipient: split.recipient,
+                amount: share,
+            });
+        }
+
+        if protocol_fee > 0 {
+            let cpi_accounts_fee = Transfer {
+                from: ctx.accounts.payer_token_account.to_account_info(),
+                to: ctx.accounts.fee_token_account.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_fee),
+                protocol_fee,
+            )?;
+        }
+
+        let nonce_account = &mut ctx.accounts.nonce_account;
+        nonce_account.used = true;
+        nonce_account.used_at = clock.unix_timestamp;
+        nonce_account.submitter = ctx.accounts.submitter.key();
+
+        let state = &mut ctx.accounts.state;
+        state.total_settlements += 1;
+        state.total_volume += amount;
+        state.total_fees += protocol_fee;
+
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.volume += amount;
+
+        emit!(SplitSettled {
+            payer: ctx.accounts.payer.key(),
+            mint: ctx.accounts.mint.key(),
+            amount,
+            protocol_fee,
+            max_settlement_amount: ctx.accounts.token_config.max_settlement_amount,
+            payouts,
+            resource,
+            nonce,
+            timestamp,
+            valid_until,
+        });
+
+        Ok(())
+    }
+
+    /// Create a spending delegation from an owner-signed authorization.
+    ///
+    /// The owner signs `(delegate, mint, limit, expiry)` off-chain; anyone may submit it.
+    /// Settlements are debited by the delegation PDA, so the owner's token account must
+    /// also have approved the PDA as SPL delegate for at least `limit`.
+    pub fn create_delegation(
+        ctx: Context<CreateDelegation>,
+        limit: u64,
+        expiry: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(limit > 0, ErrorCode::InvalidAmount);
+        require!(expiry > clock.unix_timestamp, ErrorCode::DelegationExpired);
+
+        let expected_message = build_delegation_message(
+            &MessageDomain::new(ctx.accounts.state.network_id),
+            &ctx.accounts.delegate.key(),
+            &ctx.accounts.mint.key(),
+            limit,
+            expiry,
+        );
+        verify_ed25519_via_sysvar(
+            &ctx.accounts.instructions_sysvar,
+            &ctx.accounts.owner.key(),
+            &expected_message,
+        )?;
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.owner = ctx.accounts.owner.key();
+        delegation.delegate = ctx.accounts.delegate.key();
+        delegation.mint = ctx.accounts.mint.key();
+        delegation.limit = limit;
+        delegation.spent = 0;
+        delegation.expiry = expiry;
+        delegation.bump = ctx.bumps.delegation;
+
+        emit!(DelegationCreated {
+            delegation: delegation.key(),
+            owner: delegation.owner,
+            delegate: delegation.delegate,
+            mint: delegation.mint,
+            limit,
+            expiry,
+        });
+
+        Ok(())
+    }
+
+    /// Settle a payment authorized by a delegate, debiting the delegation owner.
+    /// `valid_until` is the delegate's signed settle deadline, as in `settle`.
+    pub fn settle_delegated(
+        ctx: Context<SettleDelegated>,
+        amount: u64,
+        resource: String,
+        nonce: String,
+        timestamp: i64,
+        valid_until: i64,
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let token_config = &ctx.accounts.token_config;
+        let delegation = &ctx.accounts.delegation;
+
+        require!(!state.paused, ErrorCode::FacilitatorPaused);
+        require!(token_config.enabled, ErrorCode::TokenNotSupported);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            within_settlement_limit(token_config, amount),
+            ErrorCode::AmountExceedsLimit
+        );
+
+        let clock = Clock::get()?;
+        require!(clock.unix_timestamp < delegation.expiry, ErrorCode::DelegationExpired);
+        check_payment_window(timestamp, clock.unix_timestamp, state.max_payment_age)?;
+        check_settle_deadline(valid_until, clock.unix_timestamp)?;
+        require!(!ctx.accounts.nonce_account.used, ErrorCode::NonceAlreadyUsed);
+
+        let spent = delegation.spent.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        require!(spent <= delegation.limit, ErrorCode::DelegationLimitExceeded);
+        check_payer_balance(ctx.accounts.owner_token_account.amount, amount)?;
+
+        let expected_message = build_delegated_payment_message(
+            &MessageDomain::new(state.network_id),
+            &delegation.owner,
+            &ctx.accounts.recipient.key(),
+            &delegation.mint,
+            amount,
+            &resource,
+            &nonce,
+            timestamp,
+            valid_until,
+        );
+        verify_ed25519_via_sysvar(
+            &ctx.accounts.instructions_sysvar,
+            &delegation.delegate,
+            &expected_message,
+        )?;
+
+        let protocol_fee = (amount as u128 * state.protocol_fee_bps as u128 / 10000) as u64;
+        let recipient_amount = amount - protocol_fee;
+
+        let delegation_seeds = &[
+            b"delegation".as_ref(),
+            delegation.owner.as_ref(),
+            delegation.delegate.as_ref(),
+            delegation.mint.as_ref(),
+            &[delegation.bump],
+        ];
+        let signer = &[&delegation_seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_token_account.to_account_info(),
+            to: ctx.accounts.recipient_token_account.to_account_info(),
+            authority: ctx.accounts.delegation.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            ),
+            recipient_amount,
+        )?;
+
+        if protocol_fee > 0 {
+            let cpi_accounts_fee = Transfer {
+                from: ctx.accounts.owner_token_account.to_account_info(),
+                to: ctx.accounts.fee_token_account.to_account_info(),
+                authority: ctx.accounts.delegation.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts_fee,
+                    signer,
+                ),
+                protocol_fee,
+            )?;
+        }
+
+        let nonce_account = &mut ctx.accounts.nonce_account;
+        nonce_account.used = true;
+        nonce_account.used_at = clock.unix_timestamp;
+        nonce_account.submitter = ctx.accounts.submitter.key();
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.spent = spent;
+
+        let state = &mut ctx.accounts.state;
+        state.total_settlements += 1;
+        state.total_volume += amount;
+        state.total_fees += protocol_fee;
+
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.volume += amount;
+
+        emit!(DelegatedSettled {
+            delegation: ctx.accounts.delegation.key(),
+            owner: ctx.accounts.delegation.owner,
+            delegate: ctx.accounts.delegation.delegate,
+            recipient: ctx.accounts.recipient.key(),
+            mint: ctx.accounts.delegation.mint,
+            amount,
+            protocol_fee,
+            spent,
             resource,
             nonce,
             timestamp,
+            valid_until,
         });
 
         Ok(())
     }
 
+    /// Close a used nonce account and return its rent to the original submitter.
+    ///
+    /// Only allowed once `used_at + MAX_CLOCK_SKEW + MAX_PAYMENT_AGE_LIMIT` has passed: the
+    /// signed timestamp is never later than `used_at + MAX_CLOCK_SKEW`, and the acceptance
+    /// window can never exceed the limit, so by then no signature referencing this nonce can
+    /// still be accepted. Closing earlier would let the same authorization be replayed, since
+    /// `settle` would happily re-create the PDA. The fixed limit is used rather than the
+    /// current `max_payment_age` so that raising the age later can't revive closed nonces.
+    pub fn close_nonce(ctx: Context<CloseNonce>) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            nonce_reclaimable(&ctx.accounts.nonce_account, clock.unix_timestamp),
+            ErrorCode::NonceStillActive
+        );
+        Ok(())
+    }
+
+    /// Batch variant of `close_nonce`. Remaining accounts are `(nonce_account, submitter)` pairs.
+    pub fn close_nonces<'info>(ctx: Context<'_, '_, 'info, 'info, CloseNonces<'info>>) -> Result<()> {
+        let accounts = ctx.remaining_accounts;
+        require!(
+            !accounts.is_empty() && accounts.len() % 2 == 0,
+            ErrorCode::InvalidNonceAccounts
+        );
+        require!(
+            accounts.len() / 2 <= MAX_NONCE_CLOSE_BATCH,
+            ErrorCode::InvalidNonceAccounts
+        );
+
+        let clock = Clock::get()?;
+        for pair in accounts.chunks(2) {
+            let nonce_info = &pair[0];
+            let submitter_info = &pair[1];
+
+            let nonce_account = Account::<NonceAccount>::try_from(nonce_info)?;
+            require!(
+                nonce_account.submitter == submitter_info.key(),
+                ErrorCode::InvalidNonceAccounts
+            );
+            require!(submitter_info.is_writable, ErrorCode::InvalidNonceAccounts);
+            require!(
+                nonce_reclaimable(&nonce_account, clock.unix_timestamp),
+                ErrorCode::NonceStillActive
+            );
+
+            nonce_account.close(submitter_info.clone())?;
+        }
+        Ok(())
+    }
+
     pub fn update_fee(ctx: Context<AdminAction>, new_fee_bps: u16) -> Result<()> {
         require!(new_fee_bps <= MAX_FEE_BPS, ErrorCode::FeeTooHigh);
         ctx.accounts.state.protocol_fee_bps = new_fee_bps;
         Ok(())
     }
 
+    pub fn set_max_payment_age(ctx: Context<AdminAction>, max_payment_age: i64) -> Result<()> {
+        require!(valid_payment_age(max_payment_age), ErrorCode::InvalidPaymentAge);
+        ctx.accounts.state.max_payment_age = max_payment_age;
+        Ok(())
+    }
+
     pub fn set_paused(ctx: Context<AdminAction>, paused: bool) -> Result<()> {
         ctx.accounts.state.paused = paused;
         Ok(())
@@ -153,6 +583,15 @@ pub mod x402_facilitator {
         ctx.accounts.token_config.enabled = enabled;
         Ok(())
     }
+
+    /// Zero removes the per-settlement limit
+    pub fn set_max_settlement_amount(
+        ctx: Context<SetTokenEnabled>,
+        max_settlement_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.token_config.max_settlement_amount = max_settlement_amount;
+        Ok(())
+    }
 }
 
 // Accounts
@@ -245,6 +684,146 @@ pub struct Settle<'info> {
     pub instructions_sysvar: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(amount: u64, recipients: Vec<SplitRecipient>, resource: String, nonce: String)]
+pub struct SettleSplit<'info> {
+    #[account(mut, seeds = [b"facilitator_state"], bump)]
+    pub state: Account<'info, FacilitatorState>,
+    #[account(mut, seeds = [b"token_config", mint.key().as_ref()], bump)]
+    pub token_config: Account<'info, TokenConfig>,
+    #[account(
+        init,
+        payer = submitter,
+        space = 8 + NonceAccount::INIT_SPACE,
+        seeds = [b"nonce", payer.key().as_ref(), nonce.as_bytes()],
+        bump
+    )]
+    pub nonce_account: Account<'info, NonceAccount>,
+    /// CHECK: Token mint
+    pub mint: AccountInfo<'info>,
+    /// CHECK: Payer who signed the authorization (verified via Ed25519 instruction)
+    pub payer: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = payer
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        constraint = fee_token_account.owner == state.fee_recipient @ ErrorCode::InvalidFeeAccount
+    )]
+    pub fee_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub submitter: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Instructions sysvar for Ed25519 signature verification
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateDelegation<'info> {
+    #[account(seeds = [b"facilitator_state"], bump)]
+    pub state: Account<'info, FacilitatorState>,
+    #[account(
+        init,
+        payer = submitter,
+        space = 8 + Delegation::INIT_SPACE,
+        seeds = [b"delegation", owner.key().as_ref(), delegate.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    /// CHECK: Owner who signed the delegation (verified via Ed25519 instruction)
+    pub owner: AccountInfo<'info>,
+    /// CHECK: Delegate allowed to authorize settlements
+    pub delegate: AccountInfo<'info>,
+    /// CHECK: Token mint
+    pub mint: AccountInfo<'info>,
+    #[account(mut)]
+    pub submitter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Instructions sysvar for Ed25519 signature verification
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, resource: String, nonce: String)]
+pub struct SettleDelegated<'info> {
+    #[account(mut, seeds = [b"facilitator_state"], bump)]
+    pub state: Account<'info, FacilitatorState>,
+    #[account(mut, seeds = [b"token_config", delegation.mint.as_ref()], bump)]
+    pub token_config: Account<'info, TokenConfig>,
+    #[account(
+        mut,
+        seeds = [
+            b"delegation",
+            delegation.owner.as_ref(),
+            delegation.delegate.as_ref(),
+            delegation.mint.as_ref()
+        ],
+        bump = delegation.bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(
+        init,
+        payer = submitter,
+        space = 8 + NonceAccount::INIT_SPACE,
+        seeds = [b"nonce", delegation.delegate.as_ref(), nonce.as_bytes()],
+        bump
+    )]
+    pub nonce_account: Account<'info, NonceAccount>,
+    #[account(
+        mut,
+        token::mint = delegation.mint,
+        token::authority = delegation.owner
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Recipient
+    pub recipient: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = delegation.mint,
+        token::authority = recipient
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = delegation.mint,
+        constraint = fee_token_account.owner == state.fee_recipient @ ErrorCode::InvalidFeeAccount
+    )]
+    pub fee_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub submitter: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Instructions sysvar for Ed25519 signature verification
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseNonce<'info> {
+    #[account(
+        mut,
+        close = submitter,
+        constraint = nonce_account.submitter == submitter.key() @ ErrorCode::InvalidNonceAccounts
+    )]
+    pub nonce_account: Account<'info, NonceAccount>,
+    /// CHECK: Original submitter who paid the rent; only receives lamports
+    #[account(mut)]
+    pub submitter: AccountInfo<'info>,
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseNonces<'info> {
+    pub closer: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct AdminAction<'info> {
     #[account(
@@ -282,6 +861,10 @@ pub struct FacilitatorState {
     pub total_volume: u64,
     pub total_fees: u64,
     pub paused: bool,
+    /// Seconds after its signed timestamp that a payment can still be settled
+    pub max_payment_age: i64,
+    /// Cluster this deployment serves, part of every signed message
+    pub network_id: u64,
 }
 
 #[account]
@@ -291,6 +874,8 @@ pub struct TokenConfig {
     pub decimals: u8,
     pub enabled: bool,
     pub volume: u64,
+    /// Largest amount a single settlement may move; 0 = unlimited
+    pub max_settlement_amount: u64,
 }
 
 #[account]
@@ -298,6 +883,33 @@ pub struct TokenConfig {
 pub struct NonceAccount {
     pub used: bool,
     pub used_at: i64,
+    /// Paid the rent for this account; receives it back on close
+    pub submitter: Pubkey,
+}
+
+/// Lets `delegate` authorize settlements from `owner` up to `limit` until `expiry`
+#[account]
+#[derive(InitSpace)]
+pub struct Delegation {
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub mint: Pubkey,
+    pub limit: u64,
+    pub spent: u64,
+    pub expiry: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SplitRecipient {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SplitPayout {
+    pub recipient: Pubkey,
+    pub amount: u64,
 }
 
 // Events
@@ -309,9 +921,53 @@ pub struct PaymentSettled {
     pub mint: Pubkey,
     pub amount: u64,
     pub protocol_fee: u64,
+    /// Token limit in force at settlement; 0 = unlimited
+    pub max_settlement_amount: u64,
+    pub resource: String,
+    pub nonce: String,
+    pub timestamp: i64,
+    pub valid_until: i64,
+}
+
+#[event]
+pub struct SplitSettled {
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub protocol_fee: u64,
+    pub max_settlement_amount: u64,
+    pub payouts: Vec<SplitPayout>,
+    pub resource: String,
+    pub nonce: String,
+    pub timestamp: i64,
+    pub valid_until: i64,
+}
+
+#[event]
+pub struct DelegationCreated {
+    pub delegation: Pubkey,
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub mint: Pubkey,
+    pub limit: u64,
+    pub expiry: i64,
+}
+
+#[event]
+pub struct DelegatedSettled {
+    pub delegation: Pubkey,
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub protocol_fee: u64,
+    /// Total spent under the delegation, including this payment
+    pub spent: u64,
     pub resource: String,
     pub nonce: String,
     pub timestamp: i64,
+    pub valid_until: i64,
 }
 
 // Errors
@@ -336,38 +992,256 @@ pub enum ErrorCode {
     FeeTooHigh,
     #[msg("Invalid fee account")]
     InvalidFeeAccount,
-    #[msg("Timestamp cannot be in the future")]
+    #[msg("Timestamp is too far in the future")]
     FutureTimestamp,
     #[msg("Math overflow")]
     MathOverflow,
+    #[msg("Invalid split recipients")]
+    InvalidSplitRecipients,
+    #[msg("Split bps must be non-zero and leave room for the protocol fee")]
+    InvalidSplitBps,
+    #[msg("Invalid recipient token account")]
+    InvalidRecipientAccount,
+    #[msg("Nonce is still within the payment acceptance window")]
+    NonceStillActive,
+    #[msg("Invalid nonce accounts")]
+    InvalidNonceAccounts,
+    #[msg("Max payment age must be positive and within MAX_PAYMENT_AGE_LIMIT")]
+    InvalidPaymentAge,
+    #[msg("Amount exceeds the token's max settlement amount")]
+    AmountExceedsLimit,
+    #[msg("Delegation expired")]
+    DelegationExpired,
+    #[msg("Delegation limit exceeded")]
+    DelegationLimitExceeded,
+    #[msg("Network ID must be non-zero")]
+    InvalidNetworkId,
+    #[msg("Message version is retired; sign the current version")]
+    UnsupportedMessageVersion,
+    #[msg("Payer's token account cannot cover the payment")]
+    InsufficientPayerBalance,
+    #[msg("Payment is past its signed settle deadline")]
+    SettleDeadlinePassed,
 }
 
 // Helpers
 
+fn within_settlement_limit(token_config: &TokenConfig, amount: u64) -> bool {
+    token_config.max_settlement_amount == 0 || amount <= token_config.max_settlement_amount
+}
+
+/// Fail with a clear error up front rather than inside the SPL transfer
+fn check_payer_balance(balance: u64, amount: u64) -> Result<()> {
+    require!(balance >= amount, ErrorCode::InsufficientPayerBalance);
+    Ok(())
+}
+
+/// Every recipient gets a positive share, and the shares leave at least the
+/// protocol fee; whatever else they leave goes to the fee account with it
+fn check_split_bps(recipients: &[SplitRecipient], protocol_fee_bps: u16) -> Result<()> {
+    let mut total_bps: u16 = 0;
+    for split in recipients {
+        require!(split.bps > 0, ErrorCode::InvalidSplitBps);
+        total_bps = total_bps
+            .checked_add(split.bps)
+            .ok_or(ErrorCode::InvalidSplitBps)?;
+    }
+    require!(
+        total_bps as u32 + protocol_fee_bps as u32 <= BPS_DENOMINATOR as u32,
+        ErrorCode::InvalidSplitBps
+    );
+    Ok(())
+}
+
+/// Each recipient's share of `amount`, rounded down, and the protocol fee:
+/// what the shares leave over, i.e. the fee's bps plus the rounding dust
+fn split_shares(amount: u64, recipients: &[SplitRecipient]) -> Result<(Vec<u64>, u64)> {
+    let shares: Vec<u64> = recipients
+        .iter()
+        .map(|split| (amount as u128 * split.bps as u128 / BPS_DENOMINATOR as u128) as u64)
+        .collect();
+    let distributed = shares
+        .iter()
+        .try_fold(0u64, |total, share| total.checked_add(*share))
+        .ok_or(ErrorCode::MathOverflow)?;
+    let protocol_fee = amount.checked_sub(distributed).ok_or(ErrorCode::MathOverflow)?;
+    Ok((shares, protocol_fee))
+}
+
+fn valid_payment_age(max_payment_age: i64) -> bool {
+    max_payment_age > 0 && max_payment_age <= MAX_PAYMENT_AGE_LIMIT
+}
+
+fn check_payment_window(timestamp: i64, now: i64, max_payment_age: i64) -> Result<()> {
+    require!(
+        timestamp <= now.saturating_add(MAX_CLOCK_SKEW),
+        ErrorCode::FutureTimestamp
+    );
+    require!(
+        now <= timestamp.saturating_add(max_payment_age),
+        ErrorCode::PaymentExpired
+    );
+    Ok(())
+}
+
+/// The payer's own deadline, independent of the rolling freshness window
+fn check_settle_deadline(valid_until: i64, now: i64) -> Result<()> {
+    require!(now <= valid_until, ErrorCode::SettleDeadlinePassed);
+    Ok(())
+}
+
+fn nonce_reclaimable(nonce_account: &NonceAccount, now: i64) -> bool {
+    nonce_account.used
+        && now > nonce_account
+            .used_at
+            .saturating_add(MAX_CLOCK_SKEW)
+            .saturating_add(MAX_PAYMENT_AGE_LIMIT)
+}
+
+/// Program and network a signed message is bound to
+struct MessageDomain {
+    program_id: Pubkey,
+    network_id: u64,
+}
+
+impl MessageDomain {
+    fn new(network_id: u64) -> Self {
+        Self { program_id: crate::ID, network_id }
+    }
+
+    /// `prefix`, program id and network id, ready for the message fields
+    fn start_message(&self, prefix: &[u8], capacity: usize) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(prefix.len() + 42 + capacity);
+        msg.extend_from_slice(prefix);
+        msg.extend_from_slice(self.program_id.as_ref());
+        msg.push(b':');
+        msg.extend_from_slice(&self.network_id.to_le_bytes());
+        msg.push(b':');
+        msg
+    }
+}
+
+/// Append a string field behind its u16 LE length, so the boundary between
+/// resource and nonce is signed too: ("a:b", "c") and ("a", "b:c") differ.
+/// Both arrive as instruction data, which keeps them far below u16::MAX.
+fn push_field(msg: &mut Vec<u8>, field: &str) {
+    msg.extend_from_slice(&(field.len() as u16).to_le_bytes());
+    msg.extend_from_slice(field.as_bytes());
+}
+
 fn build_payment_message(
+    domain: &MessageDomain,
+    recipient: &Pubkey,
+    token: &Pubkey,
+    amount: u64,
+    resource: &str,
+    nonce: &str,
+    timestamp: i64,
+    valid_until: i64,
+) -> Vec<u8> {
+    let mut msg = domain.start_message(PAYMENT_MESSAGE_PREFIX, 200);
+    msg.extend_from_slice(recipient.as_ref());
+    msg.push(b':');
+    msg.extend_from_slice(token.as_ref());
+    msg.push(b':');
+    msg.extend_from_slice(&amount.to_le_bytes());
+    msg.push(b':');
+    push_field(&mut msg, resource);
+    msg.push(b':');
+    push_field(&mut msg, nonce);
+    msg.push(b':');
+    msg.extend_from_slice(&timestamp.to_le_bytes());
+    msg.push(b':');
+    msg.extend_from_slice(&valid_until.to_le_bytes());
+    msg
+}
+
+fn build_delegation_message(
+    domain: &MessageDomain,
+    delegate: &Pubkey,
+    token: &Pubkey,
+    limit: u64,
+    expiry: i64,
+) -> Vec<u8> {
+    let mut msg = domain.start_message(DELEGATION_MESSAGE_PREFIX, 128);
+    msg.extend_from_slice(delegate.as_ref());
+    msg.push(b':');
+    msg.extend_from_slice(token.as_ref());
+    msg.push(b':');
+    msg.extend_from_slice(&limit.to_le_bytes());
+    msg.push(b':');
+    msg.extend_from_slice(&expiry.to_le_bytes());
+    msg
+}
+
+/// Like the payment message, but also commits to the owner being debited
+fn build_delegated_payment_message(
+    domain: &MessageDomain,
+    owner: &Pubkey,
     recipient: &Pubkey,
     token: &Pubkey,
     amount: u64,
     resource: &str,
     nonce: &str,
     timestamp: i64,
+    valid_until: i64,
 ) -> Vec<u8> {
-    let mut msg = Vec::with_capacity(200);
-    msg.extend_from_slice(PAYMENT_MESSAGE_PREFIX);
+    let mut msg = domain.start_message(DELEGATED_PAYMENT_MESSAGE_PREFIX, 248);
+    msg.extend_from_slice(owner.as_ref());
+    msg.push(b':');
     msg.extend_from_slice(recipient.as_ref());
     msg.push(b':');
     msg.extend_from_slice(token.as_ref());
     msg.push(b':');
     msg.extend_from_slice(&amount.to_le_bytes());
     msg.push(b':');
-    msg.extend_from_slice(resource.as_bytes());
+    push_field(&mut msg, resource);
+    msg.push(b':');
+    push_field(&mut msg, nonce);
+    msg.push(b':');
+    msg.extend_from_slice(&timestamp.to_le_bytes());
+    msg.push(b':');
+    msg.extend_from_slice(&valid_until.to_le_bytes());
+    msg
+}
+
+/// The split message commits to every (recipient, bps) pair in order,
+/// so a submitter cannot reorder, drop or reweight recipients.
+fn build_split_payment_message(
+    domain: &MessageDomain,
+    token: &Pubkey,
+    amount: u64,
+    recipients: &[SplitRecipient],
+    resource: &str,
+    nonce: &str,
+    timestamp: i64,
+    valid_until: i64,
+) -> Vec<u8> {
+    let mut msg = domain.start_message(SPLIT_MESSAGE_PREFIX, 208 + recipients.len() * 34);
+    msg.extend_from_slice(token.as_ref());
+    msg.push(b':');
+    msg.extend_from_slice(&amount.to_le_bytes());
+    msg.push(b':');
+    msg.push(recipients.len() as u8);
+    for split in recipients {
+        msg.extend_from_slice(split.recipient.as_ref());
+        msg.extend_from_slice(&split.bps.to_le_bytes());
+    }
+    msg.push(b':');
+    push_field(&mut msg, resource);
     msg.push(b':');
-    msg.extend_from_slice(nonce.as_bytes());
+    push_field(&mut msg, nonce);
     msg.push(b':');
     msg.extend_from_slice(&timestamp.to_le_bytes());
+    msg.push(b':');
+    msg.extend_from_slice(&valid_until.to_le_bytes());
     msg
 }
 
+fn is_retired_message(message: &[u8]) -> bool {
+    RETIRED_MESSAGE_PREFIXES.iter().any(|prefix| message.starts_with(prefix))
+}
+
 /// Verify Ed25519 signature via the instructions sysvar
 /// 
 /// Solana's Ed25519 program must be called BEFORE this instruction in the same transaction.
@@ -440,8 +1314,307 @@ fn verify_ed25519_via_sysvar(
             msg!("Ed25519 signature verified for pubkey: {}", expected_pubkey);
             return Ok(());
         }
+        if is_retired_message(ix_message) {
+            msg!("Rejecting retired message version signed by: {}", expected_pubkey);
+            return Err(ErrorCode::UnsupportedMessageVersion.into());
+        }
     }
 
     msg!("No valid Ed25519 instruction found for pubkey: {}", expected_pubkey);
     Err(ErrorCode::InvalidSignature.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment_message(domain: &MessageDomain, recipient: &Pubkey, token: &Pubkey) -> Vec<u8> {
+        build_payment_message(
+            domain,
+            recipient,
+            token,
+            1_000,
+            "/api/data",
+            "nonce-1",
+            1_700_000_000,
+            1_700_003_600,
+        )
+    }
+
+    #[test]
+    fn test_payment_message_bound_to_program_id() {
+        let recipient = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let ours = MessageDomain::new(NETWORK_ID_MAINNET);
+        let other = MessageDomain { program_id: Pubkey::new_unique(), network_id: NETWORK_ID_MAINNET };
+
+        let signed = payment_message(&ours, &recipient, &token);
+        assert!(signed.starts_with(PAYMENT_MESSAGE_PREFIX));
+        assert_eq!(signed, payment_message(&ours, &recipient, &token));
+        assert_ne!(signed, payment_message(&other, &recipient, &token));
+    }
+
+    /// Ed25519 program instruction data carrying `pubkey` and `message`. The
+    /// signature itself is checked by the precompile, not by this program.
+    fn ed25519_ix_data(pubkey: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let mut data = vec![1u8, 0];
+        for field in [48u16, u16::MAX, 16, u16::MAX, 112, message.len() as u16, u16::MAX] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(pubkey.as_ref());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    /// Instructions sysvar data for an Ed25519 instruction followed by ours
+    fn instructions_sysvar_data(ed25519_data: &[u8]) -> Vec<u8> {
+        use anchor_lang::solana_program::instruction::BorrowedInstruction;
+
+        let ours = [0u8; 8];
+        let mut data = instructions::construct_instructions_data(&[
+            BorrowedInstruction { program_id: &ed25519_program::ID, accounts: vec![], data: ed25519_data },
+            BorrowedInstruction { program_id: &crate::ID, accounts: vec![], data: &ours },
+        ]);
+        // The trailing u16 is the index of the executing instruction
+        let len = data.len();
+        data[len - 2..].copy_from_slice(&1u16.to_le_bytes());
+        data
+    }
+
+    fn verify_against_sysvar(ed25519_data: &[u8], payer: &Pubkey, expected: &[u8]) -> Result<()> {
+        let mut data = instructions_sysvar_data(ed25519_data);
+        let mut lamports = 0;
+        let owner = anchor_lang::solana_program::sysvar::ID;
+        let info = AccountInfo::new(&instructions::ID, false, false, &mut lamports, &mut data, &owner, false, 0);
+        verify_ed25519_via_sysvar(&info, payer, expected)
+    }
+
+    #[test]
+    fn test_signature_for_another_program_is_rejected() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let program_a = MessageDomain { program_id: Pubkey::new_unique(), network_id: NETWORK_ID_MAINNET };
+        let program_b = MessageDomain::new(NETWORK_ID_MAINNET);
+
+        // Payer signed for another facilitator deployment (program A)
+        let signed_for_a = ed25519_ix_data(&payer, &payment_message(&program_a, &recipient, &token));
+
+        // This program (B) rebuilds the message under its own id and finds no match
+        assert_eq!(
+            verify_against_sysvar(&signed_for_a, &payer, &payment_message(&program_b, &recipient, &token))
+                .unwrap_err(),
+            ErrorCode::InvalidSignature.into()
+        );
+        // The same authorization does verify where it was meant to
+        assert!(
+            verify_against_sysvar(&signed_for_a, &payer, &payment_message(&program_a, &recipient, &token))
+                .is_ok()
+        );
+        // And a signature made for B verifies under B
+        let signed_for_b = ed25519_ix_data(&payer, &payment_message(&program_b, &recipient, &token));
+        assert!(
+            verify_against_sysvar(&signed_for_b, &payer, &payment_message(&program_b, &recipient, &token))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_messages_bound_to_network_id() {
+        let recipient = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let mainnet = MessageDomain::new(NETWORK_ID_MAINNET);
+        let devnet = MessageDomain::new(NETWORK_ID_DEVNET);
+
+        assert_ne!(
+            payment_message(&mainnet, &recipient, &token),
+            payment_message(&devnet, &recipient, &token)
+        );
+        assert_ne!(
+            build_delegation_message(&mainnet, &recipient, &token, 10, 100),
+            build_delegation_message(&devnet, &recipient, &token, 10, 100)
+        );
+    }
+
+    fn splits(bps: &[u16]) -> Vec<SplitRecipient> {
+        bps.iter()
+            .map(|bps| SplitRecipient { recipient: Pubkey::new_unique(), bps: *bps })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_bps_must_leave_room_for_fee() {
+        let fee_bps = 250;
+        assert!(check_split_bps(&splits(&[6_000, 3_750]), fee_bps).is_ok());
+        assert!(check_split_bps(&splits(&[10_000]), 0).is_ok());
+        // Short of the total: the gap goes to the fee account with the fee
+        assert!(check_split_bps(&splits(&[6_000, 3_000]), fee_bps).is_ok());
+        let (_, protocol_fee) = split_shares(10_000, &splits(&[6_000, 3_000])).unwrap();
+        assert_eq!(protocol_fee, 1_000);
+
+        // Over: recipients would eat into the protocol fee
+        assert_eq!(
+            check_split_bps(&splits(&[6_000, 4_000]), fee_bps).unwrap_err(),
+            ErrorCode::InvalidSplitBps.into()
+        );
+        assert!(check_split_bps(&splits(&[6_000, 3_751]), fee_bps).is_err());
+        assert!(check_split_bps(&splits(&[9_750, 0]), fee_bps).is_err());
+        assert!(check_split_bps(&splits(&[u16::MAX, 1]), fee_bps).is_err());
+    }
+
+    #[test]
+    fn test_split_rounding_dust_goes_to_protocol_fee() {
+        // 1% fee; 3 recipients at 33% each of 1_001 units
+        let recipients = splits(&[3_300, 3_300, 3_300]);
+        assert!(check_split_bps(&recipients, 100).is_ok());
+
+        let (shares, protocol_fee) = split_shares(1_001, &recipients).unwrap();
+        assert_eq!(shares, vec![330, 330, 330]);
+        // 10.01 of fee bps plus 0.99 of dust
+        assert_eq!(protocol_fee, 11);
+        assert_eq!(shares.iter().sum::<u64>() + protocol_fee, 1_001);
+
+        // Exact amounts leave only the fee's share
+        let (shares, protocol_fee) = split_shares(10_000, &recipients).unwrap();
+        assert_eq!(shares, vec![3_300, 3_300, 3_300]);
+        assert_eq!(protocol_fee, 100);
+    }
+
+    #[test]
+    fn test_underfunded_payer_rejected() {
+        assert!(check_payer_balance(1_000, 1_000).is_ok());
+        assert!(check_payer_balance(5_000, 1_000).is_ok());
+        assert!(check_payer_balance(999, 1_000).is_err());
+        assert!(check_payer_balance(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_v1_messages_detected() {
+        let mut v1 = b"x402:solana:payment:v1:".to_vec();
+        v1.extend_from_slice(Pubkey::new_unique().as_ref());
+        assert!(is_retired_message(&v1));
+        assert!(is_retired_message(b"x402:solana:delegated:v1:"));
+
+        let current = payment_message(&MessageDomain::new(NETWORK_ID_DEVNET), &Pubkey::new_unique(), &Pubkey::new_unique());
+        assert!(!is_retired_message(&current));
+    }
+
+    #[test]
+    fn test_v2_messages_without_deadline_are_retired() {
+        assert!(is_retired_message(b"x402:solana:payment:v2:"));
+        assert!(is_retired_message(b"x402:solana:split:v2:"));
+        assert!(is_retired_message(b"x402:solana:delegated:v2:"));
+        assert!(!is_retired_message(b"x402:solana:delegation:v2:"));
+
+        let domain = MessageDomain::new(NETWORK_ID_MAINNET);
+        let token = Pubkey::new_unique();
+        assert!(!is_retired_message(&split_message(&domain, &token, 1_700_003_600)));
+        assert!(!is_retired_message(&delegated_message(&domain, &token, 1_700_003_600)));
+    }
+
+    fn split_message(domain: &MessageDomain, token: &Pubkey, valid_until: i64) -> Vec<u8> {
+        let recipients = [SplitRecipient { recipient: Pubkey::new_from_array([7; 32]), bps: 5_000 }];
+        build_split_payment_message(domain, token, 1_000, &recipients, "/api", "n", 1_700_000_000, valid_until)
+    }
+
+    fn delegated_message(domain: &MessageDomain, token: &Pubkey, valid_until: i64) -> Vec<u8> {
+        let owner = Pubkey::new_from_array([8; 32]);
+        let recipient = Pubkey::new_from_array([9; 32]);
+        build_delegated_payment_message(domain, &owner, &recipient, token, 1_000, "/api", "n", 1_700_000_000, valid_until)
+    }
+
+    #[test]
+    fn test_split_and_delegated_messages_commit_to_deadline() {
+        let domain = MessageDomain::new(NETWORK_ID_MAINNET);
+        let token = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let deadline = 1_700_000_060;
+
+        let builders: [fn(&MessageDomain, &Pubkey, i64) -> Vec<u8>; 2] = [split_message, delegated_message];
+        for message in builders {
+            let signed = ed25519_ix_data(&signer, &message(&domain, &token, deadline));
+            assert!(verify_against_sysvar(&signed, &signer, &message(&domain, &token, deadline)).is_ok());
+
+            // A submitter can't extend the deadline the signer chose
+            assert_eq!(
+                verify_against_sysvar(&signed, &signer, &message(&domain, &token, deadline + 3_600))
+                    .unwrap_err(),
+                ErrorCode::InvalidSignature.into()
+            );
+        }
+
+        // Both settle paths refuse the payment once the deadline has passed,
+        // even while the timestamp is still fresh
+        let signed_at = deadline - 60;
+        assert!(check_payment_window(signed_at, deadline + 1, DEFAULT_MAX_PAYMENT_AGE).is_ok());
+        assert_eq!(
+            check_settle_deadline(deadline, deadline + 1).unwrap_err(),
+            ErrorCode::SettleDeadlinePassed.into()
+        );
+    }
+
+    #[test]
+    fn test_fresh_payment_past_deadline_is_rejected() {
+        let signed_at = 1_700_000_000;
+        let deadline = signed_at + 60;
+        let now = deadline + 1;
+
+        // Still within the rolling freshness window, but past the payer's deadline
+        assert!(check_payment_window(signed_at, now, DEFAULT_MAX_PAYMENT_AGE).is_ok());
+        assert!(check_settle_deadline(deadline, now).is_err());
+        assert!(check_settle_deadline(deadline, deadline).is_ok());
+
+        // A later deadline doesn't extend a stale payment
+        let stale = signed_at + DEFAULT_MAX_PAYMENT_AGE + 1;
+        assert!(check_settle_deadline(stale + 3_600, stale).is_ok());
+        assert!(check_payment_window(signed_at, stale, DEFAULT_MAX_PAYMENT_AGE).is_err());
+    }
+
+    #[test]
+    fn test_payment_message_commits_to_deadline() {
+        let domain = MessageDomain::new(NETWORK_ID_MAINNET);
+        let recipient = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let message = |valid_until| {
+            build_payment_message(&domain, &recipient, &token, 1_000, "/api", "n", 1_700_000_000, valid_until)
+        };
+
+        assert_ne!(message(1_700_000_060), message(1_700_003_600));
+    }
+
+    #[test]
+    fn test_resource_and_nonce_boundary_is_signed() {
+        let domain = MessageDomain::new(NETWORK_ID_MAINNET);
+        let owner = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let recipients = [SplitRecipient { recipient, bps: 5_000 }];
+
+        let builders: [&dyn Fn(&str, &str) -> Vec<u8>; 3] = [
+            &|resource, nonce| {
+                build_payment_message(&domain, &recipient, &token, 1_000, resource, nonce, 1_700_000_000, 1_700_000_060)
+            },
+            &|resource, nonce| {
+                build_split_payment_message(
+                    &domain, &token, 1_000, &recipients, resource, nonce, 1_700_000_000, 1_700_000_060,
+                )
+            },
+            &|resource, nonce| {
+                build_delegated_payment_message(
+                    &domain, &owner, &recipient, &token, 1_000, resource, nonce, 1_700_000_000, 1_700_000_060,
+                )
+            },
+        ];
+        for message in builders {
+            assert_ne!(message("a:b", "c"), message("a", "b:c"));
+            assert_ne!(message("", ":c"), message(":", "c"));
+            assert_eq!(message("a:b", "c"), message("a:b", "c"));
+        }
+
+        // Messages signed without length prefixes are refused
+        assert!(is_retired_message(b"x402:solana:payment:v3:"));
+        assert!(is_retired_message(b"x402:solana:split:v3:"));
+        assert!(is_retired_message(b"x402:solana:delegated:v3:"));
+    }
+}